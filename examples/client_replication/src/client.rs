@@ -32,6 +32,7 @@ impl Plugin for MyClientPlugin {
             incoming_latency: Duration::from_millis(0),
             incoming_jitter: Duration::from_millis(0),
             incoming_loss: 0.0,
+            ..Default::default()
         };
         let transport = match self.transport {
             Transports::Udp => TransportConfig::UdpSocket(client_addr),
@@ -47,6 +48,7 @@ impl Plugin for MyClientPlugin {
             input: InputConfig::default(),
             netcode: Default::default(),
             ping: PingConfig::default(),
+            priority: PriorityConfig::default(),
             sync: SyncConfig::default(),
             prediction: PredictionConfig::default(),
             // we are sending updates every frame (60fps), let's add a delay of 6 network-ticks
@@ -276,11 +278,11 @@ pub(crate) fn send_message(
         let message = Message1(5);
         info!("Send message: {:?}", message);
         // the message will be re-broadcasted by the server to all clients
-        client
+        if let Err(e) = client
             .send_message_to_target::<Channel1, Message1>(Message1(5), NetworkTarget::All)
-            .unwrap_or_else(|e| {
-                error!("Failed to send message: {:?}", e);
-            });
+        {
+            error!("Failed to send message: {:?}", e);
+        }
     }
 }
 
@@ -35,6 +35,7 @@ impl Plugin for MyClientPlugin {
             incoming_latency: Duration::from_millis(100),
             incoming_jitter: Duration::from_millis(10),
             incoming_loss: 0.00,
+            ..Default::default()
         };
         let transport = match self.transport {
             Transports::Udp => TransportConfig::UdpSocket(client_addr),
@@ -50,6 +51,7 @@ impl Plugin for MyClientPlugin {
             input: InputConfig::default(),
             netcode: Default::default(),
             ping: PingConfig::default(),
+            priority: PriorityConfig::default(),
             sync: SyncConfig::default(),
             prediction: PredictionConfig::default(),
             // we are sending updates every frame (60fps), let's add a delay of 6 network-ticks
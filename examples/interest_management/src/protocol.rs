@@ -5,6 +5,7 @@ use leafwing_input_manager::action_state::ActionState;
 use leafwing_input_manager::input_map::InputMap;
 use leafwing_input_manager::prelude::Actionlike;
 use leafwing_input_manager::InputManagerBundle;
+use lightyear::prelude::server::SpatialPosition;
 use lightyear::prelude::*;
 use lightyear::shared::replication::components::ReplicationMode;
 use serde::{Deserialize, Serialize};
@@ -72,6 +73,12 @@ pub struct PlayerId(pub ClientId);
 )]
 pub struct Position(pub(crate) Vec2);
 
+impl SpatialPosition for Position {
+    fn position(&self) -> Vec2 {
+        self.0
+    }
+}
+
 #[derive(Component, Message, Deserialize, Serialize, Clone, Debug, PartialEq)]
 pub struct PlayerColor(pub(crate) Color);
 
@@ -160,6 +167,7 @@ pub(crate) fn protocol() -> MyProtocol {
     protocol.add_channel::<Channel1>(ChannelSettings {
         mode: ChannelMode::OrderedReliable(ReliableSettings::default()),
         direction: ChannelDirection::Bidirectional,
+        ..Default::default()
     });
     protocol
 }
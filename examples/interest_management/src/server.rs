@@ -31,6 +31,7 @@ impl Plugin for MyServerPlugin {
             incoming_latency: Duration::from_millis(100),
             incoming_jitter: Duration::from_millis(10),
             incoming_loss: 0.00,
+            ..Default::default()
         };
         let transport = match self.transport {
             Transports::Udp => TransportConfig::UdpSocket(server_addr),
@@ -45,6 +46,7 @@ impl Plugin for MyServerPlugin {
             shared: shared_config().clone(),
             netcode: netcode_config,
             ping: PingConfig::default(),
+            priority: PriorityConfig::default(),
         };
         let plugin_config = PluginConfig::new(config, io, protocol());
         app.add_plugins(ServerPlugin::new(plugin_config));
@@ -54,14 +56,17 @@ impl Plugin for MyServerPlugin {
         // the physics/FixedUpdates systems that consume inputs should be run in this set
         app.add_plugins(LeafwingInputPlugin::<MyProtocol, Inputs>::default());
         app.add_systems(FixedUpdate, movement.in_set(FixedUpdateSet::Main));
-        app.add_systems(Update, (handle_connections, interest_management, log));
+        // replicate to each client only the entities that are close to its player
+        app.add_plugins(SpatialPlugin::<MyProtocol, Position>::new(
+            SpatialConfig::radius(INTEREST_RADIUS).with_hysteresis(20.0),
+        ));
+        app.add_systems(Update, (handle_connections, log));
     }
 }
 
 #[derive(Resource, Default)]
 pub(crate) struct Global {
     pub client_id_to_entity_id: HashMap<ClientId, Entity>,
-    pub client_id_to_room_id: HashMap<ClientId, RoomId>,
 }
 
 pub(crate) fn init(mut commands: Commands) {
@@ -105,22 +110,18 @@ pub(crate) fn handle_connections(
         let h = (((client_id * 30) % 360) as f32) / 360.0;
         let s = 0.8;
         let l = 0.5;
-        let entity = commands.spawn(PlayerBundle::new(
-            *client_id,
-            Vec2::ZERO,
-            Color::hsl(h, s, l),
+        let entity = commands.spawn((
+            PlayerBundle::new(*client_id, Vec2::ZERO, Color::hsl(h, s, l)),
+            // the player's position is used to compute which entities are visible to the client
+            SpatialViewer(*client_id),
         ));
         // Add a mapping from client id to entity id (so that when we receive an input from a client,
         // we know which entity to move)
         global
             .client_id_to_entity_id
             .insert(*client_id, entity.id());
-        // we will create a room for each client. To keep things simple, the room id will be the client id
-        let room_id = RoomId((*client_id) as u16);
-        room_manager.room_mut(room_id).add_client(*client_id);
+        // all the players can always see each other
         room_manager.room_mut(PLAYER_ROOM).add_client(*client_id);
-        // also add the player entity to that room (so that the client can always see their own player)
-        room_manager.room_mut(room_id).add_entity(entity.id());
         room_manager.room_mut(PLAYER_ROOM).add_entity(entity.id());
     }
     for disconnection in disconnections.read() {
@@ -138,33 +139,6 @@ pub(crate) fn log(tick_manager: Res<TickManager>, position: Query<&Position, Wit
     }
 }
 
-/// This is where we perform scope management:
-/// - we will add/remove other entities from the player's room only if they are close
-pub(crate) fn interest_management(
-    mut room_manager: ResMut<RoomManager>,
-    player_query: Query<(&PlayerId, Ref<Position>), Without<Circle>>,
-    circle_query: Query<(Entity, &Position), With<Circle>>,
-) {
-    for (client_id, position) in player_query.iter() {
-        if position.is_changed() {
-            let room_id = RoomId((client_id.0) as u16);
-            // let circles_in_room = server.room(room_id).entities();
-            let mut room = room_manager.room_mut(room_id);
-            for (circle_entity, circle_position) in circle_query.iter() {
-                let distance = position.distance(**circle_position);
-                if distance < INTEREST_RADIUS {
-                    // add the circle to the player's room
-                    room.add_entity(circle_entity)
-                } else {
-                    // if circles_in_room.contains(&circle_entity) {
-                    room.remove_entity(circle_entity);
-                    // }
-                }
-            }
-        }
-    }
-}
-
 /// Read client inputs and move players
 pub(crate) fn movement(mut position_query: Query<(&mut Position, &ActionState<Inputs>)>) {
     for (position, input) in position_query.iter_mut() {
@@ -41,6 +41,7 @@ impl Plugin for MyClientPlugin {
             incoming_latency: Duration::from_millis(75),
             incoming_jitter: Duration::from_millis(10),
             incoming_loss: 0.02,
+            ..Default::default()
         };
         let transport = match self.transport {
             Transports::Udp => TransportConfig::UdpSocket(client_addr),
@@ -56,6 +57,7 @@ impl Plugin for MyClientPlugin {
             input: InputConfig::default(),
             netcode: Default::default(),
             ping: PingConfig::default(),
+            priority: PriorityConfig::default(),
             sync: SyncConfig::default(),
             prediction: PredictionConfig {
                 input_delay_ticks: INPUT_DELAY_TICKS,
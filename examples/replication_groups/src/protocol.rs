@@ -302,6 +302,7 @@ pub(crate) fn protocol() -> MyProtocol {
     protocol.add_channel::<Channel1>(ChannelSettings {
         mode: ChannelMode::OrderedReliable(ReliableSettings::default()),
         direction: ChannelDirection::Bidirectional,
+        ..Default::default()
     });
     protocol
 }
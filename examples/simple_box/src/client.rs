@@ -32,6 +32,7 @@ impl Plugin for MyClientPlugin {
             incoming_latency: Duration::from_millis(200),
             incoming_jitter: Duration::from_millis(20),
             incoming_loss: 0.05,
+            ..Default::default()
         };
         let transport = match self.transport {
             Transports::Udp => TransportConfig::UdpSocket(client_addr),
@@ -47,6 +48,7 @@ impl Plugin for MyClientPlugin {
             input: InputConfig::default(),
             netcode: Default::default(),
             ping: PingConfig::default(),
+            priority: PriorityConfig::default(),
             sync: SyncConfig::default(),
             prediction: PredictionConfig::default(),
             // we are sending updates every frame (60fps), let's add a delay of 6 network-ticks
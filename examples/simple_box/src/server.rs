@@ -24,6 +24,7 @@ impl Plugin for MyServerPlugin {
             incoming_latency: Duration::from_millis(200),
             incoming_jitter: Duration::from_millis(20),
             incoming_loss: 0.05,
+            ..Default::default()
         };
         let transport = match self.transport {
             Transports::Udp => TransportConfig::UdpSocket(server_addr),
@@ -38,6 +39,7 @@ impl Plugin for MyServerPlugin {
             shared: shared_config().clone(),
             netcode: netcode_config,
             ping: PingConfig::default(),
+            priority: PriorityConfig::default(),
         };
         let plugin_config = PluginConfig::new(config, io, protocol());
         app.add_plugins(server::ServerPlugin::new(plugin_config));
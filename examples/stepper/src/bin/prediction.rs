@@ -115,6 +115,7 @@ fn main() -> anyhow::Result<()> {
         incoming_latency: Duration::from_millis(40),
         incoming_jitter: Duration::from_millis(5),
         incoming_loss: 0.05,
+        ..Default::default()
     };
     let mut stepper = BevyStepper::new(
         shared_config,
@@ -44,6 +44,7 @@ fn main() -> anyhow::Result<()> {
         incoming_latency: Duration::from_millis(40),
         incoming_jitter: Duration::from_millis(20),
         incoming_loss: 0.0,
+        ..Default::default()
     };
     let mut stepper = BevyStepper::new(
         shared_config,
@@ -22,6 +22,7 @@ pub fn bevy_setup(app: &mut App, auth: Authentication) {
         input: InputConfig::default(),
         netcode: Default::default(),
         ping: PingConfig::default(),
+        priority: PriorityConfig::default(),
         sync: SyncConfig::default(),
         prediction: PredictionConfig::default(),
         interpolation: InterpolationConfig::default(),
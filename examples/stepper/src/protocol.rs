@@ -64,10 +64,12 @@ pub fn protocol() -> MyProtocol {
     p.add_channel::<Channel1>(ChannelSettings {
         mode: ChannelMode::OrderedReliable(ReliableSettings::default()),
         direction: ChannelDirection::Bidirectional,
+        ..Default::default()
     });
     p.add_channel::<Channel2>(ChannelSettings {
         mode: ChannelMode::UnorderedUnreliable,
         direction: ChannelDirection::Bidirectional,
+        ..Default::default()
     });
     p
 }
@@ -23,6 +23,7 @@ pub fn bevy_setup(app: &mut App, addr: SocketAddr, protocol_id: u64, private_key
         },
         netcode: netcode_config,
         ping: PingConfig::default(),
+        priority: PriorityConfig::default(),
     };
     let plugin_config = PluginConfig::new(config, io, protocol());
     let plugin = ServerPlugin::new(plugin_config);
@@ -72,6 +72,7 @@ impl BevyStepper {
             shared: shared_config.clone(),
             netcode: netcode_config,
             ping: PingConfig::default(),
+            priority: PriorityConfig::default(),
         };
         let plugin_config = server::PluginConfig::new(config, io, protocol());
         let plugin = server::ServerPlugin::new(plugin_config);
@@ -96,6 +97,7 @@ impl BevyStepper {
             input: InputConfig::default(),
             netcode: Default::default(),
             ping: PingConfig::default(),
+            priority: PriorityConfig::default(),
             sync: sync_config,
             prediction: prediction_config,
             interpolation: interpolation_config,
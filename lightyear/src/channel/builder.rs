@@ -2,6 +2,7 @@
 use std::time::Duration;
 
 use lightyear_macros::ChannelInternal;
+use serde::Serialize;
 
 use crate::channel::receivers::ordered_reliable::OrderedReliableReceiver;
 use crate::channel::receivers::sequenced_reliable::SequencedReliableReceiver;
@@ -16,6 +17,7 @@ use crate::channel::senders::tick_unreliable::TickUnreliableSender;
 use crate::channel::senders::unordered_unreliable::UnorderedUnreliableSender;
 use crate::channel::senders::unordered_unreliable_with_acks::UnorderedUnreliableWithAcksSender;
 use crate::channel::senders::ChannelSender;
+use crate::packet::packet::FRAGMENT_SIZE;
 use crate::prelude::{ChannelKind, Named};
 
 /// A ChannelContainer is a struct that implements the [`Channel`] trait
@@ -58,33 +60,55 @@ impl ChannelContainer {
         let settings_clone = settings.clone();
         match settings.mode {
             ChannelMode::UnorderedUnreliableWithAcks => {
-                receiver = UnorderedUnreliableReceiver::new().into();
+                receiver = UnorderedUnreliableReceiver::new()
+                    .with_fragment_settings(settings.fragment)
+                    .into();
                 sender = UnorderedUnreliableWithAcksSender::new().into();
             }
             ChannelMode::UnorderedUnreliable => {
-                receiver = UnorderedUnreliableReceiver::new().into();
+                receiver = UnorderedUnreliableReceiver::new()
+                    .with_fragment_settings(settings.fragment)
+                    .into();
                 sender = UnorderedUnreliableSender::new().into();
             }
             ChannelMode::SequencedUnreliable => {
-                receiver = SequencedUnreliableReceiver::new().into();
+                receiver = SequencedUnreliableReceiver::new()
+                    .with_fragment_settings(settings.fragment)
+                    .into();
                 sender = SequencedUnreliableSender::new().into();
             }
             ChannelMode::UnorderedReliable(reliable_settings) => {
-                receiver = UnorderedReliableReceiver::new().into();
+                receiver = UnorderedReliableReceiver::new()
+                    .with_fragment_settings(settings.fragment)
+                    .into();
                 sender = ReliableSender::new(reliable_settings).into();
             }
             ChannelMode::SequencedReliable(reliable_settings) => {
-                receiver = SequencedReliableReceiver::new().into();
+                receiver = SequencedReliableReceiver::new()
+                    .with_fragment_settings(settings.fragment)
+                    .into();
                 sender = ReliableSender::new(reliable_settings).into();
             }
             ChannelMode::OrderedReliable(reliable_settings) => {
-                receiver = OrderedReliableReceiver::new().into();
+                receiver = OrderedReliableReceiver::new()
+                    .with_fragment_settings(settings.fragment)
+                    .into();
                 sender = ReliableSender::new(reliable_settings).into();
             }
             ChannelMode::TickBuffered => {
-                receiver = TickUnreliableReceiver::new().into();
+                receiver = TickUnreliableReceiver::new()
+                    .with_fragment_settings(settings.fragment)
+                    .into();
                 sender = TickUnreliableSender::new().into();
             }
+            ChannelMode::Streaming(stream_settings) => {
+                receiver = UnorderedReliableReceiver::new()
+                    .with_fragment_settings(settings.fragment)
+                    .into();
+                sender = ReliableSender::new(stream_settings.reliable)
+                    .with_bandwidth_cap(stream_settings.bandwidth_cap)
+                    .into();
+            }
         }
         Self {
             setting: settings_clone,
@@ -100,9 +124,27 @@ pub struct ChannelSettings {
     // TODO: split into Ordering and Reliability? Or not because we might to add new modes like TickBuffered
     pub mode: ChannelMode,
     pub direction: ChannelDirection,
+    /// Sets the priority of the channel. When the bandwidth budget of a connection is exhausted, the messages of
+    /// channels with lower priority are sent later. (the priority of a channel accumulates while it is waiting to send)
+    ///
+    /// Defaults to 1.0
+    pub priority: f32,
+    /// Limits applied when reassembling the fragmented messages received on the channel
+    pub fragment: FragmentSettings,
+}
+
+impl Default for ChannelSettings {
+    fn default() -> Self {
+        Self {
+            mode: ChannelMode::UnorderedUnreliable,
+            direction: ChannelDirection::Bidirectional,
+            priority: 1.0,
+            fragment: FragmentSettings::default(),
+        }
+    }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Serialize, Clone, Debug, PartialEq)]
 /// ChannelMode specifies how messages are sent and received
 /// See more information [here](http://www.jenkinssoftware.com/raknet/manual/reliabilitytypes.html)
 pub enum ChannelMode {
@@ -124,6 +166,9 @@ pub enum ChannelMode {
     /// Inputs from the client are associated with the current tick on the client.
     /// The server will buffer them and only receive them on the same tick.
     TickBuffered,
+    /// Unordered reliable channel that paces the messages to respect a bandwidth cap.
+    /// Used to stream large payloads in chunks without flooding the connection.
+    Streaming(StreamSettings),
 }
 
 impl ChannelMode {
@@ -136,6 +181,7 @@ impl ChannelMode {
             ChannelMode::SequencedReliable(_) => true,
             ChannelMode::OrderedReliable(_) => true,
             ChannelMode::TickBuffered => false,
+            ChannelMode::Streaming(_) => true,
         }
     }
 
@@ -149,11 +195,12 @@ impl ChannelMode {
             ChannelMode::SequencedReliable(_) => true,
             ChannelMode::OrderedReliable(_) => true,
             ChannelMode::TickBuffered => false,
+            ChannelMode::Streaming(_) => true,
         }
     }
 }
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Serialize, Clone, PartialEq, Debug)]
 /// [`ChannelDirection`] specifies in which direction the packets can be sent
 pub enum ChannelDirection {
     ClientToServer,
@@ -161,7 +208,7 @@ pub enum ChannelDirection {
     Bidirectional,
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Serialize, Clone, Debug, PartialEq)]
 pub struct ReliableSettings {
     /// Duration to wait before resending a packet if it has not been acked
     pub rtt_resend_factor: f32,
@@ -185,6 +232,76 @@ impl ReliableSettings {
     }
 }
 
+/// Limits that protect the receiver against peers that send fragments of huge messages, or fragments
+/// of messages that are never completed
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct FragmentSettings {
+    /// Maximum size in bytes of a message sent on the channel.
+    /// Bigger messages cannot be sent, and their fragments are dropped by the receiver
+    /// (on reliable channels, the remote peer is disconnected instead)
+    pub max_message_size: usize,
+    /// Maximum number of messages that can be partially reassembled at the same time.
+    /// The fragments of new messages are dropped when the limit is reached.
+    /// Only applies to unreliable channels; on reliable channels every fragment is needed to make progress
+    pub max_partial_messages: usize,
+    /// Partially reassembled messages are discarded if none of their fragments were received during this duration.
+    /// Only applies to unreliable channels; reliable channels keep re-sending the missing fragments
+    pub reassembly_timeout: Duration,
+    /// Maximum number of bytes of partially reassembled messages buffered by the channel.
+    /// The fragments that would exceed it are dropped. Must be at least `max_message_size`
+    pub max_buffered_bytes: usize,
+    /// Fragments of messages whose id is more than this ahead of the next message that the channel is waiting for are dropped.
+    /// Only applies to reliable channels, where it bounds the number of partially reassembled messages instead of
+    /// `max_partial_messages`. It must be bigger than the number of messages sent on the channel while a lost message is re-sent
+    pub max_message_id_gap: u16,
+    /// Number of fragments violating the limits that we accept from the remote peer during `violations_window`
+    /// before disconnecting it.
+    /// On reliable channels, a dropped fragment would stall the channel, so the first violation disconnects the peer
+    pub max_violations: usize,
+    /// The violations are counted over consecutive windows of this duration, so that a peer that occasionally
+    /// sends an invalid fragment is not disconnected after a long session
+    pub violations_window: Duration,
+}
+
+impl Default for FragmentSettings {
+    fn default() -> Self {
+        Self {
+            // the fragment index is a u8
+            max_message_size: u8::MAX as usize * FRAGMENT_SIZE,
+            max_partial_messages: 64,
+            reassembly_timeout: Duration::from_secs(3),
+            max_buffered_bytes: 4 * 1024 * 1024,
+            max_message_id_gap: 4096,
+            max_violations: 50,
+            violations_window: Duration::from_secs(10),
+        }
+    }
+}
+
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct StreamSettings {
+    pub reliable: ReliableSettings,
+    /// Maximum number of bytes per second sent on the channel (including the re-sent messages)
+    pub bandwidth_cap: usize,
+    /// Maximum size in bytes of a stream sent on the channel.
+    /// The receiver rejects incoming streams whose declared size is bigger, before buffering any of their chunks
+    pub max_transfer_size: usize,
+    /// Maximum number of incoming streams of the channel that can be in progress at the same time;
+    /// new streams are rejected until one of them completes or is cancelled
+    pub max_incoming_transfers: usize,
+}
+
+impl Default for StreamSettings {
+    fn default() -> Self {
+        Self {
+            reliable: ReliableSettings::default(),
+            bandwidth_cap: 100_000,
+            max_transfer_size: 4 * 1024 * 1024,
+            max_incoming_transfers: 4,
+        }
+    }
+}
+
 /// Default channel to replicate entity actions.
 /// This is an Unordered Reliable channel.
 /// (SpawnEntity, DespawnEntity, InsertComponent, RemoveComponent)
@@ -1,54 +1,193 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use anyhow::Result;
 use bytes::Bytes;
-use tracing::trace;
+use tracing::{trace, warn};
 
+use crate::channel::builder::FragmentSettings;
 use crate::packet::message::{FragmentData, MessageId, SingleData};
 use crate::packet::packet::FRAGMENT_SIZE;
 use crate::shared::time_manager::WrappedTime;
 
 /// `FragmentReceiver` is used to reconstruct fragmented messages
 pub struct FragmentReceiver {
+    settings: FragmentSettings,
     fragment_messages: HashMap<MessageId, FragmentConstructor>,
+    /// Number of bytes of the fragments buffered in `fragment_messages`
+    buffered_bytes: usize,
+    /// Number of fragments that were dropped because they violated the limits of the channel
+    /// during the current violations window
+    violations: usize,
+    violations_window_start: WrappedTime,
+    /// Set once the remote peer sent more than `max_violations` invalid fragments in a window
+    limits_exceeded: bool,
+    /// True if the fragments are received on a reliable channel
+    reliable: bool,
 }
 
 impl FragmentReceiver {
-    pub fn new() -> Self {
+    pub fn new(settings: FragmentSettings) -> Self {
         Self {
+            settings,
             fragment_messages: HashMap::new(),
+            buffered_bytes: 0,
+            violations: 0,
+            violations_window_start: WrappedTime::default(),
+            limits_exceeded: false,
+            reliable: false,
         }
     }
 
-    /// Discard all messages for which the latest fragment was received before the cleanup time
+    /// Create a receiver for a reliable channel.
+    ///
+    /// The packets of a reliable channel are acked even if some of their fragments get dropped, so the sender
+    /// would never re-send them and the channel would stall. Instead:
+    /// - the number of partially reassembled messages is bounded by `max_message_id_gap` instead of `max_partial_messages`,
+    ///   since all of them will be completed
+    /// - any fragment that violates the limits is fatal: the remote peer gets disconnected
+    pub fn new_reliable(settings: FragmentSettings) -> Self {
+        Self {
+            reliable: true,
+            ..Self::new(settings)
+        }
+    }
+
+    /// Start a new violations window if the current one has elapsed
+    pub fn update_violations(&mut self, current_time: WrappedTime) {
+        if self.violations_window_start + self.settings.violations_window <= current_time {
+            self.violations = 0;
+            self.violations_window_start = current_time;
+        }
+    }
+
+    /// Discard all messages for which the latest fragment was received more than `reassembly_timeout` ago
     /// (i.e. we probably lost some fragments and we will never complete the message)
     ///
     /// If we don't keep track of the last received time, we will never clean up the messages.
-    pub fn cleanup(&mut self, cleanup_time: WrappedTime) {
-        self.fragment_messages.retain(|_, c| {
-            c.last_received
-                .map(|t| t > cleanup_time)
-                .unwrap_or_else(|| true)
+    pub fn cleanup(&mut self, current_time: WrappedTime) {
+        let cleanup_time = current_time - self.settings.reassembly_timeout;
+        let buffered_bytes = &mut self.buffered_bytes;
+        self.fragment_messages.retain(|message_id, c| {
+            let keep = c.last_received.map(|t| t > cleanup_time).unwrap_or(true);
+            if !keep {
+                trace!(?message_id, "Discarding partially reassembled message");
+                #[cfg(feature = "metrics")]
+                metrics::counter!("fragments_dropped", c.fragments.len() as u64, "reason" => "reassembly_timeout");
+                *buffered_bytes -= c.received_bytes;
+            }
+            keep
+        })
+    }
+
+    /// Discard the partially reassembled messages older than `message_id`
+    pub fn discard_older(&mut self, message_id: MessageId) {
+        let buffered_bytes = &mut self.buffered_bytes;
+        self.fragment_messages.retain(|id, c| {
+            let keep = *id >= message_id;
+            if !keep {
+                trace!(message_id = ?id, "Discarding outdated partially reassembled message");
+                *buffered_bytes -= c.received_bytes;
+            }
+            keep
         })
     }
 
+    /// Returns true if the remote peer sent too many fragments that violated the limits of the channel
+    pub fn limits_exceeded(&self) -> bool {
+        self.limits_exceeded
+    }
+
+    /// Drop a fragment that violates the limits of the channel
+    fn drop_fragment(
+        &mut self,
+        fragment: &FragmentData,
+        reason: &'static str,
+    ) -> Option<SingleData> {
+        warn!(message_id = ?fragment.message_id, reason, "Dropping fragment");
+        #[cfg(feature = "metrics")]
+        metrics::increment_counter!("fragments_dropped", "reason" => reason);
+        self.violations += 1;
+        if self.reliable || self.violations > self.settings.max_violations {
+            self.limits_exceeded = true;
+        }
+        None
+    }
+
+    /// Receive a fragment on a reliable channel that is waiting for the message `pending_message_id`.
+    ///
+    /// The messages are never discarded, so the fragments of messages too far ahead of the pending one are dropped
+    /// to bound the number of partially reassembled messages
+    pub fn receive_reliable_fragment(
+        &mut self,
+        fragment: FragmentData,
+        pending_message_id: MessageId,
+    ) -> Result<Option<SingleData>> {
+        if (fragment.message_id - pending_message_id) as i32
+            > self.settings.max_message_id_gap as i32
+        {
+            return Ok(self.drop_fragment(&fragment, "message_id_too_far_ahead"));
+        }
+        self.receive_fragment(fragment, None)
+    }
+
     pub fn receive_fragment(
         &mut self,
         fragment: FragmentData,
         current_time: Option<WrappedTime>,
     ) -> Result<Option<SingleData>> {
+        let num_fragments = fragment.num_fragments as usize;
+        let fragment_index = fragment.fragment_id as usize;
+        let is_last_fragment = fragment_index + 1 == num_fragments;
+        if fragment_index >= num_fragments
+            || fragment.bytes.len() > FRAGMENT_SIZE
+            || (!is_last_fragment && fragment.bytes.len() != FRAGMENT_SIZE)
+        {
+            return Ok(self.drop_fragment(&fragment, "invalid"));
+        }
+        // the size of the message is only known when we receive the last fragment, but we can already
+        // reject the messages that would be too big even with an empty last fragment
+        let min_message_size = (num_fragments - 1) * FRAGMENT_SIZE
+            + if is_last_fragment {
+                fragment.bytes.len()
+            } else {
+                1
+            };
+        if min_message_size > self.settings.max_message_size {
+            return Ok(self.drop_fragment(&fragment, "message_too_large"));
+        }
+        match self.fragment_messages.get(&fragment.message_id) {
+            Some(constructor) if constructor.num_fragments != num_fragments => {
+                return Ok(self.drop_fragment(&fragment, "invalid"));
+            }
+            Some(constructor) if constructor.fragments.contains_key(&fragment_index) => {
+                trace!(message_id = ?fragment.message_id, fragment_index, "Ignoring duplicate fragment");
+                return Ok(None);
+            }
+            None if !self.reliable
+                && self.fragment_messages.len() >= self.settings.max_partial_messages =>
+            {
+                return Ok(self.drop_fragment(&fragment, "too_many_partial_messages"));
+            }
+            _ => {}
+        }
+        if self.buffered_bytes + fragment.bytes.len() > self.settings.max_buffered_bytes {
+            return Ok(self.drop_fragment(&fragment, "too_many_buffered_bytes"));
+        }
+        self.buffered_bytes += fragment.bytes.len();
+
         let fragment_message = self
             .fragment_messages
             .entry(fragment.message_id)
-            .or_insert_with(|| FragmentConstructor::new(fragment.num_fragments as usize));
+            .or_insert_with(|| FragmentConstructor::new(num_fragments));
 
         // completed the fragmented message!
         if let Some(payload) = fragment_message.receive_fragment(
-            fragment.fragment_id as usize,
+            fragment_index,
             fragment.bytes.as_ref(),
             current_time,
         )? {
             self.fragment_messages.remove(&fragment.message_id);
+            self.buffered_bytes -= payload.len();
             let mut data = SingleData::new(Some(fragment.message_id), payload);
             // TODO: verify that all fragments had the same tick
             data.tick = fragment.tick;
@@ -63,10 +202,10 @@ impl FragmentReceiver {
 /// Data structure to reconstruct a single fragmented message from individual fragments
 pub struct FragmentConstructor {
     num_fragments: usize,
-    num_received_fragments: usize,
-    received: Vec<bool>,
-    // bytes: Bytes,
-    bytes: Vec<u8>,
+    /// Fragments received so far, by index. The message is only assembled once every fragment is received,
+    /// so that the memory used grows with the bytes actually received
+    fragments: BTreeMap<usize, Vec<u8>>,
+    received_bytes: usize,
 
     last_received: Option<WrappedTime>,
 }
@@ -75,9 +214,8 @@ impl FragmentConstructor {
     pub fn new(num_fragments: usize) -> Self {
         Self {
             num_fragments,
-            num_received_fragments: 0,
-            received: vec![false; num_fragments],
-            bytes: vec![0; num_fragments * FRAGMENT_SIZE],
+            fragments: BTreeMap::new(),
+            received_bytes: 0,
             last_received: None,
         }
     }
@@ -90,26 +228,18 @@ impl FragmentConstructor {
     ) -> Result<Option<Bytes>> {
         self.last_received = received_time;
 
-        let is_last_fragment = fragment_index == self.num_fragments - 1;
-        // TODO: check sizes?
-
-        if !self.received[fragment_index] {
-            self.received[fragment_index] = true;
-            self.num_received_fragments += 1;
-
-            if is_last_fragment {
-                let len = (self.num_fragments - 1) * FRAGMENT_SIZE + bytes.len();
-                self.bytes.resize(len, 0);
-            }
-
-            let start = fragment_index * FRAGMENT_SIZE;
-            let end = start + bytes.len();
-            self.bytes[start..end].copy_from_slice(bytes);
+        if !self.fragments.contains_key(&fragment_index) {
+            self.received_bytes += bytes.len();
+            self.fragments.insert(fragment_index, bytes.to_vec());
         }
 
-        if self.num_received_fragments == self.num_fragments {
+        if self.fragments.len() == self.num_fragments {
             trace!("Received all fragments!");
-            let payload = std::mem::take(&mut self.bytes);
+            let mut payload = Vec::with_capacity(self.received_bytes);
+            for fragment in std::mem::take(&mut self.fragments).into_values() {
+                payload.extend_from_slice(&fragment);
+            }
+            self.received_bytes = 0;
             return Ok(Some(payload.into()));
         }
 
@@ -125,7 +255,7 @@ mod tests {
 
     #[test]
     fn test_receiver() -> Result<()> {
-        let mut receiver = FragmentReceiver::new();
+        let mut receiver = FragmentReceiver::new(FragmentSettings::default());
         let num_bytes = (FRAGMENT_SIZE as f32 * 1.5) as usize;
         let message_bytes = Bytes::from(vec![1u8; num_bytes]);
         let fragments =
@@ -142,4 +272,176 @@ mod tests {
         );
         Ok(())
     }
+
+    fn fragment(message_id: u16, fragment_id: u8, num_fragments: u8) -> FragmentData {
+        let len = if fragment_id + 1 == num_fragments {
+            10
+        } else {
+            FRAGMENT_SIZE
+        };
+        FragmentData {
+            message_id: MessageId(message_id),
+            tick: None,
+            fragment_id,
+            num_fragments,
+            bytes: Bytes::from(vec![0; len]),
+        }
+    }
+
+    #[test]
+    fn test_receiver_limits() -> Result<()> {
+        let mut receiver = FragmentReceiver::new(FragmentSettings {
+            max_message_size: 3 * FRAGMENT_SIZE,
+            max_partial_messages: 2,
+            reassembly_timeout: std::time::Duration::from_secs(1),
+            max_violations: 3,
+            violations_window: std::time::Duration::from_secs(10),
+            ..Default::default()
+        });
+
+        // messages that are too big are dropped
+        assert_eq!(receiver.receive_fragment(fragment(0, 0, 10), None)?, None);
+        assert!(receiver.fragment_messages.is_empty());
+        // invalid fragments are dropped
+        assert_eq!(receiver.receive_fragment(fragment(1, 3, 2), None)?, None);
+        assert!(receiver.fragment_messages.is_empty());
+        assert_eq!(receiver.violations, 2);
+
+        // at most 2 messages can be partially reassembled at the same time
+        receiver.receive_fragment(fragment(2, 0, 2), None)?;
+        receiver.receive_fragment(fragment(3, 0, 2), None)?;
+        receiver.receive_fragment(fragment(4, 0, 2), None)?;
+        assert_eq!(receiver.fragment_messages.len(), 2);
+        assert_eq!(receiver.violations, 3);
+        assert!(!receiver.limits_exceeded());
+
+        // the existing messages can still be completed
+        assert!(receiver
+            .receive_fragment(fragment(2, 1, 2), None)?
+            .is_some());
+        assert_eq!(receiver.fragment_messages.len(), 1);
+
+        // a fragment that doesn't match the other fragments of its message is dropped
+        receiver.receive_fragment(fragment(3, 0, 3), None)?;
+        assert!(receiver.limits_exceeded());
+        Ok(())
+    }
+
+    #[test]
+    fn test_reliable_receiver_limits() -> Result<()> {
+        let mut receiver = FragmentReceiver::new_reliable(FragmentSettings {
+            max_partial_messages: 1,
+            max_message_id_gap: 2,
+            max_violations: 3,
+            ..Default::default()
+        });
+        // the number of partially reassembled messages is not capped by `max_partial_messages`
+        receiver.receive_reliable_fragment(fragment(0, 0, 2), MessageId(0))?;
+        receiver.receive_reliable_fragment(fragment(1, 0, 2), MessageId(0))?;
+        assert_eq!(receiver.fragment_messages.len(), 2);
+        assert!(!receiver.limits_exceeded());
+
+        // a fragment of a message too far ahead of the pending one is fatal
+        receiver.receive_reliable_fragment(fragment(3, 0, 2), MessageId(0))?;
+        assert_eq!(receiver.fragment_messages.len(), 2);
+        assert!(receiver.limits_exceeded());
+
+        // a single invalid fragment is fatal
+        let mut receiver = FragmentReceiver::new_reliable(FragmentSettings::default());
+        receiver.receive_reliable_fragment(fragment(1, 0, 2), MessageId(0))?;
+        receiver.receive_reliable_fragment(fragment(1, 0, 3), MessageId(0))?;
+        assert!(receiver.limits_exceeded());
+        Ok(())
+    }
+
+    #[test]
+    fn test_receiver_buffered_bytes() -> Result<()> {
+        let mut receiver = FragmentReceiver::new_reliable(FragmentSettings {
+            max_buffered_bytes: 3 * FRAGMENT_SIZE + 10,
+            ..Default::default()
+        });
+        // the fragments are only buffered as they arrive
+        receiver.receive_reliable_fragment(fragment(0, 0, 100), MessageId(0))?;
+        receiver.receive_reliable_fragment(fragment(1, 0, 3), MessageId(0))?;
+        assert_eq!(receiver.buffered_bytes, 2 * FRAGMENT_SIZE);
+        assert_eq!(
+            receiver.fragment_messages[&MessageId(0)].received_bytes,
+            FRAGMENT_SIZE
+        );
+        // duplicate fragments don't count
+        receiver.receive_reliable_fragment(fragment(1, 0, 3), MessageId(0))?;
+        assert_eq!(receiver.buffered_bytes, 2 * FRAGMENT_SIZE);
+
+        // completing a message releases its bytes
+        receiver.receive_reliable_fragment(fragment(1, 1, 3), MessageId(0))?;
+        assert!(receiver
+            .receive_reliable_fragment(fragment(1, 2, 3), MessageId(0))?
+            .is_some());
+        assert_eq!(receiver.buffered_bytes, FRAGMENT_SIZE);
+        assert!(!receiver.limits_exceeded());
+
+        // a fragment that would exceed the cap is dropped
+        receiver.receive_reliable_fragment(fragment(2, 0, 3), MessageId(0))?;
+        receiver.receive_reliable_fragment(fragment(2, 1, 3), MessageId(0))?;
+        assert!(!receiver.limits_exceeded());
+        receiver.receive_reliable_fragment(fragment(3, 0, 3), MessageId(0))?;
+        assert!(receiver.limits_exceeded());
+        assert_eq!(receiver.buffered_bytes, 3 * FRAGMENT_SIZE);
+
+        // the messages that the channel won't wait for anymore release their bytes
+        receiver.discard_older(MessageId(2));
+        assert_eq!(receiver.fragment_messages.len(), 1);
+        assert_eq!(receiver.buffered_bytes, 2 * FRAGMENT_SIZE);
+        Ok(())
+    }
+
+    #[test]
+    fn test_receiver_reassembly_timeout() -> Result<()> {
+        let mut receiver = FragmentReceiver::new(FragmentSettings {
+            reassembly_timeout: std::time::Duration::from_secs(1),
+            ..Default::default()
+        });
+        let mut time = WrappedTime::new(0);
+        time += std::time::Duration::from_secs(10);
+        receiver.receive_fragment(fragment(0, 0, 2), Some(time))?;
+
+        time += std::time::Duration::from_millis(500);
+        receiver.cleanup(time);
+        assert_eq!(receiver.fragment_messages.len(), 1);
+
+        time += std::time::Duration::from_millis(600);
+        receiver.cleanup(time);
+        assert!(receiver.fragment_messages.is_empty());
+        // timeouts are expected on unreliable channels, they are not violations
+        assert_eq!(receiver.violations, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_receiver_violations_window() -> Result<()> {
+        let mut receiver = FragmentReceiver::new(FragmentSettings {
+            max_violations: 1,
+            violations_window: std::time::Duration::from_secs(1),
+            ..Default::default()
+        });
+        let mut time = WrappedTime::new(0);
+        receiver.update_violations(time);
+        receiver.receive_fragment(fragment(0, 3, 2), None)?;
+        assert_eq!(receiver.violations, 1);
+
+        // the violations of a previous window are forgotten
+        time += std::time::Duration::from_millis(1500);
+        receiver.update_violations(time);
+        assert_eq!(receiver.violations, 0);
+        receiver.receive_fragment(fragment(1, 3, 2), None)?;
+        assert!(!receiver.limits_exceeded());
+
+        // exceeding the limit within a window is not forgotten
+        receiver.receive_fragment(fragment(2, 3, 2), None)?;
+        assert!(receiver.limits_exceeded());
+        time += std::time::Duration::from_millis(1500);
+        receiver.update_violations(time);
+        assert!(receiver.limits_exceeded());
+        Ok(())
+    }
 }
@@ -35,6 +35,10 @@ pub trait ChannelReceive {
 
     /// Reads a message from the internal buffer to get its content
     fn read_message(&mut self) -> Option<SingleData>;
+
+    /// Returns true if the remote peer sent too many fragments that violated the
+    /// [`FragmentSettings`](crate::channel::builder::FragmentSettings) of the channel
+    fn fragment_limits_exceeded(&self) -> bool;
 }
 
 /// This enum contains the various types of receivers available
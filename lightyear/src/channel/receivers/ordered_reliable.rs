@@ -2,6 +2,7 @@ use std::collections::{btree_map, BTreeMap};
 
 use anyhow::anyhow;
 
+use crate::channel::builder::FragmentSettings;
 use crate::channel::receivers::fragment_receiver::FragmentReceiver;
 use crate::channel::receivers::ChannelReceive;
 use crate::packet::message::{MessageContainer, MessageId, SingleData};
@@ -25,13 +26,22 @@ impl OrderedReliableReceiver {
         Self {
             pending_recv_message_id: MessageId(0),
             recv_message_buffer: BTreeMap::new(),
-            fragment_receiver: FragmentReceiver::new(),
+            fragment_receiver: FragmentReceiver::new_reliable(FragmentSettings::default()),
         }
     }
+
+    /// Set the limits used to reassemble the fragmented messages
+    pub fn with_fragment_settings(mut self, settings: FragmentSettings) -> Self {
+        self.fragment_receiver = FragmentReceiver::new_reliable(settings);
+        self
+    }
 }
 
 impl ChannelReceive for OrderedReliableReceiver {
-    fn update(&mut self, _: &TimeManager, _: &TickManager) {}
+    fn update(&mut self, time_manager: &TimeManager, _: &TickManager) {
+        self.fragment_receiver
+            .update_violations(time_manager.current_time());
+    }
 
     /// Queues a received message in an internal buffer
     fn buffer_recv(&mut self, message: MessageContainer) -> anyhow::Result<()> {
@@ -51,8 +61,9 @@ impl ChannelReceive for OrderedReliableReceiver {
                     entry.insert(data);
                 }
                 MessageContainer::Fragment(data) => {
-                    if let Some(single_data) =
-                        self.fragment_receiver.receive_fragment(data, None)?
+                    if let Some(single_data) = self
+                        .fragment_receiver
+                        .receive_reliable_fragment(data, self.pending_recv_message_id)?
                     {
                         entry.insert(single_data);
                     }
@@ -80,15 +91,22 @@ impl ChannelReceive for OrderedReliableReceiver {
         self.pending_recv_message_id += 1;
         Some(message)
     }
+
+    fn fragment_limits_exceeded(&self) -> bool {
+        self.fragment_receiver.limits_exceeded()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use bytes::Bytes;
 
+    use crate::channel::builder::FragmentSettings;
     use crate::channel::receivers::ordered_reliable::OrderedReliableReceiver;
     use crate::channel::receivers::ChannelReceive;
+    use crate::channel::senders::fragment_sender::FragmentSender;
     use crate::packet::message::{MessageId, SingleData};
+    use crate::packet::packet::FRAGMENT_SIZE;
 
     #[test]
     fn test_ordered_reliable_receiver_internals() -> anyhow::Result<()> {
@@ -124,4 +142,43 @@ mod tests {
         assert_eq!(receiver.read_message(), Some(single2.clone()));
         Ok(())
     }
+
+    #[test]
+    fn test_ordered_reliable_receiver_many_fragmented_messages() -> anyhow::Result<()> {
+        let mut receiver =
+            OrderedReliableReceiver::new().with_fragment_settings(FragmentSettings {
+                max_partial_messages: 2,
+                ..Default::default()
+            });
+        let messages: Vec<_> = (0..4u8)
+            .map(|i| Bytes::from(vec![i; FRAGMENT_SIZE + 10]))
+            .collect();
+        let fragments: Vec<_> = messages
+            .iter()
+            .enumerate()
+            .map(|(i, bytes)| {
+                FragmentSender::new().build_fragments(MessageId(i as u16), None, bytes.clone())
+            })
+            .collect();
+
+        // receive the first fragment of more messages than the cap
+        for message_fragments in &fragments {
+            receiver.buffer_recv(message_fragments[0].clone().into())?;
+        }
+        assert!(!receiver.fragment_limits_exceeded());
+        assert_eq!(receiver.read_message(), None);
+
+        // the fragments are not re-sent once their packet is acked, so none of them can be dropped
+        for message_fragments in &fragments {
+            receiver.buffer_recv(message_fragments[1].clone().into())?;
+        }
+        for (i, bytes) in messages.into_iter().enumerate() {
+            assert_eq!(
+                receiver.read_message(),
+                Some(SingleData::new(Some(MessageId(i as u16)), bytes))
+            );
+        }
+        assert!(!receiver.fragment_limits_exceeded());
+        Ok(())
+    }
 }
@@ -2,6 +2,7 @@ use std::collections::{btree_map, BTreeMap};
 
 use anyhow::anyhow;
 
+use crate::channel::builder::FragmentSettings;
 use crate::channel::receivers::fragment_receiver::FragmentReceiver;
 use crate::channel::receivers::ChannelReceive;
 use crate::packet::message::{MessageContainer, MessageId, SingleData};
@@ -25,13 +26,22 @@ impl SequencedReliableReceiver {
         Self {
             recv_message_buffer: BTreeMap::new(),
             most_recent_message_id: MessageId(0),
-            fragment_receiver: FragmentReceiver::new(),
+            fragment_receiver: FragmentReceiver::new_reliable(FragmentSettings::default()),
         }
     }
+
+    /// Set the limits used to reassemble the fragmented messages
+    pub fn with_fragment_settings(mut self, settings: FragmentSettings) -> Self {
+        self.fragment_receiver = FragmentReceiver::new_reliable(settings);
+        self
+    }
 }
 
 impl ChannelReceive for SequencedReliableReceiver {
-    fn update(&mut self, _: &TimeManager, _: &TickManager) {}
+    fn update(&mut self, time_manager: &TimeManager, _: &TickManager) {
+        self.fragment_receiver
+            .update_violations(time_manager.current_time());
+    }
 
     /// Queues a received message in an internal buffer
     fn buffer_recv(&mut self, message: MessageContainer) -> anyhow::Result<()> {
@@ -45,8 +55,11 @@ impl ChannelReceive for SequencedReliableReceiver {
         }
 
         // update the most recent message id
+        let previous_message_id = self.most_recent_message_id;
         if message_id > self.most_recent_message_id {
             self.most_recent_message_id = message_id;
+            // the older messages will be ignored, so there is no point in reassembling them
+            self.fragment_receiver.discard_older(message_id);
         }
 
         // add the message to the buffer
@@ -56,8 +69,9 @@ impl ChannelReceive for SequencedReliableReceiver {
                     entry.insert(data);
                 }
                 MessageContainer::Fragment(data) => {
-                    if let Some(single_data) =
-                        self.fragment_receiver.receive_fragment(data, None)?
+                    if let Some(single_data) = self
+                        .fragment_receiver
+                        .receive_reliable_fragment(data, previous_message_id)?
                     {
                         entry.insert(single_data);
                     }
@@ -77,6 +91,10 @@ impl ChannelReceive for SequencedReliableReceiver {
             }
         }
     }
+
+    fn fragment_limits_exceeded(&self) -> bool {
+        self.fragment_receiver.limits_exceeded()
+    }
 }
 
 #[cfg(test)]
@@ -2,14 +2,13 @@ use std::collections::VecDeque;
 
 use anyhow::anyhow;
 
+use crate::channel::builder::FragmentSettings;
 use crate::channel::receivers::fragment_receiver::FragmentReceiver;
 use crate::channel::receivers::ChannelReceive;
 use crate::packet::message::{MessageContainer, MessageId, SingleData};
 use crate::shared::tick_manager::TickManager;
 use crate::shared::time_manager::{TimeManager, WrappedTime};
 
-const DISCARD_AFTER: chrono::Duration = chrono::Duration::milliseconds(3000);
-
 /// Sequenced Unreliable receiver:
 /// do not return messages in order, but ignore the messages that are older than the most recent one received
 pub struct SequencedUnreliableReceiver {
@@ -26,18 +25,24 @@ impl SequencedUnreliableReceiver {
         Self {
             recv_message_buffer: VecDeque::new(),
             most_recent_message_id: MessageId(0),
-            fragment_receiver: FragmentReceiver::new(),
+            fragment_receiver: FragmentReceiver::new(FragmentSettings::default()),
             // TODO: starting at 0 time could be dangerous, because the first update will bring it to time_manager time ?
             current_time: WrappedTime::default(),
         }
     }
+
+    /// Set the limits used to reassemble the fragmented messages
+    pub fn with_fragment_settings(mut self, settings: FragmentSettings) -> Self {
+        self.fragment_receiver = FragmentReceiver::new(settings);
+        self
+    }
 }
 
 impl ChannelReceive for SequencedUnreliableReceiver {
     fn update(&mut self, time_manager: &TimeManager, _: &TickManager) {
         self.current_time = time_manager.current_time();
-        self.fragment_receiver
-            .cleanup(self.current_time - DISCARD_AFTER);
+        self.fragment_receiver.cleanup(self.current_time);
+        self.fragment_receiver.update_violations(self.current_time);
     }
 
     /// Queues a received message in an internal buffer
@@ -74,6 +79,10 @@ impl ChannelReceive for SequencedUnreliableReceiver {
         self.recv_message_buffer.pop_front()
         // TODO: naia does a more optimized version by return a Vec<Message> instead of Option<Message>
     }
+
+    fn fragment_limits_exceeded(&self) -> bool {
+        self.fragment_receiver.limits_exceeded()
+    }
 }
 
 #[cfg(test)]
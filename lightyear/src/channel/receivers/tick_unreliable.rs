@@ -1,5 +1,6 @@
 use anyhow::Context;
 
+use crate::channel::builder::FragmentSettings;
 use crate::channel::receivers::fragment_receiver::FragmentReceiver;
 use crate::channel::receivers::ChannelReceive;
 use crate::packet::message::{MessageContainer, SingleData};
@@ -8,8 +9,6 @@ use crate::shared::tick_manager::TickManager;
 use crate::shared::time_manager::{TimeManager, WrappedTime};
 use crate::utils::ready_buffer::ReadyBuffer;
 
-const DISCARD_AFTER: chrono::Duration = chrono::Duration::milliseconds(3000);
-
 /// Sequenced Unreliable receiver:
 /// do not return messages in order, but ignore the messages that are older than the most recent one received
 pub struct TickUnreliableReceiver {
@@ -31,11 +30,17 @@ impl TickUnreliableReceiver {
     pub fn new() -> Self {
         Self {
             recv_message_buffer: ReadyBuffer::new(),
-            fragment_receiver: FragmentReceiver::new(),
+            fragment_receiver: FragmentReceiver::new(FragmentSettings::default()),
             current_time: WrappedTime::default(),
             current_tick: Tick(0),
         }
     }
+
+    /// Set the limits used to reassemble the fragmented messages
+    pub fn with_fragment_settings(mut self, settings: FragmentSettings) -> Self {
+        self.fragment_receiver = FragmentReceiver::new(settings);
+        self
+    }
 }
 
 impl TickUnreliableReceiver {
@@ -56,8 +61,8 @@ impl ChannelReceive for TickUnreliableReceiver {
     fn update(&mut self, time_manager: &TimeManager, tick_manager: &TickManager) {
         self.current_time = time_manager.current_time();
         self.current_tick = tick_manager.tick();
-        self.fragment_receiver
-            .cleanup(self.current_time - DISCARD_AFTER);
+        self.fragment_receiver.cleanup(self.current_time);
+        self.fragment_receiver.update_violations(self.current_time);
     }
 
     /// Queues a received message in an internal buffer
@@ -82,6 +87,10 @@ impl ChannelReceive for TickUnreliableReceiver {
             .map(|(_, data)| data)
         // TODO: naia does a more optimized version by return a Vec<Message> instead of Option<Message>
     }
+
+    fn fragment_limits_exceeded(&self) -> bool {
+        self.fragment_receiver.limits_exceeded()
+    }
 }
 
 #[cfg(test)]
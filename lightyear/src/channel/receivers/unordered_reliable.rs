@@ -2,6 +2,7 @@ use std::collections::{btree_map, BTreeMap, HashSet};
 
 use anyhow::anyhow;
 
+use crate::channel::builder::FragmentSettings;
 use crate::channel::receivers::fragment_receiver::FragmentReceiver;
 use crate::channel::receivers::ChannelReceive;
 use crate::packet::message::{MessageContainer, MessageId, SingleData};
@@ -28,14 +29,23 @@ impl UnorderedReliableReceiver {
         Self {
             pending_recv_message_id: MessageId(0),
             recv_message_buffer: BTreeMap::new(),
-            fragment_receiver: FragmentReceiver::new(),
+            fragment_receiver: FragmentReceiver::new_reliable(FragmentSettings::default()),
             received_message_ids: HashSet::new(),
         }
     }
+
+    /// Set the limits used to reassemble the fragmented messages
+    pub fn with_fragment_settings(mut self, settings: FragmentSettings) -> Self {
+        self.fragment_receiver = FragmentReceiver::new_reliable(settings);
+        self
+    }
 }
 
 impl ChannelReceive for UnorderedReliableReceiver {
-    fn update(&mut self, _: &TimeManager, _: &TickManager) {}
+    fn update(&mut self, time_manager: &TimeManager, _: &TickManager) {
+        self.fragment_receiver
+            .update_violations(time_manager.current_time());
+    }
 
     /// Queues a received message in an internal buffer
     fn buffer_recv(&mut self, message: MessageContainer) -> anyhow::Result<()> {
@@ -62,8 +72,9 @@ impl ChannelReceive for UnorderedReliableReceiver {
                     }
                 }
                 MessageContainer::Fragment(data) => {
-                    if let Some(single_data) =
-                        self.fragment_receiver.receive_fragment(data, None)?
+                    if let Some(single_data) = self
+                        .fragment_receiver
+                        .receive_reliable_fragment(data, self.pending_recv_message_id)?
                     {
                         if let Some(message_id) = single_data.id {
                             // receive the message if we haven't received it already
@@ -101,6 +112,10 @@ impl ChannelReceive for UnorderedReliableReceiver {
         // receive oldest message in the buffer
         Some(message)
     }
+
+    fn fragment_limits_exceeded(&self) -> bool {
+        self.fragment_receiver.limits_exceeded()
+    }
 }
 
 #[cfg(test)]
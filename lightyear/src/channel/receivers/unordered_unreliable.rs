@@ -1,14 +1,13 @@
 use std::collections::VecDeque;
 use tracing::info;
 
+use crate::channel::builder::FragmentSettings;
 use crate::channel::receivers::fragment_receiver::FragmentReceiver;
 use crate::channel::receivers::ChannelReceive;
 use crate::packet::message::{MessageContainer, SingleData};
 use crate::shared::tick_manager::TickManager;
 use crate::shared::time_manager::{TimeManager, WrappedTime};
 
-const DISCARD_AFTER: chrono::Duration = chrono::Duration::milliseconds(3000);
-
 pub struct UnorderedUnreliableReceiver {
     recv_message_buffer: VecDeque<SingleData>,
     fragment_receiver: FragmentReceiver,
@@ -19,17 +18,23 @@ impl UnorderedUnreliableReceiver {
     pub fn new() -> Self {
         Self {
             recv_message_buffer: VecDeque::new(),
-            fragment_receiver: FragmentReceiver::new(),
+            fragment_receiver: FragmentReceiver::new(FragmentSettings::default()),
             current_time: WrappedTime::default(),
         }
     }
+
+    /// Set the limits used to reassemble the fragmented messages
+    pub fn with_fragment_settings(mut self, settings: FragmentSettings) -> Self {
+        self.fragment_receiver = FragmentReceiver::new(settings);
+        self
+    }
 }
 
 impl ChannelReceive for UnorderedUnreliableReceiver {
     fn update(&mut self, time_manager: &TimeManager, _: &TickManager) {
         self.current_time = time_manager.current_time();
-        self.fragment_receiver
-            .cleanup(self.current_time - DISCARD_AFTER);
+        self.fragment_receiver.cleanup(self.current_time);
+        self.fragment_receiver.update_violations(self.current_time);
     }
 
     fn buffer_recv(&mut self, message: MessageContainer) -> anyhow::Result<()> {
@@ -50,6 +55,10 @@ impl ChannelReceive for UnorderedUnreliableReceiver {
     fn read_message(&mut self) -> Option<SingleData> {
         self.recv_message_buffer.pop_front()
     }
+
+    fn fragment_limits_exceeded(&self) -> bool {
+        self.fragment_receiver.limits_exceeded()
+    }
 }
 
 #[cfg(test)]
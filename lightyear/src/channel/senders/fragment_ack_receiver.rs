@@ -28,12 +28,27 @@ impl FragmentAckReceiver {
     /// (i.e. we probably lost some fragments and we will never get all the acks for this fragmented message)
     ///
     /// If we don't keep track of the last received time, we will never clean up the messages.
-    pub fn cleanup(&mut self, cleanup_time: WrappedTime) {
-        self.fragment_messages.retain(|_, c| {
-            c.last_received
+    ///
+    /// Returns the ids of the discarded messages.
+    pub fn cleanup(&mut self, cleanup_time: WrappedTime) -> Vec<MessageId> {
+        let mut discarded = Vec::new();
+        self.fragment_messages.retain(|message_id, c| {
+            let keep = c
+                .last_received
                 .map(|t| t > cleanup_time)
-                .unwrap_or_else(|| true)
-        })
+                .unwrap_or_else(|| true);
+            if !keep {
+                discarded.push(*message_id);
+            }
+            keep
+        });
+        discarded
+    }
+
+    /// Stop waiting for the acks of a fragmented message (for example because one of its fragments was lost).
+    /// Returns true if we were still waiting for acks for this message
+    pub fn discard(&mut self, message_id: MessageId) -> bool {
+        self.fragment_messages.remove(&message_id).is_some()
     }
 
     /// We receive a fragment ack, and return true if the entire fragment was acked.
@@ -44,7 +59,8 @@ impl FragmentAckReceiver {
         current_time: Option<WrappedTime>,
     ) -> bool {
         let Some(fragment_ack_tracker) = self.fragment_messages.get_mut(&message_id) else {
-            error!("Received fragment ack for unknown message id");
+            // this can happen if another fragment of the message was lost
+            trace!("Received fragment ack for unknown message id");
             return false;
         };
 
@@ -124,7 +140,7 @@ mod tests {
         receiver.add_new_fragment_to_wait_for(MessageId(0), 2);
 
         assert!(!receiver.receive_fragment_ack(MessageId(0), 0, Some(WrappedTime::new(150))));
-        receiver.cleanup(WrappedTime::new(170));
+        assert_eq!(receiver.cleanup(WrappedTime::new(170)), vec![MessageId(0)]);
         assert!(receiver.fragment_messages.is_empty());
         Ok(())
     }
@@ -3,7 +3,7 @@ use crate::shared::ping::manager::PingManager;
 use crate::shared::tick_manager::TickManager;
 use crate::shared::time_manager::TimeManager;
 use bytes::Bytes;
-use crossbeam_channel::Receiver;
+use crossbeam_channel::{Receiver, Sender};
 use enum_dispatch::enum_dispatch;
 use std::collections::VecDeque;
 
@@ -46,11 +46,25 @@ pub trait ChannelSend {
     /// Called when we receive acknowledgement that a Message has been received
     fn notify_message_delivered(&mut self, message_ack: &MessageAck);
 
+    /// Called when a packet containing the Message was not acked in time and is considered lost
+    fn notify_message_lost(&mut self, message_ack: &MessageAck);
+
     /// Returns true if there are messages in the buffer that are ready to be sent
     fn has_messages_to_send(&self) -> bool;
 
+    /// Number of messages that are kept until the remote peer acknowledges them
+    fn num_unacked_messages(&self) -> usize;
+
     /// Create a new receiver that will receive a message id when a sent message is acked
     fn subscribe_acks(&mut self) -> Receiver<MessageId>;
+
+    /// Create a new receiver that will receive a message id when a sent message is lost
+    fn subscribe_losses(&mut self) -> Receiver<MessageId>;
+}
+
+/// Notify the subscribers of a channel, and forget the ones that dropped their receiver
+pub(crate) fn notify_subscribers(subscribers: &mut Vec<Sender<MessageId>>, message_id: MessageId) {
+    subscribers.retain(|sender| sender.send(message_id).is_ok());
 }
 
 /// Enum dispatch lets us derive ChannelSend on each enum variant
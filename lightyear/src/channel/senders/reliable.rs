@@ -3,12 +3,12 @@ use std::collections::{BTreeMap, HashSet};
 use std::time::Duration;
 
 use bytes::Bytes;
-use crossbeam_channel::Receiver;
+use crossbeam_channel::{Receiver, Sender};
 use tracing::trace;
 
 use crate::channel::builder::ReliableSettings;
 use crate::channel::senders::fragment_sender::FragmentSender;
-use crate::channel::senders::ChannelSend;
+use crate::channel::senders::{notify_subscribers, ChannelSend};
 use crate::packet::message::{FragmentData, MessageAck, MessageId, SingleData};
 use crate::shared::ping::manager::PingManager;
 use crate::shared::tick_manager::TickManager;
@@ -53,10 +53,22 @@ pub struct ReliableSender {
     /// Used to split a message into fragments if the message is too big
     fragment_sender: FragmentSender,
 
+    /// List of senders that want to be notified when a message is acked
+    ack_senders: Vec<Sender<MessageId>>,
+
+    /// If set, maximum number of bytes per second that the channel can send
+    bandwidth_cap: Option<usize>,
+    /// Number of bytes that the channel can still send. Can become negative if a message
+    /// bigger than the remaining budget was sent
+    bandwidth_budget: f32,
+
     current_rtt: Duration,
     current_time: WrappedTime,
 }
 
+/// Maximum duration of unused bandwidth that a paced channel can accumulate
+const MAX_BANDWIDTH_BURST: Duration = Duration::from_millis(100);
+
 impl ReliableSender {
     pub fn new(reliable_settings: ReliableSettings) -> Self {
         Self {
@@ -67,10 +79,19 @@ impl ReliableSender {
             fragmented_messages_to_send: Default::default(),
             message_ids_to_send: Default::default(),
             fragment_sender: FragmentSender::new(),
+            ack_senders: Vec::new(),
+            bandwidth_cap: None,
+            bandwidth_budget: 0.0,
             current_rtt: Duration::default(),
             current_time: WrappedTime::default(),
         }
     }
+
+    /// Pace the messages so that the channel sends at most `bandwidth_cap` bytes per second
+    pub fn with_bandwidth_cap(mut self, bandwidth_cap: usize) -> Self {
+        self.bandwidth_cap = Some(bandwidth_cap);
+        self
+    }
 }
 
 // Stragegy:
@@ -86,6 +107,12 @@ impl ChannelSend for ReliableSender {
     fn update(&mut self, time_manager: &TimeManager, ping_manager: &PingManager, _: &TickManager) {
         self.current_time = time_manager.current_time();
         self.current_rtt = ping_manager.rtt();
+        if let Some(bandwidth_cap) = self.bandwidth_cap {
+            // the unused budget accumulates for at most 100ms, to limit bursts after an idle period
+            self.bandwidth_budget = (self.bandwidth_budget
+                + bandwidth_cap as f32 * time_manager.delta().as_secs_f32())
+            .min(bandwidth_cap as f32 * MAX_BANDWIDTH_BURST.as_secs_f32());
+        }
     }
 
     /// Add a new message to the buffer of messages to be sent.
@@ -162,8 +189,13 @@ impl ChannelSend for ReliableSender {
             }
         };
 
+        let paced = self.bandwidth_cap.is_some();
         // Iterate through all unacked messages, oldest message ids first
         for (message_id, unacked_message) in self.unacked_messages.iter_mut() {
+            // stop once a paced channel has exhausted its bandwidth budget
+            if paced && self.bandwidth_budget <= 0.0 {
+                break;
+            }
             match unacked_message {
                 UnackedMessage::Single {
                     bytes,
@@ -178,6 +210,7 @@ impl ChannelSend for ReliableSender {
                         };
                         if !self.message_ids_to_send.contains(&message_info) {
                             let message = SingleData::new(Some(*message_id), bytes.clone());
+                            self.bandwidth_budget -= bytes.len() as f32;
                             self.single_messages_to_send.push_back(message);
                             self.message_ids_to_send.insert(message_info);
                             *last_sent = Some(self.current_time);
@@ -186,22 +219,26 @@ impl ChannelSend for ReliableSender {
                 }
                 UnackedMessage::Fragmented(fragment_acks) => {
                     // only send the fragments that haven't been acked and should be resent
-                    fragment_acks
+                    for f in fragment_acks
                         .iter_mut()
                         .filter(|f| !f.acked && should_send(&f.last_sent))
-                        .for_each(|f| {
-                            // TODO: need a mechanism like message_ids_to_send? (message/fragmnet_id) to send?
-                            let message_info = MessageAck {
-                                message_id: *message_id,
-                                fragment_id: Some(f.data.fragment_id),
-                            };
-                            if !self.message_ids_to_send.contains(&message_info) {
-                                let message = f.data.clone();
-                                self.fragmented_messages_to_send.push_back(message);
-                                self.message_ids_to_send.insert(message_info);
-                                f.last_sent = Some(self.current_time);
-                            }
-                        })
+                    {
+                        if paced && self.bandwidth_budget <= 0.0 {
+                            break;
+                        }
+                        // TODO: need a mechanism like message_ids_to_send? (message/fragmnet_id) to send?
+                        let message_info = MessageAck {
+                            message_id: *message_id,
+                            fragment_id: Some(f.data.fragment_id),
+                        };
+                        if !self.message_ids_to_send.contains(&message_info) {
+                            let message = f.data.clone();
+                            self.bandwidth_budget -= message.bytes.len() as f32;
+                            self.fragmented_messages_to_send.push_back(message);
+                            self.message_ids_to_send.insert(message_info);
+                            f.last_sent = Some(self.current_time);
+                        }
+                    }
                 }
             }
         }
@@ -217,6 +254,7 @@ impl ChannelSend for ReliableSender {
                         )
                     }
                     self.unacked_messages.remove(&message_ack.message_id);
+                    notify_subscribers(&mut self.ack_senders, message_ack.message_id);
                 }
                 UnackedMessage::Fragmented(fragment_acks) => {
                     let Some(fragment_id) = message_ack.fragment_id else {
@@ -228,6 +266,7 @@ impl ChannelSend for ReliableSender {
                         // all fragments were acked
                         if fragment_acks.iter().all(|f| f.acked) {
                             self.unacked_messages.remove(&message_ack.message_id);
+                            notify_subscribers(&mut self.ack_senders, message_ack.message_id);
                         }
                     }
                 }
@@ -239,8 +278,24 @@ impl ChannelSend for ReliableSender {
         !self.single_messages_to_send.is_empty() || !self.fragmented_messages_to_send.is_empty()
     }
 
+    fn num_unacked_messages(&self) -> usize {
+        self.unacked_messages.len()
+    }
+
+    // the lost messages will be resent
+    fn notify_message_lost(&mut self, _: &MessageAck) {}
+
+    /// Create a new receiver that will receive a message id when a message is acked
+    /// (when all its fragments are acked for fragmented messages)
     fn subscribe_acks(&mut self) -> Receiver<MessageId> {
-        todo!()
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        self.ack_senders.push(sender);
+        receiver
+    }
+
+    /// Reliable messages are resent until they are acked, so they are never lost
+    fn subscribe_losses(&mut self) -> Receiver<MessageId> {
+        crossbeam_channel::never()
     }
 }
 
@@ -263,6 +318,7 @@ mod tests {
         });
         sender.current_rtt = Duration::from_millis(100);
         sender.current_time = WrappedTime::new(0);
+        let acks = sender.subscribe_acks();
 
         // Buffer a new message
         let message1 = Bytes::from("hello");
@@ -293,10 +349,34 @@ mod tests {
             fragment_id: None,
         });
         assert_eq!(sender.unacked_messages.len(), 0);
+        assert_eq!(acks.try_recv().unwrap(), MessageId(0));
 
         // Advance by a time that is above the resend threshold
         sender.current_time += Duration::from_millis(200);
         // this time there are no new messages to send
         assert_eq!(sender.single_messages_to_send.len(), 1);
     }
+
+    #[test]
+    fn test_reliable_sender_bandwidth_cap() {
+        let mut sender = ReliableSender::new(ReliableSettings::default()).with_bandwidth_cap(1000);
+        for _ in 0..10 {
+            sender.buffer_send(Bytes::from(vec![0; 100]));
+        }
+        // no budget yet
+        sender.collect_messages_to_send();
+        assert_eq!(sender.single_messages_to_send.len(), 0);
+
+        // 100ms of budget: 100 bytes, which lets us send a single message
+        sender.bandwidth_budget = 100.0;
+        sender.collect_messages_to_send();
+        assert_eq!(sender.single_messages_to_send.len(), 1);
+        assert_eq!(sender.bandwidth_budget, 0.0);
+
+        // a message can exceed the remaining budget, the excess is taken from the next budget
+        sender.bandwidth_budget = 150.0;
+        sender.collect_messages_to_send();
+        assert_eq!(sender.single_messages_to_send.len(), 3);
+        assert_eq!(sender.bandwidth_budget, -50.0);
+    }
 }
@@ -75,13 +75,24 @@ impl ChannelSend for UnorderedUnreliableSender {
 
     fn notify_message_delivered(&mut self, _: &MessageAck) {}
 
+    fn notify_message_lost(&mut self, _: &MessageAck) {}
+
     fn has_messages_to_send(&self) -> bool {
         !self.single_messages_to_send.is_empty() || !self.fragmented_messages_to_send.is_empty()
     }
 
+    // unreliable messages are not kept after they are sent
+    fn num_unacked_messages(&self) -> usize {
+        0
+    }
+
     fn subscribe_acks(&mut self) -> Receiver<MessageId> {
         unreachable!()
     }
+
+    fn subscribe_losses(&mut self) -> Receiver<MessageId> {
+        unreachable!()
+    }
 }
 
 #[cfg(test)]
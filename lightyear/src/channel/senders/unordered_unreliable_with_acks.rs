@@ -5,7 +5,7 @@ use crossbeam_channel::{Receiver, Sender};
 
 use crate::channel::senders::fragment_ack_receiver::FragmentAckReceiver;
 use crate::channel::senders::fragment_sender::FragmentSender;
-use crate::channel::senders::ChannelSend;
+use crate::channel::senders::{notify_subscribers, ChannelSend};
 use crate::packet::message::{FragmentData, MessageAck, MessageId, SingleData};
 use crate::shared::ping::manager::PingManager;
 use crate::shared::tick_manager::TickManager;
@@ -30,6 +30,8 @@ pub struct UnorderedUnreliableWithAcksSender {
     // TODO: use a crate to broadcast to all subscribers?
     /// List of senders that want to be notified when a message is acked
     ack_senders: Vec<Sender<MessageId>>,
+    /// List of senders that want to be notified when a message is lost
+    loss_senders: Vec<Sender<MessageId>>,
     /// Keep track of which fragments were acked, so we can know when the entire fragment message
     /// was acked
     fragment_ack_receiver: FragmentAckReceiver,
@@ -44,6 +46,7 @@ impl UnorderedUnreliableWithAcksSender {
             next_send_message_id: MessageId::default(),
             fragment_sender: FragmentSender::new(),
             ack_senders: Vec::new(),
+            loss_senders: Vec::new(),
             fragment_ack_receiver: FragmentAckReceiver::new(),
             current_time: WrappedTime::default(),
         }
@@ -53,8 +56,13 @@ impl UnorderedUnreliableWithAcksSender {
 impl ChannelSend for UnorderedUnreliableWithAcksSender {
     fn update(&mut self, time_manager: &TimeManager, _: &PingManager, _: &TickManager) {
         self.current_time = time_manager.current_time();
-        self.fragment_ack_receiver
-            .cleanup(self.current_time - DISCARD_AFTER);
+        // fragmented messages that we stopped waiting for are lost
+        for message_id in self
+            .fragment_ack_receiver
+            .cleanup(self.current_time - DISCARD_AFTER)
+        {
+            notify_subscribers(&mut self.loss_senders, message_id);
+        }
     }
 
     /// Add a new message to the buffer of messages to be sent.
@@ -95,36 +103,50 @@ impl ChannelSend for UnorderedUnreliableWithAcksSender {
 
     /// Notify any subscribers that a message was acked
     fn notify_message_delivered(&mut self, ack: &MessageAck) {
-        ack.fragment_id.map_or_else(
-            || {
-                for sender in &self.ack_senders {
-                    sender.send(ack.message_id).unwrap();
-                }
-            },
-            |fragment_index| {
-                if self.fragment_ack_receiver.receive_fragment_ack(
-                    ack.message_id,
-                    fragment_index,
-                    None,
-                ) {
-                    for sender in &self.ack_senders {
-                        sender.send(ack.message_id).unwrap();
-                    }
-                }
-            },
-        );
+        let delivered = ack.fragment_id.map_or(true, |fragment_index| {
+            self.fragment_ack_receiver.receive_fragment_ack(
+                ack.message_id,
+                fragment_index,
+                Some(self.current_time),
+            )
+        });
+        if delivered {
+            notify_subscribers(&mut self.ack_senders, ack.message_id);
+        }
+    }
+
+    /// Notify any subscribers that a message was lost.
+    /// A fragmented message is lost as soon as one of its fragments is lost
+    fn notify_message_lost(&mut self, ack: &MessageAck) {
+        if ack.fragment_id.is_some() && !self.fragment_ack_receiver.discard(ack.message_id) {
+            // we already notified the loss of another fragment of this message
+            return;
+        }
+        notify_subscribers(&mut self.loss_senders, ack.message_id);
     }
 
     fn has_messages_to_send(&self) -> bool {
         !self.single_messages_to_send.is_empty() || !self.fragmented_messages_to_send.is_empty()
     }
 
+    // unreliable messages are not kept after they are sent
+    fn num_unacked_messages(&self) -> usize {
+        0
+    }
+
     /// Create a new receiver that will receive a message id when a message is acked
     fn subscribe_acks(&mut self) -> Receiver<MessageId> {
         let (sender, receiver) = crossbeam_channel::unbounded();
         self.ack_senders.push(sender);
         receiver
     }
+
+    /// Create a new receiver that will receive a message id when a message is lost
+    fn subscribe_losses(&mut self) -> Receiver<MessageId> {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        self.loss_senders.push(sender);
+        receiver
+    }
 }
 
 #[cfg(test)]
@@ -172,4 +194,46 @@ mod tests {
         });
         assert_eq!(receiver.try_recv().unwrap(), message_id);
     }
+
+    #[test]
+    fn test_message_lost() {
+        let mut sender = UnorderedUnreliableWithAcksSender::new();
+        let receiver = sender.subscribe_losses();
+
+        let message_id = sender.buffer_send(Bytes::from("hello")).unwrap();
+        sender.notify_message_lost(&MessageAck {
+            message_id,
+            fragment_id: None,
+        });
+        assert_eq!(receiver.try_recv().unwrap(), message_id);
+
+        // the fragmented message is lost only once, even if several fragments are lost
+        const NUM_BYTES: usize = (FRAGMENT_SIZE as f32 * 2.5) as usize;
+        let message_id = sender.buffer_send(Bytes::from(vec![0; NUM_BYTES])).unwrap();
+        sender.notify_message_lost(&MessageAck {
+            message_id,
+            fragment_id: Some(0),
+        });
+        sender.notify_message_lost(&MessageAck {
+            message_id,
+            fragment_id: Some(2),
+        });
+        assert_eq!(receiver.try_iter().collect::<Vec<_>>(), vec![message_id]);
+    }
+
+    #[test]
+    fn test_dropped_subscriber() {
+        let mut sender = UnorderedUnreliableWithAcksSender::new();
+        drop(sender.subscribe_acks());
+        let receiver = sender.subscribe_acks();
+
+        // the subscriber that dropped its receiver is forgotten
+        let message_id = sender.buffer_send(Bytes::from("hello")).unwrap();
+        sender.notify_message_delivered(&MessageAck {
+            message_id,
+            fragment_id: None,
+        });
+        assert_eq!(receiver.try_recv().unwrap(), message_id);
+        assert_eq!(sender.ack_senders.len(), 1);
+    }
 }
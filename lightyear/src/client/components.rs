@@ -30,14 +30,14 @@ pub trait LerpFn<C> {
 }
 
 /// Defines how to do interpolation/correction for the component
-pub trait SyncMetadata<C> {
+pub trait SyncMetadata<C>: 'static {
     type Interpolator: LerpFn<C> + 'static;
     type Corrector: LerpFn<C> + 'static;
 
     fn mode() -> ComponentSyncMode;
 }
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
 /// Defines how a predicted or interpolated component will be replicated from confirmed to predicted/interpolated
 ///
 /// We use a single enum instead of 2 separate enums because we want to be able to use the same enum for both predicted and interpolated components
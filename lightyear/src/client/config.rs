@@ -7,6 +7,7 @@ use crate::client::input::InputConfig;
 use crate::client::interpolation::plugin::InterpolationConfig;
 use crate::client::prediction::plugin::PredictionConfig;
 use crate::client::sync::SyncConfig;
+use crate::packet::priority_manager::PriorityConfig;
 use crate::shared::config::SharedConfig;
 use crate::shared::ping::manager::PingConfig;
 
@@ -67,6 +68,7 @@ pub struct ClientConfig {
     pub netcode: NetcodeConfig,
     pub input: InputConfig,
     pub ping: PingConfig,
+    pub priority: PriorityConfig,
     pub sync: SyncConfig,
     pub prediction: PredictionConfig,
     pub interpolation: InterpolationConfig,
@@ -4,6 +4,7 @@ use std::time::Duration;
 use anyhow::Result;
 use bevy::ecs::component::Tick as BevyTick;
 use bevy::prelude::{Res, ResMut, Resource, World};
+use bytes::Bytes;
 use serde::Serialize;
 use tracing::{debug, info, trace, trace_span};
 
@@ -13,18 +14,23 @@ use crate::client::sync::SyncConfig;
 use crate::connection::events::ConnectionEvents;
 use crate::connection::message::{ClientMessage, ServerMessage};
 use crate::inputs::native::input_buffer::InputBuffer;
+use crate::packet::message::MessageHandle;
 use crate::packet::message_manager::MessageManager;
 use crate::packet::packet_manager::Payload;
+use crate::packet::priority_manager::PriorityConfig;
 use crate::prelude::{Channel, ChannelKind, MapEntities, Message, NetworkTarget};
 use crate::protocol::channel::ChannelRegistry;
+use crate::protocol::registry::ProtocolRegistry;
 use crate::protocol::Protocol;
-use crate::serialize::reader::ReadBuffer;
+use crate::serialize::wordbuffer::reader::ReadWordBuffer;
 use crate::shared::ping::manager::{PingConfig, PingManager};
 use crate::shared::ping::message::SyncMessage;
 use crate::shared::replication::receive::ReplicationReceiver;
 use crate::shared::replication::send::ReplicationSender;
 use crate::shared::replication::ReplicationMessage;
 use crate::shared::replication::ReplicationMessageData;
+use crate::shared::rpc::{check_request_channel, Request, RequestId, RpcManager};
+use crate::shared::stream::{StreamManager, TransferDirection, TransferId};
 use crate::shared::tick_manager::Tick;
 use crate::shared::tick_manager::TickManager;
 use crate::shared::time_manager::TimeManager;
@@ -42,6 +48,8 @@ pub struct ConnectionManager<P: Protocol> {
     pub(crate) ping_manager: PingManager,
     pub(crate) input_buffer: InputBuffer<P::Input>,
     pub(crate) sync_manager: SyncManager,
+    pub(crate) rpc: RpcManager<P::Message>,
+    pub(crate) stream: StreamManager,
     // TODO: maybe don't do any replication until connection is synced?
 }
 
@@ -89,10 +97,11 @@ impl<P: Protocol> ConnectionManager<P> {
         channel_registry: &ChannelRegistry,
         sync_config: SyncConfig,
         ping_config: &PingConfig,
+        priority_config: PriorityConfig,
         input_delay_ticks: u16,
     ) -> Self {
         // create the message manager and the channels
-        let mut message_manager = MessageManager::new(channel_registry);
+        let mut message_manager = MessageManager::new(channel_registry, priority_config);
         // get the acks-tracker for entity updates
         let update_acks_tracker = message_manager
             .channels
@@ -109,6 +118,8 @@ impl<P: Protocol> ConnectionManager<P> {
             ping_manager: PingManager::new(ping_config),
             input_buffer: InputBuffer::default(),
             sync_manager: SyncManager::new(sync_config, input_delay_ticks),
+            rpc: RpcManager::default(),
+            stream: StreamManager::default(),
             events: ConnectionEvents::default(),
         }
     }
@@ -146,13 +157,23 @@ impl<P: Protocol> ConnectionManager<P> {
         self.message_manager
             .update(time_manager, &self.ping_manager, tick_manager);
         self.ping_manager.update(time_manager);
+        for request_id in self.rpc.update(time_manager.delta()) {
+            self.events.push_request_timeout(request_id);
+        }
 
         // we update the sync manager in POST_UPDATE
         // self.sync_manager.update(time_manager);
     }
 
     /// Send a message to the server
-    pub fn send_message<C: Channel, M: Message>(&mut self, message: M) -> Result<()>
+    ///
+    /// Returns a [`MessageHandle`] if the channel tracks acks, which lets you match the message
+    /// with the [`MessageAckEvent`](crate::client::events::MessageAckEvent) or
+    /// [`MessageLostEvent`](crate::client::events::MessageLostEvent) emitted for it
+    pub fn send_message<C: Channel, M: Message>(
+        &mut self,
+        message: M,
+    ) -> Result<Option<MessageHandle>>
     where
         P::Message: From<M>,
     {
@@ -165,7 +186,7 @@ impl<P: Protocol> ConnectionManager<P> {
         &mut self,
         message: M,
         target: NetworkTarget,
-    ) -> Result<()>
+    ) -> Result<Option<MessageHandle>>
     where
         P::Message: From<M>,
     {
@@ -178,7 +199,7 @@ impl<P: Protocol> ConnectionManager<P> {
         message: P::Message,
         channel: ChannelKind,
         target: NetworkTarget,
-    ) -> Result<()> {
+    ) -> Result<Option<MessageHandle>> {
         // TODO: i know channel names never change so i should be able to get them as static
         // TODO: just have a channel registry enum as well?
         let channel_name = self
@@ -189,10 +210,77 @@ impl<P: Protocol> ConnectionManager<P> {
             .to_string();
         let message = ClientMessage::<P>::Message(message, target);
         message.emit_send_logs(&channel_name);
+        let message_id = self.message_manager.buffer_send(message, channel)?;
+        Ok(self.message_manager.track_delivery(channel, message_id))
+    }
+
+    /// Send a request to the server on the reliable channel `C`
+    ///
+    /// The response can be read with [`ClientRpc`](crate::client::rpc::ClientRpc), and a
+    /// [`RequestTimeoutEvent`](crate::client::events::RequestTimeoutEvent) is emitted if no response
+    /// of the right type is received within [`Request::TIMEOUT`]
+    pub fn send_request<C: Channel, R: Request>(&mut self, request: R) -> Result<RequestId>
+    where
+        P::Message: From<R>,
+    {
+        let channel = ChannelKind::of::<C>();
+        check_request_channel(&self.message_manager, channel)?;
+        let request_id = self.rpc.new_request::<R>();
+        self.buffer_rpc_message(
+            ClientMessage::<P>::Request(request_id, request.into()),
+            channel,
+        )?;
+        Ok(request_id)
+    }
+
+    /// Send the response to a request received from the server
+    pub(crate) fn send_response(
+        &mut self,
+        request_id: RequestId,
+        channel: ChannelKind,
+        response: P::Message,
+    ) -> Result<()> {
+        self.buffer_rpc_message(ClientMessage::<P>::Response(request_id, response), channel)
+    }
+
+    fn buffer_rpc_message(
+        &mut self,
+        message: ClientMessage<P>,
+        channel: ChannelKind,
+    ) -> Result<()> {
+        let channel_name = self
+            .message_manager
+            .channel_registry
+            .name(&channel)
+            .unwrap_or("unknown")
+            .to_string();
+        message.emit_send_logs(&channel_name);
         self.message_manager.buffer_send(message, channel)?;
         Ok(())
     }
 
+    /// Start streaming `bytes` to the server on the reliable channel `C`
+    ///
+    /// The payload is sent in chunks over several frames; use a [`ChannelMode::Streaming`](crate::prelude::ChannelMode::Streaming)
+    /// channel to cap the bandwidth used by the transfer. A [`TransferProgressEvent`](crate::client::events::TransferProgressEvent)
+    /// is emitted whenever the server acks some of the chunks
+    pub fn send_stream<C: Channel>(&mut self, bytes: impl Into<Bytes>) -> Result<TransferId> {
+        self.stream.send(
+            &mut self.message_manager,
+            ChannelKind::of::<C>(),
+            bytes.into(),
+        )
+    }
+
+    /// Cancel a stream that we are sending or receiving; the server is notified of the cancellation
+    pub fn cancel_stream(
+        &mut self,
+        transfer_id: TransferId,
+        direction: TransferDirection,
+    ) -> Result<()> {
+        self.stream.cancel(transfer_id, direction)
+    }
+
     pub fn buffer_replication_messages(&mut self, tick: Tick, bevy_tick: BevyTick) -> Result<()> {
         // NOTE: this doesn't work too well because then duplicate actions/updates are accumulated before the connection is synced
         // if !self.sync_manager.is_synced() {
@@ -202,8 +290,9 @@ impl<P: Protocol> ConnectionManager<P> {
         //     // self.replication_sender.pending_unique_components.clear();
         //     return Ok(());
         // }
+        let messages = self.replication_sender.finalize(tick);
         self.replication_sender
-            .finalize(tick)
+            .sort_by_priority(messages)
             .into_iter()
             .try_for_each(|(channel, group_id, message_data)| {
                 let should_track_ack = matches!(message_data, ReplicationMessageData::Updates(_));
@@ -217,18 +306,31 @@ impl<P: Protocol> ConnectionManager<P> {
                     group_id,
                     data: message_data,
                 });
+                let message_bytes = self.message_manager.encode(&message)?;
+                // entity actions are reliable and are always sent, but entity updates can be deferred
+                // if they don't fit in what is left of the bandwidth budget
+                let priority_manager = &mut self.message_manager.priority_manager;
+                if should_track_ack
+                    && priority_manager
+                        .available_bytes()
+                        .map_or(false, |bytes_left| message_bytes.len() > bytes_left)
+                {
+                    self.replication_sender.defer_update(group_id);
+                    return Ok(());
+                }
+                // the bytes are taken from the budget now, so that the message cannot be deferred later
+                priority_manager.reserve(channel, message_bytes.len());
                 trace!("Sending replication message: {:?}", message);
                 message.emit_send_logs(&channel_name);
                 let message_id = self
                     .message_manager
-                    .buffer_send(message, channel)?
+                    .buffer_send_bytes(message_bytes, channel)?
                     .expect("The EntityUpdatesChannel should always return a message_id");
 
                 // keep track of the group associated with the message, so we can handle receiving an ACK for that message_id later
                 if should_track_ack {
                     self.replication_sender
-                        .updates_message_id_to_group_id
-                        .insert(message_id, (group_id, bevy_tick));
+                        .track_update_message(message_id, group_id, bevy_tick);
                 }
                 Ok(())
             })
@@ -271,7 +373,26 @@ impl<P: Protocol> ConnectionManager<P> {
                     Ok::<(), anyhow::Error>(())
                 })?;
         }
-        self.message_manager.send_packets(tick_manager.tick())
+        // buffer the next chunks of the streams
+        self.stream.send_chunks(|channel, message| {
+            let message = ClientMessage::<P>::Stream(message);
+            let channel_name = self
+                .message_manager
+                .channel_registry
+                .name(&channel)
+                .unwrap_or("unknown");
+            message.emit_send_logs(channel_name);
+            self.message_manager.buffer_send(message, channel)
+        })?;
+        let payloads = self.message_manager.send_packets(tick_manager.tick())?;
+        // the groups whose updates were written into a packet stop accumulating priority
+        for message_id in self
+            .message_manager
+            .sent_message_ids(&ChannelKind::of::<EntityUpdatesChannel>())
+        {
+            self.replication_sender.reset_update_priority(*message_id);
+        }
+        Ok(payloads)
     }
 
     pub fn receive(
@@ -281,7 +402,15 @@ impl<P: Protocol> ConnectionManager<P> {
         tick_manager: &TickManager,
     ) -> ConnectionEvents<P> {
         let _span = trace_span!("receive").entered();
-        for (channel_kind, messages) in self.message_manager.read_messages::<ServerMessage<P>>() {
+        // the registry is needed to decode the registered messages and components
+        let empty_registry = ProtocolRegistry::default();
+        let registry = world
+            .get_resource::<ProtocolRegistry>()
+            .unwrap_or(&empty_registry);
+        let received = self
+            .message_manager
+            .read_messages::<ServerMessage<P>>(registry);
+        for (channel_kind, messages) in received {
             let channel_name = self
                 .message_manager
                 .channel_registry
@@ -309,6 +438,23 @@ impl<P: Protocol> ConnectionManager<P> {
                             // buffer the message
                             self.events.push_message(channel_kind, message);
                         }
+                        ServerMessage::Request(request_id, mut request) => {
+                            request.map_entities(Box::new(
+                                &self.replication_receiver.remote_entity_map,
+                            ));
+                            self.rpc.recv_request(request_id, channel_kind, request);
+                        }
+                        ServerMessage::Response(request_id, mut response) => {
+                            response.map_entities(Box::new(
+                                &self.replication_receiver.remote_entity_map,
+                            ));
+                            self.rpc.recv_response(request_id, response);
+                        }
+                        ServerMessage::Stream(message) => {
+                            let settings =
+                                StreamManager::settings(&self.message_manager, channel_kind);
+                            self.stream.recv_message(&settings, channel_kind, message);
+                        }
                         ServerMessage::Replication(replication) => {
                             // buffer the replication message
                             self.replication_receiver.recv_message(replication, tick);
@@ -365,6 +511,25 @@ impl<P: Protocol> ConnectionManager<P> {
             }
         }
 
+        // Report the delivery of the messages we sent
+        let (acked, lost) = self.message_manager.drain_delivery_notifications();
+        acked
+            .into_iter()
+            .for_each(|handle| self.events.push_message_ack(handle));
+        lost.into_iter()
+            .for_each(|handle| self.events.push_message_lost(handle));
+
+        // Report the progress of the streams
+        self.stream.process_acks();
+        self.stream
+            .drain_progress()
+            .into_iter()
+            .for_each(|progress| self.events.push_transfer_progress(progress));
+        self.stream
+            .drain_received()
+            .into_iter()
+            .for_each(|(transfer_id, bytes)| self.events.push_stream_received(transfer_id, bytes));
+
         // TODO: do i really need this? I could just create events in this function directly?
         //  why do i need to make events a field of the connection?
         //  is it because of push_connection?
@@ -373,7 +538,7 @@ impl<P: Protocol> ConnectionManager<P> {
 
     pub fn recv_packet(
         &mut self,
-        reader: &mut impl ReadBuffer,
+        reader: &mut ReadWordBuffer,
         tick_manager: &TickManager,
     ) -> Result<()> {
         self.replication_sender.recv_update_acks();
@@ -12,3 +12,8 @@ pub type ComponentUpdateEvent<C> = crate::shared::events::ComponentUpdateEvent<C
 pub type ComponentInsertEvent<C> = crate::shared::events::ComponentInsertEvent<C, ()>;
 pub type ComponentRemoveEvent<C> = crate::shared::events::ComponentRemoveEvent<C, ()>;
 pub type MessageEvent<M> = crate::shared::events::MessageEvent<M, ()>;
+pub type MessageAckEvent = crate::shared::events::MessageAckEvent<()>;
+pub type MessageLostEvent = crate::shared::events::MessageLostEvent<()>;
+pub type RequestTimeoutEvent = crate::shared::events::RequestTimeoutEvent<()>;
+pub type TransferProgressEvent = crate::shared::events::TransferProgressEvent<()>;
+pub type StreamReceivedEvent = crate::shared::events::StreamReceivedEvent<()>;
@@ -14,6 +14,7 @@ use crate::client::prediction::{Rollback, RollbackState};
 use crate::client::resource::Client;
 use crate::client::sync::client_is_synced;
 use crate::inputs::native::UserAction;
+use crate::inputs::InterpolationInfo;
 use crate::prelude::TickManager;
 use crate::protocol::Protocol;
 use crate::shared::sets::{FixedUpdateSet, MainSet};
@@ -26,12 +27,19 @@ pub struct InputConfig {
     /// For instance, a value of 3 means that each input packet will contain the inputs for all the ticks
     ///  for the 3 last packets.
     pub(crate) packet_redundancy: u16,
+    /// If true, the input messages will include the client's interpolation tick and overstep,
+    /// so that the server can know which state of the world the client was seeing when generating the inputs.
+    /// This is required for lag compensation.
+    ///
+    /// Disabled by default, since it makes every input message larger.
+    pub send_interpolation_info: bool,
 }
 
 impl Default for InputConfig {
     fn default() -> Self {
         InputConfig {
             packet_redundancy: 10,
+            send_interpolation_info: false,
         }
     }
 }
@@ -220,26 +228,33 @@ fn prepare_input_message<P: Protocol>(
     //  - buffer an input every frame; and require some redundancy (number of tick per frame)
     //  - or buffer an input only when we are sending, and require more redundancy
     // let message_len = 20 as u16;
-    let message = connection
+    let interpolation_tick = connection.sync_manager.interpolation_tick(&tick_manager);
+    let mut message = connection
         .input_buffer
         .create_message(tick_manager.tick(), message_len);
+    // let the server know which state of the world the client was seeing, so that it can perform lag compensation
+    if config.input.send_interpolation_info {
+        message.interpolation = Some(InterpolationInfo {
+            tick: interpolation_tick,
+            overstep: connection
+                .sync_manager
+                .interpolation_overstep(&tick_manager),
+        });
+    }
     // all inputs are absent
     if !message.is_empty() {
         // TODO: should we provide variants of each user-facing function, so that it pushes the error
         //  to the ConnectionEvents?
         debug!("sending input message: {:?}", message.end_tick);
-        connection
-            .send_message::<InputChannel, _>(message)
-            .unwrap_or_else(|err| {
-                error!("Error while sending input message: {:?}", err);
-            })
+        if let Err(err) = connection.send_message::<InputChannel, _>(message) {
+            error!("Error while sending input message: {:?}", err);
+        }
     }
     // NOTE: actually we keep the input values! because they might be needed when we rollback for client prediction
     // TODO: figure out when we can delete old inputs. Basically when the oldest prediction group tick has passed?
     //  maybe at interpolation_tick(), since it's before any latest server update we receive?
 
     // delete old input values
-    let interpolation_tick = connection.sync_manager.interpolation_tick(&tick_manager);
     connection.input_buffer.pop(interpolation_tick);
     // .pop(current_tick - (message_len + 1));
 }
@@ -19,6 +19,7 @@ use crate::inputs::leafwing::input_buffer::{
     ActionDiff, ActionDiffBuffer, ActionDiffEvent, InputBuffer, InputMessage, InputTarget,
 };
 use crate::inputs::leafwing::LeafwingUserAction;
+use crate::inputs::InterpolationInfo;
 use crate::prelude::{MapEntities, TickManager};
 use crate::protocol::Protocol;
 use crate::shared::replication::components::PrePredicted;
@@ -501,6 +502,15 @@ fn prepare_input_message<P: Protocol, A: LeafwingUserAction>(
     // delete old input values
     // anything beyond interpolation tick should be safe to be deleted
     let interpolation_tick = connection.sync_manager.interpolation_tick(&tick_manager);
+    // let the server know which state of the world the client was seeing, so that it can perform lag compensation
+    if config.input.send_interpolation_info {
+        message.interpolation = Some(InterpolationInfo {
+            tick: interpolation_tick,
+            overstep: connection
+                .sync_manager
+                .interpolation_overstep(&tick_manager),
+        });
+    }
     trace!(
         "popping all inputs since interpolation tick: {:?}",
         interpolation_tick
@@ -595,11 +605,9 @@ fn prepare_input_message<P: Protocol, A: LeafwingUserAction>(
             "sending input message: {:?}",
             message.diffs
         );
-        connection
-            .send_message::<InputChannel, InputMessage<A>>(message)
-            .unwrap_or_else(|err| {
-                error!("Error while sending input message: {:?}", err);
-            })
+        if let Err(err) = connection.send_message::<InputChannel, InputMessage<A>>(message) {
+            error!("Error while sending input message: {:?}", err);
+        }
     }
 
     // NOTE: actually we keep the input values! because they might be needed when we rollback for client prediction
@@ -2,14 +2,12 @@ use bevy::ecs::system::EntityCommands;
 use bevy::prelude::{Commands, Component, Entity, Mut, Query, Res, ResMut};
 use tracing::{info, trace};
 
-use crate::_reexport::ComponentProtocol;
-use crate::client::components::{ComponentSyncMode, SyncComponent, SyncMetadata};
+use crate::client::components::{ComponentSyncMode, LerpFn, SyncComponent, SyncMetadata};
 use crate::client::config::ClientConfig;
-use crate::client::connection::ConnectionManager;
 use crate::client::interpolation::interpolation_history::ConfirmedHistory;
 use crate::client::resource::Client;
+use crate::client::sync::SyncStatus;
 use crate::prelude::TickManager;
-use crate::protocol::Protocol;
 use crate::shared::tick_manager::Tick;
 
 // if we haven't received updates since UPDATE_INTERPOLATION_START_TICK_FACTOR * send_interval
@@ -34,9 +32,9 @@ pub struct InterpolateStatus<C: Component> {
 
 /// At the end of each frame, interpolate the components between the last 2 confirmed server states
 /// Invariant: start_tick <= current_interpolate_tick <= end_tick
-pub(crate) fn update_interpolate_status<C: SyncComponent, P: Protocol>(
+pub(crate) fn update_interpolate_status<C: SyncComponent>(
     config: Res<ClientConfig>,
-    connection: Res<ConnectionManager<P>>,
+    sync_status: Res<SyncStatus>,
     tick_manager: Res<TickManager>,
     mut query: Query<(
         Entity,
@@ -44,11 +42,9 @@ pub(crate) fn update_interpolate_status<C: SyncComponent, P: Protocol>(
         &mut InterpolateStatus<C>,
         &mut ConfirmedHistory<C>,
     )>,
-) where
-    P::Components: SyncMetadata<C>,
-{
+) {
     let kind = C::type_name();
-    if !connection.is_synced() {
+    if !sync_status.synced {
         return;
     }
 
@@ -58,9 +54,7 @@ pub(crate) fn update_interpolate_status<C: SyncComponent, P: Protocol>(
         / config.shared.tick.tick_duration.as_secs_f32()) as i16
         + 1;
 
-    let current_interpolate_tick = connection
-        .sync_manager
-        .interpolation_tick(tick_manager.as_ref());
+    let current_interpolate_tick = sync_status.interpolation_tick;
     for (entity, component, mut status, mut history) in query.iter_mut() {
         let mut start = status.start.take();
         let mut end = status.end.take();
@@ -169,7 +163,7 @@ pub(crate) fn update_interpolate_status<C: SyncComponent, P: Protocol>(
             ?entity,
             component = ?kind,
             ?current_interpolate_tick,
-            last_received_server_tick = ?connection.latest_received_server_tick(),
+            last_received_server_tick = ?sync_status.latest_received_server_tick,
             start_tick = ?start.as_ref().map(|(tick, _)| tick),
             end_tick = ?end.as_ref().map(|(tick, _) | tick),
             "update_interpolate_status");
@@ -185,12 +179,10 @@ pub(crate) fn update_interpolate_status<C: SyncComponent, P: Protocol>(
     }
 }
 
-pub(crate) fn interpolate<C: Component + Clone, P: Protocol>(
+pub(crate) fn interpolate<C: Component + Clone, M: SyncMetadata<C>>(
     mut commands: Commands,
     mut query: Query<(Entity, Option<&mut C>, &InterpolateStatus<C>)>,
-) where
-    P::Components: SyncMetadata<C>,
-{
+) {
     let set_value = |mut commands: EntityCommands, component: Option<Mut<C>>, value: C| {
         if let Some(mut component) = component {
             *component = value;
@@ -217,7 +209,7 @@ pub(crate) fn interpolate<C: Component + Clone, P: Protocol>(
                 if start_tick != end_tick {
                     let t =
                         (status.current - *start_tick) as f32 / (*end_tick - *start_tick) as f32;
-                    let value = P::Components::lerp(start_value.clone(), end_value.clone(), t);
+                    let value = M::Interpolator::lerp(start_value.clone(), end_value.clone(), t);
                     set_value(entity_commands, component, value);
                 } else {
                     set_value(entity_commands, component, start_value.clone());
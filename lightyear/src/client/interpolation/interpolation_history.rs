@@ -7,13 +7,12 @@ use tracing::{debug, error, info, trace};
 
 use crate::client::components::{ComponentSyncMode, SyncComponent};
 use crate::client::components::{Confirmed, SyncMetadata};
-use crate::client::connection::ConnectionManager;
 use crate::client::interpolation::interpolate::InterpolateStatus;
 use crate::client::interpolation::resource::InterpolationManager;
 use crate::client::interpolation::Interpolated;
 use crate::client::resource::Client;
+use crate::client::sync::SyncStatus;
 use crate::prelude::TickManager;
-use crate::protocol::Protocol;
 use crate::shared::tick_manager::Tick;
 use crate::utils::ready_buffer::ReadyBuffer;
 
@@ -77,16 +76,14 @@ impl<T: SyncComponent> ConfirmedHistory<T> {
 }
 
 // TODO: maybe add the component history on the Confirmed entity instead of Interpolated? would make more sense maybe
-pub(crate) fn add_component_history<C: SyncComponent, P: Protocol>(
+pub(crate) fn add_component_history<C: SyncComponent, M: SyncMetadata<C>>(
     manager: Res<InterpolationManager>,
     tick_manager: Res<TickManager>,
     mut commands: Commands,
-    connection: Res<ConnectionManager<P>>,
+    sync_status: Res<SyncStatus>,
     interpolated_entities: Query<Entity, (Without<ConfirmedHistory<C>>, With<Interpolated>)>,
     confirmed_entities: Query<(&Confirmed, Ref<C>)>,
-) where
-    P::Components: SyncMetadata<C>,
-{
+) {
     for (confirmed_entity, confirmed_component) in confirmed_entities.iter() {
         if let Some(p) = confirmed_entity.interpolated {
             if let Ok(interpolated_entity) = interpolated_entities.get(p) {
@@ -99,7 +96,7 @@ pub(crate) fn add_component_history<C: SyncComponent, P: Protocol>(
                     // map any entities from confirmed to interpolated
                     let mut new_component = confirmed_component.deref().clone();
                     new_component.map_entities(Box::new(&manager.interpolated_entity_map));
-                    match P::Components::mode() {
+                    match M::mode() {
                         ComponentSyncMode::Full => {
                             trace!(?interpolated_entity, tick=?tick_manager.tick(),  "spawn interpolation history");
                             interpolated_entity_mut.insert((
@@ -110,9 +107,7 @@ pub(crate) fn add_component_history<C: SyncComponent, P: Protocol>(
                                 InterpolateStatus::<C> {
                                     start: None,
                                     end: None,
-                                    current: connection
-                                        .sync_manager
-                                        .interpolation_tick(tick_manager.as_ref()),
+                                    current: sync_status.interpolation_tick,
                                 },
                             ));
                         }
@@ -129,16 +124,14 @@ pub(crate) fn add_component_history<C: SyncComponent, P: Protocol>(
 }
 
 /// When we receive a server update for an interpolated component, we need to store it in the confirmed history,
-pub(crate) fn apply_confirmed_update_mode_full<C: SyncComponent, P: Protocol>(
+pub(crate) fn apply_confirmed_update_mode_full<C: SyncComponent>(
     manager: ResMut<InterpolationManager>,
     mut interpolated_entities: Query<
         &mut ConfirmedHistory<C>,
         (With<Interpolated>, Without<Confirmed>),
     >,
     confirmed_entities: Query<(Entity, &Confirmed, Ref<C>)>,
-) where
-    P::Components: SyncMetadata<C>,
-{
+) {
     for (confirmed_entity, confirmed, confirmed_component) in confirmed_entities.iter() {
         if let Some(p) = confirmed.interpolated {
             if confirmed_component.is_changed() && !confirmed_component.is_added() {
@@ -170,13 +163,11 @@ pub(crate) fn apply_confirmed_update_mode_full<C: SyncComponent, P: Protocol>(
 }
 
 /// When we receive a server update for a simple component, we just update the entity directly
-pub(crate) fn apply_confirmed_update_mode_simple<C: SyncComponent, P: Protocol>(
+pub(crate) fn apply_confirmed_update_mode_simple<C: SyncComponent>(
     manager: ResMut<InterpolationManager>,
     mut interpolated_entities: Query<&mut C, (With<Interpolated>, Without<Confirmed>)>,
     confirmed_entities: Query<(Entity, &Confirmed, Ref<C>)>,
-) where
-    P::Components: SyncMetadata<C>,
-{
+) {
     for (confirmed_entity, confirmed, confirmed_component) in confirmed_entities.iter() {
         if let Some(p) = confirmed.interpolated {
             if confirmed_component.is_changed() && !confirmed_component.is_added() {
@@ -11,6 +11,7 @@ use crate::client::interpolation::despawn::{despawn_interpolated, removed_compon
 use crate::client::interpolation::interpolate::{interpolate, update_interpolate_status};
 use crate::client::interpolation::resource::InterpolationManager;
 use crate::protocol::component::ComponentProtocol;
+use crate::protocol::registry::ProtocolRegistry;
 use crate::protocol::Protocol;
 
 use super::interpolation_history::{
@@ -149,21 +150,28 @@ pub fn add_prepare_interpolation_systems<C: SyncComponent, P: Protocol>(app: &mu
 where
     P::Components: SyncMetadata<C>,
 {
+    add_component_prepare_interpolation_systems::<C, P::Components>(app);
+}
+
+/// Add the systems that prepare the interpolation of the component `C`, whose sync mode is defined by `M`
+pub(crate) fn add_component_prepare_interpolation_systems<C: SyncComponent, M: SyncMetadata<C>>(
+    app: &mut App,
+) {
     // TODO: maybe create an overarching prediction set that contains all others?
     app.add_systems(
         Update,
         (
-            add_component_history::<C, P>.in_set(InterpolationSet::SpawnHistory),
+            add_component_history::<C, M>.in_set(InterpolationSet::SpawnHistory),
             removed_components::<C>.in_set(InterpolationSet::Despawn),
         ),
     );
-    match P::Components::mode() {
+    match M::mode() {
         ComponentSyncMode::Full => {
             app.add_systems(
                 Update,
                 (
-                    apply_confirmed_update_mode_full::<C, P>,
-                    update_interpolate_status::<C, P>,
+                    apply_confirmed_update_mode_full::<C>,
+                    update_interpolate_status::<C>,
                 )
                     .chain()
                     .in_set(InterpolationSet::PrepareInterpolation),
@@ -172,7 +180,7 @@ where
         ComponentSyncMode::Simple => {
             app.add_systems(
                 Update,
-                apply_confirmed_update_mode_simple::<C, P>
+                apply_confirmed_update_mode_simple::<C>
                     .in_set(InterpolationSet::PrepareInterpolation),
             );
         }
@@ -186,9 +194,16 @@ pub fn add_interpolation_systems<C: Component + Clone, P: Protocol>(app: &mut Ap
 where
     P::Components: SyncMetadata<C>,
 {
+    add_component_interpolation_systems::<C, P::Components>(app);
+}
+
+/// Add the system that interpolates the component `C`, whose interpolation function is defined by `M`
+pub(crate) fn add_component_interpolation_systems<C: Component + Clone, M: SyncMetadata<C>>(
+    app: &mut App,
+) {
     app.add_systems(
         Update,
-        interpolate::<C, P>.in_set(InterpolationSet::Interpolate),
+        interpolate::<C, M>.in_set(InterpolationSet::Interpolate),
     );
 }
 
@@ -236,4 +251,19 @@ impl<P: Protocol> Plugin for InterpolationPlugin<P> {
             ),
         );
     }
+
+    fn finish(&self, app: &mut App) {
+        // components can be registered after the plugin is built, so their systems are added when the app is finished
+        let registrations = app
+            .world
+            .get_resource::<ProtocolRegistry>()
+            .map(ProtocolRegistry::full_sync_registrations)
+            .unwrap_or_default();
+        for registration in registrations {
+            (registration.add_prepare_interpolation_systems)(app);
+            if !self.config.custom_interpolation_logic {
+                (registration.add_interpolation_systems)(app);
+            }
+        }
+    }
 }
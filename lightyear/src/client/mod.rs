@@ -19,6 +19,8 @@ pub mod prediction;
 
 pub mod resource;
 
+pub mod rpc;
+
 pub mod sync;
 
 mod diagnostics;
@@ -10,17 +10,28 @@ use bevy::time::common_conditions::on_timer;
 use bevy::transform::TransformSystem;
 use bevy::utils::Duration;
 
-use crate::client::events::{ConnectEvent, DisconnectEvent, EntityDespawnEvent, EntitySpawnEvent};
+use crate::client::events::{
+    ConnectEvent, DisconnectEvent, EntityDespawnEvent, EntitySpawnEvent, MessageAckEvent,
+    MessageLostEvent, RequestTimeoutEvent, StreamReceivedEvent, TransferProgressEvent,
+};
 use crate::client::input::InputPlugin;
-use crate::client::interpolation::plugin::InterpolationPlugin;
-use crate::client::prediction::plugin::{is_connected, is_in_rollback, PredictionPlugin};
+use crate::client::interpolation::plugin::{InterpolationPlugin, InterpolationSet};
+use crate::client::prediction::plugin::{
+    is_connected, is_in_rollback, PredictionPlugin, PredictionSet,
+};
 use crate::client::prediction::Rollback;
-use crate::client::resource::{Authentication, Client};
-use crate::client::systems::{receive, send, sync_update};
+use crate::client::resource::{Authentication, Client, PendingToken};
+use crate::client::sync::SyncStatus;
+use crate::client::systems::{
+    poll_token_request, receive, send, sync_registered_interpolated_components,
+    sync_registered_predicted_components, sync_update, update_sync_status,
+};
 use crate::connection::events::ConnectionEvents;
+use crate::netcode::{generate_key, ConnectToken, TokenRequest};
 use crate::prelude::{ReplicationSet, ShouldBePredicted, TimeManager};
 use crate::protocol::component::ComponentProtocol;
 use crate::protocol::message::MessageProtocol;
+use crate::protocol::registry::ProtocolRegistry;
 use crate::protocol::Protocol;
 use crate::shared::plugin::SharedPlugin;
 use crate::shared::replication::systems::add_replication_send_systems;
@@ -66,14 +77,33 @@ impl<P: Protocol> Plugin for ClientPlugin<P> {
     fn build(&self, app: &mut App) {
         let config = self.config.lock().unwrap().deref_mut().take().unwrap();
 
-        let token = config
-            .auth
-            .get_token(config.client_config.netcode.client_timeout_secs)
-            .expect("could not generate token");
+        let (token, pending_token) = match config.auth {
+            // the token is fetched in the background: use a placeholder token until it is received
+            Authentication::Remote {
+                auth_addr,
+                credentials,
+            } => (
+                ConnectToken::build(auth_addr, 0, 0, generate_key())
+                    .generate()
+                    .ok(),
+                PendingToken::new(TokenRequest::start(auth_addr, credentials)),
+            ),
+            auth => (
+                auth.get_token(config.client_config.netcode.client_timeout_secs),
+                PendingToken::default(),
+            ),
+        };
+        let token = token.expect("could not generate token");
         let token_bytes = token.try_into_bytes().unwrap();
-        let netcode =
-            crate::netcode::Client::with_config(&token_bytes, config.client_config.netcode.build())
-                .expect("could not create netcode client");
+        let netcode = crate::netcode::Client::with_config(
+            &token_bytes,
+            config
+                .client_config
+                .netcode
+                .build()
+                .protocol_fingerprint(config.protocol.fingerprint()),
+        )
+        .expect("could not create netcode client");
         let fixed_timestep = config.client_config.shared.tick.tick_duration;
         let clean_interval = fixed_timestep * (i16::MAX as u32 / 3);
 
@@ -105,14 +135,18 @@ impl<P: Protocol> Plugin for ClientPlugin<P> {
             .insert_resource(config.client_config.clone())
             .insert_resource(config.io)
             .insert_resource(netcode)
+            .insert_resource(pending_token)
             .insert_resource(ConnectionManager::<P>::new(
                 config.protocol.channel_registry(),
                 config.client_config.sync,
                 &config.client_config.ping,
+                config.client_config.priority.clone(),
                 config.client_config.prediction.input_delay_ticks,
             ))
             .insert_resource(ConnectionEvents::<P>::new())
             .insert_resource(config.protocol)
+            .init_resource::<ProtocolRegistry>()
+            .init_resource::<SyncStatus>()
             // SYSTEM SETS //
             .configure_sets(PreUpdate, (MainSet::Receive, MainSet::ReceiveFlush).chain())
             .configure_sets(
@@ -166,11 +200,19 @@ impl<P: Protocol> Plugin for ClientPlugin<P> {
             .add_event::<DisconnectEvent>()
             .add_event::<EntitySpawnEvent>()
             .add_event::<EntityDespawnEvent>()
+            .add_event::<MessageAckEvent>()
+            .add_event::<MessageLostEvent>()
+            .add_event::<RequestTimeoutEvent>()
+            .add_event::<TransferProgressEvent>()
+            .add_event::<StreamReceivedEvent>()
             // SYSTEMS //
             .add_systems(
                 PreUpdate,
                 (
-                    receive::<P>.in_set(MainSet::Receive),
+                    poll_token_request::<P>.before(MainSet::Receive),
+                    (receive::<P>, update_sync_status::<P>)
+                        .chain()
+                        .in_set(MainSet::Receive),
                     apply_deferred.in_set(MainSet::ReceiveFlush),
                 ),
             )
@@ -182,9 +224,32 @@ impl<P: Protocol> Plugin for ClientPlugin<P> {
                     sync_update::<P>.in_set(MainSet::Sync),
                 ),
             )
+            .add_systems(
+                PreUpdate,
+                sync_registered_predicted_components.in_set(PredictionSet::SpawnHistory),
+            )
+            .add_systems(
+                Update,
+                sync_registered_interpolated_components.in_set(InterpolationSet::SpawnHistory),
+            )
             .add_systems(
                 Last,
                 replication_clean::<P>.run_if(on_timer(clean_interval)),
             );
     }
+
+    fn finish(&self, app: &mut App) {
+        // the messages and components registered at runtime are part of the protocol
+        let netcode_fingerprint = app
+            .world
+            .resource::<crate::netcode::Client>()
+            .protocol_fingerprint();
+        let fingerprint = app
+            .world
+            .resource::<ProtocolRegistry>()
+            .fingerprint(netcode_fingerprint);
+        app.world
+            .resource_mut::<crate::netcode::Client>()
+            .set_protocol_fingerprint(fingerprint);
+    }
 }
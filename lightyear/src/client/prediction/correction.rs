@@ -6,15 +6,15 @@
 // - interpolate (provided)
 // - custom
 
+use std::any::TypeId;
+
 use bevy::prelude::{Commands, Component, Entity, Query, Res};
 use tracing::{debug, info};
 
-use crate::_reexport::ComponentProtocol;
 use crate::client::components::{LerpFn, SyncComponent, SyncMetadata};
 use crate::client::easings::{ease_out_quad, ease_out_quart};
 use crate::client::resource::Client;
 use crate::prelude::{Tick, TickManager};
-use crate::protocol::Protocol;
 
 // TODO: instead of requiring the component to implement the correction, we could have a separate
 //  'type registry' that stores the correction function for each component type.
@@ -44,6 +44,11 @@ impl<C: Component> LerpFn<C> for InstantCorrector {
     // }
 }
 
+/// Returns false if the component is corrected instantly
+pub(crate) fn has_correction<C, M: SyncMetadata<C>>() -> bool {
+    TypeId::of::<M::Corrector>() != TypeId::of::<InstantCorrector>()
+}
+
 /// We use the components interpolation behaviour to interpolate from the Predicted state to the
 /// Corrected state
 pub struct InterpolatedCorrector;
@@ -83,13 +88,11 @@ pub struct Correction<C: Component> {
 
 /// Visually update the component to the a value that is interpolated between the original prediction
 /// and the Corrected state
-pub(crate) fn get_visually_corrected_state<C: SyncComponent, P: Protocol>(
+pub(crate) fn get_visually_corrected_state<C: SyncComponent, M: SyncMetadata<C>>(
     tick_manager: Res<TickManager>,
     mut commands: Commands,
     mut query: Query<(Entity, &mut C, &mut Correction<C>)>,
-) where
-    P::Components: SyncMetadata<C>,
-{
+) {
     for (entity, mut component, mut correction) in query.iter_mut() {
         let current_tick = tick_manager.tick();
         let mut t = (current_tick - correction.original_tick) as f32
@@ -110,11 +113,8 @@ pub(crate) fn get_visually_corrected_state<C: SyncComponent, P: Protocol>(
             correction.current_correction = Some(component.clone());
             // TODO: avoid all these clones
             // visually update the component
-            let visual = P::Components::correct(
-                correction.original_prediction.clone(),
-                component.clone(),
-                t,
-            );
+            let visual =
+                M::Corrector::lerp(correction.original_prediction.clone(), component.clone(), t);
             // store the current visual value
             correction.current_visual = Some(visual.clone());
             // set the component value to the visual value
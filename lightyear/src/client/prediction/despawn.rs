@@ -109,14 +109,12 @@ pub struct RemovedCache<C: Component>(pub Option<C>);
 
 #[allow(clippy::type_complexity)]
 /// Instead of despawning the entity, we remove all components except the history and the predicted marker
-pub(crate) fn remove_component_for_despawn_predicted<C: SyncComponent, P: Protocol>(
+pub(crate) fn remove_component_for_despawn_predicted<C: SyncComponent, M: SyncMetadata<C>>(
     mut commands: Commands,
     full_query: Query<Entity, (With<C>, With<PredictionDespawnMarker>)>,
     simple_query: Query<(Entity, &C), With<PredictionDespawnMarker>>,
-) where
-    P::Components: SyncMetadata<C>,
-{
-    match P::Components::mode() {
+) {
+    match M::mode() {
         // for full components, we can delete the component
         // it will get re-instated during rollback if the confirmed entity doesn't get despawned
         ComponentSyncMode::Full => {
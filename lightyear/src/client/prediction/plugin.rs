@@ -25,6 +25,7 @@ use crate::client::prediction::resource::PredictionManager;
 use crate::client::resource::Client;
 use crate::prelude::ReplicationSet;
 use crate::protocol::component::ComponentProtocol;
+use crate::protocol::registry::ProtocolRegistry;
 use crate::protocol::Protocol;
 use crate::shared::sets::{FixedUpdateSet, MainSet};
 
@@ -162,15 +163,22 @@ where
     P::ComponentKinds: FromType<C>,
     P::Components: SyncMetadata<C>,
 {
+    add_component_prediction_systems::<C, P::Components>(app);
+}
+
+/// Add the prediction systems of the component `C`, whose sync mode and correction are defined by `M`
+pub(crate) fn add_component_prediction_systems<C: SyncComponent, M: SyncMetadata<C>>(
+    app: &mut App,
+) {
     // TODO: maybe create an overarching prediction set that contains all others?
     app.add_systems(
         PreUpdate,
         (
             // handle components being added
-            add_component_history::<C, P>.in_set(PredictionSet::SpawnHistory),
+            add_component_history::<C, M>.in_set(PredictionSet::SpawnHistory),
         ),
     );
-    match P::Components::mode() {
+    match M::mode() {
         ComponentSyncMode::Full => {
             app.add_systems(
                 PreUpdate,
@@ -182,22 +190,22 @@ where
                 PreUpdate,
                 (
                     // for SyncMode::Full, we need to check if we need to rollback.
-                    check_rollback::<C, P>.in_set(PredictionSet::CheckRollback),
-                    (prepare_rollback::<C, P>, prepare_rollback_prespawn::<C, P>)
+                    check_rollback::<C, M>.in_set(PredictionSet::CheckRollback),
+                    (prepare_rollback::<C, M>, prepare_rollback_prespawn::<C, M>)
                         .in_set(PredictionSet::PrepareRollback),
                 ),
             );
             app.add_systems(
                 FixedUpdate,
                 (
-                    add_prespawned_component_history::<C, P>.in_set(PredictionSet::SpawnHistory),
+                    add_prespawned_component_history::<C, M>.in_set(PredictionSet::SpawnHistory),
                     // we need to run this during fixed update to know accurately the history for each tick
                     update_prediction_history::<C>.in_set(PredictionSet::UpdateHistory),
                 ),
             );
             app.add_systems(
                 PostUpdate,
-                get_visually_corrected_state::<C, P>.in_set(PredictionSet::VisualCorrection),
+                get_visually_corrected_state::<C, M>.in_set(PredictionSet::VisualCorrection),
             );
         }
         ComponentSyncMode::Simple => {
@@ -205,7 +213,7 @@ where
                 PreUpdate,
                 (
                     // for SyncMode::Simple, just copy the confirmed components
-                    apply_confirmed_update::<C, P>.in_set(PredictionSet::CheckRollback),
+                    apply_confirmed_update::<C, M>.in_set(PredictionSet::CheckRollback),
                     // if we are rolling back (maybe because the predicted entity despawn is getting cancelled, restore components)
                     restore_components_if_despawn_rolled_back::<C>
                         // .before(run_rollback::<P>)
@@ -226,7 +234,7 @@ where
     };
     app.add_systems(
         FixedUpdate,
-        remove_component_for_despawn_predicted::<C, P>.in_set(PredictionSet::EntityDespawn),
+        remove_component_for_despawn_predicted::<C, M>.in_set(PredictionSet::EntityDespawn),
     );
 }
 
@@ -360,4 +368,19 @@ impl<P: Protocol> Plugin for PredictionPlugin<P> {
                 .run_if(is_connected),
         );
     }
+
+    fn finish(&self, app: &mut App) {
+        if self.config.disable {
+            return;
+        }
+        // components can be registered after the plugin is built, so their systems are added when the app is finished
+        let registrations = app
+            .world
+            .get_resource::<ProtocolRegistry>()
+            .map(ProtocolRegistry::full_sync_registrations)
+            .unwrap_or_default();
+        for registration in registrations {
+            (registration.add_prediction_systems)(app);
+        }
+    }
 }
@@ -11,7 +11,6 @@ use crate::client::connection::ConnectionManager;
 use crate::client::prediction::resource::PredictionManager;
 use crate::client::resource::Client;
 use crate::prelude::{Named, PreSpawnedPlayerObject, ShouldBePredicted, TickManager};
-use crate::protocol::Protocol;
 use crate::shared::tick_manager::Tick;
 use crate::utils::ready_buffer::ReadyBuffer;
 
@@ -118,7 +117,7 @@ impl<T: SyncComponent> PredictionHistory<T> {
 
 // TODO: only run this for SyncComponent where SyncMode != None
 #[allow(clippy::type_complexity)]
-pub(crate) fn add_component_history<C: SyncComponent, P: Protocol>(
+pub(crate) fn add_component_history<C: SyncComponent, M: SyncMetadata<C>>(
     manager: Res<PredictionManager>,
     mut commands: Commands,
     tick_manager: Res<TickManager>,
@@ -131,15 +130,13 @@ pub(crate) fn add_component_history<C: SyncComponent, P: Protocol>(
         ),
     >,
     confirmed_entities: Query<(Entity, &Confirmed, Option<Ref<C>>)>,
-) where
-    P::Components: SyncMetadata<C>,
-{
+) {
     let tick = tick_manager.tick();
     for (confirmed_entity, confirmed, confirmed_component) in confirmed_entities.iter() {
         if let Some(p) = confirmed.predicted {
             if let Ok((predicted_entity, predicted_component)) = predicted_entities.get(p) {
                 // if component got added on predicted side, add history
-                add_history::<C, P>(tick, predicted_entity, &predicted_component, &mut commands);
+                add_history::<C, M>(tick, predicted_entity, &predicted_component, &mut commands);
 
                 // if component got added on confirmed side
                 // - full: sync component and add history
@@ -153,7 +150,7 @@ pub(crate) fn add_component_history<C: SyncComponent, P: Protocol>(
                         // map any entities from confirmed to predicted
                         let mut new_component = confirmed_component.deref().clone();
                         new_component.map_entities(Box::new(&manager.predicted_entity_map));
-                        match P::Components::mode() {
+                        match M::mode() {
                             ComponentSyncMode::Full => {
                                 // insert history, it will be quickly filled by a rollback (since it starts empty before the current client tick)
                                 // TODO: then there's no need to add the component here, since it's going to get added during rollback anyway
@@ -188,7 +185,7 @@ pub(crate) fn add_component_history<C: SyncComponent, P: Protocol>(
 /// Add the history for prespawned entities.
 /// This must run on FixedUpdate (for entities spawned on FixedUpdate and PreUpdate (for entities spawned on Update)
 #[allow(clippy::type_complexity)]
-pub fn add_prespawned_component_history<C: SyncComponent, P: Protocol>(
+pub fn add_prespawned_component_history<C: SyncComponent, M: SyncMetadata<C>>(
     mut commands: Commands,
     tick_manager: Res<TickManager>,
     prespawned_query: Query<
@@ -200,12 +197,10 @@ pub fn add_prespawned_component_history<C: SyncComponent, P: Protocol>(
             Or<(With<ShouldBePredicted>, With<PreSpawnedPlayerObject>)>,
         ),
     >,
-) where
-    P::Components: SyncMetadata<C>,
-{
+) {
     // add component history for pre-spawned entities right away
     for (predicted_entity, predicted_component) in prespawned_query.iter() {
-        add_history::<C, P>(
+        add_history::<C, M>(
             tick_manager.tick(),
             predicted_entity,
             &predicted_component,
@@ -215,16 +210,14 @@ pub fn add_prespawned_component_history<C: SyncComponent, P: Protocol>(
 }
 
 /// Add history when a predicted component gets added
-fn add_history<C: SyncComponent, P: Protocol>(
+fn add_history<C: SyncComponent, M: SyncMetadata<C>>(
     tick: Tick,
     predicted_entity: Entity,
     predicted_component: &Option<Ref<C>>,
     commands: &mut Commands,
-) where
-    P::Components: SyncMetadata<C>,
-{
+) {
     let kind = C::type_name();
-    if P::Components::mode() == ComponentSyncMode::Full {
+    if M::mode() == ComponentSyncMode::Full {
         if let Some(predicted_component) = predicted_component {
             // component got added on predicted side, add history
             if predicted_component.is_added() {
@@ -313,7 +306,7 @@ pub fn update_prediction_history<T: SyncComponent>(
 
 /// When we receive a server update, we might want to apply it to the predicted entity
 #[allow(clippy::type_complexity)]
-pub(crate) fn apply_confirmed_update<C: SyncComponent, P: Protocol>(
+pub(crate) fn apply_confirmed_update<C: SyncComponent, M: SyncMetadata<C>>(
     manager: Res<PredictionManager>,
     mut predicted_entities: Query<
         &mut C,
@@ -324,14 +317,12 @@ pub(crate) fn apply_confirmed_update<C: SyncComponent, P: Protocol>(
         ),
     >,
     confirmed_entities: Query<(&Confirmed, Ref<C>)>,
-) where
-    P::Components: SyncMetadata<C>,
-{
+) {
     for (confirmed_entity, confirmed_component) in confirmed_entities.iter() {
         if let Some(p) = confirmed_entity.predicted {
             if confirmed_component.is_changed() && !confirmed_component.is_added() {
                 if let Ok(mut predicted_component) = predicted_entities.get_mut(p) {
-                    match P::Components::mode() {
+                    match M::mode() {
                         ComponentSyncMode::Full => {
                             error!(
                                 "The predicted entity {:?} should have a ComponentHistory",
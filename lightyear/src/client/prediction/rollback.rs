@@ -7,45 +7,40 @@ use bevy::prelude::{
 use bevy::utils::{EntityHashSet, HashSet};
 use tracing::{debug, error, info, trace, trace_span};
 
-use crate::_reexport::{ComponentProtocol, FromType};
 use crate::client::components::{ComponentSyncMode, Confirmed, SyncComponent};
 use crate::client::config::ClientConfig;
-use crate::client::connection::ConnectionManager;
-use crate::client::prediction::correction::Correction;
+use crate::client::prediction::correction::{has_correction, Correction};
 use crate::client::prediction::predicted_history::ComponentState;
 use crate::client::prediction::resource::PredictionManager;
 use crate::client::resource::Client;
+use crate::client::sync::SyncStatus;
 use crate::prelude::client::SyncMetadata;
 use crate::prelude::{PreSpawnedPlayerObject, TickManager};
-use crate::protocol::Protocol;
 
 use super::predicted_history::PredictionHistory;
 use super::{Predicted, Rollback, RollbackState};
 
 #[allow(clippy::type_complexity)]
 #[allow(clippy::too_many_arguments)]
-pub(crate) fn check_rollback<C: SyncComponent, P: Protocol>(
+pub(crate) fn check_rollback<C: SyncComponent, M: SyncMetadata<C>>(
     // TODO: have a way to only get the updates of entities that are predicted?
     tick_manager: Res<TickManager>,
-    connection: Res<ConnectionManager<P>>,
+    sync_status: Res<SyncStatus>,
 
     // We also snap the value of the component to the server state if we are in rollback
     // We use Option<> because the predicted component could have been removed while it still exists in Confirmed
     mut predicted_query: Query<&mut PredictionHistory<C>, (With<Predicted>, Without<Confirmed>)>,
     confirmed_query: Query<(Entity, Option<&C>, Ref<Confirmed>)>,
     mut rollback: ResMut<Rollback>,
-) where
-    <P as Protocol>::ComponentKinds: FromType<C>,
-    P::Components: SyncMetadata<C>,
-{
-    let kind = <P::ComponentKinds as FromType<C>>::from_type();
+) {
+    let kind = C::type_name();
     // TODO: maybe change this into a run condition so that we don't even run the system (reduces parallelism)
-    if P::Components::mode() != ComponentSyncMode::Full {
+    if M::mode() != ComponentSyncMode::Full {
         return;
     }
 
     // TODO: for mode=simple/once, we still need to re-add the component if the entity ends up not being despawned!
-    if !connection.is_synced() || !connection.received_new_server_tick() {
+    if !sync_status.synced || !sync_status.received_new_server_tick {
         return;
     }
 
@@ -137,7 +132,7 @@ pub(crate) fn check_rollback<C: SyncComponent, P: Protocol>(
 
 #[allow(clippy::type_complexity)]
 #[allow(clippy::too_many_arguments)]
-pub(crate) fn prepare_rollback<C: SyncComponent, P: Protocol>(
+pub(crate) fn prepare_rollback<C: SyncComponent, M: SyncMetadata<C>>(
     // TODO: have a way to only get the updates of entities that are predicted?
     mut commands: Commands,
     config: Res<ClientConfig>,
@@ -159,14 +154,11 @@ pub(crate) fn prepare_rollback<C: SyncComponent, P: Protocol>(
     >,
     confirmed_query: Query<(Entity, Option<&C>, Ref<Confirmed>)>,
     rollback: Res<Rollback>,
-) where
-    <P as Protocol>::ComponentKinds: FromType<C>,
-    P::Components: SyncMetadata<C>,
-{
-    let kind = <P::ComponentKinds as FromType<C>>::from_type();
+) {
+    let kind = C::type_name();
 
     // TODO: maybe change this into a run condition so that we don't even run the system (reduces parallelism)
-    if P::Components::mode() != ComponentSyncMode::Full {
+    if M::mode() != ComponentSyncMode::Full {
         return;
     }
     let _span = trace_span!("client rollback prepare");
@@ -234,7 +226,7 @@ pub(crate) fn prepare_rollback<C: SyncComponent, P: Protocol>(
                             .round() as i16;
 
                         // no need to add the Correction if the correction is instant
-                        if correction_ticks != 0 && P::Components::has_correction() {
+                        if correction_ticks != 0 && has_correction::<C, M>() {
                             let final_correction_tick = current_tick + correction_ticks;
                             if let Some(correction) = correction.as_mut() {
                                 debug!("updating existing correction");
@@ -278,7 +270,7 @@ pub(crate) fn prepare_rollback<C: SyncComponent, P: Protocol>(
 /// - TODO: do we need any correction?
 #[allow(clippy::type_complexity)]
 #[allow(clippy::too_many_arguments)]
-pub(crate) fn prepare_rollback_prespawn<C: SyncComponent, P: Protocol>(
+pub(crate) fn prepare_rollback_prespawn<C: SyncComponent, M: SyncMetadata<C>>(
     // TODO: have a way to only get the updates of entities that are predicted?
     mut commands: Commands,
     config: Res<ClientConfig>,
@@ -300,14 +292,11 @@ pub(crate) fn prepare_rollback_prespawn<C: SyncComponent, P: Protocol>(
         ),
     >,
     rollback: Res<Rollback>,
-) where
-    <P as Protocol>::ComponentKinds: FromType<C>,
-    P::Components: SyncMetadata<C>,
-{
-    let kind = <P::ComponentKinds as FromType<C>>::from_type();
+) {
+    let kind = C::type_name();
 
     // TODO: maybe change this into a run condition so that we don't even run the system (reduces parallelism)
-    // if P::Components::mode() != ComponentSyncMode::Full {
+    // if M::mode() != ComponentSyncMode::Full {
     //     return;
     // }
     let _span = trace_span!("client prepare rollback for pre-spawned entities");
@@ -374,7 +363,7 @@ pub(crate) fn prepare_rollback_prespawn<C: SyncComponent, P: Protocol>(
                         .round() as i16;
 
                     // no need to add the Correction if the correction is instant
-                    if correction_ticks != 0 && P::Components::has_correction() {
+                    if correction_ticks != 0 && has_correction::<C, M>() {
                         let final_correction_tick = current_tick + correction_ticks;
                         if let Some(correction) = correction.as_mut() {
                             debug!("updating existing correction");
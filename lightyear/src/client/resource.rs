@@ -2,26 +2,29 @@
 use std::net::SocketAddr;
 use std::time::Duration;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use bevy::ecs::component::Tick as BevyTick;
 use bevy::ecs::system::SystemParam;
-use bevy::prelude::{Entity, Res, ResMut, Resource, World};
+use bevy::prelude::{Entity, EventWriter, Res, ResMut, Resource, World};
 use bevy::utils::EntityHashMap;
-use tracing::{debug, trace, trace_span};
+use serde::Serialize;
+use tracing::{debug, error, trace, trace_span};
 
 use crate::_reexport::ReplicationSend;
 use crate::channel::builder::Channel;
 use crate::connection::events::ConnectionEvents;
 use crate::inputs::native::input_buffer::InputBuffer;
+use crate::netcode::{fetch_token, ClientState, ConnectToken, DisconnectReason, Key, TokenRequest};
 use crate::netcode::{Client as NetcodeClient, ClientId};
-use crate::netcode::{ConnectToken, Key};
-use crate::packet::message::Message;
+use crate::packet::message::{Message, MessageHandle};
 use crate::prelude::NetworkTarget;
 use crate::protocol::channel::ChannelKind;
+use crate::protocol::registry::{DynamicMessage, ProtocolRegistry, RegisteredMessage};
 use crate::protocol::Protocol;
 use crate::shared::replication::components::Replicate;
 use crate::shared::replication::receive::ReplicationReceiver;
 use crate::shared::replication::send::ReplicationSender;
+use crate::shared::rpc::{Request, RequestId};
 use crate::shared::tick_manager::Tick;
 use crate::shared::tick_manager::TickManager;
 use crate::shared::time_manager::TimeManager;
@@ -30,6 +33,7 @@ use crate::transport::{PacketReceiver, PacketSender, Transport};
 
 use super::config::ClientConfig;
 use super::connection::ConnectionManager;
+use super::events::DisconnectEvent;
 
 #[derive(SystemParam)]
 pub struct Client<'w, 's, P: Protocol> {
@@ -63,8 +67,11 @@ pub struct ClientMut<'w, 's, P: Protocol> {
     pub(crate) connection: ResMut<'w, ConnectionManager<P>>,
     // protocol
     protocol: ResMut<'w, P>,
+    registry: Res<'w, ProtocolRegistry>,
     // events
     events: ResMut<'w, ConnectionEvents<P>>,
+    disconnect_events: EventWriter<'w, DisconnectEvent>,
+    pending_token: ResMut<'w, PendingToken>,
     // syncing
     pub(crate) time_manager: ResMut<'w, TimeManager>,
     pub(crate) tick_manager: ResMut<'w, TickManager>,
@@ -75,6 +82,7 @@ impl<'w, 's, P: Protocol> ClientMut<'w, 's, P> {
     /// Maintain connection with server, queues up any packet received from the server
     pub(crate) fn update(&mut self, delta: Duration) -> Result<()> {
         self.time_manager.update(delta);
+        self.io.update(delta)?;
         self.netcode.try_update(delta.as_secs_f64(), &mut self.io)?;
 
         // only start the connection (sending messages, sending pings, starting sync, etc.)
@@ -106,8 +114,101 @@ impl<'w, 's, P: Protocol> ClientMut<'w, 's, P> {
     // NETCODE
 
     /// Start the connection process with the server
+    ///
+    /// If the connect token is still being fetched (see [`Authentication::Remote`]),
+    /// the connection starts once the token is received.
     pub fn connect(&mut self) {
+        if self.pending_token.request.is_some() {
+            self.pending_token.connect = true;
+            return;
+        }
+        self.netcode.connect();
+    }
+
+    /// Start the connection process with the server, with new credentials
+    /// (for example because the previous connect token expired).
+    ///
+    /// The token of the previous session is presented to the server: if the server still holds the session
+    /// (see [`NetcodeConfig::with_resumption_grace_period`](crate::server::config::NetcodeConfig::with_resumption_grace_period)),
+    /// it is resumed with the same replication state instead of starting a new one.
+    ///
+    /// With [`Authentication::Remote`], the token is fetched in a background thread and the connection
+    /// starts once it is received.
+    pub fn reconnect(&mut self, auth: Authentication) -> Result<()> {
+        if let Authentication::Remote {
+            auth_addr,
+            credentials,
+        } = auth
+        {
+            *self.pending_token = PendingToken::new(TokenRequest::start(auth_addr, credentials));
+            self.pending_token.connect = true;
+            return Ok(());
+        }
+        let token = auth
+            .get_token(self.config.netcode.client_timeout_secs)
+            .context("could not generate token")?;
+        self.set_token(token)?;
         self.netcode.connect();
+        Ok(())
+    }
+
+    /// Returns true if the connect token is still being fetched (see [`Authentication::Remote`])
+    pub fn is_fetching_token(&self) -> bool {
+        self.pending_token.request.is_some()
+    }
+
+    /// Use the token that was fetched in the background once it is received, and connect if it was requested
+    pub(crate) fn poll_token_request(&mut self) {
+        let Some(result) = self
+            .pending_token
+            .request
+            .as_ref()
+            .and_then(|request| request.try_recv())
+        else {
+            return;
+        };
+        self.pending_token.request = None;
+        let connect = std::mem::take(&mut self.pending_token.connect);
+        match result
+            .map_err(anyhow::Error::from)
+            .and_then(|token| self.set_token(token))
+        {
+            Ok(()) if connect => self.netcode.connect(),
+            Ok(()) => {}
+            Err(e) => error!("could not fetch token: {}", e),
+        }
+    }
+
+    /// Replace the netcode client with one that uses the given token, keeping the resumption token of the previous session
+    fn set_token(&mut self, token: ConnectToken) -> Result<()> {
+        let token_bytes = token.try_into_bytes()?;
+        let mut netcode = NetcodeClient::with_config(
+            &token_bytes,
+            self.config
+                .netcode
+                .build()
+                .protocol_fingerprint(self.netcode.protocol_fingerprint()),
+        )?;
+        netcode.set_resumption_token(self.netcode.resumption_token());
+        *self.netcode = netcode;
+        Ok(())
+    }
+
+    /// Disconnect from the server
+    ///
+    /// A [`DisconnectEvent`] with the reason [`DisconnectReason::ClientDisconnected`] is emitted
+    /// if the client was connected.
+    pub fn disconnect(&mut self) -> Result<()> {
+        let was_connected = self.netcode.is_connected();
+        self.netcode.disconnect(&mut self.io)?;
+        if was_connected {
+            self.disconnect_events.send(DisconnectEvent::with_reason(
+                (),
+                DisconnectReason::ClientDisconnected,
+                vec![],
+            ));
+        }
+        Ok(())
     }
 
     // MESSAGES
@@ -122,7 +223,7 @@ impl<'w, 's, P: Protocol> ClientMut<'w, 's, P> {
         &mut self,
         message: M,
         target: NetworkTarget,
-    ) -> Result<()>
+    ) -> Result<Option<MessageHandle>>
     where
         P::Message: From<M>,
     {
@@ -132,7 +233,14 @@ impl<'w, 's, P: Protocol> ClientMut<'w, 's, P> {
     }
 
     /// Send a message to the server
-    pub fn send_message<C: Channel, M: Message>(&mut self, message: M) -> Result<()>
+    ///
+    /// Returns a [`MessageHandle`] if the channel tracks acks, which lets you match the message
+    /// with the [`MessageAckEvent`](crate::client::events::MessageAckEvent) or
+    /// [`MessageLostEvent`](crate::client::events::MessageLostEvent) emitted for it
+    pub fn send_message<C: Channel, M: Message>(
+        &mut self,
+        message: M,
+    ) -> Result<Option<MessageHandle>>
     where
         P::Message: From<M>,
     {
@@ -141,6 +249,39 @@ impl<'w, 's, P: Protocol> ClientMut<'w, 's, P> {
             .buffer_message(message.into(), channel, NetworkTarget::None)
     }
 
+    /// Send a message that was registered with [`AppRegistrationExt::register_message`] to the server
+    ///
+    /// [`AppRegistrationExt::register_message`]: crate::protocol::registry::AppRegistrationExt::register_message
+    pub fn send_registered_message<C: Channel, M: RegisteredMessage>(
+        &mut self,
+        message: M,
+    ) -> Result<Option<MessageHandle>> {
+        let message = self.registry.message(message)?;
+        self.send_message::<C, DynamicMessage>(message)
+    }
+
+    /// Send a message that was registered with [`AppRegistrationExt::register_message`] to other clients, via the server
+    ///
+    /// [`AppRegistrationExt::register_message`]: crate::protocol::registry::AppRegistrationExt::register_message
+    pub fn send_registered_message_to_target<C: Channel, M: RegisteredMessage>(
+        &mut self,
+        message: M,
+        target: NetworkTarget,
+    ) -> Result<Option<MessageHandle>> {
+        let message = self.registry.message(message)?;
+        self.send_message_to_target::<C, DynamicMessage>(message, target)
+    }
+
+    /// Send a request to the server on the reliable channel `C`
+    ///
+    /// The response can be read with [`ClientRpc`](crate::client::rpc::ClientRpc)
+    pub fn send_request<C: Channel, R: Request>(&mut self, request: R) -> Result<RequestId>
+    where
+        P::Message: From<R>,
+    {
+        self.connection.send_request::<C, R>(request)
+    }
+
     // INPUTS
 
     // TODO: maybe put the input_buffer directly in Client ?
@@ -162,6 +303,15 @@ pub enum Authentication {
         private_key: Key,
         protocol_id: u64,
     },
+    /// Or fetch a `ConnectToken` from a [`TokenServer`](crate::netcode::TokenServer) listening at `auth_addr`,
+    /// sending it the `credentials`.
+    ///
+    /// The token is fetched in a background thread, and the client connects once it is received.
+    /// The credentials and the token are sent in plaintext, see [`TokenServer`](crate::netcode::TokenServer#security).
+    Remote {
+        auth_addr: SocketAddr,
+        credentials: Vec<u8>,
+    },
 }
 
 impl Authentication {
@@ -177,6 +327,30 @@ impl Authentication {
                 .timeout_seconds(client_timeout_secs)
                 .generate()
                 .ok(),
+            // blocks until the token is received
+            Authentication::Remote {
+                auth_addr,
+                credentials,
+            } => fetch_token(auth_addr, &credentials)
+                .map_err(|e| error!(?auth_addr, "could not fetch token: {}", e))
+                .ok(),
+        }
+    }
+}
+
+/// Connect token that is being fetched in a background thread, see [`Authentication::Remote`]
+#[derive(Resource, Default)]
+pub(crate) struct PendingToken {
+    request: Option<TokenRequest>,
+    /// true if the client should connect once the token is received
+    connect: bool,
+}
+
+impl PendingToken {
+    pub(crate) fn new(request: TokenRequest) -> Self {
+        Self {
+            request: Some(request),
+            connect: false,
         }
     }
 }
@@ -223,6 +397,11 @@ impl<'w, 's, P: Protocol> Client<'w, 's, P> {
         self.netcode.is_connected()
     }
 
+    /// Returns the state of the netcode connection (for example to check why a connection was denied)
+    pub fn connection_state(&self) -> ClientState {
+        self.netcode.state()
+    }
+
     /// Returns true if the client is connected and has been time-synced with the server
     pub fn is_synced(&self) -> bool {
         self.connection.sync_manager.is_synced()
@@ -382,6 +561,8 @@ impl<P: Protocol> ReplicationSend<P> for ConnectionManager<P> {
     ) -> Result<()> {
         let kind: P::ComponentKinds = (&component).into();
         let group = replicate.group_id(Some(entity));
+        self.replication_sender
+            .update_base_priority(group, replicate.priority);
         // TODO: should we have additional state tracking so that we know we are in the process of sending this entity to clients?
         let collect_changes_since_this_tick = self
             .replication_sender
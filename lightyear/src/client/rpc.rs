@@ -0,0 +1,64 @@
+//! System parameter to exchange requests and responses with the server
+use std::marker::PhantomData;
+
+use anyhow::Result;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::ResMut;
+
+use crate::channel::builder::Channel;
+use crate::protocol::Protocol;
+use crate::shared::rpc::{ReceivedRequest, Request, RequestId};
+
+use super::connection::ConnectionManager;
+
+/// Send requests of type `R` to the server and read their responses,
+/// or read the requests of type `R` sent by the server and respond to them.
+///
+/// Requests and responses that are not read stay buffered until they are read.
+#[derive(SystemParam)]
+pub struct ClientRpc<'w, P: Protocol, R: Request> {
+    connection: ResMut<'w, ConnectionManager<P>>,
+    _marker: PhantomData<R>,
+}
+
+impl<'w, P: Protocol, R: Request> ClientRpc<'w, P, R> {
+    /// Send a request to the server on the reliable channel `C`
+    pub fn send_request<C: Channel>(&mut self, request: R) -> Result<RequestId>
+    where
+        P::Message: From<R>,
+    {
+        self.connection.send_request::<C, R>(request)
+    }
+
+    /// Take the responses to the requests we sent
+    pub fn drain_responses(&mut self) -> Vec<(RequestId, R::Response)>
+    where
+        P::Message: TryInto<R::Response, Error = ()>,
+    {
+        self.connection.rpc.drain_responses::<R>()
+    }
+
+    /// Take the requests sent by the server
+    pub fn drain_requests(&mut self) -> Vec<(ReceivedRequest, R)>
+    where
+        P::Message: TryInto<R, Error = ()>,
+    {
+        self.connection
+            .rpc
+            .drain_requests::<R>()
+            .into_iter()
+            .map(|(request_id, channel, request)| {
+                (ReceivedRequest::new(request_id, channel, ()), request)
+            })
+            .collect()
+    }
+
+    /// Send the response to a request sent by the server
+    pub fn respond(&mut self, request: ReceivedRequest, response: R::Response) -> Result<()>
+    where
+        P::Message: From<R::Response>,
+    {
+        self.connection
+            .send_response(request.request_id, request.channel, response.into())
+    }
+}
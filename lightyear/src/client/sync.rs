@@ -3,7 +3,7 @@
 use std::time::Duration;
 
 use crate::client::connection::ConnectionManager;
-use bevy::prelude::Res;
+use bevy::prelude::{Res, Resource};
 use chrono::Duration as ChronoDuration;
 use tracing::{debug, info, trace, warn};
 
@@ -82,6 +82,19 @@ impl SentPacketStore {
     }
 }
 
+/// Copy of the state of the [`SyncManager`], updated every frame after the server packets are received.
+///
+/// The prediction and interpolation systems read this resource instead of the [`ConnectionManager`], so that they
+/// don't depend on the [`Protocol`] and can also be added for the components registered at runtime.
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq)]
+pub(crate) struct SyncStatus {
+    pub(crate) synced: bool,
+    /// True if a packet from the server was received this frame
+    pub(crate) received_new_server_tick: bool,
+    pub(crate) latest_received_server_tick: Tick,
+    pub(crate) interpolation_tick: Tick,
+}
+
 /// In charge of syncing the client's tick/time with the server's tick/time
 /// right after the connection is established
 pub struct SyncManager {
@@ -340,6 +353,12 @@ impl SyncManager {
         )
     }
 
+    /// Fraction of the way between the interpolation tick and the next tick
+    pub(crate) fn interpolation_overstep(&self, tick_manager: &TickManager) -> f32 {
+        let tick_duration = tick_manager.config.tick_duration.as_nanos();
+        (self.interpolation_time.elapsed.as_nanos() % tick_duration) as f32 / tick_duration as f32
+    }
+
     // TODO: only run when there's a change? (new server tick received or new ping received)
     // TODO: change name to make it clear that we might modify speed
     pub(crate) fn update_interpolation_time(
@@ -572,6 +591,7 @@ mod tests {
             incoming_latency: Duration::from_millis(20),
             incoming_jitter: Duration::from_millis(0),
             incoming_loss: 0.0,
+            ..Default::default()
         };
         let mut stepper = BevyStepper::new(
             shared_config,
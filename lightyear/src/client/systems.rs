@@ -8,15 +8,28 @@ use std::ops::DerefMut;
 use tracing::{error, info, trace};
 
 use crate::_reexport::ReplicationSend;
+use crate::client::components::{ComponentSyncMode, Confirmed};
 use crate::client::config::ClientConfig;
 use crate::client::connection::ConnectionManager;
-use crate::client::events::{EntityDespawnEvent, EntitySpawnEvent};
+use crate::client::events::{
+    DisconnectEvent, EntityDespawnEvent, EntitySpawnEvent, MessageAckEvent, MessageLostEvent,
+    RequestTimeoutEvent, StreamReceivedEvent, TransferProgressEvent,
+};
 use crate::client::resource::{Client, ClientMut};
-use crate::connection::events::{IterEntityDespawnEvent, IterEntitySpawnEvent};
+use crate::client::sync::SyncStatus;
+use crate::connection::events::{
+    IterEntityDespawnEvent, IterEntitySpawnEvent, IterMessageAckEvent, IterMessageLostEvent,
+    IterRequestTimeoutEvent, IterStreamReceivedEvent, IterTransferProgressEvent,
+};
+use crate::netcode::DisconnectReason;
 use crate::prelude::{Io, TickManager, TimeManager};
 use crate::protocol::component::ComponentProtocol;
 use crate::protocol::message::MessageProtocol;
+use crate::protocol::registry::ProtocolRegistry;
 use crate::protocol::Protocol;
+use crate::shared::systems::events::{
+    push_registered_component_events, push_registered_message_events,
+};
 use crate::shared::tick_manager::TickEvent;
 
 pub(crate) fn receive<P: Protocol>(world: &mut World) {
@@ -43,9 +56,25 @@ pub(crate) fn receive<P: Protocol>(world: &mut World) {
                                         // UPDATE: update client state, send keep-alives, receive packets from io, update connection sync state
                                         time_manager.update(delta);
                                         trace!(time = ?time_manager.current_time(), tick = ?tick_manager.tick(), "receive");
+                                        // update the link conditioners and send the delayed packets
+                                        io.update(delta).unwrap_or_else(|e| {
+                                            error!("Error updating io: {}", e);
+                                        });
+                                        let was_connected = netcode.is_connected();
                                         netcode
                                             .try_update(delta.as_secs_f64(), io.deref_mut())
                                             .unwrap();
+                                        if was_connected && !netcode.is_connected() {
+                                            let reason = netcode
+                                                .disconnect_reason()
+                                                .unwrap_or(DisconnectReason::Unspecified);
+                                            info!(?reason, "Client disconnected");
+                                            world.send_event(DisconnectEvent::with_reason(
+                                                (),
+                                                reason,
+                                                netcode.disconnect_payload().to_vec(),
+                                            ));
+                                        }
 
                                         // only start the connection (sending messages, sending pings, starting sync, etc.)
                                         // once we are connected
@@ -63,6 +92,21 @@ pub(crate) fn receive<P: Protocol>(world: &mut World) {
                                                 .unwrap();
                                         }
 
+                                        // disconnect from a server that keeps sending fragments that violate the limits of the channels
+                                        if netcode.is_connected() && connection.message_manager.fragment_limits_exceeded() {
+                                            error!("Disconnecting from server that violated the fragment limits");
+                                            netcode
+                                                .disconnect_with_reason(DisconnectReason::ProtocolViolation, io.deref_mut())
+                                                .unwrap_or_else(|e| {
+                                                    error!("Error disconnecting from server: {}", e);
+                                                });
+                                            world.send_event(DisconnectEvent::with_reason(
+                                                (),
+                                                DisconnectReason::ProtocolViolation,
+                                                vec![],
+                                            ));
+                                        }
+
                                         // RECEIVE: receive packets from message managers
                                         let mut events = connection.receive(
                                             world,
@@ -88,8 +132,60 @@ pub(crate) fn receive<P: Protocol>(world: &mut World) {
                                             // }
 
                                             // Message Events
+                                            push_registered_message_events(world, &mut events);
                                             P::Message::push_message_events(world, &mut events);
 
+                                            // Message delivery Events
+                                            if events.has_message_acks() {
+                                                let mut message_ack_event_writer = world
+                                                    .get_resource_mut::<Events<MessageAckEvent>>()
+                                                    .unwrap();
+                                                for (handle, _) in events.into_iter_message_acks() {
+                                                    message_ack_event_writer
+                                                        .send(MessageAckEvent::new(handle, ()));
+                                                }
+                                            }
+                                            if events.has_message_losses() {
+                                                let mut message_lost_event_writer = world
+                                                    .get_resource_mut::<Events<MessageLostEvent>>()
+                                                    .unwrap();
+                                                for (handle, _) in events.into_iter_message_losses() {
+                                                    message_lost_event_writer
+                                                        .send(MessageLostEvent::new(handle, ()));
+                                                }
+                                            }
+
+                                            // Request timeout Events
+                                            if events.has_request_timeouts() {
+                                                let mut request_timeout_event_writer = world
+                                                    .get_resource_mut::<Events<RequestTimeoutEvent>>()
+                                                    .unwrap();
+                                                for (request_id, _) in events.into_iter_request_timeouts() {
+                                                    request_timeout_event_writer
+                                                        .send(RequestTimeoutEvent::new(request_id, ()));
+                                                }
+                                            }
+
+                                            // Stream Events
+                                            if events.has_transfer_progress() {
+                                                let mut transfer_progress_event_writer = world
+                                                    .get_resource_mut::<Events<TransferProgressEvent>>()
+                                                    .unwrap();
+                                                for (progress, _) in events.into_iter_transfer_progress() {
+                                                    transfer_progress_event_writer
+                                                        .send(TransferProgressEvent::new(progress, ()));
+                                                }
+                                            }
+                                            if events.has_streams_received() {
+                                                let mut stream_received_event_writer = world
+                                                    .get_resource_mut::<Events<StreamReceivedEvent>>()
+                                                    .unwrap();
+                                                for (transfer_id, bytes, _) in events.into_iter_streams_received() {
+                                                    stream_received_event_writer
+                                                        .send(StreamReceivedEvent::new(transfer_id, bytes, ()));
+                                                }
+                                            }
+
                                             // SpawnEntity event
                                             if events.has_entity_spawn() {
                                                 let mut entity_spawn_event_writer = world
@@ -114,6 +210,7 @@ pub(crate) fn receive<P: Protocol>(world: &mut World) {
                                             }
 
                                             // Update component events (updates, inserts, removes)
+                                            push_registered_component_events(world, &mut events);
                                             P::Components::push_component_events(
                                                 world,
                                                 &mut events,
@@ -161,6 +258,25 @@ pub fn send<P: Protocol>(
     // client.connection.clear();
 }
 
+/// Use the connect token once it has been fetched in the background (see [`Authentication::Remote`](crate::client::resource::Authentication::Remote))
+pub(crate) fn poll_token_request<P: Protocol>(mut client: ClientMut<P>) {
+    client.poll_token_request();
+}
+
+/// Copy the sync state of the [`ConnectionManager`] into the [`SyncStatus`] resource
+pub(crate) fn update_sync_status<P: Protocol>(
+    connection: Res<ConnectionManager<P>>,
+    tick_manager: Res<TickManager>,
+    mut sync_status: ResMut<SyncStatus>,
+) {
+    *sync_status = SyncStatus {
+        synced: connection.is_synced(),
+        received_new_server_tick: connection.received_new_server_tick(),
+        latest_received_server_tick: connection.latest_received_server_tick(),
+        interpolation_tick: connection.sync_manager.interpolation_tick(&tick_manager),
+    };
+}
+
 /// Update the sync manager.
 /// We run this at PostUpdate because:
 /// - client prediction time is computed from ticks, which haven't been updated yet at PreUpdate
@@ -218,3 +334,78 @@ pub(crate) fn sync_update<P: Protocol>(
         // }
     };
 }
+
+/// Copy the components registered with
+/// [`AppRegistrationExt::register_component`](crate::protocol::registry::AppRegistrationExt::register_component)
+/// from the confirmed entities to their predicted entities
+pub(crate) fn sync_registered_predicted_components(
+    world: &mut World,
+    query: &mut QueryState<(Entity, &Confirmed)>,
+) {
+    sync_registered_components(world, query, |confirmed| confirmed.predicted);
+}
+
+/// Copy the components registered with
+/// [`AppRegistrationExt::register_component`](crate::protocol::registry::AppRegistrationExt::register_component)
+/// from the confirmed entities to their interpolated entities
+pub(crate) fn sync_registered_interpolated_components(
+    world: &mut World,
+    query: &mut QueryState<(Entity, &Confirmed)>,
+) {
+    sync_registered_components(world, query, |confirmed| confirmed.interpolated);
+}
+
+/// The component is copied if the target entity doesn't have it yet, or if it changed on the confirmed entity
+/// (except for [`ComponentSyncMode::Once`]).
+fn sync_registered_components(
+    world: &mut World,
+    query: &mut QueryState<(Entity, &Confirmed)>,
+    target: fn(&Confirmed) -> Option<Entity>,
+) {
+    let last_run = world.last_change_tick();
+    let this_run = world.change_tick();
+    let Some(registry) = world.get_resource::<ProtocolRegistry>() else {
+        return;
+    };
+    let registrations = registry
+        .component_registrations()
+        // the components with ComponentSyncMode::Full are synced by their prediction/interpolation history
+        .filter(|(_, registration)| {
+            matches!(
+                registration.mode,
+                ComponentSyncMode::Simple | ComponentSyncMode::Once
+            )
+        })
+        .map(|(_, registration)| *registration)
+        .collect::<Vec<_>>();
+    if registrations.is_empty() {
+        return;
+    }
+    let mut syncs = vec![];
+    for (confirmed_entity, confirmed) in query.iter(world) {
+        let Some(target_entity) = target(confirmed) else {
+            continue;
+        };
+        let (Some(confirmed_ref), Some(target_ref)) = (
+            world.get_entity(confirmed_entity),
+            world.get_entity(target_entity),
+        ) else {
+            continue;
+        };
+        for registration in registrations.iter() {
+            let Some(ticks) = (registration.change_ticks)(&confirmed_ref) else {
+                continue;
+            };
+            let target_has_component = (registration.change_ticks)(&target_ref).is_some();
+            if !target_has_component
+                || (registration.mode != ComponentSyncMode::Once
+                    && ticks.is_changed(last_run, this_run))
+            {
+                syncs.push((registration.sync, confirmed_entity, target_entity));
+            }
+        }
+    }
+    for (sync, confirmed_entity, target_entity) in syncs {
+        sync(world, confirmed_entity, target_entity);
+    }
+}
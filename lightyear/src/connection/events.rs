@@ -4,16 +4,19 @@ use std::iter;
 
 use bevy::prelude::{Component, Entity, Resource};
 use bevy::utils::HashMap;
+use bytes::Bytes;
 use tracing::trace;
 
 use crate::_reexport::{FromType, MessageProtocol};
 #[cfg(feature = "leafwing")]
 use crate::inputs::leafwing::{InputMessage, LeafwingUserAction};
-use crate::packet::message::Message;
+use crate::packet::message::{Message, MessageHandle};
 use crate::prelude::{Named, Tick};
 use crate::protocol::channel::ChannelKind;
 use crate::protocol::message::MessageKind;
 use crate::protocol::{EventContext, Protocol};
+use crate::shared::rpc::RequestId;
+use crate::shared::stream::{TransferId, TransferProgress};
 
 // TODO: don't make fields pub but instead make accessors
 #[derive(Debug, Resource)]
@@ -31,6 +34,14 @@ pub struct ConnectionEvents<P: Protocol> {
     // replication
     pub spawns: Vec<Entity>,
     pub despawns: Vec<Entity>,
+    // delivery of the messages we sent
+    pub message_acks: Vec<MessageHandle>,
+    pub message_losses: Vec<MessageHandle>,
+    // requests we sent that did not receive a response in time
+    pub request_timeouts: Vec<RequestId>,
+    // streams
+    pub transfer_progress: Vec<TransferProgress>,
+    pub streams_received: Vec<(TransferId, Bytes)>,
 
     // TODO: [IMPORTANT]: add ticks as well?
     // - should we just return the latest update for a given component/entity, or all of them?
@@ -74,6 +85,11 @@ impl<P: Protocol> ConnectionEvents<P> {
             // replication
             spawns: Vec::new(),
             despawns: Vec::new(),
+            message_acks: Vec::new(),
+            message_losses: Vec::new(),
+            request_timeouts: Vec::new(),
+            transfer_progress: Vec::new(),
+            streams_received: Vec::new(),
             component_inserts: Default::default(),
             component_removes: Default::default(),
             component_updates: Default::default(),
@@ -90,6 +106,11 @@ impl<P: Protocol> ConnectionEvents<P> {
         self.messages.clear();
         self.spawns.clear();
         self.despawns.clear();
+        self.message_acks.clear();
+        self.message_losses.clear();
+        self.request_timeouts.clear();
+        self.transfer_progress.clear();
+        self.streams_received.clear();
         self.component_inserts.clear();
         self.component_removes.clear();
         self.component_updates.clear();
@@ -146,6 +167,36 @@ impl<P: Protocol> ConnectionEvents<P> {
         self.empty = false;
     }
 
+    pub(crate) fn push_message_ack(&mut self, handle: MessageHandle) {
+        trace!(?handle, "Message was acked");
+        self.message_acks.push(handle);
+        self.empty = false;
+    }
+
+    pub(crate) fn push_message_lost(&mut self, handle: MessageHandle) {
+        trace!(?handle, "Message was lost");
+        self.message_losses.push(handle);
+        self.empty = false;
+    }
+
+    pub(crate) fn push_request_timeout(&mut self, request_id: RequestId) {
+        trace!(?request_id, "Request timed out");
+        self.request_timeouts.push(request_id);
+        self.empty = false;
+    }
+
+    pub(crate) fn push_transfer_progress(&mut self, progress: TransferProgress) {
+        trace!(?progress, "Stream progressed");
+        self.transfer_progress.push(progress);
+        self.empty = false;
+    }
+
+    pub(crate) fn push_stream_received(&mut self, transfer_id: TransferId, bytes: Bytes) {
+        trace!(?transfer_id, "Received stream");
+        self.streams_received.push((transfer_id, bytes));
+        self.empty = false;
+    }
+
     pub(crate) fn push_spawn(&mut self, entity: Entity) {
         trace!(?entity, "Received entity spawn");
         #[cfg(feature = "metrics")]
@@ -326,6 +377,98 @@ impl<P: Protocol> IterEntitySpawnEvent for ConnectionEvents<P> {
     }
 }
 
+pub trait IterMessageAckEvent<Ctx: EventContext = ()> {
+    fn into_iter_message_acks(&mut self) -> Box<dyn Iterator<Item = (MessageHandle, Ctx)> + '_>;
+    fn has_message_acks(&self) -> bool;
+}
+
+impl<P: Protocol> IterMessageAckEvent for ConnectionEvents<P> {
+    fn into_iter_message_acks(&mut self) -> Box<dyn Iterator<Item = (MessageHandle, ())> + '_> {
+        let acks = std::mem::take(&mut self.message_acks);
+        Box::new(acks.into_iter().map(|handle| (handle, ())))
+    }
+
+    fn has_message_acks(&self) -> bool {
+        !self.message_acks.is_empty()
+    }
+}
+
+pub trait IterMessageLostEvent<Ctx: EventContext = ()> {
+    fn into_iter_message_losses(&mut self) -> Box<dyn Iterator<Item = (MessageHandle, Ctx)> + '_>;
+    fn has_message_losses(&self) -> bool;
+}
+
+impl<P: Protocol> IterMessageLostEvent for ConnectionEvents<P> {
+    fn into_iter_message_losses(&mut self) -> Box<dyn Iterator<Item = (MessageHandle, ())> + '_> {
+        let losses = std::mem::take(&mut self.message_losses);
+        Box::new(losses.into_iter().map(|handle| (handle, ())))
+    }
+
+    fn has_message_losses(&self) -> bool {
+        !self.message_losses.is_empty()
+    }
+}
+
+pub trait IterRequestTimeoutEvent<Ctx: EventContext = ()> {
+    fn into_iter_request_timeouts(&mut self) -> Box<dyn Iterator<Item = (RequestId, Ctx)> + '_>;
+    fn has_request_timeouts(&self) -> bool;
+}
+
+impl<P: Protocol> IterRequestTimeoutEvent for ConnectionEvents<P> {
+    fn into_iter_request_timeouts(&mut self) -> Box<dyn Iterator<Item = (RequestId, ())> + '_> {
+        let timeouts = std::mem::take(&mut self.request_timeouts);
+        Box::new(timeouts.into_iter().map(|request_id| (request_id, ())))
+    }
+
+    fn has_request_timeouts(&self) -> bool {
+        !self.request_timeouts.is_empty()
+    }
+}
+
+pub trait IterTransferProgressEvent<Ctx: EventContext = ()> {
+    fn into_iter_transfer_progress(
+        &mut self,
+    ) -> Box<dyn Iterator<Item = (TransferProgress, Ctx)> + '_>;
+    fn has_transfer_progress(&self) -> bool;
+}
+
+impl<P: Protocol> IterTransferProgressEvent for ConnectionEvents<P> {
+    fn into_iter_transfer_progress(
+        &mut self,
+    ) -> Box<dyn Iterator<Item = (TransferProgress, ())> + '_> {
+        let progress = std::mem::take(&mut self.transfer_progress);
+        Box::new(progress.into_iter().map(|progress| (progress, ())))
+    }
+
+    fn has_transfer_progress(&self) -> bool {
+        !self.transfer_progress.is_empty()
+    }
+}
+
+pub trait IterStreamReceivedEvent<Ctx: EventContext = ()> {
+    fn into_iter_streams_received(
+        &mut self,
+    ) -> Box<dyn Iterator<Item = (TransferId, Bytes, Ctx)> + '_>;
+    fn has_streams_received(&self) -> bool;
+}
+
+impl<P: Protocol> IterStreamReceivedEvent for ConnectionEvents<P> {
+    fn into_iter_streams_received(
+        &mut self,
+    ) -> Box<dyn Iterator<Item = (TransferId, Bytes, ())> + '_> {
+        let streams = std::mem::take(&mut self.streams_received);
+        Box::new(
+            streams
+                .into_iter()
+                .map(|(transfer_id, bytes)| (transfer_id, bytes, ())),
+        )
+    }
+
+    fn has_streams_received(&self) -> bool {
+        !self.streams_received.is_empty()
+    }
+}
+
 pub trait IterEntityDespawnEvent<Ctx: EventContext = ()> {
     fn into_iter_entity_despawn(&mut self) -> Box<dyn Iterator<Item = (Entity, Ctx)> + '_>;
     fn has_entity_despawn(&self) -> bool;
@@ -344,17 +487,32 @@ impl<P: Protocol> IterEntityDespawnEvent for ConnectionEvents<P> {
 
 /// Iterate through all the events for a given entity
 pub trait IterComponentUpdateEvent<P: Protocol, Ctx: EventContext = ()> {
+    /// Find all the updates of the component of the given kind
+    fn iter_component_update_kind(
+        &mut self,
+        kind: P::ComponentKinds,
+    ) -> Box<dyn Iterator<Item = (Entity, Ctx)> + '_>;
+
+    /// Is there any update for the component of the given kind
+    fn has_component_update_kind(&self, kind: P::ComponentKinds) -> bool;
+
     /// Find all the updates of component C
     fn iter_component_update<C: Component>(
         &mut self,
     ) -> Box<dyn Iterator<Item = (Entity, Ctx)> + '_>
     where
-        P::ComponentKinds: FromType<C>;
+        P::ComponentKinds: FromType<C>,
+    {
+        self.iter_component_update_kind(<P::ComponentKinds as FromType<C>>::from_type())
+    }
 
     /// Is there any update for component C
     fn has_component_update<C: Component>(&self) -> bool
     where
-        P::ComponentKinds: FromType<C>;
+        P::ComponentKinds: FromType<C>,
+    {
+        self.has_component_update_kind(<P::ComponentKinds as FromType<C>>::from_type())
+    }
 
     // /// Find all the updates of component C for a given entity
     // fn get_component_update<C: Component>(&self, entity: Entity) -> Option<Ctx>
@@ -363,12 +521,11 @@ pub trait IterComponentUpdateEvent<P: Protocol, Ctx: EventContext = ()> {
 }
 
 impl<P: Protocol> IterComponentUpdateEvent<P> for ConnectionEvents<P> {
-    fn iter_component_update<C: Component>(&mut self) -> Box<dyn Iterator<Item = (Entity, ())> + '_>
-    where
-        P::ComponentKinds: FromType<C>,
-    {
-        let component_kind = <P::ComponentKinds as FromType<C>>::from_type();
-        if let Some(data) = self.component_updates.remove(&component_kind) {
+    fn iter_component_update_kind(
+        &mut self,
+        kind: P::ComponentKinds,
+    ) -> Box<dyn Iterator<Item = (Entity, ())> + '_> {
+        if let Some(data) = self.component_updates.remove(&kind) {
             return Box::new(data.into_iter().map(|entity| (entity, ())));
         }
         Box::new(iter::empty())
@@ -381,12 +538,8 @@ impl<P: Protocol> IterComponentUpdateEvent<P> for ConnectionEvents<P> {
         // )
     }
 
-    fn has_component_update<C: Component>(&self) -> bool
-    where
-        P::ComponentKinds: FromType<C>,
-    {
-        let component_kind = <P::ComponentKinds as FromType<C>>::from_type();
-        self.component_updates.contains_key(&component_kind)
+    fn has_component_update_kind(&self, kind: P::ComponentKinds) -> bool {
+        self.component_updates.contains_key(&kind)
         // self.components_with_updates.contains(&C::into_kind())
     }
 
@@ -406,67 +559,81 @@ impl<P: Protocol> IterComponentUpdateEvent<P> for ConnectionEvents<P> {
 }
 
 pub trait IterComponentRemoveEvent<P: Protocol, Ctx: EventContext = ()> {
+    fn iter_component_remove_kind(
+        &mut self,
+        kind: P::ComponentKinds,
+    ) -> Box<dyn Iterator<Item = (Entity, Ctx)> + '_>;
+    fn has_component_remove_kind(&self, kind: P::ComponentKinds) -> bool;
+
     fn iter_component_remove<C: Component>(
         &mut self,
     ) -> Box<dyn Iterator<Item = (Entity, Ctx)> + '_>
     where
-        P::ComponentKinds: FromType<C>;
+        P::ComponentKinds: FromType<C>,
+    {
+        self.iter_component_remove_kind(<P::ComponentKinds as FromType<C>>::from_type())
+    }
     fn has_component_remove<C: Component>(&self) -> bool
     where
-        P::ComponentKinds: FromType<C>;
+        P::ComponentKinds: FromType<C>,
+    {
+        self.has_component_remove_kind(<P::ComponentKinds as FromType<C>>::from_type())
+    }
 }
 
 // TODO: move these implementations to client?
 impl<P: Protocol> IterComponentRemoveEvent<P> for ConnectionEvents<P> {
-    fn iter_component_remove<C: Component>(&mut self) -> Box<dyn Iterator<Item = (Entity, ())> + '_>
-    where
-        P::ComponentKinds: FromType<C>,
-    {
-        let component_kind = <P::ComponentKinds as FromType<C>>::from_type();
-        if let Some(data) = self.component_removes.remove(&component_kind) {
+    fn iter_component_remove_kind(
+        &mut self,
+        kind: P::ComponentKinds,
+    ) -> Box<dyn Iterator<Item = (Entity, ())> + '_> {
+        if let Some(data) = self.component_removes.remove(&kind) {
             return Box::new(data.into_iter().map(|entity| (entity, ())));
         }
         Box::new(iter::empty())
     }
 
-    fn has_component_remove<C: Component>(&self) -> bool
-    where
-        P::ComponentKinds: FromType<C>,
-    {
-        let component_kind = <P::ComponentKinds as FromType<C>>::from_type();
-        self.component_removes.contains_key(&component_kind)
+    fn has_component_remove_kind(&self, kind: P::ComponentKinds) -> bool {
+        self.component_removes.contains_key(&kind)
     }
 }
 
 pub trait IterComponentInsertEvent<P: Protocol, Ctx: EventContext = ()> {
+    fn iter_component_insert_kind(
+        &mut self,
+        kind: P::ComponentKinds,
+    ) -> Box<dyn Iterator<Item = (Entity, Ctx)> + '_>;
+    fn has_component_insert_kind(&self, kind: P::ComponentKinds) -> bool;
+
     fn iter_component_insert<C: Component>(
         &mut self,
     ) -> Box<dyn Iterator<Item = (Entity, Ctx)> + '_>
     where
-        P::ComponentKinds: FromType<C>;
+        P::ComponentKinds: FromType<C>,
+    {
+        self.iter_component_insert_kind(<P::ComponentKinds as FromType<C>>::from_type())
+    }
     fn has_component_insert<C: Component>(&self) -> bool
     where
-        P::ComponentKinds: FromType<C>;
+        P::ComponentKinds: FromType<C>,
+    {
+        self.has_component_insert_kind(<P::ComponentKinds as FromType<C>>::from_type())
+    }
 }
 
 impl<P: Protocol> IterComponentInsertEvent<P> for ConnectionEvents<P> {
-    fn iter_component_insert<C: Component>(&mut self) -> Box<dyn Iterator<Item = (Entity, ())> + '_>
-    where
-        P::ComponentKinds: FromType<C>,
-    {
-        let component_kind = <P::ComponentKinds as FromType<C>>::from_type();
-        if let Some(data) = self.component_inserts.remove(&component_kind) {
+    fn iter_component_insert_kind(
+        &mut self,
+        kind: P::ComponentKinds,
+    ) -> Box<dyn Iterator<Item = (Entity, ())> + '_> {
+        if let Some(data) = self.component_inserts.remove(&kind) {
             return Box::new(data.into_iter().map(|entity| (entity, ())));
         }
         Box::new(iter::empty())
     }
 
-    fn has_component_insert<C: Component>(&self) -> bool
-    where
-        P::ComponentKinds: FromType<C>,
-    {
-        let component_kind = <P::ComponentKinds as FromType<C>>::from_type();
-        self.component_inserts.contains_key(&component_kind)
+    fn has_component_insert_kind(&self, kind: P::ComponentKinds) -> bool {
+        self.component_inserts.contains_key(&kind)
     }
 }
 
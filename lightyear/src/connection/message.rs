@@ -8,44 +8,124 @@ use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use tracing::{info_span, trace};
 
-use crate::_reexport::{BitSerializable, MessageProtocol, ReadBuffer, WriteBuffer};
+use crate::_reexport::{
+    BitSerializable, ComponentProtocol, MessageProtocol, ReadBuffer, ReadWordBuffer, WriteBuffer,
+    WriteWordBuffer,
+};
 use crate::prelude::{ChannelKind, NetworkTarget};
+use crate::protocol::registry::ProtocolRegistry;
 use crate::protocol::Protocol;
 use crate::shared::ping::message::SyncMessage;
 use crate::shared::replication::{ReplicationMessage, ReplicationMessageData};
+use crate::shared::rpc::RequestId;
+use crate::shared::stream::StreamMessage;
 
 pub(crate) struct MessageMetadata {
     pub(crate) target: NetworkTarget,
     pub(crate) channel: ChannelKind,
 }
 
-// ClientMessages can include some extra Metadata
-#[derive(Encode, Decode, Clone, Debug)]
-pub enum ClientMessage<P: Protocol> {
+/// Variant of a [`ClientMessage`] or a [`ServerMessage`].
+///
+/// It is written before the content of the message, which goes through [`BitSerializable`] so that the
+/// messages and components can use their own serialization mode (see [`crate::serialize::mode`]).
+/// The frequencies give the most common variants the shortest codes.
+#[derive(Encode, Decode, Clone, Copy)]
+enum MessageTag {
     #[bitcode_hint(frequency = 2)]
-    #[bitcode(with_serde)]
-    Message(P::Message, NetworkTarget),
+    Message,
     #[bitcode_hint(frequency = 3)]
-    #[bitcode(with_serde)]
-    Replication(ReplicationMessage<P::Components, P::ComponentKinds>),
+    Replication,
+    #[bitcode_hint(frequency = 1)]
+    Sync,
+    #[bitcode_hint(frequency = 1)]
+    Request,
+    #[bitcode_hint(frequency = 1)]
+    Response,
     #[bitcode_hint(frequency = 1)]
+    Stream,
+}
+
+// ClientMessages can include some extra Metadata
+#[derive(Clone, Debug)]
+pub enum ClientMessage<P: Protocol> {
+    Message(P::Message, NetworkTarget),
+    Replication(
+        ReplicationMessage<
+            P::Components,
+            P::ComponentKinds,
+            <P::Components as ComponentProtocol>::Delta,
+        >,
+    ),
     // the reason why we include sync here instead of doing another MessageManager is so that
     // the sync messages can be added to packets that have other messages
     Sync(SyncMessage),
+    Request(RequestId, P::Message),
+    Response(RequestId, P::Message),
+    Stream(StreamMessage),
 }
 
 impl<P: Protocol> BitSerializable for ClientMessage<P> {
-    fn encode(&self, writer: &mut impl WriteBuffer) -> anyhow::Result<()> {
-        writer.encode(self, Fixed).context("could not encode")
-        // Encode::encode(self, Fixed, writer).context("could not encode")
-        // self.encode(Fixed, writer).context("could not encode")
+    fn encode(&self, writer: &mut WriteWordBuffer) -> anyhow::Result<()> {
+        match self {
+            ClientMessage::Message(message, target) => {
+                writer.encode(&MessageTag::Message, Fixed)?;
+                message.encode(writer)?;
+                writer.serialize(target)
+            }
+            ClientMessage::Replication(message) => {
+                writer.encode(&MessageTag::Replication, Fixed)?;
+                message.encode(writer)
+            }
+            ClientMessage::Sync(message) => {
+                writer.encode(&MessageTag::Sync, Fixed)?;
+                writer.encode(message, Fixed)
+            }
+            ClientMessage::Request(request_id, message) => {
+                writer.encode(&MessageTag::Request, Fixed)?;
+                writer.serialize(request_id)?;
+                message.encode(writer)
+            }
+            ClientMessage::Response(request_id, message) => {
+                writer.encode(&MessageTag::Response, Fixed)?;
+                writer.serialize(request_id)?;
+                message.encode(writer)
+            }
+            ClientMessage::Stream(message) => {
+                writer.encode(&MessageTag::Stream, Fixed)?;
+                writer.serialize(message)
+            }
+        }
+        .context("could not encode")
+    }
+    fn decode(reader: &mut ReadWordBuffer) -> anyhow::Result<Self> {
+        Self::decode_with(reader, &ProtocolRegistry::default())
     }
-    fn decode(reader: &mut impl ReadBuffer) -> anyhow::Result<Self>
-    where
-        Self: Sized,
-    {
-        reader.decode::<Self>(Fixed).context("could not decode")
-        // <Self as Decode>::decode(Fixed, reader).context("could not decode")
+
+    fn decode_with(
+        reader: &mut ReadWordBuffer,
+        registry: &ProtocolRegistry,
+    ) -> anyhow::Result<Self> {
+        let message = match reader.decode::<MessageTag>(Fixed)? {
+            MessageTag::Message => ClientMessage::Message(
+                P::Message::decode_with(reader, registry)?,
+                reader.deserialize()?,
+            ),
+            MessageTag::Replication => {
+                ClientMessage::Replication(ReplicationMessage::decode_with(reader, registry)?)
+            }
+            MessageTag::Sync => ClientMessage::Sync(reader.decode(Fixed)?),
+            MessageTag::Request => ClientMessage::Request(
+                reader.deserialize()?,
+                P::Message::decode_with(reader, registry)?,
+            ),
+            MessageTag::Response => ClientMessage::Response(
+                reader.deserialize()?,
+                P::Message::decode_with(reader, registry)?,
+            ),
+            MessageTag::Stream => ClientMessage::Stream(reader.deserialize()?),
+        };
+        Ok(message)
     }
 }
 
@@ -133,6 +213,22 @@ impl<P: Protocol> ClientMessage<P> {
                     }
                 }
             }
+            ClientMessage::Request(request_id, message) => {
+                trace!(channel = ?channel_name, ?request_id, request = ?message.name(), "Sending request");
+            }
+            ClientMessage::Response(request_id, message) => {
+                trace!(channel = ?channel_name, ?request_id, response = ?message.name(), "Sending response");
+            }
+            ClientMessage::Stream(StreamMessage::Chunk {
+                transfer_id,
+                offset,
+                ..
+            }) => {
+                trace!(channel = ?channel_name, ?transfer_id, ?offset, "Sending stream chunk");
+            }
+            ClientMessage::Stream(message) => {
+                trace!(channel = ?channel_name, ?message, "Sending stream message");
+            }
             ClientMessage::Sync(message) => match message {
                 SyncMessage::Ping(_) => {
                     trace!(channel = ?channel_name, "Sending ping");
@@ -149,31 +245,83 @@ impl<P: Protocol> ClientMessage<P> {
     }
 }
 
-#[derive(Encode, Decode, Clone, Debug)]
+#[derive(Clone, Debug)]
 pub enum ServerMessage<P: Protocol> {
-    #[bitcode_hint(frequency = 2)]
-    #[bitcode(with_serde)]
     Message(P::Message),
-    #[bitcode_hint(frequency = 3)]
-    #[bitcode(with_serde)]
-    Replication(ReplicationMessage<P::Components, P::ComponentKinds>),
+    Replication(
+        ReplicationMessage<
+            P::Components,
+            P::ComponentKinds,
+            <P::Components as ComponentProtocol>::Delta,
+        >,
+    ),
     // the reason why we include sync here instead of doing another MessageManager is so that
     // the sync messages can be added to packets that have other messages
-    #[bitcode_hint(frequency = 1)]
     Sync(SyncMessage),
+    Request(RequestId, P::Message),
+    Response(RequestId, P::Message),
+    Stream(StreamMessage),
 }
 
 impl<P: Protocol> BitSerializable for ServerMessage<P> {
-    fn encode(&self, writer: &mut impl WriteBuffer) -> anyhow::Result<()> {
-        writer.encode(self, Fixed).context("could not encode")
-        // writer.serialize(self)
+    fn encode(&self, writer: &mut WriteWordBuffer) -> anyhow::Result<()> {
+        match self {
+            ServerMessage::Message(message) => {
+                writer.encode(&MessageTag::Message, Fixed)?;
+                message.encode(writer)
+            }
+            ServerMessage::Replication(message) => {
+                writer.encode(&MessageTag::Replication, Fixed)?;
+                message.encode(writer)
+            }
+            ServerMessage::Sync(message) => {
+                writer.encode(&MessageTag::Sync, Fixed)?;
+                writer.encode(message, Fixed)
+            }
+            ServerMessage::Request(request_id, message) => {
+                writer.encode(&MessageTag::Request, Fixed)?;
+                writer.serialize(request_id)?;
+                message.encode(writer)
+            }
+            ServerMessage::Response(request_id, message) => {
+                writer.encode(&MessageTag::Response, Fixed)?;
+                writer.serialize(request_id)?;
+                message.encode(writer)
+            }
+            ServerMessage::Stream(message) => {
+                writer.encode(&MessageTag::Stream, Fixed)?;
+                writer.serialize(message)
+            }
+        }
+        .context("could not encode")
+    }
+    fn decode(reader: &mut ReadWordBuffer) -> anyhow::Result<Self> {
+        Self::decode_with(reader, &ProtocolRegistry::default())
     }
-    fn decode(reader: &mut impl ReadBuffer) -> anyhow::Result<Self>
-    where
-        Self: Sized,
-    {
-        reader.decode::<Self>(Fixed).context("could not decode")
-        // reader.deserialize::<Self>()
+
+    fn decode_with(
+        reader: &mut ReadWordBuffer,
+        registry: &ProtocolRegistry,
+    ) -> anyhow::Result<Self> {
+        let message = match reader.decode::<MessageTag>(Fixed)? {
+            MessageTag::Message => {
+                ServerMessage::Message(P::Message::decode_with(reader, registry)?)
+            }
+            MessageTag::Replication => {
+                ServerMessage::Replication(ReplicationMessage::decode_with(reader, registry)?)
+            }
+            MessageTag::Sync => ServerMessage::Sync(reader.decode(Fixed)?),
+            MessageTag::Request => ServerMessage::Request(
+                reader.deserialize()?,
+                P::Message::decode_with(reader, registry)?,
+            ),
+            MessageTag::Response => ServerMessage::Response(
+                reader.deserialize()?,
+                P::Message::decode_with(reader, registry)?,
+            ),
+            MessageTag::Stream => ServerMessage::Stream(reader.deserialize()?),
+        };
+        Ok(message)
     }
 }
 
@@ -261,6 +409,22 @@ impl<P: Protocol> ServerMessage<P> {
                     }
                 }
             }
+            ServerMessage::Request(request_id, message) => {
+                trace!(channel = ?channel_name, ?request_id, request = ?message.name(), "Sending request");
+            }
+            ServerMessage::Response(request_id, message) => {
+                trace!(channel = ?channel_name, ?request_id, response = ?message.name(), "Sending response");
+            }
+            ServerMessage::Stream(StreamMessage::Chunk {
+                transfer_id,
+                offset,
+                ..
+            }) => {
+                trace!(channel = ?channel_name, ?transfer_id, ?offset, "Sending stream chunk");
+            }
+            ServerMessage::Stream(message) => {
+                trace!(channel = ?channel_name, ?message, "Sending stream message");
+            }
             ServerMessage::Sync(message) => match message {
                 SyncMessage::Ping(_) => {
                     trace!(channel = ?channel_name, "Sending ping");
@@ -1,6 +1,8 @@
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, VecDeque};
 use std::fmt::{Debug, Formatter};
 
+use anyhow::bail;
+
 use bevy::math::Vec2;
 use bevy::prelude::{Component, Entity, Event, FromReflect, Reflect, Resource, TypePath};
 use bevy::reflect::DynamicTypePath;
@@ -11,9 +13,18 @@ use leafwing_input_manager::Actionlike;
 use serde::{Deserialize, Serialize};
 use tracing::trace;
 
+use crate::_reexport::{ReadBuffer, ReadWordBuffer, WriteBuffer, WriteWordBuffer};
+use crate::inputs::InterpolationInfo;
 use crate::prelude::client::SyncComponent;
 use crate::prelude::{EntityMapper, MapEntities, Message, Named};
+use crate::protocol::schema::format::{
+    ContainerFormat, Format, Named as NamedFormat, VariantFormat,
+};
+use crate::protocol::schema::tracer::Tracer;
 use crate::protocol::BitSerializable;
+use crate::serialize::mode::{
+    decode_len, decode_seq, decode_variant_index, encode_len, encode_seq, encode_variant_index,
+};
 use crate::shared::tick_manager::Tick;
 
 use super::LeafwingUserAction;
@@ -124,7 +135,7 @@ impl<A: LeafwingUserAction> Default for InputBuffer<A> {
 /// input actions are represented by a `Resource`)
 ///
 /// These are typically accessed using the `Events<ActionDiffEvent>` resource.
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Event)]
+#[derive(Clone, Debug, PartialEq, Event)]
 pub struct ActionDiffEvent<A: LeafwingUserAction> {
     /// If some: the entity that has the `ActionState<A>` component
     /// If none: `ActionState<A>` is a Resource, not a component
@@ -139,7 +150,7 @@ pub struct ActionDiffEvent<A: LeafwingUserAction> {
 /// Uses a minimal storage format, in order to facilitate transport over the network.
 ///
 /// An `ActionState` can be fully reconstructed from a stream of `ActionDiff`
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Reflect)]
+#[derive(Clone, Debug, PartialEq, Reflect)]
 pub enum ActionDiff<A: LeafwingUserAction> {
     /// The action was pressed
     Pressed {
@@ -209,12 +220,14 @@ impl<A: LeafwingUserAction> ActionDiff<A> {
     }
 }
 
-// TODO: use Mode to specify how to serialize a message (serde vs bitcode)! + can specify custom serialize function as well (similar to interpolation mode)
-#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Reflect)]
+#[derive(Clone, PartialEq, Debug, Reflect)]
 /// We serialize the inputs by sending only the ActionDiffs of the last few ticks
 /// We will store the last N inputs starting from start_tick (in case of packet loss)
 pub struct InputMessage<A: LeafwingUserAction> {
     pub(crate) end_tick: Tick,
+    /// Interpolation state of the client at the time the message was sent,
+    /// i.e. the state of the server world that the client was seeing when generating the input for `end_tick`
+    pub(crate) interpolation: Option<InterpolationInfo>,
     // first element is tick end_tick-N+1, last element is end_tick
     pub(crate) diffs: Vec<(InputTarget, Vec<Vec<ActionDiff<A>>>)>,
 }
@@ -229,6 +242,145 @@ pub enum InputTarget {
     PrePredictedEntity(Entity),
 }
 
+// The actions are written with `BitSerializable` so that they can use their own serialization mode.
+// The bits are the same as the ones of the serde encoding.
+impl<A: LeafwingUserAction> BitSerializable for ActionDiff<A> {
+    fn encode(&self, writer: &mut WriteWordBuffer) -> anyhow::Result<()> {
+        match self {
+            ActionDiff::Pressed { action } => {
+                encode_variant_index(0, writer)?;
+                action.encode(writer)
+            }
+            ActionDiff::Released { action } => {
+                encode_variant_index(1, writer)?;
+                action.encode(writer)
+            }
+            ActionDiff::ValueChanged { action, value } => {
+                encode_variant_index(2, writer)?;
+                action.encode(writer)?;
+                writer.serialize(value)
+            }
+            ActionDiff::AxisPairChanged { action, axis_pair } => {
+                encode_variant_index(3, writer)?;
+                action.encode(writer)?;
+                writer.serialize(axis_pair)
+            }
+        }
+    }
+
+    fn decode(reader: &mut ReadWordBuffer) -> anyhow::Result<Self> {
+        match decode_variant_index(reader)? {
+            0 => Ok(ActionDiff::Pressed {
+                action: A::decode(reader)?,
+            }),
+            1 => Ok(ActionDiff::Released {
+                action: A::decode(reader)?,
+            }),
+            2 => Ok(ActionDiff::ValueChanged {
+                action: A::decode(reader)?,
+                value: reader.deserialize()?,
+            }),
+            3 => Ok(ActionDiff::AxisPairChanged {
+                action: A::decode(reader)?,
+                axis_pair: reader.deserialize()?,
+            }),
+            index => bail!("invalid action diff variant {index}"),
+        }
+    }
+
+    fn format(tracer: &mut Tracer) -> Format {
+        let field = |name: &str, value| NamedFormat {
+            name: name.to_string(),
+            value,
+        };
+        let variant = |name: &str, fields| NamedFormat {
+            name: name.to_string(),
+            value: VariantFormat::Struct(fields),
+        };
+        let action = A::format(tracer);
+        let variants = BTreeMap::from([
+            (0, variant("Pressed", vec![field("action", action.clone())])),
+            (
+                1,
+                variant("Released", vec![field("action", action.clone())]),
+            ),
+            (
+                2,
+                variant(
+                    "ValueChanged",
+                    vec![field("action", action.clone()), field("value", Format::F32)],
+                ),
+            ),
+            (
+                3,
+                variant(
+                    "AxisPairChanged",
+                    vec![
+                        field("action", action),
+                        field("axis_pair", tracer.trace::<Vec2>()),
+                    ],
+                ),
+            ),
+        ]);
+        tracer.container("ActionDiff", ContainerFormat::Enum(variants))
+    }
+}
+
+impl<A: LeafwingUserAction> BitSerializable for InputMessage<A> {
+    fn encode(&self, writer: &mut WriteWordBuffer) -> anyhow::Result<()> {
+        writer.serialize(&self.end_tick)?;
+        writer.serialize(&self.interpolation)?;
+        encode_len(self.diffs.len(), writer)?;
+        for (target, diffs) in &self.diffs {
+            writer.serialize(target)?;
+            encode_len(diffs.len(), writer)?;
+            for diffs_per_tick in diffs {
+                encode_seq(diffs_per_tick, writer)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn decode(reader: &mut ReadWordBuffer) -> anyhow::Result<Self> {
+        let end_tick = reader.deserialize()?;
+        let interpolation = reader.deserialize()?;
+        // the lengths are not trusted, so we don't allocate them upfront
+        let mut diffs = Vec::new();
+        for _ in 0..decode_len(reader)? {
+            let target = reader.deserialize()?;
+            let mut target_diffs = Vec::new();
+            for _ in 0..decode_len(reader)? {
+                target_diffs.push(decode_seq(reader)?);
+            }
+            diffs.push((target, target_diffs));
+        }
+        Ok(Self {
+            end_tick,
+            interpolation,
+            diffs,
+        })
+    }
+
+    fn format(tracer: &mut Tracer) -> Format {
+        let field = |name: &str, value| NamedFormat {
+            name: name.to_string(),
+            value,
+        };
+        let diffs = Format::Tuple(vec![
+            tracer.trace::<InputTarget>(),
+            Format::Seq(Box::new(Format::Seq(Box::new(ActionDiff::<A>::format(
+                tracer,
+            ))))),
+        ]);
+        let fields = vec![
+            field("end_tick", tracer.trace::<Tick>()),
+            field("interpolation", tracer.trace::<Option<InterpolationInfo>>()),
+            field("diffs", Format::Seq(Box::new(diffs))),
+        ];
+        tracer.container("InputMessage", ContainerFormat::Struct(fields))
+    }
+}
+
 impl<A: LeafwingUserAction> Named for InputMessage<A> {
     // const NAME: &'static str = formatcp!("InputMessage<{}>", A::short_type_path());
     const NAME: &'static str = "InputMessage";
@@ -276,6 +428,7 @@ impl<T: LeafwingUserAction> InputMessage<T> {
     pub fn new(end_tick: Tick) -> Self {
         Self {
             end_tick,
+            interpolation: None,
             diffs: vec![],
         }
     }
@@ -654,6 +807,35 @@ mod tests {
     //     );
     // }
 
+    #[test]
+    fn test_input_message_serialization() -> anyhow::Result<()> {
+        let message = InputMessage::<Action> {
+            end_tick: Tick(10),
+            interpolation: None,
+            diffs: vec![(
+                InputTarget::Global,
+                vec![
+                    vec![],
+                    vec![
+                        ActionDiff::Pressed {
+                            action: Action::Jump,
+                        },
+                        ActionDiff::AxisPairChanged {
+                            action: Action::Jump,
+                            axis_pair: Vec2::new(1.0, -1.0),
+                        },
+                    ],
+                ],
+            )],
+        };
+        let mut writer = WriteWordBuffer::with_capacity(50);
+        message.encode(&mut writer)?;
+        let bytes = writer.finish_write();
+        let mut reader = ReadWordBuffer::start_read(bytes);
+        assert_eq!(InputMessage::<Action>::decode(&mut reader)?, message);
+        Ok(())
+    }
+
     #[test]
     fn test_update_from_message() {
         let mut diff_buffer = ActionDiffBuffer::default();
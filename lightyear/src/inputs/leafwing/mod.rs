@@ -13,6 +13,11 @@ use crate::protocol::BitSerializable;
 
 pub(crate) mod input_buffer;
 
+/// An action of `leafwing_input_manager` that is networked from the client to the server
+///
+/// The action is serialized with its `serde` implementation; to use bitcode or custom serialization
+/// functions instead, derive `Message` with the `#[message(bitcode)]` or
+/// `#[message(serialize_with = "..", deserialize_with = "..")]` attribute.
 pub trait LeafwingUserAction:
     BitSerializable
     + Copy
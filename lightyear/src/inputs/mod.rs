@@ -1,5 +1,20 @@
+use bevy::reflect::Reflect;
+use serde::{Deserialize, Serialize};
+
+use crate::shared::tick_manager::Tick;
+
 // TODO: import this as inputs, check how xwt/party does it
 #[cfg(feature = "leafwing")]
 pub mod leafwing;
 
 pub mod native;
+
+/// State of the client's interpolation timeline at the time it generated an input.
+///
+/// The client was displaying the interpolated entities between `tick` and `tick + 1`, `overstep` being
+/// the fraction (between 0.0 and 1.0) of the way between the two ticks.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Reflect)]
+pub struct InterpolationInfo {
+    pub tick: Tick,
+    pub overstep: f32,
+}
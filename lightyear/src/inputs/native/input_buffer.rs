@@ -1,13 +1,22 @@
 use std::collections::VecDeque;
 use std::fmt::Debug;
 
+use std::collections::BTreeMap;
+
+use anyhow::bail;
 use bevy::prelude::Resource;
-use serde::{Deserialize, Serialize};
 use tracing::{info, trace};
 
 use lightyear_macros::MessageInternal;
 
+use crate::_reexport::{ReadBuffer, ReadWordBuffer, WriteBuffer, WriteWordBuffer};
+use crate::inputs::InterpolationInfo;
+use crate::protocol::schema::format::{
+    ContainerFormat, Format, Named as NamedFormat, VariantFormat,
+};
+use crate::protocol::schema::tracer::Tracer;
 use crate::protocol::BitSerializable;
+use crate::serialize::mode::{decode_seq, decode_variant_index, encode_seq, encode_variant_index};
 use crate::shared::tick_manager::Tick;
 
 use super::UserAction;
@@ -20,23 +29,97 @@ pub struct InputBuffer<T: UserAction> {
 
 // TODO: add encode directive to encode even more efficiently
 /// We use this structure to efficiently compress the inputs that we send to the server
-#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug)]
 pub(crate) enum InputData<T: UserAction> {
     Absent,
     SameAsPrecedent,
     Input(T),
 }
 
-// TODO: use Mode to specify how to serialize a message (serde vs bitcode)! + can specify custom serialize function as well (similar to interpolation mode)
-#[derive(MessageInternal, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[derive(MessageInternal, Clone, PartialEq, Debug)]
 /// Message that we use to send the client inputs to the server
 /// We will store the last N inputs starting from start_tick (in case of packet loss)
 pub struct InputMessage<T: UserAction> {
     pub(crate) end_tick: Tick,
+    /// Interpolation state of the client at the time the message was sent,
+    /// i.e. the state of the server world that the client was seeing when generating the input for `end_tick`
+    pub(crate) interpolation: Option<InterpolationInfo>,
     // first element is tick end_tick-N+1, last element is end_tick
     pub(crate) inputs: Vec<InputData<T>>,
 }
 
+// The inputs are written with `BitSerializable` so that they can use their own serialization mode.
+// The bits are the same as the ones of the serde encoding.
+impl<T: UserAction> BitSerializable for InputData<T> {
+    fn encode(&self, writer: &mut WriteWordBuffer) -> anyhow::Result<()> {
+        match self {
+            InputData::Absent => encode_variant_index(0, writer),
+            InputData::SameAsPrecedent => encode_variant_index(1, writer),
+            InputData::Input(input) => {
+                encode_variant_index(2, writer)?;
+                input.encode(writer)
+            }
+        }
+    }
+
+    fn decode(reader: &mut ReadWordBuffer) -> anyhow::Result<Self> {
+        match decode_variant_index(reader)? {
+            0 => Ok(InputData::Absent),
+            1 => Ok(InputData::SameAsPrecedent),
+            2 => Ok(InputData::Input(T::decode(reader)?)),
+            index => bail!("invalid input variant {index}"),
+        }
+    }
+
+    fn format(tracer: &mut Tracer) -> Format {
+        let variant = |name: &str, value| NamedFormat {
+            name: name.to_string(),
+            value,
+        };
+        let variants = BTreeMap::from([
+            (0, variant("Absent", VariantFormat::Unit)),
+            (1, variant("SameAsPrecedent", VariantFormat::Unit)),
+            (
+                2,
+                variant("Input", VariantFormat::NewType(Box::new(T::format(tracer)))),
+            ),
+        ]);
+        tracer.container("InputData", ContainerFormat::Enum(variants))
+    }
+}
+
+impl<T: UserAction> BitSerializable for InputMessage<T> {
+    fn encode(&self, writer: &mut WriteWordBuffer) -> anyhow::Result<()> {
+        writer.serialize(&self.end_tick)?;
+        writer.serialize(&self.interpolation)?;
+        encode_seq(&self.inputs, writer)
+    }
+
+    fn decode(reader: &mut ReadWordBuffer) -> anyhow::Result<Self> {
+        Ok(Self {
+            end_tick: reader.deserialize()?,
+            interpolation: reader.deserialize()?,
+            inputs: decode_seq(reader)?,
+        })
+    }
+
+    fn format(tracer: &mut Tracer) -> Format {
+        let field = |name: &str, value| NamedFormat {
+            name: name.to_string(),
+            value,
+        };
+        let fields = vec![
+            field("end_tick", tracer.trace::<Tick>()),
+            field("interpolation", tracer.trace::<Option<InterpolationInfo>>()),
+            field(
+                "inputs",
+                Format::Seq(Box::new(InputData::<T>::format(tracer))),
+            ),
+        ];
+        tracer.container("InputMessage", ContainerFormat::Struct(fields))
+    }
+}
+
 impl<T: UserAction> InputMessage<T> {
     pub fn is_empty(&self) -> bool {
         if self.inputs.len() == 0 {
@@ -189,7 +272,11 @@ impl<T: UserAction> InputBuffer<T> {
                 inputs.push(value);
             }
         }
-        InputMessage { inputs, end_tick }
+        InputMessage {
+            inputs,
+            end_tick,
+            interpolation: None,
+        }
     }
 }
 
@@ -232,6 +319,7 @@ mod tests {
             message,
             InputMessage {
                 end_tick: Tick(10),
+                interpolation: None,
                 inputs: vec![
                     InputData::Absent,
                     InputData::Input(0),
@@ -252,6 +340,7 @@ mod tests {
 
         let message = InputMessage {
             end_tick: Tick(20),
+            interpolation: None,
             inputs: vec![
                 InputData::Absent,
                 InputData::Input(0),
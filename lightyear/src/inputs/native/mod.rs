@@ -12,6 +12,11 @@ use crate::protocol::BitSerializable;
 pub mod input_buffer;
 
 // TODO: should we request that a user input is a message?
+/// An input sent by the client to the server every tick
+///
+/// The input is serialized with its `serde` implementation; to use bitcode or custom serialization
+/// functions instead, derive `Message` with the `#[message(bitcode)]` or
+/// `#[message(serialize_with = "..", deserialize_with = "..")]` attribute.
 pub trait UserAction:
     BitSerializable + Clone + Eq + PartialEq + Send + Sync + Debug + 'static
 {
@@ -18,7 +18,8 @@ pub mod _reexport {
     pub use paste::paste;
 
     pub use lightyear_macros::{
-        component_protocol_internal, message_protocol_internal, ChannelInternal, MessageInternal,
+        component_protocol_internal, message_protocol_internal, ChannelInternal, DiffableInternal,
+        MessageInternal,
     };
 
     pub use crate::channel::builder::TickBufferChannel;
@@ -40,7 +41,10 @@ pub mod _reexport {
     };
     pub use crate::protocol::message::InputMessageKind;
     pub use crate::protocol::message::{MessageKind, MessageProtocol};
+    pub use crate::protocol::registry::{DynamicComponent, DynamicMessage, ProtocolRegistry};
     pub use crate::protocol::{BitSerializable, EventContext};
+    pub use crate::serialize::mode as serialize_mode;
+    pub use crate::serialize::quantize;
     pub use crate::serialize::reader::ReadBuffer;
     pub use crate::serialize::wordbuffer::reader::ReadWordBuffer;
     pub use crate::serialize::wordbuffer::writer::WriteWordBuffer;
@@ -49,6 +53,7 @@ pub mod _reexport {
         ComponentInsertEvent, ComponentRemoveEvent, ComponentUpdateEvent,
     };
     pub use crate::shared::replication::components::ShouldBeInterpolated;
+    pub use crate::shared::replication::delta::Diffable;
     pub use crate::shared::replication::systems::add_per_component_replication_send_systems;
     pub use crate::shared::replication::ReplicationSend;
     pub use crate::shared::systems::events::{
@@ -61,20 +66,23 @@ pub mod _reexport {
 
 /// Prelude containing commonly used types
 pub mod prelude {
-    pub use lightyear_macros::{component_protocol, message_protocol, Channel, Message};
+    pub use lightyear_macros::{component_protocol, message_protocol, Channel, Diffable, Message};
 
     pub use crate::channel::builder::TickBufferChannel;
     pub use crate::channel::builder::{
         Channel, ChannelBuilder, ChannelContainer, ChannelDirection, ChannelMode, ChannelSettings,
-        DefaultUnorderedUnreliableChannel, ReliableSettings,
+        DefaultUnorderedUnreliableChannel, FragmentSettings, ReliableSettings, StreamSettings,
     };
     pub use crate::client::prediction::prespawn::PreSpawnedPlayerObject;
     #[cfg(feature = "leafwing")]
     pub use crate::inputs::leafwing::LeafwingUserAction;
     pub use crate::inputs::native::UserAction;
-    pub use crate::netcode::{generate_key, ClientId, Key};
-    pub use crate::packet::message::Message;
+    pub use crate::inputs::InterpolationInfo;
+    pub use crate::netcode::{generate_key, ClientId, DenyReason, DisconnectReason, Key};
+    pub use crate::packet::message::{Message, MessageHandle};
+    pub use crate::packet::priority_manager::PriorityConfig;
     pub use crate::protocol::channel::{ChannelKind, ChannelRegistry};
+    pub use crate::protocol::registry::{AppRegistrationExt, ProtocolRegistry};
     pub use crate::protocol::Protocol;
     pub use crate::protocolize;
     pub use crate::shared::config::SharedConfig;
@@ -84,13 +92,22 @@ pub mod prelude {
     pub use crate::shared::replication::components::{
         NetworkTarget, ReplicationGroup, ReplicationMode, ShouldBePredicted,
     };
+    pub use crate::shared::replication::delta::Diffable;
     pub use crate::shared::replication::entity_map::{EntityMapper, MapEntities, RemoteEntityMap};
+    pub use crate::shared::rpc::{ReceivedRequest, Request, RequestId};
     pub use crate::shared::sets::{FixedUpdateSet, MainSet, ReplicationSet};
+    pub use crate::shared::stream::{
+        TransferDirection, TransferId, TransferProgress, TransferStatus,
+    };
     pub use crate::shared::tick_manager::TickManager;
     pub use crate::shared::tick_manager::{Tick, TickConfig};
     pub use crate::shared::time_manager::TimeManager;
-    pub use crate::transport::conditioner::LinkConditionerConfig;
+    pub use crate::transport::conditioner::{
+        BandwidthConfig, BurstLossConfig, LinkConditionerConfig, NetworkSpike,
+    };
     pub use crate::transport::io::{Io, IoConfig, TransportConfig};
+    #[cfg(feature = "compression")]
+    pub use crate::transport::compression::CompressionConfig;
     pub use crate::utils::named::Named;
 
     pub mod client {
@@ -101,7 +118,9 @@ pub mod prelude {
         pub use crate::client::config::NetcodeConfig;
         pub use crate::client::events::{
             ComponentInsertEvent, ComponentRemoveEvent, ComponentUpdateEvent, ConnectEvent,
-            DisconnectEvent, EntityDespawnEvent, EntitySpawnEvent, InputEvent, MessageEvent,
+            DisconnectEvent, EntityDespawnEvent, EntitySpawnEvent, InputEvent, MessageAckEvent,
+            MessageEvent, MessageLostEvent, RequestTimeoutEvent, StreamReceivedEvent,
+            TransferProgressEvent,
         };
         pub use crate::client::input::{InputConfig, InputSystemSet};
         pub use crate::client::interpolation::interpolation_history::ConfirmedHistory;
@@ -118,6 +137,7 @@ pub mod prelude {
         pub use crate::client::resource::Authentication;
         pub use crate::client::sync::SyncConfig;
         pub use crate::netcode::Client as NetClient;
+        pub use crate::netcode::{ClientState, ResumptionToken};
 
         #[cfg(feature = "leafwing")]
         pub use crate::client::input_leafwing::{LeafwingInputConfig, LeafwingInputPlugin};
@@ -126,14 +146,26 @@ pub mod prelude {
         #[cfg(feature = "webtransport")]
         pub use wtransport::tls::Certificate;
 
-        pub use crate::server::config::NetcodeConfig;
+        pub use crate::netcode::{BanList, RateLimitConfig, RejectionStats};
+        pub use crate::server::config::{AdmissionFn, NetcodeConfig};
         pub use crate::server::config::ServerConfig;
         pub use crate::server::events::{
             ComponentInsertEvent, ComponentRemoveEvent, ComponentUpdateEvent, ConnectEvent,
-            DisconnectEvent, EntityDespawnEvent, EntitySpawnEvent, InputEvent, MessageEvent,
+            DisconnectEvent, EntityDespawnEvent, EntitySpawnEvent, InputEvent, MessageAckEvent,
+            MessageEvent, MessageLostEvent, RequestTimeoutEvent, StreamReceivedEvent,
+            TransferProgressEvent,
+        };
+        pub use crate::server::lag_compensation::{
+            LagCompensated, LagCompensation, LagCompensationConfig, LagCompensationHistory,
+            LagCompensationPlugin, LagCompensationSet,
         };
         pub use crate::server::plugin::{PluginConfig, ServerPlugin};
         pub use crate::server::room::{RoomId, RoomManager, RoomMut, RoomRef};
+        pub use crate::server::spatial::{
+            SpatialConfig, SpatialManager, SpatialMode, SpatialPlugin, SpatialPosition, SpatialSet,
+            SpatialViewer,
+        };
+        pub use crate::server::visibility::VisibilityManager;
 
         #[cfg(feature = "leafwing")]
         pub use crate::server::input_leafwing::LeafwingInputPlugin;
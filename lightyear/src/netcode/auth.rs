@@ -0,0 +1,406 @@
+//! A minimal auth backend that hands out [`ConnectToken`]s over TCP.
+//!
+//! Clients should not have access to the server's private key, so the connect tokens have to be generated
+//! by a trusted backend. The [`TokenServer`] is the smallest such backend: it listens on a TCP socket and,
+//! for every incoming connection, reads the credentials sent by the client (a little-endian `u16` length
+//! followed by at most [`MAX_CREDENTIALS_BYTES`] bytes) and asks a user-provided callback which [`ClientId`]
+//! (and user data) to assign to the requester. It then writes the serialized token (exactly [`CONNECT_TOKEN_BYTES`] bytes)
+//! and closes the connection. If the callback refuses the request, the connection is closed without
+//! writing anything. Each connection is handled on its own thread, so that a slow client doesn't delay the others.
+//!
+//! On the client side, [`fetch_token`] (or [`Authentication::Remote`](crate::client::resource::Authentication::Remote),
+//! which fetches the token in a background thread) retrieves a token from the backend before connecting to the game server.
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crossbeam_channel::{Receiver, TryRecvError};
+use tracing::{debug, error, info};
+
+use super::crypto::Key;
+use super::token::ConnectToken;
+use super::{ClientId, Error, Result, CONNECT_TOKEN_BYTES, USER_DATA_BYTES};
+
+/// How long a token request can take before the connection is dropped
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+/// How often the listener thread checks if it should stop
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+/// Maximum size of the credentials sent with a token request
+pub const MAX_CREDENTIALS_BYTES: usize = 1024;
+/// Maximum number of token requests handled at the same time; further connections are dropped
+const MAX_CONCURRENT_REQUESTS: usize = 64;
+
+/// Decides which client id and user data to put in the token of the client connecting from the given address,
+/// with the given credentials.
+///
+/// Returning `None` refuses the request. The function can be called from several threads at the same time.
+pub type TokenFn = Box<
+    dyn Fn(SocketAddr, &[u8]) -> Option<(ClientId, [u8; USER_DATA_BYTES])> + Send + Sync + 'static,
+>;
+
+/// A TCP server that issues [`ConnectToken`]s for a game server
+///
+/// # Security
+///
+/// The credentials and the token are sent in plaintext, and the token contains the keys that encrypt the
+/// packets of the session: anyone who can read the traffic can impersonate the client.
+/// [`TokenServer::start`] and [`fetch_token`] must only be used on a trusted network (or behind a proxy that
+/// terminates TLS). Otherwise, wrap the connections in TLS and use [`TokenServer::handle_stream`] and [`fetch_token_from`],
+/// which work on any stream.
+pub struct TokenServer {
+    server_addr: SocketAddr,
+    protocol_id: u64,
+    private_key: Key,
+    expire_seconds: Option<i32>,
+    timeout_seconds: Option<i32>,
+    token_fn: TokenFn,
+}
+
+impl TokenServer {
+    /// Create a token server for the game server at `server_addr`.
+    ///
+    /// The `protocol_id` and `private_key` must match the ones of the game server.
+    pub fn new(
+        server_addr: SocketAddr,
+        protocol_id: u64,
+        private_key: Key,
+        token_fn: impl Fn(SocketAddr, &[u8]) -> Option<(ClientId, [u8; USER_DATA_BYTES])>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        Self {
+            server_addr,
+            protocol_id,
+            private_key,
+            expire_seconds: None,
+            timeout_seconds: None,
+            token_fn: Box::new(token_fn),
+        }
+    }
+
+    /// Sets the time in seconds that the issued tokens will be valid for.
+    pub fn expire_seconds(mut self, expire_seconds: i32) -> Self {
+        self.expire_seconds = Some(expire_seconds);
+        self
+    }
+
+    /// Sets the connection timeout of the issued tokens.
+    pub fn timeout_seconds(mut self, timeout_seconds: i32) -> Self {
+        self.timeout_seconds = Some(timeout_seconds);
+        self
+    }
+
+    /// Generate the token for a client connecting from `requester` with `credentials`, or `None` if the request is refused
+    pub fn issue_token(
+        &self,
+        requester: SocketAddr,
+        credentials: &[u8],
+    ) -> Result<Option<ConnectToken>> {
+        let Some((client_id, user_data)) = (self.token_fn)(requester, credentials) else {
+            return Ok(None);
+        };
+        let mut builder = ConnectToken::build(
+            self.server_addr,
+            self.protocol_id,
+            client_id,
+            self.private_key,
+        )
+        .user_data(user_data);
+        if let Some(expire_seconds) = self.expire_seconds {
+            builder = builder.expire_seconds(expire_seconds);
+        }
+        if let Some(timeout_seconds) = self.timeout_seconds {
+            builder = builder.timeout_seconds(timeout_seconds);
+        }
+        builder.generate().map(Some)
+    }
+
+    /// Start listening for token requests on `auth_addr`, in a background thread.
+    ///
+    /// Each request is handled on its own thread (at most 64 at the same time).
+    /// The server stops accepting requests when the returned [`TokenServerHandle`] is dropped.
+    pub fn start(self, auth_addr: impl ToSocketAddrs) -> Result<TokenServerHandle> {
+        let listener = TcpListener::bind(auth_addr)?;
+        // non-blocking so that the thread can notice when it should stop
+        listener.set_nonblocking(true)?;
+        let local_addr = listener.local_addr()?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let server = Arc::new(self);
+        let pending_requests = Arc::new(AtomicUsize::new(0));
+        let thread = std::thread::spawn(move || {
+            info!(?local_addr, "Token server started");
+            while !thread_stop.load(Ordering::Relaxed) {
+                match listener.accept() {
+                    Ok((stream, requester)) => {
+                        if pending_requests.fetch_add(1, Ordering::Relaxed)
+                            >= MAX_CONCURRENT_REQUESTS
+                        {
+                            pending_requests.fetch_sub(1, Ordering::Relaxed);
+                            error!(
+                                ?requester,
+                                "Too many token requests, dropping the connection"
+                            );
+                            continue;
+                        }
+                        let server = server.clone();
+                        let pending_requests = pending_requests.clone();
+                        // reading the request can block for up to REQUEST_TIMEOUT
+                        std::thread::spawn(move || {
+                            if let Err(e) = server.handle_request(stream, requester) {
+                                error!(?requester, "Could not handle token request: {}", e);
+                            }
+                            pending_requests.fetch_sub(1, Ordering::Relaxed);
+                        });
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                        std::thread::sleep(POLL_INTERVAL);
+                    }
+                    Err(e) => error!("Token server could not accept connection: {}", e),
+                }
+            }
+        });
+        Ok(TokenServerHandle {
+            local_addr,
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    fn handle_request(&self, stream: TcpStream, requester: SocketAddr) -> Result<()> {
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+        stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+        self.handle_stream(stream, requester)
+    }
+
+    /// Answer the token request of the client connected to `stream`: read its credentials and write its token.
+    ///
+    /// This can be used to serve the tokens over an encrypted stream (for example TLS) instead of [`TokenServer::start`].
+    pub fn handle_stream(
+        &self,
+        mut stream: impl Read + Write,
+        requester: SocketAddr,
+    ) -> Result<()> {
+        let mut len = [0u8; 2];
+        stream.read_exact(&mut len)?;
+        let len = u16::from_le_bytes(len) as usize;
+        if len > MAX_CREDENTIALS_BYTES {
+            return Err(Error::SizeMismatch(MAX_CREDENTIALS_BYTES, len));
+        }
+        let mut credentials = vec![0u8; len];
+        stream.read_exact(&mut credentials)?;
+        match self.issue_token(requester, &credentials)? {
+            Some(token) => {
+                debug!(?requester, "Issuing connect token");
+                stream.write_all(&token.try_into_bytes()?)?;
+            }
+            None => debug!(?requester, "Token request refused"),
+        }
+        Ok(())
+    }
+}
+
+/// Handle to a running [`TokenServer`]; the server stops when it is dropped
+pub struct TokenServerHandle {
+    local_addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl TokenServerHandle {
+    /// The address the token server is listening on
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+impl Drop for TokenServerHandle {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Request a [`ConnectToken`] from the [`TokenServer`] listening on `auth_addr`, sending `credentials`
+/// (at most [`MAX_CREDENTIALS_BYTES`] bytes).
+///
+/// This blocks until the token is received, see [`TokenRequest`] to fetch it in a background thread.
+pub fn fetch_token(auth_addr: SocketAddr, credentials: &[u8]) -> Result<ConnectToken> {
+    let stream = TcpStream::connect_timeout(&auth_addr, REQUEST_TIMEOUT)?;
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+    fetch_token_from(stream, credentials)
+}
+
+/// Request a [`ConnectToken`] from the [`TokenServer`] at the other end of `stream`, sending `credentials`.
+///
+/// This can be used to fetch the token over an encrypted stream (for example TLS) instead of [`fetch_token`].
+pub fn fetch_token_from(mut stream: impl Read + Write, credentials: &[u8]) -> Result<ConnectToken> {
+    if credentials.len() > MAX_CREDENTIALS_BYTES {
+        return Err(Error::SizeMismatch(
+            MAX_CREDENTIALS_BYTES,
+            credentials.len(),
+        ));
+    }
+    stream.write_all(&(credentials.len() as u16).to_le_bytes())?;
+    stream.write_all(credentials)?;
+    stream.flush()?;
+    let mut bytes = Vec::with_capacity(CONNECT_TOKEN_BYTES);
+    stream.read_to_end(&mut bytes)?;
+    if bytes.is_empty() {
+        return Err(Error::Io(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "the auth server refused the token request",
+        )));
+    }
+    ConnectToken::try_from_bytes(&bytes).map_err(Error::InvalidToken)
+}
+
+/// A [`ConnectToken`] that is being fetched with [`fetch_token`] in a background thread
+pub struct TokenRequest {
+    receiver: Receiver<Result<ConnectToken>>,
+}
+
+impl TokenRequest {
+    /// Start fetching a token from the [`TokenServer`] listening on `auth_addr`
+    pub fn start(auth_addr: SocketAddr, credentials: Vec<u8>) -> Self {
+        let (sender, receiver) = crossbeam_channel::bounded(1);
+        std::thread::spawn(move || {
+            let _ = sender.send(fetch_token(auth_addr, &credentials));
+        });
+        Self { receiver }
+    }
+
+    /// Returns the result of the request if it is finished
+    pub fn try_recv(&self) -> Option<Result<ConnectToken>> {
+        match self.receiver.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(Error::Io(io::Error::new(
+                io::ErrorKind::Other,
+                "the token request thread stopped",
+            )))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::netcode::bytes::Bytes;
+    use crate::netcode::generate_key;
+    use crate::netcode::token::ConnectTokenPrivate;
+
+    use super::*;
+
+    #[test]
+    fn test_fetch_token() {
+        let server_addr = SocketAddr::from(([127, 0, 0, 1], 5000));
+        let protocol_id = 1;
+        let private_key = generate_key();
+        let auth_server = TokenServer::new(
+            server_addr,
+            protocol_id,
+            private_key,
+            |requester, credentials| {
+                assert!(requester.ip().is_loopback());
+                (credentials == b"secret").then_some((7, [3; USER_DATA_BYTES]))
+            },
+        )
+        .timeout_seconds(2)
+        .start("127.0.0.1:0")
+        .unwrap();
+
+        let mut token = fetch_token(auth_server.local_addr(), b"secret").unwrap();
+        assert_eq!(token.protocol_id, protocol_id);
+        assert_eq!(token.timeout_seconds, 2);
+        assert_eq!(token.server_addresses[0], server_addr);
+
+        // the private part of the token can be read by the game server
+        let private_token = ConnectTokenPrivate::decrypt(
+            &mut token.private_data,
+            protocol_id,
+            token.expire_timestamp,
+            token.nonce,
+            &private_key,
+        )
+        .unwrap();
+        assert_eq!(private_token.client_id, 7);
+        assert_eq!(private_token.user_data, [3; USER_DATA_BYTES]);
+    }
+
+    #[test]
+    fn test_fetch_token_refused() {
+        let auth_server = TokenServer::new(
+            SocketAddr::from(([127, 0, 0, 1], 5000)),
+            1,
+            generate_key(),
+            |_, _| None,
+        )
+        .start("127.0.0.1:0")
+        .unwrap();
+
+        let result = fetch_token(auth_server.local_addr(), b"secret");
+        assert!(matches!(result, Err(Error::Io(e)) if e.kind() == io::ErrorKind::PermissionDenied));
+    }
+
+    #[test]
+    fn test_slow_request_does_not_block_others() {
+        let auth_server = TokenServer::new(
+            SocketAddr::from(([127, 0, 0, 1], 5000)),
+            1,
+            generate_key(),
+            |_, _| Some((7, [0; USER_DATA_BYTES])),
+        )
+        .start("127.0.0.1:0")
+        .unwrap();
+
+        // a client that connects but never sends its credentials
+        let _stalled = TcpStream::connect(auth_server.local_addr()).unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+
+        let start = std::time::Instant::now();
+        let token = fetch_token(auth_server.local_addr(), b"secret").unwrap();
+        assert_eq!(token.protocol_id, 1);
+        // the request is not queued behind the stalled one
+        assert!(start.elapsed() < REQUEST_TIMEOUT / 2);
+    }
+
+    #[test]
+    fn test_token_request() {
+        let auth_server = TokenServer::new(
+            SocketAddr::from(([127, 0, 0, 1], 5000)),
+            1,
+            generate_key(),
+            |_, credentials| (credentials == b"secret").then_some((7, [0; USER_DATA_BYTES])),
+        )
+        .start("127.0.0.1:0")
+        .unwrap();
+
+        // the token is fetched in a background thread
+        let request = TokenRequest::start(auth_server.local_addr(), b"secret".to_vec());
+        let token = loop {
+            if let Some(result) = request.try_recv() {
+                break result.unwrap();
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        };
+        assert_eq!(token.protocol_id, 1);
+
+        // wrong credentials are refused
+        let request = TokenRequest::start(auth_server.local_addr(), b"wrong".to_vec());
+        let result = loop {
+            if let Some(result) = request.try_recv() {
+                break result;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        };
+        assert!(result.is_err());
+    }
+}
@@ -16,12 +16,16 @@ use super::{
     bytes::Bytes,
     error::{Error, Result},
     packet::{
-        DisconnectPacket, KeepAlivePacket, Packet, PayloadPacket, RequestPacket, ResponsePacket,
+        DenyReason, DisconnectPacket, DisconnectReason, KeepAlivePacket, Packet, PayloadPacket,
+        RequestPacket, ResponsePacket,
     },
     replay::ReplayProtection,
+    session::{resumption_token, ResumptionToken},
     token::{ChallengeToken, ConnectToken},
     ClientId, MAX_PACKET_SIZE, MAX_PKT_BUF_SIZE, PACKET_SEND_RATE_SEC,
 };
+#[cfg(feature = "compression")]
+use crate::transport::compression::Compressor;
 
 type Callback<Ctx> = Box<dyn FnMut(ClientState, ClientState, &mut Ctx) + Send + Sync + 'static>;
 
@@ -55,6 +59,7 @@ type Callback<Ctx> = Box<dyn FnMut(ClientState, ClientState, &mut Ctx) + Send +
 pub struct ClientConfig<Ctx> {
     num_disconnect_packets: usize,
     packet_send_rate: f64,
+    protocol_fingerprint: u64,
     context: Ctx,
     on_state_change: Option<Callback<Ctx>>,
 }
@@ -64,6 +69,7 @@ impl Default for ClientConfig<()> {
         Self {
             num_disconnect_packets: 10,
             packet_send_rate: PACKET_SEND_RATE_SEC,
+            protocol_fingerprint: 0,
             context: (),
             on_state_change: None,
         }
@@ -80,6 +86,7 @@ impl<Ctx> ClientConfig<Ctx> {
         Self {
             num_disconnect_packets: 10,
             packet_send_rate: PACKET_SEND_RATE_SEC,
+            protocol_fingerprint: 0,
             context: ctx,
             on_state_change: None,
         }
@@ -96,6 +103,13 @@ impl<Ctx> ClientConfig<Ctx> {
         self.packet_send_rate = rate_seconds;
         self
     }
+    /// Set the fingerprint of the protocol used by the client, that is sent to the server during the handshake.
+    /// The server denies the connection with [`DenyReason::ProtocolMismatch`] if it doesn't match its own. <br>
+    /// The default is `0`.
+    pub fn protocol_fingerprint(mut self, protocol_fingerprint: u64) -> Self {
+        self.protocol_fingerprint = protocol_fingerprint;
+        self
+    }
     /// Set a callback that will be called when the client changes states.
     pub fn on_state_change<F>(mut self, cb: F) -> Self
     where
@@ -143,6 +157,9 @@ pub enum ClientState {
     ChallengeResponseTimedOut,
     /// The server has denied the client's connection request, most likely due to the server being full.
     ConnectionDenied,
+    /// The server has rejected the client's connection request for the given reason
+    /// (for example because the client is banned or running an incompatible version).
+    ConnectionRejected(DenyReason),
     /// The client is disconnected from the server.
     Disconnected,
     /// The client is waiting for a response from the server after sending a connection request packet.
@@ -194,7 +211,12 @@ pub struct Client<Ctx = ()> {
     replay_protection: ReplayProtection,
     should_disconnect: bool,
     should_disconnect_state: ClientState,
+    disconnect_reason: Option<DisconnectReason>,
+    disconnect_payload: Vec<u8>,
+    resumption_token: Option<ResumptionToken>,
     packet_queue: VecDeque<ReadWordBuffer>,
+    #[cfg(feature = "compression")]
+    compressor: Option<Compressor>,
     cfg: ClientConfig<Ctx>,
 }
 
@@ -228,7 +250,12 @@ impl<Ctx> Client<Ctx> {
             replay_protection: ReplayProtection::new(),
             should_disconnect: false,
             should_disconnect_state: ClientState::Disconnected,
+            disconnect_reason: None,
+            disconnect_payload: vec![],
+            resumption_token: None,
             packet_queue: VecDeque::new(),
+            #[cfg(feature = "compression")]
+            compressor: None,
             cfg,
         })
     }
@@ -332,7 +359,12 @@ impl<Ctx> Client<Ctx> {
             }
             ClientState::SendingChallengeResponse => {
                 debug!("client sending connection response packet to server");
-                ResponsePacket::create(self.challenge_token_sequence, self.challenge_token_data)
+                ResponsePacket::create(
+                    self.challenge_token_sequence,
+                    self.challenge_token_data,
+                    self.cfg.protocol_fingerprint,
+                    self.resumption_token,
+                )
             }
             ClientState::Connected => {
                 trace!("client sending connection keep-alive packet to server");
@@ -351,6 +383,16 @@ impl<Ctx> Client<Ctx> {
         self.connect();
         Ok(())
     }
+    /// Create the compressor from the compression config of the `Io`, the first time that it is used
+    #[cfg(feature = "compression")]
+    fn init_compressor(&mut self, io: &Io) -> Result<()> {
+        if self.compressor.is_none() {
+            if let Some(config) = io.compression() {
+                self.compressor = Some(Compressor::new(config.clone(), MAX_PACKET_SIZE)?);
+            }
+        }
+        Ok(())
+    }
     fn send_packet(&mut self, packet: Packet, io: &mut Io) -> Result<()> {
         let mut buf = [0u8; MAX_PKT_BUF_SIZE];
         let size = packet.write(
@@ -375,11 +417,22 @@ impl<Ctx> Client<Ctx> {
         }
         match (packet, self.state) {
             (
-                Packet::Denied(_),
+                Packet::Denied(pkt),
                 ClientState::SendingConnectionRequest | ClientState::SendingChallengeResponse,
             ) => {
+                debug!(reason = ?pkt.reason, "client received connection denied packet from server");
+                if pkt.reason == DenyReason::ProtocolMismatch {
+                    error!(
+                        "server denied the connection: the client and server were built with different protocols"
+                    );
+                }
                 self.should_disconnect = true;
-                self.should_disconnect_state = ClientState::ConnectionDenied;
+                self.should_disconnect_state = match pkt.reason {
+                    DenyReason::Unspecified | DenyReason::ServerFull => {
+                        ClientState::ConnectionDenied
+                    }
+                    reason => ClientState::ConnectionRejected(reason),
+                };
             }
             (Packet::Challenge(pkt), ClientState::SendingConnectionRequest) => {
                 debug!("client received connection challenge packet from server");
@@ -394,15 +447,33 @@ impl<Ctx> Client<Ctx> {
                 debug!("client received connection keep-alive packet from server");
                 self.set_state(ClientState::Connected);
                 self.id = pkt.client_id;
+                // the token of the new session, that can be presented to resume it after a timeout
+                self.resumption_token = Some(resumption_token(
+                    pkt.client_id,
+                    &self.token.client_to_server_key,
+                ));
                 info!("client connected to server");
             }
             (Packet::Payload(pkt), ClientState::Connected) => {
                 trace!("client received payload packet from server");
-                let reader = ReadWordBuffer::start_read(pkt.buf);
+                #[cfg(feature = "compression")]
+                let buf = match self.compressor.as_mut().map(|c| c.decompress(pkt.buf)) {
+                    Some(Ok(buf)) => buf,
+                    Some(Err(e)) => {
+                        error!("could not decompress payload packet: {:?}", e);
+                        return Ok(());
+                    }
+                    None => pkt.buf,
+                };
+                #[cfg(not(feature = "compression"))]
+                let buf = pkt.buf;
+                let reader = ReadWordBuffer::start_read(buf);
                 self.packet_queue.push_back(reader);
             }
-            (Packet::Disconnect(_), ClientState::Connected) => {
-                debug!("client received disconnect packet from server");
+            (Packet::Disconnect(pkt), ClientState::Connected) => {
+                debug!(reason = ?pkt.reason, "client received disconnect packet from server");
+                self.disconnect_reason = Some(pkt.reason);
+                self.disconnect_payload = pkt.payload;
                 self.should_disconnect = true;
                 self.should_disconnect_state = ClientState::Disconnected;
             }
@@ -453,6 +524,15 @@ impl<Ctx> Client<Ctx> {
             }
             _ => return,
         };
+        if self.disconnect_reason.is_none() {
+            self.disconnect_reason = Some(match new_state {
+                ClientState::ConnectTokenExpired => DisconnectReason::TokenExpired,
+                ClientState::ConnectionTimedOut
+                | ClientState::ConnectionRequestTimedOut
+                | ClientState::ChallengeResponseTimedOut => DisconnectReason::TimedOut,
+                _ => DisconnectReason::Unspecified,
+            });
+        }
         self.reset(new_state);
     }
 
@@ -495,11 +575,41 @@ impl<Ctx> Client<Ctx> {
         self.id
     }
 
+    /// Returns the token that can be used to resume the current session if it times out,
+    /// or `None` if the client has never been connected.
+    ///
+    /// The token is presented to the server automatically when reconnecting with [`connect`](Client::connect).
+    /// When connecting with a new connect token, pass it to the new client with
+    /// [`set_resumption_token`](Client::set_resumption_token).
+    pub fn resumption_token(&self) -> Option<ResumptionToken> {
+        self.resumption_token
+    }
+
+    /// Sets the token of a previous session, that will be presented to the server when connecting.
+    ///
+    /// If the server still holds the session (see [`ServerConfig::resumption_grace_period`](super::ServerConfig::resumption_grace_period)),
+    /// it is resumed instead of starting a new one.
+    pub fn set_resumption_token(&mut self, token: Option<ResumptionToken>) {
+        self.resumption_token = token;
+    }
+
+    /// The fingerprint of the protocol that is sent to the server when connecting
+    pub fn protocol_fingerprint(&self) -> u64 {
+        self.cfg.protocol_fingerprint
+    }
+
+    /// Sets the fingerprint of the protocol that is sent to the server when connecting
+    pub(crate) fn set_protocol_fingerprint(&mut self, protocol_fingerprint: u64) {
+        self.cfg.protocol_fingerprint = protocol_fingerprint;
+    }
+
     /// Prepares the client to connect to the server.
     ///
     /// This function does not perform any IO, it only readies the client to send/receive packets on the next call to [`update`](Client::update). <br>
     pub fn connect(&mut self) {
         self.reset_connection();
+        self.disconnect_reason = None;
+        self.disconnect_payload.clear();
         self.set_state(ClientState::SendingConnectionRequest);
         info!(
             "client connecting to server {} [{}/{}]",
@@ -530,7 +640,13 @@ impl<Ctx> Client<Ctx> {
     /// Returns an error if the client can't send or receive packets.
     pub fn try_update(&mut self, delta_ms: f64, io: &mut Io) -> Result<()> {
         self.time += delta_ms;
+        #[cfg(feature = "compression")]
+        self.init_compressor(io)?;
         self.recv_packets(io)?;
+        #[cfg(feature = "compression")]
+        if let Some(compressor) = self.compressor.as_mut() {
+            io.stats.compression += compressor.take_stats();
+        }
         self.send_packets(io)?;
         self.update_state();
         Ok(())
@@ -583,20 +699,45 @@ impl<Ctx> Client<Ctx> {
         if buf.len() > MAX_PACKET_SIZE {
             return Err(Error::SizeMismatch(MAX_PACKET_SIZE, buf.len()));
         }
+        #[cfg(feature = "compression")]
+        {
+            self.init_compressor(io)?;
+            if let Some(mut compressor) = self.compressor.take() {
+                // the compressor is taken out of the client while its buffer is borrowed
+                let result = match compressor.compress(buf) {
+                    Ok(buf) => self.send_packet(PayloadPacket::create(buf), io),
+                    Err(e) => Err(e.into()),
+                };
+                io.stats.compression += compressor.take_stats();
+                self.compressor = Some(compressor);
+                return result;
+            }
+        }
         self.send_packet(PayloadPacket::create(buf), io)?;
         Ok(())
     }
+
     /// Disconnects the client from the server.
     ///
     /// The client will send a number of redundant disconnect packets to the server before transitioning to `Disconnected`.
     pub fn disconnect(&mut self, io: &mut Io) -> Result<()> {
+        self.disconnect_with_reason(DisconnectReason::ClientDisconnected, io)
+    }
+
+    /// Disconnects the client from the server, sending the `reason` to the server.
+    pub(crate) fn disconnect_with_reason(
+        &mut self,
+        reason: DisconnectReason,
+        io: &mut Io,
+    ) -> Result<()> {
         debug!(
-            "client sending {} disconnect packets to server",
-            self.cfg.num_disconnect_packets
+            ?reason,
+            "client sending {} disconnect packets to server", self.cfg.num_disconnect_packets
         );
         for _ in 0..self.cfg.num_disconnect_packets {
-            self.send_packet(DisconnectPacket::create(), io)?;
+            self.send_packet(DisconnectPacket::create(reason, &[]), io)?;
         }
+        self.disconnect_reason = Some(reason);
         self.reset(ClientState::Disconnected);
         Ok(())
     }
@@ -605,6 +746,23 @@ impl<Ctx> Client<Ctx> {
     pub fn state(&self) -> ClientState {
         self.state
     }
+    /// Returns the reason given by the server if it rejected the connection request.
+    pub fn deny_reason(&self) -> Option<DenyReason> {
+        match self.state {
+            ClientState::ConnectionRejected(reason) => Some(reason),
+            _ => None,
+        }
+    }
+    /// Returns the reason of the last disconnection, if the client got disconnected.
+    ///
+    /// The reason is either sent by the server, or filled in locally (for example when the connection times out).
+    pub fn disconnect_reason(&self) -> Option<DisconnectReason> {
+        self.disconnect_reason
+    }
+    /// Returns the payload that the server sent along with the disconnect reason.
+    pub fn disconnect_payload(&self) -> &[u8] {
+        &self.disconnect_payload
+    }
     /// Returns true if the client is in an error state.
     pub fn is_error(&self) -> bool {
         self.state < ClientState::Disconnected
@@ -0,0 +1,207 @@
+//! Limits applied by the netcode server to incoming connection requests:
+//! per-IP rate limiting, ban list and counters of rejected packets.
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+
+use super::ClientId;
+
+/// Limits the number of connection requests that a single IP address can send.
+///
+/// If an IP address sends more than `max_requests` connection requests within `window_secs` seconds,
+/// all its packets will be dropped for `block_secs` seconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimitConfig {
+    /// Maximum number of connection requests allowed per IP address within the window
+    pub max_requests: u32,
+    /// Duration of the window (in seconds)
+    pub window_secs: f64,
+    /// How long (in seconds) an IP address is blocked after exceeding the limit
+    pub block_secs: f64,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            max_requests: 50,
+            window_secs: 1.0,
+            block_secs: 10.0,
+        }
+    }
+}
+
+impl RateLimitConfig {
+    pub fn new(max_requests: u32, window_secs: f64, block_secs: f64) -> Self {
+        Self {
+            max_requests,
+            window_secs,
+            block_secs,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct RateLimitEntry {
+    window_start: f64,
+    requests: u32,
+    blocked_until: f64,
+}
+
+/// Tracks the connection requests received from each IP address
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    config: RateLimitConfig,
+    entries: HashMap<IpAddr, RateLimitEntry>,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Returns true if the address is currently blocked
+    pub(crate) fn is_blocked(&self, ip: &IpAddr, time: f64) -> bool {
+        self.entries
+            .get(ip)
+            .map_or(false, |entry| entry.blocked_until > time)
+    }
+
+    /// Record a connection request from the address.
+    ///
+    /// Returns false if the request exceeds the limit (the address is then blocked)
+    pub(crate) fn check(&mut self, ip: IpAddr, time: f64) -> bool {
+        let config = self.config;
+        let entry = self.entries.entry(ip).or_insert(RateLimitEntry {
+            window_start: time,
+            requests: 0,
+            blocked_until: f64::NEG_INFINITY,
+        });
+        if entry.blocked_until > time {
+            return false;
+        }
+        if time - entry.window_start >= config.window_secs {
+            entry.window_start = time;
+            entry.requests = 0;
+        }
+        entry.requests += 1;
+        if entry.requests > config.max_requests {
+            entry.blocked_until = time + config.block_secs;
+            return false;
+        }
+        true
+    }
+
+    /// Remove the entries that are not blocked and whose window has expired
+    pub(crate) fn cleanup(&mut self, time: f64) {
+        let window = self.config.window_secs;
+        self.entries
+            .retain(|_, entry| entry.blocked_until > time || time - entry.window_start < window);
+    }
+}
+
+/// List of banned IP addresses and client ids.
+///
+/// Packets from banned IP addresses are dropped, and connection requests from banned client ids are denied
+/// with [`DenyReason::Banned`](super::DenyReason::Banned).
+/// Clients that are already connected are disconnected when they get banned.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BanList {
+    ips: HashSet<IpAddr>,
+    client_ids: HashSet<ClientId>,
+}
+
+impl BanList {
+    pub fn ban_ip(&mut self, ip: IpAddr) -> bool {
+        self.ips.insert(ip)
+    }
+    pub fn unban_ip(&mut self, ip: &IpAddr) -> bool {
+        self.ips.remove(ip)
+    }
+    pub fn ban_client_id(&mut self, client_id: ClientId) -> bool {
+        self.client_ids.insert(client_id)
+    }
+    pub fn unban_client_id(&mut self, client_id: &ClientId) -> bool {
+        self.client_ids.remove(client_id)
+    }
+    pub fn is_ip_banned(&self, ip: &IpAddr) -> bool {
+        self.ips.contains(ip)
+    }
+    pub fn is_client_id_banned(&self, client_id: &ClientId) -> bool {
+        self.client_ids.contains(client_id)
+    }
+    pub fn banned_ips(&self) -> impl Iterator<Item = &IpAddr> {
+        self.ips.iter()
+    }
+    pub fn banned_client_ids(&self) -> impl Iterator<Item = &ClientId> {
+        self.client_ids.iter()
+    }
+    pub fn clear(&mut self) {
+        self.ips.clear();
+        self.client_ids.clear();
+    }
+}
+
+/// Number of packets rejected by the server, by reason
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RejectionStats {
+    /// Connection requests denied because the server was full
+    pub server_full: u64,
+    /// Connection requests dropped because the IP address exceeded the rate limit
+    pub rate_limited: u64,
+    /// Packets dropped because the IP address is banned
+    pub banned_ip: u64,
+    /// Connection requests denied because the client id is banned
+    pub banned_client_id: u64,
+    /// Connection requests dropped because the server address was not in the connect token
+    pub server_address_mismatch: u64,
+    /// Connection requests denied by the admission callback
+    pub admission_denied: u64,
+    /// Connection responses denied because the client's protocol fingerprint didn't match the server's
+    pub protocol_mismatch: u64,
+}
+
+impl RejectionStats {
+    /// Total number of rejected packets
+    pub fn total(&self) -> u64 {
+        self.server_full
+            + self.rate_limited
+            + self.banned_ip
+            + self.banned_client_id
+            + self.server_address_mismatch
+            + self.admission_denied
+            + self.protocol_mismatch
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+
+    #[test]
+    fn test_rate_limiter() {
+        let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let other_ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let mut limiter = RateLimiter::new(RateLimitConfig::new(2, 1.0, 5.0));
+
+        assert!(limiter.check(ip, 0.0));
+        assert!(limiter.check(ip, 0.1));
+        // third request in the window: blocked
+        assert!(!limiter.check(ip, 0.2));
+        assert!(limiter.is_blocked(&ip, 0.2));
+        // other addresses are not affected
+        assert!(limiter.check(other_ip, 0.2));
+
+        // still blocked even though the window has expired
+        assert!(!limiter.check(ip, 2.0));
+        // the block has expired
+        assert!(!limiter.is_blocked(&ip, 5.3));
+        assert!(limiter.check(ip, 5.3));
+
+        limiter.cleanup(10.0);
+        assert!(limiter.entries.is_empty());
+    }
+}
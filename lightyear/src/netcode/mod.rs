@@ -20,6 +20,11 @@
  The protocol does not specify how the web backend should be implemented, but it should probably be a typical HTTPS server
  that provides a means for clients to authenticate and request connection tokens.
 
+ A minimal backend is provided by [`TokenServer`]: it hands out tokens over TCP, with a callback deciding which client id
+ to assign to each requester based on the credentials it sent. Clients can fetch a token from it with [`fetch_token`].
+ The tokens contain the keys of the session and are sent in plaintext, so it must only be used on a trusted network
+ or over TLS (see [`TokenServer::handle_stream`] and [`fetch_token_from`]).
+
  The sequence of operations for a client to connect to a server is as follows:
 
  1. The `Client` authenticates with the web backend service. (e.g., by OAuth or some other means)
@@ -123,21 +128,34 @@ loop {
 ```
 */
 
+pub use auth::{
+    fetch_token, fetch_token_from, TokenFn, TokenRequest, TokenServer, TokenServerHandle,
+    MAX_CREDENTIALS_BYTES,
+};
 pub use client::{Client, ClientConfig, ClientState};
 pub use crypto::{generate_key, try_generate_key, Key};
 pub use error::{Error, Result};
-pub use server::{Callback, ClientId, NetcodeServer, Server, ServerConfig};
+pub use limits::{BanList, RateLimitConfig, RejectionStats};
+pub use packet::{DenyReason, DisconnectReason};
+pub use server::{
+    AdmissionCallback, Callback, ClientId, DisconnectCallback, NetcodeServer, Server, ServerConfig,
+    MAX_CLIENTS,
+};
+pub use session::{ResumptionToken, RESUMPTION_TOKEN_BYTES};
 pub use token::{ConnectToken, ConnectTokenBuilder, InvalidTokenError};
 
+mod auth;
 mod bytes;
 mod client;
 mod crypto;
 mod error;
 
 mod free_list;
+mod limits;
 mod packet;
 mod replay;
 mod server;
+mod session;
 mod token;
 
 pub(crate) const MAC_BYTES: usize = 16;
@@ -153,5 +171,11 @@ pub const USER_DATA_BYTES: usize = 256;
 pub const CONNECT_TOKEN_BYTES: usize = 2048;
 /// The maximum size of a packet in bytes.
 pub const MAX_PACKET_SIZE: usize = 1200;
+/// The maximum size of the payload that can be sent along with a [`DisconnectReason`], in bytes.
+pub const MAX_DISCONNECT_PAYLOAD_BYTES: usize = 256;
 /// The version of the netcode protocol implemented by this crate.
-pub const NETCODE_VERSION: &[u8; 13] = b"NETCODE 1.02\0";
+///
+/// This is not the netcode.io 1.02 standard anymore: the denied packet contains a [`DenyReason`], the
+/// disconnect packet contains a [`DisconnectReason`] and a payload, and the connection response packet contains
+/// a resumption token and the protocol fingerprint, so peers using the standard packet format are rejected.
+pub const NETCODE_VERSION: &[u8; 13] = b"NETCODE 1.03\0";
@@ -14,8 +14,9 @@ use super::{
     crypto::{self, Key},
     error::Error as NetcodeError,
     replay::ReplayProtection,
+    session::{ResumptionToken, RESUMPTION_TOKEN_BYTES},
     token::{ChallengeToken, ConnectTokenPrivate},
-    MAC_BYTES, MAX_PKT_BUF_SIZE, NETCODE_VERSION,
+    MAC_BYTES, MAX_DISCONNECT_PAYLOAD_BYTES, MAX_PKT_BUF_SIZE, NETCODE_VERSION,
 };
 
 #[derive(thiserror::Error, Debug)]
@@ -156,22 +157,74 @@ impl Bytes for RequestPacket {
     }
 }
 
-pub struct DeniedPacket {}
+/// The reason why a server denied a connection request, sent to the client inside a [`DeniedPacket`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DenyReason {
+    /// The server did not give any reason
+    Unspecified,
+    /// The server has reached its maximum number of clients
+    ServerFull,
+    /// The client (or its address) is banned from the server
+    Banned,
+    /// The client is running an incompatible version of the game
+    VersionMismatch,
+    /// The client was not assigned to this server (for example by a matchmaker)
+    NotAssigned,
+    /// The client was built with a different protocol (channels, messages or components) than the server
+    ProtocolMismatch,
+    /// An application-defined reason
+    Custom(u16),
+}
+
+impl DenyReason {
+    const UNSPECIFIED: u8 = 0;
+    const SERVER_FULL: u8 = 1;
+    const BANNED: u8 = 2;
+    const VERSION_MISMATCH: u8 = 3;
+    const NOT_ASSIGNED: u8 = 4;
+    const CUSTOM: u8 = 5;
+    const PROTOCOL_MISMATCH: u8 = 6;
+}
+
+pub struct DeniedPacket {
+    pub reason: DenyReason,
+}
 
 impl DeniedPacket {
-    pub fn create() -> Packet<'static> {
-        Packet::Denied(DeniedPacket {})
+    pub fn create(reason: DenyReason) -> Packet<'static> {
+        Packet::Denied(DeniedPacket { reason })
     }
 }
 
 impl Bytes for DeniedPacket {
     type Error = io::Error;
-    fn write_to(&self, _writer: &mut impl WriteBytesExt) -> Result<(), Self::Error> {
+    fn write_to(&self, writer: &mut impl WriteBytesExt) -> Result<(), Self::Error> {
+        match self.reason {
+            DenyReason::Unspecified => writer.write_u8(DenyReason::UNSPECIFIED)?,
+            DenyReason::ServerFull => writer.write_u8(DenyReason::SERVER_FULL)?,
+            DenyReason::Banned => writer.write_u8(DenyReason::BANNED)?,
+            DenyReason::VersionMismatch => writer.write_u8(DenyReason::VERSION_MISMATCH)?,
+            DenyReason::NotAssigned => writer.write_u8(DenyReason::NOT_ASSIGNED)?,
+            DenyReason::ProtocolMismatch => writer.write_u8(DenyReason::PROTOCOL_MISMATCH)?,
+            DenyReason::Custom(code) => {
+                writer.write_u8(DenyReason::CUSTOM)?;
+                writer.write_u16::<LittleEndian>(code)?;
+            }
+        }
         Ok(())
     }
 
-    fn read_from(_reader: &mut impl byteorder::ReadBytesExt) -> Result<Self, io::Error> {
-        Ok(Self {})
+    fn read_from(reader: &mut impl byteorder::ReadBytesExt) -> Result<Self, io::Error> {
+        let reason = match reader.read_u8()? {
+            DenyReason::SERVER_FULL => DenyReason::ServerFull,
+            DenyReason::BANNED => DenyReason::Banned,
+            DenyReason::VERSION_MISMATCH => DenyReason::VersionMismatch,
+            DenyReason::NOT_ASSIGNED => DenyReason::NotAssigned,
+            DenyReason::PROTOCOL_MISMATCH => DenyReason::ProtocolMismatch,
+            DenyReason::CUSTOM => DenyReason::Custom(reader.read_u16::<LittleEndian>()?),
+            _ => DenyReason::Unspecified,
+        };
+        Ok(Self { reason })
     }
 }
 
@@ -208,13 +261,24 @@ impl Bytes for ChallengePacket {
 pub struct ResponsePacket {
     pub sequence: u64,
     pub token: [u8; ChallengeToken::SIZE],
+    /// Fingerprint of the client's protocol, that must match the server's
+    pub protocol_fingerprint: u64,
+    /// Token of a previous session that the client wants to resume
+    pub resumption_token: Option<ResumptionToken>,
 }
 
 impl ResponsePacket {
-    pub fn create(sequence: u64, token_bytes: [u8; ChallengeToken::SIZE]) -> Packet<'static> {
+    pub fn create(
+        sequence: u64,
+        token_bytes: [u8; ChallengeToken::SIZE],
+        protocol_fingerprint: u64,
+        resumption_token: Option<ResumptionToken>,
+    ) -> Packet<'static> {
         Packet::Response(ResponsePacket {
             sequence,
             token: token_bytes,
+            protocol_fingerprint,
+            resumption_token,
         })
     }
 }
@@ -224,6 +288,14 @@ impl Bytes for ResponsePacket {
     fn write_to(&self, writer: &mut impl WriteBytesExt) -> Result<(), Self::Error> {
         writer.write_u64::<LittleEndian>(self.sequence)?;
         writer.write_all(&self.token)?;
+        writer.write_u64::<LittleEndian>(self.protocol_fingerprint)?;
+        match &self.resumption_token {
+            Some(resumption_token) => {
+                writer.write_u8(1)?;
+                writer.write_all(resumption_token)?;
+            }
+            None => writer.write_u8(0)?,
+        }
         Ok(())
     }
 
@@ -231,7 +303,27 @@ impl Bytes for ResponsePacket {
         let sequence = reader.read_u64::<LittleEndian>()?;
         let mut token = [0; ChallengeToken::SIZE];
         reader.read_exact(&mut token)?;
-        Ok(Self { sequence, token })
+        let protocol_fingerprint = reader.read_u64::<LittleEndian>()?;
+        let resumption_token = match reader.read_u8()? {
+            0 => None,
+            1 => {
+                let mut resumption_token = [0; RESUMPTION_TOKEN_BYTES];
+                reader.read_exact(&mut resumption_token)?;
+                Some(resumption_token)
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "invalid resumption token flag",
+                ))
+            }
+        };
+        Ok(Self {
+            sequence,
+            token,
+            protocol_fingerprint,
+            resumption_token,
+        })
     }
 }
 
@@ -268,22 +360,124 @@ impl PayloadPacket<'_> {
     }
 }
 
-pub struct DisconnectPacket {}
+/// The reason why a connection was closed.
+///
+/// It is sent to the remote peer inside the [`DisconnectPacket`]s, or filled in locally
+/// (for example when the connection times out).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DisconnectReason {
+    /// No reason was given
+    Unspecified,
+    /// The client disconnected from the server
+    ClientDisconnected,
+    /// The server kicked the client
+    Kicked,
+    /// The server is shutting down
+    ServerShutdown,
+    /// No packets were received from the remote peer for too long
+    TimedOut,
+    /// The connect token expired
+    TokenExpired,
+    /// The client and the server use incompatible protocols
+    ProtocolMismatch,
+    /// The client got banned from the server
+    Banned,
+    /// The remote peer kept sending data that violated the limits of the connection
+    ProtocolViolation,
+    /// An application-defined reason
+    Custom(u16),
+}
+
+impl DisconnectReason {
+    const UNSPECIFIED: u8 = 0;
+    const CLIENT_DISCONNECTED: u8 = 1;
+    const KICKED: u8 = 2;
+    const SERVER_SHUTDOWN: u8 = 3;
+    const TIMED_OUT: u8 = 4;
+    const TOKEN_EXPIRED: u8 = 5;
+    const PROTOCOL_MISMATCH: u8 = 6;
+    const BANNED: u8 = 7;
+    const CUSTOM: u8 = 8;
+    const PROTOCOL_VIOLATION: u8 = 9;
+}
+
+pub struct DisconnectPacket {
+    pub reason: DisconnectReason,
+    /// Application-defined data sent along with the reason, at most [`MAX_DISCONNECT_PAYLOAD_BYTES`] long
+    pub payload: Vec<u8>,
+}
 
 impl DisconnectPacket {
-    pub fn create() -> Packet<'static> {
-        Packet::Disconnect(Self {})
+    pub fn create(reason: DisconnectReason, payload: &[u8]) -> Packet<'static> {
+        Packet::Disconnect(Self {
+            reason,
+            payload: payload.to_vec(),
+        })
     }
 }
 
 impl Bytes for DisconnectPacket {
     type Error = io::Error;
-    fn write_to(&self, _writer: &mut impl WriteBytesExt) -> Result<(), Self::Error> {
+    fn write_to(&self, writer: &mut impl WriteBytesExt) -> Result<(), Self::Error> {
+        match self.reason {
+            DisconnectReason::Unspecified => writer.write_u8(DisconnectReason::UNSPECIFIED)?,
+            DisconnectReason::ClientDisconnected => {
+                writer.write_u8(DisconnectReason::CLIENT_DISCONNECTED)?
+            }
+            DisconnectReason::Kicked => writer.write_u8(DisconnectReason::KICKED)?,
+            DisconnectReason::ServerShutdown => {
+                writer.write_u8(DisconnectReason::SERVER_SHUTDOWN)?
+            }
+            DisconnectReason::TimedOut => writer.write_u8(DisconnectReason::TIMED_OUT)?,
+            DisconnectReason::TokenExpired => writer.write_u8(DisconnectReason::TOKEN_EXPIRED)?,
+            DisconnectReason::ProtocolMismatch => {
+                writer.write_u8(DisconnectReason::PROTOCOL_MISMATCH)?
+            }
+            DisconnectReason::Banned => writer.write_u8(DisconnectReason::BANNED)?,
+            DisconnectReason::ProtocolViolation => {
+                writer.write_u8(DisconnectReason::PROTOCOL_VIOLATION)?
+            }
+            DisconnectReason::Custom(code) => {
+                writer.write_u8(DisconnectReason::CUSTOM)?;
+                writer.write_u16::<LittleEndian>(code)?;
+            }
+        }
+        if self.payload.len() > MAX_DISCONNECT_PAYLOAD_BYTES {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "disconnect payload is too large",
+            ));
+        }
+        writer.write_u16::<LittleEndian>(self.payload.len() as u16)?;
+        writer.write_all(&self.payload)?;
         Ok(())
     }
 
-    fn read_from(_reader: &mut impl byteorder::ReadBytesExt) -> Result<Self, io::Error> {
-        Ok(Self {})
+    fn read_from(reader: &mut impl byteorder::ReadBytesExt) -> Result<Self, io::Error> {
+        let reason = match reader.read_u8()? {
+            DisconnectReason::CLIENT_DISCONNECTED => DisconnectReason::ClientDisconnected,
+            DisconnectReason::KICKED => DisconnectReason::Kicked,
+            DisconnectReason::SERVER_SHUTDOWN => DisconnectReason::ServerShutdown,
+            DisconnectReason::TIMED_OUT => DisconnectReason::TimedOut,
+            DisconnectReason::TOKEN_EXPIRED => DisconnectReason::TokenExpired,
+            DisconnectReason::PROTOCOL_MISMATCH => DisconnectReason::ProtocolMismatch,
+            DisconnectReason::BANNED => DisconnectReason::Banned,
+            DisconnectReason::PROTOCOL_VIOLATION => DisconnectReason::ProtocolViolation,
+            DisconnectReason::CUSTOM => {
+                DisconnectReason::Custom(reader.read_u16::<LittleEndian>()?)
+            }
+            _ => DisconnectReason::Unspecified,
+        };
+        let len = reader.read_u16::<LittleEndian>()? as usize;
+        if len > MAX_DISCONNECT_PAYLOAD_BYTES {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "disconnect payload is too large",
+            ));
+        }
+        let mut payload = vec![0; len];
+        reader.read_exact(&mut payload)?;
+        Ok(Self { reason, payload })
     }
 }
 
@@ -580,29 +774,42 @@ mod tests {
     fn denied_packet() {
         let packet_key = generate_key();
         let protocol_id = 0x1234_5678_9abc_def0;
-        let sequence = 0u64;
         let mut replay_protection = ReplayProtection::new();
 
-        let packet = Packet::Denied(DeniedPacket {});
-
-        let mut buf = [0u8; MAX_PKT_BUF_SIZE];
-        let size = packet
-            .write(&mut buf, sequence, &packet_key, protocol_id)
+        for (sequence, reason) in [
+            DenyReason::Unspecified,
+            DenyReason::ServerFull,
+            DenyReason::Banned,
+            DenyReason::VersionMismatch,
+            DenyReason::NotAssigned,
+            DenyReason::ProtocolMismatch,
+            DenyReason::Custom(0xBEEF),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let packet = DeniedPacket::create(reason);
+
+            let mut buf = [0u8; MAX_PKT_BUF_SIZE];
+            let size = packet
+                .write(&mut buf, sequence as u64, &packet_key, protocol_id)
+                .unwrap();
+
+            let packet = Packet::read(
+                &mut buf[..size],
+                protocol_id,
+                0,
+                packet_key,
+                Some(&mut replay_protection),
+                0xff,
+            )
             .unwrap();
 
-        let packet = Packet::read(
-            &mut buf[..size],
-            protocol_id,
-            0,
-            packet_key,
-            Some(&mut replay_protection),
-            0xff,
-        )
-        .unwrap();
-
-        let Packet::Denied(_denied_pkt) = packet else {
-            panic!("wrong packet type");
-        };
+            let Packet::Denied(denied_pkt) = packet else {
+                panic!("wrong packet type");
+            };
+            assert_eq!(denied_pkt.reason, reason);
+        }
     }
 
     #[test]
@@ -671,32 +878,86 @@ mod tests {
     }
 
     #[test]
-    pub fn disconnect_packet() {
+    pub fn response_packet() {
         let packet_key = generate_key();
         let protocol_id = 0x1234_5678_9abc_def0;
-        let sequence = 0u64;
+        let token = [7u8; ChallengeToken::SIZE];
         let mut replay_protection = ReplayProtection::new();
 
-        let packet = Packet::Disconnect(DisconnectPacket {});
-
-        let mut buf = [0u8; MAX_PKT_BUF_SIZE];
-        let size = packet
-            .write(&mut buf, sequence, &packet_key, protocol_id)
+        for (sequence, resumption_token) in [(0u64, None), (1, Some([3u8; RESUMPTION_TOKEN_BYTES]))]
+        {
+            let packet = ResponsePacket::create(sequence, token, 42, resumption_token);
+
+            let mut buf = [0u8; MAX_PKT_BUF_SIZE];
+            let size = packet
+                .write(&mut buf, sequence, &packet_key, protocol_id)
+                .unwrap();
+
+            let packet = Packet::read(
+                &mut buf[..size],
+                protocol_id,
+                0,
+                packet_key,
+                Some(&mut replay_protection),
+                0xff,
+            )
             .unwrap();
 
-        let packet = Packet::read(
-            &mut buf[..size],
-            protocol_id,
-            0,
-            packet_key,
-            Some(&mut replay_protection),
-            0xff,
-        )
-        .unwrap();
+            let Packet::Response(response_pkt) = packet else {
+                panic!("wrong packet type");
+            };
 
-        let Packet::Disconnect(_disconnect_pkt) = packet else {
-            panic!("wrong packet type");
-        };
+            assert_eq!(response_pkt.sequence, sequence);
+            assert_eq!(response_pkt.token, token);
+            assert_eq!(response_pkt.protocol_fingerprint, 42);
+            assert_eq!(response_pkt.resumption_token, resumption_token);
+        }
+    }
+
+    #[test]
+    pub fn disconnect_packet() {
+        let packet_key = generate_key();
+        let protocol_id = 0x1234_5678_9abc_def0;
+        let mut replay_protection = ReplayProtection::new();
+
+        for (sequence, reason) in [
+            DisconnectReason::Unspecified,
+            DisconnectReason::ClientDisconnected,
+            DisconnectReason::Kicked,
+            DisconnectReason::ServerShutdown,
+            DisconnectReason::TimedOut,
+            DisconnectReason::TokenExpired,
+            DisconnectReason::ProtocolMismatch,
+            DisconnectReason::Banned,
+            DisconnectReason::ProtocolViolation,
+            DisconnectReason::Custom(3),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let packet = DisconnectPacket::create(reason, b"server restarting");
+
+            let mut buf = [0u8; MAX_PKT_BUF_SIZE];
+            let size = packet
+                .write(&mut buf, sequence as u64, &packet_key, protocol_id)
+                .unwrap();
+
+            let packet = Packet::read(
+                &mut buf[..size],
+                protocol_id,
+                0,
+                packet_key,
+                Some(&mut replay_protection),
+                0xff,
+            )
+            .unwrap();
+
+            let Packet::Disconnect(disconnect_pkt) = packet else {
+                panic!("wrong packet type");
+            };
+            assert_eq!(disconnect_pkt.reason, reason);
+            assert_eq!(disconnect_pkt.payload, b"server restarting");
+        }
     }
 
     #[test]
@@ -17,22 +17,34 @@ use super::{
     crypto::{self, Key},
     error::{Error, Result},
     generate_key,
+    limits::{BanList, RateLimitConfig, RateLimiter, RejectionStats},
     packet::{
-        ChallengePacket, DeniedPacket, DisconnectPacket, KeepAlivePacket, Packet, PayloadPacket,
-        RequestPacket, ResponsePacket,
+        ChallengePacket, DeniedPacket, DenyReason, DisconnectPacket, DisconnectReason,
+        KeepAlivePacket, Packet, PayloadPacket, RequestPacket, ResponsePacket,
     },
     replay::ReplayProtection,
+    session::{resumption_token, SuspendedSessions},
     token::{ChallengeToken, ConnectToken, ConnectTokenBuilder, ConnectTokenPrivate},
-    MAC_BYTES, MAX_PACKET_SIZE, MAX_PKT_BUF_SIZE, PACKET_SEND_RATE_SEC,
+    MAC_BYTES, MAX_DISCONNECT_PAYLOAD_BYTES, MAX_PACKET_SIZE, MAX_PKT_BUF_SIZE,
+    PACKET_SEND_RATE_SEC, USER_DATA_BYTES,
 };
+#[cfg(feature = "compression")]
+use crate::transport::compression::Compressor;
 
 pub const MAX_CLIENTS: usize = 256;
 
+/// Maximum number of connect tokens that the server remembers
+const MAX_TOKEN_ENTRIES: usize = MAX_CLIENTS * 8;
+
 #[derive(Clone, Copy)]
 struct TokenEntry {
     time: f64,
     mac: [u8; 16],
     addr: SocketAddr,
+    client_id: ClientId,
+    /// Decision of the admission callback, so that it is only called once per token
+    /// even though the client sends several connection requests during the handshake
+    admission: Option<std::result::Result<(), DenyReason>>,
 }
 
 struct TokenEntries {
@@ -43,12 +55,18 @@ impl TokenEntries {
     fn new() -> Self {
         Self { inner: Vec::new() }
     }
-    fn find_or_insert(&mut self, entry: TokenEntry) -> bool {
+    /// Returns the index of the entry with the same token, or of the newly inserted entry.
+    ///
+    /// Returns None if the token was already used from a different address, unless `allow_new_address` is true
+    /// (the client might be resuming its suspended session from a new address).
+    /// The address of the entry is not changed: it only changes once the client proved that it owns the session
+    /// (see [`TokenEntries::set_addr`]).
+    fn find_or_insert(&mut self, entry: TokenEntry, allow_new_address: bool) -> Option<usize> {
         let (mut oldest, mut matching) = (None, None);
         let mut oldest_time = f64::INFINITY;
         // Perform a linear search for the oldest and matching entries at the same time
         for (idx, saved_entry) in self.inner.iter().enumerate() {
-            if entry.time < oldest_time {
+            if saved_entry.time < oldest_time {
                 oldest_time = saved_entry.time;
                 oldest = Some(idx);
             }
@@ -56,18 +74,27 @@ impl TokenEntries {
                 matching = Some(idx);
             }
         }
-        let Some(oldest) = oldest else {
-            // If there is no oldest entry then the list is empty, so just insert the entry
-            self.inner.push(entry);
-            return true;
-        };
         if let Some(matching) = matching {
             // Allow reusing tokens only if the address matches
-            self.inner[matching].addr == entry.addr
-        } else {
-            // If there is no matching entry, replace the oldest one
-            self.inner[oldest] = entry;
-            true
+            return (allow_new_address || self.inner[matching].addr == entry.addr)
+                .then_some(matching);
+        }
+        match oldest {
+            // If the list is full, replace the oldest entry
+            Some(oldest) if self.inner.len() >= MAX_TOKEN_ENTRIES => {
+                self.inner[oldest] = entry;
+                Some(oldest)
+            }
+            _ => {
+                self.inner.push(entry);
+                Some(self.inner.len() - 1)
+            }
+        }
+    }
+    /// Change the address of the tokens of a client that resumed its session from a new address
+    fn set_addr(&mut self, client_id: ClientId, addr: SocketAddr) {
+        for entry in self.inner.iter_mut().filter(|e| e.client_id == client_id) {
+            entry.addr = addr;
         }
     }
 }
@@ -182,6 +209,18 @@ impl ConnectionCache {
         self.clients.remove(&client_id);
     }
 
+    /// Move the connection of the client to a new address
+    fn set_addr(&mut self, client_id: ClientId, addr: SocketAddr) {
+        let Some(conn) = self.clients.get_mut(&client_id) else {
+            return;
+        };
+        if self.client_id_map.get(&conn.addr) == Some(&client_id) {
+            self.client_id_map.remove(&conn.addr);
+        }
+        conn.addr = addr;
+        self.client_id_map.insert(addr, client_id);
+    }
+
     fn ids(&self) -> Vec<ClientId> {
         self.clients.keys().cloned().collect()
     }
@@ -201,12 +240,42 @@ impl ConnectionCache {
 
 pub type Callback<Ctx> = Box<dyn FnMut(ClientId, &mut Ctx) + Send + Sync + 'static>;
 
+/// Callback called when a client gets disconnected, with the reason of the disconnection
+/// and the payload that was sent along with it.
+pub type DisconnectCallback<Ctx> =
+    Box<dyn FnMut(ClientId, DisconnectReason, &[u8], &mut Ctx) + Send + Sync + 'static>;
+
+/// Callback that decides whether a client is allowed to connect to the server.
+///
+/// It receives the client id, the client's address and the user data from the client's connect token,
+/// and returns `Err(reason)` to deny the connection. The reason is sent back to the client.
+pub type AdmissionCallback<Ctx> = Box<
+    dyn FnMut(
+            ClientId,
+            SocketAddr,
+            &[u8; USER_DATA_BYTES],
+            &mut Ctx,
+        ) -> std::result::Result<(), DenyReason>
+        + Send
+        + Sync
+        + 'static,
+>;
+
 /// Configuration for a server.
 ///
 /// * `num_disconnect_packets` - The number of redundant disconnect packets that will be sent to a client when the server is disconnecting it.
 /// * `keep_alive_send_rate` - The rate at which keep-alive packets will be sent to clients.
 /// * `on_connect` - A callback that will be called when a client is connected to the server.
 /// * `on_disconnect` - A callback that will be called when a client is disconnected from the server.
+/// * `admission` - A callback that decides whether a client is allowed to connect to the server.
+/// * `max_clients` - The maximum number of clients that can be connected at the same time.
+/// * `rate_limit` - Optional per-IP rate limit on connection requests.
+/// * `ban_list` - IP addresses and client ids that are not allowed to connect.
+/// * `check_server_address` - Whether connect tokens must contain the server's address.
+/// * `resumption_grace_period` - How long the session of a client that timed out can still be resumed.
+/// * `protocol_fingerprint` - If set, clients whose protocol fingerprint is different are denied.
+/// * `on_suspend` - A callback that will be called when the session of a client that timed out is suspended.
+/// * `on_resume` - A callback that will be called when a client resumes its suspended session.
 ///
 /// # Example
 /// ```
@@ -229,7 +298,17 @@ pub struct ServerConfig<Ctx> {
     keep_alive_send_rate: f64,
     context: Ctx,
     on_connect: Option<Callback<Ctx>>,
-    on_disconnect: Option<Callback<Ctx>>,
+    on_disconnect: Option<DisconnectCallback<Ctx>>,
+    admission: Option<AdmissionCallback<Ctx>>,
+    max_clients: usize,
+    rate_limit: Option<RateLimitConfig>,
+    ban_list: BanList,
+    check_server_address: bool,
+    public_addresses: Vec<SocketAddr>,
+    resumption_grace_period: Option<f64>,
+    protocol_fingerprint: Option<u64>,
+    on_suspend: Option<Callback<Ctx>>,
+    on_resume: Option<Callback<Ctx>>,
 }
 
 impl Default for ServerConfig<()> {
@@ -240,6 +319,16 @@ impl Default for ServerConfig<()> {
             context: (),
             on_connect: None,
             on_disconnect: None,
+            admission: None,
+            max_clients: MAX_CLIENTS,
+            rate_limit: None,
+            ban_list: BanList::default(),
+            check_server_address: false,
+            public_addresses: vec![],
+            resumption_grace_period: None,
+            protocol_fingerprint: None,
+            on_suspend: None,
+            on_resume: None,
         }
     }
 }
@@ -257,6 +346,16 @@ impl<Ctx> ServerConfig<Ctx> {
             context: ctx,
             on_connect: None,
             on_disconnect: None,
+            admission: None,
+            max_clients: MAX_CLIENTS,
+            rate_limit: None,
+            ban_list: BanList::default(),
+            check_server_address: false,
+            public_addresses: vec![],
+            resumption_grace_period: None,
+            protocol_fingerprint: None,
+            on_suspend: None,
+            on_resume: None,
         }
     }
     /// Set the number of redundant disconnect packets that will be sent to a client when the server is disconnecting it. <br>
@@ -283,16 +382,106 @@ impl<Ctx> ServerConfig<Ctx> {
         self
     }
     /// Provide a callback that will be called when a client is disconnected from the server. <br>
-    /// The callback will be called with the client index and the context that was provided (provide a `None` context if you don't need one).
+    /// The callback will be called with the client index, the reason of the disconnection, the payload sent along with the reason
+    /// and the context that was provided (provide a `None` context if you don't need one).
     ///
     /// See [`ServerConfig`] for an example.
     pub fn on_disconnect<F>(mut self, cb: F) -> Self
     where
-        F: FnMut(ClientId, &mut Ctx) + Send + Sync + 'static,
+        F: FnMut(ClientId, DisconnectReason, &[u8], &mut Ctx) + Send + Sync + 'static,
     {
         self.on_disconnect = Some(Box::new(cb));
         self
     }
+    /// Provide a callback that will be called when a client sends a valid connection request. <br>
+    /// The callback receives the client id, the client's address, the user data from the connect token
+    /// and the context that was provided. Returning `Err(reason)` denies the connection, and the reason
+    /// is sent to the client in the denied packet.
+    /// The callback is called once per connect token: its decision is reused for the connection requests
+    /// that the client keeps sending during the handshake.
+    pub fn admission<F>(mut self, cb: F) -> Self
+    where
+        F: FnMut(
+                ClientId,
+                SocketAddr,
+                &[u8; USER_DATA_BYTES],
+                &mut Ctx,
+            ) -> std::result::Result<(), DenyReason>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.admission = Some(Box::new(cb));
+        self
+    }
+    /// Set the maximum number of clients that can be connected to the server at the same time. <br>
+    /// The default is [`MAX_CLIENTS`].
+    pub fn max_clients(mut self, max_clients: usize) -> Self {
+        self.max_clients = max_clients;
+        self
+    }
+    /// Limit the rate of connection requests per IP address. <br>
+    /// By default there is no rate limit.
+    pub fn rate_limit(mut self, rate_limit: RateLimitConfig) -> Self {
+        self.rate_limit = Some(rate_limit);
+        self
+    }
+    /// Set the initial list of banned IP addresses and client ids. <br>
+    /// The list can be edited at runtime with [`NetcodeServer::ban_list_mut`].
+    pub fn ban_list(mut self, ban_list: BanList) -> Self {
+        self.ban_list = ban_list;
+        self
+    }
+    /// Set whether the server should ignore connection requests whose connect token does not contain
+    /// the server's address. <br>
+    /// The server's addresses are the [`public_addresses`](ServerConfig::public_addresses) if they are set, otherwise
+    /// the local addresses of its [`Io`]: set the public addresses if the server is behind a NAT or a port mapping. <br>
+    /// The default is `false`.
+    pub fn check_server_address(mut self, check: bool) -> Self {
+        self.check_server_address = check;
+        self
+    }
+    /// Set the public addresses of the server, that connect tokens must contain. <br>
+    /// If empty (the default), the local addresses of the server's [`Io`] (of all its transports) are used instead; a local address with
+    /// an unspecified IP (`0.0.0.0`) matches any token address with the same port.
+    pub fn public_addresses(mut self, addresses: Vec<SocketAddr>) -> Self {
+        self.public_addresses = addresses;
+        self
+    }
+    /// Keep the session of a client that timed out for `grace_period_seconds`, during which the client can
+    /// reconnect and resume it by presenting its [`ResumptionToken`](super::ResumptionToken). <br>
+    /// While the session is suspended, `on_suspend` is called instead of `on_disconnect`; if the client resumes it,
+    /// `on_resume` is called instead of `on_connect`, otherwise `on_disconnect` is called when the grace period is over. <br>
+    /// By default sessions are not resumable.
+    pub fn resumption_grace_period(mut self, grace_period_seconds: f64) -> Self {
+        self.resumption_grace_period = Some(grace_period_seconds);
+        self
+    }
+    /// Set the fingerprint of the protocol used by the server. Clients that send a different fingerprint
+    /// during the handshake are denied with [`DenyReason::ProtocolMismatch`]. <br>
+    /// By default the fingerprint is not checked.
+    pub fn protocol_fingerprint(mut self, protocol_fingerprint: u64) -> Self {
+        self.protocol_fingerprint = Some(protocol_fingerprint);
+        self
+    }
+    /// Provide a callback that will be called when the session of a client that timed out is suspended. <br>
+    /// See [`ServerConfig::resumption_grace_period`].
+    pub fn on_suspend<F>(mut self, cb: F) -> Self
+    where
+        F: FnMut(ClientId, &mut Ctx) + Send + Sync + 'static,
+    {
+        self.on_suspend = Some(Box::new(cb));
+        self
+    }
+    /// Provide a callback that will be called when a client resumes its suspended session. <br>
+    /// See [`ServerConfig::resumption_grace_period`].
+    pub fn on_resume<F>(mut self, cb: F) -> Self
+    where
+        F: FnMut(ClientId, &mut Ctx) + Send + Sync + 'static,
+    {
+        self.on_resume = Some(Box::new(cb));
+        self
+    }
 }
 
 /// The `netcode` server.
@@ -338,6 +527,13 @@ pub struct NetcodeServer<Ctx = ()> {
     protocol_id: u64,
     conn_cache: ConnectionCache,
     token_entries: TokenEntries,
+    /// Addresses that the server's `Io` is bound to
+    local_addrs: Vec<SocketAddr>,
+    rate_limiter: Option<RateLimiter>,
+    rejection_stats: RejectionStats,
+    suspended_sessions: SuspendedSessions,
+    #[cfg(feature = "compression")]
+    compressor: Option<Compressor>,
     cfg: ServerConfig<Ctx>,
 }
 
@@ -356,6 +552,12 @@ impl NetcodeServer {
             challenge_key: crypto::generate_key(),
             conn_cache: ConnectionCache::new(0.0),
             token_entries: TokenEntries::new(),
+            local_addrs: vec![],
+            rate_limiter: None,
+            rejection_stats: RejectionStats::default(),
+            suspended_sessions: SuspendedSessions::default(),
+            #[cfg(feature = "compression")]
+            compressor: None,
             cfg: ServerConfig::default(),
         };
         // info!("server started on {}", server.io.local_addr());
@@ -390,6 +592,12 @@ impl<Ctx> NetcodeServer<Ctx> {
             challenge_key: crypto::generate_key(),
             conn_cache: ConnectionCache::new(0.0),
             token_entries: TokenEntries::new(),
+            local_addrs: vec![],
+            rate_limiter: cfg.rate_limit.map(RateLimiter::new),
+            rejection_stats: RejectionStats::default(),
+            suspended_sessions: SuspendedSessions::default(),
+            #[cfg(feature = "compression")]
+            compressor: None,
             cfg,
         };
         // info!("server started on {}", server.addr());
@@ -408,11 +616,32 @@ impl<Ctx> NetcodeServer<Ctx> {
             cb(client_id, &mut self.cfg.context)
         }
     }
-    fn on_disconnect(&mut self, client_id: ClientId) {
+    fn on_disconnect(&mut self, client_id: ClientId, reason: DisconnectReason, payload: &[u8]) {
         if let Some(cb) = self.cfg.on_disconnect.as_mut() {
+            cb(client_id, reason, payload, &mut self.cfg.context)
+        }
+    }
+    fn on_suspend(&mut self, client_id: ClientId) {
+        if let Some(cb) = self.cfg.on_suspend.as_mut() {
+            cb(client_id, &mut self.cfg.context)
+        }
+    }
+    fn on_resume(&mut self, client_id: ClientId) {
+        if let Some(cb) = self.cfg.on_resume.as_mut() {
             cb(client_id, &mut self.cfg.context)
         }
     }
+    fn admit(
+        &mut self,
+        client_id: ClientId,
+        addr: SocketAddr,
+        user_data: &[u8; USER_DATA_BYTES],
+    ) -> std::result::Result<(), DenyReason> {
+        match self.cfg.admission.as_mut() {
+            Some(cb) => cb(client_id, addr, user_data, &mut self.cfg.context),
+            None => Ok(()),
+        }
+    }
     fn touch_client(&mut self, client_id: Option<ClientId>) -> Result<()> {
         let Some(id) = client_id else {
             return Ok(());
@@ -448,16 +677,30 @@ impl<Ctx> NetcodeServer<Ctx> {
             Packet::Payload(packet) => {
                 self.touch_client(client_id)?;
                 if let Some(idx) = client_id {
+                    #[cfg(feature = "compression")]
+                    let buf = match self.compressor.as_mut().map(|c| c.decompress(packet.buf)) {
+                        Some(Ok(buf)) => buf,
+                        Some(Err(e)) => {
+                            error!(
+                                "could not decompress payload packet from client {idx}: {:?}",
+                                e
+                            );
+                            return Ok(());
+                        }
+                        None => packet.buf,
+                    };
+                    #[cfg(not(feature = "compression"))]
+                    let buf = packet.buf;
                     self.conn_cache
                         .packet_queue
-                        .push_back((ReadWordBuffer::start_read(packet.buf), idx));
+                        .push_back((ReadWordBuffer::start_read(buf), idx));
                 }
                 Ok(())
             }
-            Packet::Disconnect(_) => {
+            Packet::Disconnect(packet) => {
                 if let Some(idx) = client_id {
-                    debug!("server disconnected client {idx}");
-                    self.on_disconnect(idx);
+                    debug!(reason = ?packet.reason, "server disconnected client {idx}");
+                    self.on_disconnect(idx, packet.reason, &packet.payload);
                     self.conn_cache.remove(idx);
                 }
                 Ok(())
@@ -478,6 +721,16 @@ impl<Ctx> NetcodeServer<Ctx> {
         self.sequence += 1;
         Ok(())
     }
+    /// Create the compressor from the compression config of the `Io`, the first time that it is used
+    #[cfg(feature = "compression")]
+    fn init_compressor(&mut self, io: &Io) -> Result<()> {
+        if self.compressor.is_none() {
+            if let Some(config) = io.compression() {
+                self.compressor = Some(Compressor::new(config.clone(), MAX_PACKET_SIZE)?);
+            }
+        }
+        Ok(())
+    }
     fn send_to_client(
         &mut self,
         packet: Packet,
@@ -508,17 +761,19 @@ impl<Ctx> NetcodeServer<Ctx> {
             debug!("server ignored connection request. failed to read connect token");
             return Ok(());
         };
-        // TODO
-        // if !token
-        //     .server_addresses
-        //     .iter()
-        //     .any(|(_, addr)| addr == io.local_addr())
-        // {
-        //     debug!(
-        //         "server ignored connection request. server address not in connect token whitelist"
-        //     );
-        //     return Ok(());
-        // };
+        if self.cfg.check_server_address
+            && !token
+                .server_addresses
+                .iter()
+                .any(|(_, addr)| self.is_server_address(&addr))
+        {
+            debug!(
+                token_addresses = ?token.server_addresses.iter().map(|(_, addr)| addr).collect::<Vec<_>>(),
+                "server ignored connection request from {from_addr}. server address not in connect token whitelist"
+            );
+            self.rejection_stats.server_address_mismatch += 1;
+            return Ok(());
+        };
         if self
             .conn_cache
             .find_by_addr(&from_addr)
@@ -538,19 +793,62 @@ impl<Ctx> NetcodeServer<Ctx> {
         let entry = TokenEntry {
             time: self.time,
             addr: from_addr,
+            client_id: token.client_id,
             mac: packet.token_data
                 [ConnectTokenPrivate::SIZE - MAC_BYTES..ConnectTokenPrivate::SIZE]
                 .try_into()
                 .expect("valid MAC size"),
+            admission: None,
         };
-        if !self.token_entries.find_or_insert(entry) {
+        // the token of a suspended session can be used from a new address, to resume the session after the client's address changed.
+        // The handshake can only be completed with the keys of the token, and the new address is only accepted
+        // if the connection response presents the resumption token of the session (see `process_connection_response`)
+        let resuming = self.suspended_sessions.contains(&token.client_id);
+        let Some(entry_idx) = self.token_entries.find_or_insert(entry, resuming) else {
             debug!("server ignored connection request. connect token has already been used");
             return Ok(());
         };
-        if self.num_connected_clients() >= MAX_CLIENTS {
+        if self.cfg.ban_list.is_client_id_banned(&token.client_id) {
+            debug!(
+                "server denied connection request. client {} is banned",
+                token.client_id
+            );
+            self.rejection_stats.banned_client_id += 1;
+            self.send_to_addr(
+                DeniedPacket::create(DenyReason::Banned),
+                from_addr,
+                token.server_to_client_key,
+                sender,
+            )?;
+            return Ok(());
+        };
+        if self.num_connected_clients() >= self.cfg.max_clients {
             debug!("server denied connection request. server is full");
+            self.rejection_stats.server_full += 1;
             self.send_to_addr(
-                DeniedPacket::create(),
+                DeniedPacket::create(DenyReason::ServerFull),
+                from_addr,
+                token.server_to_client_key,
+                sender,
+            )?;
+            return Ok(());
+        };
+        let admission = match self.token_entries.inner[entry_idx].admission {
+            Some(admission) => admission,
+            None => {
+                let admission = self.admit(token.client_id, from_addr, &token.user_data);
+                self.token_entries.inner[entry_idx].admission = Some(admission);
+                admission
+            }
+        };
+        if let Err(reason) = admission {
+            debug!(
+                ?reason,
+                "server denied connection request from client {}", token.client_id
+            );
+            self.rejection_stats.admission_denied += 1;
+            self.send_to_addr(
+                DeniedPacket::create(reason),
                 from_addr,
                 token.server_to_client_key,
                 sender,
@@ -603,11 +901,30 @@ impl<Ctx> NetcodeServer<Ctx> {
             debug!("server ignored connection request. a client with this id is already connected");
             return Ok(());
         };
+        if let Some(protocol_fingerprint) = self.cfg.protocol_fingerprint {
+            if packet.protocol_fingerprint != protocol_fingerprint {
+                let send_key = conn.send_key;
+                error!(
+                    expected = protocol_fingerprint,
+                    actual = packet.protocol_fingerprint,
+                    "server denied connection response from client {id}. the client was built with a different protocol"
+                );
+                self.rejection_stats.protocol_mismatch += 1;
+                self.send_to_addr(
+                    DeniedPacket::create(DenyReason::ProtocolMismatch),
+                    from_addr,
+                    send_key,
+                    sender,
+                )?;
+                return Ok(());
+            }
+        };
 
-        if self.num_connected_clients() >= MAX_CLIENTS {
+        if self.num_connected_clients() >= self.cfg.max_clients {
             debug!("server denied connection response. server is full");
+            self.rejection_stats.server_full += 1;
             self.send_to_addr(
-                DeniedPacket::create(),
+                DeniedPacket::create(DenyReason::ServerFull),
                 from_addr,
                 self.conn_cache
                     .clients
@@ -618,6 +935,33 @@ impl<Ctx> NetcodeServer<Ctx> {
             )?;
             return Ok(());
         };
+        // the request packet is not encrypted and can be replayed by anyone, so a client whose session is suspended
+        // is only allowed to connect from a different address if it proves that it owns the session
+        let new_address = self
+            .suspended_sessions
+            .addr(&id)
+            .map_or(false, |addr| addr != from_addr);
+        if new_address && packet.resumption_token != self.suspended_sessions.token(&id) {
+            debug!("server ignored connection response from {from_addr}. client {id} must resume its session to change address");
+            return Ok(());
+        }
+        let resumed = match self.suspended_sessions.remove(&id) {
+            Some(token) if packet.resumption_token == Some(token) => true,
+            Some(_) => {
+                // the client started a new session: the suspended one is over
+                debug!("server could not resume the session of client {id}");
+                self.on_disconnect(id, DisconnectReason::TimedOut, &[]);
+                false
+            }
+            None => false,
+        };
+        if new_address {
+            debug!("server resumed the session of client {id} from a new address {from_addr}");
+            self.token_entries.set_addr(id, from_addr);
+        }
+        if conn.addr != from_addr {
+            self.conn_cache.set_addr(id, from_addr);
+        }
         let client = self
             .conn_cache
             .clients
@@ -631,7 +975,35 @@ impl<Ctx> NetcodeServer<Ctx> {
             id, challenge_token.client_id
         );
         self.send_to_client(KeepAlivePacket::create(id), id, sender)?;
-        self.on_connect(id);
+        if resumed {
+            debug!("server resumed the session of client {id}");
+            self.on_resume(id);
+        } else {
+            self.on_connect(id);
+        }
+        Ok(())
+    }
+    fn is_server_address(&self, addr: &SocketAddr) -> bool {
+        if !self.cfg.public_addresses.is_empty() {
+            return self.cfg.public_addresses.contains(addr);
+        }
+        self.local_addrs.iter().any(|local_addr| {
+            local_addr.port() == addr.port()
+                && (local_addr.ip().is_unspecified() || local_addr.ip() == addr.ip())
+        })
+    }
+    fn disconnect_banned(&mut self, io: &mut Io) -> Result<()> {
+        for id in self.connected_client_ids() {
+            let Some(conn) = self.conn_cache.clients.get(&id) else {
+                continue;
+            };
+            if self.cfg.ban_list.is_client_id_banned(&id)
+                || self.cfg.ban_list.is_ip_banned(&conn.addr.ip())
+            {
+                debug!("server disconnecting banned client {id}");
+                self.disconnect(id, DisconnectReason::Banned, &[], io)?;
+            }
+        }
         Ok(())
     }
     fn check_for_timeouts(&mut self) {
@@ -646,11 +1018,24 @@ impl<Ctx> NetcodeServer<Ctx> {
                 && client.last_receive_time + (client.timeout as f64) < self.time
             {
                 debug!("server timed out client {id}");
-                self.on_disconnect(id);
+                if let Some(grace_period) = self.cfg.resumption_grace_period {
+                    let token = resumption_token(id, &client.receive_key);
+                    self.suspended_sessions
+                        .suspend(id, token, client.addr, self.time + grace_period);
+                    self.on_suspend(id);
+                } else {
+                    self.on_disconnect(id, DisconnectReason::TimedOut, &[]);
+                }
                 self.conn_cache.remove(id);
             }
         }
     }
+    fn expire_sessions(&mut self) {
+        for id in self.suspended_sessions.expire(self.time) {
+            debug!("server expired the suspended session of client {id}");
+            self.on_disconnect(id, DisconnectReason::TimedOut, &[]);
+        }
+    }
     fn send_packets(&mut self, io: &mut Io) -> Result<()> {
         for id in self.conn_cache.ids() {
             let Some(client) = self.conn_cache.clients.get_mut(&id) else {
@@ -679,6 +1064,23 @@ impl<Ctx> NetcodeServer<Ctx> {
             // Too small to be a packet
             return Ok(());
         }
+        if self.cfg.ban_list.is_ip_banned(&addr.ip()) {
+            trace!("server ignored packet from banned address {addr}");
+            self.rejection_stats.banned_ip += 1;
+            return Ok(());
+        }
+        if let Some(rate_limiter) = self.rate_limiter.as_mut() {
+            let allowed = if buf[0] == Packet::REQUEST {
+                rate_limiter.check(addr.ip(), self.time)
+            } else {
+                !rate_limiter.is_blocked(&addr.ip(), self.time)
+            };
+            if !allowed {
+                trace!("server ignored packet from rate-limited address {addr}");
+                self.rejection_stats.rate_limited += 1;
+                return Ok(());
+            }
+        }
         let (key, replay_protection) = match self.conn_cache.find_by_addr(&addr) {
             // Regardless of whether an entry in the connection cache exists for the client or not,
             // if the packet is a connection request we need to use the server's private key to decrypt it.
@@ -752,8 +1154,22 @@ impl<Ctx> NetcodeServer<Ctx> {
     pub fn try_update(&mut self, delta_ms: f64, io: &mut Io) -> Result<()> {
         self.time += delta_ms;
         self.conn_cache.update(delta_ms);
+        if self.local_addrs != io.local_addrs() {
+            self.local_addrs = io.local_addrs().to_vec();
+        }
+        if let Some(rate_limiter) = self.rate_limiter.as_mut() {
+            rate_limiter.cleanup(self.time);
+        }
+        self.expire_sessions();
+        #[cfg(feature = "compression")]
+        self.init_compressor(io)?;
         let (sender, receiver) = io.split();
         self.recv_packets(sender, receiver)?;
+        #[cfg(feature = "compression")]
+        if let Some(compressor) = self.compressor.as_mut() {
+            io.stats.compression += compressor.take_stats();
+        }
+        self.disconnect_banned(io)?;
         self.send_packets(io)?;
         self.check_for_timeouts();
         Ok(())
@@ -810,6 +1226,20 @@ impl<Ctx> NetcodeServer<Ctx> {
             // send a keep-alive packet to the client to confirm the connection
             self.send_to_client(KeepAlivePacket::create(client_id), client_id, io)?;
         }
+        #[cfg(feature = "compression")]
+        {
+            self.init_compressor(io)?;
+            if let Some(mut compressor) = self.compressor.take() {
+                // the compressor is taken out of the server while its buffer is borrowed
+                let result = match compressor.compress(buf) {
+                    Ok(buf) => self.send_to_client(PayloadPacket::create(buf), client_id, io),
+                    Err(e) => Err(e.into()),
+                };
+                io.stats.compression += compressor.take_stats();
+                self.compressor = Some(compressor);
+                return result;
+            }
+        }
         let packet = PayloadPacket::create(buf);
         self.send_to_client(packet, client_id, io)
     }
@@ -863,31 +1293,64 @@ impl<Ctx> NetcodeServer<Ctx> {
     }
     /// Disconnects a client.
     ///
-    /// The server will send a number of redundant disconnect packets to the client, and then remove its connection info.
-    pub fn disconnect(&mut self, client_id: ClientId, io: &mut Io) -> Result<()> {
+    /// The server will send a number of redundant disconnect packets to the client, containing the `reason` and the
+    /// `payload`, and then remove its connection info.
+    ///
+    /// The `payload` must be smaller than [`MAX_DISCONNECT_PAYLOAD_BYTES`].
+    pub fn disconnect(
+        &mut self,
+        client_id: ClientId,
+        reason: DisconnectReason,
+        payload: &[u8],
+        io: &mut Io,
+    ) -> Result<()> {
+        if payload.len() > MAX_DISCONNECT_PAYLOAD_BYTES {
+            return Err(Error::SizeMismatch(
+                MAX_DISCONNECT_PAYLOAD_BYTES,
+                payload.len(),
+            ));
+        }
+        if self.suspended_sessions.remove(&client_id).is_some() {
+            // the client cannot be reached: just end its session
+            debug!(
+                ?reason,
+                "server ending the suspended session of client {client_id}"
+            );
+            self.on_disconnect(client_id, reason, payload);
+            return Ok(());
+        }
         let Some(conn) = self.conn_cache.clients.get_mut(&client_id) else {
             return Ok(());
         };
         if !conn.is_connected() {
             return Ok(());
         }
-        debug!("server disconnecting client {client_id}");
+        debug!(?reason, "server disconnecting client {client_id}");
         for _ in 0..self.cfg.num_disconnect_packets {
-            self.send_to_client(DisconnectPacket::create(), client_id, io)?;
+            self.send_to_client(DisconnectPacket::create(reason, payload), client_id, io)?;
         }
-        self.on_disconnect(client_id);
+        self.on_disconnect(client_id, reason, payload);
         self.conn_cache.remove(client_id);
         Ok(())
     }
-    /// Disconnects all clients.
-    pub fn disconnect_all(&mut self, io: &mut Io) -> Result<()> {
+    /// Disconnects all clients, with the same `reason` and `payload`
+    /// (for example [`DisconnectReason::ServerShutdown`] when the server is stopping).
+    pub fn disconnect_all(
+        &mut self,
+        reason: DisconnectReason,
+        payload: &[u8],
+        io: &mut Io,
+    ) -> Result<()> {
         debug!("server disconnecting all clients");
+        for id in self.suspended_sessions.ids() {
+            self.disconnect(id, reason, payload, io)?;
+        }
         for id in self.conn_cache.ids() {
             let Some(conn) = self.conn_cache.clients.get_mut(&id) else {
                 continue;
             };
             if conn.is_connected() {
-                self.disconnect(id, io)?;
+                self.disconnect(id, reason, payload, io)?;
             }
         }
         Ok(())
@@ -915,16 +1378,40 @@ impl<Ctx> NetcodeServer<Ctx> {
             .count()
     }
 
+    /// Returns true if the client timed out and its session can still be resumed. <br>
+    /// See [`ServerConfig::resumption_grace_period`].
+    pub fn is_suspended(&self, client_id: ClientId) -> bool {
+        self.suspended_sessions.contains(&client_id)
+    }
+
     /// Gets the address of a client.
     pub fn client_addr(&self, client_id: ClientId) -> Option<SocketAddr> {
         self.conn_cache.clients.get(&client_id).map(|c| c.addr)
     }
+
+    /// Gets the list of banned IP addresses and client ids.
+    pub fn ban_list(&self) -> &BanList {
+        &self.cfg.ban_list
+    }
+
+    /// Gets a mutable reference to the list of banned IP addresses and client ids. <br>
+    /// Connected clients that get banned will be disconnected during the next [`update`](NetcodeServer::update).
+    pub fn ban_list_mut(&mut self) -> &mut BanList {
+        &mut self.cfg.ban_list
+    }
+
+    /// Gets the number of packets that were rejected by the server, by reason.
+    pub fn rejection_stats(&self) -> &RejectionStats {
+        &self.rejection_stats
+    }
 }
 
 #[derive(Default)]
 pub(crate) struct NetcodeServerContext {
     pub(crate) connections: Vec<ClientId>,
-    pub(crate) disconnections: Vec<ClientId>,
+    pub(crate) disconnections: Vec<(ClientId, DisconnectReason, Vec<u8>)>,
+    pub(crate) suspensions: Vec<ClientId>,
+    pub(crate) resumptions: Vec<ClientId>,
 }
 
 #[derive(Resource)]
@@ -933,7 +1420,7 @@ pub struct Server {
 }
 
 impl Server {
-    pub(crate) fn new(config: NetcodeConfig) -> Self {
+    pub(crate) fn new(config: NetcodeConfig, protocol_fingerprint: u64) -> Self {
         let private_key = config.private_key.unwrap_or(generate_key());
         // create context
         let context = NetcodeServerContext::default();
@@ -941,11 +1428,31 @@ impl Server {
             .on_connect(|id, ctx| {
                 ctx.connections.push(id);
             })
-            .on_disconnect(|id, ctx| {
-                ctx.disconnections.push(id);
+            .on_disconnect(|id, reason, payload, ctx| {
+                ctx.disconnections.push((id, reason, payload.to_vec()));
+            })
+            .on_suspend(|id, ctx| {
+                ctx.suspensions.push(id);
+            })
+            .on_resume(|id, ctx| {
+                ctx.resumptions.push(id);
             });
+        if let Some(admission) = config.admission {
+            cfg = cfg.admission(move |id, addr, user_data, _| admission(id, addr, user_data));
+        }
+        if let Some(rate_limit) = config.rate_limit {
+            cfg = cfg.rate_limit(rate_limit);
+        }
         cfg = cfg.keep_alive_send_rate(config.keep_alive_send_rate);
         cfg = cfg.num_disconnect_packets(config.num_disconnect_packets);
+        cfg = cfg.max_clients(config.max_clients);
+        cfg = cfg.ban_list(config.ban_list);
+        cfg = cfg.check_server_address(config.check_server_address);
+        cfg = cfg.public_addresses(config.public_addresses);
+        cfg = cfg.protocol_fingerprint(protocol_fingerprint);
+        if let Some(grace_period) = config.resumption_grace_period {
+            cfg = cfg.resumption_grace_period(grace_period.as_secs_f64());
+        }
         let server = NetcodeServer::with_config(config.protocol_id, private_key, cfg)
             .expect("Could not create server netcode");
 
@@ -956,13 +1463,67 @@ impl Server {
         self.server.connected_client_ids()
     }
 
+    /// The fingerprint of the protocol that clients must present when connecting, if it is checked
+    pub(crate) fn protocol_fingerprint(&self) -> Option<u64> {
+        self.server.cfg.protocol_fingerprint
+    }
+
+    /// Sets the fingerprint of the protocol that clients must present when connecting
+    pub(crate) fn set_protocol_fingerprint(&mut self, protocol_fingerprint: u64) {
+        self.server.cfg.protocol_fingerprint = Some(protocol_fingerprint);
+    }
+
     pub fn recv(&mut self) -> Option<(ReadWordBuffer, ClientId)> {
         self.server.recv()
     }
+
+    /// Gets the list of banned IP addresses and client ids.
+    pub fn ban_list(&self) -> &BanList {
+        self.server.ban_list()
+    }
+
+    /// Gets a mutable reference to the list of banned IP addresses and client ids,
+    /// for example to ban a client from a system with `ResMut<NetServer>`.
+    pub fn ban_list_mut(&mut self) -> &mut BanList {
+        self.server.ban_list_mut()
+    }
+
+    /// Gets the number of packets that were rejected by the server, by reason.
+    pub fn rejection_stats(&self) -> &RejectionStats {
+        self.server.rejection_stats()
+    }
+
+    /// Returns true if the client timed out and its session can still be resumed.
+    pub fn is_suspended(&self, client_id: ClientId) -> bool {
+        self.server.is_suspended(client_id)
+    }
     pub(crate) fn send(&mut self, buf: &[u8], client_id: ClientId, io: &mut Io) -> Result<()> {
         self.server.send(buf, client_id, io)
     }
 
+    /// Disconnects a client, sending it the `reason` and the `payload`.
+    ///
+    /// The disconnection will be processed (and the `DisconnectEvent` emitted) during the next update.
+    pub fn disconnect(
+        &mut self,
+        client_id: ClientId,
+        reason: DisconnectReason,
+        payload: &[u8],
+        io: &mut Io,
+    ) -> Result<()> {
+        self.server.disconnect(client_id, reason, payload, io)
+    }
+
+    /// Disconnects all clients, sending them the `reason` and the `payload`.
+    pub fn disconnect_all(
+        &mut self,
+        reason: DisconnectReason,
+        payload: &[u8],
+        io: &mut Io,
+    ) -> Result<()> {
+        self.server.disconnect_all(reason, payload, io)
+    }
+
     pub(crate) fn try_update(
         &mut self,
         delta_ms: f64,
@@ -989,3 +1550,545 @@ impl Server {
     //         .unwrap()
     // }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::netcode::{generate_key, Client, ClientState};
+    use crate::prelude::{IoConfig, TransportConfig};
+    use crate::transport::LOCAL_SOCKET;
+
+    use super::*;
+
+    /// Create the client and server `Io`s, connected with local channels
+    fn local_ios() -> (Io, Io) {
+        let (client_config, server_config) = local_io_configs();
+        (client_config.get_io(), server_config.get_io())
+    }
+
+    fn local_io_configs() -> (IoConfig, IoConfig) {
+        let (from_server_send, from_server_recv) = crossbeam_channel::unbounded();
+        let (to_server_send, to_server_recv) = crossbeam_channel::unbounded();
+        let client_config = IoConfig::from_transport(TransportConfig::LocalChannel {
+            send: to_server_send,
+            recv: from_server_recv,
+        });
+        let server_config = IoConfig::from_transport(TransportConfig::Channels {
+            channels: vec![(LOCAL_SOCKET, to_server_recv, from_server_send)],
+        });
+        (client_config, server_config)
+    }
+
+    /// Run the connection process between a netcode client and a netcode server
+    fn run_connection<Ctx>(
+        server: &mut NetcodeServer<Ctx>,
+        client: &mut Client,
+        client_io: &mut Io,
+        server_io: &mut Io,
+    ) {
+        client.connect();
+        for _ in 0..10 {
+            client.try_update(0.1, client_io).unwrap();
+            server.try_update(0.1, server_io).unwrap();
+            if !client.is_pending() {
+                break;
+            }
+        }
+    }
+
+    /// Connect a netcode client to a netcode server over local channels,
+    /// and return the client
+    fn connect<Ctx>(server: &mut NetcodeServer<Ctx>, user_data: [u8; USER_DATA_BYTES]) -> Client {
+        let (mut client_io, mut server_io) = local_ios();
+        let token = server
+            .token(1, LOCAL_SOCKET)
+            .user_data(user_data)
+            .generate()
+            .unwrap();
+        let mut client = Client::new(&token.try_into_bytes().unwrap()).unwrap();
+        run_connection(server, &mut client, &mut client_io, &mut server_io);
+        client
+    }
+
+    /// The payloads are compressed before they get encrypted
+    #[cfg(feature = "compression")]
+    #[test]
+    fn test_compression() {
+        use crate::serialize::reader::BitRead;
+        use crate::transport::compression::CompressionConfig;
+        use std::num::NonZeroUsize;
+
+        fn read_payload(reader: &mut ReadWordBuffer, len: usize) -> Vec<u8> {
+            reader
+                .read_bytes(NonZeroUsize::new(len).unwrap())
+                .unwrap()
+                .to_vec()
+        }
+
+        let compression = CompressionConfig::zstd(3);
+        let (client_config, server_config) = local_io_configs();
+        let mut client_io = client_config
+            .with_compression(compression.clone())
+            .unwrap()
+            .get_io();
+        let mut server_io = server_config
+            .with_compression(compression)
+            .unwrap()
+            .get_io();
+        let mut server = NetcodeServer::new(0, generate_key()).unwrap();
+        let token = server.token(1, LOCAL_SOCKET).generate().unwrap();
+        let mut client = Client::new(&token.try_into_bytes().unwrap()).unwrap();
+        run_connection(&mut server, &mut client, &mut client_io, &mut server_io);
+        assert!(client.is_connected());
+
+        // client to server
+        let payload = [1, 2, 3, 4].repeat(100);
+        client.send(&payload, &mut client_io).unwrap();
+        server.try_update(0.1, &mut server_io).unwrap();
+        let (mut received, client_id) = server.recv().unwrap();
+        assert_eq!(client_id, 1);
+        assert_eq!(read_payload(&mut received, payload.len()), payload);
+
+        // server to client
+        let bytes_sent = server_io.stats().bytes_sent;
+        server.send(&payload, 1, &mut server_io).unwrap();
+        // the encrypted packet is smaller than the payload
+        assert!(server_io.stats().bytes_sent - bytes_sent < payload.len());
+        client.try_update(0.1, &mut client_io).unwrap();
+        let mut received = client.recv().unwrap();
+        assert_eq!(read_payload(&mut received, payload.len()), payload);
+
+        let stats = &server_io.stats().compression;
+        assert_eq!(stats.uncompressed_bytes_sent, payload.len());
+        assert_eq!(stats.uncompressed_bytes_received, payload.len());
+        assert!(stats.compressed_bytes_sent < payload.len());
+        assert_eq!(
+            client_io.stats().compression.compressed_bytes_sent,
+            stats.compressed_bytes_received
+        );
+    }
+
+    #[test]
+    fn test_admission_accept() {
+        let cfg = ServerConfig::with_context(()).admission(|_, _, user_data, _| {
+            if user_data[0] == 1 {
+                Ok(())
+            } else {
+                Err(DenyReason::VersionMismatch)
+            }
+        });
+        let mut server = NetcodeServer::with_config(0, generate_key(), cfg).unwrap();
+        let mut user_data = [0; USER_DATA_BYTES];
+        user_data[0] = 1;
+        let client = connect(&mut server, user_data);
+        assert_eq!(client.state(), ClientState::Connected);
+        assert_eq!(server.connected_client_ids(), vec![1]);
+    }
+
+    #[test]
+    fn test_admission_deny() {
+        let cfg = ServerConfig::with_context(0).admission(|client_id, addr, _, calls| {
+            *calls += 1;
+            assert_eq!(client_id, 1);
+            assert_eq!(addr, LOCAL_SOCKET);
+            Err(DenyReason::Custom(7))
+        });
+        let mut server = NetcodeServer::with_config(0, generate_key(), cfg).unwrap();
+        let client = connect(&mut server, [0; USER_DATA_BYTES]);
+        assert_eq!(
+            client.state(),
+            ClientState::ConnectionRejected(DenyReason::Custom(7))
+        );
+        assert_eq!(client.deny_reason(), Some(DenyReason::Custom(7)));
+        assert!(client.is_error());
+        assert_eq!(server.num_connected_clients(), 0);
+        assert!(server.cfg.context > 0);
+    }
+
+    #[test]
+    fn test_admission_called_once_per_token() {
+        let cfg = ServerConfig::with_context(0).admission(|_, _, _, calls| {
+            *calls += 1;
+            Err(DenyReason::Custom(7))
+        });
+        let mut server = NetcodeServer::with_config(0, generate_key(), cfg).unwrap();
+        let (mut client_io, mut server_io) = local_ios();
+        let token = server.token(1, LOCAL_SOCKET).generate().unwrap();
+        let mut client = Client::new(&token.try_into_bytes().unwrap()).unwrap();
+        client.connect();
+        // the client sends several connection requests before the server processes them
+        for _ in 0..3 {
+            client.try_update(0.1, &mut client_io).unwrap();
+        }
+        server.try_update(0.1, &mut server_io).unwrap();
+        assert!(server.rejection_stats().admission_denied > 1);
+        assert_eq!(server.cfg.context, 1);
+    }
+
+    #[test]
+    fn test_max_clients() {
+        let cfg = ServerConfig::with_context(()).max_clients(0);
+        let mut server = NetcodeServer::with_config(0, generate_key(), cfg).unwrap();
+        let client = connect(&mut server, [0; USER_DATA_BYTES]);
+        assert_eq!(client.state(), ClientState::ConnectionDenied);
+        assert!(server.rejection_stats().server_full > 0);
+    }
+
+    #[test]
+    fn test_banned_client_id() {
+        let mut ban_list = BanList::default();
+        ban_list.ban_client_id(1);
+        let cfg = ServerConfig::with_context(()).ban_list(ban_list);
+        let mut server = NetcodeServer::with_config(0, generate_key(), cfg).unwrap();
+        let client = connect(&mut server, [0; USER_DATA_BYTES]);
+        assert_eq!(
+            client.state(),
+            ClientState::ConnectionRejected(DenyReason::Banned)
+        );
+        assert!(server.rejection_stats().banned_client_id > 0);
+    }
+
+    #[test]
+    fn test_ban_connected_client() {
+        let mut server = NetcodeServer::new(0, generate_key()).unwrap();
+        let (mut client_io, mut server_io) = local_ios();
+        let token = server.token(1, LOCAL_SOCKET).generate().unwrap();
+        let mut client = Client::new(&token.try_into_bytes().unwrap()).unwrap();
+        run_connection(&mut server, &mut client, &mut client_io, &mut server_io);
+        assert_eq!(client.state(), ClientState::Connected);
+
+        // ban the client's ip at runtime: the client gets disconnected
+        server.ban_list_mut().ban_ip(LOCAL_SOCKET.ip());
+        server.try_update(0.1, &mut server_io).unwrap();
+        client.try_update(0.1, &mut client_io).unwrap();
+        assert_eq!(server.num_connected_clients(), 0);
+        assert_eq!(client.state(), ClientState::Disconnected);
+
+        // further packets from the client are dropped
+        client.connect();
+        client.try_update(0.1, &mut client_io).unwrap();
+        server.try_update(0.1, &mut server_io).unwrap();
+        assert!(server.rejection_stats().banned_ip > 0);
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let cfg = ServerConfig::with_context(()).rate_limit(RateLimitConfig::new(0, 1.0, 10.0));
+        let mut server = NetcodeServer::with_config(0, generate_key(), cfg).unwrap();
+        let client = connect(&mut server, [0; USER_DATA_BYTES]);
+        assert!(client.is_pending());
+        assert_eq!(server.num_connected_clients(), 0);
+        assert!(server.rejection_stats().rate_limited > 0);
+    }
+
+    #[test]
+    fn test_server_address_check() {
+        let token_addr = SocketAddr::from(([10, 0, 0, 1], 5000));
+
+        // the server address is not checked by default
+        let mut server = NetcodeServer::new(0, generate_key()).unwrap();
+        let (mut client_io, mut server_io) = local_ios();
+        let token = server
+            .token(1, LOCAL_SOCKET)
+            .internal_addresses(token_addr)
+            .unwrap()
+            .generate()
+            .unwrap();
+        let mut client = Client::new(&token.try_into_bytes().unwrap()).unwrap();
+        run_connection(&mut server, &mut client, &mut client_io, &mut server_io);
+        assert_eq!(client.state(), ClientState::Connected);
+
+        // the token's internal addresses don't contain the server's address: the request is ignored
+        let cfg = ServerConfig::with_context(()).check_server_address(true);
+        let mut server = NetcodeServer::with_config(0, generate_key(), cfg).unwrap();
+        let (mut client_io, mut server_io) = local_ios();
+        let token = server
+            .token(1, LOCAL_SOCKET)
+            .internal_addresses(token_addr)
+            .unwrap()
+            .generate()
+            .unwrap();
+        let mut client = Client::new(&token.try_into_bytes().unwrap()).unwrap();
+        run_connection(&mut server, &mut client, &mut client_io, &mut server_io);
+        assert!(client.is_pending());
+        assert!(server.rejection_stats().server_address_mismatch > 0);
+
+        // the token contains one of the server's public addresses
+        let cfg = ServerConfig::with_context(())
+            .check_server_address(true)
+            .public_addresses(vec![token_addr]);
+        let mut server = NetcodeServer::with_config(0, generate_key(), cfg).unwrap();
+        let (mut client_io, mut server_io) = local_ios();
+        let token = server
+            .token(1, LOCAL_SOCKET)
+            .internal_addresses(token_addr)
+            .unwrap()
+            .generate()
+            .unwrap();
+        let mut client = Client::new(&token.try_into_bytes().unwrap()).unwrap();
+        run_connection(&mut server, &mut client, &mut client_io, &mut server_io);
+        assert_eq!(client.state(), ClientState::Connected);
+
+        // the token contains the address of the second transport of a multiplexed `Io`
+        let cfg = ServerConfig::with_context(()).check_server_address(true);
+        let mut server = NetcodeServer::with_config(0, generate_key(), cfg).unwrap();
+        let (from_server_send, from_server_recv) = crossbeam_channel::unbounded();
+        let (to_server_send, to_server_recv) = crossbeam_channel::unbounded();
+        let mut client_io = IoConfig::from_transport(TransportConfig::LocalChannel {
+            send: to_server_send,
+            recv: from_server_recv,
+        })
+        .get_io();
+        let mut server_io = IoConfig::from_transport(TransportConfig::Multi(vec![
+            TransportConfig::UdpSocket(SocketAddr::from(([127, 0, 0, 1], 0))),
+            TransportConfig::Channels {
+                channels: vec![(LOCAL_SOCKET, to_server_recv, from_server_send)],
+            },
+        ]))
+        .get_io();
+        assert_ne!(server_io.local_addr(), LOCAL_SOCKET);
+        let token = server.token(1, LOCAL_SOCKET).generate().unwrap();
+        let mut client = Client::new(&token.try_into_bytes().unwrap()).unwrap();
+        run_connection(&mut server, &mut client, &mut client_io, &mut server_io);
+        assert_eq!(client.state(), ClientState::Connected);
+    }
+
+    #[test]
+    fn test_disconnect_reason() {
+        let cfg = ServerConfig::with_context(vec![]).on_disconnect(|id, reason, payload, ctx| {
+            ctx.push((id, reason, payload.to_vec()));
+        });
+        let mut server = NetcodeServer::with_config(0, generate_key(), cfg).unwrap();
+        let (mut client_io, mut server_io) = local_ios();
+        let token = server.token(1, LOCAL_SOCKET).generate().unwrap();
+        let mut client = Client::new(&token.try_into_bytes().unwrap()).unwrap();
+
+        // the server sends the reason to the client
+        run_connection(&mut server, &mut client, &mut client_io, &mut server_io);
+        assert_eq!(client.state(), ClientState::Connected);
+        server
+            .disconnect(1, DisconnectReason::Custom(4), b"restart", &mut server_io)
+            .unwrap();
+        client.try_update(0.1, &mut client_io).unwrap();
+        assert_eq!(client.state(), ClientState::Disconnected);
+        assert_eq!(
+            client.disconnect_reason(),
+            Some(DisconnectReason::Custom(4))
+        );
+        assert_eq!(client.disconnect_payload(), b"restart");
+        assert_eq!(
+            server.cfg.context.pop(),
+            Some((1, DisconnectReason::Custom(4), b"restart".to_vec()))
+        );
+
+        // the client sends the reason to the server
+        run_connection(&mut server, &mut client, &mut client_io, &mut server_io);
+        assert_eq!(client.state(), ClientState::Connected);
+        client.disconnect(&mut client_io).unwrap();
+        server.try_update(0.1, &mut server_io).unwrap();
+        assert_eq!(
+            server.cfg.context.pop(),
+            Some((1, DisconnectReason::ClientDisconnected, vec![]))
+        );
+
+        // the payload is too large
+        assert!(server
+            .disconnect(
+                1,
+                DisconnectReason::Kicked,
+                &[0; MAX_DISCONNECT_PAYLOAD_BYTES + 1],
+                &mut server_io
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_timeout_reason() {
+        let mut server = NetcodeServer::new(0, generate_key()).unwrap();
+        let (mut client_io, mut server_io) = local_ios();
+        let token = server
+            .token(1, LOCAL_SOCKET)
+            .timeout_seconds(1)
+            .generate()
+            .unwrap();
+        let mut client = Client::new(&token.try_into_bytes().unwrap()).unwrap();
+        run_connection(&mut server, &mut client, &mut client_io, &mut server_io);
+        assert_eq!(client.state(), ClientState::Connected);
+
+        // the server stops sending packets
+        for _ in 0..20 {
+            client.try_update(0.1, &mut client_io).unwrap();
+        }
+        assert_eq!(client.state(), ClientState::ConnectionTimedOut);
+        assert_eq!(client.disconnect_reason(), Some(DisconnectReason::TimedOut));
+    }
+
+    /// Server that records the connection events of its clients
+    fn session_server(grace_period: f64) -> NetcodeServer<Vec<&'static str>> {
+        let cfg = ServerConfig::with_context(vec![])
+            .resumption_grace_period(grace_period)
+            .on_connect(|_, events| events.push("connect"))
+            .on_disconnect(|_, _, _, events| events.push("disconnect"))
+            .on_suspend(|_, events| events.push("suspend"))
+            .on_resume(|_, events| events.push("resume"));
+        NetcodeServer::with_config(0, generate_key(), cfg).unwrap()
+    }
+
+    #[test]
+    fn test_session_resumption() {
+        let mut server = session_server(5.0);
+        let (mut client_io, mut server_io) = local_ios();
+        let token = server
+            .token(1, LOCAL_SOCKET)
+            .timeout_seconds(1)
+            .generate()
+            .unwrap();
+        let mut client = Client::new(&token.try_into_bytes().unwrap()).unwrap();
+        run_connection(&mut server, &mut client, &mut client_io, &mut server_io);
+        assert_eq!(client.state(), ClientState::Connected);
+        assert!(client.resumption_token().is_some());
+
+        // the client stops sending packets: its session is suspended instead of being disconnected
+        for _ in 0..20 {
+            server.try_update(0.1, &mut server_io).unwrap();
+        }
+        assert!(server.is_suspended(1));
+        assert!(server.connected_client_ids().is_empty());
+        assert_eq!(server.cfg.context, vec!["connect", "suspend"]);
+
+        // the client reconnects within the grace period and resumes its session
+        run_connection(&mut server, &mut client, &mut client_io, &mut server_io);
+        assert_eq!(client.state(), ClientState::Connected);
+        assert!(!server.is_suspended(1));
+        assert_eq!(server.connected_client_ids(), vec![1]);
+        assert_eq!(server.cfg.context, vec!["connect", "suspend", "resume"]);
+    }
+
+    /// Create the `Io`s of a client at [`LOCAL_SOCKET`], of a client at `new_addr`, and of the server
+    fn two_address_ios(new_addr: SocketAddr) -> (Io, Io, Io) {
+        let (from_server_send, from_server_recv) = crossbeam_channel::unbounded();
+        let (to_server_send, to_server_recv) = crossbeam_channel::unbounded();
+        let (new_from_server_send, new_from_server_recv) = crossbeam_channel::unbounded();
+        let (new_to_server_send, new_to_server_recv) = crossbeam_channel::unbounded();
+        let client_io = IoConfig::from_transport(TransportConfig::LocalChannel {
+            send: to_server_send,
+            recv: from_server_recv,
+        })
+        .get_io();
+        let new_client_io = IoConfig::from_transport(TransportConfig::LocalChannel {
+            send: new_to_server_send,
+            recv: new_from_server_recv,
+        })
+        .get_io();
+        let server_io = IoConfig::from_transport(TransportConfig::Channels {
+            channels: vec![
+                (LOCAL_SOCKET, to_server_recv, from_server_send),
+                (new_addr, new_to_server_recv, new_from_server_send),
+            ],
+        })
+        .get_io();
+        (client_io, new_client_io, server_io)
+    }
+
+    /// A client whose address changed resumes its session with its original connect token
+    #[test]
+    fn test_session_resumption_new_address() {
+        let mut server = session_server(5.0);
+        let new_addr = SocketAddr::from(([127, 0, 0, 1], 1234));
+        let (mut client_io, mut new_client_io, mut server_io) = two_address_ios(new_addr);
+        let token = server
+            .token(1, LOCAL_SOCKET)
+            .timeout_seconds(1)
+            .generate()
+            .unwrap();
+        let mut client = Client::new(&token.try_into_bytes().unwrap()).unwrap();
+        run_connection(&mut server, &mut client, &mut client_io, &mut server_io);
+        assert_eq!(client.state(), ClientState::Connected);
+
+        for _ in 0..20 {
+            server.try_update(0.1, &mut server_io).unwrap();
+        }
+        assert!(server.is_suspended(1));
+
+        // the same token is used from the new address
+        run_connection(&mut server, &mut client, &mut new_client_io, &mut server_io);
+        assert_eq!(client.state(), ClientState::Connected);
+        assert_eq!(server.client_addr(1), Some(new_addr));
+        assert_eq!(server.cfg.context, vec!["connect", "suspend", "resume"]);
+    }
+
+    /// A replayed connect token cannot move a suspended session to another address
+    #[test]
+    fn test_session_resumption_hijack() {
+        let mut server = session_server(5.0);
+        let new_addr = SocketAddr::from(([127, 0, 0, 1], 1234));
+        let (mut client_io, mut new_client_io, mut server_io) = two_address_ios(new_addr);
+        let token_bytes = server
+            .token(1, LOCAL_SOCKET)
+            .timeout_seconds(1)
+            .generate()
+            .unwrap()
+            .try_into_bytes()
+            .unwrap();
+        let mut client = Client::new(&token_bytes).unwrap();
+        run_connection(&mut server, &mut client, &mut client_io, &mut server_io);
+        for _ in 0..20 {
+            server.try_update(0.1, &mut server_io).unwrap();
+        }
+        assert!(server.is_suspended(1));
+
+        // the token is replayed from another address, without the resumption token of the session
+        let mut attacker = Client::new(&token_bytes).unwrap();
+        run_connection(
+            &mut server,
+            &mut attacker,
+            &mut new_client_io,
+            &mut server_io,
+        );
+        assert_ne!(attacker.state(), ClientState::Connected);
+        assert!(server.is_suspended(1));
+        assert_eq!(server.cfg.context, vec!["connect", "suspend"]);
+
+        // the client can still resume its session from its address
+        run_connection(&mut server, &mut client, &mut client_io, &mut server_io);
+        assert_eq!(client.state(), ClientState::Connected);
+        assert_eq!(server.client_addr(1), Some(LOCAL_SOCKET));
+        assert_eq!(server.cfg.context, vec!["connect", "suspend", "resume"]);
+    }
+
+    #[test]
+    fn test_session_expired() {
+        let mut server = session_server(1.0);
+        let (mut client_io, mut server_io) = local_ios();
+        let token_bytes = server
+            .token(1, LOCAL_SOCKET)
+            .timeout_seconds(1)
+            .generate()
+            .unwrap()
+            .try_into_bytes()
+            .unwrap();
+        let mut client = Client::new(&token_bytes).unwrap();
+        run_connection(&mut server, &mut client, &mut client_io, &mut server_io);
+
+        // the grace period is over: the session ends
+        for _ in 0..40 {
+            server.try_update(0.1, &mut server_io).unwrap();
+        }
+        assert!(!server.is_suspended(1));
+        assert_eq!(server.cfg.context, vec!["connect", "suspend", "disconnect"]);
+
+        // the client connects again and times out again
+        run_connection(&mut server, &mut client, &mut client_io, &mut server_io);
+        for _ in 0..20 {
+            server.try_update(0.1, &mut server_io).unwrap();
+        }
+        assert!(server.is_suspended(1));
+        server.cfg.context.clear();
+
+        // a client without the resumption token ends the suspended session and starts a new one
+        let mut new_client = Client::new(&token_bytes).unwrap();
+        run_connection(&mut server, &mut new_client, &mut client_io, &mut server_io);
+        assert_eq!(new_client.state(), ClientState::Connected);
+        assert!(!server.is_suspended(1));
+        assert_eq!(server.cfg.context, vec!["disconnect", "connect"]);
+    }
+}
@@ -0,0 +1,139 @@
+//! Resumption of client sessions that were interrupted by a timeout.
+//!
+//! When the server is configured with a resumption grace period, a client that times out is not disconnected
+//! right away: its session is suspended instead. If the client connects again within the grace period
+//! (with the same connect token, even from a new address, or a new token with the same client id) and presents the [`ResumptionToken`]
+//! of its previous session, the session is resumed instead of starting a new one.
+//! The client can only connect from a new address if it presents the resumption token.
+use std::collections::HashMap;
+use std::net::SocketAddr;
+
+use super::crypto::{self, Key};
+use super::{ClientId, MAC_BYTES};
+
+/// The size of a resumption token in bytes.
+pub const RESUMPTION_TOKEN_BYTES: usize = MAC_BYTES;
+
+/// Secret identifying a client session, presented by the client to resume the session after a timeout.
+///
+/// It is derived from the client-to-server key of the session, which is only known by the client and the server.
+pub type ResumptionToken = [u8; RESUMPTION_TOKEN_BYTES];
+
+/// Nonce used to derive the resumption token; packet sequence numbers never reach it.
+const RESUMPTION_NONCE: u64 = u64::MAX;
+
+/// Derive the resumption token of the session of `client_id` that uses `key` as its client-to-server key.
+pub(crate) fn resumption_token(client_id: ClientId, key: &Key) -> ResumptionToken {
+    // the token is the MAC of the client id, authenticated with the session key
+    let mut token = [0u8; RESUMPTION_TOKEN_BYTES];
+    crypto::chacha_encrypt(
+        &mut token,
+        Some(&client_id.to_le_bytes()),
+        RESUMPTION_NONCE,
+        key,
+    )
+    .expect("the buffer has room for the MAC");
+    token
+}
+
+#[derive(Debug, Clone, Copy)]
+struct SuspendedSession {
+    token: ResumptionToken,
+    addr: SocketAddr,
+    expire_time: f64,
+}
+
+/// Sessions of the clients that timed out and can still be resumed
+#[derive(Debug, Default)]
+pub(crate) struct SuspendedSessions {
+    sessions: HashMap<ClientId, SuspendedSession>,
+}
+
+impl SuspendedSessions {
+    pub(crate) fn suspend(
+        &mut self,
+        client_id: ClientId,
+        token: ResumptionToken,
+        addr: SocketAddr,
+        expire_time: f64,
+    ) {
+        self.sessions.insert(
+            client_id,
+            SuspendedSession {
+                token,
+                addr,
+                expire_time,
+            },
+        );
+    }
+
+    pub(crate) fn contains(&self, client_id: &ClientId) -> bool {
+        self.sessions.contains_key(client_id)
+    }
+
+    /// The resumption token of the suspended session of the client
+    pub(crate) fn token(&self, client_id: &ClientId) -> Option<ResumptionToken> {
+        self.sessions.get(client_id).map(|session| session.token)
+    }
+
+    /// The address of the client when its session was suspended
+    pub(crate) fn addr(&self, client_id: &ClientId) -> Option<SocketAddr> {
+        self.sessions.get(client_id).map(|session| session.addr)
+    }
+
+    pub(crate) fn ids(&self) -> Vec<ClientId> {
+        self.sessions.keys().copied().collect()
+    }
+
+    /// Remove the session of the client, returning its resumption token
+    pub(crate) fn remove(&mut self, client_id: &ClientId) -> Option<ResumptionToken> {
+        self.sessions.remove(client_id).map(|session| session.token)
+    }
+
+    /// Remove the sessions whose grace period is over, returning their client ids
+    pub(crate) fn expire(&mut self, time: f64) -> Vec<ClientId> {
+        let expired: Vec<ClientId> = self
+            .sessions
+            .iter()
+            .filter(|(_, session)| session.expire_time <= time)
+            .map(|(id, _)| *id)
+            .collect();
+        for id in &expired {
+            self.sessions.remove(id);
+        }
+        expired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::netcode::generate_key;
+
+    use super::*;
+
+    #[test]
+    fn test_resumption_token() {
+        let key = generate_key();
+        let token = resumption_token(1, &key);
+        // the token is deterministic
+        assert_eq!(token, resumption_token(1, &key));
+        // and depends on both the client id and the key
+        assert_ne!(token, resumption_token(2, &key));
+        assert_ne!(token, resumption_token(1, &generate_key()));
+    }
+
+    #[test]
+    fn test_suspended_sessions_expire() {
+        let mut sessions = SuspendedSessions::default();
+        let addr = "127.0.0.1:5000".parse().unwrap();
+        sessions.suspend(1, [1; RESUMPTION_TOKEN_BYTES], addr, 5.0);
+        sessions.suspend(2, [2; RESUMPTION_TOKEN_BYTES], addr, 10.0);
+
+        assert!(sessions.expire(4.0).is_empty());
+        assert_eq!(sessions.expire(5.0), vec![1]);
+        assert!(!sessions.contains(&1));
+        assert_eq!(sessions.addr(&2), Some(addr));
+        assert_eq!(sessions.remove(&2), Some([2; RESUMPTION_TOKEN_BYTES]));
+        assert!(sessions.ids().is_empty());
+    }
+}
@@ -441,6 +441,21 @@ impl ConnectToken {
         })?;
         Ok(buf)
     }
+
+    /// Tries to read a token from a 2048-byte array, for example one received from an auth server.
+    pub fn try_from_bytes(bytes: &[u8]) -> Result<Self, InvalidTokenError> {
+        if bytes.len() != CONNECT_TOKEN_BYTES {
+            return Err(InvalidTokenError::Io(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "expected {} bytes but got {}",
+                    CONNECT_TOKEN_BYTES,
+                    bytes.len()
+                ),
+            )));
+        }
+        Self::read_from(&mut io::Cursor::new(bytes))
+    }
 }
 
 impl Bytes for ConnectToken {
@@ -92,18 +92,22 @@ impl PacketHeaderManager {
         }
     }
 
-    pub(crate) fn update(&mut self, time_manager: &TimeManager) {
+    /// Update the current time, and return the list of sent packets that are now considered lost
+    pub(crate) fn update(&mut self, time_manager: &TimeManager) -> Vec<PacketId> {
         self.current_time = time_manager.current_time();
         self.stats_manager.update(time_manager);
         // clear sent packets that haven't received any ack for a while
+        let mut lost_packets = Vec::new();
         self.sent_packets_not_acked.retain(|packet_id, time_sent| {
             if self.current_time - (*time_sent) > CLEAR_UNACKED_PACKETS_DELAY {
                 trace!("sent packet got lost");
                 self.stats_manager.sent_packet_lost();
+                lost_packets.push(*packet_id);
                 return false;
             }
             true
         });
+        lost_packets
     }
 
     // /// Get the receiver for the ack notification channel
@@ -5,6 +5,7 @@ use bytes::Bytes;
 use bitcode::encoding::{Fixed, Gamma};
 
 use crate::packet::packet::FRAGMENT_SIZE;
+use crate::protocol::channel::ChannelKind;
 use crate::protocol::EventContext;
 use crate::serialize::reader::ReadBuffer;
 use crate::serialize::writer::WriteBuffer;
@@ -24,6 +25,21 @@ wrapping_id!(MessageId);
 
 pub type FragmentIndex = u8;
 
+/// Identifies a message that was sent on a channel that tracks acks (reliable channels and
+/// `UnorderedUnreliableWithAcks` channels), so that it can be matched with the
+/// [`MessageAckEvent`](crate::shared::events::MessageAckEvent) or
+/// [`MessageLostEvent`](crate::shared::events::MessageLostEvent) emitted for it
+///
+/// Message ids wrap around and start again for each connection, so two handles with the same `message_id`
+/// can refer to different messages: they are told apart by their generation.
+#[derive(Hash, PartialEq, Eq, Debug, Clone, Copy)]
+pub struct MessageHandle {
+    pub channel: ChannelKind,
+    pub message_id: MessageId,
+    /// Unique number of the message among all the tracked messages
+    pub(crate) generation: u64,
+}
+
 /// Struct to keep track of which messages/slices have been received by the remote
 #[derive(Hash, PartialEq, Eq, Debug, Clone, Copy)]
 pub(crate) struct MessageAck {
@@ -1,16 +1,19 @@
 use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
 
 use anyhow::{anyhow, Context};
+use crossbeam_channel::Receiver;
 use tracing::trace;
 
 use crate::channel::builder::ChannelContainer;
 use crate::channel::receivers::ChannelReceive;
 use crate::channel::senders::ChannelSend;
-use crate::packet::message::{FragmentData, MessageAck, MessageId, SingleData};
+use crate::packet::message::{FragmentData, MessageAck, MessageHandle, MessageId, SingleData};
 use crate::packet::packet::{Packet, PacketId};
 use crate::packet::packet_manager::{PacketBuilder, Payload, PACKET_BUFFER_CAPACITY};
+use crate::packet::priority_manager::{PriorityConfig, PriorityManager};
 use crate::protocol::channel::{ChannelKind, ChannelRegistry};
-use crate::protocol::registry::NetId;
+use crate::protocol::registry::{NetId, ProtocolRegistry};
 use crate::protocol::BitSerializable;
 use crate::serialize::reader::ReadBuffer;
 use crate::serialize::wordbuffer::reader::ReadWordBuffer;
@@ -21,6 +24,10 @@ use crate::shared::tick_manager::Tick;
 use crate::shared::tick_manager::TickManager;
 use crate::shared::time_manager::TimeManager;
 
+/// Generation of the next tracked message. It is shared by all the connections, so that a handle never
+/// matches a message of another connection
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(0);
+
 // TODO: hard to split message manager into send/receive because the acks need both the send side and receive side
 //  maybe have a separate actor for acks?
 
@@ -29,23 +36,52 @@ use crate::shared::time_manager::TimeManager;
 pub struct MessageManager {
     /// Handles sending/receiving packets (including acks)
     packet_manager: PacketBuilder,
-    // TODO: add ordering of channels per priority
+    /// Handles the bandwidth budget and the ordering of channels per priority
+    pub(crate) priority_manager: PriorityManager,
     pub(crate) channels: HashMap<ChannelKind, ChannelContainer>,
     pub(crate) channel_registry: ChannelRegistry,
     // TODO: can use Vec<ChannelKind, Vec<MessageId>> to be more efficient?
     /// Map to keep track of which messages have been sent in which packets, so that
     /// reliable senders can stop trying to send a message that has already been received
     packet_to_message_ack_map: HashMap<PacketId, HashMap<ChannelKind, Vec<MessageAck>>>,
+    /// For each channel that tracks acks, receivers notified when a message is acked or lost
+    delivery_receivers: HashMap<ChannelKind, (Receiver<MessageId>, Receiver<MessageId>)>,
+    /// Messages whose delivery should be reported to the user
+    tracked_messages: HashMap<(ChannelKind, MessageId), MessageHandle>,
+    /// Tracked messages whose id was reused by a newer message before they were acked or lost.
+    /// They will never be notified, so they are reported as lost
+    stale_messages: Vec<MessageHandle>,
+    /// Ids of the messages that were written into packets during the last call to `send_packets`
+    sent_message_ids: HashMap<ChannelKind, Vec<MessageId>>,
     writer: WriteWordBuffer,
 }
 
 impl MessageManager {
-    pub fn new(channel_registry: &ChannelRegistry) -> Self {
+    pub fn new(channel_registry: &ChannelRegistry, priority_config: PriorityConfig) -> Self {
+        let mut channels = channel_registry.channels();
+        let delivery_receivers = channels
+            .iter_mut()
+            .filter(|(_, channel)| channel.setting.mode.is_watching_acks())
+            .map(|(kind, channel)| {
+                (
+                    *kind,
+                    (
+                        channel.sender.subscribe_acks(),
+                        channel.sender.subscribe_losses(),
+                    ),
+                )
+            })
+            .collect();
         Self {
             packet_manager: PacketBuilder::new(),
-            channels: channel_registry.channels(),
+            priority_manager: PriorityManager::new(priority_config),
+            channels,
             channel_registry: channel_registry.clone(),
             packet_to_message_ack_map: HashMap::new(),
+            delivery_receivers,
+            tracked_messages: HashMap::new(),
+            stale_messages: Vec::new(),
+            sent_message_ids: HashMap::new(),
             writer: WriteWordBuffer::with_capacity(PACKET_BUFFER_CAPACITY),
         }
     }
@@ -57,7 +93,19 @@ impl MessageManager {
         ping_manager: &PingManager,
         tick_manager: &TickManager,
     ) {
-        self.packet_manager.header_manager.update(time_manager);
+        let lost_packets = self.packet_manager.header_manager.update(time_manager);
+        for lost_packet in lost_packets {
+            if let Some(message_map) = self.packet_to_message_ack_map.remove(&lost_packet) {
+                for (channel_kind, message_acks) in message_map {
+                    if let Some(channel) = self.channels.get_mut(&channel_kind) {
+                        for message_ack in message_acks {
+                            channel.sender.notify_message_lost(&message_ack);
+                        }
+                    }
+                }
+            }
+        }
+        self.priority_manager.update(time_manager.delta());
         for channel in self.channels.values_mut() {
             channel
                 .sender
@@ -72,17 +120,91 @@ impl MessageManager {
         &mut self,
         message: M,
         channel_kind: ChannelKind,
+    ) -> anyhow::Result<Option<MessageId>> {
+        let message_bytes = self.encode(&message)?;
+        self.buffer_send_bytes(message_bytes, channel_kind)
+    }
+
+    /// Serialize a message, without buffering it
+    pub(crate) fn encode<M: BitSerializable>(&mut self, message: &M) -> anyhow::Result<Vec<u8>> {
+        self.writer.start_write();
+        message.encode(&mut self.writer)?;
+        Ok(self.writer.finish_write().into())
+    }
+
+    /// Buffer a message that was already serialized with [`MessageManager::encode`]
+    pub(crate) fn buffer_send_bytes(
+        &mut self,
+        message_bytes: Vec<u8>,
+        channel_kind: ChannelKind,
     ) -> anyhow::Result<Option<MessageId>> {
         let channel = self
             .channels
             .get_mut(&channel_kind)
             .context("Channel not found")?;
-        self.writer.start_write();
-        message.encode(&mut self.writer)?;
-        let message_bytes: Vec<u8> = self.writer.finish_write().into();
+        anyhow::ensure!(
+            message_bytes.len() <= channel.setting.fragment.max_message_size,
+            "the message is bigger than the maximum message size of the channel"
+        );
         Ok(channel.sender.buffer_send(message_bytes.into()))
     }
 
+    /// Returns true if the remote peer sent too many fragments that violated the limits of one of the channels
+    pub(crate) fn fragment_limits_exceeded(&self) -> bool {
+        self.channels
+            .values()
+            .any(|channel| channel.receiver.fragment_limits_exceeded())
+    }
+
+    /// Number of reliable messages that were sent but not acknowledged yet by the remote peer
+    pub(crate) fn num_unacked_messages(&self) -> usize {
+        self.channels
+            .values()
+            .map(|channel| channel.sender.num_unacked_messages())
+            .sum()
+    }
+
+    /// Report the delivery of the message with the given id, if the channel tracks acks.
+    /// Returns the handle that will be included in the delivery notifications
+    pub(crate) fn track_delivery(
+        &mut self,
+        channel_kind: ChannelKind,
+        message_id: Option<MessageId>,
+    ) -> Option<MessageHandle> {
+        if !self.delivery_receivers.contains_key(&channel_kind) {
+            return None;
+        }
+        let handle = MessageHandle {
+            channel: channel_kind,
+            message_id: message_id?,
+            generation: NEXT_GENERATION.fetch_add(1, Ordering::Relaxed),
+        };
+        if let Some(stale) = self
+            .tracked_messages
+            .insert((channel_kind, handle.message_id), handle)
+        {
+            self.stale_messages.push(stale);
+        }
+        Some(handle)
+    }
+
+    /// Returns the tracked messages that were acked and the tracked messages that were lost since the last call
+    pub(crate) fn drain_delivery_notifications(
+        &mut self,
+    ) -> (Vec<MessageHandle>, Vec<MessageHandle>) {
+        let mut acked = Vec::new();
+        let mut lost = std::mem::take(&mut self.stale_messages);
+        for (channel_kind, (ack_receiver, loss_receiver)) in self.delivery_receivers.iter() {
+            for (receiver, handles) in [(ack_receiver, &mut acked), (loss_receiver, &mut lost)] {
+                // messages that are not tracked were sent internally (replication, etc.)
+                handles.extend(receiver.try_iter().filter_map(|message_id| {
+                    self.tracked_messages.remove(&(*channel_kind, message_id))
+                }));
+            }
+        }
+        (acked, lost)
+    }
+
     /// Prepare buckets from the internal send buffers, and return the bytes to send
     // TODO: maybe pass TickManager instead of Tick? Find a more elegant way to pass extra data that might not be used?
     //  (ticks are not purely necessary without client prediction)
@@ -90,19 +212,53 @@ impl MessageManager {
     pub fn send_packets(&mut self, current_tick: Tick) -> anyhow::Result<Vec<Payload>> {
         // Step 1. Get the list of packets to send from all channels
         // for each channel, prepare packets using the buffered messages that are ready to be sent
-        // TODO: iterate through the channels in order of channel priority? (with accumulation)
+        // Channels are iterated in order of accumulated priority; once the bandwidth budget is exhausted,
+        // the remaining channels keep their messages until the next send interval
         let mut data_to_send: BTreeMap<NetId, (VecDeque<SingleData>, VecDeque<FragmentData>)> =
             BTreeMap::new();
-        for (channel_kind, channel) in self.channels.iter_mut() {
+        let channels_with_messages = self.channels.iter_mut().filter_map(|(kind, channel)| {
+            channel.sender.collect_messages_to_send();
+            channel
+                .sender
+                .has_messages_to_send()
+                .then_some((*kind, channel.setting.priority))
+        });
+        self.sent_message_ids.clear();
+        for channel_kind in self.priority_manager.sort_channels(channels_with_messages) {
+            // messages whose bytes were reserved when they were buffered are always sent
+            let reserved = self.priority_manager.take_reserved(channel_kind);
+            if reserved == 0 && self.priority_manager.is_exhausted() {
+                trace!(
+                    ?channel_kind,
+                    "bandwidth budget exhausted, deferring channel"
+                );
+                continue;
+            }
             let channel_id = self
                 .channel_registry
-                .get_net_from_kind(channel_kind)
+                .get_net_from_kind(&channel_kind)
                 .context("cannot find channel id")?;
-            channel.sender.collect_messages_to_send();
-            if channel.sender.has_messages_to_send() {
-                data_to_send.insert(*channel_id, channel.sender.send_packet());
-            }
+            let channel = self
+                .channels
+                .get_mut(&channel_kind)
+                .context("Channel not found")?;
+            let (single_data, fragment_data) = channel.sender.send_packet();
+            let num_bytes = single_data.iter().map(|d| d.bytes.len()).sum::<usize>()
+                + fragment_data.iter().map(|d| d.bytes.len()).sum::<usize>();
+            self.priority_manager
+                .consume(num_bytes.saturating_sub(reserved));
+            self.priority_manager.reset_channel(channel_kind);
+            self.sent_message_ids.insert(
+                channel_kind,
+                single_data
+                    .iter()
+                    .filter_map(|data| data.id)
+                    .chain(fragment_data.iter().map(|data| data.message_id))
+                    .collect(),
+            );
+            data_to_send.insert(*channel_id, (single_data, fragment_data));
         }
+        self.priority_manager.finish_send();
         for (channel_id, (single_data, fragment_data)) in data_to_send.iter() {
             let channel_kind = self
                 .channel_registry
@@ -166,10 +322,17 @@ impl MessageManager {
         Ok(bytes)
     }
 
+    /// Ids of the messages of the channel that were written into packets during the last call to [`MessageManager::send_packets`]
+    pub(crate) fn sent_message_ids(&self, channel_kind: &ChannelKind) -> &[MessageId] {
+        self.sent_message_ids
+            .get(channel_kind)
+            .map_or(&[], |ids| ids.as_slice())
+    }
+
     /// Process packet received over the network as raw bytes
     /// Update the acks, and put the messages from the packets in internal buffers
     /// Returns the tick of the packet
-    pub fn recv_packet(&mut self, reader: &mut impl ReadBuffer) -> anyhow::Result<Tick> {
+    pub fn recv_packet(&mut self, reader: &mut ReadWordBuffer) -> anyhow::Result<Tick> {
         // Step 1. Parse the packet
         let packet: Packet = self.packet_manager.decode_packet(reader)?;
         let tick = packet.header().tick;
@@ -230,16 +393,22 @@ impl MessageManager {
     }
 
     /// Read all the messages in the internal buffers that are ready to be processed
+    ///
+    /// The `registry` is used to decode the registered messages and components
     // TODO: this is where naia converts the messages to events and pushes them to an event queue
     //  let be conservative and just return the messages right now. We could switch to an iterator
-    pub fn read_messages<M: BitSerializable>(&mut self) -> HashMap<ChannelKind, Vec<(Tick, M)>> {
+    pub fn read_messages<M: BitSerializable>(
+        &mut self,
+        registry: &ProtocolRegistry,
+    ) -> HashMap<ChannelKind, Vec<(Tick, M)>> {
         let mut map = HashMap::new();
         for (channel_kind, channel) in self.channels.iter_mut() {
             let mut messages = vec![];
             while let Some(single_data) = channel.receiver.read_message() {
                 trace!(?channel_kind, "reading message: {:?}", single_data);
                 let mut reader = ReadWordBuffer::start_read(single_data.bytes.as_ref());
-                let message = M::decode(&mut reader).expect("Could not decode message");
+                let message =
+                    M::decode_with(&mut reader, registry).expect("Could not decode message");
                 // TODO: why do we need finish read? to check for errors?
                 // reader.finish_read()?;
 
@@ -282,8 +451,10 @@ mod tests {
         let protocol = protocol();
 
         // Create message managers
-        let mut client_message_manager = MessageManager::new(protocol.channel_registry());
-        let mut server_message_manager = MessageManager::new(protocol.channel_registry());
+        let mut client_message_manager =
+            MessageManager::new(protocol.channel_registry(), PriorityConfig::default());
+        let mut server_message_manager =
+            MessageManager::new(protocol.channel_registry(), PriorityConfig::default());
 
         // client: buffer send messages, and then send
         let message = MyMessageProtocol::Message1(Message1("1".to_string()));
@@ -311,7 +482,7 @@ mod tests {
             server_message_manager
                 .recv_packet(&mut ReadWordBuffer::start_read(packet_byte.as_slice()))?;
         }
-        let mut data = server_message_manager.read_messages();
+        let mut data = server_message_manager.read_messages(&ProtocolRegistry::default());
         assert_eq!(
             data.get(&channel_kind_1).unwrap(),
             &vec![(Tick(0), message.clone())]
@@ -322,7 +493,7 @@ mod tests {
         );
 
         // Confirm what happens if we try to receive but there is nothing on the io
-        data = server_message_manager.read_messages();
+        data = server_message_manager.read_messages(&ProtocolRegistry::default());
         assert!(data.is_empty());
 
         // Check the state of the packet headers
@@ -367,8 +538,10 @@ mod tests {
         let protocol = protocol();
 
         // Create message managers
-        let mut client_message_manager = MessageManager::new(protocol.channel_registry());
-        let mut server_message_manager = MessageManager::new(protocol.channel_registry());
+        let mut client_message_manager =
+            MessageManager::new(protocol.channel_registry(), PriorityConfig::default());
+        let mut server_message_manager =
+            MessageManager::new(protocol.channel_registry(), PriorityConfig::default());
 
         // client: buffer send messages, and then send
         const MESSAGE_SIZE: usize = (1.5 * FRAGMENT_SIZE as f32) as usize;
@@ -412,7 +585,7 @@ mod tests {
             server_message_manager
                 .recv_packet(&mut ReadWordBuffer::start_read(packet_byte.as_slice()))?;
         }
-        let mut data = server_message_manager.read_messages();
+        let mut data = server_message_manager.read_messages(&ProtocolRegistry::default());
         assert_eq!(
             data.get(&channel_kind_1).unwrap(),
             &vec![(Tick(0), message.clone())]
@@ -423,7 +596,7 @@ mod tests {
         );
 
         // Confirm what happens if we try to receive but there is nothing on the io
-        data = server_message_manager.read_messages();
+        data = server_message_manager.read_messages(&ProtocolRegistry::default());
         assert!(data.is_empty());
 
         // Check the state of the packet headers
@@ -466,13 +639,49 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    /// Messages whose bytes were reserved in the budget are sent even if the budget is exhausted
+    fn test_send_reserved_messages() -> anyhow::Result<()> {
+        let protocol = protocol();
+        let mut message_manager = MessageManager::new(
+            protocol.channel_registry(),
+            PriorityConfig::default().with_bandwidth_cap(1000),
+        );
+        message_manager
+            .priority_manager
+            .update(Duration::from_millis(10));
+
+        let message = MyMessageProtocol::Message1(Message1("1".to_string()));
+        let updates_channel = ChannelKind::of::<EntityUpdatesChannel>();
+        let bytes = message_manager.encode(&message)?;
+        message_manager
+            .priority_manager
+            .reserve(updates_channel, bytes.len());
+        let message_id = message_manager
+            .buffer_send_bytes(bytes, updates_channel)?
+            .unwrap();
+        // exhaust the rest of the budget
+        message_manager.priority_manager.consume(10);
+        assert!(message_manager.priority_manager.is_exhausted());
+
+        let packets = message_manager.send_packets(Tick(0))?;
+        assert_eq!(packets.len(), 1);
+        assert_eq!(
+            message_manager.sent_message_ids(&updates_channel),
+            &[message_id]
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_notify_ack() -> anyhow::Result<()> {
         let protocol = protocol();
 
         // Create message managers
-        let mut client_message_manager = MessageManager::new(protocol.channel_registry());
-        let mut server_message_manager = MessageManager::new(protocol.channel_registry());
+        let mut client_message_manager =
+            MessageManager::new(protocol.channel_registry(), PriorityConfig::default());
+        let mut server_message_manager =
+            MessageManager::new(protocol.channel_registry(), PriorityConfig::default());
 
         let update_acks_tracker = client_message_manager
             .channels
@@ -520,4 +729,25 @@ mod tests {
         assert_eq!(update_acks_tracker.try_recv()?, message_id);
         Ok(())
     }
+
+    #[test]
+    fn test_tracked_message_id_reused() {
+        let protocol = protocol();
+        let mut message_manager =
+            MessageManager::new(protocol.channel_registry(), PriorityConfig::default());
+        let channel = Channel2::kind();
+        let old = message_manager
+            .track_delivery(channel, Some(MessageId(0)))
+            .unwrap();
+        // the message id wrapped around: the old message will never be notified
+        let new = message_manager
+            .track_delivery(channel, Some(MessageId(0)))
+            .unwrap();
+        assert_ne!(old, new);
+        assert_eq!(
+            message_manager.drain_delivery_notifications(),
+            (vec![], vec![old])
+        );
+        assert_eq!(message_manager.tracked_messages.len(), 1);
+    }
 }
@@ -65,7 +65,7 @@ impl<M: BitSerializable> MessageReceiver<M> {
     /// Process packet received over the network as raw bytes
     /// Update the acks, and put the messages from the packets in internal buffers
     /// Returns the tick of the packet
-    pub fn recv_packet(&mut self, reader: &mut impl ReadBuffer) -> anyhow::Result<Tick> {
+    pub fn recv_packet(&mut self, reader: &mut ReadWordBuffer) -> anyhow::Result<Tick> {
         // Step 1. Parse the packet
         let packet: Packet = self.packet_manager.decode_packet(reader)?;
         let tick = packet.header().tick;
@@ -34,6 +34,9 @@ pub mod packet;
 /// Manages building a single [`Packet`](packet::Packet) from multiple [`Messages`](message::Message)
 pub(crate) mod packet_manager;
 
+/// Manages the bandwidth budget of a connection and the priority of the data to send
+pub mod priority_manager;
+
 pub(crate) mod message_receivers;
 pub(crate) mod message_sender;
 /// Defines the [`PacketType`](packet_type::PacketType) enum
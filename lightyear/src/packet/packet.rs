@@ -10,12 +10,19 @@ use crate::protocol::channel::ChannelId;
 use crate::protocol::registry::NetId;
 use crate::protocol::BitSerializable;
 use crate::serialize::reader::ReadBuffer;
+use crate::serialize::wordbuffer::reader::ReadWordBuffer;
+use crate::serialize::wordbuffer::writer::WriteWordBuffer;
 use crate::serialize::writer::WriteBuffer;
 use crate::utils::wrapping_id::wrapping_id;
 
 // Internal id that we assign to each packet sent over the network
 wrapping_id!(PacketId);
 
+/// Version of the format of the packets (header, channels, messages and fragments) sent inside the netcode packets.
+///
+/// Must be incremented when the format changes, so that external decoders can detect it.
+pub const PACKET_FORMAT_VERSION: u32 = 1;
+
 /// Maximum number of bytes to write the header
 /// PacketType: 2 bits
 /// Rest: 10 bytes
@@ -105,7 +112,7 @@ impl SinglePacket {
 impl BitSerializable for SinglePacket {
     /// An expectation of the encoding is that we always have at least one channel that we can encode per packet.
     /// However, some channels might not have any messages (for example if we start writing the channel at the very end of the packet)
-    fn encode(&self, writer: &mut impl WriteBuffer) -> anyhow::Result<()> {
+    fn encode(&self, writer: &mut WriteWordBuffer) -> anyhow::Result<()> {
         self.data
             .iter()
             .enumerate()
@@ -131,7 +138,7 @@ impl BitSerializable for SinglePacket {
             })
     }
 
-    fn decode(reader: &mut impl ReadBuffer) -> anyhow::Result<Self>
+    fn decode(reader: &mut ReadWordBuffer) -> anyhow::Result<Self>
     where
         Self: Sized,
     {
@@ -196,7 +203,7 @@ impl FragmentedPacket {
 impl BitSerializable for FragmentedPacket {
     /// An expectation of the encoding is that we always have at least one channel that we can encode per packet.
     /// However, some channels might not have any messages (for example if we start writing the channel at the very end of the packet)
-    fn encode(&self, writer: &mut impl WriteBuffer) -> anyhow::Result<()> {
+    fn encode(&self, writer: &mut WriteWordBuffer) -> anyhow::Result<()> {
         writer.encode(&self.channel_id, Gamma)?;
         self.fragment.encode(writer)?;
         // continuation bit: is there single packet data?
@@ -204,7 +211,7 @@ impl BitSerializable for FragmentedPacket {
         self.packet.encode(writer)
     }
 
-    fn decode(reader: &mut impl ReadBuffer) -> anyhow::Result<Self>
+    fn decode(reader: &mut ReadWordBuffer) -> anyhow::Result<Self>
     where
         Self: Sized,
     {
@@ -286,7 +293,7 @@ impl Packet {
     }
 
     /// Encode a packet into the write buffer
-    pub fn encode(&self, writer: &mut impl WriteBuffer) -> anyhow::Result<()> {
+    pub fn encode(&self, writer: &mut WriteWordBuffer) -> anyhow::Result<()> {
         // use encode to force Fixed encoding
         // should still use gamma for packet type
         // TODO: add test
@@ -298,7 +305,7 @@ impl Packet {
     }
 
     /// Decode a packet from the read buffer. The read buffer will only contain the bytes for a single packet
-    pub fn decode(reader: &mut impl ReadBuffer) -> anyhow::Result<Packet> {
+    pub fn decode(reader: &mut ReadWordBuffer) -> anyhow::Result<Packet> {
         let header = reader.decode::<PacketHeader>(Fixed)?;
         let packet_type = header.get_packet_type();
         match packet_type {
@@ -388,6 +395,7 @@ mod tests {
         let settings = ChannelSettings {
             mode: ChannelMode::UnorderedUnreliable,
             direction: ChannelDirection::Bidirectional,
+            ..Default::default()
         };
         let mut c = ChannelRegistry::new();
         c.add::<Channel1>(settings.clone());
@@ -12,6 +12,7 @@ use crate::packet::packet_type::PacketType;
 use crate::protocol::registry::NetId;
 use crate::protocol::BitSerializable;
 use crate::serialize::reader::ReadBuffer;
+use crate::serialize::wordbuffer::reader::ReadWordBuffer;
 use crate::serialize::wordbuffer::writer::WriteWordBuffer;
 use crate::serialize::writer::WriteBuffer;
 
@@ -80,7 +81,7 @@ impl PacketBuilder {
     /// Decode a packet from raw bytes
     // TODO: the reader buffer will be created from the io (we copy the io bytes into a buffer)
     // Should we decode the packet and get ChannelKinds directly?
-    pub(crate) fn decode_packet(&mut self, reader: &mut impl ReadBuffer) -> anyhow::Result<Packet> {
+    pub(crate) fn decode_packet(&mut self, reader: &mut ReadWordBuffer) -> anyhow::Result<Packet> {
         Packet::decode(reader)
     }
 
@@ -640,6 +641,7 @@ mod tests {
         let settings = ChannelSettings {
             mode: ChannelMode::UnorderedUnreliable,
             direction: ChannelDirection::Bidirectional,
+            ..Default::default()
         };
         let mut c = ChannelRegistry::new();
         c.add::<Channel1>(settings.clone());
@@ -0,0 +1,183 @@
+//! Bandwidth budget and priority accumulation for a single connection
+//!
+//! Every send interval, each channel (and each replication group) that has data to send accumulates
+//! its priority. Data is then sent in order of accumulated priority until the byte budget of the connection
+//! is exhausted; whatever could not be sent keeps its accumulated priority so that it gets sent eventually.
+//! See <https://gafferongames.com/post/state_synchronization/> for more details.
+use std::time::Duration;
+
+use bevy::utils::HashMap;
+
+use crate::protocol::channel::ChannelKind;
+
+#[derive(Clone, Debug)]
+pub struct PriorityConfig {
+    /// Maximum number of bytes per second that can be sent to each remote
+    pub bandwidth_cap: usize,
+    /// If false, there is no limit to the number of bytes that can be sent
+    pub enabled: bool,
+}
+
+impl Default for PriorityConfig {
+    fn default() -> Self {
+        Self {
+            // 50 KB/s
+            bandwidth_cap: 50_000,
+            enabled: false,
+        }
+    }
+}
+
+impl PriorityConfig {
+    /// Enable the bandwidth limit, with the provided number of bytes per second
+    pub fn with_bandwidth_cap(mut self, bandwidth_cap: usize) -> Self {
+        self.bandwidth_cap = bandwidth_cap;
+        self.enabled = true;
+        self
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct PriorityManager {
+    config: PriorityConfig,
+    /// Number of bytes that can still be sent during the current send interval.
+    /// Can be negative if we went over budget, in which case the debt is carried over to the next interval
+    budget: f32,
+    /// Priority accumulated by each channel since the last time it was allowed to send messages
+    channel_priorities: HashMap<ChannelKind, f32>,
+    /// Bytes that were already taken from the budget when the messages were buffered in a channel
+    /// (for example replication updates), so that the channel is not deferred or charged twice when sending
+    reserved_bytes: HashMap<ChannelKind, usize>,
+}
+
+impl PriorityManager {
+    pub(crate) fn new(config: PriorityConfig) -> Self {
+        Self {
+            config,
+            budget: 0.0,
+            channel_priorities: HashMap::default(),
+            reserved_bytes: HashMap::default(),
+        }
+    }
+
+    /// Increase the budget according to the time elapsed since the last frame
+    pub(crate) fn update(&mut self, delta: Duration) {
+        if self.config.enabled {
+            self.budget += self.config.bandwidth_cap as f32 * delta.as_secs_f32();
+        }
+    }
+
+    /// Number of bytes that can still be sent during this send interval, or None if there is no limit
+    pub(crate) fn available_bytes(&self) -> Option<usize> {
+        self.config.enabled.then(|| self.budget.max(0.0) as usize)
+    }
+
+    /// Returns true if we cannot send any more bytes during this send interval
+    pub(crate) fn is_exhausted(&self) -> bool {
+        self.config.enabled && self.budget <= 0.0
+    }
+
+    /// Record that we sent `bytes` bytes
+    pub(crate) fn consume(&mut self, bytes: usize) {
+        if self.config.enabled {
+            self.budget -= bytes as f32;
+        }
+    }
+
+    /// Take `bytes` from the budget for messages that were just buffered in the channel.
+    /// These messages will be sent during this send interval even if the budget is exhausted by then.
+    pub(crate) fn reserve(&mut self, kind: ChannelKind, bytes: usize) {
+        if self.config.enabled {
+            self.budget -= bytes as f32;
+            *self.reserved_bytes.entry(kind).or_default() += bytes;
+        }
+    }
+
+    /// Number of bytes that were reserved for the channel during this send interval
+    pub(crate) fn take_reserved(&mut self, kind: ChannelKind) -> usize {
+        self.reserved_bytes.remove(&kind).unwrap_or_default()
+    }
+
+    /// Accumulate the priority of the channels that have messages to send,
+    /// and return them sorted by accumulated priority (highest first)
+    pub(crate) fn sort_channels(
+        &mut self,
+        channels: impl Iterator<Item = (ChannelKind, f32)>,
+    ) -> Vec<ChannelKind> {
+        let mut channels: Vec<(ChannelKind, f32)> = channels
+            .map(|(kind, priority)| {
+                let accumulated = self.channel_priorities.entry(kind).or_default();
+                *accumulated += priority;
+                (kind, *accumulated)
+            })
+            .collect();
+        channels.sort_by(|a, b| b.1.total_cmp(&a.1));
+        channels.into_iter().map(|(kind, _)| kind).collect()
+    }
+
+    /// The channel was able to send its messages, reset its accumulated priority
+    pub(crate) fn reset_channel(&mut self, kind: ChannelKind) {
+        self.channel_priorities.remove(&kind);
+    }
+
+    /// Called at the end of a send interval: unused budget is not carried over to the next interval
+    pub(crate) fn finish_send(&mut self) {
+        self.budget = self.budget.min(0.0);
+        self.reserved_bytes.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::_reexport::{EntityActionsChannel, EntityUpdatesChannel};
+
+    use super::*;
+
+    #[test]
+    fn test_budget() {
+        let mut manager = PriorityManager::new(PriorityConfig::default().with_bandwidth_cap(1000));
+        manager.update(Duration::from_millis(100));
+        assert_eq!(manager.available_bytes(), Some(100));
+
+        // going over budget creates a debt for the next interval
+        manager.consume(150);
+        assert!(manager.is_exhausted());
+        manager.finish_send();
+        manager.update(Duration::from_millis(100));
+        assert_eq!(manager.available_bytes(), Some(50));
+
+        // unused budget is not carried over
+        manager.finish_send();
+        manager.update(Duration::from_millis(100));
+        assert_eq!(manager.available_bytes(), Some(100));
+    }
+
+    #[test]
+    fn test_reserved_bytes() {
+        let mut manager = PriorityManager::new(PriorityConfig::default().with_bandwidth_cap(1000));
+        let kind = ChannelKind::of::<EntityUpdatesChannel>();
+        manager.update(Duration::from_millis(100));
+
+        // the reserved bytes are taken from the budget right away
+        manager.reserve(kind, 100);
+        assert!(manager.is_exhausted());
+        assert_eq!(manager.take_reserved(kind), 100);
+        assert_eq!(manager.take_reserved(kind), 0);
+    }
+
+    #[test]
+    fn test_channel_priority_accumulation() {
+        let mut manager = PriorityManager::new(PriorityConfig::default().with_bandwidth_cap(1000));
+        let high = ChannelKind::of::<EntityActionsChannel>();
+        let low = ChannelKind::of::<EntityUpdatesChannel>();
+
+        let sorted = manager.sort_channels(vec![(high, 2.0), (low, 1.5)].into_iter());
+        assert_eq!(sorted, vec![high, low]);
+        // only the high priority channel could send
+        manager.reset_channel(high);
+
+        // the low priority channel accumulated priority, so now it goes first
+        let sorted = manager.sort_channels(vec![(high, 2.0), (low, 1.5)].into_iter());
+        assert_eq!(sorted, vec![low, high]);
+    }
+}
@@ -1,10 +1,15 @@
 use serde::Deserialize;
 use std::any::TypeId;
 use std::collections::HashMap;
+use std::hash::Hasher;
 
 use crate::channel::builder::ChannelContainer;
-use crate::channel::builder::{Channel, ChannelBuilder, ChannelSettings};
+use crate::channel::builder::{
+    Channel, ChannelBuilder, ChannelDirection, ChannelMode, ChannelSettings,
+};
+use crate::protocol::fingerprint::FingerprintHasher;
 use crate::protocol::registry::{NetId, TypeKind, TypeMapper};
+use crate::protocol::schema::ChannelSchema;
 
 /// ChannelKind - internal wrapper around the type of the channel
 #[derive(Debug, Eq, Hash, Copy, Clone, PartialEq)]
@@ -88,6 +93,50 @@ impl ChannelRegistry {
         self.get_builder_from_kind(channel_kind)
     }
 
+    /// Hash the name, mode and direction of every channel, in the order in which they were registered.
+    ///
+    /// The other settings (priority, fragment limits, etc.) only affect the local peer, so they are not included.
+    pub(crate) fn hash_channels(&self, hasher: &mut FingerprintHasher) {
+        for net_id in 0..self.kind_map.next_net_id {
+            let Some(kind) = self.kind_map.kind(net_id) else {
+                continue;
+            };
+            hasher.write_name(self.name(kind).unwrap_or_default());
+            let settings = &self.builder_map[kind].settings;
+            hasher.write_u8(match settings.mode {
+                ChannelMode::UnorderedUnreliableWithAcks => 0,
+                ChannelMode::UnorderedUnreliable => 1,
+                ChannelMode::SequencedUnreliable => 2,
+                ChannelMode::UnorderedReliable(_) => 3,
+                ChannelMode::SequencedReliable(_) => 4,
+                ChannelMode::OrderedReliable(_) => 5,
+                ChannelMode::TickBuffered => 6,
+                ChannelMode::Streaming(_) => 7,
+            });
+            hasher.write_u8(match settings.direction {
+                ChannelDirection::ClientToServer => 0,
+                ChannelDirection::ServerToClient => 1,
+                ChannelDirection::Bidirectional => 2,
+            });
+        }
+    }
+
+    /// Describe the channels, in the order of their net ids
+    pub(crate) fn schema(&self) -> Vec<ChannelSchema> {
+        (0..self.kind_map.next_net_id)
+            .filter_map(|net_id| {
+                let kind = self.kind_map.kind(net_id)?;
+                let settings = &self.builder_map[kind].settings;
+                Some(ChannelSchema {
+                    net_id,
+                    name: self.name(kind).unwrap_or_default().to_string(),
+                    mode: settings.mode.clone(),
+                    direction: settings.direction.clone(),
+                })
+            })
+            .collect()
+    }
+
     #[cfg(test)]
     fn len(&self) -> usize {
         self.kind_map.len()
@@ -112,6 +161,7 @@ mod tests {
         let settings = ChannelSettings {
             mode: ChannelMode::UnorderedUnreliable,
             direction: ChannelDirection::Bidirectional,
+            ..Default::default()
         };
         registry.add::<MyChannel>(settings.clone());
         assert_eq!(registry.len(), 1);
@@ -15,6 +15,7 @@ use crate::connection::events::{
     IterComponentInsertEvent, IterComponentRemoveEvent, IterComponentUpdateEvent,
 };
 use crate::prelude::{EntityMapper, MapEntities, Message, Named, PreSpawnedPlayerObject};
+use crate::protocol::registry::DynamicComponent;
 use crate::protocol::{BitSerializable, EventContext, Protocol};
 use crate::shared::replication::components::ShouldBeInterpolated;
 use crate::shared::replication::components::ShouldBePredicted;
@@ -24,11 +25,8 @@ use crate::shared::replication::ReplicationSend;
 // each message must derive message
 
 // that big enum will implement MessageProtocol via a proc macro
-// TODO: remove the extra  Serialize + DeserializeOwned + Clone  bounds
 pub trait ComponentProtocol:
     BitSerializable
-    + Serialize
-    + DeserializeOwned
     + for<'a> MapEntities<'a>
     + ComponentBehaviour
     + Debug
@@ -37,9 +35,13 @@ pub trait ComponentProtocol:
     + From<ShouldBePredicted>
     + From<ShouldBeInterpolated>
     + TryInto<ShouldBePredicted>
+    + From<DynamicComponent>
 {
     type Protocol: Protocol;
 
+    /// Returns the names of the component types, in the order of the enum variants
+    fn type_names() -> Vec<&'static str>;
+
     /// Apply a ComponentInsert to an entity
     fn insert(self, entity: &mut EntityWorldMut);
 
@@ -75,6 +77,18 @@ pub trait ComponentProtocol:
     /// Add all component systems for the Interpolation SystemSet
     fn add_interpolation_systems(app: &mut App);
 
+    /// Delta of any of the delta-compressed components
+    type Delta: Serialize + DeserializeOwned + Clone + PartialEq + Debug + Send + Sync;
+
+    /// Compute the delta between `self` (the base value) and `new`.
+    /// Returns None if the component is not replicated with delta-compression
+    fn diff(&self, new: &Self) -> Option<Self::Delta>;
+
+    /// Apply a delta on top of `self` (the base value) to get the full component value.
+    fn apply_diff(&self, delta: &Self::Delta) -> anyhow::Result<Self>
+    where
+        Self: Sized;
+
     // /// Get the sync mode for the component
     // fn mode<C>() -> ComponentSyncMode
     // where
@@ -197,6 +211,7 @@ cfg_if!(
             + Sync
             + Display
             + for<'a> From<&'a <Self::Protocol as Protocol>::Components>
+            + for<'a> From<&'a <<Self::Protocol as Protocol>::Components as ComponentProtocol>::Delta>
             + ComponentKindBehaviour
             + FromType<ShouldBePredicted>
             + FromType<ShouldBeInterpolated>
@@ -222,6 +237,7 @@ cfg_if!(
             + Sync
             + Display
             + for<'a> From<&'a <Self::Protocol as Protocol>::Components>
+            + for<'a> From<&'a <<Self::Protocol as Protocol>::Components as ComponentProtocol>::Delta>
             + ComponentKindBehaviour
             + FromType<ShouldBePredicted>
             + FromType<ShouldBeInterpolated>
@@ -236,6 +252,9 @@ cfg_if!(
 pub trait ComponentKindBehaviour {
     /// Remove the component for an entity
     fn remove(self, entity: &mut EntityWorldMut);
+
+    /// Returns true if the component is replicated with delta-compression
+    fn is_delta_compressed(&self) -> bool;
 }
 
 // /// Trait to convert a component type into the corresponding ComponentProtocolKind
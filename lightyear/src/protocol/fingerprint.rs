@@ -0,0 +1,58 @@
+use std::hash::Hasher;
+
+/// Hasher used to compute the fingerprint of a [`Protocol`](super::Protocol).
+///
+/// Uses 64-bit FNV-1a, because the output of the std `DefaultHasher` is not guaranteed to be the
+/// same across Rust versions, and the client and server can be compiled separately.
+pub(crate) struct FingerprintHasher(u64);
+
+impl FingerprintHasher {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+
+    pub(crate) fn new() -> Self {
+        Self(Self::OFFSET_BASIS)
+    }
+
+    /// Write a string, followed by a separator so that ["ab", "c"] and ["a", "bc"] hash differently
+    pub(crate) fn write_name(&mut self, name: &str) {
+        self.write(name.as_bytes());
+        self.write_u8(0xff);
+    }
+}
+
+impl Hasher for FingerprintHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(Self::PRIME);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint_hasher() {
+        // reference values of FNV-1a 64
+        let hasher = FingerprintHasher::new();
+        assert_eq!(hasher.finish(), 0xcbf2_9ce4_8422_2325);
+        let mut hasher = FingerprintHasher::new();
+        hasher.write(b"a");
+        assert_eq!(hasher.finish(), 0xaf63_dc4c_8601_ec8c);
+
+        let mut hasher_1 = FingerprintHasher::new();
+        hasher_1.write_name("ab");
+        hasher_1.write_name("c");
+        let mut hasher_2 = FingerprintHasher::new();
+        hasher_2.write_name("a");
+        hasher_2.write_name("bc");
+        assert_ne!(hasher_1.finish(), hasher_2.finish());
+    }
+}
@@ -9,7 +9,7 @@ use crate::connection::events::IterMessageEvent;
 use crate::inputs::native::input_buffer::InputMessage;
 use crate::packet::message::Message;
 use crate::prelude::MapEntities;
-use crate::protocol::registry::TypeKind;
+use crate::protocol::registry::{DynamicMessage, TypeKind};
 use crate::protocol::{BitSerializable, EventContext, Protocol};
 #[cfg(feature = "leafwing")]
 use crate::shared::events::InputMessageEvent;
@@ -28,8 +28,6 @@ pub enum InputMessageKind {
 // that big enum will implement MessageProtocol via a proc macro
 pub trait MessageProtocol:
     BitSerializable
-    + Serialize
-    + DeserializeOwned
     + Clone
     + for<'a> MapEntities<'a>
     + Debug
@@ -37,6 +35,8 @@ pub trait MessageProtocol:
     + Sync
     + From<InputMessage<<<Self as MessageProtocol>::Protocol as Protocol>::Input>>
     + TryInto<InputMessage<<<Self as MessageProtocol>::Protocol as Protocol>::Input>, Error = ()>
+    + From<DynamicMessage>
+    + TryInto<DynamicMessage, Error = ()>
 {
     type Protocol: Protocol;
 
@@ -46,6 +46,9 @@ pub trait MessageProtocol:
     /// Returns the MessageKind of the Message
     fn kind(&self) -> MessageKind;
 
+    /// Returns the names of the message types, in the order of the enum variants
+    fn type_names() -> Vec<&'static str>;
+
     /// Returns true if the message is an input message
     fn input_message_kind(&self) -> InputMessageKind;
 
@@ -7,6 +7,7 @@
 
 use anyhow::Context;
 use std::fmt::Debug;
+use std::hash::Hasher;
 
 use bevy::prelude::{App, Resource};
 use bitcode::encoding::Fixed;
@@ -17,8 +18,15 @@ use serde::Serialize;
 use crate::channel::builder::{Channel, ChannelSettings};
 use crate::protocol::channel::ChannelRegistry;
 use crate::protocol::component::{ComponentProtocol, ComponentProtocolKind};
+use crate::protocol::fingerprint::FingerprintHasher;
 use crate::protocol::message::MessageProtocol;
+use crate::protocol::registry::ProtocolRegistry;
+use crate::protocol::schema::format::Format;
+use crate::protocol::schema::tracer::Tracer;
+use crate::protocol::schema::ProtocolSchema;
 use crate::serialize::reader::ReadBuffer;
+use crate::serialize::wordbuffer::reader::ReadWordBuffer;
+use crate::serialize::wordbuffer::writer::WriteWordBuffer;
 use crate::serialize::writer::WriteBuffer;
 use crate::shared::replication::ReplicationSend;
 
@@ -32,7 +40,13 @@ pub(crate) mod component;
 pub(crate) mod message;
 
 /// Provides a mapping from a type to a unique identifier that can be serialized
-pub(crate) mod registry;
+pub mod registry;
+
+/// Stable hash used to check that the client and server use the same protocol
+pub(crate) mod fingerprint;
+
+/// Machine-readable description of the protocol, for external tools and clients
+pub mod schema;
 
 // TODO: how to make components or messages or inputs optional? Just by having an implementation for () ?
 /// The [`Protocol`] trait defines the various channels, inputs, messages and components that will be used in the game.
@@ -83,6 +97,32 @@ pub trait Protocol: Send + Sync + Clone + Debug + Resource + 'static {
     fn add_channel<C: Channel>(&mut self, settings: ChannelSettings) -> &mut Self;
     fn channel_registry(&self) -> &ChannelRegistry;
     fn add_per_component_replication_send_systems<R: ReplicationSend<Self>>(app: &mut App);
+
+    /// Stable hash of the channels, messages and components of the protocol, and of their order.
+    ///
+    /// The client sends it to the server during the connection handshake; the server denies the connection
+    /// with [`DenyReason::ProtocolMismatch`](crate::netcode::DenyReason::ProtocolMismatch) if it doesn't match its own fingerprint.
+    fn fingerprint(&self) -> u64 {
+        let mut hasher = FingerprintHasher::new();
+        self.channel_registry().hash_channels(&mut hasher);
+        hasher.write_name("messages");
+        for name in Self::Message::type_names() {
+            hasher.write_name(name);
+        }
+        hasher.write_name("components");
+        for name in Self::Components::type_names() {
+            hasher.write_name(name);
+        }
+        hasher.finish()
+    }
+
+    /// Machine-readable description of the channels, messages and components of the protocol, with the layout
+    /// of each message and component. It can be exported as JSON to generate decoders for external tools and clients.
+    ///
+    /// Use [`ProtocolSchema::with_registry`] to also include the types registered at runtime.
+    fn schema(&self) -> ProtocolSchema {
+        ProtocolSchema::new(self)
+    }
 }
 
 // TODO: give an option to change names of types
@@ -144,14 +184,18 @@ macro_rules! protocolize {
                     protocol.add_channel::<EntityActionsChannel>(ChannelSettings {
                         mode: ChannelMode::UnorderedReliable(ReliableSettings::default()),
                         direction: ChannelDirection::Bidirectional,
+                        ..Default::default()
                     });
                     protocol.add_channel::<EntityUpdatesChannel>(ChannelSettings {
                         mode: ChannelMode::UnorderedUnreliableWithAcks,
                         direction: ChannelDirection::Bidirectional,
+                        ..Default::default()
                     });
                     protocol.add_channel::<PingChannel>(ChannelSettings {
                         mode: ChannelMode::SequencedUnreliable,
                         direction: ChannelDirection::Bidirectional,
+                        priority: 10.0,
+                        ..Default::default()
                     });
                     protocol.add_channel::<InputChannel>(ChannelSettings {
                         // we want to use unordered unreliable because the server has a buffer to re-order the inputs anyway
@@ -160,14 +204,19 @@ macro_rules! protocolize {
                         //  because our messages contain the last 10 ticks of input anyway, so we don't need to read older ones.
                         mode: ChannelMode::UnorderedUnreliable,
                         direction: ChannelDirection::ClientToServer,
+                        priority: 10.0,
+                        ..Default::default()
                     });
                     protocol.add_channel::<DefaultUnorderedUnreliableChannel>(ChannelSettings {
                         mode: ChannelMode::UnorderedUnreliable,
                         direction: ChannelDirection::Bidirectional,
+                        ..Default::default()
                     });
                     protocol.add_channel::<TickBufferChannel>(ChannelSettings {
                         mode: ChannelMode::TickBuffered,
                         direction: ChannelDirection::ClientToServer,
+                        priority: 10.0,
+                        ..Default::default()
                     });
                     protocol
                 }
@@ -181,6 +230,8 @@ macro_rules! protocolize {
         pub type ServerMut<'w, 's> = $shared_crate_name::server::resource::ServerMut<'w, 's, $protocol>;
         pub type ClientConnectionManager = $shared_crate_name::client::connection::ConnectionManager<$protocol>;
         pub type ServerConnectionManager = $shared_crate_name::server::connection::ConnectionManager<$protocol>;
+        pub type ClientRpc<'w, R> = $shared_crate_name::client::rpc::ClientRpc<'w, $protocol, R>;
+        pub type ServerRpc<'w, R> = $shared_crate_name::server::rpc::ServerRpc<'w, $protocol, R>;
         }
     };
 
@@ -237,26 +288,35 @@ macro_rules! protocolize {
                     protocol.add_channel::<EntityActionsChannel>(ChannelSettings {
                         mode: ChannelMode::UnorderedReliable(ReliableSettings::default()),
                         direction: ChannelDirection::Bidirectional,
+                        ..Default::default()
                     });
                     protocol.add_channel::<EntityUpdatesChannel>(ChannelSettings {
                         mode: ChannelMode::UnorderedUnreliableWithAcks,
                         direction: ChannelDirection::Bidirectional,
+                        ..Default::default()
                     });
                     protocol.add_channel::<PingChannel>(ChannelSettings {
                         mode: ChannelMode::SequencedUnreliable,
                         direction: ChannelDirection::Bidirectional,
+                        priority: 10.0,
+                        ..Default::default()
                     });
                     protocol.add_channel::<InputChannel>(ChannelSettings {
                         mode: ChannelMode::UnorderedUnreliable,
                         direction: ChannelDirection::ClientToServer,
+                        priority: 10.0,
+                        ..Default::default()
                     });
                     protocol.add_channel::<DefaultUnorderedUnreliableChannel>(ChannelSettings {
                         mode: ChannelMode::UnorderedUnreliable,
                         direction: ChannelDirection::Bidirectional,
+                        ..Default::default()
                     });
                     protocol.add_channel::<TickBufferChannel>(ChannelSettings {
                         mode: ChannelMode::TickBuffered,
                         direction: ChannelDirection::ClientToServer,
+                        priority: 10.0,
+                        ..Default::default()
                     });
                     protocol
                 }
@@ -270,6 +330,8 @@ macro_rules! protocolize {
         pub type ServerMut<'w, 's> = $shared_crate_name::server::resource::ServerMut<'w, 's, $protocol>;
         pub type ClientConnectionManager = $shared_crate_name::client::connection::ConnectionManager<$protocol>;
         pub type ServerConnectionManager = $shared_crate_name::server::connection::ConnectionManager<$protocol>;
+        pub type ClientRpc<'w, R> = $shared_crate_name::client::rpc::ClientRpc<'w, $protocol, R>;
+        pub type ServerRpc<'w, R> = $shared_crate_name::server::rpc::ServerRpc<'w, $protocol, R>;
         }
     };
 
@@ -375,28 +437,59 @@ macro_rules! protocolize {
 
 /// Something that can be serialized bit by bit
 pub trait BitSerializable: Clone {
-    fn encode(&self, writer: &mut impl WriteBuffer) -> anyhow::Result<()>;
+    fn encode(&self, writer: &mut WriteWordBuffer) -> anyhow::Result<()>;
 
-    fn decode(reader: &mut impl ReadBuffer) -> anyhow::Result<Self>
+    fn decode(reader: &mut ReadWordBuffer) -> anyhow::Result<Self>
     where
         Self: Sized;
+
+    /// Decode the value with the [`ProtocolRegistry`], which is needed to decode the registered messages and
+    /// components ([`DynamicMessage`](registry::DynamicMessage), [`DynamicComponent`](registry::DynamicComponent))
+    /// that the value contains.
+    ///
+    /// Only the types that can contain registered types need to override it.
+    fn decode_with(
+        reader: &mut ReadWordBuffer,
+        _registry: &ProtocolRegistry,
+    ) -> anyhow::Result<Self>
+    where
+        Self: Sized,
+    {
+        Self::decode(reader)
+    }
+
+    /// Layout of the type in the [`ProtocolSchema`].
+    ///
+    /// The bits of the types that don't use serde are written by custom code, so their layout is not known
+    #[doc(hidden)]
+    fn format(_tracer: &mut Tracer) -> Format
+    where
+        Self: Sized,
+    {
+        Format::Opaque
+    }
 }
 
-// TODO: allow for either decode/encode directly, or use serde if we add an attribute with_serde?
+// Types can choose to be serialized with bitcode or with custom functions (instead of serde)
+// with the `#[message(...)]` attribute: see [`crate::serialize::mode`]
 impl<T> BitSerializable for T
 where
     T: Serialize + DeserializeOwned + Clone,
 {
-    fn encode(&self, writer: &mut impl WriteBuffer) -> anyhow::Result<()> {
+    fn encode(&self, writer: &mut WriteWordBuffer) -> anyhow::Result<()> {
         writer.serialize(self)
     }
 
-    fn decode(reader: &mut impl ReadBuffer) -> anyhow::Result<Self>
+    fn decode(reader: &mut ReadWordBuffer) -> anyhow::Result<Self>
     where
         Self: Sized,
     {
         reader.deserialize::<Self>()
     }
+
+    fn format(tracer: &mut Tracer) -> Format {
+        tracer.trace::<Self>()
+    }
 }
 
 // impl<T> BitSerializable for T
@@ -420,3 +513,52 @@ where
 pub trait EventContext: Send + Sync + 'static {}
 
 impl<T: Send + Sync + 'static> EventContext for T {}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use crate::tests::protocol::*;
+
+    fn protocol_with(settings: ChannelSettings) -> MyProtocol {
+        let mut p = MyProtocol::default();
+        p.add_channel::<Channel1>(settings);
+        p
+    }
+
+    #[test]
+    fn test_protocol_fingerprint() {
+        let settings = ChannelSettings {
+            mode: ChannelMode::UnorderedUnreliable,
+            direction: ChannelDirection::Bidirectional,
+            ..Default::default()
+        };
+        let fingerprint = protocol_with(settings.clone()).fingerprint();
+        assert_eq!(protocol_with(settings.clone()).fingerprint(), fingerprint);
+
+        // settings that only affect the local peer don't change the fingerprint
+        let local_settings = ChannelSettings {
+            priority: 5.0,
+            fragment: FragmentSettings {
+                max_partial_messages: 1,
+                ..Default::default()
+            },
+            ..settings.clone()
+        };
+        assert_eq!(protocol_with(local_settings).fingerprint(), fingerprint);
+
+        let other_mode = ChannelSettings {
+            mode: ChannelMode::SequencedUnreliable,
+            ..settings.clone()
+        };
+        assert_ne!(protocol_with(other_mode).fingerprint(), fingerprint);
+
+        // the order of the channels matters
+        let mut p = protocol_with(settings.clone());
+        p.add_channel::<Channel2>(settings.clone());
+        let mut other_order = MyProtocol::default();
+        other_order.add_channel::<Channel2>(settings.clone());
+        other_order.add_channel::<Channel1>(settings);
+        assert_ne!(p.fingerprint(), other_order.fingerprint());
+        assert_ne!(p.fingerprint(), fingerprint);
+    }
+}
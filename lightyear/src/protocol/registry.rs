@@ -1,6 +1,40 @@
-use std::any::TypeId;
+use std::any::{type_name, Any, TypeId};
 use std::collections::HashMap;
-use std::hash::Hash;
+use std::fmt::{Debug, Formatter};
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+
+use anyhow::{bail, Context, Result};
+use bevy::ecs::component::ComponentTicks;
+use bevy::ecs::event::ManualEventReader;
+use bevy::ecs::removal_detection::RemovedComponentEntity;
+use bevy::prelude::{
+    App, Component, Entity, EntityRef, EntityWorldMut, Event, Events, Resource, World,
+};
+use tracing::error;
+
+use crate::_reexport::{
+    ComponentProtocol, MessageInternal, MessageProtocol, ReadBuffer, ReadWordBuffer, WriteBuffer,
+    WriteWordBuffer,
+};
+use crate::client::components::{ComponentSyncMode, LerpFn, SyncMetadata};
+use crate::client::interpolation::plugin::{
+    add_component_interpolation_systems, add_component_prepare_interpolation_systems,
+};
+use crate::client::interpolation::NullInterpolator;
+use crate::client::prediction::correction::InstantCorrector;
+use crate::client::prediction::plugin::add_component_prediction_systems;
+use crate::netcode::ClientId;
+use crate::packet::message::Message;
+use crate::prelude::Named;
+use crate::protocol::fingerprint::FingerprintHasher;
+use crate::protocol::schema::format::Format;
+use crate::protocol::schema::tracer::Tracer;
+use crate::protocol::schema::TypeSchema;
+use crate::protocol::{BitSerializable, EventContext, Protocol};
+use crate::shared::events::{
+    ComponentInsertEvent, ComponentRemoveEvent, ComponentUpdateEvent, MessageEvent,
+};
 
 /// Id used to serialize IDs over the network efficiently
 // TODO: have different types of net-id (ChannelId, ComponentId, MessageId), and make type-mapper generic over that
@@ -62,3 +96,869 @@ impl<K: TypeKind> TypeMapper<K> {
         self.kind_map.len()
     }
 }
+
+/// Runtime registry of the messages and components that were added to the [`App`] with [`AppRegistrationExt`],
+/// on top of the ones listed in the `#[message_protocol]` and `#[component_protocol]` enums.
+///
+/// This lets crates ship their own networked types without having to edit the protocol enums.
+/// Registered types are sent inside the [`DynamicMessage`] and [`DynamicComponent`] variants of the protocol enums.
+///
+/// The net id of a registered type is its index in the list of registered types sorted by [`Named::NAME`], so the
+/// client and the server agree on the ids as long as they register the same types, no matter in which order.
+/// The name is also what identifies the type in the protocol fingerprint, so two registered messages (or two
+/// registered components) cannot have the same name.
+///
+/// The registry is needed to decode the received [`DynamicMessage`]s and [`DynamicComponent`]s,
+/// see [`BitSerializable::decode_with`].
+#[derive(Resource, Default)]
+pub struct ProtocolRegistry {
+    messages: Vec<MessageRegistration>,
+    components: Vec<ComponentRegistration>,
+    message_ids: HashMap<TypeId, NetId>,
+    component_ids: HashMap<TypeId, NetId>,
+}
+
+type DecodeFn = fn(&mut ReadWordBuffer) -> Result<Box<dyn RegisteredValue>>;
+
+/// Type-erased functions used to handle a registered message
+#[derive(Clone, Copy)]
+pub(crate) struct MessageRegistration {
+    name: &'static str,
+    type_id: TypeId,
+    decode: DecodeFn,
+    /// Record the layout of the message
+    format: fn(&mut Tracer) -> Format,
+    client_event: fn(&mut World, Box<dyn RegisteredValue>, ()) -> Result<()>,
+    server_event: fn(&mut World, Box<dyn RegisteredValue>, ClientId) -> Result<()>,
+}
+
+/// Type-erased functions used to write the component events of a registered component
+#[derive(Clone, Copy)]
+pub(crate) struct ComponentEvents<Ctx> {
+    pub(crate) insert: fn(&mut World, Entity, Ctx),
+    pub(crate) update: fn(&mut World, Entity, Ctx),
+    pub(crate) remove: fn(&mut World, Entity, Ctx),
+}
+
+/// Type-erased functions used to handle a registered component
+#[derive(Clone, Copy)]
+pub(crate) struct ComponentRegistration {
+    name: &'static str,
+    type_id: TypeId,
+    pub(crate) mode: ComponentSyncMode,
+    decode: DecodeFn,
+    /// Insert the component on the entity
+    pub(crate) insert: fn(&mut EntityWorldMut, Box<dyn RegisteredValue>) -> Result<()>,
+    /// Replace the value of the entity's component
+    pub(crate) update: fn(&mut EntityWorldMut, Box<dyn RegisteredValue>) -> Result<()>,
+    pub(crate) remove: fn(&mut EntityWorldMut),
+    /// Clone the entity's component, if the entity has it
+    pub(crate) clone: fn(&EntityRef) -> Option<Box<dyn RegisteredValue>>,
+    /// Record the layout of the component
+    format: fn(&mut Tracer) -> Format,
+    pub(crate) change_ticks: fn(&EntityRef) -> Option<ComponentTicks>,
+    /// Read the entities whose component was removed since the last time the reader was used
+    pub(crate) removed: fn(&World, &mut ManualEventReader<RemovedComponentEntity>) -> Vec<Entity>,
+    /// Copy the component from the first entity to the second entity
+    pub(crate) sync: fn(&mut World, Entity, Entity),
+    /// Add the prediction systems of the component, for [`ComponentSyncMode::Full`]
+    pub(crate) add_prediction_systems: fn(&mut App),
+    /// Add the systems that prepare the interpolation of the component, for [`ComponentSyncMode::Full`]
+    pub(crate) add_prepare_interpolation_systems: fn(&mut App),
+    /// Add the system that interpolates the component, for [`ComponentSyncMode::Full`]
+    pub(crate) add_interpolation_systems: fn(&mut App),
+    client_events: ComponentEvents<()>,
+    server_events: ComponentEvents<ClientId>,
+}
+
+/// Contexts (client or server) in which the events of the registered types can be written
+pub(crate) trait RegistryContext: EventContext + Copy {
+    fn message_event(
+        registration: &MessageRegistration,
+    ) -> fn(&mut World, Box<dyn RegisteredValue>, Self) -> Result<()>;
+
+    fn component_events(registration: &ComponentRegistration) -> ComponentEvents<Self>;
+}
+
+impl RegistryContext for () {
+    fn message_event(
+        registration: &MessageRegistration,
+    ) -> fn(&mut World, Box<dyn RegisteredValue>, ()) -> Result<()> {
+        registration.client_event
+    }
+
+    fn component_events(registration: &ComponentRegistration) -> ComponentEvents<()> {
+        registration.client_events
+    }
+}
+
+impl RegistryContext for ClientId {
+    fn message_event(
+        registration: &MessageRegistration,
+    ) -> fn(&mut World, Box<dyn RegisteredValue>, ClientId) -> Result<()> {
+        registration.server_event
+    }
+
+    fn component_events(registration: &ComponentRegistration) -> ComponentEvents<ClientId> {
+        registration.server_events
+    }
+}
+
+impl ProtocolRegistry {
+    /// Register a message type
+    ///
+    /// Returns an error if the message was already registered, or if another registered message has the same name
+    pub fn add_message<M: RegisteredMessage>(&mut self) -> Result<()> {
+        if self.message_ids.contains_key(&TypeId::of::<M>()) {
+            bail!("message {} is already registered", M::NAME);
+        }
+        if self.messages.iter().any(|m| m.name == M::NAME) {
+            bail!(
+                "another message named {} is already registered, use `#[message(name = \"...\")]` to rename one of them",
+                M::NAME
+            );
+        }
+        self.messages.push(MessageRegistration {
+            name: M::NAME,
+            type_id: TypeId::of::<M>(),
+            decode: decode::<M>,
+            format: M::format,
+            client_event: push_message_event::<M, ()>,
+            server_event: push_message_event::<M, ClientId>,
+        });
+        self.messages.sort_by_key(|m| m.name);
+        self.message_ids = self
+            .messages
+            .iter()
+            .enumerate()
+            .map(|(net_id, m)| (m.type_id, net_id as NetId))
+            .collect();
+        Ok(())
+    }
+
+    /// Register a component type, with the [`ComponentSyncMode`] used to sync it from the confirmed entity to the
+    /// predicted/interpolated entities
+    ///
+    /// With [`ComponentSyncMode::Full`], the component is rolled back and corrected instantly on the predicted
+    /// entities, and it is not smoothed between the server updates on the interpolated entities
+    /// (see [`ProtocolRegistry::add_interpolated_component`]).
+    ///
+    /// Returns an error if the component was already registered, or if another registered component has the same name
+    pub fn add_component<C: RegisteredComponent>(&mut self, mode: ComponentSyncMode) -> Result<()> {
+        self.push_component::<C, RegisteredSyncMetadata>(mode)
+    }
+
+    /// Register a component type with [`ComponentSyncMode::Full`], that is interpolated with `I` on the
+    /// interpolated entities
+    ///
+    /// Returns an error if the component was already registered, or if another registered component has the same name
+    pub fn add_interpolated_component<C: RegisteredComponent, I: LerpFn<C> + 'static>(
+        &mut self,
+    ) -> Result<()> {
+        self.push_component::<C, RegisteredSyncMetadata<I>>(ComponentSyncMode::Full)
+    }
+
+    fn push_component<C: RegisteredComponent, M: SyncMetadata<C>>(
+        &mut self,
+        mode: ComponentSyncMode,
+    ) -> Result<()> {
+        if self.component_ids.contains_key(&TypeId::of::<C>()) {
+            bail!("component {} is already registered", C::NAME);
+        }
+        if self.components.iter().any(|c| c.name == C::NAME) {
+            bail!(
+                "another component named {} is already registered, use `#[message(name = \"...\")]` to rename one of them",
+                C::NAME
+            );
+        }
+        self.components.push(ComponentRegistration {
+            name: C::NAME,
+            type_id: TypeId::of::<C>(),
+            mode,
+            decode: decode::<C>,
+            insert: insert_component::<C>,
+            update: update_component::<C>,
+            remove: remove_component::<C>,
+            clone: clone_component::<C>,
+            format: C::format,
+            change_ticks: component_change_ticks::<C>,
+            removed: removed_components::<C>,
+            sync: sync_component::<C>,
+            add_prediction_systems: add_component_prediction_systems::<C, M>,
+            add_prepare_interpolation_systems: add_component_prepare_interpolation_systems::<C, M>,
+            add_interpolation_systems: add_component_interpolation_systems::<C, M>,
+            client_events: ComponentEvents {
+                insert: push_component_event::<ComponentInsertEvent<C, ()>>,
+                update: push_component_event::<ComponentUpdateEvent<C, ()>>,
+                remove: push_component_event::<ComponentRemoveEvent<C, ()>>,
+            },
+            server_events: ComponentEvents {
+                insert: push_component_event::<ComponentInsertEvent<C, ClientId>>,
+                update: push_component_event::<ComponentUpdateEvent<C, ClientId>>,
+                remove: push_component_event::<ComponentRemoveEvent<C, ClientId>>,
+            },
+        });
+        self.components.sort_by_key(|c| c.name);
+        self.component_ids = self
+            .components
+            .iter()
+            .enumerate()
+            .map(|(net_id, c)| (c.type_id, net_id as NetId))
+            .collect();
+        Ok(())
+    }
+
+    /// Returns true if no message or component was registered
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty() && self.components.is_empty()
+    }
+
+    /// Net id of a registered message
+    pub fn message_net_id<M: 'static>(&self) -> Option<NetId> {
+        self.message_ids.get(&TypeId::of::<M>()).copied()
+    }
+
+    /// Net id of a registered component
+    pub fn component_net_id<C: 'static>(&self) -> Option<NetId> {
+        self.component_ids.get(&TypeId::of::<C>()).copied()
+    }
+
+    /// Wrap a registered message into a [`DynamicMessage`] that can be sent through the [`MessageProtocol`]
+    pub fn message<M: RegisteredMessage>(&self, message: M) -> Result<DynamicMessage> {
+        let net_id = self
+            .message_net_id::<M>()
+            .with_context(|| format!("message {} is not registered", M::NAME))?;
+        Ok(DynamicMessage {
+            net_id,
+            value: Box::new(message),
+        })
+    }
+
+    /// Wrap a registered component into a [`DynamicComponent`] that can be sent through the [`ComponentProtocol`]
+    pub fn component<C: RegisteredComponent>(&self, component: C) -> Result<DynamicComponent> {
+        let net_id = self
+            .component_net_id::<C>()
+            .with_context(|| format!("component {} is not registered", C::NAME))?;
+        Ok(DynamicComponent {
+            net_id,
+            value: Box::new(component),
+        })
+    }
+
+    /// Names of the registered messages, in the order of their net ids
+    pub fn message_names(&self) -> Vec<&'static str> {
+        self.messages.iter().map(|m| m.name).collect()
+    }
+
+    /// Names of the registered components, in the order of their net ids
+    pub fn component_names(&self) -> Vec<&'static str> {
+        self.components.iter().map(|c| c.name).collect()
+    }
+
+    /// Describe the registered messages, in the order of their net ids
+    pub(crate) fn message_schemas(&self, tracer: &mut Tracer) -> Vec<TypeSchema> {
+        self.messages
+            .iter()
+            .enumerate()
+            .map(|(net_id, m)| TypeSchema {
+                net_id: net_id as NetId,
+                name: m.name.to_string(),
+                format: (m.format)(tracer),
+            })
+            .collect()
+    }
+
+    /// Describe the registered components, in the order of their net ids
+    pub(crate) fn component_schemas(&self, tracer: &mut Tracer) -> Vec<TypeSchema> {
+        self.components
+            .iter()
+            .enumerate()
+            .map(|(net_id, c)| TypeSchema {
+                net_id: net_id as NetId,
+                name: c.name.to_string(),
+                format: (c.format)(tracer),
+            })
+            .collect()
+    }
+
+    pub(crate) fn message_registration(&self, net_id: NetId) -> Option<&MessageRegistration> {
+        self.messages.get(net_id as usize)
+    }
+
+    pub(crate) fn component_registration(&self, net_id: NetId) -> Option<&ComponentRegistration> {
+        self.components.get(net_id as usize)
+    }
+
+    /// Iterate through the registered components and their net ids
+    pub(crate) fn component_registrations(
+        &self,
+    ) -> impl Iterator<Item = (NetId, &ComponentRegistration)> {
+        self.components
+            .iter()
+            .enumerate()
+            .map(|(net_id, c)| (net_id as NetId, c))
+    }
+
+    /// Registrations of the components registered with [`ComponentSyncMode::Full`]
+    pub(crate) fn full_sync_registrations(&self) -> Vec<ComponentRegistration> {
+        self.components
+            .iter()
+            .filter(|c| c.mode == ComponentSyncMode::Full)
+            .copied()
+            .collect()
+    }
+
+    /// Combine the fingerprint of the [`Protocol`](super::Protocol) with the registered types.
+    ///
+    /// The fingerprint is left unchanged if no type was registered.
+    pub fn fingerprint(&self, protocol_fingerprint: u64) -> u64 {
+        if self.is_empty() {
+            return protocol_fingerprint;
+        }
+        let mut hasher = FingerprintHasher::new();
+        hasher.write_u64(protocol_fingerprint);
+        hasher.write_name("registered_messages");
+        for message in &self.messages {
+            hasher.write_name(message.name);
+        }
+        hasher.write_name("registered_components");
+        for component in &self.components {
+            hasher.write_name(component.name);
+            hasher.write_u8(match component.mode {
+                ComponentSyncMode::Full => 0,
+                ComponentSyncMode::Simple => 1,
+                ComponentSyncMode::Once => 2,
+                ComponentSyncMode::None => 3,
+            });
+        }
+        hasher.finish()
+    }
+}
+
+/// Bounds required for a message to be registered with [`AppRegistrationExt::register_message`]
+pub trait RegisteredMessage: Message + BitSerializable {}
+impl<T: Message + BitSerializable> RegisteredMessage for T {}
+
+/// Bounds required for a component to be registered with [`AppRegistrationExt::register_component`]
+pub trait RegisteredComponent: Component + Message + BitSerializable + PartialEq {}
+impl<T: Component + Message + BitSerializable + PartialEq> RegisteredComponent for T {}
+
+/// [`SyncMetadata`] of the registered components: they are interpolated with `I`, and snapped instantly to the
+/// corrected state after a rollback
+pub(crate) struct RegisteredSyncMetadata<I = NullInterpolator>(PhantomData<I>);
+
+impl<C: Component, I: LerpFn<C> + 'static> SyncMetadata<C> for RegisteredSyncMetadata<I> {
+    type Interpolator = I;
+    type Corrector = InstantCorrector;
+
+    // only used by the prediction/interpolation systems, which are added for ComponentSyncMode::Full
+    fn mode() -> ComponentSyncMode {
+        ComponentSyncMode::Full
+    }
+}
+
+/// A registered message or component, whose type is only known through its net id
+pub(crate) trait RegisteredValue: Send + Sync {
+    fn clone_value(&self) -> Box<dyn RegisteredValue>;
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+    fn type_path(&self) -> &'static str;
+    /// Serialize the value directly into the surrounding buffer
+    fn encode_value(&self, writer: &mut WriteWordBuffer) -> Result<()>;
+}
+
+impl<T: BitSerializable + Send + Sync + 'static> RegisteredValue for T {
+    fn clone_value(&self) -> Box<dyn RegisteredValue> {
+        Box::new(self.clone())
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+
+    fn type_path(&self) -> &'static str {
+        type_name::<T>()
+    }
+
+    fn encode_value(&self, writer: &mut WriteWordBuffer) -> Result<()> {
+        self.encode(writer)
+    }
+}
+
+impl dyn RegisteredValue {
+    fn downcast<T: 'static>(self: Box<Self>) -> Result<T> {
+        let type_path = self.type_path();
+        self.into_any()
+            .downcast::<T>()
+            .map(|value| *value)
+            .map_err(|_| anyhow::anyhow!("expected {}, got {}", type_name::<T>(), type_path))
+    }
+
+    /// Serialized bits of the value, used to compare values whose type is unknown
+    fn bits(&self) -> Option<(usize, Vec<u8>)> {
+        let mut writer = WriteWordBuffer::with_capacity(64);
+        self.encode_value(&mut writer).ok()?;
+        Some((writer.num_bits_written(), writer.finish_write().to_vec()))
+    }
+}
+
+/// A registered message.
+///
+/// This is the variant of the [`MessageProtocol`] enum that carries all the types registered with
+/// [`AppRegistrationExt::register_message`].
+#[derive(MessageInternal)]
+pub struct DynamicMessage {
+    pub(crate) net_id: NetId,
+    pub(crate) value: Box<dyn RegisteredValue>,
+}
+
+/// A registered component.
+///
+/// This is the variant of the [`ComponentProtocol`] enum that carries all the types registered with
+/// [`AppRegistrationExt::register_component`]. It is never inserted on an entity: the registered component
+/// is inserted instead.
+#[derive(MessageInternal, Component)]
+pub struct DynamicComponent {
+    pub(crate) net_id: NetId,
+    pub(crate) value: Box<dyn RegisteredValue>,
+}
+
+/// The net id is written, followed by the value (without any length prefix)
+impl BitSerializable for DynamicMessage {
+    fn encode(&self, writer: &mut WriteWordBuffer) -> Result<()> {
+        writer.serialize(&self.net_id)?;
+        self.value.encode_value(writer)
+    }
+
+    fn decode(reader: &mut ReadWordBuffer) -> Result<Self> {
+        Self::decode_with(reader, &ProtocolRegistry::default())
+    }
+
+    fn decode_with(reader: &mut ReadWordBuffer, registry: &ProtocolRegistry) -> Result<Self> {
+        let net_id = reader.deserialize::<NetId>()?;
+        let registration = registry
+            .message_registration(net_id)
+            .with_context(|| format!("message net id {net_id} is not registered"))?;
+        Ok(Self {
+            net_id,
+            value: (registration.decode)(reader)?,
+        })
+    }
+}
+
+impl Clone for DynamicMessage {
+    fn clone(&self) -> Self {
+        Self {
+            net_id: self.net_id,
+            value: self.value.clone_value(),
+        }
+    }
+}
+
+impl PartialEq for DynamicMessage {
+    fn eq(&self, other: &Self) -> bool {
+        self.net_id == other.net_id && self.value.bits() == other.value.bits()
+    }
+}
+
+impl Debug for DynamicMessage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DynamicMessage")
+            .field("net_id", &self.net_id)
+            .field("type", &self.value.type_path())
+            .finish()
+    }
+}
+
+/// The net id is written, followed by the value (without any length prefix)
+impl BitSerializable for DynamicComponent {
+    fn encode(&self, writer: &mut WriteWordBuffer) -> Result<()> {
+        writer.serialize(&self.net_id)?;
+        self.value.encode_value(writer)
+    }
+
+    fn decode(reader: &mut ReadWordBuffer) -> Result<Self> {
+        Self::decode_with(reader, &ProtocolRegistry::default())
+    }
+
+    fn decode_with(reader: &mut ReadWordBuffer, registry: &ProtocolRegistry) -> Result<Self> {
+        let net_id = reader.deserialize::<NetId>()?;
+        let registration = registry
+            .component_registration(net_id)
+            .with_context(|| format!("component net id {net_id} is not registered"))?;
+        Ok(Self {
+            net_id,
+            value: (registration.decode)(reader)?,
+        })
+    }
+}
+
+impl Clone for DynamicComponent {
+    fn clone(&self) -> Self {
+        Self {
+            net_id: self.net_id,
+            value: self.value.clone_value(),
+        }
+    }
+}
+
+impl PartialEq for DynamicComponent {
+    fn eq(&self, other: &Self) -> bool {
+        self.net_id == other.net_id && self.value.bits() == other.value.bits()
+    }
+}
+
+impl Debug for DynamicComponent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DynamicComponent")
+            .field("net_id", &self.net_id)
+            .field("type", &self.value.type_path())
+            .finish()
+    }
+}
+
+impl DynamicComponent {
+    pub fn net_id(&self) -> NetId {
+        self.net_id
+    }
+
+    /// Insert the registered component on the entity
+    pub fn insert(self, entity: &mut EntityWorldMut) {
+        let Some(insert) = registration(entity, self.net_id).map(|r| r.insert) else {
+            error!(net_id = ?self.net_id, "received a component that is not registered");
+            return;
+        };
+        insert(entity, self.value).unwrap_or_else(|e| {
+            error!("could not insert registered component: {:?}", e);
+        });
+    }
+
+    /// Update the registered component of the entity
+    pub fn update(self, entity: &mut EntityWorldMut) {
+        let Some(update) = registration(entity, self.net_id).map(|r| r.update) else {
+            error!(net_id = ?self.net_id, "received a component that is not registered");
+            return;
+        };
+        update(entity, self.value).unwrap_or_else(|e| {
+            error!("could not update registered component: {:?}", e);
+        });
+    }
+
+    /// Remove the registered component with the given net id from the entity
+    pub fn remove(net_id: NetId, entity: &mut EntityWorldMut) {
+        let Some(remove) = registration(entity, net_id).map(|r| r.remove) else {
+            error!(?net_id, "received a component that is not registered");
+            return;
+        };
+        remove(entity);
+    }
+}
+
+fn registration(entity: &mut EntityWorldMut, net_id: NetId) -> Option<ComponentRegistration> {
+    entity.world_scope(|world| {
+        world
+            .get_resource::<ProtocolRegistry>()
+            .and_then(|registry| registry.component_registration(net_id).copied())
+    })
+}
+
+/// Extension trait to register networked types on the [`App`], without adding them to the protocol enums
+pub trait AppRegistrationExt {
+    /// Register a message that can be sent with `send_registered_message` and read with `EventReader<MessageEvent<M>>`
+    ///
+    /// Returns an error if the message is already registered, or if another registered message has the same
+    /// [`Named::NAME`]
+    fn register_message<M: RegisteredMessage>(&mut self) -> Result<&mut Self>;
+
+    /// Register a component that is replicated for the entities that have a `Replicate` component.
+    ///
+    /// On the client, the `mode` controls how the component is copied from the confirmed entity to the
+    /// predicted/interpolated entities. With [`ComponentSyncMode::Full`], the component is rolled back on the
+    /// predicted entities, but it is not smoothed between the server updates on the interpolated entities: use
+    /// [`register_interpolated_component`](AppRegistrationExt::register_interpolated_component) for that.
+    /// The prediction and interpolation systems of the component are added when the app is finished, so it
+    /// must be registered before the app runs.
+    ///
+    /// Returns an error if the component is already registered, or if another registered component has the
+    /// same [`Named::NAME`]
+    fn register_component<C: RegisteredComponent>(
+        &mut self,
+        mode: ComponentSyncMode,
+    ) -> Result<&mut Self>;
+
+    /// Register a component with [`ComponentSyncMode::Full`], that is interpolated with `I` on the
+    /// interpolated entities (for example [`LinearInterpolator`](crate::client::interpolation::LinearInterpolator))
+    ///
+    /// Returns an error if the component is already registered, or if another registered component has the
+    /// same [`Named::NAME`]
+    fn register_interpolated_component<C: RegisteredComponent, I: LerpFn<C> + 'static>(
+        &mut self,
+    ) -> Result<&mut Self>;
+}
+
+impl AppRegistrationExt for App {
+    fn register_message<M: RegisteredMessage>(&mut self) -> Result<&mut Self> {
+        self.world
+            .get_resource_or_insert_with(ProtocolRegistry::default)
+            .add_message::<M>()?;
+        Ok(self
+            .add_event::<MessageEvent<M, ()>>()
+            .add_event::<MessageEvent<M, ClientId>>())
+    }
+
+    fn register_component<C: RegisteredComponent>(
+        &mut self,
+        mode: ComponentSyncMode,
+    ) -> Result<&mut Self> {
+        self.world
+            .get_resource_or_insert_with(ProtocolRegistry::default)
+            .add_component::<C>(mode)?;
+        Ok(add_component_events::<C>(self))
+    }
+
+    fn register_interpolated_component<C: RegisteredComponent, I: LerpFn<C> + 'static>(
+        &mut self,
+    ) -> Result<&mut Self> {
+        self.world
+            .get_resource_or_insert_with(ProtocolRegistry::default)
+            .add_interpolated_component::<C, I>()?;
+        Ok(add_component_events::<C>(self))
+    }
+}
+
+fn add_component_events<C: Component>(app: &mut App) -> &mut App {
+    app.add_event::<ComponentInsertEvent<C, ()>>()
+        .add_event::<ComponentUpdateEvent<C, ()>>()
+        .add_event::<ComponentRemoveEvent<C, ()>>()
+        .add_event::<ComponentInsertEvent<C, ClientId>>()
+        .add_event::<ComponentUpdateEvent<C, ClientId>>()
+        .add_event::<ComponentRemoveEvent<C, ClientId>>()
+}
+
+fn decode<T: BitSerializable + Send + Sync + 'static>(
+    reader: &mut ReadWordBuffer,
+) -> Result<Box<dyn RegisteredValue>> {
+    Ok(Box::new(T::decode(reader)?))
+}
+
+fn push_message_event<M: RegisteredMessage, Ctx: EventContext>(
+    world: &mut World,
+    value: Box<dyn RegisteredValue>,
+    ctx: Ctx,
+) -> Result<()> {
+    let message = value.downcast::<M>()?;
+    world
+        .get_resource_mut::<Events<MessageEvent<M, Ctx>>>()
+        .context("message events are not registered")?
+        .send(MessageEvent::new(message, ctx));
+    Ok(())
+}
+
+fn push_component_event<E: Event + RegisteredComponentEvent>(
+    world: &mut World,
+    entity: Entity,
+    ctx: E::Ctx,
+) {
+    if let Some(mut events) = world.get_resource_mut::<Events<E>>() {
+        events.send(E::new(entity, ctx));
+    }
+}
+
+/// Component events that can be written for a registered component
+trait RegisteredComponentEvent {
+    type Ctx;
+    fn new(entity: Entity, ctx: Self::Ctx) -> Self;
+}
+
+impl<C: Component, Ctx: EventContext> RegisteredComponentEvent for ComponentInsertEvent<C, Ctx> {
+    type Ctx = Ctx;
+    fn new(entity: Entity, ctx: Ctx) -> Self {
+        ComponentInsertEvent::new(entity, ctx)
+    }
+}
+
+impl<C: Component, Ctx: EventContext> RegisteredComponentEvent for ComponentUpdateEvent<C, Ctx> {
+    type Ctx = Ctx;
+    fn new(entity: Entity, ctx: Ctx) -> Self {
+        ComponentUpdateEvent::new(entity, ctx)
+    }
+}
+
+impl<C: Component, Ctx: EventContext> RegisteredComponentEvent for ComponentRemoveEvent<C, Ctx> {
+    type Ctx = Ctx;
+    fn new(entity: Entity, ctx: Ctx) -> Self {
+        ComponentRemoveEvent::new(entity, ctx)
+    }
+}
+
+fn insert_component<C: RegisteredComponent>(
+    entity: &mut EntityWorldMut,
+    value: Box<dyn RegisteredValue>,
+) -> Result<()> {
+    entity.insert(value.downcast::<C>()?);
+    Ok(())
+}
+
+fn update_component<C: RegisteredComponent>(
+    entity: &mut EntityWorldMut,
+    value: Box<dyn RegisteredValue>,
+) -> Result<()> {
+    let component = value.downcast::<C>()?;
+    if let Some(mut c) = entity.get_mut::<C>() {
+        *c = component;
+    }
+    Ok(())
+}
+
+fn remove_component<C: RegisteredComponent>(entity: &mut EntityWorldMut) {
+    entity.remove::<C>();
+}
+
+fn clone_component<C: RegisteredComponent>(entity: &EntityRef) -> Option<Box<dyn RegisteredValue>> {
+    entity
+        .get::<C>()
+        .map(|component| Box::new(component.clone()) as Box<dyn RegisteredValue>)
+}
+
+fn component_change_ticks<C: RegisteredComponent>(entity: &EntityRef) -> Option<ComponentTicks> {
+    entity.get_change_ticks::<C>()
+}
+
+fn removed_components<C: RegisteredComponent>(
+    world: &World,
+    reader: &mut ManualEventReader<RemovedComponentEntity>,
+) -> Vec<Entity> {
+    world
+        .components()
+        .component_id::<C>()
+        .and_then(|component_id| world.removed_components().get(component_id))
+        .map(|events| reader.read(events).cloned().map(Entity::from).collect())
+        .unwrap_or_default()
+}
+
+fn sync_component<C: RegisteredComponent>(world: &mut World, from: Entity, to: Entity) {
+    let Some(component) = world.get::<C>(from).cloned() else {
+        return;
+    };
+    if let Some(mut entity) = world.get_entity_mut(to) {
+        // avoid triggering change detection if the value is already up-to-date
+        if entity.get::<C>() != Some(&component) {
+            entity.insert(component);
+        }
+    }
+}
+
+/// The [`ComponentProtocolKind`](crate::protocol::component::ComponentProtocolKind) of the registered component with the given net id
+pub(crate) fn registered_kind<P: Protocol>(net_id: NetId) -> P::ComponentKinds {
+    let component = P::Components::from(DynamicComponent {
+        net_id,
+        value: Box::new(()),
+    });
+    P::ComponentKinds::from(&component)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::_reexport::{ReadBuffer, ReadWordBuffer};
+    use crate::client::interpolation::LinearInterpolator;
+    use crate::tests::protocol::*;
+
+    #[test]
+    fn test_net_ids_do_not_depend_on_registration_order() -> Result<()> {
+        let mut a = ProtocolRegistry::default();
+        a.add_message::<Message1>()?;
+        a.add_message::<Message2>()?;
+        a.add_component::<Component1>(ComponentSyncMode::Simple)?;
+        let mut b = ProtocolRegistry::default();
+        b.add_component::<Component1>(ComponentSyncMode::Simple)?;
+        b.add_message::<Message2>()?;
+        b.add_message::<Message1>()?;
+
+        assert_eq!(
+            a.message_net_id::<Message1>(),
+            b.message_net_id::<Message1>()
+        );
+        assert_eq!(
+            a.message_net_id::<Message2>(),
+            b.message_net_id::<Message2>()
+        );
+        assert_eq!(a.fingerprint(0), b.fingerprint(0));
+
+        // the sync mode is part of the fingerprint
+        let mut c = ProtocolRegistry::default();
+        c.add_message::<Message1>()?;
+        c.add_message::<Message2>()?;
+        c.add_component::<Component1>(ComponentSyncMode::Once)?;
+        assert_ne!(a.fingerprint(0), c.fingerprint(0));
+        // an empty registry doesn't change the protocol fingerprint
+        assert_eq!(ProtocolRegistry::default().fingerprint(0), 0);
+        Ok(())
+    }
+
+    mod first {
+        #[derive(
+            crate::_reexport::MessageInternal, serde::Serialize, serde::Deserialize, Clone,
+        )]
+        pub struct Chat(pub String);
+    }
+
+    mod second {
+        #[derive(
+            crate::_reexport::MessageInternal, serde::Serialize, serde::Deserialize, Clone,
+        )]
+        #[message(name = "second::Chat")]
+        pub struct Chat(pub u32);
+    }
+
+    mod third {
+        #[derive(
+            crate::_reexport::MessageInternal, serde::Serialize, serde::Deserialize, Clone,
+        )]
+        pub struct Chat(pub bool);
+    }
+
+    #[test]
+    fn test_types_with_the_same_name() -> Result<()> {
+        let mut registry = ProtocolRegistry::default();
+        registry.add_message::<first::Chat>()?;
+        // the types are identified by their name, so it must be unique
+        assert!(registry.add_message::<third::Chat>().is_err());
+        assert!(registry.add_message::<first::Chat>().is_err());
+        registry.add_message::<second::Chat>()?;
+        assert_eq!(registry.message_net_id::<first::Chat>(), Some(0));
+        assert_eq!(registry.message_net_id::<second::Chat>(), Some(1));
+        assert_eq!(registry.message_net_id::<third::Chat>(), None);
+
+        // the value is serialized directly with the message
+        let message = registry.message(second::Chat(3))?;
+        let mut writer = WriteWordBuffer::with_capacity(10);
+        message.encode(&mut writer)?;
+        let bytes = writer.finish_write().to_vec();
+
+        // the registry is needed to decode the message
+        let mut reader = ReadWordBuffer::start_read(&bytes);
+        assert!(DynamicMessage::decode(&mut reader).is_err());
+        let mut reader = ReadWordBuffer::start_read(&bytes);
+        let decoded = DynamicMessage::decode_with(&mut reader, &registry)?;
+        assert_eq!(decoded.net_id, 1);
+        assert_eq!(decoded.value.downcast::<second::Chat>()?.0, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_full_sync_registrations() -> Result<()> {
+        let mut registry = ProtocolRegistry::default();
+        registry.add_component::<Component1>(ComponentSyncMode::Full)?;
+        registry.add_component::<Component2>(ComponentSyncMode::Simple)?;
+        registry.add_interpolated_component::<Component3, LinearInterpolator>()?;
+        assert_eq!(
+            registry
+                .full_sync_registrations()
+                .iter()
+                .map(|c| c.name)
+                .collect::<Vec<_>>(),
+            vec![Component1::NAME, Component3::NAME]
+        );
+        Ok(())
+    }
+}
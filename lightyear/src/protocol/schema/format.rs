@@ -0,0 +1,105 @@
+//! Description of the serde data model of a type, as recorded by the [`Tracer`](super::tracer::Tracer)
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+/// Layout of a value.
+///
+/// Named containers (structs and enums) are described once in [`ProtocolSchema::types`](super::ProtocolSchema::types),
+/// and referenced with [`Format::TypeName`].
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub enum Format {
+    /// The layout could not be traced (for example because the type uses `deserialize_any`)
+    Unknown,
+    /// Bits written by the type's own code instead of serde (for example with the `bitcode` or custom serialization
+    /// modes of `#[derive(Message)]`). There is no length prefix, so the layout is only known to the type itself
+    Opaque,
+    /// Reference to a container of [`ProtocolSchema::types`](super::ProtocolSchema::types)
+    TypeName(String),
+    Unit,
+    Bool,
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    F32,
+    F64,
+    Char,
+    Str,
+    /// Byte array, prefixed by its length
+    Bytes,
+    Option(Box<Format>),
+    Seq(Box<Format>),
+    Map {
+        key: Box<Format>,
+        value: Box<Format>,
+    },
+    Tuple(Vec<Format>),
+}
+
+/// Layout of a named container
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub enum ContainerFormat {
+    UnitStruct,
+    NewTypeStruct(Box<Format>),
+    TupleStruct(Vec<Format>),
+    Struct(Vec<Named<Format>>),
+    /// The variants of the enum, indexed by the variant index that is written on the wire
+    Enum(BTreeMap<u32, Named<VariantFormat>>),
+}
+
+/// Layout of an enum variant
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub enum VariantFormat {
+    /// The layout of the variant could not be traced
+    Unknown,
+    Unit,
+    NewType(Box<Format>),
+    Tuple(Vec<Format>),
+    Struct(Vec<Named<Format>>),
+}
+
+/// A struct field or an enum variant
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Named<T> {
+    pub name: String,
+    pub value: T,
+}
+
+impl Format {
+    /// Returns true if some part of the layout is [`Format::Unknown`]
+    pub(crate) fn is_partial(&self) -> bool {
+        match self {
+            Format::Unknown => true,
+            Format::Option(f) | Format::Seq(f) => f.is_partial(),
+            Format::Map { key, value } => key.is_partial() || value.is_partial(),
+            Format::Tuple(formats) => formats.iter().any(Format::is_partial),
+            _ => false,
+        }
+    }
+}
+
+impl ContainerFormat {
+    /// Returns true if some part of the layout is unknown (not including the referenced containers)
+    pub(crate) fn is_partial(&self) -> bool {
+        match self {
+            ContainerFormat::UnitStruct => false,
+            ContainerFormat::NewTypeStruct(f) => f.is_partial(),
+            ContainerFormat::TupleStruct(formats) => formats.iter().any(Format::is_partial),
+            ContainerFormat::Struct(fields) => fields.iter().any(|f| f.value.is_partial()),
+            ContainerFormat::Enum(variants) => variants.values().any(|v| match &v.value {
+                VariantFormat::Unknown => true,
+                VariantFormat::Unit => false,
+                VariantFormat::NewType(f) => f.is_partial(),
+                VariantFormat::Tuple(formats) => formats.iter().any(Format::is_partial),
+                VariantFormat::Struct(fields) => fields.iter().any(|f| f.value.is_partial()),
+            }),
+        }
+    }
+}
@@ -0,0 +1,295 @@
+//! Machine-readable description of a [`Protocol`], for tools and clients that are not written in Rust.
+//!
+//! The layouts of the messages and components are recorded from their serde `Deserialize` implementation,
+//! which is what lightyear uses to write them with bitcode. The types that are written by their own code instead
+//! (see [`crate::serialize::mode`]) are described as [`Format::Opaque`]. Named containers (structs and enums) are identified by their name,
+//! so two different types with the same name are described by the same entry of [`ProtocolSchema::types`].
+//!
+//! The schema can be exported as JSON with the `schema` feature.
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::channel::builder::{ChannelDirection, ChannelMode};
+use crate::netcode::NETCODE_VERSION;
+use crate::packet::packet::PACKET_FORMAT_VERSION;
+use crate::protocol::registry::{NetId, ProtocolRegistry};
+use crate::protocol::schema::format::{ContainerFormat, Format, VariantFormat};
+use crate::protocol::schema::tracer::Tracer;
+use crate::protocol::{BitSerializable, Protocol};
+
+pub mod format;
+#[doc(hidden)]
+pub mod tracer;
+
+/// Version of the layout of [`ProtocolSchema`]
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Description of the channels, messages and components of a [`Protocol`], that can be exported as JSON
+/// with [`ProtocolSchema::to_json`].
+///
+/// The messages (and components) are sent as the variants of the `#[message_protocol]` (and `#[component_protocol]`) enum:
+/// the net id of a message is the index of its variant.
+/// The messages and components registered with [`AppRegistrationExt`](crate::protocol::registry::AppRegistrationExt)
+/// are sent inside the `DynamicMessage` (and `DynamicComponent`) variant, as their net id followed by the serialized value.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ProtocolSchema {
+    /// Version of the layout of the schema, see [`SCHEMA_VERSION`]
+    pub schema_version: u32,
+    /// Version of the netcode protocol, see [`NETCODE_VERSION`]
+    pub netcode_version: String,
+    /// Version of the format of the lightyear packets, see [`PACKET_FORMAT_VERSION`]
+    pub packet_version: u32,
+    /// Fingerprint that the client sends to the server during the handshake
+    pub fingerprint: u64,
+    pub channels: Vec<ChannelSchema>,
+    pub messages: Vec<TypeSchema>,
+    pub components: Vec<TypeSchema>,
+    pub registered_messages: Vec<TypeSchema>,
+    pub registered_components: Vec<TypeSchema>,
+    /// Layout of the named containers (structs and enums) referenced by [`Format::TypeName`]
+    pub types: BTreeMap<String, ContainerFormat>,
+}
+
+/// Description of a channel
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ChannelSchema {
+    pub net_id: NetId,
+    pub name: String,
+    pub mode: ChannelMode,
+    pub direction: ChannelDirection,
+}
+
+/// Description of a message or component
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TypeSchema {
+    pub net_id: NetId,
+    pub name: String,
+    pub format: Format,
+}
+
+impl ProtocolSchema {
+    pub fn new<P: Protocol>(protocol: &P) -> Self {
+        let mut tracer = Tracer::default();
+        let messages = P::Message::format(&mut tracer);
+        let components = P::Components::format(&mut tracer);
+        let types = tracer.into_types();
+        Self {
+            schema_version: SCHEMA_VERSION,
+            netcode_version: String::from_utf8_lossy(NETCODE_VERSION)
+                .trim_end_matches('\0')
+                .to_string(),
+            packet_version: PACKET_FORMAT_VERSION,
+            fingerprint: protocol.fingerprint(),
+            channels: protocol.channel_registry().schema(),
+            messages: variants(&types, &messages),
+            components: variants(&types, &components),
+            registered_messages: vec![],
+            registered_components: vec![],
+            types,
+        }
+    }
+
+    /// Add the messages and components of the registry to the schema
+    pub fn with_registry(mut self, registry: &ProtocolRegistry) -> Self {
+        let mut tracer = Tracer::default();
+        self.fingerprint = registry.fingerprint(self.fingerprint);
+        self.registered_messages = registry.message_schemas(&mut tracer);
+        self.registered_components = registry.component_schemas(&mut tracer);
+        self.types.extend(tracer.into_types());
+        self
+    }
+
+    /// Serialize the schema as pretty-printed JSON
+    #[cfg(feature = "schema")]
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+/// List the variants of the protocol enum referenced by `format`, as messages or components
+fn variants(types: &BTreeMap<String, ContainerFormat>, format: &Format) -> Vec<TypeSchema> {
+    let Format::TypeName(name) = format else {
+        return vec![];
+    };
+    let Some(ContainerFormat::Enum(variants)) = types.get(name) else {
+        return vec![];
+    };
+    variants
+        .iter()
+        .map(|(index, variant)| TypeSchema {
+            net_id: *index as NetId,
+            name: variant.name.clone(),
+            format: match &variant.value {
+                VariantFormat::NewType(format) => *format.clone(),
+                VariantFormat::Unit => Format::Unit,
+                _ => Format::Unknown,
+            },
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use crate::_reexport::{ReadBuffer, ReadWordBuffer, WriteBuffer, WriteWordBuffer};
+    use crate::client::components::ComponentSyncMode;
+    use crate::protocol::channel::ChannelKind;
+    use crate::protocol::schema::format::Named;
+    use crate::tests::protocol::*;
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Shape {
+        Empty,
+        Circle(f32),
+        Polygon {
+            points: Vec<(f32, f32)>,
+            name: Option<String>,
+        },
+        Group(Vec<Shape>),
+    }
+
+    #[test]
+    fn test_trace_enum() -> anyhow::Result<()> {
+        let mut tracer = Tracer::default();
+        assert_eq!(
+            tracer.trace::<Shape>(),
+            Format::TypeName("Shape".to_string())
+        );
+        let types = tracer.into_types();
+        let ContainerFormat::Enum(variants) = &types["Shape"] else {
+            panic!("Shape should be an enum");
+        };
+        let variants: Vec<_> = variants.values().map(|v| v.value.clone()).collect();
+        assert_eq!(
+            variants,
+            vec![
+                VariantFormat::Unit,
+                VariantFormat::NewType(Box::new(Format::F32)),
+                VariantFormat::Struct(vec![
+                    Named {
+                        name: "points".to_string(),
+                        value: Format::Seq(Box::new(Format::Tuple(vec![Format::F32, Format::F32]))),
+                    },
+                    Named {
+                        name: "name".to_string(),
+                        value: Format::Option(Box::new(Format::Str)),
+                    },
+                ]),
+                // recursive types are referenced by name
+                VariantFormat::NewType(Box::new(Format::Seq(Box::new(Format::TypeName(
+                    "Shape".to_string()
+                ))))),
+            ]
+        );
+
+        // every variant can be sent
+        let shape = Shape::Group(vec![
+            Shape::Empty,
+            Shape::Circle(1.0),
+            Shape::Polygon {
+                points: vec![(0.0, 0.0), (1.0, 2.0)],
+                name: Some("triangle".to_string()),
+            },
+        ]);
+        let mut writer = WriteWordBuffer::with_capacity(50);
+        writer.serialize(&shape)?;
+        let mut reader = ReadWordBuffer::start_read(writer.finish_write());
+        assert_eq!(reader.deserialize::<Shape>()?, shape);
+        Ok(())
+    }
+
+    #[test]
+    fn test_protocol_schema() {
+        let protocol = protocol();
+        let schema = protocol.schema();
+        assert_eq!(schema.fingerprint, protocol.fingerprint());
+        assert_eq!(schema.netcode_version, "NETCODE 1.03");
+
+        // the channels used internally by lightyear are registered first
+        let channel1 = schema
+            .channels
+            .iter()
+            .find(|c| c.name == "Channel1")
+            .unwrap();
+        assert_eq!(
+            protocol
+                .channel_registry()
+                .get_kind_from_net_id(channel1.net_id),
+            Some(&ChannelKind::of::<Channel1>())
+        );
+        assert_eq!(channel1.mode, ChannelMode::UnorderedUnreliable);
+
+        assert_eq!(schema.messages[0].name, "Message1");
+        assert_eq!(schema.messages[0].net_id, 0);
+        assert_eq!(
+            schema.messages[0].format,
+            Format::TypeName("Message1".to_string())
+        );
+        assert_eq!(
+            schema.types["Message1"],
+            ContainerFormat::NewTypeStruct(Box::new(Format::Str))
+        );
+        assert_eq!(schema.messages[1].name, "Message2");
+
+        let component5 = schema
+            .components
+            .iter()
+            .find(|c| c.name == "Component5")
+            .unwrap();
+        assert_eq!(
+            component5.format,
+            Format::TypeName("Component5".to_string())
+        );
+        assert_eq!(
+            schema.types["Component5"],
+            ContainerFormat::Struct(vec![
+                Named {
+                    name: "x".to_string(),
+                    value: Format::F32,
+                },
+                Named {
+                    name: "y".to_string(),
+                    value: Format::F32,
+                },
+            ])
+        );
+    }
+
+    #[cfg(feature = "schema")]
+    #[test]
+    fn test_schema_json() {
+        let schema = protocol().schema();
+        let json: serde_json::Value = serde_json::from_str(&schema.to_json().unwrap()).unwrap();
+        assert_eq!(json["messages"][0]["name"], "Message1");
+    }
+
+    #[test]
+    fn test_registry_schema() {
+        let protocol = protocol();
+        let mut registry = ProtocolRegistry::default();
+        registry.add_message::<Message2>().unwrap();
+        registry
+            .add_component::<Component5>(ComponentSyncMode::Simple)
+            .unwrap();
+        let schema = protocol.schema().with_registry(&registry);
+
+        assert_eq!(
+            schema.fingerprint,
+            registry.fingerprint(protocol.fingerprint())
+        );
+        assert_eq!(
+            schema.registered_messages,
+            vec![TypeSchema {
+                net_id: 0,
+                name: "Message2".to_string(),
+                format: Format::TypeName("Message2".to_string()),
+            }]
+        );
+        assert_eq!(schema.registered_components[0].name, "Component5");
+        assert!(schema.types.contains_key("Component5"));
+    }
+}
@@ -0,0 +1,497 @@
+//! Records the layout of a type by deserializing it with a [`Deserializer`](serde::Deserializer)
+//! that keeps track of the methods called by its `Deserialize` implementation.
+//!
+//! The tracer returns placeholder values (0, empty strings, sequences with a single element, etc.) so that
+//! the whole type can be visited. Each call to [`Tracer::trace`] deserializes the type multiple times,
+//! choosing a different variant every time an enum is found, until the layout of all the variants is known.
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter};
+
+use serde::de::value::U32Deserializer;
+use serde::de::{
+    DeserializeOwned, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess,
+    VariantAccess, Visitor,
+};
+
+use crate::protocol::schema::format::{ContainerFormat, Format, Named, VariantFormat};
+
+/// Maximum number of times a type is deserialized while looking for the variants of its enums
+const MAX_PASSES: usize = 1000;
+
+/// Records the layout of types from their `Deserialize` implementation
+#[derive(Default)]
+pub struct Tracer {
+    /// Layout of the named containers that were found
+    types: BTreeMap<String, ContainerFormat>,
+    enums: HashMap<&'static str, EnumProgress>,
+    /// Containers that are currently being traced, used to detect recursive types
+    stack: Vec<&'static str>,
+}
+
+/// Variants of an enum whose layout is known
+struct EnumProgress {
+    variants: &'static [&'static str],
+    formats: Vec<Option<VariantFormat>>,
+    /// Next variant to visit once all variants are known, to reach the enums that are nested in the other variants
+    next: usize,
+}
+
+impl EnumProgress {
+    fn is_complete(&self) -> bool {
+        self.formats.iter().all(Option::is_some)
+    }
+
+    fn next_variant(&mut self) -> u32 {
+        let index = self
+            .formats
+            .iter()
+            .position(Option::is_none)
+            .unwrap_or_else(|| {
+                self.next = (self.next + 1) % self.formats.len();
+                self.next
+            });
+        index as u32
+    }
+
+    fn container(&self) -> ContainerFormat {
+        ContainerFormat::Enum(
+            self.variants
+                .iter()
+                .zip(self.formats.iter())
+                .enumerate()
+                .map(|(index, (name, format))| {
+                    (
+                        index as u32,
+                        Named {
+                            name: name.to_string(),
+                            value: format.clone().unwrap_or(VariantFormat::Unknown),
+                        },
+                    )
+                })
+                .collect(),
+        )
+    }
+}
+
+impl Tracer {
+    /// Record the layout of `T`.
+    ///
+    /// The parts of the layout that cannot be traced are [`Format::Unknown`].
+    pub fn trace<T: DeserializeOwned>(&mut self) -> Format {
+        let mut format = Format::Unknown;
+        for _ in 0..MAX_PASSES {
+            format = Format::Unknown;
+            // errors only mean that the rest of the value could not be visited;
+            // what was recorded before the error is still valid
+            let _ = T::deserialize(Deserializer {
+                tracer: self,
+                format: &mut format,
+            });
+            if self.enums.values().all(EnumProgress::is_complete) {
+                break;
+            }
+        }
+        format
+    }
+
+    /// Record the layout of a container whose bits are not written by serde, and return a reference to it.
+    ///
+    /// Used by the protocol enums, that write the index of the variant followed by the value.
+    pub fn container(&mut self, name: &'static str, container: ContainerFormat) -> Format {
+        self.record(name, container);
+        Format::TypeName(name.to_string())
+    }
+
+    /// Layout of the named containers that were found while tracing
+    pub(crate) fn into_types(self) -> BTreeMap<String, ContainerFormat> {
+        self.types
+    }
+
+    fn enter(&mut self, name: &'static str) -> Result<(), Error> {
+        if self.stack.contains(&name) {
+            return Err(Error(format!("recursive type {name} cannot be traced")));
+        }
+        self.stack.push(name);
+        Ok(())
+    }
+
+    fn exit(&mut self) {
+        self.stack.pop();
+    }
+
+    /// Record the layout of a struct; the layouts that are more complete are kept
+    fn record(&mut self, name: &'static str, container: ContainerFormat) {
+        if container.is_partial() && self.types.contains_key(name) {
+            return;
+        }
+        self.types.insert(name.to_string(), container);
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Error(String);
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl serde::de::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+fn unknown<T>(format: &mut Format, method: &str) -> Result<T, Error> {
+    *format = Format::Unknown;
+    Err(Error(format!("{method} is not supported")))
+}
+
+/// Deserializer that records the layout of the value in `format`
+struct Deserializer<'a> {
+    tracer: &'a mut Tracer,
+    format: &'a mut Format,
+}
+
+macro_rules! trace_primitive {
+    ($method:ident, $format:ident, $visit:ident, $value:expr) => {
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            *self.format = Format::$format;
+            visitor.$visit($value)
+        }
+    };
+}
+
+impl<'de, 'a> serde::Deserializer<'de> for Deserializer<'a> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Error> {
+        unknown(self.format, "deserialize_any")
+    }
+
+    trace_primitive!(deserialize_bool, Bool, visit_bool, false);
+    trace_primitive!(deserialize_i8, I8, visit_i8, 0);
+    trace_primitive!(deserialize_i16, I16, visit_i16, 0);
+    trace_primitive!(deserialize_i32, I32, visit_i32, 0);
+    trace_primitive!(deserialize_i64, I64, visit_i64, 0);
+    trace_primitive!(deserialize_i128, I128, visit_i128, 0);
+    trace_primitive!(deserialize_u8, U8, visit_u8, 0);
+    trace_primitive!(deserialize_u16, U16, visit_u16, 0);
+    trace_primitive!(deserialize_u32, U32, visit_u32, 0);
+    trace_primitive!(deserialize_u64, U64, visit_u64, 0);
+    trace_primitive!(deserialize_u128, U128, visit_u128, 0);
+    trace_primitive!(deserialize_f32, F32, visit_f32, 0.0);
+    trace_primitive!(deserialize_f64, F64, visit_f64, 0.0);
+    trace_primitive!(deserialize_char, Char, visit_char, 'a');
+    trace_primitive!(deserialize_str, Str, visit_str, "");
+    trace_primitive!(deserialize_string, Str, visit_str, "");
+    trace_primitive!(deserialize_bytes, Bytes, visit_bytes, &[]);
+    trace_primitive!(deserialize_byte_buf, Bytes, visit_bytes, &[]);
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        *self.format = Format::Unit;
+        visitor.visit_unit()
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let mut inner = Format::Unknown;
+        let result = visitor.visit_some(Deserializer {
+            tracer: &mut *self.tracer,
+            format: &mut inner,
+        });
+        *self.format = Format::Option(Box::new(inner));
+        result
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        *self.format = Format::TypeName(name.to_string());
+        self.tracer.record(name, ContainerFormat::UnitStruct);
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        *self.format = Format::TypeName(name.to_string());
+        self.tracer.enter(name)?;
+        let mut inner = Format::Unknown;
+        let result = visitor.visit_newtype_struct(Deserializer {
+            tracer: &mut *self.tracer,
+            format: &mut inner,
+        });
+        self.tracer.exit();
+        self.tracer
+            .record(name, ContainerFormat::NewTypeStruct(Box::new(inner)));
+        result
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        // visit a single element to get its layout
+        let mut element = Format::Unknown;
+        let result = visitor.visit_seq(SeqTracer {
+            tracer: &mut *self.tracer,
+            formats: std::iter::once(&mut element),
+        });
+        *self.format = Format::Seq(Box::new(element));
+        result
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Error> {
+        let mut elements = vec![Format::Unknown; len];
+        let result = visitor.visit_seq(SeqTracer {
+            tracer: &mut *self.tracer,
+            formats: elements.iter_mut(),
+        });
+        *self.format = Format::Tuple(elements);
+        result
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        *self.format = Format::TypeName(name.to_string());
+        self.tracer.enter(name)?;
+        let mut elements = vec![Format::Unknown; len];
+        let result = visitor.visit_seq(SeqTracer {
+            tracer: &mut *self.tracer,
+            formats: elements.iter_mut(),
+        });
+        self.tracer.exit();
+        self.tracer
+            .record(name, ContainerFormat::TupleStruct(elements));
+        result
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        // visit a single entry to get the layout of the keys and values
+        let mut key = Format::Unknown;
+        let mut value = Format::Unknown;
+        let result = visitor.visit_map(MapTracer {
+            tracer: &mut *self.tracer,
+            key: Some(&mut key),
+            value: Some(&mut value),
+        });
+        *self.format = Format::Map {
+            key: Box::new(key),
+            value: Box::new(value),
+        };
+        result
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        *self.format = Format::TypeName(name.to_string());
+        self.tracer.enter(name)?;
+        let mut fields = named_unknowns(fields);
+        let result = visitor.visit_seq(SeqTracer {
+            tracer: &mut *self.tracer,
+            formats: fields.iter_mut().map(|field| &mut field.value),
+        });
+        self.tracer.exit();
+        self.tracer.record(name, ContainerFormat::Struct(fields));
+        result
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        *self.format = Format::TypeName(name.to_string());
+        if variants.is_empty() {
+            self.tracer
+                .record(name, ContainerFormat::Enum(BTreeMap::new()));
+            return Err(Error(format!("enum {name} has no variants")));
+        }
+        self.tracer.enter(name)?;
+        let index = self
+            .tracer
+            .enums
+            .entry(name)
+            .or_insert_with(|| EnumProgress {
+                variants,
+                formats: vec![None; variants.len()],
+                next: 0,
+            })
+            .next_variant();
+        let mut variant = VariantFormat::Unknown;
+        let result = visitor.visit_enum(EnumTracer {
+            tracer: &mut *self.tracer,
+            index,
+            format: &mut variant,
+        });
+        self.tracer.exit();
+        let progress = self.tracer.enums.get_mut(name).unwrap();
+        progress.formats[index as usize] = Some(variant);
+        let container = progress.container();
+        self.tracer.types.insert(name.to_string(), container);
+        result
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Error> {
+        unknown(self.format, "deserialize_identifier")
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Error> {
+        unknown(self.format, "deserialize_ignored_any")
+    }
+
+    fn is_human_readable(&self) -> bool {
+        // the messages are serialized with bitcode, which is not human-readable
+        false
+    }
+}
+
+fn named_unknowns(names: &[&str]) -> Vec<Named<Format>> {
+    names
+        .iter()
+        .map(|name| Named {
+            name: name.to_string(),
+            value: Format::Unknown,
+        })
+        .collect()
+}
+
+/// Visits the elements of a sequence, tuple or struct, recording the layout of each element
+struct SeqTracer<'a, I> {
+    tracer: &'a mut Tracer,
+    formats: I,
+}
+
+impl<'de, 'a, I: Iterator<Item = &'a mut Format> + ExactSizeIterator> SeqAccess<'de>
+    for SeqTracer<'a, I>
+{
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        let Some(format) = self.formats.next() else {
+            return Ok(None);
+        };
+        seed.deserialize(Deserializer {
+            tracer: &mut *self.tracer,
+            format,
+        })
+        .map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.formats.len())
+    }
+}
+
+/// Visits a single entry of a map
+struct MapTracer<'a> {
+    tracer: &'a mut Tracer,
+    key: Option<&'a mut Format>,
+    value: Option<&'a mut Format>,
+}
+
+impl<'de, 'a> MapAccess<'de> for MapTracer<'a> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        let Some(format) = self.key.take() else {
+            return Ok(None);
+        };
+        seed.deserialize(Deserializer {
+            tracer: &mut *self.tracer,
+            format,
+        })
+        .map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let format = self
+            .value
+            .take()
+            .ok_or_else(|| Error("the map value was already visited".to_string()))?;
+        seed.deserialize(Deserializer {
+            tracer: &mut *self.tracer,
+            format,
+        })
+    }
+}
+
+/// Visits the variant `index` of an enum
+struct EnumTracer<'a> {
+    tracer: &'a mut Tracer,
+    index: u32,
+    format: &'a mut VariantFormat,
+}
+
+impl<'de, 'a> EnumAccess<'de> for EnumTracer<'a> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self), Error> {
+        let index: U32Deserializer<Error> = self.index.into_deserializer();
+        let value = seed.deserialize(index)?;
+        Ok((value, self))
+    }
+}
+
+impl<'de, 'a> VariantAccess<'de> for EnumTracer<'a> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        *self.format = VariantFormat::Unit;
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        let mut inner = Format::Unknown;
+        let result = seed.deserialize(Deserializer {
+            tracer: &mut *self.tracer,
+            format: &mut inner,
+        });
+        *self.format = VariantFormat::NewType(Box::new(inner));
+        result
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Error> {
+        let mut elements = vec![Format::Unknown; len];
+        let result = visitor.visit_seq(SeqTracer {
+            tracer: &mut *self.tracer,
+            formats: elements.iter_mut(),
+        });
+        *self.format = VariantFormat::Tuple(elements);
+        result
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let mut fields = named_unknowns(fields);
+        let result = visitor.visit_seq(SeqTracer {
+            tracer: &mut *self.tracer,
+            formats: fields.iter_mut().map(|field| &mut field.value),
+        });
+        *self.format = VariantFormat::Struct(fields);
+        result
+    }
+}
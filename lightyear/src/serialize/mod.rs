@@ -1,4 +1,6 @@
 //! Serialization and deserialization of types
+pub mod mode;
+pub mod quantize;
 pub mod reader;
 pub mod wordbuffer;
 pub mod writer;
@@ -0,0 +1,170 @@
+//! Serialization modes that can be selected with the `#[message(...)]` attribute of `#[derive(Message)]`.
+//!
+//! By default a type is serialized with its `serde` implementation. It can instead be serialized with:
+//! - `#[message(bitcode)]`: its native bitcode [`Encode`]/[`Decode`] implementation
+//! - `#[message(serialize_with = "path", deserialize_with = "path")]`: a pair of custom functions
+//!   `fn(&T, &mut WriteWordBuffer) -> anyhow::Result<()>` and `fn(&mut ReadWordBuffer) -> anyhow::Result<T>`
+//!
+//! In both cases the derive implements [`BitSerializable`] for the type instead of `Serialize`/`Deserialize`:
+//! the bits are written directly into the buffer of the packet, without a length prefix or any padding.
+//!
+//! The protocol enums, and the lightyear messages that contain the protocol types, are written through
+//! [`BitSerializable`] as well, with the helpers of this module. They produce the same bits as `serde` would.
+use bitcode::encoding::{Fixed, Gamma};
+use bitcode::{Decode, Encode};
+
+use crate::protocol::registry::ProtocolRegistry;
+use crate::protocol::BitSerializable;
+use crate::serialize::reader::ReadBuffer;
+use crate::serialize::wordbuffer::reader::ReadWordBuffer;
+use crate::serialize::wordbuffer::writer::WriteWordBuffer;
+use crate::serialize::writer::WriteBuffer;
+
+/// Encode function used by `#[message(bitcode)]`
+pub fn bitcode_encode<T: Encode + ?Sized>(
+    value: &T,
+    writer: &mut WriteWordBuffer,
+) -> anyhow::Result<()> {
+    writer.encode(value, Fixed)
+}
+
+/// Decode function used by `#[message(bitcode)]`
+pub fn bitcode_decode<T: Decode>(reader: &mut ReadWordBuffer) -> anyhow::Result<T> {
+    reader.decode::<T>(Fixed)
+}
+
+/// Write the index of an enum variant, the same way serde enums are serialized
+pub fn encode_variant_index(index: u32, writer: &mut WriteWordBuffer) -> anyhow::Result<()> {
+    writer.encode(&index, Gamma)
+}
+
+/// Read the index of an enum variant written with [`encode_variant_index`]
+pub fn decode_variant_index(reader: &mut ReadWordBuffer) -> anyhow::Result<u32> {
+    reader.decode::<u32>(Gamma)
+}
+
+/// Write the length of a sequence, the same way serde sequences are serialized.
+/// Used to write sequences whose elements are written with custom code
+pub fn encode_len(len: usize, writer: &mut WriteWordBuffer) -> anyhow::Result<()> {
+    writer.encode(&len, Gamma)
+}
+
+/// Read the length of a sequence written with [`encode_len`].
+/// The length is not trusted, so it should not be used to allocate the sequence upfront
+pub fn decode_len(reader: &mut ReadWordBuffer) -> anyhow::Result<usize> {
+    reader.decode::<usize>(Gamma)
+}
+
+/// Write a sequence of values, the same way serde sequences are serialized (the length followed by the values)
+pub fn encode_seq<T: BitSerializable>(
+    values: &[T],
+    writer: &mut WriteWordBuffer,
+) -> anyhow::Result<()> {
+    encode_len(values.len(), writer)?;
+    values.iter().try_for_each(|value| value.encode(writer))
+}
+
+/// Read a sequence of values written with [`encode_seq`]
+pub fn decode_seq<T: BitSerializable>(reader: &mut ReadWordBuffer) -> anyhow::Result<Vec<T>> {
+    decode_seq_with(reader, &ProtocolRegistry::default())
+}
+
+/// Read a sequence of values written with [`encode_seq`], that can contain registered messages or components
+pub fn decode_seq_with<T: BitSerializable>(
+    reader: &mut ReadWordBuffer,
+    registry: &ProtocolRegistry,
+) -> anyhow::Result<Vec<T>> {
+    let len = decode_len(reader)?;
+    // the length is not trusted, so we don't allocate it upfront
+    let mut values = Vec::new();
+    for _ in 0..len {
+        values.push(T::decode_with(reader, registry)?);
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcode::encoding::Fixed;
+    use bitcode::{Decode, Encode};
+
+    use crate::_reexport::{
+        BitSerializable, MessageInternal, ReadBuffer, ReadWordBuffer, WriteBuffer, WriteWordBuffer,
+    };
+    use crate::serialize::reader::BitRead;
+    use crate::serialize::writer::BitWrite;
+
+    #[derive(MessageInternal, Encode, Decode, Clone, Debug, PartialEq)]
+    #[message(bitcode)]
+    struct BitcodeMessage {
+        #[bitcode_hint(expected_range = "0..16")]
+        value: u32,
+        flag: bool,
+        history: Vec<u16>,
+    }
+
+    #[derive(MessageInternal, Clone, Debug, PartialEq)]
+    #[message(serialize_with = "write_flags", deserialize_with = "read_flags")]
+    struct Flags([bool; 3]);
+
+    fn write_flags(flags: &Flags, writer: &mut WriteWordBuffer) -> anyhow::Result<()> {
+        flags.0.iter().for_each(|flag| writer.write_bit(*flag));
+        Ok(())
+    }
+
+    fn read_flags(reader: &mut ReadWordBuffer) -> anyhow::Result<Flags> {
+        Ok(Flags([
+            reader.read_bit()?,
+            reader.read_bit()?,
+            reader.read_bit()?,
+        ]))
+    }
+
+    #[test]
+    fn test_serialize_modes() -> anyhow::Result<()> {
+        let message = BitcodeMessage {
+            value: 7,
+            flag: true,
+            history: vec![3, 300],
+        };
+        let flags = Flags([true, false, true]);
+
+        // the values are written directly into the buffer, without a length prefix or padding
+        let mut native = WriteWordBuffer::with_capacity(10);
+        native.encode(&message, Fixed)?;
+        let mut writer = WriteWordBuffer::with_capacity(10);
+        BitSerializable::encode(&message, &mut writer)?;
+        assert_eq!(writer.num_bits_written(), native.num_bits_written());
+        flags.encode(&mut writer)?;
+        assert_eq!(writer.num_bits_written(), native.num_bits_written() + 3);
+        super::encode_seq(&[flags.clone(), flags.clone()], &mut writer)?;
+        let bytes = writer.finish_write();
+
+        let mut reader = ReadWordBuffer::start_read(bytes);
+        assert_eq!(
+            <BitcodeMessage as BitSerializable>::decode(&mut reader)?,
+            message
+        );
+        assert_eq!(Flags::decode(&mut reader)?, flags);
+        assert_eq!(super::decode_seq::<Flags>(&mut reader)?, vec![flags; 2]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_same_bits_as_serde() -> anyhow::Result<()> {
+        #[derive(serde::Serialize)]
+        enum Value {
+            A,
+            B(Vec<u16>),
+        }
+
+        let mut serde = WriteWordBuffer::with_capacity(10);
+        serde.serialize(&Value::B(vec![3, 300]))?;
+        let mut writer = WriteWordBuffer::with_capacity(10);
+        super::encode_variant_index(1, &mut writer)?;
+        super::encode_seq(&[3_u16, 300], &mut writer)?;
+        assert_eq!(writer.num_bits_written(), serde.num_bits_written());
+        assert_eq!(writer.finish_write(), serde.finish_write());
+        Ok(())
+    }
+}
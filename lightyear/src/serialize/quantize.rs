@@ -0,0 +1,345 @@
+//! Encodings used by the `#[lightyear(...)]` field attributes of `#[derive(Message)]`.
+//!
+//! They map a field to a small unsigned integer that is sent instead of the field itself,
+//! using only the number of bits needed to hold it (see [`Bits`]).
+use std::f64::consts::FRAC_1_SQRT_2;
+use std::fmt::Formatter;
+
+use bevy::math::Vec3A;
+use bevy::prelude::{Quat, Vec2, Vec3, Vec4};
+use serde::de::{Error, SeqAccess, Visitor};
+use serde::ser::SerializeTuple;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// An unsigned integer that is serialized with exactly `N` bits (at most 64).
+///
+/// It is serialized as a tuple of `N / 8` `u8`s followed by `N % 8` `bool`s, starting from the least
+/// significant bits: bitcode writes each `u8` with 8 bits and each `bool` with a single bit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bits<const N: usize>(pub u64);
+
+impl<const N: usize> Bits<N> {
+    const NUM_BYTES: usize = N / 8;
+    const LEN: usize = N / 8 + N % 8;
+}
+
+impl<const N: usize> Serialize for Bits<N> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut tuple = serializer.serialize_tuple(Self::LEN)?;
+        for i in 0..Self::NUM_BYTES {
+            tuple.serialize_element(&((self.0 >> (8 * i)) as u8))?;
+        }
+        for i in 8 * Self::NUM_BYTES..N {
+            tuple.serialize_element(&((self.0 >> i) & 1 == 1))?;
+        }
+        tuple.end()
+    }
+}
+
+impl<'de, const N: usize> Deserialize<'de> for Bits<N> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct BitsVisitor<const N: usize>;
+
+        impl<'de, const N: usize> Visitor<'de> for BitsVisitor<N> {
+            type Value = Bits<N>;
+
+            fn expecting(&self, formatter: &mut Formatter) -> std::fmt::Result {
+                write!(formatter, "an integer of {N} bits")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut value = 0;
+                for i in 0..Bits::<N>::NUM_BYTES {
+                    let byte: u8 = seq
+                        .next_element()?
+                        .ok_or_else(|| A::Error::invalid_length(i, &self))?;
+                    value |= (byte as u64) << (8 * i);
+                }
+                for i in 8 * Bits::<N>::NUM_BYTES..N {
+                    let bit: bool = seq
+                        .next_element()?
+                        .ok_or_else(|| A::Error::invalid_length(i, &self))?;
+                    value |= (bit as u64) << i;
+                }
+                Ok(Bits(value))
+            }
+        }
+
+        deserializer.deserialize_tuple(Self::LEN, BitsVisitor::<N>)
+    }
+}
+
+/// Number of quantization steps needed to cover `[min, max]` with the given precision
+pub fn num_steps(min: f64, max: f64, precision: f64) -> u64 {
+    ((max - min) / precision).ceil() as u64
+}
+
+/// Map `value` to an integer in `0..=num_steps(min, max, precision)`.
+///
+/// Values outside of `[min, max]` are clamped.
+pub fn quantize_float(value: f64, min: f64, max: f64, precision: f64) -> u64 {
+    let value = if value.is_nan() {
+        min
+    } else {
+        value.clamp(min, max)
+    };
+    (((value - min) / precision).round() as u64).min(num_steps(min, max, precision))
+}
+
+/// Inverse of [`quantize_float`]: the result is within `precision / 2` of the original value
+pub fn dequantize_float(quantized: u64, min: f64, max: f64, precision: f64) -> f64 {
+    (min + quantized as f64 * precision).min(max)
+}
+
+/// Types that can be sent with the `quantize` encoding: each float component is quantized with
+/// [`quantize_float`] and sent with `N` bits.
+///
+/// It is implemented for `f32`, `f64`, `Vec2`, `Vec3`, `Vec3A` and `Vec4`.
+pub trait Quantize: Sized {
+    /// Wire representation of the value, with one `T` per component
+    type Wire<T>;
+
+    fn quantize<const N: usize>(self, min: f64, max: f64, precision: f64) -> Self::Wire<Bits<N>>;
+
+    fn dequantize<const N: usize>(
+        wire: Self::Wire<Bits<N>>,
+        min: f64,
+        max: f64,
+        precision: f64,
+    ) -> Self;
+}
+
+macro_rules! impl_quantize_scalar {
+    ($t:ty) => {
+        impl Quantize for $t {
+            type Wire<T> = T;
+
+            fn quantize<const N: usize>(
+                self,
+                min: f64,
+                max: f64,
+                precision: f64,
+            ) -> Self::Wire<Bits<N>> {
+                Bits(quantize_float(self as f64, min, max, precision))
+            }
+
+            fn dequantize<const N: usize>(
+                wire: Self::Wire<Bits<N>>,
+                min: f64,
+                max: f64,
+                precision: f64,
+            ) -> Self {
+                dequantize_float(wire.0, min, max, precision) as $t
+            }
+        }
+    };
+}
+
+macro_rules! impl_quantize_vector {
+    ($t:ty, $len:literal) => {
+        impl Quantize for $t {
+            type Wire<T> = [T; $len];
+
+            fn quantize<const N: usize>(
+                self,
+                min: f64,
+                max: f64,
+                precision: f64,
+            ) -> Self::Wire<Bits<N>> {
+                self.to_array()
+                    .map(|v| Bits(quantize_float(v as f64, min, max, precision)))
+            }
+
+            fn dequantize<const N: usize>(
+                wire: Self::Wire<Bits<N>>,
+                min: f64,
+                max: f64,
+                precision: f64,
+            ) -> Self {
+                <$t>::from_array(wire.map(|v| dequantize_float(v.0, min, max, precision) as f32))
+            }
+        }
+    };
+}
+
+impl_quantize_scalar!(f32);
+impl_quantize_scalar!(f64);
+impl_quantize_vector!(Vec2, 2);
+impl_quantize_vector!(Vec3, 3);
+impl_quantize_vector!(Vec3A, 3);
+impl_quantize_vector!(Vec4, 4);
+
+/// Map an integer in `[min, max]` to an integer in `0..=(max - min)`.
+///
+/// Values outside of `[min, max]` are clamped.
+pub fn encode_range(value: i128, min: i128, max: i128) -> u64 {
+    (value.clamp(min, max) - min) as u64
+}
+
+/// Inverse of [`encode_range`]
+pub fn decode_range(encoded: u64, min: i128, max: i128) -> i128 {
+    (min + encoded as i128).min(max)
+}
+
+/// Number of bits used by [`encode_quat`]
+pub fn smallest_three_bits(bits: u32) -> u32 {
+    2 + 3 * bits
+}
+
+/// Encode a unit quaternion with the 'smallest three' compression.
+///
+/// The largest component is dropped (it can be recovered because the quaternion is normalized),
+/// and the 3 other components, which are in `[-1/sqrt(2), 1/sqrt(2)]`, are quantized with `bits` bits each.
+/// The result uses `2 + 3 * bits` bits: the index of the dropped component followed by the 3 quantized components.
+pub fn encode_quat(quat: Quat, bits: u32) -> u64 {
+    let components = Vec4::from(quat)
+        .try_normalize()
+        .unwrap_or(Vec4::W)
+        .to_array();
+    let largest = (0..4)
+        .max_by(|a, b| components[*a].abs().total_cmp(&components[*b].abs()))
+        .unwrap();
+    // q and -q represent the same rotation: flip the quaternion so that the dropped component is positive
+    let sign = if components[largest] < 0.0 { -1.0 } else { 1.0 };
+    let precision = 2.0 * FRAC_1_SQRT_2 / ((1u64 << bits) - 1) as f64;
+    let mut encoded = largest as u64;
+    for (i, component) in components.iter().enumerate() {
+        if i != largest {
+            let quantized = quantize_float(
+                (sign * component) as f64,
+                -FRAC_1_SQRT_2,
+                FRAC_1_SQRT_2,
+                precision,
+            );
+            encoded = (encoded << bits) | quantized;
+        }
+    }
+    encoded
+}
+
+/// Inverse of [`encode_quat`]
+pub fn decode_quat(encoded: u64, bits: u32) -> Quat {
+    let largest = (encoded >> (3 * bits)) as usize & 0b11;
+    let precision = 2.0 * FRAC_1_SQRT_2 / ((1u64 << bits) - 1) as f64;
+    let mask = (1u64 << bits) - 1;
+    let mut components = [0.0; 4];
+    let mut shift = 3 * bits;
+    let mut sum_squares = 0.0;
+    for (i, component) in components.iter_mut().enumerate() {
+        if i != largest {
+            shift -= bits;
+            let quantized = (encoded >> shift) & mask;
+            *component =
+                dequantize_float(quantized, -FRAC_1_SQRT_2, FRAC_1_SQRT_2, precision) as f32;
+            sum_squares += *component * *component;
+        }
+    }
+    components[largest] = (1.0 - sum_squares).max(0.0).sqrt();
+    Quat::from_array(components).normalize()
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::Vec2;
+
+    use crate::_reexport::{
+        MessageInternal, ReadBuffer, ReadWordBuffer, WriteBuffer, WriteWordBuffer,
+    };
+
+    use super::*;
+
+    #[derive(MessageInternal, Debug, PartialEq, Clone)]
+    struct PlayerState {
+        #[lightyear(quantize(min = -1000.0, max = 1000.0, precision = 0.01))]
+        position: Vec2,
+        #[lightyear(quantize(min = 0.0, max = 10.0, precision = 0.1))]
+        speed: f32,
+        #[lightyear(range(-10..=100))]
+        health: i32,
+        #[lightyear(smallest_three)]
+        rotation: Quat,
+        name: String,
+    }
+
+    #[derive(MessageInternal, Debug, PartialEq, Clone)]
+    struct Level(#[lightyear(range(0..16))] u64);
+
+    #[test]
+    fn test_quantize_float() {
+        let (min, max, precision) = (-1000.0, 1000.0, 0.01);
+        assert_eq!(num_steps(min, max, precision), 200_000);
+        for value in [-1000.0, -12.345, 0.0, 0.004, 999.99, 1000.0] {
+            let quantized = quantize_float(value, min, max, precision);
+            assert!(quantized <= 200_000);
+            let decoded = dequantize_float(quantized, min, max, precision);
+            assert!((decoded - value).abs() <= precision / 2.0 + 1e-9);
+        }
+        // values out of range are clamped
+        assert_eq!(quantize_float(2000.0, min, max, precision), 200_000);
+        assert_eq!(quantize_float(-2000.0, min, max, precision), 0);
+    }
+
+    #[test]
+    fn test_encode_range() {
+        assert_eq!(encode_range(-5, -5, 10), 0);
+        assert_eq!(encode_range(10, -5, 10), 15);
+        assert_eq!(encode_range(100, -5, 10), 15);
+        assert_eq!(decode_range(encode_range(3, -5, 10), -5, 10), 3);
+    }
+
+    #[test]
+    fn test_encode_quat() {
+        for bits in [10, 15] {
+            let max_error = 2.0 * FRAC_1_SQRT_2 as f32 / ((1u64 << bits) - 1) as f32;
+            for quat in [
+                Quat::IDENTITY,
+                Quat::from_rotation_y(2.0),
+                Quat::from_euler(bevy::prelude::EulerRot::XYZ, 0.3, -1.2, 2.5),
+                -Quat::from_rotation_z(0.7),
+            ] {
+                let encoded = encode_quat(quat, bits);
+                assert!(encoded < 1 << smallest_three_bits(bits));
+                let decoded = decode_quat(encoded, bits);
+                // q and -q are the same rotation
+                assert!(decoded.dot(quat).abs() > 1.0 - max_error);
+            }
+        }
+    }
+
+    #[test]
+    fn test_encoded_fields() -> anyhow::Result<()> {
+        let state = PlayerState {
+            position: Vec2::new(-123.456, 999.99),
+            speed: 2.72,
+            health: 42,
+            rotation: Quat::from_rotation_y(1.0),
+            name: "player".to_string(),
+        };
+        let mut writer = WriteWordBuffer::with_capacity(100);
+        writer.serialize(&state)?;
+        // position: 2 * 18 bits, speed: 7 bits, health: 7 bits, rotation: 32 bits (instead of 8 + 4 + 4 + 16 bytes)
+        let mut plain_writer = WriteWordBuffer::with_capacity(100);
+        plain_writer.serialize(&state.name)?;
+        assert_eq!(
+            writer.num_bits_written() - plain_writer.num_bits_written(),
+            2 * 18 + 7 + 7 + 32
+        );
+        let bytes = writer.finish_write();
+        let mut reader = ReadWordBuffer::start_read(bytes);
+        let decoded = reader.deserialize::<PlayerState>()?;
+        assert!((decoded.position - state.position).length() < 0.01);
+        assert!((decoded.speed - state.speed).abs() <= 0.05);
+        assert_eq!(decoded.health, 42);
+        assert!(decoded.rotation.dot(state.rotation).abs() > 0.99);
+        assert_eq!(decoded.name, state.name);
+
+        let mut writer = WriteWordBuffer::with_capacity(10);
+        writer.serialize(&Level(20))?;
+        assert_eq!(writer.num_bits_written(), 4);
+        let bytes = writer.finish_write();
+        let mut reader = ReadWordBuffer::start_read(bytes);
+        // values outside of the range are clamped
+        assert_eq!(reader.deserialize::<Level>()?, Level(15));
+        Ok(())
+    }
+}
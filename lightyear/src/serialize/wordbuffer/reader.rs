@@ -17,7 +17,7 @@ pub struct Reader<'a>(Option<(WordReader<'a>, WordContext)>);
 
 #[derive(Decode)]
 // #[bitcode_hint(gamma)]
-struct OnlyGammaDecode<T: DeserializeOwned>(#[bitcode(with_serde)] T);
+pub(crate) struct OnlyGammaDecode<T: DeserializeOwned>(#[bitcode(with_serde)] pub(crate) T);
 
 // We use self_cell because the reader contains a reference to the WordBuffer
 // (it will take ownership of the buffer's contents to write into)
@@ -92,20 +92,44 @@ impl ReadBuffer for ReadWordBuffer {
 }
 
 impl BitRead for ReadWordBuffer {
-    fn advance(&mut self, _bits: usize) {
-        todo!()
+    fn advance(&mut self, bits: usize) {
+        self.with_dependent_mut(|_buffer, reader| {
+            let reader = reader
+                .0
+                .as_mut()
+                .map_or_else(|| panic!("no reader"), |(reader, _)| reader);
+            reader.advance(bits)
+        })
     }
 
     fn peek_bits(&mut self) -> anyhow::Result<Word> {
-        todo!()
+        self.with_dependent_mut(|_buffer, reader| {
+            let reader = reader
+                .0
+                .as_mut()
+                .map_or_else(|| panic!("no reader"), |(reader, _)| reader);
+            reader.peek_bits().context("error peeking bits")
+        })
     }
 
     fn read_bit(&mut self) -> anyhow::Result<bool> {
-        todo!()
+        self.with_dependent_mut(|_buffer, reader| {
+            let reader = reader
+                .0
+                .as_mut()
+                .map_or_else(|| panic!("no reader"), |(reader, _)| reader);
+            reader.read_bit().context("error reading bit")
+        })
     }
 
-    fn read_bits(&mut self, _bits: usize) -> anyhow::Result<Word> {
-        todo!()
+    fn read_bits(&mut self, bits: usize) -> anyhow::Result<Word> {
+        self.with_dependent_mut(|_buffer, reader| {
+            let reader = reader
+                .0
+                .as_mut()
+                .map_or_else(|| panic!("no reader"), |(reader, _)| reader);
+            reader.read_bits(bits).context("error reading bits")
+        })
     }
 
     fn read_bytes(&mut self, len: NonZeroUsize) -> anyhow::Result<&[u8]> {
@@ -118,7 +142,12 @@ impl BitRead for ReadWordBuffer {
         })
     }
 
-    fn reserve_bits(&self, _bits: usize) -> anyhow::Result<()> {
-        todo!()
+    fn reserve_bits(&self, bits: usize) -> anyhow::Result<()> {
+        let reader = self
+            .borrow_dependent()
+            .0
+            .as_ref()
+            .map_or_else(|| panic!("no reader"), |(reader, _)| reader);
+        reader.reserve_bits(bits).context("error reserving bits")
     }
 }
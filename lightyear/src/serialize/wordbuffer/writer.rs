@@ -26,7 +26,9 @@ pub struct WriteWordBuffer {
 
 #[derive(Encode, Serialize)]
 // #[bitcode_hint(gamma)]
-struct OnlyGammaEncode<'a, T: Serialize + ?Sized>(#[bitcode(with_serde)] &'a T);
+pub(crate) struct OnlyGammaEncode<'a, T: Serialize + ?Sized>(
+    #[bitcode(with_serde)] pub(crate) &'a T,
+);
 
 impl WriteBuffer for WriteWordBuffer {
     // fn serialize<T: Serialize + ?Sized>(&mut self, t: &T) -> anyhow::Result<()> {
@@ -97,8 +99,8 @@ impl BitWrite for WriteWordBuffer {
         self.writer.write_bit(bit)
     }
 
-    fn write_bits(&mut self, _bits: u32) {
-        todo!()
+    fn write_bits(&mut self, word: u64, bits: usize) {
+        self.writer.write_bits(word, bits)
     }
 
     fn write_bytes(&mut self, bytes: &[u8]) {
@@ -40,6 +40,7 @@ pub trait WriteBuffer: BitWrite {
 
 pub trait BitWrite {
     fn write_bit(&mut self, bit: bool);
-    fn write_bits(&mut self, bits: u32);
+    /// Writes the `bits` least significant bits of `word`. `bits` must be in range `0..=64`.
+    fn write_bits(&mut self, word: u64, bits: usize);
     fn write_bytes(&mut self, bytes: &[u8]);
 }
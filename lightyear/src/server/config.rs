@@ -1,11 +1,24 @@
 //! Defines server-specific configuration options
 use bevy::prelude::Resource;
+use std::net::SocketAddr;
+use std::sync::Arc;
 use std::time::Duration;
 
-use crate::netcode::Key;
+use crate::netcode::{
+    BanList, ClientId, DenyReason, Key, RateLimitConfig, MAX_CLIENTS, USER_DATA_BYTES,
+};
+use crate::packet::priority_manager::PriorityConfig;
 use crate::shared::config::SharedConfig;
 use crate::shared::ping::manager::PingConfig;
 
+/// Function that decides whether a client is allowed to connect.
+///
+/// It receives the client id, the client's address and the user data from the connect token,
+/// and returns `Err(reason)` to deny the connection.
+pub type AdmissionFn = Arc<
+    dyn Fn(ClientId, SocketAddr, &[u8; USER_DATA_BYTES]) -> Result<(), DenyReason> + Send + Sync,
+>;
+
 #[derive(Clone)]
 pub struct NetcodeConfig {
     pub num_disconnect_packets: usize,
@@ -15,6 +28,30 @@ pub struct NetcodeConfig {
     pub client_timeout_secs: i32,
     pub protocol_id: u64,
     pub private_key: Option<Key>,
+    /// called once for each connect token of the valid connection requests; can deny the connection
+    /// with a [`DenyReason`] that will be sent to the client
+    pub admission: Option<AdmissionFn>,
+    /// maximum number of clients that can be connected at the same time
+    pub max_clients: usize,
+    /// if set, limits the number of connection requests per IP address, and temporarily blocks
+    /// the addresses that exceed the limit
+    pub rate_limit: Option<RateLimitConfig>,
+    /// initial list of banned IP addresses and client ids. It can be edited at runtime with
+    /// [`NetServer::ban_list_mut`](crate::netcode::Server::ban_list_mut)
+    pub ban_list: BanList,
+    /// if true, connection requests whose connect token doesn't contain the server's address are ignored.
+    /// Disabled by default, since the address seen by the server can differ from the address in the tokens
+    /// (NAT, port mapping, binding to `0.0.0.0`); set `public_addresses` in that case
+    pub check_server_address: bool,
+    /// public addresses of the server that connect tokens must contain.
+    /// If empty, the local addresses of the server's [`Io`](crate::transport::io::Io) are used
+    pub public_addresses: Vec<SocketAddr>,
+    /// if set, clients that time out keep their connection state (replication state, input buffer, rooms)
+    /// for this duration, and can resume their session by reconnecting with the same client id
+    pub resumption_grace_period: Option<Duration>,
+    /// maximum number of reliable messages that are buffered for a suspended client, waiting to be re-sent
+    /// once the session is resumed. The session is ended if the limit is exceeded
+    pub resumption_max_unacked_messages: usize,
 }
 
 impl Default for NetcodeConfig {
@@ -25,6 +62,14 @@ impl Default for NetcodeConfig {
             client_timeout_secs: 10,
             protocol_id: 0,
             private_key: None,
+            admission: None,
+            max_clients: MAX_CLIENTS,
+            rate_limit: None,
+            ban_list: BanList::default(),
+            check_server_address: false,
+            public_addresses: vec![],
+            resumption_grace_period: None,
+            resumption_max_unacked_messages: 1024,
         }
     }
 }
@@ -43,6 +88,56 @@ impl NetcodeConfig {
         self.client_timeout_secs = client_timeout_secs;
         self
     }
+
+    /// Set the function used to accept or deny incoming connection requests
+    pub fn with_admission(
+        mut self,
+        admission: impl Fn(ClientId, SocketAddr, &[u8; USER_DATA_BYTES]) -> Result<(), DenyReason>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        self.admission = Some(Arc::new(admission));
+        self
+    }
+
+    pub fn with_max_clients(mut self, max_clients: usize) -> Self {
+        self.max_clients = max_clients;
+        self
+    }
+
+    pub fn with_rate_limit(mut self, rate_limit: RateLimitConfig) -> Self {
+        self.rate_limit = Some(rate_limit);
+        self
+    }
+
+    pub fn with_ban_list(mut self, ban_list: BanList) -> Self {
+        self.ban_list = ban_list;
+        self
+    }
+
+    pub fn with_server_address_check(mut self, check_server_address: bool) -> Self {
+        self.check_server_address = check_server_address;
+        self
+    }
+
+    pub fn with_public_addresses(mut self, public_addresses: Vec<SocketAddr>) -> Self {
+        self.public_addresses = public_addresses;
+        self
+    }
+
+    /// Let clients that time out resume their session if they reconnect within `grace_period`
+    pub fn with_resumption_grace_period(mut self, grace_period: Duration) -> Self {
+        self.resumption_grace_period = Some(grace_period);
+        self
+    }
+
+    /// Set the maximum number of reliable messages that are buffered for a suspended client.
+    /// The session is ended if the limit is exceeded
+    pub fn with_resumption_max_unacked_messages(mut self, max_unacked_messages: usize) -> Self {
+        self.resumption_max_unacked_messages = max_unacked_messages;
+        self
+    }
 }
 
 #[derive(Clone)]
@@ -72,4 +167,5 @@ pub struct ServerConfig {
     pub shared: SharedConfig,
     pub netcode: NetcodeConfig,
     pub ping: PingConfig,
+    pub priority: PriorityConfig,
 }
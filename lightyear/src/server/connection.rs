@@ -1,8 +1,11 @@
 //! Specify how a Server sends/receives messages with a Client
+use std::collections::BTreeMap;
+
 use anyhow::{Context, Result};
 use bevy::ecs::component::Tick as BevyTick;
 use bevy::prelude::{Entity, Res, ResMut, Resource, World};
 use bevy::utils::{EntityHashMap, Entry, HashMap, HashSet};
+use bytes::Bytes;
 use serde::Serialize;
 use tracing::{debug, debug_span, info, trace, trace_span};
 
@@ -10,14 +13,18 @@ use crate::_reexport::{EntityUpdatesChannel, InputMessageKind, MessageProtocol,
 use crate::channel::senders::ChannelSend;
 use crate::connection::events::ConnectionEvents;
 use crate::connection::message::{ClientMessage, ServerMessage};
-use crate::inputs::native::input_buffer::InputBuffer;
-use crate::netcode::ClientId;
+use crate::inputs::native::input_buffer::{InputBuffer, InputMessage};
+use crate::inputs::InterpolationInfo;
+use crate::netcode::{ClientId, DisconnectReason};
+use crate::packet::message::MessageHandle;
 use crate::packet::message_manager::MessageManager;
 use crate::packet::packet_manager::Payload;
+use crate::packet::priority_manager::PriorityConfig;
 use crate::prelude::{Channel, ChannelKind, MapEntities, Message};
 use crate::protocol::channel::ChannelRegistry;
+use crate::protocol::registry::ProtocolRegistry;
 use crate::protocol::Protocol;
-use crate::serialize::reader::ReadBuffer;
+use crate::serialize::wordbuffer::reader::ReadWordBuffer;
 use crate::server::events::ServerEvents;
 use crate::shared::ping::manager::{PingConfig, PingManager};
 use crate::shared::ping::message::SyncMessage;
@@ -26,10 +33,15 @@ use crate::shared::replication::receive::ReplicationReceiver;
 use crate::shared::replication::send::ReplicationSender;
 use crate::shared::replication::ReplicationMessage;
 use crate::shared::replication::ReplicationMessageData;
+use crate::shared::rpc::{check_request_channel, Request, RequestId, RpcManager};
+use crate::shared::stream::{StreamManager, TransferDirection, TransferId};
 use crate::shared::tick_manager::Tick;
 use crate::shared::tick_manager::TickManager;
 use crate::shared::time_manager::TimeManager;
 
+/// Number of ticks for which we keep the interpolation state reported by the input messages
+const MAX_INTERPOLATION_HISTORY_TICKS: i16 = 256;
+
 #[derive(Resource)]
 pub struct ConnectionManager<P: Protocol> {
     pub(crate) connections: HashMap<ClientId, Connection<P>>,
@@ -44,6 +56,10 @@ pub struct ConnectionManager<P: Protocol> {
     // list of clients that connected since the last time we sent replication messages
     // (we want to keep track of them because we need to replicate the entire world state to them)
     pub(crate) new_clients: Vec<ClientId>,
+    // local clients that disconnected since the last time we received packets
+    pub(crate) local_disconnections: Vec<(ClientId, DisconnectReason, Vec<u8>)>,
+    /// Messages sent to the local client, that are delivered directly as client events
+    pub(crate) local_client_events: ConnectionEvents<P>,
 }
 
 /// Do some regular cleanup on the internals of replication:
@@ -95,6 +111,8 @@ impl<P: Protocol> ConnectionManager<P> {
             events: ServerEvents::new(),
             replicate_component_cache: EntityHashMap::default(),
             new_clients: vec![],
+            local_disconnections: vec![],
+            local_client_events: ConnectionEvents::default(),
         }
     }
 
@@ -104,7 +122,13 @@ impl<P: Protocol> ConnectionManager<P> {
         target: NetworkTarget,
     ) -> Box<dyn Iterator<Item = ClientId>> {
         // TODO: avoid this vec allocation
-        let connected_clients: Vec<ClientId> = self.connections.keys().copied().collect();
+        // local clients share the server's World, so they never need to receive replication messages
+        let connected_clients: Vec<ClientId> = self
+            .connections
+            .iter()
+            .filter(|(_, connection)| !connection.is_local)
+            .map(|(client_id, _)| *client_id)
+            .collect();
         match target {
             NetworkTarget::All => {
                 // TODO: maybe only send stuff when the client is time-synced ?
@@ -124,7 +148,11 @@ impl<P: Protocol> ConnectionManager<P> {
                 )
             }
             NetworkTarget::Single(client_id) => {
-                if self.connections.contains_key(&client_id) {
+                if self
+                    .connections
+                    .get(&client_id)
+                    .map_or(false, |connection| !connection.is_local)
+                {
                     Box::new(std::iter::once(client_id))
                 } else {
                     Box::new(std::iter::empty())
@@ -152,18 +180,27 @@ impl<P: Protocol> ConnectionManager<P> {
     }
 
     pub(crate) fn update(&mut self, time_manager: &TimeManager, tick_manager: &TickManager) {
-        self.connections.values_mut().for_each(|connection| {
-            connection.update(time_manager, tick_manager);
-        });
+        self.connections
+            .values_mut()
+            .filter(|connection| !connection.is_local)
+            .for_each(|connection| {
+                connection.update(time_manager, tick_manager);
+            });
     }
 
-    pub(crate) fn add(&mut self, client_id: ClientId, ping_config: &PingConfig) {
+    pub(crate) fn add(
+        &mut self,
+        client_id: ClientId,
+        ping_config: &PingConfig,
+        priority_config: &PriorityConfig,
+    ) {
         if let Entry::Vacant(e) = self.connections.entry(client_id) {
             #[cfg(feature = "metrics")]
             metrics::increment_gauge!("connected_clients", 1.0);
 
             info!("New connection from id: {}", client_id);
-            let mut connection = Connection::new(&self.channel_registry, ping_config);
+            let mut connection =
+                Connection::new(&self.channel_registry, ping_config, priority_config);
             connection.events.push_connection();
             self.new_clients.push(client_id);
             e.insert(connection);
@@ -172,20 +209,163 @@ impl<P: Protocol> ConnectionManager<P> {
         }
     }
 
-    pub(crate) fn remove(&mut self, client_id: ClientId) {
+    pub(crate) fn remove(
+        &mut self,
+        client_id: ClientId,
+        reason: DisconnectReason,
+        payload: Vec<u8>,
+    ) {
         #[cfg(feature = "metrics")]
         metrics::decrement_gauge!("connected_clients", 1.0);
 
-        info!("Client {} disconnected", client_id);
-        self.events.push_disconnects(client_id);
+        info!("Client {} disconnected: {:?}", client_id, reason);
+        self.events.push_disconnects(client_id, reason, payload);
         self.connections.remove(&client_id);
     }
 
+    /// Suspend the connection of a client that timed out, while it can still resume its session.
+    ///
+    /// The connection state (replication state, input buffer, room membership) is kept, and messages keep
+    /// being buffered for the client: reliable messages will be re-sent once the session is resumed,
+    /// and component updates will be sent from the last update that the client acknowledged.
+    /// If more than [`NetcodeConfig::resumption_max_unacked_messages`](crate::server::config::NetcodeConfig::resumption_max_unacked_messages)
+    /// reliable messages are buffered for the client, the session is ended.
+    pub(crate) fn suspend(&mut self, client_id: ClientId) {
+        if let Some(connection) = self.connections.get_mut(&client_id) {
+            info!("Connection of client {} suspended", client_id);
+            connection.is_suspended = true;
+        }
+    }
+
+    /// Resume the suspended connection of a client that reconnected
+    pub(crate) fn resume(&mut self, client_id: ClientId) {
+        if let Some(connection) = self.connections.get_mut(&client_id) {
+            info!("Connection of client {} resumed", client_id);
+            connection.is_suspended = false;
+        }
+    }
+
+    /// Returns true if the client timed out and can still resume its session
+    pub fn is_suspended(&self, client_id: ClientId) -> bool {
+        self.connections
+            .get(&client_id)
+            .map_or(false, |connection| connection.is_suspended)
+    }
+
+    /// Connect a local client: a client that runs in the same app (and the same `World`) as the server.
+    ///
+    /// This is used for host-server mode (listen server), where one of the players is also hosting the game.
+    /// The local client does not go through netcode or the io; instead:
+    /// - it gets a [`Connection`] and emits the usual `ConnectEvent`/`DisconnectEvent`, and can be added to rooms
+    /// - its inputs are written directly into the server's input buffer via [`ConnectionManager::add_local_input`]
+    /// - no replication messages are sent to it, since the host can read the server entities directly
+    ///   (there are no Confirmed/Predicted/Interpolated copies of the entities)
+    /// - messages sent to it are delivered directly as client-side
+    ///   [`MessageEvent`](crate::client::events::MessageEvent)s during the next receive
+    ///
+    /// Returns an error if a local client is already connected, or if the `client_id` is already used by a remote client.
+    /// A remote client that later connects with the id of the local client is disconnected.
+    pub fn connect_local_client(&mut self, client_id: ClientId) -> Result<()> {
+        anyhow::ensure!(
+            !self
+                .connections
+                .values()
+                .any(|connection| connection.is_local),
+            "a local client is already connected"
+        );
+        let Entry::Vacant(e) = self.connections.entry(client_id) else {
+            anyhow::bail!("client id {client_id} is already used by a remote client");
+        };
+        #[cfg(feature = "metrics")]
+        metrics::increment_gauge!("connected_clients", 1.0);
+
+        info!("New local connection with id: {}", client_id);
+        let mut connection = Connection::new(
+            &self.channel_registry,
+            &PingConfig::default(),
+            &PriorityConfig::default(),
+        );
+        connection.is_local = true;
+        connection.events.push_connection();
+        e.insert(connection);
+        Ok(())
+    }
+
+    /// Disconnect a local client that was added with [`ConnectionManager::connect_local_client`].
+    ///
+    /// The client is removed during the next receive.
+    pub fn disconnect_local_client(&mut self, client_id: ClientId) {
+        if self.is_local_client(client_id) {
+            self.local_disconnections.push((
+                client_id,
+                DisconnectReason::ClientDisconnected,
+                vec![],
+            ));
+        }
+    }
+
+    /// Clients that sent too many fragments that violated the limits of the channels
+    pub(crate) fn fragment_limits_violators(&self) -> Vec<ClientId> {
+        self.connections
+            .iter()
+            .filter(|(_, connection)| connection.message_manager.fragment_limits_exceeded())
+            .map(|(client_id, _)| *client_id)
+            .collect()
+    }
+
+    /// Suspended clients for which more than `max_unacked_messages` reliable messages are buffered
+    pub(crate) fn suspended_buffer_violators(&self, max_unacked_messages: usize) -> Vec<ClientId> {
+        self.connections
+            .iter()
+            .filter(|(_, connection)| {
+                connection.is_suspended
+                    && connection.message_manager.num_unacked_messages() > max_unacked_messages
+            })
+            .map(|(client_id, _)| *client_id)
+            .collect()
+    }
+
+    /// Returns true if the client runs in the same app as the server
+    pub fn is_local_client(&self, client_id: ClientId) -> bool {
+        self.connections
+            .get(&client_id)
+            .map_or(false, |connection| connection.is_local)
+    }
+
+    /// Buffer the input of a local client for the given tick.
+    ///
+    /// The input is added directly to the server's input buffer, and will be returned as an `InputEvent`
+    /// during the given tick.
+    pub fn add_local_input(
+        &mut self,
+        client_id: ClientId,
+        input: P::Input,
+        tick: Tick,
+    ) -> Result<()> {
+        let connection = self
+            .connections
+            .get_mut(&client_id)
+            .filter(|connection| connection.is_local)
+            .context("local client id not found")?;
+        connection.input_buffer.set(tick, Some(input));
+        Ok(())
+    }
+
+    /// Interpolation state of the client at the time it generated its input for the given tick,
+    /// i.e. which state of the world the client was seeing.
+    ///
+    /// Returns None if the client does not send its interpolation state with its inputs.
+    pub fn interpolation_info(&self, client_id: ClientId, tick: Tick) -> Option<InterpolationInfo> {
+        self.connections
+            .get(&client_id)
+            .and_then(|connection| connection.interpolation_info(tick))
+    }
+
     /// Get the inputs for all clients for the given tick
     pub(crate) fn pop_inputs(
         &mut self,
         tick: Tick,
-    ) -> impl Iterator<Item = (Option<P::Input>, ClientId)> + '_ {
+    ) -> impl Iterator<Item = (Option<P::Input>, ClientId, Option<InterpolationInfo>)> + '_ {
         self.connections
             .iter_mut()
             .map(move |(client_id, connection)| {
@@ -213,7 +393,7 @@ impl<P: Protocol> ConnectionManager<P> {
                 // TODO: We should also let the user know that it needs to send inputs a bit earlier so that
                 //  we have more of a buffer. Send a SyncMessage to tell the user to speed up?
                 //  See Overwatch GDC video
-                (input, *client_id)
+                (input, *client_id, connection.interpolation_info(tick))
             })
     }
 
@@ -225,16 +405,27 @@ impl<P: Protocol> ConnectionManager<P> {
     ) -> Result<()> {
         // Rc is fine because the copies are all created on the same thread
         // let message = Rc::new(message);
+        if self
+            .connections
+            .iter()
+            .any(|(id, c)| c.is_local && target.should_send_to(id))
+        {
+            self.local_client_events
+                .push_message(channel, message.clone());
+        }
         self.connections
             .iter_mut()
-            .filter(|(id, _)| target.should_send_to(id))
+            .filter(|(id, c)| !c.is_local && target.should_send_to(id))
             // TODO: here we should avoid the clone, it's the same message.. just use Rc?
             //  need to update the ServerMessage enum to use Rc<P::Message>!
             //  or serialize first, so we can use Bytes? where would the buffer be?
-            .try_for_each(|(_, c)| c.buffer_message(message.clone(), channel))
+            .try_for_each(|(_, c)| c.buffer_message(message.clone(), channel).map(|_| ()))
     }
 
     /// Queues up a message to be sent to all clients
+    ///
+    /// The message can be sent to several connections, so no [`MessageHandle`] is returned;
+    /// use [`ConnectionManager::send_message`] to track the delivery of a message to a single client
     pub fn send_message_to_target<C: Channel, M: Message>(
         &mut self,
         message: M,
@@ -248,16 +439,72 @@ impl<P: Protocol> ConnectionManager<P> {
     }
 
     /// Queues up a message to be sent to a client
+    ///
+    /// Returns a [`MessageHandle`] if the channel tracks acks, which lets you match the message
+    /// with the [`MessageAckEvent`](crate::server::events::MessageAckEvent) or
+    /// [`MessageLostEvent`](crate::server::events::MessageLostEvent) emitted for it
     pub fn send_message<C: Channel, M: Message>(
         &mut self,
         client_id: ClientId,
         message: M,
-    ) -> Result<()>
+    ) -> Result<Option<MessageHandle>>
     where
         M: Clone,
         P::Message: From<M>,
     {
-        self.send_message_to_target::<C, M>(message, NetworkTarget::Only(vec![client_id]))
+        let Some(connection) = self.connections.get_mut(&client_id) else {
+            return Ok(None);
+        };
+        if connection.is_local {
+            // local clients don't receive any packets: the message is delivered directly
+            self.local_client_events
+                .push_message(ChannelKind::of::<C>(), message.into());
+            return Ok(None);
+        }
+        connection.buffer_message(message.into(), ChannelKind::of::<C>())
+    }
+
+    /// Send a request to a client on the reliable channel `C`
+    ///
+    /// The response can be read with [`ServerRpc`](crate::server::rpc::ServerRpc), and a
+    /// [`RequestTimeoutEvent`](crate::server::events::RequestTimeoutEvent) is emitted if no response
+    /// of the right type is received within [`Request::TIMEOUT`]
+    pub fn send_request<C: Channel, R: Request>(
+        &mut self,
+        client_id: ClientId,
+        request: R,
+    ) -> Result<RequestId>
+    where
+        P::Message: From<R>,
+    {
+        self.connection_mut(client_id)?
+            .send_request::<C, R>(request)
+    }
+
+    /// Start streaming `bytes` to a client on the reliable channel `C`
+    ///
+    /// The payload is sent in chunks over several frames; use a [`ChannelMode::Streaming`](crate::prelude::ChannelMode::Streaming)
+    /// channel to cap the bandwidth used by the transfer. A [`TransferProgressEvent`](crate::server::events::TransferProgressEvent)
+    /// is emitted whenever the client acks some of the chunks
+    pub fn send_stream<C: Channel>(
+        &mut self,
+        client_id: ClientId,
+        bytes: impl Into<Bytes>,
+    ) -> Result<TransferId> {
+        self.connection_mut(client_id)?
+            .send_stream(ChannelKind::of::<C>(), bytes.into())
+    }
+
+    /// Cancel a stream that we are sending to or receiving from a client; the client is notified of the cancellation
+    pub fn cancel_stream(
+        &mut self,
+        client_id: ClientId,
+        transfer_id: TransferId,
+        direction: TransferDirection,
+    ) -> Result<()> {
+        self.connection_mut(client_id)?
+            .stream
+            .cancel(transfer_id, direction)
     }
 
     /// Buffer all the replication messages to send.
@@ -314,16 +561,29 @@ pub struct Connection<P: Protocol> {
     /// Stores the last input we have received from the client.
     /// In case we are missing the client input for a tick, we will fallback to using this.
     pub(crate) last_input: Option<P::Input>,
+    /// Interpolation state reported by each input message, indexed by the end tick of the message.
+    /// Used for lag compensation.
+    pub(crate) interpolation_history: BTreeMap<Tick, InterpolationInfo>,
     // TODO: maybe don't do any replication until connection is synced?
 
     // messages that we have received that need to be rebroadcasted to other clients
     pub(crate) messages_to_rebroadcast: Vec<(P::Message, NetworkTarget, ChannelKind)>,
+    /// True if the client runs in the same app as the server (host-server mode)
+    pub(crate) is_local: bool,
+    /// True if the client timed out and can still resume its session
+    pub(crate) is_suspended: bool,
+    pub(crate) rpc: RpcManager<P::Message>,
+    pub(crate) stream: StreamManager,
 }
 
 impl<P: Protocol> Connection<P> {
-    pub(crate) fn new(channel_registry: &ChannelRegistry, ping_config: &PingConfig) -> Self {
+    pub(crate) fn new(
+        channel_registry: &ChannelRegistry,
+        ping_config: &PingConfig,
+        priority_config: &PriorityConfig,
+    ) -> Self {
         // create the message manager and the channels
-        let mut message_manager = MessageManager::new(channel_registry);
+        let mut message_manager = MessageManager::new(channel_registry, priority_config.clone());
         // get the acks-tracker for entity updates
         let update_acks_tracker = message_manager
             .channels
@@ -340,22 +600,61 @@ impl<P: Protocol> Connection<P> {
             ping_manager: PingManager::new(ping_config),
             input_buffer: InputBuffer::default(),
             last_input: None,
+            interpolation_history: BTreeMap::new(),
             events: ConnectionEvents::default(),
             messages_to_rebroadcast: vec![],
+            is_local: false,
+            is_suspended: false,
+            rpc: RpcManager::default(),
+            stream: StreamManager::default(),
+        }
+    }
+
+    /// Update the interpolation state of the client using the information contained in an input message
+    pub(crate) fn update_interpolation(
+        &mut self,
+        end_tick: Tick,
+        interpolation: Option<InterpolationInfo>,
+    ) {
+        if let Some(interpolation) = interpolation {
+            self.interpolation_history.insert(end_tick, interpolation);
+            // the interpolation state of old ticks won't be needed anymore
+            self.interpolation_history
+                .retain(|tick, _| end_tick - *tick < MAX_INTERPOLATION_HISTORY_TICKS);
+        }
+    }
+
+    /// Interpolation state of the client at the time it generated its input for the given tick.
+    ///
+    /// This is the state sent with the first input message that contained the input for that tick.
+    pub(crate) fn interpolation_info(&self, tick: Tick) -> Option<InterpolationInfo> {
+        // the host sees the server world directly, with no interpolation delay
+        if self.is_local {
+            return Some(InterpolationInfo {
+                tick,
+                overstep: 0.0,
+            });
         }
+        self.interpolation_history
+            .range(tick..)
+            .next()
+            .map(|(_, interpolation)| *interpolation)
     }
 
     pub(crate) fn update(&mut self, time_manager: &TimeManager, tick_manager: &TickManager) {
         self.message_manager
             .update(time_manager, &self.ping_manager, tick_manager);
         self.ping_manager.update(time_manager);
+        for request_id in self.rpc.update(time_manager.delta()) {
+            self.events.push_request_timeout(request_id);
+        }
     }
 
     pub(crate) fn buffer_message(
         &mut self,
         message: P::Message,
         channel: ChannelKind,
-    ) -> Result<()> {
+    ) -> Result<Option<MessageHandle>> {
         // TODO: i know channel names never change so i should be able to get them as static
         // TODO: just have a channel registry enum as well?
         let channel_name = self
@@ -366,6 +665,53 @@ impl<P: Protocol> Connection<P> {
             .to_string();
         let message = ServerMessage::<P>::Message(message);
         message.emit_send_logs(&channel_name);
+        let message_id = self.message_manager.buffer_send(message, channel)?;
+        Ok(self.message_manager.track_delivery(channel, message_id))
+    }
+
+    pub(crate) fn send_request<C: Channel, R: Request>(&mut self, request: R) -> Result<RequestId>
+    where
+        P::Message: From<R>,
+    {
+        // local clients don't receive any packets
+        anyhow::ensure!(!self.is_local, "cannot send requests to a local client");
+        let channel = ChannelKind::of::<C>();
+        check_request_channel(&self.message_manager, channel)?;
+        let request_id = self.rpc.new_request::<R>();
+        self.buffer_rpc_message(
+            ServerMessage::<P>::Request(request_id, request.into()),
+            channel,
+        )?;
+        Ok(request_id)
+    }
+
+    pub(crate) fn send_response(
+        &mut self,
+        request_id: RequestId,
+        channel: ChannelKind,
+        response: P::Message,
+    ) -> Result<()> {
+        self.buffer_rpc_message(ServerMessage::<P>::Response(request_id, response), channel)
+    }
+
+    pub(crate) fn send_stream(&mut self, channel: ChannelKind, bytes: Bytes) -> Result<TransferId> {
+        // local clients don't receive any packets
+        anyhow::ensure!(!self.is_local, "cannot send streams to a local client");
+        self.stream.send(&mut self.message_manager, channel, bytes)
+    }
+
+    fn buffer_rpc_message(
+        &mut self,
+        message: ServerMessage<P>,
+        channel: ChannelKind,
+    ) -> Result<()> {
+        let channel_name = self
+            .message_manager
+            .channel_registry
+            .name(&channel)
+            .unwrap_or("unknown")
+            .to_string();
+        message.emit_send_logs(&channel_name);
         self.message_manager.buffer_send(message, channel)?;
         Ok(())
     }
@@ -375,8 +721,9 @@ impl<P: Protocol> Connection<P> {
         tick: Tick,
         bevy_tick: BevyTick,
     ) -> Result<()> {
+        let messages = self.replication_sender.finalize(tick);
         self.replication_sender
-            .finalize(tick)
+            .sort_by_priority(messages)
             .into_iter()
             .try_for_each(|(channel, group_id, message_data)| {
                 let should_track_ack = matches!(message_data, ReplicationMessageData::Updates(_));
@@ -386,21 +733,35 @@ impl<P: Protocol> Connection<P> {
                     .name(&channel)
                     .unwrap_or("unknown")
                     .to_string();
-                let message = ClientMessage::<P>::Replication(ReplicationMessage {
+                let message = ServerMessage::<P>::Replication(ReplicationMessage {
                     group_id,
                     data: message_data,
                 });
+                let message_bytes = self.message_manager.encode(&message)?;
+                // entity actions are reliable and are always sent, but entity updates can be deferred
+                // if they don't fit in what is left of the bandwidth budget
+                let priority_manager = &mut self.message_manager.priority_manager;
+                if should_track_ack
+                    && priority_manager
+                        .available_bytes()
+                        .map_or(false, |bytes_left| message_bytes.len() > bytes_left)
+                {
+                    self.replication_sender.defer_update(group_id);
+                    return Ok(());
+                }
+                // the bytes are taken from the budget now, so that the message cannot be deferred later
+                priority_manager.reserve(channel, message_bytes.len());
+                trace!("Sending replication message: {:?}", message);
                 message.emit_send_logs(&channel_name);
                 let message_id = self
                     .message_manager
-                    .buffer_send(message, channel)?
+                    .buffer_send_bytes(message_bytes, channel)?
                     .expect("The EntityUpdatesChannel should always return a message_id");
 
                 // keep track of the group associated with the message, so we can handle receiving an ACK for that message_id later
                 if should_track_ack {
                     self.replication_sender
-                        .updates_message_id_to_group_id
-                        .insert(message_id, (group_id, bevy_tick));
+                        .track_update_message(message_id, group_id, bevy_tick);
                 }
                 Ok(())
             })
@@ -441,7 +802,26 @@ impl<P: Protocol> Connection<P> {
                     Ok::<(), anyhow::Error>(())
                 })?;
         }
-        self.message_manager.send_packets(tick_manager.tick())
+        // buffer the next chunks of the streams
+        self.stream.send_chunks(|channel, message| {
+            let message = ServerMessage::<P>::Stream(message);
+            let channel_name = self
+                .message_manager
+                .channel_registry
+                .name(&channel)
+                .unwrap_or("unknown");
+            message.emit_send_logs(channel_name);
+            self.message_manager.buffer_send(message, channel)
+        })?;
+        let payloads = self.message_manager.send_packets(tick_manager.tick())?;
+        // the groups whose updates were written into a packet stop accumulating priority
+        for message_id in self
+            .message_manager
+            .sent_message_ids(&ChannelKind::of::<EntityUpdatesChannel>())
+        {
+            self.replication_sender.reset_update_priority(*message_id);
+        }
+        Ok(payloads)
     }
 
     pub fn receive(
@@ -451,7 +831,15 @@ impl<P: Protocol> Connection<P> {
         tick_manager: &TickManager,
     ) -> ConnectionEvents<P> {
         let _span = trace_span!("receive").entered();
-        for (channel_kind, messages) in self.message_manager.read_messages::<ClientMessage<P>>() {
+        // the registry is needed to decode the registered messages and components
+        let empty_registry = ProtocolRegistry::default();
+        let registry = world
+            .get_resource::<ProtocolRegistry>()
+            .unwrap_or(&empty_registry);
+        let received = self
+            .message_manager
+            .read_messages::<ClientMessage<P>>(registry);
+        for (channel_kind, messages) in received {
             let channel_name = self
                 .message_manager
                 .channel_registry
@@ -487,8 +875,13 @@ impl<P: Protocol> Connection<P> {
                                     self.events.push_input_message(message);
                                 }
                                 InputMessageKind::Native => {
-                                    let input_message = message.try_into().unwrap();
+                                    let input_message: InputMessage<P::Input> =
+                                        message.try_into().unwrap();
                                     debug!("Received input message: {:?}", input_message.end_tick);
+                                    self.update_interpolation(
+                                        input_message.end_tick,
+                                        input_message.interpolation,
+                                    );
                                     self.input_buffer.update_from_message(input_message);
                                 }
                                 InputMessageKind::None => {
@@ -497,6 +890,23 @@ impl<P: Protocol> Connection<P> {
                                 }
                             }
                         }
+                        ClientMessage::Request(request_id, mut request) => {
+                            request.map_entities(Box::new(
+                                &self.replication_receiver.remote_entity_map,
+                            ));
+                            self.rpc.recv_request(request_id, channel_kind, request);
+                        }
+                        ClientMessage::Response(request_id, mut response) => {
+                            response.map_entities(Box::new(
+                                &self.replication_receiver.remote_entity_map,
+                            ));
+                            self.rpc.recv_response(request_id, response);
+                        }
+                        ClientMessage::Stream(message) => {
+                            let settings =
+                                StreamManager::settings(&self.message_manager, channel_kind);
+                            self.stream.recv_message(&settings, channel_kind, message);
+                        }
                         ClientMessage::Replication(replication) => {
                             // buffer the replication message
                             self.replication_receiver.recv_message(replication, tick);
@@ -538,6 +948,25 @@ impl<P: Protocol> Connection<P> {
             }
         }
 
+        // Report the delivery of the messages we sent
+        let (acked, lost) = self.message_manager.drain_delivery_notifications();
+        acked
+            .into_iter()
+            .for_each(|handle| self.events.push_message_ack(handle));
+        lost.into_iter()
+            .for_each(|handle| self.events.push_message_lost(handle));
+
+        // Report the progress of the streams
+        self.stream.process_acks();
+        self.stream
+            .drain_progress()
+            .into_iter()
+            .for_each(|progress| self.events.push_transfer_progress(progress));
+        self.stream
+            .drain_received()
+            .into_iter()
+            .for_each(|(transfer_id, bytes)| self.events.push_stream_received(transfer_id, bytes));
+
         // TODO: do i really need this? I could just create events in this function directly?
         //  why do i need to make events a field of the connection?
         //  is it because of push_connection?
@@ -546,7 +975,7 @@ impl<P: Protocol> Connection<P> {
 
     pub fn recv_packet(
         &mut self,
-        reader: &mut impl ReadBuffer,
+        reader: &mut ReadWordBuffer,
         tick_manager: &TickManager,
     ) -> Result<()> {
         self.replication_sender.recv_update_acks();
@@ -555,3 +984,218 @@ impl<P: Protocol> Connection<P> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::prelude::{EventReader, Events, FixedUpdate, IntoSystemConfigs, ResMut, Resource};
+
+    use crate::prelude::client::{InterpolationConfig, PredictionConfig, SyncConfig};
+    use crate::prelude::server::ConnectEvent;
+    use crate::prelude::*;
+    use crate::shared::events::InputEvent;
+    use crate::shared::sets::FixedUpdateSet;
+    use crate::tests::protocol::*;
+    use crate::tests::stepper::{BevyStepper, Step};
+
+    use super::{Connection, InterpolationInfo, PingConfig, PriorityConfig};
+
+    #[derive(Resource, Default)]
+    struct ReceivedInputs(Vec<(ClientId, MyInput)>);
+
+    fn read_inputs(
+        mut received: ResMut<ReceivedInputs>,
+        mut events: EventReader<InputEvent<MyInput, ClientId>>,
+    ) {
+        for event in events.read() {
+            if let Some(input) = event.input() {
+                received.0.push((*event.context(), input.clone()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_local_client() {
+        let frame_duration = Duration::from_millis(10);
+        let tick_duration = Duration::from_millis(10);
+        let shared_config = SharedConfig {
+            enable_replication: true,
+            tick: TickConfig::new(tick_duration),
+            ..Default::default()
+        };
+        let link_conditioner = LinkConditionerConfig {
+            incoming_latency: Duration::from_millis(0),
+            incoming_jitter: Duration::from_millis(0),
+            incoming_loss: 0.0,
+            ..Default::default()
+        };
+        let mut stepper = BevyStepper::new(
+            shared_config,
+            SyncConfig::default(),
+            PredictionConfig::default(),
+            InterpolationConfig::default(),
+            link_conditioner,
+            frame_duration,
+        );
+        stepper.init();
+        stepper.server_app.init_resource::<ReceivedInputs>();
+        stepper
+            .server_app
+            .add_systems(FixedUpdate, read_inputs.in_set(FixedUpdateSet::Main));
+
+        let local_client_id = 0;
+        stepper
+            .server_app
+            .world
+            .resource_mut::<ServerConnectionManager>()
+            .connect_local_client(local_client_id)
+            .unwrap();
+        stepper.frame_step();
+        assert!(stepper
+            .server_app
+            .world
+            .resource::<Events<ConnectEvent>>()
+            .iter_current_update_events()
+            .any(|event| *event.context() == local_client_id));
+
+        // the id of a connected client cannot be reused, and there can only be one local client
+        let mut manager = stepper
+            .server_app
+            .world
+            .resource_mut::<ServerConnectionManager>();
+        assert!(manager.connect_local_client(111).is_err());
+        assert!(manager.connect_local_client(1).is_err());
+
+        // messages sent to the local client are delivered directly as client events
+        manager
+            .send_message::<Channel1, _>(local_client_id, Message1("host".to_string()))
+            .unwrap();
+        stepper.frame_step();
+        assert_eq!(
+            stepper
+                .server_app
+                .world
+                .resource::<Events<client::MessageEvent<Message1>>>()
+                .iter_current_update_events()
+                .map(|event| event.message().clone())
+                .collect::<Vec<_>>(),
+            vec![Message1("host".to_string())]
+        );
+
+        // the inputs of the local client go directly into the server input buffer
+        let tick = stepper.server_app.world.resource::<TickManager>().tick() + 1;
+        stepper
+            .server_app
+            .world
+            .resource_mut::<ServerConnectionManager>()
+            .add_local_input(local_client_id, MyInput(3), tick)
+            .unwrap();
+
+        // entities are only replicated to the remote client
+        let server_entity = stepper
+            .server_app
+            .world
+            .spawn((Component1(1.0), Replicate::default()))
+            .id();
+        stepper.frame_step();
+        stepper.frame_step();
+        assert!(stepper
+            .server_app
+            .world
+            .resource::<ReceivedInputs>()
+            .0
+            .contains(&(local_client_id, MyInput(3))));
+        let manager = stepper
+            .server_app
+            .world
+            .resource::<ServerConnectionManager>();
+        assert!(manager
+            .connection(local_client_id)
+            .unwrap()
+            .replication_sender
+            .group_channels
+            .is_empty());
+        assert!(stepper
+            .client_app
+            .world
+            .resource::<ClientConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .is_some());
+
+        // disconnect
+        stepper
+            .server_app
+            .world
+            .resource_mut::<ServerConnectionManager>()
+            .disconnect_local_client(local_client_id);
+        stepper.frame_step();
+        let manager = stepper
+            .server_app
+            .world
+            .resource::<ServerConnectionManager>();
+        assert!(!manager.is_local_client(local_client_id));
+        assert!(manager.connection(111).is_ok());
+    }
+
+    #[test]
+    fn test_local_client_id_collision() {
+        let frame_duration = Duration::from_millis(10);
+        let shared_config = SharedConfig {
+            enable_replication: false,
+            tick: TickConfig::new(frame_duration),
+            ..Default::default()
+        };
+        let mut stepper = BevyStepper::new(
+            shared_config,
+            SyncConfig::default(),
+            PredictionConfig::default(),
+            InterpolationConfig::default(),
+            LinkConditionerConfig::default(),
+            frame_duration,
+        );
+        // the local client uses the id of the remote client
+        stepper
+            .server_app
+            .world
+            .resource_mut::<ServerConnectionManager>()
+            .connect_local_client(111)
+            .unwrap();
+        stepper.init();
+
+        // the remote client gets disconnected, and the local client is kept
+        assert!(!stepper
+            .client_app
+            .world
+            .resource::<crate::netcode::Client>()
+            .is_connected());
+        assert!(stepper
+            .server_app
+            .world
+            .resource::<ServerConnectionManager>()
+            .is_local_client(111));
+    }
+
+    #[test]
+    fn test_interpolation_info_per_tick() {
+        let mut connection = Connection::<MyProtocol>::new(
+            protocol().channel_registry(),
+            &PingConfig::default(),
+            &PriorityConfig::default(),
+        );
+        let info = |tick: u16| InterpolationInfo {
+            tick: Tick(tick),
+            overstep: 0.5,
+        };
+        connection.update_interpolation(Tick(10), Some(info(5)));
+        connection.update_interpolation(Tick(12), Some(info(8)));
+
+        assert_eq!(connection.interpolation_info(Tick(10)), Some(info(5)));
+        // the input for tick 11 was first sent with the message for tick 12
+        assert_eq!(connection.interpolation_info(Tick(11)), Some(info(8)));
+        assert_eq!(connection.interpolation_info(Tick(12)), Some(info(8)));
+        assert_eq!(connection.interpolation_info(Tick(13)), None);
+    }
+}
@@ -2,6 +2,7 @@
 use std::collections::HashMap;
 
 use bevy::prelude::{Component, Entity};
+use bytes::Bytes;
 use tracing::trace;
 
 use crate::_reexport::{
@@ -10,18 +11,22 @@ use crate::_reexport::{
 #[cfg(feature = "leafwing")]
 use crate::connection::events::IterInputMessageEvent;
 use crate::connection::events::{
-    ConnectionEvents, IterEntityDespawnEvent, IterEntitySpawnEvent, IterMessageEvent,
+    ConnectionEvents, IterEntityDespawnEvent, IterEntitySpawnEvent, IterMessageAckEvent,
+    IterMessageEvent, IterMessageLostEvent, IterRequestTimeoutEvent, IterStreamReceivedEvent,
+    IterTransferProgressEvent,
 };
 #[cfg(feature = "leafwing")]
 use crate::inputs::leafwing::{InputMessage, LeafwingUserAction};
-use crate::netcode::ClientId;
-use crate::packet::message::Message;
+use crate::netcode::{ClientId, DisconnectReason};
+use crate::packet::message::{Message, MessageHandle};
 use crate::protocol::Protocol;
+use crate::shared::rpc::RequestId;
+use crate::shared::stream::{TransferId, TransferProgress};
 
 #[derive(Debug)]
 pub struct ServerEvents<P: Protocol> {
     // have to handle disconnects separately because the [`ConnectionEvents`] are removed upon disconnection
-    pub disconnects: Vec<ClientId>,
+    pub disconnects: Vec<(ClientId, DisconnectReason, Vec<u8>)>,
     pub events: HashMap<ClientId, ConnectionEvents<P>>,
     pub empty: bool,
 }
@@ -103,7 +108,9 @@ impl<P: Protocol> ServerEvents<P> {
     //     })
     // }
 
-    pub fn iter_disconnections(&mut self) -> impl Iterator<Item = ClientId> + '_ {
+    pub fn iter_disconnections(
+        &mut self,
+    ) -> impl Iterator<Item = (ClientId, DisconnectReason, Vec<u8>)> + '_ {
         std::mem::take(&mut self.disconnects).into_iter()
     }
 
@@ -125,8 +132,13 @@ impl<P: Protocol> ServerEvents<P> {
 
     // Cannot only use the 'disconnect' field in the events, because we remove the events
     // upon disconnection
-    pub(crate) fn push_disconnects(&mut self, client_id: ClientId) {
-        self.disconnects.push(client_id);
+    pub(crate) fn push_disconnects(
+        &mut self,
+        client_id: ClientId,
+        reason: DisconnectReason,
+        payload: Vec<u8>,
+    ) {
+        self.disconnects.push((client_id, reason, payload));
         self.events.remove(&client_id);
         self.empty = false;
     }
@@ -184,6 +196,100 @@ impl<P: Protocol> IterMessageEvent<P, ClientId> for ServerEvents<P> {
     }
 }
 
+impl<P: Protocol> IterMessageAckEvent<ClientId> for ServerEvents<P> {
+    fn into_iter_message_acks(
+        &mut self,
+    ) -> Box<dyn Iterator<Item = (MessageHandle, ClientId)> + '_> {
+        Box::new(self.events.iter_mut().flat_map(|(client_id, events)| {
+            let handles = events.into_iter_message_acks().map(|(handle, _)| handle);
+            let client_ids = std::iter::once(*client_id).cycle();
+            handles.zip(client_ids)
+        }))
+    }
+
+    fn has_message_acks(&self) -> bool {
+        self.events
+            .iter()
+            .any(|(_, connection_events)| connection_events.has_message_acks())
+    }
+}
+
+impl<P: Protocol> IterMessageLostEvent<ClientId> for ServerEvents<P> {
+    fn into_iter_message_losses(
+        &mut self,
+    ) -> Box<dyn Iterator<Item = (MessageHandle, ClientId)> + '_> {
+        Box::new(self.events.iter_mut().flat_map(|(client_id, events)| {
+            let handles = events.into_iter_message_losses().map(|(handle, _)| handle);
+            let client_ids = std::iter::once(*client_id).cycle();
+            handles.zip(client_ids)
+        }))
+    }
+
+    fn has_message_losses(&self) -> bool {
+        self.events
+            .iter()
+            .any(|(_, connection_events)| connection_events.has_message_losses())
+    }
+}
+
+impl<P: Protocol> IterRequestTimeoutEvent<ClientId> for ServerEvents<P> {
+    fn into_iter_request_timeouts(
+        &mut self,
+    ) -> Box<dyn Iterator<Item = (RequestId, ClientId)> + '_> {
+        Box::new(self.events.iter_mut().flat_map(|(client_id, events)| {
+            let request_ids = events
+                .into_iter_request_timeouts()
+                .map(|(request_id, _)| request_id);
+            let client_ids = std::iter::once(*client_id).cycle();
+            request_ids.zip(client_ids)
+        }))
+    }
+
+    fn has_request_timeouts(&self) -> bool {
+        self.events
+            .iter()
+            .any(|(_, connection_events)| connection_events.has_request_timeouts())
+    }
+}
+
+impl<P: Protocol> IterTransferProgressEvent<ClientId> for ServerEvents<P> {
+    fn into_iter_transfer_progress(
+        &mut self,
+    ) -> Box<dyn Iterator<Item = (TransferProgress, ClientId)> + '_> {
+        Box::new(self.events.iter_mut().flat_map(|(client_id, events)| {
+            let progress = events
+                .into_iter_transfer_progress()
+                .map(|(progress, _)| progress);
+            let client_ids = std::iter::once(*client_id).cycle();
+            progress.zip(client_ids)
+        }))
+    }
+
+    fn has_transfer_progress(&self) -> bool {
+        self.events
+            .iter()
+            .any(|(_, connection_events)| connection_events.has_transfer_progress())
+    }
+}
+
+impl<P: Protocol> IterStreamReceivedEvent<ClientId> for ServerEvents<P> {
+    fn into_iter_streams_received(
+        &mut self,
+    ) -> Box<dyn Iterator<Item = (TransferId, Bytes, ClientId)> + '_> {
+        Box::new(self.events.iter_mut().flat_map(|(client_id, events)| {
+            events
+                .into_iter_streams_received()
+                .map(|(transfer_id, bytes, _)| (transfer_id, bytes, *client_id))
+        }))
+    }
+
+    fn has_streams_received(&self) -> bool {
+        self.events
+            .iter()
+            .any(|(_, connection_events)| connection_events.has_streams_received())
+    }
+}
+
 impl<P: Protocol> IterEntitySpawnEvent<ClientId> for ServerEvents<P> {
     fn into_iter_entity_spawn(&mut self) -> Box<dyn Iterator<Item = (Entity, ClientId)> + '_> {
         Box::new(self.events.iter_mut().flat_map(|(client_id, events)| {
@@ -217,80 +323,65 @@ impl<P: Protocol> IterEntityDespawnEvent<ClientId> for ServerEvents<P> {
 }
 
 impl<P: Protocol> IterComponentUpdateEvent<P, ClientId> for ServerEvents<P> {
-    fn iter_component_update<C: Component>(
+    fn iter_component_update_kind(
         &mut self,
-    ) -> Box<dyn Iterator<Item = (Entity, ClientId)> + '_>
-    where
-        P::ComponentKinds: FromType<C>,
-    {
-        Box::new(self.events.iter_mut().flat_map(|(client_id, events)| {
+        kind: P::ComponentKinds,
+    ) -> Box<dyn Iterator<Item = (Entity, ClientId)> + '_> {
+        Box::new(self.events.iter_mut().flat_map(move |(client_id, events)| {
             let updates = events
-                .iter_component_update::<C>()
+                .iter_component_update_kind(kind)
                 .map(|(entity, _)| entity);
             let client_ids = std::iter::once(*client_id).cycle();
             updates.zip(client_ids)
         }))
     }
 
-    fn has_component_update<C: Component>(&self) -> bool
-    where
-        P::ComponentKinds: FromType<C>,
-    {
+    fn has_component_update_kind(&self, kind: P::ComponentKinds) -> bool {
         self.events
             .iter()
-            .any(|(_, connection_events)| connection_events.has_component_update::<C>())
+            .any(|(_, connection_events)| connection_events.has_component_update_kind(kind))
     }
 }
 
 impl<P: Protocol> IterComponentRemoveEvent<P, ClientId> for ServerEvents<P> {
-    fn iter_component_remove<C: Component>(
+    fn iter_component_remove_kind(
         &mut self,
-    ) -> Box<dyn Iterator<Item = (Entity, ClientId)> + '_>
-    where
-        P::ComponentKinds: FromType<C>,
-    {
-        Box::new(self.events.iter_mut().flat_map(|(client_id, events)| {
+        kind: P::ComponentKinds,
+    ) -> Box<dyn Iterator<Item = (Entity, ClientId)> + '_> {
+        Box::new(self.events.iter_mut().flat_map(move |(client_id, events)| {
             let updates = events
-                .iter_component_remove::<C>()
+                .iter_component_remove_kind(kind)
                 .map(|(entity, _)| entity);
             let client_ids = std::iter::once(*client_id).cycle();
             updates.zip(client_ids)
         }))
     }
 
-    fn has_component_remove<C: Component>(&self) -> bool
-    where
-        P::ComponentKinds: FromType<C>,
-    {
+    fn has_component_remove_kind(&self, kind: P::ComponentKinds) -> bool {
         self.events
             .iter()
-            .any(|(_, connection_events)| connection_events.has_component_remove::<C>())
+            .any(|(_, connection_events)| connection_events.has_component_remove_kind(kind))
     }
 }
 
 impl<P: Protocol> IterComponentInsertEvent<P, ClientId> for ServerEvents<P> {
-    fn iter_component_insert<C: Component>(
+    fn iter_component_insert_kind(
         &mut self,
-    ) -> Box<dyn Iterator<Item = (Entity, ClientId)> + '_>
-    where
-        P::ComponentKinds: FromType<C>,
-    {
-        Box::new(self.events.iter_mut().flat_map(|(client_id, events)| {
+        kind: P::ComponentKinds,
+    ) -> Box<dyn Iterator<Item = (Entity, ClientId)> + '_> {
+        Box::new(self.events.iter_mut().flat_map(move |(client_id, events)| {
             let updates = events
-                .iter_component_insert::<C>()
+                .iter_component_insert_kind(kind)
                 .map(|(entity, _)| entity);
             let client_ids = std::iter::once(*client_id).cycle();
             updates.zip(client_ids)
         }))
     }
 
-    fn has_component_insert<C: Component>(&self) -> bool
-    where
-        P::ComponentKinds: FromType<C>,
-    {
+    fn has_component_insert_kind(&self, kind: P::ComponentKinds) -> bool {
         self.events
             .iter()
-            .any(|(_, connection_events)| connection_events.has_component_insert::<C>())
+            .any(|(_, connection_events)| connection_events.has_component_insert_kind(kind))
     }
 }
 
@@ -306,6 +397,11 @@ pub type ComponentRemoveEvent<C> = crate::shared::events::ComponentRemoveEvent<C
 #[cfg(feature = "leafwing")]
 pub(crate) type InputMessageEvent<A> = crate::shared::events::InputMessageEvent<A, ClientId>;
 pub type MessageEvent<M> = crate::shared::events::MessageEvent<M, ClientId>;
+pub type MessageAckEvent = crate::shared::events::MessageAckEvent<ClientId>;
+pub type MessageLostEvent = crate::shared::events::MessageLostEvent<ClientId>;
+pub type RequestTimeoutEvent = crate::shared::events::RequestTimeoutEvent<ClientId>;
+pub type TransferProgressEvent = crate::shared::events::TransferProgressEvent<ClientId>;
+pub type StreamReceivedEvent = crate::shared::events::StreamReceivedEvent<ClientId>;
 
 #[cfg(test)]
 mod tests {
@@ -87,8 +87,8 @@ fn write_input_event<P: Protocol>(
     mut input_events: EventWriter<InputEvent<P::Input, ClientId>>,
 ) {
     let tick = tick_manager.tick();
-    for (input, client_id) in connection_manager.pop_inputs(tick) {
-        input_events.send(InputEvent::new(input, client_id));
+    for (input, client_id, interpolation) in connection_manager.pop_inputs(tick) {
+        input_events.send(InputEvent::new(input, client_id).with_interpolation(interpolation));
     }
 }
 
@@ -109,3 +109,111 @@ fn clear_input_events<P: Protocol>(mut input_events: EventReader<InputEvent<P::I
 //   - can use system piping?
 // - Send:
 //   - we read the
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::prelude::{Resource, Update};
+
+    use crate::prelude::client::{
+        ClientConfig, InputSystemSet as ClientInputSystemSet, SyncConfig,
+    };
+    use crate::prelude::*;
+    use crate::shared::events::InputEvent;
+    use crate::tests::protocol::*;
+    use crate::tests::stepper::{BevyStepper, Step};
+
+    use super::*;
+
+    #[derive(Resource, Default)]
+    struct ReceivedInterpolation(Vec<Option<crate::inputs::InterpolationInfo>>);
+
+    fn press_input(
+        mut connection: ResMut<ClientConnectionManager>,
+        tick_manager: Res<TickManager>,
+    ) {
+        connection.add_input(MyInput(0), tick_manager.tick());
+    }
+
+    fn read_input(
+        mut received: ResMut<ReceivedInterpolation>,
+        mut events: EventReader<InputEvent<MyInput, ClientId>>,
+    ) {
+        for event in events.read() {
+            if event.input().is_some() {
+                received.0.push(event.interpolation());
+            }
+        }
+    }
+
+    fn setup(send_interpolation_info: bool) -> BevyStepper {
+        let frame_duration = Duration::from_millis(10);
+        let tick_duration = Duration::from_millis(10);
+        let shared_config = SharedConfig {
+            enable_replication: false,
+            tick: TickConfig::new(tick_duration),
+            ..Default::default()
+        };
+        let link_conditioner = LinkConditionerConfig {
+            incoming_latency: Duration::from_millis(20),
+            incoming_jitter: Duration::from_millis(0),
+            incoming_loss: 0.0,
+            ..Default::default()
+        };
+        let mut stepper = BevyStepper::new(
+            shared_config,
+            SyncConfig::default(),
+            client::PredictionConfig::default(),
+            client::InterpolationConfig::default(),
+            link_conditioner,
+            frame_duration,
+        );
+        stepper
+            .client_app
+            .world
+            .resource_mut::<ClientConfig>()
+            .input
+            .send_interpolation_info = send_interpolation_info;
+        stepper.client_app.add_systems(
+            FixedUpdate,
+            press_input.in_set(ClientInputSystemSet::BufferInputs),
+        );
+        stepper.server_app.init_resource::<ReceivedInterpolation>();
+        stepper
+            .server_app
+            .add_systems(FixedUpdate, read_input.in_set(FixedUpdateSet::Main));
+        stepper.init();
+        for _ in 0..20 {
+            stepper.frame_step();
+        }
+        stepper
+    }
+
+    #[test]
+    fn test_input_event_interpolation() {
+        let stepper = setup(true);
+        let received = &stepper
+            .server_app
+            .world
+            .resource::<ReceivedInterpolation>()
+            .0;
+        let interpolation = received.last().unwrap().unwrap();
+        let server_tick = stepper.server_app.world.resource::<TickManager>().tick();
+        // the client is seeing the world in the past
+        assert!(interpolation.tick < server_tick);
+        assert!((0.0..1.0).contains(&interpolation.overstep));
+    }
+
+    #[test]
+    fn test_input_event_no_interpolation() {
+        let stepper = setup(false);
+        let received = &stepper
+            .server_app
+            .world
+            .resource::<ReceivedInterpolation>()
+            .0;
+        assert!(!received.is_empty());
+        assert!(received.iter().all(|interpolation| interpolation.is_none()));
+    }
+}
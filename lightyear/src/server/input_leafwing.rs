@@ -128,9 +128,12 @@ fn update_action_diff_buffers<P: Protocol, A: LeafwingUserAction>(
 ) where
     P::Message: TryInto<InputMessage<A>, Error = ()>,
 {
-    // let manager = &mut server.connection_manager;
-    for (mut message, client_id) in connection_manager.events.into_iter_input_messages::<A>() {
+    let manager = connection_manager.as_mut();
+    for (mut message, client_id) in manager.events.into_iter_input_messages::<A>() {
         debug!(action = ?A::short_type_path(), ?message.end_tick, ?message.diffs, "received input message");
+        if let Some(connection) = manager.connections.get_mut(&client_id) {
+            connection.update_interpolation(message.end_tick, message.interpolation);
+        }
 
         for (target, diffs) in std::mem::take(&mut message.diffs) {
             match target {
@@ -222,6 +225,7 @@ mod tests {
             incoming_latency: Duration::from_millis(0),
             incoming_jitter: Duration::from_millis(0),
             incoming_loss: 0.0,
+            ..Default::default()
         };
         let sync_config = SyncConfig::default().speedup_factor(1.0);
         let prediction_config = PredictionConfig::default().disable(false);
@@ -0,0 +1,536 @@
+//! Lag compensation: evaluate client actions against the state of the world that the client was seeing
+//!
+//! Clients render the other (interpolated) entities in the past, at their interpolation tick.
+//! When a client shoots at an entity, the server needs to check the hit against the position that the entity
+//! had at that interpolation tick, and not against its current position.
+//!
+//! The [`LagCompensationPlugin`] records a bounded history of the component `C` for every entity
+//! that has the [`LagCompensated`] marker component. The input messages sent by the clients include
+//! their interpolation tick, so that the [`LagCompensation`] system param can rewind the history to the
+//! tick that each client was seeing.
+//!
+//! The clients need to enable [`InputConfig::send_interpolation_info`](crate::client::input::InputConfig::send_interpolation_info),
+//! which is disabled by default.
+//!
+//! The tick sent by a client is not trusted: it is clamped so that the server never rewinds more than
+//! [`LagCompensationConfig::max_rewind_ticks`] in the past, or to a tick that is ahead of the server.
+//!
+//! ```rust,ignore
+//! fn hit_scan(lag_compensation: LagCompensation<MyProtocol, Position>, mut events: EventReader<InputEvent<Inputs>>) {
+//!     for event in events.read() {
+//!         lag_compensation.rewind_query(*event.context(), |world| {
+//!             for (entity, position) in world.iter() {
+//!                 // check the hit against the position of the entity at the client's interpolation tick
+//!             }
+//!         });
+//!     }
+//! }
+//! ```
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::{
+    App, Commands, Component, Entity, FixedUpdate, IntoSystemConfigs, IntoSystemSetConfigs, Plugin,
+    Query, Res, Resource, SystemSet, With, Without,
+};
+
+use crate::netcode::ClientId;
+use crate::protocol::Protocol;
+use crate::server::connection::ConnectionManager;
+use crate::shared::sets::FixedUpdateSet;
+use crate::shared::tick_manager::{Tick, TickManager};
+
+/// Configuration of a [`LagCompensationPlugin`].
+///
+/// Each plugin (i.e. each lag compensated component) has its own config.
+#[derive(Clone, Debug)]
+pub struct LagCompensationConfig {
+    /// Number of ticks of history that we keep for each entity.
+    /// Clients whose interpolation tick is further in the past than this cannot be compensated.
+    pub history_ticks: u16,
+    /// Maximum number of ticks that the world can be rewound for a client.
+    /// A client claiming an older interpolation tick (to favour its own actions) is rewound by this amount instead.
+    ///
+    /// It should cover the RTT of the clients plus their interpolation delay.
+    pub max_rewind_ticks: u16,
+}
+
+impl Default for LagCompensationConfig {
+    fn default() -> Self {
+        Self {
+            history_ticks: 64,
+            max_rewind_ticks: 32,
+        }
+    }
+}
+
+impl LagCompensationConfig {
+    pub fn with_history_ticks(mut self, history_ticks: u16) -> Self {
+        self.history_ticks = history_ticks;
+        self
+    }
+
+    pub fn with_max_rewind_ticks(mut self, max_rewind_ticks: u16) -> Self {
+        self.max_rewind_ticks = max_rewind_ticks;
+        self
+    }
+}
+
+/// Config of the [`LagCompensationPlugin`] of the component `C`
+#[derive(Resource, Debug)]
+struct LagCompensationConfigOf<C> {
+    config: LagCompensationConfig,
+    _marker: PhantomData<C>,
+}
+
+/// Marker component for the entities whose history should be recorded for lag compensation
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub struct LagCompensated;
+
+/// Ring buffer of the past values of the component `C` for one entity
+#[derive(Component, Debug)]
+pub struct LagCompensationHistory<C: Component + Clone> {
+    /// Values of the component, ordered by tick (oldest first)
+    buffer: VecDeque<(Tick, C)>,
+}
+
+impl<C: Component + Clone> Default for LagCompensationHistory<C> {
+    fn default() -> Self {
+        Self {
+            buffer: VecDeque::new(),
+        }
+    }
+}
+
+impl<C: Component + Clone> LagCompensationHistory<C> {
+    /// Record the value of the component at the given tick, and drop the values that are more
+    /// than `history_ticks` ticks old
+    pub(crate) fn add(&mut self, tick: Tick, value: C, history_ticks: u16) {
+        // the tick might have been recorded already (for example if the tick was not incremented this frame)
+        if self.buffer.back().map_or(false, |(t, _)| *t >= tick) {
+            self.buffer.pop_back();
+        }
+        self.buffer.push_back((tick, value));
+        while self
+            .buffer
+            .front()
+            .map_or(false, |(t, _)| tick - *t > history_ticks as i16)
+        {
+            self.buffer.pop_front();
+        }
+    }
+
+    /// Get the value of the component at the given tick (i.e. the most recent value recorded at or before `tick`)
+    pub fn get(&self, tick: Tick) -> Option<&C> {
+        self.buffer
+            .iter()
+            .rev()
+            .find(|(t, _)| *t <= tick)
+            .map(|(_, value)| value)
+    }
+
+    /// Oldest tick for which we have a value
+    pub fn oldest_tick(&self) -> Option<Tick> {
+        self.buffer.front().map(|(tick, _)| *tick)
+    }
+}
+
+/// System set where the history of the lag compensated components is recorded.
+/// Runs in `FixedUpdate` after [`FixedUpdateSet::Main`]
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub struct LagCompensationSet;
+
+/// Plugin that records the history of the component `C` for every [`LagCompensated`] entity
+pub struct LagCompensationPlugin<P: Protocol, C: Component + Clone> {
+    config: LagCompensationConfig,
+    _marker: PhantomData<(P, C)>,
+}
+
+impl<P: Protocol, C: Component + Clone> LagCompensationPlugin<P, C> {
+    pub fn new(config: LagCompensationConfig) -> Self {
+        Self {
+            config,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<P: Protocol, C: Component + Clone> Default for LagCompensationPlugin<P, C> {
+    fn default() -> Self {
+        Self::new(LagCompensationConfig::default())
+    }
+}
+
+impl<P: Protocol, C: Component + Clone> Plugin for LagCompensationPlugin<P, C> {
+    fn build(&self, app: &mut App) {
+        // RESOURCES
+        app.insert_resource(LagCompensationConfigOf::<C> {
+            config: self.config.clone(),
+            _marker: PhantomData,
+        });
+        // SETS
+        app.configure_sets(FixedUpdate, LagCompensationSet.after(FixedUpdateSet::Main));
+        // SYSTEMS
+        app.add_systems(
+            FixedUpdate,
+            (add_history::<C>, record_history::<C>)
+                .chain()
+                .in_set(LagCompensationSet),
+        );
+    }
+}
+
+/// Add a history component to the lag compensated entities
+fn add_history<C: Component + Clone>(
+    mut commands: Commands,
+    query: Query<
+        Entity,
+        (
+            With<LagCompensated>,
+            With<C>,
+            Without<LagCompensationHistory<C>>,
+        ),
+    >,
+) {
+    for entity in query.iter() {
+        commands
+            .entity(entity)
+            .insert(LagCompensationHistory::<C>::default());
+    }
+}
+
+/// Record the value of the component for the current tick
+fn record_history<C: Component + Clone>(
+    config: Res<LagCompensationConfigOf<C>>,
+    tick_manager: Res<TickManager>,
+    mut query: Query<(&C, &mut LagCompensationHistory<C>)>,
+) {
+    let tick = tick_manager.tick();
+    for (component, mut history) in query.iter_mut() {
+        history.add(tick, component.clone(), config.config.history_ticks);
+    }
+}
+
+/// System param used to evaluate the world from the point of view of a client
+#[derive(SystemParam)]
+pub struct LagCompensation<'w, 's, P: Protocol, C: Component + Clone> {
+    config: Res<'w, LagCompensationConfigOf<C>>,
+    connection_manager: Res<'w, ConnectionManager<P>>,
+    tick_manager: Res<'w, TickManager>,
+    histories: Query<'w, 's, (Entity, &'static LagCompensationHistory<C>)>,
+}
+
+impl<'w, 's, P: Protocol, C: Component + Clone> LagCompensation<'w, 's, P, C> {
+    /// Tick of the world that the client was seeing when it generated its input for the current tick.
+    ///
+    /// The tick is clamped between `max_rewind_ticks` in the past and the current tick.
+    /// Returns None if the client hasn't sent any input yet.
+    pub fn rewind_tick(&self, client_id: ClientId) -> Option<Tick> {
+        let current_tick = self.tick_manager.tick();
+        self.connection_manager
+            .interpolation_info(client_id, current_tick)
+            .map(|interpolation| {
+                clamp_rewind_tick(
+                    interpolation.tick,
+                    current_tick,
+                    self.config.config.max_rewind_ticks,
+                )
+            })
+    }
+
+    /// Run `f` against the state of the lag compensated entities at the tick that the client was seeing.
+    ///
+    /// Returns None if the client hasn't sent any input yet.
+    pub fn rewind_query<R>(
+        &self,
+        client_id: ClientId,
+        f: impl FnOnce(RewoundWorld<'_, 'w, 's, C>) -> R,
+    ) -> Option<R> {
+        let tick = self.rewind_tick(client_id)?;
+        Some(f(RewoundWorld {
+            tick,
+            histories: &self.histories,
+        }))
+    }
+}
+
+/// Clamp the tick sent by a client to `[current_tick - max_rewind_ticks, current_tick]`
+fn clamp_rewind_tick(tick: Tick, current_tick: Tick, max_rewind_ticks: u16) -> Tick {
+    let rewind = current_tick - tick;
+    if rewind < 0 {
+        current_tick
+    } else if rewind > max_rewind_ticks as i16 {
+        current_tick - max_rewind_ticks
+    } else {
+        tick
+    }
+}
+
+/// View of the lag compensated entities at a past tick
+pub struct RewoundWorld<'a, 'w, 's, C: Component + Clone> {
+    tick: Tick,
+    histories: &'a Query<'w, 's, (Entity, &'static LagCompensationHistory<C>)>,
+}
+
+impl<'a, 'w, 's, C: Component + Clone> RewoundWorld<'a, 'w, 's, C> {
+    /// Tick that the world was rewound to
+    pub fn tick(&self) -> Tick {
+        self.tick
+    }
+
+    /// Value of the component for the entity at the rewound tick
+    pub fn get(&self, entity: Entity) -> Option<&'a C> {
+        self.histories
+            .get(entity)
+            .ok()
+            .and_then(|(_, history)| history.get(self.tick))
+    }
+
+    /// Iterate through all the lag compensated entities that existed at the rewound tick
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, &'a C)> + '_ {
+        self.histories
+            .iter()
+            .filter_map(|(entity, history)| history.get(self.tick).map(|value| (entity, value)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::ecs::system::RunSystemOnce;
+    use bevy::prelude::{ResMut, Update};
+
+    use crate::prelude::client::*;
+    use crate::prelude::*;
+    use crate::tests::protocol::*;
+    use crate::tests::stepper::{BevyStepper, Step};
+
+    use super::*;
+
+    #[test]
+    fn test_history() {
+        let mut history = LagCompensationHistory::<Component1>::default();
+        history.add(Tick(1), Component1(1.0), 3);
+        history.add(Tick(2), Component1(2.0), 3);
+        history.add(Tick(4), Component1(4.0), 3);
+        assert_eq!(history.get(Tick(0)), None);
+        assert_eq!(history.get(Tick(2)), Some(&Component1(2.0)));
+        // no value recorded at tick 3: use the most recent value before that
+        assert_eq!(history.get(Tick(3)), Some(&Component1(2.0)));
+        assert_eq!(history.get(Tick(10)), Some(&Component1(4.0)));
+
+        // old values get dropped
+        history.add(Tick(5), Component1(5.0), 3);
+        assert_eq!(history.oldest_tick(), Some(Tick(2)));
+    }
+
+    #[test]
+    fn test_clamp_rewind_tick() {
+        assert_eq!(clamp_rewind_tick(Tick(95), Tick(100), 10), Tick(95));
+        assert_eq!(clamp_rewind_tick(Tick(80), Tick(100), 10), Tick(90));
+        assert_eq!(clamp_rewind_tick(Tick(105), Tick(100), 10), Tick(100));
+        // wrapping
+        assert_eq!(
+            clamp_rewind_tick(Tick(u16::MAX - 20), Tick(3), 10),
+            Tick(u16::MAX - 6)
+        );
+    }
+
+    fn press_input(
+        mut connection: ResMut<ClientConnectionManager>,
+        tick_manager: Res<TickManager>,
+    ) {
+        connection.add_input(MyInput(0), tick_manager.tick());
+    }
+
+    #[test]
+    fn test_rewind_query() {
+        let frame_duration = Duration::from_millis(10);
+        let tick_duration = Duration::from_millis(10);
+        let shared_config = SharedConfig {
+            enable_replication: false,
+            tick: TickConfig::new(tick_duration),
+            ..Default::default()
+        };
+        let link_conditioner = LinkConditionerConfig {
+            incoming_latency: Duration::from_millis(20),
+            incoming_jitter: Duration::from_millis(0),
+            incoming_loss: 0.0,
+            ..Default::default()
+        };
+        let mut stepper = BevyStepper::new(
+            shared_config,
+            SyncConfig::default(),
+            PredictionConfig::default(),
+            InterpolationConfig::default(),
+            link_conditioner,
+            frame_duration,
+        );
+        stepper
+            .client_app
+            .world
+            .resource_mut::<ClientConfig>()
+            .input
+            .send_interpolation_info = true;
+        stepper
+            .server_app
+            .add_plugins(LagCompensationPlugin::<MyProtocol, Component1>::default());
+        stepper.client_app.add_systems(
+            FixedUpdate,
+            press_input.in_set(InputSystemSet::BufferInputs),
+        );
+        // the component value is the tick at which it was recorded
+        stepper.server_app.add_systems(
+            FixedUpdate,
+            (|tick_manager: Res<TickManager>, mut query: Query<&mut Component1>| {
+                for mut component in query.iter_mut() {
+                    component.0 = tick_manager.tick().0 as f32;
+                }
+            })
+            .in_set(FixedUpdateSet::Main),
+        );
+        stepper.init();
+        let server_entity = stepper
+            .server_app
+            .world
+            .spawn((Component1(0.0), LagCompensated))
+            .id();
+        for _ in 0..20 {
+            stepper.frame_step();
+        }
+
+        let client_id = 111;
+        let (rewind_tick, value) = stepper.server_app.world.run_system_once(
+            move |lag_compensation: LagCompensation<MyProtocol, Component1>| {
+                let rewind_tick = lag_compensation.rewind_tick(client_id).unwrap();
+                let value = lag_compensation
+                    .rewind_query(client_id, |world| world.get(server_entity).cloned())
+                    .unwrap();
+                (rewind_tick, value)
+            },
+        );
+        let server_tick = stepper.server_app.world.resource::<TickManager>().tick();
+        // the client is seeing the world in the past
+        assert!(rewind_tick < server_tick);
+        assert_eq!(value, Some(Component1(rewind_tick.0 as f32)));
+    }
+
+    /// A client that claims an interpolation tick outside of `[current_tick - max_rewind_ticks, current_tick]`
+    /// gets its tick clamped
+    #[test]
+    fn test_rewind_tick_out_of_range() {
+        let frame_duration = Duration::from_millis(10);
+        let shared_config = SharedConfig {
+            enable_replication: false,
+            tick: TickConfig::new(frame_duration),
+            ..Default::default()
+        };
+        let mut stepper = BevyStepper::new(
+            shared_config,
+            SyncConfig::default(),
+            PredictionConfig::default(),
+            InterpolationConfig::default(),
+            LinkConditionerConfig::default(),
+            frame_duration,
+        );
+        stepper
+            .server_app
+            .add_plugins(LagCompensationPlugin::<MyProtocol, Component1>::new(
+                LagCompensationConfig::default()
+                    .with_history_ticks(64)
+                    .with_max_rewind_ticks(5),
+            ));
+        stepper.init();
+        for _ in 0..20 {
+            stepper.frame_step();
+        }
+
+        let client_id = 111;
+        let server_tick = stepper.server_tick();
+        for (claimed_tick, expected_tick) in [
+            // too far in the past
+            (server_tick - 15, server_tick - 5),
+            // ahead of the server
+            (server_tick + 3, server_tick),
+            // in range
+            (server_tick - 2, server_tick - 2),
+        ] {
+            stepper
+                .server_app
+                .world
+                .resource_mut::<ServerConnectionManager>()
+                .connections
+                .get_mut(&client_id)
+                .unwrap()
+                .interpolation_history
+                .insert(
+                    server_tick,
+                    InterpolationInfo {
+                        tick: claimed_tick,
+                        overstep: 0.0,
+                    },
+                );
+            let rewind_tick = stepper.server_app.world.run_system_once(
+                move |lag_compensation: LagCompensation<MyProtocol, Component1>| {
+                    lag_compensation.rewind_tick(client_id).unwrap()
+                },
+            );
+            assert_eq!(rewind_tick, expected_tick);
+        }
+    }
+
+    #[test]
+    fn test_config_per_component() {
+        let frame_duration = Duration::from_millis(10);
+        let shared_config = SharedConfig {
+            enable_replication: false,
+            tick: TickConfig::new(frame_duration),
+            ..Default::default()
+        };
+        let mut stepper = BevyStepper::new(
+            shared_config,
+            SyncConfig::default(),
+            PredictionConfig::default(),
+            InterpolationConfig::default(),
+            LinkConditionerConfig::default(),
+            frame_duration,
+        );
+        stepper.server_app.add_plugins((
+            LagCompensationPlugin::<MyProtocol, Component1>::new(
+                LagCompensationConfig::default().with_history_ticks(3),
+            ),
+            LagCompensationPlugin::<MyProtocol, Component2>::new(
+                LagCompensationConfig::default().with_history_ticks(10),
+            ),
+        ));
+        stepper.init();
+        let server_entity = stepper
+            .server_app
+            .world
+            .spawn((Component1(0.0), Component2(0.0), LagCompensated))
+            .id();
+        for _ in 0..20 {
+            stepper.frame_step();
+        }
+
+        // each component keeps the history length of its own plugin
+        let server_tick = stepper.server_tick();
+        let entity = stepper.server_app.world.entity(server_entity);
+        assert_eq!(
+            entity
+                .get::<LagCompensationHistory<Component1>>()
+                .unwrap()
+                .oldest_tick(),
+            Some(server_tick - 3)
+        );
+        assert_eq!(
+            entity
+                .get::<LagCompensationHistory<Component2>>()
+                .unwrap()
+                .oldest_tick(),
+            Some(server_tick - 10)
+        );
+    }
+}
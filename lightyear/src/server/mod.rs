@@ -11,12 +11,20 @@ pub mod events;
 
 mod input;
 
+pub mod lag_compensation;
+
 pub mod plugin;
 
 pub mod resource;
 
 pub mod room;
 
+pub mod rpc;
+
+pub mod spatial;
+
+pub mod visibility;
+
 #[cfg(feature = "leafwing")]
 pub mod input_leafwing;
 pub(crate) mod prediction;
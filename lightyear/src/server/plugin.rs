@@ -14,15 +14,20 @@ use crate::netcode::ClientId;
 use crate::prelude::{ShouldBePredicted, TimeManager};
 use crate::protocol::component::ComponentProtocol;
 use crate::protocol::message::MessageProtocol;
+use crate::protocol::registry::ProtocolRegistry;
 use crate::protocol::Protocol;
 use crate::server::connection::replication_clean;
 use crate::server::connection::ConnectionManager;
-use crate::server::events::{ConnectEvent, DisconnectEvent, EntityDespawnEvent, EntitySpawnEvent};
+use crate::server::events::{
+    ConnectEvent, DisconnectEvent, EntityDespawnEvent, EntitySpawnEvent, MessageAckEvent,
+    MessageLostEvent, RequestTimeoutEvent, StreamReceivedEvent, TransferProgressEvent,
+};
 use crate::server::input::InputPlugin;
 use crate::server::prediction::compute_hash;
 use crate::server::resource::Server;
 use crate::server::room::RoomPlugin;
 use crate::server::systems::clear_events;
+use crate::server::visibility::VisibilityPlugin;
 use crate::shared::plugin::SharedPlugin;
 use crate::shared::replication::systems::add_replication_send_systems;
 use crate::shared::sets::ReplicationSet;
@@ -67,7 +72,10 @@ impl<P: Protocol> ServerPlugin<P> {
 impl<P: Protocol> PluginType for ServerPlugin<P> {
     fn build(&self, app: &mut App) {
         let config = self.config.lock().unwrap().deref_mut().take().unwrap();
-        let netserver = crate::netcode::Server::new(config.server_config.netcode.clone());
+        let netserver = crate::netcode::Server::new(
+            config.server_config.netcode.clone(),
+            config.protocol.fingerprint(),
+        );
 
         let tick_duration = config.server_config.shared.tick.tick_duration;
         // TODO: have better constants for clean_interval?
@@ -79,6 +87,8 @@ impl<P: Protocol> PluginType for ServerPlugin<P> {
         P::Components::add_events::<ClientId>(app);
 
         P::Message::add_events::<ClientId>(app);
+        // messages sent to the local client are read as client events
+        P::Message::add_events::<()>(app);
 
         app
             // PLUGINS
@@ -88,6 +98,7 @@ impl<P: Protocol> PluginType for ServerPlugin<P> {
             })
             .add_plugins(InputPlugin::<P>::default())
             .add_plugins(RoomPlugin::<P>::default())
+            .add_plugins(VisibilityPlugin::<P>::default())
             .add_plugins(TimePlugin {
                 send_interval: config.server_config.shared.server_send_interval,
             })
@@ -99,6 +110,7 @@ impl<P: Protocol> PluginType for ServerPlugin<P> {
                 config.protocol.channel_registry().clone(),
             ))
             .insert_resource(config.protocol)
+            .init_resource::<ProtocolRegistry>()
             // .insert_resource(server)
             // SYSTEM SETS //
             .configure_sets(
@@ -143,6 +155,11 @@ impl<P: Protocol> PluginType for ServerPlugin<P> {
             .add_event::<DisconnectEvent>()
             .add_event::<EntitySpawnEvent>()
             .add_event::<EntityDespawnEvent>()
+            .add_event::<MessageAckEvent>()
+            .add_event::<MessageLostEvent>()
+            .add_event::<RequestTimeoutEvent>()
+            .add_event::<TransferProgressEvent>()
+            .add_event::<StreamReceivedEvent>()
             // SYSTEMS //
             .add_systems(
                 PreUpdate,
@@ -164,4 +181,22 @@ impl<P: Protocol> PluginType for ServerPlugin<P> {
                 replication_clean::<P>.run_if(on_timer(clean_interval)),
             );
     }
+
+    fn finish(&self, app: &mut App) {
+        // the messages and components registered at runtime are part of the protocol
+        let Some(netcode_fingerprint) = app
+            .world
+            .resource::<crate::netcode::Server>()
+            .protocol_fingerprint()
+        else {
+            return;
+        };
+        let fingerprint = app
+            .world
+            .resource::<ProtocolRegistry>()
+            .fingerprint(netcode_fingerprint);
+        app.world
+            .resource_mut::<crate::netcode::Server>()
+            .set_protocol_fingerprint(fingerprint);
+    }
 }
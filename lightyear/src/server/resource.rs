@@ -8,19 +8,22 @@ use bevy::ecs::system::SystemParam;
 use bevy::prelude::{Entity, Res, ResMut, Resource, World};
 use bevy::utils::{EntityHashMap, HashSet};
 use crossbeam_channel::Sender;
+use serde::Serialize;
 use tracing::{debug, debug_span, error, info, trace, trace_span};
 
 use crate::_reexport::FromType;
 use crate::channel::builder::Channel;
-use crate::netcode::{generate_key, ClientId, ConnectToken};
-use crate::packet::message::Message;
+use crate::netcode::{generate_key, ClientId, ConnectToken, DisconnectReason};
+use crate::packet::message::{Message, MessageHandle};
 use crate::prelude::PreSpawnedPlayerObject;
 use crate::protocol::channel::ChannelKind;
+use crate::protocol::registry::{DynamicMessage, ProtocolRegistry, RegisteredMessage};
 use crate::protocol::Protocol;
 use crate::server::room::{RoomId, RoomManager, RoomMut, RoomRef};
 use crate::shared::replication::components::{NetworkTarget, Replicate};
 use crate::shared::replication::components::{ShouldBeInterpolated, ShouldBePredicted};
 use crate::shared::replication::ReplicationSend;
+use crate::shared::rpc::{Request, RequestId};
 use crate::shared::tick_manager::Tick;
 use crate::shared::tick_manager::TickManager;
 use crate::shared::time_manager::TimeManager;
@@ -63,6 +66,7 @@ pub struct ServerMut<'w, 's, P: Protocol> {
     pub(crate) connection_manager: ResMut<'w, ConnectionManager<P>>,
     // Protocol
     pub protocol: ResMut<'w, P>,
+    registry: Res<'w, ProtocolRegistry>,
     // Rooms
     pub(crate) room_manager: ResMut<'w, RoomManager>,
     // Time
@@ -82,6 +86,7 @@ impl<'w, 's, P: Protocol> ServerMut<'w, 's, P> {
     pub(crate) fn update(&mut self, delta: Duration) -> Result<()> {
         // update time manager
         self.time_manager.update(delta);
+        self.io.update(delta)?;
 
         // update netcode server
         let context = self
@@ -97,12 +102,30 @@ impl<'w, 's, P: Protocol> ServerMut<'w, 's, P> {
         for client_id in context.connections.iter().copied() {
             // let client_addr = self.netcode.client_addr(client_id).unwrap();
             // info!("New connection from {} (id: {})", client_addr, client_id);
-            self.connection_manager.add(client_id, &self.config.ping);
+            if self.connection_manager.is_local_client(client_id) {
+                error!(
+                    ?client_id,
+                    "Disconnecting remote client that uses the id of the local client"
+                );
+                self.netcode
+                    .disconnect(client_id, DisconnectReason::Kicked, &[], &mut self.io)?;
+                continue;
+            }
+            self.connection_manager
+                .add(client_id, &self.config.ping, &self.config.priority);
         }
 
         // handle disconnections
-        for client_id in context.disconnections.iter().copied() {
-            self.connection_manager.remove(client_id);
+        // (a remote client that was rejected because it used the id of the local client must not remove the local client)
+        let disconnections: Vec<_> = context
+            .disconnections
+            .into_iter()
+            .filter(|(client_id, _, _)| !self.connection_manager.is_local_client(*client_id))
+            .collect();
+        let local_disconnections =
+            std::mem::take(&mut self.connection_manager.local_disconnections);
+        for (client_id, reason, payload) in disconnections.into_iter().chain(local_disconnections) {
+            self.connection_manager.remove(client_id, reason, payload);
             self.room_manager.client_disconnect(client_id);
         }
         Ok(())
@@ -148,16 +171,64 @@ impl<'w, 's, P: Protocol> ServerMut<'w, 's, P> {
     }
 
     /// Queues up a message to be sent to a client
+    ///
+    /// Returns a [`MessageHandle`] if the channel tracks acks, which lets you match the message
+    /// with the [`MessageAckEvent`](crate::server::events::MessageAckEvent) or
+    /// [`MessageLostEvent`](crate::server::events::MessageLostEvent) emitted for it
     pub fn send_message<C: Channel, M: Message>(
         &mut self,
         client_id: ClientId,
         message: M,
-    ) -> Result<()>
+    ) -> Result<Option<MessageHandle>>
     where
         M: Clone,
         P::Message: From<M>,
     {
-        self.send_message_to_target::<C, M>(message, NetworkTarget::Only(vec![client_id]))
+        let _span =
+            debug_span!("send_message", channel = ?C::type_name(), message = ?message.name(), ?client_id)
+                .entered();
+        self.connection_manager
+            .send_message::<C, M>(client_id, message)
+    }
+
+    /// Queues up a message that was registered with [`AppRegistrationExt::register_message`] to be sent to all clients
+    /// that match the target
+    ///
+    /// [`AppRegistrationExt::register_message`]: crate::protocol::registry::AppRegistrationExt::register_message
+    pub fn send_registered_message_to_target<C: Channel, M: RegisteredMessage>(
+        &mut self,
+        message: M,
+        target: NetworkTarget,
+    ) -> Result<()> {
+        let message = self.registry.message(message)?;
+        self.send_message_to_target::<C, DynamicMessage>(message, target)
+    }
+
+    /// Queues up a message that was registered with [`AppRegistrationExt::register_message`] to be sent to a client
+    ///
+    /// [`AppRegistrationExt::register_message`]: crate::protocol::registry::AppRegistrationExt::register_message
+    pub fn send_registered_message<C: Channel, M: RegisteredMessage>(
+        &mut self,
+        client_id: ClientId,
+        message: M,
+    ) -> Result<Option<MessageHandle>> {
+        let message = self.registry.message(message)?;
+        self.send_message::<C, DynamicMessage>(client_id, message)
+    }
+
+    /// Send a request to a client on the reliable channel `C`
+    ///
+    /// The response can be read with [`ServerRpc`](crate::server::rpc::ServerRpc)
+    pub fn send_request<C: Channel, R: Request>(
+        &mut self,
+        client_id: ClientId,
+        request: R,
+    ) -> Result<RequestId>
+    where
+        P::Message: From<R>,
+    {
+        self.connection_manager
+            .send_request::<C, R>(client_id, request)
     }
 
     // ROOM
@@ -430,6 +501,7 @@ impl<P: Protocol> ReplicationSend<P> for ConnectionManager<P> {
         self.apply_replication(target).try_for_each(|client_id| {
             // TODO: should we have additional state tracking so that we know we are in the process of sending this entity to clients?
             let replication_sender = &mut self.connection_mut(client_id)?.replication_sender;
+            replication_sender.update_base_priority(group, replicate.priority);
             let collect_changes_since_this_tick = replication_sender
                 .group_channels
                 .entry(group)
@@ -9,7 +9,7 @@ use crate::netcode::ClientId;
 use crate::prelude::ReplicationSet;
 use crate::protocol::Protocol;
 use crate::server::resource::Server;
-use crate::shared::replication::components::{DespawnTracker, Replicate};
+use crate::shared::replication::components::{DespawnTracker, Replicate, ReplicationMode};
 use crate::shared::time_manager::is_ready_to_send;
 use crate::utils::wrapping_id::wrapping_id;
 
@@ -38,6 +38,19 @@ pub struct RoomData {
     rooms: HashMap<RoomId, Room>,
 }
 
+impl RoomData {
+    /// Returns true if the client and the entity are both in at least one common room
+    fn share_room(&self, client_id: ClientId, entity: Entity) -> bool {
+        let (Some(client_rooms), Some(entity_rooms)) = (
+            self.client_to_rooms.get(&client_id),
+            self.entity_to_rooms.get(&entity),
+        ) else {
+            return false;
+        };
+        !client_rooms.is_disjoint(entity_rooms)
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct Room {
     /// list of clients that are in the room
@@ -262,6 +275,24 @@ impl<'s> RoomRef<'s> {
             .get(&self.id)
             .map_or_else(|| false, |room| room.entities.contains(&entity))
     }
+
+    /// Iterate through the entities that are in the room
+    pub fn entities(&self) -> impl Iterator<Item = Entity> + 's {
+        self.manager
+            .data
+            .rooms
+            .get(&self.id)
+            .into_iter()
+            .flat_map(|room| room.entities.iter().copied())
+    }
+
+    /// Returns true if there are no clients and no entities in the room
+    pub fn is_empty(&self) -> bool {
+        self.manager.data.rooms.get(&self.id).map_or_else(
+            || true,
+            |room| room.clients.is_empty() && room.entities.is_empty(),
+        )
+    }
 }
 
 impl RoomEvents {
@@ -371,7 +402,11 @@ fn update_entity_replication_cache<P: Protocol>(
         rooms.iter().for_each(|room_id| {
             let room = room_manager.data.rooms.get(room_id).unwrap();
             room.clients.iter().for_each(|client_id| {
-                if let Ok(mut replicate) = query.get_mut(*entity) {
+                if let Some(mut replicate) = query
+                    .get_mut(*entity)
+                    .ok()
+                    .filter(|r| r.replication_mode == ReplicationMode::Room)
+                {
                     // only set it to gained if it wasn't present before
                     replicate
                         .replication_clients_cache
@@ -387,7 +422,15 @@ fn update_entity_replication_cache<P: Protocol>(
         rooms.iter().for_each(|room_id| {
             let room = room_manager.data.rooms.get(room_id).unwrap();
             room.clients.iter().for_each(|client_id| {
-                if let Ok(mut replicate) = query.get_mut(*entity) {
+                // the entity stays visible if the client and entity still share another room
+                if room_manager.data.share_room(*client_id, *entity) {
+                    return;
+                }
+                if let Some(mut replicate) = query
+                    .get_mut(*entity)
+                    .ok()
+                    .filter(|r| r.replication_mode == ReplicationMode::Room)
+                {
                     if let Some(visibility) = replicate.replication_clients_cache.get_mut(client_id)
                     {
                         *visibility = ClientVisibility::Lost;
@@ -401,7 +444,11 @@ fn update_entity_replication_cache<P: Protocol>(
         rooms.iter().for_each(|room_id| {
             let room = room_manager.data.rooms.get(room_id).unwrap();
             room.entities.iter().for_each(|entity| {
-                if let Ok(mut replicate) = query.get_mut(*entity) {
+                if let Some(mut replicate) = query
+                    .get_mut(*entity)
+                    .ok()
+                    .filter(|r| r.replication_mode == ReplicationMode::Room)
+                {
                     replicate
                         .replication_clients_cache
                         .entry(*client_id)
@@ -415,7 +462,15 @@ fn update_entity_replication_cache<P: Protocol>(
         rooms.iter().for_each(|room_id| {
             let room = room_manager.data.rooms.get(room_id).unwrap();
             room.entities.iter().for_each(|entity| {
-                if let Ok(mut replicate) = query.get_mut(*entity) {
+                // the entity stays visible if the client and entity still share another room
+                if room_manager.data.share_room(*client_id, *entity) {
+                    return;
+                }
+                if let Some(mut replicate) = query
+                    .get_mut(*entity)
+                    .ok()
+                    .filter(|r| r.replication_mode == ReplicationMode::Room)
+                {
                     if let Some(visibility) = replicate.replication_clients_cache.get_mut(client_id)
                     {
                         *visibility = ClientVisibility::Lost;
@@ -487,6 +542,7 @@ mod tests {
             incoming_latency: Duration::from_millis(0),
             incoming_jitter: Duration::from_millis(0),
             incoming_loss: 0.0,
+            ..Default::default()
         };
         let sync_config = SyncConfig::default().speedup_factor(1.0);
         let prediction_config = PredictionConfig::default().disable(false);
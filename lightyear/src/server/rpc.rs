@@ -0,0 +1,87 @@
+//! System parameter to exchange requests and responses with the clients
+use std::marker::PhantomData;
+
+use anyhow::Result;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::ResMut;
+
+use crate::channel::builder::Channel;
+use crate::netcode::ClientId;
+use crate::protocol::Protocol;
+use crate::shared::rpc::{ReceivedRequest, Request, RequestId};
+
+use super::connection::ConnectionManager;
+
+/// Send requests of type `R` to clients and read their responses,
+/// or read the requests of type `R` sent by clients and respond to them.
+///
+/// Requests and responses that are not read stay buffered until they are read, or until the client disconnects.
+#[derive(SystemParam)]
+pub struct ServerRpc<'w, P: Protocol, R: Request> {
+    connection_manager: ResMut<'w, ConnectionManager<P>>,
+    _marker: PhantomData<R>,
+}
+
+impl<'w, P: Protocol, R: Request> ServerRpc<'w, P, R> {
+    /// Send a request to a client on the reliable channel `C`
+    pub fn send_request<C: Channel>(&mut self, client_id: ClientId, request: R) -> Result<RequestId>
+    where
+        P::Message: From<R>,
+    {
+        self.connection_manager
+            .send_request::<C, R>(client_id, request)
+    }
+
+    /// Take the responses to the requests we sent, along with the client that sent them
+    pub fn drain_responses(&mut self) -> Vec<(ClientId, RequestId, R::Response)>
+    where
+        P::Message: TryInto<R::Response, Error = ()>,
+    {
+        self.connection_manager
+            .connections
+            .iter_mut()
+            .flat_map(|(client_id, connection)| {
+                connection
+                    .rpc
+                    .drain_responses::<R>()
+                    .into_iter()
+                    .map(|(request_id, response)| (*client_id, request_id, response))
+            })
+            .collect()
+    }
+
+    /// Take the requests sent by the clients
+    pub fn drain_requests(&mut self) -> Vec<(ReceivedRequest<ClientId>, R)>
+    where
+        P::Message: TryInto<R, Error = ()>,
+    {
+        self.connection_manager
+            .connections
+            .iter_mut()
+            .flat_map(|(client_id, connection)| {
+                connection.rpc.drain_requests::<R>().into_iter().map(
+                    |(request_id, channel, request)| {
+                        (
+                            ReceivedRequest::new(request_id, channel, *client_id),
+                            request,
+                        )
+                    },
+                )
+            })
+            .collect()
+    }
+
+    /// Send the response to a request sent by a client
+    pub fn respond(
+        &mut self,
+        request: ReceivedRequest<ClientId>,
+        response: R::Response,
+    ) -> Result<()>
+    where
+        P::Message: From<R::Response>,
+    {
+        self.connection_manager
+            .connection_mut(*request.context())?
+            .send_response(request.request_id, request.channel, response.into())
+    }
+}
@@ -0,0 +1,775 @@
+//! Spatial interest management built on top of [`Rooms`](crate::server::room)
+//!
+//! Instead of manually adding clients and entities to rooms, the [`SpatialPlugin`] reads a position component
+//! and automatically updates the rooms so that each client only receives the entities that are close to its
+//! [`SpatialViewer`] entity.
+//!
+//! Two modes are available:
+//! - [`SpatialMode::Grid`]: the world is divided in square cells, each cell is backed by a room.
+//!   Entities join the room of the cell they are in, and clients join the rooms of every cell within `view_distance`
+//!   cells of their viewer.
+//! - [`SpatialMode::Radius`]: each client has its own room, and every entity within `radius` of the viewer joins that room.
+//!   The entities are indexed in a grid of cells, and only the viewers and entities whose position changed are updated.
+//!
+//! In both modes, a `hysteresis` margin is applied so that entities that move back and forth around a border
+//! don't get spawned/despawned on the client every send interval.
+use std::marker::PhantomData;
+
+use bevy::prelude::{
+    App, Component, DetectChanges, Entity, EventReader, IVec2, IntoSystemConfigs,
+    IntoSystemSetConfigs, Plugin, PostUpdate, Query, Ref, RemovedComponents, Res, ResMut, Resource,
+    SystemSet, Vec2,
+};
+use bevy::utils::{HashMap, HashSet};
+use tracing::error;
+
+use crate::netcode::ClientId;
+use crate::protocol::Protocol;
+use crate::server::connection::ConnectionManager;
+use crate::server::events::DisconnectEvent;
+use crate::server::room::{RoomId, RoomManager, RoomSystemSets};
+use crate::shared::replication::components::{Replicate, ReplicationMode};
+use crate::shared::time_manager::is_ready_to_send;
+
+/// Trait to implement on the component that holds the position of an entity
+/// (for example your `Position` or `Transform` component)
+pub trait SpatialPosition: Component {
+    /// Position of the entity in the 2D plane used for interest management
+    fn position(&self) -> Vec2;
+}
+
+/// Marker component to add on the entity that represents the point of view of a client
+/// (usually the entity controlled by the client). The position of this entity will be used to compute
+/// which entities are replicated to the client.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct SpatialViewer(pub ClientId);
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SpatialMode {
+    /// The world is divided in a grid of square cells
+    Grid {
+        /// Size of each cell
+        cell_size: f32,
+        /// Clients receive the entities that are at most `view_distance` cells away from their viewer's cell
+        view_distance: u32,
+    },
+    /// Clients receive the entities that are within `radius` of their viewer
+    Radius { radius: f32 },
+}
+
+#[derive(Clone, Debug)]
+pub struct SpatialConfig {
+    pub mode: SpatialMode,
+    /// Distance that an entity must travel past a border (cell border or radius) before we
+    /// update its visibility. This avoids entities flapping in and out of replication at borders.
+    pub hysteresis: f32,
+    /// The plugin allocates rooms starting from this id; you should not use room ids
+    /// greater or equal to this value for your own rooms.
+    pub first_room_id: RoomId,
+}
+
+impl Default for SpatialConfig {
+    fn default() -> Self {
+        Self {
+            mode: SpatialMode::Grid {
+                cell_size: 100.0,
+                view_distance: 1,
+            },
+            hysteresis: 10.0,
+            first_room_id: RoomId(u16::MAX / 2 + 1),
+        }
+    }
+}
+
+impl SpatialConfig {
+    /// Use a grid of cells of size `cell_size`
+    pub fn grid(cell_size: f32, view_distance: u32) -> Self {
+        Self {
+            mode: SpatialMode::Grid {
+                cell_size,
+                view_distance,
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Replicate the entities that are within `radius` of the viewer
+    pub fn radius(radius: f32) -> Self {
+        Self {
+            mode: SpatialMode::Radius { radius },
+            ..Default::default()
+        }
+    }
+
+    pub fn with_hysteresis(mut self, hysteresis: f32) -> Self {
+        self.hysteresis = hysteresis;
+        self
+    }
+
+    pub fn with_first_room_id(mut self, first_room_id: RoomId) -> Self {
+        self.first_room_id = first_room_id;
+        self
+    }
+}
+
+/// Plugin that assigns entities and clients to rooms based on the position component `C`.
+///
+/// Only entities with a [`Replicate`] component whose `replication_mode` is [`ReplicationMode::Room`] are affected.
+pub struct SpatialPlugin<P: Protocol, C: SpatialPosition> {
+    config: SpatialConfig,
+    _marker: PhantomData<(P, C)>,
+}
+
+impl<P: Protocol, C: SpatialPosition> SpatialPlugin<P, C> {
+    pub fn new(config: SpatialConfig) -> Self {
+        Self {
+            config,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<P: Protocol, C: SpatialPosition> Default for SpatialPlugin<P, C> {
+    fn default() -> Self {
+        Self::new(SpatialConfig::default())
+    }
+}
+
+/// System set where the rooms are updated from the positions of the entities
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub struct SpatialSet;
+
+impl<P: Protocol, C: SpatialPosition> Plugin for SpatialPlugin<P, C> {
+    fn build(&self, app: &mut App) {
+        // RESOURCES
+        app.insert_resource(SpatialManager::new(self.config.clone()));
+        // SETS
+        app.configure_sets(
+            PostUpdate,
+            SpatialSet
+                .before(RoomSystemSets::UpdateReplicationCaches)
+                .run_if(is_ready_to_send),
+        );
+        // SYSTEMS
+        app.add_systems(PostUpdate, cleanup_disconnected.before(SpatialSet));
+        match self.config.mode {
+            SpatialMode::Grid { .. } => app.add_systems(
+                PostUpdate,
+                (cleanup_removed::<C>, update_grid::<P, C>)
+                    .chain()
+                    .in_set(SpatialSet),
+            ),
+            SpatialMode::Radius { .. } => app.add_systems(
+                PostUpdate,
+                (cleanup_removed::<C>, update_radius::<P, C>)
+                    .chain()
+                    .in_set(SpatialSet),
+            ),
+        };
+    }
+}
+
+/// Keeps track of the rooms allocated by the [`SpatialPlugin`], and of the cell of each entity/viewer
+#[derive(Resource, Debug)]
+pub struct SpatialManager {
+    config: SpatialConfig,
+    /// Next room id that has never been allocated (None if we ran out of room ids)
+    next_room_id: Option<u16>,
+    /// Room ids that were allocated but are not used anymore
+    free_rooms: Vec<RoomId>,
+    /// Room associated with each grid cell
+    cell_rooms: HashMap<IVec2, RoomId>,
+    /// Room associated with each client (in [`SpatialMode::Radius`])
+    client_rooms: HashMap<ClientId, RoomId>,
+    /// Current cell of each entity
+    entity_cells: HashMap<Entity, IVec2>,
+    /// Entities of each cell (in [`SpatialMode::Radius`], where the cells are only used as an index)
+    cell_entities: HashMap<IVec2, HashSet<Entity>>,
+    /// Current cell of each viewer
+    viewer_cells: HashMap<ClientId, IVec2>,
+    /// Client associated with each viewer entity
+    viewers: HashMap<Entity, ClientId>,
+}
+
+impl SpatialManager {
+    fn new(config: SpatialConfig) -> Self {
+        Self {
+            next_room_id: Some(config.first_room_id.0),
+            config,
+            free_rooms: Vec::new(),
+            cell_rooms: HashMap::default(),
+            client_rooms: HashMap::default(),
+            entity_cells: HashMap::default(),
+            cell_entities: HashMap::default(),
+            viewer_cells: HashMap::default(),
+            viewers: HashMap::default(),
+        }
+    }
+
+    /// Returns the room that backs the given cell, if it has been allocated
+    pub fn cell_room(&self, cell: IVec2) -> Option<RoomId> {
+        self.cell_rooms.get(&cell).copied()
+    }
+
+    /// Returns the current cell of the entity
+    pub fn entity_cell(&self, entity: Entity) -> Option<IVec2> {
+        self.entity_cells.get(&entity).copied()
+    }
+
+    fn allocate_room(&mut self) -> Option<RoomId> {
+        if let Some(room_id) = self.free_rooms.pop() {
+            return Some(room_id);
+        }
+        let Some(id) = self.next_room_id else {
+            error!("SpatialPlugin ran out of room ids");
+            return None;
+        };
+        self.next_room_id = id.checked_add(1);
+        Some(RoomId(id))
+    }
+
+    fn get_or_allocate_cell_room(&mut self, cell: IVec2) -> Option<RoomId> {
+        if let Some(room_id) = self.cell_rooms.get(&cell) {
+            return Some(*room_id);
+        }
+        let room_id = self.allocate_room()?;
+        self.cell_rooms.insert(cell, room_id);
+        Some(room_id)
+    }
+
+    /// Compute the new cell of a position, staying in the current cell
+    /// if the position is within `hysteresis` of the current cell
+    fn compute_cell(&self, current: Option<IVec2>, position: Vec2, cell_size: f32) -> IVec2 {
+        let new = (position / cell_size).floor().as_ivec2();
+        match current {
+            Some(cell) if cell != new => {
+                let min = cell.as_vec2() * cell_size - Vec2::splat(self.config.hysteresis);
+                let max =
+                    (cell + IVec2::ONE).as_vec2() * cell_size + Vec2::splat(self.config.hysteresis);
+                if position.cmpge(min).all() && position.cmple(max).all() {
+                    cell
+                } else {
+                    new
+                }
+            }
+            _ => new,
+        }
+    }
+
+    /// Return the rooms of the cells that are within `view_distance` of the cell
+    fn visible_cells(cell: IVec2, view_distance: u32) -> impl Iterator<Item = IVec2> {
+        let d = view_distance as i32;
+        (-d..=d).flat_map(move |x| (-d..=d).map(move |y| cell + IVec2::new(x, y)))
+    }
+
+    /// Move the entity to the given cell of the index
+    fn index_entity(&mut self, entity: Entity, cell: IVec2) {
+        if let Some(previous) = self.entity_cells.insert(entity, cell) {
+            if previous == cell {
+                return;
+            }
+            self.unindex_entity_cell(entity, previous);
+        }
+        self.cell_entities.entry(cell).or_default().insert(entity);
+    }
+
+    fn unindex_entity_cell(&mut self, entity: Entity, cell: IVec2) {
+        if let Some(entities) = self.cell_entities.get_mut(&cell) {
+            entities.remove(&entity);
+            if entities.is_empty() {
+                self.cell_entities.remove(&cell);
+            }
+        }
+    }
+
+    /// Remove the client from the cell rooms it can see, and free its own room
+    fn remove_client(&mut self, client_id: ClientId, room_manager: &mut RoomManager) {
+        if let (Some(cell), SpatialMode::Grid { view_distance, .. }) =
+            (self.viewer_cells.remove(&client_id), &self.config.mode)
+        {
+            for visible in SpatialManager::visible_cells(cell, *view_distance) {
+                if let Some(room_id) = self.cell_room(visible) {
+                    room_manager.room_mut(room_id).remove_client(client_id);
+                }
+            }
+        }
+        if let Some(room_id) = self.client_rooms.remove(&client_id) {
+            room_manager.room_mut(room_id).remove_client(client_id);
+            // empty the room before it gets re-used by another client
+            let entities: Vec<Entity> = room_manager.room(room_id).entities().collect();
+            for entity in entities {
+                room_manager.room_mut(room_id).remove_entity(entity);
+            }
+            self.free_rooms.push(room_id);
+        }
+    }
+
+    /// Free the rooms of the cells that don't contain any client or entity anymore
+    fn free_empty_rooms(&mut self, room_manager: &RoomManager) {
+        let free_rooms = &mut self.free_rooms;
+        self.cell_rooms.retain(|_, room_id| {
+            if room_manager.room(*room_id).is_empty() {
+                free_rooms.push(*room_id);
+                false
+            } else {
+                true
+            }
+        });
+    }
+}
+
+/// Remove entities and viewers that don't have a position (or were despawned)
+fn cleanup_removed<C: SpatialPosition>(
+    mut manager: ResMut<SpatialManager>,
+    mut room_manager: ResMut<RoomManager>,
+    mut removed_positions: RemovedComponents<C>,
+    mut removed_viewers: RemovedComponents<SpatialViewer>,
+) {
+    for entity in removed_positions.read() {
+        if let Some(cell) = manager.entity_cells.remove(&entity) {
+            if let Some(room_id) = manager.cell_room(cell) {
+                room_manager.room_mut(room_id).remove_entity(entity);
+            }
+            manager.unindex_entity_cell(entity, cell);
+        }
+        for room_id in manager.client_rooms.values() {
+            if room_manager.room(*room_id).has_entity(entity) {
+                room_manager.room_mut(*room_id).remove_entity(entity);
+            }
+        }
+    }
+    for entity in removed_viewers.read() {
+        let Some(client_id) = manager.viewers.remove(&entity) else {
+            continue;
+        };
+        manager.remove_client(client_id, &mut room_manager);
+    }
+}
+
+/// Free the rooms of the clients that disconnected, even if their viewer entity still exists.
+///
+/// This runs every frame (and not only when we are ready to send) so that no event is missed.
+fn cleanup_disconnected(
+    mut manager: ResMut<SpatialManager>,
+    mut room_manager: ResMut<RoomManager>,
+    mut disconnections: EventReader<DisconnectEvent>,
+) {
+    for event in disconnections.read() {
+        let client_id = *event.context();
+        manager.viewers.retain(|_, viewer| *viewer != client_id);
+        manager.remove_client(client_id, &mut room_manager);
+    }
+}
+
+/// Update the cell of every entity and viewer that moved, and update the cell rooms accordingly
+fn update_grid<P: Protocol, C: SpatialPosition>(
+    mut manager: ResMut<SpatialManager>,
+    mut room_manager: ResMut<RoomManager>,
+    connection_manager: Res<ConnectionManager<P>>,
+    entities: Query<(Entity, Ref<C>, &Replicate<P>)>,
+    viewers: Query<(Entity, Ref<C>, &SpatialViewer)>,
+) {
+    let SpatialMode::Grid {
+        cell_size,
+        view_distance,
+    } = manager.config.mode
+    else {
+        return;
+    };
+    for (entity, position, replicate) in entities.iter() {
+        if replicate.replication_mode != ReplicationMode::Room {
+            continue;
+        }
+        let current = manager.entity_cells.get(&entity).copied();
+        if current.is_some() && !position.is_changed() {
+            continue;
+        }
+        let cell = manager.compute_cell(current, position.position(), cell_size);
+        if current == Some(cell) {
+            continue;
+        }
+        let Some(room_id) = manager.get_or_allocate_cell_room(cell) else {
+            continue;
+        };
+        if let Some(previous_room_id) = current.and_then(|c| manager.cell_room(c)) {
+            room_manager
+                .room_mut(previous_room_id)
+                .remove_entity(entity);
+        }
+        room_manager.room_mut(room_id).add_entity(entity);
+        manager.entity_cells.insert(entity, cell);
+    }
+    for (entity, position, viewer) in viewers.iter() {
+        let client_id = viewer.0;
+        // the rooms of disconnected clients are freed, don't allocate them again
+        if connection_manager.connection(client_id).is_err() {
+            continue;
+        }
+        manager.viewers.insert(entity, client_id);
+        let current = manager.viewer_cells.get(&client_id).copied();
+        if current.is_some() && !position.is_changed() {
+            continue;
+        }
+        let cell = manager.compute_cell(current, position.position(), cell_size);
+        if current == Some(cell) {
+            continue;
+        }
+        let new_cells: HashSet<IVec2> =
+            SpatialManager::visible_cells(cell, view_distance).collect();
+        if let Some(current) = current {
+            for previous in SpatialManager::visible_cells(current, view_distance) {
+                if new_cells.contains(&previous) {
+                    continue;
+                }
+                if let Some(room_id) = manager.cell_room(previous) {
+                    room_manager.room_mut(room_id).remove_client(client_id);
+                }
+            }
+        }
+        for visible in new_cells {
+            if let Some(room_id) = manager.get_or_allocate_cell_room(visible) {
+                room_manager.room_mut(room_id).add_client(client_id);
+            }
+        }
+        manager.viewer_cells.insert(client_id, cell);
+    }
+    manager.free_empty_rooms(&room_manager);
+}
+
+/// Add the entities that are within the radius of each viewer to the viewer's room,
+/// and remove the ones that are further than the radius (plus the hysteresis margin).
+///
+/// The entities are indexed in cells of size `radius + hysteresis`, so that a viewer that moved only needs to check
+/// the entities of the cells around it. A viewer that didn't move only checks the entities that moved.
+fn update_radius<P: Protocol, C: SpatialPosition>(
+    mut manager: ResMut<SpatialManager>,
+    mut room_manager: ResMut<RoomManager>,
+    connection_manager: Res<ConnectionManager<P>>,
+    entities: Query<(Entity, Ref<C>, &Replicate<P>)>,
+    viewers: Query<(Entity, Ref<C>, &SpatialViewer)>,
+) {
+    let SpatialMode::Radius { radius } = manager.config.mode else {
+        return;
+    };
+    let exit_radius = radius + manager.config.hysteresis;
+    let cell = |position: Vec2| (position / exit_radius).floor().as_ivec2();
+    let mut moved = vec![];
+    for (entity, position, replicate) in entities.iter() {
+        if replicate.replication_mode != ReplicationMode::Room {
+            continue;
+        }
+        if manager.entity_cells.contains_key(&entity) && !position.is_changed() {
+            continue;
+        }
+        manager.index_entity(entity, cell(position.position()));
+        moved.push((entity, position.position()));
+    }
+    for (viewer_entity, viewer_position, viewer) in viewers.iter() {
+        let client_id = viewer.0;
+        // the rooms of disconnected clients are freed, don't allocate them again
+        if connection_manager.connection(client_id).is_err() {
+            continue;
+        }
+        manager.viewers.insert(viewer_entity, client_id);
+        let viewer_position_changed = viewer_position.is_changed();
+        let viewer_position = viewer_position.position();
+        let (room_id, new_room) = match manager.client_rooms.get(&client_id) {
+            Some(room_id) => (*room_id, false),
+            None => {
+                let Some(room_id) = manager.allocate_room() else {
+                    continue;
+                };
+                manager.client_rooms.insert(client_id, room_id);
+                room_manager.room_mut(room_id).add_client(client_id);
+                (room_id, true)
+            }
+        };
+        let update_visibility =
+            |room_manager: &mut RoomManager, entity: Entity, position: Vec2| {
+                let distance = viewer_position.distance(position);
+                let visible = room_manager.room(room_id).has_entity(entity);
+                if !visible && distance <= radius {
+                    room_manager.room_mut(room_id).add_entity(entity);
+                } else if visible && distance > exit_radius {
+                    room_manager.room_mut(room_id).remove_entity(entity);
+                }
+            };
+        if new_room || viewer_position_changed {
+            // the entities that can be visible are the ones already in the room,
+            // and the ones in the cells around the viewer
+            let mut candidates: HashSet<Entity> = room_manager.room(room_id).entities().collect();
+            for visible in SpatialManager::visible_cells(cell(viewer_position), 1) {
+                if let Some(cell_entities) = manager.cell_entities.get(&visible) {
+                    candidates.extend(cell_entities.iter().copied());
+                }
+            }
+            for entity in candidates {
+                if let Ok((_, position, replicate)) = entities.get(entity) {
+                    if replicate.replication_mode == ReplicationMode::Room {
+                        update_visibility(&mut room_manager, entity, position.position());
+                    }
+                }
+            }
+        } else {
+            for (entity, position) in moved.iter() {
+                update_visibility(&mut room_manager, *entity, *position);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::ecs::system::SystemState;
+    use bevy::prelude::Vec2;
+
+    use crate::prelude::client::*;
+    use crate::prelude::*;
+    use crate::tests::protocol::Replicate;
+    use crate::tests::protocol::*;
+    use crate::tests::stepper::{BevyStepper, Step};
+
+    use super::*;
+
+    impl SpatialPosition for Component1 {
+        fn position(&self) -> Vec2 {
+            Vec2::new(self.0, 0.0)
+        }
+    }
+
+    fn setup(config: SpatialConfig) -> BevyStepper {
+        let frame_duration = Duration::from_millis(10);
+        let tick_duration = Duration::from_millis(10);
+        let shared_config = SharedConfig {
+            enable_replication: true,
+            tick: TickConfig::new(tick_duration),
+            ..Default::default()
+        };
+        let link_conditioner = LinkConditionerConfig {
+            incoming_latency: Duration::from_millis(0),
+            incoming_jitter: Duration::from_millis(0),
+            incoming_loss: 0.0,
+            ..Default::default()
+        };
+        let sync_config = SyncConfig::default().speedup_factor(1.0);
+        let prediction_config = PredictionConfig::default().disable(false);
+        let interpolation_config = InterpolationConfig::default();
+        let mut stepper = BevyStepper::new(
+            shared_config,
+            sync_config,
+            prediction_config,
+            interpolation_config,
+            link_conditioner,
+            frame_duration,
+        );
+        stepper
+            .server_app
+            .add_plugins(SpatialPlugin::<MyProtocol, Component1>::new(config));
+        stepper.init();
+        stepper
+    }
+
+    /// Returns the client entity that corresponds to the server entity, if it is replicated
+    fn client_entity(stepper: &BevyStepper, server_entity: Entity) -> Option<Entity> {
+        stepper
+            .client_app
+            .world
+            .resource::<ClientConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .copied()
+    }
+
+    fn move_to(stepper: &mut BevyStepper, entity: Entity, x: f32) {
+        stepper
+            .server_app
+            .world
+            .entity_mut(entity)
+            .get_mut::<Component1>()
+            .unwrap()
+            .0 = x;
+        stepper.frame_step();
+        stepper.frame_step();
+    }
+
+    #[test]
+    fn test_compute_cell_hysteresis() {
+        let manager = SpatialManager::new(SpatialConfig::grid(10.0, 1).with_hysteresis(2.0));
+        assert_eq!(
+            manager.compute_cell(None, Vec2::new(11.0, 5.0), 10.0),
+            IVec2::new(1, 0)
+        );
+        // within the hysteresis margin of the current cell: we don't switch cells
+        assert_eq!(
+            manager.compute_cell(Some(IVec2::new(0, 0)), Vec2::new(11.0, 5.0), 10.0),
+            IVec2::new(0, 0)
+        );
+        assert_eq!(
+            manager.compute_cell(Some(IVec2::new(0, 0)), Vec2::new(12.5, 5.0), 10.0),
+            IVec2::new(1, 0)
+        );
+        assert_eq!(
+            manager.compute_cell(Some(IVec2::new(0, 0)), Vec2::new(-1.0, -1.0), 10.0),
+            IVec2::new(0, 0)
+        );
+    }
+
+    #[test]
+    fn test_grid_visibility() {
+        let mut stepper = setup(SpatialConfig::grid(10.0, 1).with_hysteresis(2.0));
+        let client_id = 111;
+
+        stepper
+            .server_app
+            .world
+            .spawn((Component1(5.0), SpatialViewer(client_id)));
+        let server_entity = stepper
+            .server_app
+            .world
+            .spawn((
+                Component1(15.0),
+                Replicate {
+                    replication_mode: ReplicationMode::Room,
+                    ..Default::default()
+                },
+            ))
+            .id();
+        stepper.frame_step();
+        stepper.frame_step();
+        // the entity is in a neighbouring cell: it is visible
+        assert!(client_entity(&stepper, server_entity).is_some());
+
+        // the entity moves 2 cells away: it is not visible anymore
+        move_to(&mut stepper, server_entity, 25.0);
+        let client_entity_id = client_entity(&stepper, server_entity);
+        assert!(client_entity_id.map_or(true, |e| stepper
+            .client_app
+            .world
+            .get_entity(e)
+            .is_none()));
+
+        // the entity moves back to the neighbouring cell, but stays within the hysteresis margin:
+        // it is still not visible
+        move_to(&mut stepper, server_entity, 19.0);
+        assert!(
+            client_entity(&stepper, server_entity).map_or(true, |e| stepper
+                .client_app
+                .world
+                .get_entity(e)
+                .is_none())
+        );
+
+        // the entity moves further into the neighbouring cell: it becomes visible again
+        move_to(&mut stepper, server_entity, 15.0);
+        assert!(
+            client_entity(&stepper, server_entity).is_some_and(|e| stepper
+                .client_app
+                .world
+                .get_entity(e)
+                .is_some())
+        );
+    }
+
+    #[test]
+    fn test_radius_visibility() {
+        let mut stepper = setup(SpatialConfig::radius(10.0).with_hysteresis(2.0));
+        let client_id = 111;
+
+        let viewer = stepper
+            .server_app
+            .world
+            .spawn((Component1(0.0), SpatialViewer(client_id)))
+            .id();
+        let server_entity = stepper
+            .server_app
+            .world
+            .spawn((
+                Component1(20.0),
+                Replicate {
+                    replication_mode: ReplicationMode::Room,
+                    ..Default::default()
+                },
+            ))
+            .id();
+        stepper.frame_step();
+        stepper.frame_step();
+        assert!(client_entity(&stepper, server_entity).is_none());
+
+        // entering the radius
+        move_to(&mut stepper, server_entity, 9.0);
+        assert!(
+            client_entity(&stepper, server_entity).is_some_and(|e| stepper
+                .client_app
+                .world
+                .get_entity(e)
+                .is_some())
+        );
+
+        // leaving the radius but staying within the hysteresis margin
+        move_to(&mut stepper, server_entity, 11.0);
+        assert!(
+            client_entity(&stepper, server_entity).is_some_and(|e| stepper
+                .client_app
+                .world
+                .get_entity(e)
+                .is_some())
+        );
+
+        // leaving the hysteresis margin
+        move_to(&mut stepper, server_entity, 13.0);
+        assert!(
+            client_entity(&stepper, server_entity).map_or(true, |e| stepper
+                .client_app
+                .world
+                .get_entity(e)
+                .is_none())
+        );
+
+        // the viewer moves towards the entity, which doesn't move
+        move_to(&mut stepper, viewer, 5.0);
+        assert!(
+            client_entity(&stepper, server_entity).is_some_and(|e| stepper
+                .client_app
+                .world
+                .get_entity(e)
+                .is_some())
+        );
+    }
+
+    #[test]
+    fn test_radius_free_room_on_disconnect() {
+        let mut stepper = setup(SpatialConfig::radius(10.0));
+        let client_id = 111;
+
+        stepper
+            .server_app
+            .world
+            .spawn((Component1(0.0), SpatialViewer(client_id)));
+        stepper.frame_step();
+        stepper.frame_step();
+        let room_id = *stepper
+            .server_app
+            .world
+            .resource::<SpatialManager>()
+            .client_rooms
+            .get(&client_id)
+            .unwrap();
+
+        // the viewer entity is not despawned, but the client disconnects
+        let mut client = SystemState::<ClientMut>::new(&mut stepper.client_app.world);
+        client
+            .get_mut(&mut stepper.client_app.world)
+            .disconnect()
+            .unwrap();
+        for _ in 0..5 {
+            stepper.frame_step();
+        }
+        let manager = stepper.server_app.world.resource::<SpatialManager>();
+        assert!(manager.client_rooms.is_empty());
+        assert!(manager.viewers.is_empty());
+        assert_eq!(manager.free_rooms, vec![room_id]);
+    }
+}
@@ -7,16 +7,27 @@ use tracing::{debug, error, info, trace, trace_span};
 
 use crate::_reexport::ComponentProtocol;
 use crate::client::resource::ClientMut;
-use crate::connection::events::{IterEntityDespawnEvent, IterEntitySpawnEvent};
+use crate::connection::events::{
+    IterEntityDespawnEvent, IterEntitySpawnEvent, IterMessageAckEvent, IterMessageLostEvent,
+    IterRequestTimeoutEvent, IterStreamReceivedEvent, IterTransferProgressEvent,
+};
+use crate::netcode::DisconnectReason;
 use crate::prelude::{Io, TickManager, TimeManager};
 use crate::protocol::message::MessageProtocol;
 use crate::protocol::Protocol;
 use crate::server::config::ServerConfig;
 use crate::server::connection::ConnectionManager;
-use crate::server::events::{ConnectEvent, DisconnectEvent, EntityDespawnEvent, EntitySpawnEvent};
+use crate::server::events::{
+    ConnectEvent, DisconnectEvent, EntityDespawnEvent, EntitySpawnEvent, MessageAckEvent,
+    MessageLostEvent, RequestTimeoutEvent, StreamReceivedEvent, TransferProgressEvent,
+};
 use crate::server::resource::{Server, ServerMut};
 use crate::server::room::RoomManager;
+use crate::server::visibility::VisibilityManager;
 use crate::shared::replication::ReplicationSend;
+use crate::shared::systems::events::{
+    push_registered_component_events, push_registered_message_events,
+};
 
 pub(crate) fn receive<P: Protocol>(world: &mut World) {
     trace!("Receive client packets");
@@ -35,6 +46,10 @@ pub(crate) fn receive<P: Protocol>(world: &mut World) {
                                             // update time manager
                                             time_manager.update(delta);
                                             trace!(time = ?time_manager.current_time(), tick = ?tick_manager.tick(), "receive");
+                                            // update the link conditioners and send the delayed packets
+                                            io.update(delta).unwrap_or_else(|e| {
+                                                error!("Error updating io: {}", e);
+                                            });
 
                                             // update netcode server
                                             let context = netcode
@@ -45,19 +60,43 @@ pub(crate) fn receive<P: Protocol>(world: &mut World) {
                                             connection_manager
                                                 .update(time_manager.as_ref(), tick_manager.as_ref());
 
+                                            // handle disconnections
+                                            // (before connections, because a client whose suspended session expired can connect again
+                                            // during the same update)
+                                            // (a remote client that was rejected because it used the id of the local client must not remove the local client)
+                                            let disconnections: Vec<_> = context.disconnections.into_iter().filter(|(client_id, _, _)| !connection_manager.is_local_client(*client_id)).collect();
+                                            let local_disconnections = std::mem::take(&mut connection_manager.local_disconnections);
+                                            for (client_id, reason, payload) in disconnections.into_iter().chain(local_disconnections) {
+                                                connection_manager.remove(client_id, reason, payload);
+                                                room_manager.client_disconnect(client_id);
+                                                world.resource_mut::<VisibilityManager>().client_disconnect(client_id);
+                                            };
+
+                                            // handle session suspensions/resumptions: the connection state is kept
+                                            for client_id in context.suspensions {
+                                                connection_manager.suspend(client_id);
+                                            }
+                                            for client_id in context.resumptions {
+                                                connection_manager.resume(client_id);
+                                            }
+
                                             // handle connection
                                             for client_id in context.connections.iter().copied() {
                                                 // let client_addr = self.netcode.client_addr(client_id).unwrap();
                                                 // info!("New connection from {} (id: {})", client_addr, client_id);
-                                                connection_manager.add(client_id, &world.resource::<ServerConfig>().ping);
+                                                if connection_manager.is_local_client(client_id) {
+                                                    error!(?client_id, "Disconnecting remote client that uses the id of the local client");
+                                                    netcode
+                                                        .disconnect(client_id, DisconnectReason::Kicked, &[], io.deref_mut())
+                                                        .unwrap_or_else(|e| {
+                                                            error!("Error disconnecting client: {}", e);
+                                                        });
+                                                    continue;
+                                                }
+                                                let config = world.resource::<ServerConfig>();
+                                                connection_manager.add(client_id, &config.ping, &config.priority);
                                             }
 
-                                            // handle disconnections
-                                            for client_id in context.disconnections.iter().copied() {
-                                                connection_manager.remove(client_id);
-                                                room_manager.client_disconnect(client_id);
-                                            };
-
                                             // RECV_PACKETS: buffer packets into message managers
                                             while let Some((mut reader, client_id)) = netcode.recv() {
                                                 // TODO: use connection to apply on BOTH message manager and replication manager
@@ -68,6 +107,27 @@ pub(crate) fn receive<P: Protocol>(world: &mut World) {
                                                     .expect("could not recv packet");
                                             }
 
+                                            // disconnect the clients that keep sending fragments that violate the limits of the channels
+                                            for client_id in connection_manager.fragment_limits_violators() {
+                                                error!(?client_id, "Disconnecting client that violated the fragment limits");
+                                                netcode
+                                                    .disconnect(client_id, DisconnectReason::ProtocolViolation, &[], io.deref_mut())
+                                                    .unwrap_or_else(|e| {
+                                                        error!("Error disconnecting client: {}", e);
+                                                    });
+                                            }
+
+                                            // end the suspended sessions that buffered too many reliable messages for their client
+                                            let max_unacked_messages = world.resource::<ServerConfig>().netcode.resumption_max_unacked_messages;
+                                            for client_id in connection_manager.suspended_buffer_violators(max_unacked_messages) {
+                                                info!(?client_id, "Ending the suspended session of a client that has too many unacked messages");
+                                                netcode
+                                                    .disconnect(client_id, DisconnectReason::TimedOut, &[], io.deref_mut())
+                                                    .unwrap_or_else(|e| {
+                                                        error!("Error disconnecting client: {}", e);
+                                                    });
+                                            }
+
                                             // RECEIVE: read messages and parse them into events
                                             connection_manager
                                                 .receive(world, time_manager.as_ref(), tick_manager.as_ref())
@@ -75,6 +135,13 @@ pub(crate) fn receive<P: Protocol>(world: &mut World) {
                                                     error!("Error during receive: {}", e);
                                                 });
 
+                                            // the messages sent to the local client are delivered directly as client events
+                                            if !connection_manager.local_client_events.is_empty() {
+                                                push_registered_message_events(world, &mut connection_manager.local_client_events);
+                                                P::Message::push_message_events(world, &mut connection_manager.local_client_events);
+                                                connection_manager.local_client_events.clear();
+                                            }
+
                                             // EVENTS: Write the received events into bevy events
                                             if !connection_manager.events.is_empty() {
                                                 // TODO: write these as systems? might be easier to also add the events to the app
@@ -97,15 +164,62 @@ pub(crate) fn receive<P: Protocol>(world: &mut World) {
                                                 if connection_manager.events.has_disconnections() {
                                                     let mut connect_event_writer =
                                                         world.get_resource_mut::<Events<DisconnectEvent>>().unwrap();
-                                                    for client_id in connection_manager.events.iter_disconnections() {
-                                                        debug!("Client disconnected event: {}", client_id);
-                                                        connect_event_writer.send(DisconnectEvent::new(client_id));
+                                                    for (client_id, reason, payload) in connection_manager.events.iter_disconnections() {
+                                                        debug!("Client disconnected event: {} ({:?})", client_id, reason);
+                                                        connect_event_writer.send(DisconnectEvent::with_reason(client_id, reason, payload));
                                                     }
                                                 }
 
                                                 // Message Events
+                                                push_registered_message_events(world, &mut connection_manager.events);
                                                 P::Message::push_message_events(world, &mut connection_manager.events);
 
+                                                // Message delivery Events
+                                                if connection_manager.events.has_message_acks() {
+                                                    let mut message_ack_event_writer = world
+                                                        .get_resource_mut::<Events<MessageAckEvent>>()
+                                                        .unwrap();
+                                                    for (handle, client_id) in connection_manager.events.into_iter_message_acks() {
+                                                        message_ack_event_writer.send(MessageAckEvent::new(handle, client_id));
+                                                    }
+                                                }
+                                                if connection_manager.events.has_message_losses() {
+                                                    let mut message_lost_event_writer = world
+                                                        .get_resource_mut::<Events<MessageLostEvent>>()
+                                                        .unwrap();
+                                                    for (handle, client_id) in connection_manager.events.into_iter_message_losses() {
+                                                        message_lost_event_writer.send(MessageLostEvent::new(handle, client_id));
+                                                    }
+                                                }
+
+                                                // Request timeout Events
+                                                if connection_manager.events.has_request_timeouts() {
+                                                    let mut request_timeout_event_writer = world
+                                                        .get_resource_mut::<Events<RequestTimeoutEvent>>()
+                                                        .unwrap();
+                                                    for (request_id, client_id) in connection_manager.events.into_iter_request_timeouts() {
+                                                        request_timeout_event_writer.send(RequestTimeoutEvent::new(request_id, client_id));
+                                                    }
+                                                }
+
+                                                // Stream Events
+                                                if connection_manager.events.has_transfer_progress() {
+                                                    let mut transfer_progress_event_writer = world
+                                                        .get_resource_mut::<Events<TransferProgressEvent>>()
+                                                        .unwrap();
+                                                    for (progress, client_id) in connection_manager.events.into_iter_transfer_progress() {
+                                                        transfer_progress_event_writer.send(TransferProgressEvent::new(progress, client_id));
+                                                    }
+                                                }
+                                                if connection_manager.events.has_streams_received() {
+                                                    let mut stream_received_event_writer = world
+                                                        .get_resource_mut::<Events<StreamReceivedEvent>>()
+                                                        .unwrap();
+                                                    for (transfer_id, bytes, client_id) in connection_manager.events.into_iter_streams_received() {
+                                                        stream_received_event_writer.send(StreamReceivedEvent::new(transfer_id, bytes, client_id));
+                                                    }
+                                                }
+
                                                 // EntitySpawn Events
                                                 if connection_manager.events.has_entity_spawn() {
                                                     let mut entity_spawn_event_writer = world
@@ -126,6 +240,7 @@ pub(crate) fn receive<P: Protocol>(world: &mut World) {
                                                 }
 
                                                 // Update component events (updates, inserts, removes)
+                                                push_registered_component_events(world, &mut connection_manager.events);
                                                 P::Components::push_component_events(world, &mut connection_manager.events);
                                             }
                                         });
@@ -159,10 +274,18 @@ pub(crate) fn send<P: Protocol>(
     connection_manager
         .connections
         .iter_mut()
+        // local clients don't receive any packets
+        .filter(|(_, connection)| !connection.is_local)
         .try_for_each(|(client_id, connection)| {
             let client_span =
                 trace_span!("send_packets_to_client", client_id = ?client_id).entered();
-            for packet_byte in connection.send_packets(&time_manager, &tick_manager)? {
+            let packets = connection.send_packets(&time_manager, &tick_manager)?;
+            // the client of a suspended connection cannot be reached: the packets are dropped as if they
+            // were lost, so that reliable messages are re-sent once the session is resumed
+            if connection.is_suspended {
+                return Ok(());
+            }
+            for packet_byte in packets {
                 netserver.send(packet_byte.as_slice(), *client_id, io.deref_mut())?;
             }
             Ok(())
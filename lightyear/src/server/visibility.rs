@@ -0,0 +1,275 @@
+//! Per-client visibility control
+//!
+//! Entities with [`ReplicationMode::Visibility`] are only replicated to the clients that were explicitly given
+//! visibility of the entity via the [`VisibilityManager`]. This gives the same spawn/despawn semantics as rooms,
+//! but with arbitrary control over each (client, entity) pair; which is useful for fog of war, stealth, line-of-sight, etc.
+use bevy::app::App;
+use bevy::prelude::{Entity, IntoSystemConfigs, Plugin, PostUpdate, Query, ResMut, Resource};
+use bevy::utils::{HashMap, HashSet};
+
+use crate::netcode::ClientId;
+use crate::protocol::Protocol;
+use crate::server::room::{ClientVisibility, RoomSystemSets};
+use crate::shared::replication::components::{Replicate, ReplicationMode};
+
+/// Resource that lets you control which clients can see which entities.
+///
+/// Visibility changes are buffered and applied every send_interval, right before replication. That means that
+/// if visibility is gained then lost within the same send_interval, nothing is sent to the client.
+#[derive(Resource, Debug, Default)]
+pub struct VisibilityManager {
+    /// Visibility changes since the last send_interval (true if visibility was gained)
+    events: HashMap<Entity, HashMap<ClientId, bool>>,
+    /// Clients that disconnected since the last send_interval
+    disconnected: HashSet<ClientId>,
+}
+
+impl VisibilityManager {
+    /// The client will start receiving the entity
+    pub fn gain_visibility(&mut self, client_id: ClientId, entity: Entity) {
+        self.events
+            .entry(entity)
+            .or_default()
+            .insert(client_id, true);
+    }
+
+    /// The entity will get despawned on the client, and the client will stop receiving updates for it
+    pub fn lose_visibility(&mut self, client_id: ClientId, entity: Entity) {
+        self.events
+            .entry(entity)
+            .or_default()
+            .insert(client_id, false);
+    }
+
+    /// Forget about a client that disconnected
+    pub(crate) fn client_disconnect(&mut self, client_id: ClientId) {
+        self.events.values_mut().for_each(|clients| {
+            clients.remove(&client_id);
+        });
+        self.disconnected.insert(client_id);
+    }
+}
+
+pub struct VisibilityPlugin<P: Protocol> {
+    _marker: std::marker::PhantomData<P>,
+}
+
+impl<P: Protocol> Default for VisibilityPlugin<P> {
+    fn default() -> Self {
+        Self {
+            _marker: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<P: Protocol> Plugin for VisibilityPlugin<P> {
+    fn build(&self, app: &mut App) {
+        // RESOURCES
+        app.init_resource::<VisibilityManager>();
+        // SYSTEMS
+        // the sets are configured by the RoomPlugin
+        app.add_systems(
+            PostUpdate,
+            update_visibility_cache::<P>.in_set(RoomSystemSets::UpdateReplicationCaches),
+        );
+    }
+}
+
+/// Apply the visibility changes to the entities' replication-client-list
+fn update_visibility_cache<P: Protocol>(
+    mut manager: ResMut<VisibilityManager>,
+    mut query: Query<&mut Replicate<P>>,
+) {
+    let manager = manager.as_mut();
+    // the entities don't need to be despawned on clients that disconnected
+    if !manager.disconnected.is_empty() {
+        for mut replicate in query.iter_mut() {
+            if replicate.replication_mode == ReplicationMode::Visibility {
+                replicate
+                    .replication_clients_cache
+                    .retain(|client_id, _| !manager.disconnected.contains(client_id));
+            }
+        }
+        manager.disconnected.clear();
+    }
+    for (entity, clients) in manager.events.drain() {
+        let Some(mut replicate) = query
+            .get_mut(entity)
+            .ok()
+            .filter(|r| r.replication_mode == ReplicationMode::Visibility)
+        else {
+            continue;
+        };
+        for (client_id, visible) in clients {
+            if visible {
+                replicate
+                    .replication_clients_cache
+                    .entry(client_id)
+                    .or_insert(ClientVisibility::Gained);
+            } else if let Some(visibility) = replicate.replication_clients_cache.get_mut(&client_id)
+            {
+                *visibility = ClientVisibility::Lost;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::prelude::client::*;
+    use crate::prelude::*;
+    use crate::tests::protocol::Replicate;
+    use crate::tests::protocol::*;
+    use crate::tests::stepper::{BevyStepper, Step};
+
+    use super::*;
+
+    fn setup() -> BevyStepper {
+        let frame_duration = Duration::from_millis(10);
+        let tick_duration = Duration::from_millis(10);
+        let shared_config = SharedConfig {
+            enable_replication: true,
+            tick: TickConfig::new(tick_duration),
+            ..Default::default()
+        };
+        let link_conditioner = LinkConditionerConfig {
+            incoming_latency: Duration::from_millis(0),
+            incoming_jitter: Duration::from_millis(0),
+            incoming_loss: 0.0,
+            ..Default::default()
+        };
+        let sync_config = SyncConfig::default().speedup_factor(1.0);
+        let prediction_config = PredictionConfig::default().disable(false);
+        let interpolation_config = InterpolationConfig::default();
+        let mut stepper = BevyStepper::new(
+            shared_config,
+            sync_config,
+            prediction_config,
+            interpolation_config,
+            link_conditioner,
+            frame_duration,
+        );
+        stepper.init();
+        stepper
+    }
+
+    #[test]
+    fn test_gain_lose_visibility() {
+        let mut stepper = setup();
+        let client_id = 111;
+
+        let server_entity = stepper
+            .server_app
+            .world
+            .spawn((
+                Component1(1.0),
+                Replicate {
+                    replication_mode: ReplicationMode::Visibility,
+                    ..Default::default()
+                },
+            ))
+            .id();
+        stepper.frame_step();
+        stepper.frame_step();
+        // the entity is not visible by default
+        assert!(stepper
+            .client_app
+            .world
+            .resource::<ClientConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .is_none());
+
+        // gain visibility
+        stepper
+            .server_app
+            .world
+            .resource_mut::<VisibilityManager>()
+            .gain_visibility(client_id, server_entity);
+        stepper.frame_step();
+        assert_eq!(
+            stepper
+                .server_app
+                .world
+                .get::<Replicate>(server_entity)
+                .unwrap()
+                .replication_clients_cache,
+            HashMap::from([(client_id, ClientVisibility::Maintained)])
+        );
+        stepper.frame_step();
+        let client_entity = *stepper
+            .client_app
+            .world
+            .resource::<ClientConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .unwrap();
+        assert_eq!(
+            stepper
+                .client_app
+                .world
+                .get::<Component1>(client_entity)
+                .unwrap(),
+            &Component1(1.0)
+        );
+
+        // lose visibility
+        stepper
+            .server_app
+            .world
+            .resource_mut::<VisibilityManager>()
+            .lose_visibility(client_id, server_entity);
+        stepper.frame_step();
+        assert!(stepper
+            .server_app
+            .world
+            .get::<Replicate>(server_entity)
+            .unwrap()
+            .replication_clients_cache
+            .is_empty());
+        stepper.frame_step();
+        assert!(stepper.client_app.world.get_entity(client_entity).is_none());
+    }
+
+    #[test]
+    fn test_visibility_changes_cancel_out() {
+        let mut stepper = setup();
+        let client_id = 111;
+
+        let server_entity = stepper
+            .server_app
+            .world
+            .spawn(Replicate {
+                replication_mode: ReplicationMode::Visibility,
+                ..Default::default()
+            })
+            .id();
+        stepper.frame_step();
+
+        // gaining then losing visibility within the same send_interval does nothing
+        let mut manager = stepper.server_app.world.resource_mut::<VisibilityManager>();
+        manager.gain_visibility(client_id, server_entity);
+        manager.lose_visibility(client_id, server_entity);
+        stepper.frame_step();
+        stepper.frame_step();
+        assert!(stepper
+            .server_app
+            .world
+            .get::<Replicate>(server_entity)
+            .unwrap()
+            .replication_clients_cache
+            .is_empty());
+        assert!(stepper
+            .client_app
+            .world
+            .resource::<ClientConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .is_none());
+    }
+}
@@ -2,10 +2,15 @@
 use std::marker::PhantomData;
 
 use bevy::prelude::{Component, Entity, Event};
+use bytes::Bytes;
 
 #[cfg(feature = "leafwing")]
 use crate::inputs::leafwing::InputMessage;
-use crate::packet::message::Message;
+use crate::inputs::InterpolationInfo;
+use crate::netcode::DisconnectReason;
+use crate::packet::message::{Message, MessageHandle};
+use crate::shared::rpc::RequestId;
+use crate::shared::stream::{TransferId, TransferProgress};
 
 #[derive(Event)]
 pub struct ConnectEvent<Ctx = ()>(Ctx);
@@ -20,15 +25,27 @@ impl<Ctx> ConnectEvent<Ctx> {
 }
 
 #[derive(Event)]
-pub struct DisconnectEvent<Ctx = ()>(Ctx);
+pub struct DisconnectEvent<Ctx = ()>(Ctx, DisconnectReason, Vec<u8>);
 
 impl<Ctx> DisconnectEvent<Ctx> {
     pub fn new(context: Ctx) -> Self {
-        Self(context)
+        Self::with_reason(context, DisconnectReason::Unspecified, vec![])
+    }
+    /// Create an event for a disconnection with the given reason and application-defined payload
+    pub fn with_reason(context: Ctx, reason: DisconnectReason, payload: Vec<u8>) -> Self {
+        Self(context, reason, payload)
     }
     pub fn context(&self) -> &Ctx {
         &self.0
     }
+    /// Why the connection was closed
+    pub fn reason(&self) -> DisconnectReason {
+        self.1
+    }
+    /// Application-defined data that was sent along with the reason
+    pub fn payload(&self) -> &[u8] {
+        &self.2
+    }
 }
 
 #[cfg(feature = "leafwing")]
@@ -71,16 +88,148 @@ impl<M: Message, Ctx> MessageEvent<M, Ctx> {
     }
 }
 
+#[derive(Event)]
+/// Event emitted when the remote acknowledged a message that we sent on a channel that tracks acks
+/// (for reliable channels, this means that the message was delivered)
+pub struct MessageAckEvent<Ctx = ()> {
+    handle: MessageHandle,
+    context: Ctx,
+}
+
+impl<Ctx> MessageAckEvent<Ctx> {
+    pub fn new(handle: MessageHandle, context: Ctx) -> Self {
+        Self { handle, context }
+    }
+
+    /// The handle returned when the message was sent
+    pub fn handle(&self) -> MessageHandle {
+        self.handle
+    }
+
+    pub fn context(&self) -> &Ctx {
+        &self.context
+    }
+}
+
+#[derive(Event)]
+/// Event emitted when a message that we sent on an `UnorderedUnreliableWithAcks` channel was lost
+/// (messages sent on reliable channels are resent until they are delivered)
+pub struct MessageLostEvent<Ctx = ()> {
+    handle: MessageHandle,
+    context: Ctx,
+}
+
+impl<Ctx> MessageLostEvent<Ctx> {
+    pub fn new(handle: MessageHandle, context: Ctx) -> Self {
+        Self { handle, context }
+    }
+
+    /// The handle returned when the message was sent
+    pub fn handle(&self) -> MessageHandle {
+        self.handle
+    }
+
+    pub fn context(&self) -> &Ctx {
+        &self.context
+    }
+}
+
+#[derive(Event)]
+/// Event emitted when a request that we sent did not receive a response within [`Request::TIMEOUT`](crate::shared::rpc::Request::TIMEOUT)
+pub struct RequestTimeoutEvent<Ctx = ()> {
+    request_id: RequestId,
+    context: Ctx,
+}
+
+impl<Ctx> RequestTimeoutEvent<Ctx> {
+    pub fn new(request_id: RequestId, context: Ctx) -> Self {
+        Self {
+            request_id,
+            context,
+        }
+    }
+
+    /// The id returned when the request was sent
+    pub fn request_id(&self) -> RequestId {
+        self.request_id
+    }
+
+    pub fn context(&self) -> &Ctx {
+        &self.context
+    }
+}
+
+#[derive(Event)]
+/// Event emitted when a stream that we send or receive progressed, completed or was cancelled
+pub struct TransferProgressEvent<Ctx = ()> {
+    progress: TransferProgress,
+    context: Ctx,
+}
+
+impl<Ctx> TransferProgressEvent<Ctx> {
+    pub fn new(progress: TransferProgress, context: Ctx) -> Self {
+        Self { progress, context }
+    }
+
+    pub fn progress(&self) -> &TransferProgress {
+        &self.progress
+    }
+
+    pub fn context(&self) -> &Ctx {
+        &self.context
+    }
+}
+
+#[derive(Event)]
+/// Event emitted when we received the full payload of a stream
+pub struct StreamReceivedEvent<Ctx = ()> {
+    transfer_id: TransferId,
+    bytes: Bytes,
+    context: Ctx,
+}
+
+impl<Ctx> StreamReceivedEvent<Ctx> {
+    pub fn new(transfer_id: TransferId, bytes: Bytes, context: Ctx) -> Self {
+        Self {
+            transfer_id,
+            bytes,
+            context,
+        }
+    }
+
+    pub fn transfer_id(&self) -> TransferId {
+        self.transfer_id
+    }
+
+    pub fn bytes(&self) -> &Bytes {
+        &self.bytes
+    }
+
+    pub fn context(&self) -> &Ctx {
+        &self.context
+    }
+}
+
 #[derive(Event)]
 /// Event emitted on server every time we receive an event
 pub struct InputEvent<I: crate::inputs::native::UserAction, Ctx = ()> {
     input: Option<I>,
     context: Ctx,
+    interpolation: Option<InterpolationInfo>,
 }
 
 impl<I: crate::inputs::native::UserAction, Ctx> InputEvent<I, Ctx> {
     pub fn new(input: Option<I>, context: Ctx) -> Self {
-        Self { input, context }
+        Self {
+            input,
+            context,
+            interpolation: None,
+        }
+    }
+
+    pub(crate) fn with_interpolation(mut self, interpolation: Option<InterpolationInfo>) -> Self {
+        self.interpolation = interpolation;
+        self
     }
 
     pub fn input(&self) -> &Option<I> {
@@ -90,6 +239,12 @@ impl<I: crate::inputs::native::UserAction, Ctx> InputEvent<I, Ctx> {
     pub fn context(&self) -> &Ctx {
         &self.context
     }
+
+    /// On the server: state of the client's interpolation timeline when it generated this input
+    /// (only available if the client sends its interpolation state with its inputs)
+    pub fn interpolation(&self) -> Option<InterpolationInfo> {
+        self.interpolation
+    }
 }
 
 #[derive(Event)]
@@ -12,8 +12,12 @@ pub mod plugin;
 
 pub mod replication;
 
+pub mod rpc;
+
 pub mod sets;
 
+pub mod stream;
+
 // TODO: refactor this out
 pub mod systems;
 
@@ -41,6 +41,10 @@ pub struct Replicate<P: Protocol> {
     // TODO: currently, if the host removes Replicate, then the entity is not removed in the remote
     //  it just keeps living but doesn't receive any updates. Should we make this configurable?
     pub replication_group: ReplicationGroup,
+    /// Priority of the entity's updates when the bandwidth budget of a connection is exhausted.
+    /// Entities that could not be sent accumulate their priority, so that they are sent eventually.
+    /// (all the entities of a [`ReplicationGroup`] share the same priority)
+    pub priority: f32,
 
     /// Lets you override the replication modalities for a specific component
     pub per_component_metadata: HashMap<P::ComponentKinds, PerComponentReplicationMetadata>,
@@ -85,7 +89,11 @@ impl<P: Protocol> Replicate<P> {
     where
         P::ComponentKinds: FromType<C>,
     {
-        let kind = <P::ComponentKinds as FromType<C>>::from_type();
+        self.is_kind_disabled(<P::ComponentKinds as FromType<C>>::from_type())
+    }
+
+    /// Returns true if we don't want to replicate the component of the given kind
+    pub fn is_kind_disabled(&self, kind: P::ComponentKinds) -> bool {
         self.per_component_metadata
             .get(&kind)
             .is_some_and(|metadata| metadata.disabled)
@@ -97,7 +105,11 @@ impl<P: Protocol> Replicate<P> {
     where
         P::ComponentKinds: FromType<C>,
     {
-        let kind = <P::ComponentKinds as FromType<C>>::from_type();
+        self.is_kind_replicate_once(<P::ComponentKinds as FromType<C>>::from_type())
+    }
+
+    /// If true, the component of the given kind will be replicated only once, when the entity is spawned.
+    pub fn is_kind_replicate_once(&self, kind: P::ComponentKinds) -> bool {
         self.per_component_metadata
             .get(&kind)
             .is_some_and(|metadata| metadata.replicate_once)
@@ -106,11 +118,19 @@ impl<P: Protocol> Replicate<P> {
     /// Replication target for this specific component
     /// This will be the intersection of the provided `entity_target`, and the `target` of the component
     /// if it exists
-    pub fn target<C>(&self, mut entity_target: NetworkTarget) -> NetworkTarget
+    pub fn target<C>(&self, entity_target: NetworkTarget) -> NetworkTarget
     where
         P::ComponentKinds: FromType<C>,
     {
-        let kind = <P::ComponentKinds as FromType<C>>::from_type();
+        self.kind_target(<P::ComponentKinds as FromType<C>>::from_type(), entity_target)
+    }
+
+    /// Replication target for the component of the given kind
+    pub fn kind_target(
+        &self,
+        kind: P::ComponentKinds,
+        mut entity_target: NetworkTarget,
+    ) -> NetworkTarget {
         match self.per_component_metadata.get(&kind) {
             None => entity_target,
             Some(metadata) => {
@@ -212,6 +232,9 @@ pub struct ReplicationGroupId(pub u64);
 pub enum ReplicationMode {
     /// We will replicate this entity only to clients that are in the same room as the entity
     Room,
+    /// We will replicate this entity only to clients that were explicitly given visibility of the entity
+    /// via the [`VisibilityManager`](crate::server::visibility::VisibilityManager)
+    Visibility,
     /// We will replicate this entity to clients using only the [`NetworkTarget`], without caring about rooms
     #[default]
     NetworkTarget,
@@ -227,6 +250,7 @@ impl<P: Protocol> Default for Replicate<P> {
             replication_clients_cache: HashMap::new(),
             replication_mode: ReplicationMode::default(),
             replication_group: Default::default(),
+            priority: 1.0,
             per_component_metadata: HashMap::default(),
         };
         // those metadata components should only be replicated once
@@ -0,0 +1,178 @@
+//! Delta-compression of component updates
+//!
+//! Components marked with `#[replication(delta)]` in the `ComponentProtocol` are not sent in full
+//! every time they change. Instead, the sender keeps track of the last value that was acked by the remote
+//! and only sends the [`Diffable::Delta`] between that value and the new value.
+//! The receiver keeps a short history of the values it received, so that it can rebuild the full value
+//! from the delta. The sender sends the full value instead of a delta if the remote could have
+//! dropped the base value from its history.
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::hash::Hash;
+
+use bevy::prelude::Entity;
+use bevy::utils::{EntityHashMap, HashMap};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::_reexport::ComponentProtocol;
+use crate::protocol::Protocol;
+use crate::shared::tick_manager::Tick;
+
+/// Maximum number of past values that we keep for each delta-compressed component on the receiver side
+pub(crate) const MAX_DELTA_HISTORY_LEN: usize = 64;
+
+/// Trait for components that can be replicated using delta-compression.
+///
+/// You can derive it for structs with `#[derive(Diffable)]`: the generated delta will contain only
+/// the fields that changed.
+pub trait Diffable: Clone {
+    /// Representation of the difference between two values
+    type Delta: Serialize + DeserializeOwned + Clone + PartialEq + Debug + Send + Sync + 'static;
+
+    /// Compute the delta that needs to be applied to `self` to get `new`
+    fn diff(&self, new: &Self) -> Self::Delta;
+
+    /// Apply a delta (computed with [`Diffable::diff`]) on top of `self`
+    fn apply_diff(&mut self, delta: &Self::Delta);
+}
+
+/// Delta of any of the delta-compressed components of the protocol
+pub(crate) type ComponentDeltaOf<P> = <<P as Protocol>::Components as ComponentProtocol>::Delta;
+
+/// Delta of a single component, computed against the value sent at `base_tick`
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct ComponentDelta<D> {
+    /// Tick of the (acked) update message that contained the base value
+    pub(crate) base_tick: Tick,
+    pub(crate) delta: D,
+}
+
+/// History of the values received for each delta-compressed component, so that we can apply incoming deltas
+#[derive(Debug)]
+pub(crate) struct DeltaHistory<K, C> {
+    values: EntityHashMap<Entity, HashMap<K, BTreeMap<Tick, C>>>,
+}
+
+impl<K, C> Default for DeltaHistory<K, C> {
+    fn default() -> Self {
+        Self {
+            values: EntityHashMap::default(),
+        }
+    }
+}
+
+impl<K: Copy + Eq + Hash, C: Clone> DeltaHistory<K, C> {
+    /// Record the full value of a component received at `tick`
+    pub(crate) fn record(&mut self, entity: Entity, kind: K, tick: Tick, value: C) {
+        let history = self
+            .values
+            .entry(entity)
+            .or_default()
+            .entry(kind)
+            .or_default();
+        history.insert(tick, value);
+        while history.len() > MAX_DELTA_HISTORY_LEN {
+            if let Some(first) = history.keys().next().copied() {
+                history.remove(&first);
+            }
+        }
+    }
+
+    /// Get the value that was received at `base_tick`
+    pub(crate) fn base(&self, entity: Entity, kind: K, base_tick: Tick) -> Option<&C> {
+        self.values.get(&entity)?.get(&kind)?.get(&base_tick)
+    }
+
+    /// Remove the values of an entity that were received before (or at) `tick`
+    pub(crate) fn remove_entity(&mut self, entity: Entity, tick: Tick) {
+        if let Some(components) = self.values.get_mut(&entity) {
+            components.values_mut().for_each(|history| {
+                *history = history.split_off(&(tick + 1));
+            });
+            components.retain(|_, history| !history.is_empty());
+            if components.is_empty() {
+                self.values.remove(&entity);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Stats {
+        health: u32,
+        mana: u32,
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+    struct StatsDelta {
+        health: Option<u32>,
+        mana: Option<u32>,
+    }
+
+    impl Diffable for Stats {
+        type Delta = StatsDelta;
+
+        fn diff(&self, new: &Self) -> Self::Delta {
+            StatsDelta {
+                health: (self.health != new.health).then_some(new.health),
+                mana: (self.mana != new.mana).then_some(new.mana),
+            }
+        }
+
+        fn apply_diff(&mut self, delta: &Self::Delta) {
+            if let Some(health) = delta.health {
+                self.health = health;
+            }
+            if let Some(mana) = delta.mana {
+                self.mana = mana;
+            }
+        }
+    }
+
+    #[test]
+    fn test_diff_roundtrip() {
+        let base = Stats {
+            health: 10,
+            mana: 5,
+        };
+        let new = Stats {
+            health: 10,
+            mana: 2,
+        };
+        let delta = base.diff(&new);
+        assert_eq!(
+            delta,
+            StatsDelta {
+                health: None,
+                mana: Some(2),
+            }
+        );
+        let mut value = base.clone();
+        value.apply_diff(&delta);
+        assert_eq!(value, new);
+    }
+
+    #[test]
+    fn test_delta_history() {
+        let mut history = DeltaHistory::<u8, u32>::default();
+        let entity = Entity::from_raw(0);
+        history.record(entity, 0, Tick(1), 1);
+        history.record(entity, 0, Tick(3), 3);
+        history.record(entity, 0, Tick(5), 5);
+
+        assert_eq!(history.base(entity, 0, Tick(3)), Some(&3));
+        assert_eq!(history.base(entity, 0, Tick(4)), None);
+
+        // only the values received after the despawn are kept
+        history.remove_entity(entity, Tick(3));
+        assert_eq!(history.base(entity, 0, Tick(3)), None);
+        assert_eq!(history.base(entity, 0, Tick(5)), Some(&5));
+        history.remove_entity(entity, Tick(5));
+        assert!(history.values.is_empty());
+    }
+}
@@ -1,23 +1,34 @@
 //! Module to handle replicating entities and components from server to client
 use std::hash::Hash;
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use bevy::ecs::component::Tick as BevyTick;
 use bevy::prelude::{Component, Entity, Resource};
 use bevy::reflect::Map;
 use bevy::utils::{EntityHashMap, HashSet};
-use serde::{Deserialize, Serialize};
+use bitcode::encoding::Gamma;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 
-use crate::_reexport::{ComponentProtocol, ComponentProtocolKind, ShouldBeInterpolated};
+use crate::_reexport::{
+    BitSerializable, ComponentProtocol, ComponentProtocolKind, ReadBuffer, ReadWordBuffer,
+    ShouldBeInterpolated, WriteBuffer, WriteWordBuffer,
+};
 use crate::channel::builder::Channel;
 use crate::netcode::ClientId;
 use crate::packet::message::MessageId;
 use crate::prelude::{EntityMapper, MapEntities, NetworkTarget, ShouldBePredicted, Tick};
+use crate::protocol::registry::ProtocolRegistry;
 use crate::protocol::Protocol;
+use crate::serialize::mode::{
+    decode_seq_with, decode_variant_index, encode_seq, encode_variant_index,
+};
 use crate::shared::replication::components::{Replicate, ReplicationGroupId};
+use crate::shared::replication::delta::ComponentDelta;
 
 pub mod components;
 
+pub mod delta;
 pub mod entity_map;
 pub(crate) mod receive;
 pub(crate) mod send;
@@ -41,7 +52,7 @@ pub mod systems;
 //     EntityUpdate(Entity, Vec<C>),
 // }
 
-#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug)]
 pub struct EntityActions<C, K: Hash + Eq> {
     pub(crate) spawn: bool,
     pub(crate) despawn: bool,
@@ -66,34 +77,169 @@ impl<C, K: Hash + Eq> Default for EntityActions<C, K> {
 
 // TODO: 99% of the time the ReplicationGroup is the same as the Entity in the hashmap, and there's only 1 entity
 //  have an optimization for that
-#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug)]
 pub struct EntityActionMessage<C, K: Hash + Eq> {
     sequence_id: MessageId,
     // we use vec but the order of entities should not matter
     pub(crate) actions: Vec<(Entity, EntityActions<C, K>)>,
 }
 
-#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
-pub struct EntityUpdatesMessage<C> {
+#[derive(Clone, PartialEq, Debug)]
+pub struct EntityUpdatesMessage<C, D> {
     /// The last tick for which we sent an EntityActionsMessage for this group
     /// We set this to None after a certain amount of time without any new Actions, to signify on the receiver side
     /// that there is no ordering constraint with respect to Actions for this group (i.e. the Update can be applied immediately)
     last_action_tick: Option<Tick>,
     pub(crate) updates: Vec<(Entity, Vec<C>)>,
+    /// Updates for delta-compressed components, that only contain the difference with a previously acked value.
+    /// They get converted back to full updates by the receiver as soon as the message is received.
+    pub(crate) deltas: Vec<(Entity, Vec<ComponentDelta<D>>)>,
 }
 
-#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
-pub enum ReplicationMessageData<C, K: Hash + Eq> {
+#[derive(Clone, PartialEq, Debug)]
+pub enum ReplicationMessageData<C, K: Hash + Eq, D> {
     /// All the entity actions (Spawn/despawn/inserts/removals) for a given group
     Actions(EntityActionMessage<C, K>),
     /// All the entity updates for a given group
-    Updates(EntityUpdatesMessage<C>),
+    Updates(EntityUpdatesMessage<C, D>),
 }
 
-#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
-pub struct ReplicationMessage<C, K: Hash + Eq> {
+#[derive(Clone, PartialEq, Debug)]
+pub struct ReplicationMessage<C, K: Hash + Eq, D> {
     pub(crate) group_id: ReplicationGroupId,
-    pub(crate) data: ReplicationMessageData<C, K>,
+    pub(crate) data: ReplicationMessageData<C, K, D>,
+}
+
+// The replication messages are written with `BitSerializable` instead of serde, so that the components can use
+// their own serialization mode. The bits are the same as the ones of the serde encoding.
+impl<C: BitSerializable, K: Serialize + DeserializeOwned + Clone + Hash + Eq> BitSerializable
+    for EntityActions<C, K>
+{
+    fn encode(&self, writer: &mut WriteWordBuffer) -> Result<()> {
+        writer.serialize(&self.spawn)?;
+        writer.serialize(&self.despawn)?;
+        encode_seq(&self.insert, writer)?;
+        writer.serialize(&self.remove)?;
+        encode_seq(&self.updates, writer)
+    }
+
+    fn decode(reader: &mut ReadWordBuffer) -> Result<Self> {
+        Self::decode_with(reader, &ProtocolRegistry::default())
+    }
+
+    fn decode_with(reader: &mut ReadWordBuffer, registry: &ProtocolRegistry) -> Result<Self> {
+        Ok(Self {
+            spawn: reader.deserialize()?,
+            despawn: reader.deserialize()?,
+            insert: decode_seq_with(reader, registry)?,
+            remove: reader.deserialize()?,
+            updates: decode_seq_with(reader, registry)?,
+        })
+    }
+}
+
+impl<C: BitSerializable, K: Serialize + DeserializeOwned + Clone + Hash + Eq> BitSerializable
+    for EntityActionMessage<C, K>
+{
+    fn encode(&self, writer: &mut WriteWordBuffer) -> Result<()> {
+        writer.serialize(&self.sequence_id)?;
+        writer.encode(&self.actions.len(), Gamma)?;
+        self.actions.iter().try_for_each(|(entity, actions)| {
+            writer.serialize(entity)?;
+            actions.encode(writer)
+        })
+    }
+
+    fn decode(reader: &mut ReadWordBuffer) -> Result<Self> {
+        Self::decode_with(reader, &ProtocolRegistry::default())
+    }
+
+    fn decode_with(reader: &mut ReadWordBuffer, registry: &ProtocolRegistry) -> Result<Self> {
+        let sequence_id = reader.deserialize()?;
+        let len = reader.decode::<usize>(Gamma)?;
+        let mut actions = Vec::new();
+        for _ in 0..len {
+            actions.push((
+                reader.deserialize()?,
+                EntityActions::decode_with(reader, registry)?,
+            ));
+        }
+        Ok(Self {
+            sequence_id,
+            actions,
+        })
+    }
+}
+
+impl<C: BitSerializable, D: Serialize + DeserializeOwned + Clone> BitSerializable
+    for EntityUpdatesMessage<C, D>
+{
+    fn encode(&self, writer: &mut WriteWordBuffer) -> Result<()> {
+        writer.serialize(&self.last_action_tick)?;
+        writer.encode(&self.updates.len(), Gamma)?;
+        self.updates.iter().try_for_each(|(entity, components)| {
+            writer.serialize(entity)?;
+            encode_seq(components, writer)
+        })?;
+        writer.serialize(&self.deltas)
+    }
+
+    fn decode(reader: &mut ReadWordBuffer) -> Result<Self> {
+        Self::decode_with(reader, &ProtocolRegistry::default())
+    }
+
+    fn decode_with(reader: &mut ReadWordBuffer, registry: &ProtocolRegistry) -> Result<Self> {
+        let last_action_tick = reader.deserialize()?;
+        let len = reader.decode::<usize>(Gamma)?;
+        let mut updates = Vec::new();
+        for _ in 0..len {
+            updates.push((reader.deserialize()?, decode_seq_with(reader, registry)?));
+        }
+        Ok(Self {
+            last_action_tick,
+            updates,
+            deltas: reader.deserialize()?,
+        })
+    }
+}
+
+impl<
+        C: BitSerializable,
+        K: Serialize + DeserializeOwned + Clone + Hash + Eq,
+        D: Serialize + DeserializeOwned + Clone,
+    > BitSerializable for ReplicationMessage<C, K, D>
+{
+    fn encode(&self, writer: &mut WriteWordBuffer) -> Result<()> {
+        writer.serialize(&self.group_id)?;
+        match &self.data {
+            ReplicationMessageData::Actions(actions) => {
+                encode_variant_index(0, writer)?;
+                actions.encode(writer)
+            }
+            ReplicationMessageData::Updates(updates) => {
+                encode_variant_index(1, writer)?;
+                updates.encode(writer)
+            }
+        }
+    }
+
+    fn decode(reader: &mut ReadWordBuffer) -> Result<Self> {
+        Self::decode_with(reader, &ProtocolRegistry::default())
+    }
+
+    fn decode_with(reader: &mut ReadWordBuffer, registry: &ProtocolRegistry) -> Result<Self> {
+        let group_id = reader.deserialize()?;
+        let data = match decode_variant_index(reader)? {
+            0 => {
+                ReplicationMessageData::Actions(EntityActionMessage::decode_with(reader, registry)?)
+            }
+            1 => ReplicationMessageData::Updates(EntityUpdatesMessage::decode_with(
+                reader, registry,
+            )?),
+            index => bail!("invalid replication message variant {index}"),
+        };
+        Ok(Self { group_id, data })
+    }
 }
 
 pub trait ReplicationSend<P: Protocol>: Resource {
@@ -189,6 +335,7 @@ mod tests {
             incoming_latency: Duration::from_millis(0),
             incoming_jitter: Duration::from_millis(0),
             incoming_loss: 0.0,
+            ..Default::default()
         };
         let sync_config = SyncConfig::default().speedup_factor(1.0);
         let prediction_config = PredictionConfig::default().disable(false);
@@ -250,4 +397,102 @@ mod tests {
             .is_none());
         Ok(())
     }
+
+    // A delta-compressed component gets replicated from server to client,
+    // the updates should be sent as deltas once the server has received an ack for the first update
+    #[test]
+    fn test_delta_compressed_component_update() -> anyhow::Result<()> {
+        let frame_duration = Duration::from_millis(10);
+        let tick_duration = Duration::from_millis(10);
+        let shared_config = SharedConfig {
+            enable_replication: true,
+            tick: TickConfig::new(tick_duration),
+            ..Default::default()
+        };
+        let link_conditioner = LinkConditionerConfig {
+            incoming_latency: Duration::from_millis(0),
+            incoming_jitter: Duration::from_millis(0),
+            incoming_loss: 0.0,
+            ..Default::default()
+        };
+        let sync_config = SyncConfig::default().speedup_factor(1.0);
+        let prediction_config = PredictionConfig::default().disable(false);
+        let interpolation_config = InterpolationConfig::default();
+        let mut stepper = BevyStepper::new(
+            shared_config,
+            sync_config,
+            prediction_config,
+            interpolation_config,
+            link_conditioner,
+            frame_duration,
+        );
+        stepper.init();
+
+        // Create an entity on server
+        let server_entity = stepper
+            .server_app
+            .world
+            .spawn((Component5 { x: 0.0, y: 0.0 }, Replicate::default()))
+            .id();
+        stepper.frame_step();
+        stepper.frame_step();
+        let client_entity = *stepper
+            .client_app
+            .world
+            .resource::<ClientConnectionManager>()
+            .replication_receiver
+            .remote_entity_map
+            .get_local(server_entity)
+            .unwrap();
+
+        // Update the component a few times, so that the first update gets acked
+        for i in 1..=5 {
+            stepper
+                .server_app
+                .world
+                .entity_mut(server_entity)
+                .get_mut::<Component5>()
+                .unwrap()
+                .x = i as f32;
+            stepper.frame_step();
+            stepper.frame_step();
+        }
+        assert_eq!(
+            stepper
+                .client_app
+                .world
+                .entity(client_entity)
+                .get::<Component5>()
+                .unwrap(),
+            &Component5 { x: 5.0, y: 0.0 }
+        );
+
+        // Check that the server has an acked base value to compute deltas against
+        let server_manager = stepper
+            .server_app
+            .world
+            .resource::<ServerConnectionManager>();
+        let connection = server_manager.connections.values().next().unwrap();
+        assert!(connection
+            .replication_sender
+            .delta_acked_state
+            .get(&server_entity)
+            .is_some_and(|acked| acked.contains_key(&MyComponentsProtocolKind::Component5)));
+        // and that the client was able to rebuild the full values from the deltas
+        assert!(stepper
+            .client_app
+            .world
+            .resource::<ClientConnectionManager>()
+            .replication_receiver
+            .delta_history
+            .base(
+                server_entity,
+                MyComponentsProtocolKind::Component5,
+                connection.replication_sender.delta_acked_state[&server_entity]
+                    [&MyComponentsProtocolKind::Component5]
+                    .0
+            )
+            .is_some());
+        Ok(())
+    }
 }
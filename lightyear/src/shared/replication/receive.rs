@@ -18,6 +18,7 @@ use crate::protocol::component::ComponentProtocol;
 use crate::protocol::component::{ComponentBehaviour, ComponentKindBehaviour};
 use crate::protocol::Protocol;
 use crate::shared::replication::components::ReplicationGroupId;
+use crate::shared::replication::delta::{ComponentDeltaOf, DeltaHistory};
 
 use super::entity_map::RemoteEntityMap;
 use super::{
@@ -31,6 +32,9 @@ pub(crate) struct ReplicationReceiver<P: Protocol> {
     /// Map from remote entity to the replication group-id
     pub remote_entity_to_group: EntityHashMap<Entity, ReplicationGroupId>,
 
+    /// Values received for the delta-compressed components, used to rebuild the full values from deltas
+    pub delta_history: DeltaHistory<P::ComponentKinds, P::Components>,
+
     // BOTH
     /// Buffer to so that we have an ordered receiver per group
     pub group_channels: EntityHashMap<ReplicationGroupId, GroupChannel<P>>,
@@ -42,6 +46,7 @@ impl<P: Protocol> ReplicationReceiver<P> {
             // RECEIVE
             remote_entity_map: RemoteEntityMap::default(),
             remote_entity_to_group: Default::default(),
+            delta_history: DeltaHistory::default(),
             // BOTH
             group_channels: Default::default(),
         }
@@ -50,7 +55,7 @@ impl<P: Protocol> ReplicationReceiver<P> {
     /// Recv a new replication message and buffer it
     pub(crate) fn recv_message(
         &mut self,
-        message: ReplicationMessage<P::Components, P::ComponentKinds>,
+        message: ReplicationMessage<P::Components, P::ComponentKinds, ComponentDeltaOf<P>>,
         remote_tick: Tick,
     ) {
         trace!(?message, ?remote_tick, "Received replication message");
@@ -75,7 +80,11 @@ impl<P: Protocol> ReplicationReceiver<P> {
                     .actions_recv_message_buffer
                     .insert(m.sequence_id, (remote_tick, m));
             }
-            ReplicationMessageData::Updates(m) => {
+            ReplicationMessageData::Updates(mut m) => {
+                // rebuild the full values of delta-compressed components. This must be done even if the
+                // message is stale, because the sender might use these values as the base for future deltas
+                Self::resolve_deltas(&mut self.delta_history, &mut m, remote_tick);
+
                 // NOTE: this is valid instead after tick wrapping because we keep clamping the latest_tick values
                 //  for each channel
                 // if we have already applied a more recent update for this group, no need to keep this one
@@ -107,6 +116,53 @@ impl<P: Protocol> ReplicationReceiver<P> {
         trace!(?channel, "group channel after buffering");
     }
 
+    /// Convert the deltas contained in the update message back into full component values,
+    /// and record the values of delta-compressed components so that they can be used as a base later
+    fn resolve_deltas(
+        delta_history: &mut DeltaHistory<P::ComponentKinds, P::Components>,
+        message: &mut EntityUpdatesMessage<P::Components, ComponentDeltaOf<P>>,
+        remote_tick: Tick,
+    ) {
+        for (entity, deltas) in std::mem::take(&mut message.deltas) {
+            let mut components = Vec::with_capacity(deltas.len());
+            for delta in deltas {
+                let kind = P::ComponentKinds::from(&delta.delta);
+                let Some(base) = delta_history.base(entity, kind, delta.base_tick) else {
+                    // this cannot happen unless the message is corrupted: the sender only computes deltas
+                    // against values that are still in our history
+                    error!(
+                        ?entity,
+                        ?kind,
+                        base_tick = ?delta.base_tick,
+                        "Received a component delta but the base value is missing"
+                    );
+                    continue;
+                };
+                match base.apply_diff(&delta.delta) {
+                    Ok(component) => components.push(component),
+                    Err(e) => {
+                        error!(?kind, "could not apply component delta: {:?}", e)
+                    }
+                }
+            }
+            if components.is_empty() {
+                continue;
+            }
+            match message.updates.iter_mut().find(|(e, _)| *e == entity) {
+                Some((_, updates)) => updates.extend(components),
+                None => message.updates.push((entity, components)),
+            }
+        }
+        for (entity, components) in message.updates.iter() {
+            for component in components {
+                let kind: P::ComponentKinds = component.into();
+                if kind.is_delta_compressed() {
+                    delta_history.record(*entity, kind, remote_tick, component.clone());
+                }
+            }
+        }
+    }
+
     /// Return the list of replication messages that are ready to be applied to the World
     /// Also include the server_tick when that replication message was emitted
     ///
@@ -124,7 +180,7 @@ impl<P: Protocol> ReplicationReceiver<P> {
         ReplicationGroupId,
         Vec<(
             Tick,
-            ReplicationMessageData<P::Components, P::ComponentKinds>,
+            ReplicationMessageData<P::Components, P::ComponentKinds, ComponentDeltaOf<P>>,
         )>,
     )> {
         self.group_channels
@@ -189,7 +245,7 @@ impl<P: Protocol> ReplicationReceiver<P> {
         &mut self,
         world: &mut World,
         tick: Tick,
-        replication: ReplicationMessageData<P::Components, P::ComponentKinds>,
+        replication: ReplicationMessageData<P::Components, P::ComponentKinds, ComponentDeltaOf<P>>,
         group_id: ReplicationGroupId,
         events: &mut ConnectionEvents<P>,
     ) {
@@ -240,6 +296,7 @@ impl<P: Protocol> ReplicationReceiver<P> {
                             }
                             events.push_despawn(local_entity);
                             self.remote_entity_to_group.remove(&entity);
+                            self.delta_history.remove_entity(entity, tick);
                         } else {
                             error!("Received despawn for an entity that does not exist")
                         }
@@ -372,10 +429,10 @@ pub struct GroupChannel<P: Protocol> {
     // the first tick is the last_action_tick (we can only apply the update if the last action tick has been reached)
     // the second tick is the update's server tick when it was sent
     pub buffered_updates_with_last_action_tick:
-        BTreeMap<Tick, BTreeMap<Tick, EntityUpdatesMessage<P::Components>>>,
+        BTreeMap<Tick, BTreeMap<Tick, EntityUpdatesMessage<P::Components, ComponentDeltaOf<P>>>>,
     // updates for which there is no condition on the last_action_tick: we can apply them immediately
     pub buffered_updates_without_last_action_tick:
-        BTreeMap<Tick, EntityUpdatesMessage<P::Components>>,
+        BTreeMap<Tick, EntityUpdatesMessage<P::Components, ComponentDeltaOf<P>>>,
     /// remote tick of the latest update/action that we applied to the local group
     pub latest_tick: Option<Tick>,
 }
@@ -430,7 +487,12 @@ impl<P: Protocol> GroupChannel<P> {
         Some(message)
     }
 
-    fn read_buffered_updates(&mut self) -> Vec<(Tick, EntityUpdatesMessage<P::Components>)> {
+    fn read_buffered_updates(
+        &mut self,
+    ) -> Vec<(
+        Tick,
+        EntityUpdatesMessage<P::Components, ComponentDeltaOf<P>>,
+    )> {
         // if we haven't applied any actions (latest_tick is None) we cannot apply any updates
         let Some(latest_tick) = self.latest_tick else {
             return vec![];
@@ -472,7 +534,7 @@ impl<P: Protocol> GroupChannel<P> {
     ) -> Option<
         Vec<(
             Tick,
-            ReplicationMessageData<P::Components, P::ComponentKinds>,
+            ReplicationMessageData<P::Components, P::ComponentKinds, ComponentDeltaOf<P>>,
         )>,
     > {
         let mut res = Vec::new();
@@ -559,6 +621,7 @@ mod tests {
                 data: ReplicationMessageData::Updates(EntityUpdatesMessage {
                     last_action_tick: Some(Tick(0)),
                     updates: Default::default(),
+                    deltas: Default::default(),
                 }),
             },
             Tick(1),
@@ -580,6 +643,7 @@ mod tests {
                 data: ReplicationMessageData::Updates(EntityUpdatesMessage {
                     last_action_tick: Some(Tick(2)),
                     updates: Default::default(),
+                    deltas: Default::default(),
                 }),
             },
             Tick(4),
@@ -19,6 +19,7 @@ use crate::protocol::component::ComponentProtocol;
 use crate::protocol::component::{ComponentBehaviour, ComponentKindBehaviour};
 use crate::protocol::Protocol;
 use crate::shared::replication::components::{Replicate, ReplicationGroupId};
+use crate::shared::replication::delta::{ComponentDelta, ComponentDeltaOf, MAX_DELTA_HISTORY_LEN};
 
 use super::{EntityActionMessage, EntityActions, EntityUpdatesMessage, ReplicationMessageData};
 
@@ -47,6 +48,18 @@ pub(crate) struct ReplicationSender<P: Protocol> {
 
     /// Buffer to so that we have an ordered receiver per group
     pub group_channels: EntityHashMap<ReplicationGroupId, GroupChannel>,
+
+    // DELTA-COMPRESSION
+    /// Last value of each delta-compressed component that was acked by the remote, along with the tick
+    /// of the update message that contained it
+    pub delta_acked_state: EntityHashMap<Entity, HashMap<P::ComponentKinds, (Tick, P::Components)>>,
+    /// Full values of the delta-compressed components included in the update messages that were just finalized
+    pub pending_delta_values:
+        EntityHashMap<ReplicationGroupId, (Tick, Vec<(Entity, P::Components)>)>,
+    /// Map from message-id to the full values of the delta-compressed components sent in that update message,
+    /// so that they can become the new base once the message is acked
+    pub updates_message_id_to_delta_values:
+        HashMap<MessageId, (ReplicationGroupId, Tick, Vec<(Entity, P::Components)>)>,
 }
 
 impl<P: Protocol> ReplicationSender<P> {
@@ -61,6 +74,26 @@ impl<P: Protocol> ReplicationSender<P> {
             pending_unique_components: EntityHashMap::default(),
             // BOTH
             group_channels: Default::default(),
+            // DELTA-COMPRESSION
+            delta_acked_state: EntityHashMap::default(),
+            pending_delta_values: EntityHashMap::default(),
+            updates_message_id_to_delta_values: HashMap::default(),
+        }
+    }
+
+    /// Keep track of the update message that was buffered for a given group, so that we can handle
+    /// receiving an ACK for that message-id later
+    pub(crate) fn track_update_message(
+        &mut self,
+        message_id: MessageId,
+        group_id: ReplicationGroupId,
+        bevy_tick: BevyTick,
+    ) {
+        self.updates_message_id_to_group_id
+            .insert(message_id, (group_id, bevy_tick));
+        if let Some((tick, values)) = self.pending_delta_values.remove(&group_id) {
+            self.updates_message_id_to_delta_values
+                .insert(message_id, (group_id, tick, values));
         }
     }
 
@@ -74,6 +107,7 @@ impl<P: Protocol> ReplicationSender<P> {
             {
                 let channel = self.group_channels.entry(*group_id).or_default();
                 channel.update_collect_changes_since_this_tick(*bevy_tick);
+                self.ack_delta_values(message_id);
             } else {
                 error!(
                     "Received an update message-id ack but we don't have the corresponding group"
@@ -81,6 +115,60 @@ impl<P: Protocol> ReplicationSender<P> {
             }
         }
     }
+
+    /// The delta-compressed values sent in the message are now known by the remote, we can use them
+    /// as the base for future deltas
+    fn ack_delta_values(&mut self, message_id: MessageId) {
+        let Some((group_id, tick, values)) =
+            self.updates_message_id_to_delta_values.remove(&message_id)
+        else {
+            return;
+        };
+        for (entity, component) in values {
+            let kind: P::ComponentKinds = (&component).into();
+            let acked = self.delta_acked_state.entry(entity).or_default();
+            match acked.get(&kind) {
+                Some((acked_tick, _)) if *acked_tick >= tick => {}
+                _ => {
+                    acked.insert(kind, (tick, component));
+                }
+            }
+        }
+        // older update messages for this group can never become the base anymore
+        self.updates_message_id_to_delta_values
+            .retain(|_, (group, message_tick, _)| *group != group_id || *message_tick > tick);
+    }
+
+    /// Number of update messages that contain a value for the delta-compressed component and that were
+    /// sent after the base value at `base_tick` (and not acked yet).
+    /// The remote records each of these values in its history, which could push out the base value.
+    fn sent_since_base(
+        &self,
+        group_id: ReplicationGroupId,
+        entity: Entity,
+        kind: P::ComponentKinds,
+        base_tick: Tick,
+    ) -> usize {
+        self.updates_message_id_to_delta_values
+            .values()
+            .filter(|(group, message_tick, values)| {
+                *group == group_id
+                    && *message_tick > base_tick
+                    && values
+                        .iter()
+                        .any(|(e, c)| *e == entity && P::ComponentKinds::from(c) == kind)
+            })
+            .count()
+    }
+
+    /// Forget the delta-compression state of an entity when it gets spawned or despawned,
+    /// so that we never compute deltas against values from a previous lifetime of the entity
+    fn clear_delta_state(&mut self, entity: Entity) {
+        self.delta_acked_state.remove(&entity);
+        self.updates_message_id_to_delta_values
+            .values_mut()
+            .for_each(|(_, _, values)| values.retain(|(e, _)| *e != entity));
+    }
 }
 
 /// We want:
@@ -93,9 +181,15 @@ impl<P: Protocol> ReplicationSender<P> {
     //  use a OnceCell that gets set with the channel name mapping when the protocol is finalized?
     //  the other option is to have wrappers in Connection, but that's pretty ugly
 
+    /// Set the priority used for the update messages of the group
+    pub(crate) fn update_base_priority(&mut self, group: ReplicationGroupId, priority: f32) {
+        self.group_channels.entry(group).or_default().base_priority = priority;
+    }
+
     /// Host has spawned an entity, and we want to replicate this to remote
     /// Returns true if we should send a message
     pub(crate) fn prepare_entity_spawn(&mut self, entity: Entity, group: ReplicationGroupId) {
+        self.clear_delta_state(entity);
         let actions = self
             .pending_actions
             .entry(group)
@@ -106,6 +200,7 @@ impl<P: Protocol> ReplicationSender<P> {
     }
 
     pub(crate) fn prepare_entity_despawn(&mut self, entity: Entity, group: ReplicationGroupId) {
+        self.clear_delta_state(entity);
         self.pending_actions
             .entry(group)
             .or_default()
@@ -266,7 +361,7 @@ impl<P: Protocol> ReplicationSender<P> {
     ) -> Vec<(
         ChannelKind,
         ReplicationGroupId,
-        ReplicationMessageData<P::Components, P::ComponentKinds>,
+        ReplicationMessageData<P::Components, P::ComponentKinds, ComponentDeltaOf<P>>,
     )> {
         let mut messages = Vec::new();
 
@@ -281,7 +376,7 @@ impl<P: Protocol> ReplicationSender<P> {
                         .entry(entity)
                         .or_default()
                         .updates
-                        .extend(components.into_iter());
+                        .extend(components);
                 }
             }
             let channel = self.group_channels.entry(group_id).or_default();
@@ -294,14 +389,63 @@ impl<P: Protocol> ReplicationSender<P> {
                 ReplicationMessageData::Actions(EntityActionMessage {
                     sequence_id: message_id,
                     // TODO: maybe we can just send the HashMap directly?
-                    actions: Vec::from_iter(actions.into_iter()),
+                    actions: Vec::from_iter(actions),
                 }),
             ));
             debug!("final action messages to send: {:?}", messages);
         }
         // send the remaining updates
-        for (group_id, updates) in self.pending_updates.drain() {
+        for (group_id, updates) in std::mem::take(&mut self.pending_updates) {
             trace!(?group_id, "pending updates: {:?}", updates);
+            let mut full_updates = Vec::with_capacity(updates.len());
+            let mut deltas = Vec::new();
+            let mut delta_values = Vec::new();
+            for (entity, components) in updates {
+                let mut entity_updates = Vec::with_capacity(components.len());
+                let mut entity_deltas = Vec::new();
+                for component in components {
+                    let kind: P::ComponentKinds = (&component).into();
+                    if !kind.is_delta_compressed() {
+                        entity_updates.push(component);
+                        continue;
+                    }
+                    delta_values.push((entity, component.clone()));
+                    // compute the delta against the last value acked by the remote, if there is one
+                    let Some((base_tick, base)) = self
+                        .delta_acked_state
+                        .get(&entity)
+                        .and_then(|acked| acked.get(&kind))
+                    else {
+                        entity_updates.push(component);
+                        continue;
+                    };
+                    // the remote only keeps the most recent values in its history: if we sent too many
+                    // values since the base, the base might be gone, so we send the full value instead
+                    if self.sent_since_base(group_id, entity, kind, *base_tick) + 1
+                        >= MAX_DELTA_HISTORY_LEN
+                    {
+                        entity_updates.push(component);
+                        continue;
+                    }
+                    match base.diff(&component) {
+                        Some(delta) => entity_deltas.push(ComponentDelta {
+                            base_tick: *base_tick,
+                            delta,
+                        }),
+                        None => entity_updates.push(component),
+                    }
+                }
+                if !entity_updates.is_empty() {
+                    full_updates.push((entity, entity_updates));
+                }
+                if !entity_deltas.is_empty() {
+                    deltas.push((entity, entity_deltas));
+                }
+            }
+            if !delta_values.is_empty() {
+                self.pending_delta_values
+                    .insert(group_id, (tick, delta_values));
+            }
             let channel = self.group_channels.entry(group_id).or_default();
             messages.push((
                 ChannelKind::of::<EntityUpdatesChannel>(),
@@ -310,7 +454,8 @@ impl<P: Protocol> ReplicationSender<P> {
                     // SAFETY: the last action tick is always set because we send Actions before Updates
                     last_action_tick: channel.last_action_tick,
                     // TODO: maybe we can just send the HashMap directly?
-                    updates: Vec::from_iter(updates.into_iter()),
+                    updates: full_updates,
+                    deltas,
                 }),
             ));
         }
@@ -325,6 +470,65 @@ impl<P: Protocol> ReplicationSender<P> {
     }
 }
 
+impl<P: Protocol> ReplicationSender<P> {
+    /// Accumulate the priority of the groups that have updates to send, and sort the messages
+    /// so that actions are sent first, then updates in order of accumulated priority (highest first)
+    pub(crate) fn sort_by_priority(
+        &mut self,
+        mut messages: Vec<(
+            ChannelKind,
+            ReplicationGroupId,
+            ReplicationMessageData<P::Components, P::ComponentKinds, ComponentDeltaOf<P>>,
+        )>,
+    ) -> Vec<(
+        ChannelKind,
+        ReplicationGroupId,
+        ReplicationMessageData<P::Components, P::ComponentKinds, ComponentDeltaOf<P>>,
+    )> {
+        let priorities: EntityHashMap<ReplicationGroupId, f32> = messages
+            .iter()
+            .filter(|(_, _, data)| matches!(data, ReplicationMessageData::Updates(_)))
+            .map(|(_, group_id, _)| {
+                let channel = self.group_channels.entry(*group_id).or_default();
+                channel.accumulated_priority += channel.base_priority;
+                (*group_id, channel.accumulated_priority)
+            })
+            .collect();
+        // the sort is stable, so the actions keep their order
+        messages.sort_by(
+            |(_, group_a, data_a), (_, group_b, data_b)| match (data_a, data_b) {
+                (ReplicationMessageData::Actions(_), ReplicationMessageData::Actions(_)) => {
+                    std::cmp::Ordering::Equal
+                }
+                (ReplicationMessageData::Actions(_), _) => std::cmp::Ordering::Less,
+                (_, ReplicationMessageData::Actions(_)) => std::cmp::Ordering::Greater,
+                _ => priorities[group_b].total_cmp(&priorities[group_a]),
+            },
+        );
+        messages
+    }
+
+    /// The update message was written into a packet, so the group can stop accumulating priority
+    pub(crate) fn reset_update_priority(&mut self, message_id: MessageId) {
+        if let Some((group_id, _)) = self.updates_message_id_to_group_id.get(&message_id) {
+            if let Some(channel) = self.group_channels.get_mut(group_id) {
+                channel.accumulated_priority = 0.0;
+            }
+        }
+    }
+
+    /// The update message of the group did not fit in the bandwidth budget.
+    /// The group keeps its accumulated priority, and the updates will be collected again during the next send
+    /// (since they are computed from the last acked tick)
+    pub(crate) fn defer_update(&mut self, group_id: ReplicationGroupId) {
+        trace!(
+            ?group_id,
+            "bandwidth budget exhausted, deferring group updates"
+        );
+        self.pending_delta_values.remove(&group_id);
+    }
+}
+
 /// Channel to keep track of sending replication messages for a given Group
 #[derive(Debug)]
 pub struct GroupChannel {
@@ -335,6 +539,10 @@ pub struct GroupChannel {
     pub collect_changes_since_this_tick: Option<BevyTick>,
     // last tick for which we sent an action message
     pub last_action_tick: Option<Tick>,
+    /// Priority of the group (from the `Replicate` component)
+    pub base_priority: f32,
+    /// Priority accumulated since the last time we could send updates for this group
+    pub accumulated_priority: f32,
 }
 
 impl Default for GroupChannel {
@@ -343,6 +551,8 @@ impl Default for GroupChannel {
             actions_next_send_message_id: MessageId(0),
             last_action_tick: None,
             collect_changes_since_this_tick: None,
+            base_priority: 1.0,
+            accumulated_priority: 0.0,
         }
     }
 }
@@ -469,6 +679,7 @@ mod tests {
                         entity_3,
                         vec![MyComponentsProtocol::Component3(Component3(5.0))]
                     )],
+                    deltas: vec![],
                 })
             )
         );
@@ -489,4 +700,107 @@ mod tests {
             Some(Tick(2))
         );
     }
+
+    #[test]
+    fn test_sort_by_priority() {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let mut manager = ReplicationSender::<MyProtocol>::new(receiver);
+
+        let entity_1 = Entity::from_raw(0);
+        let entity_2 = Entity::from_raw(1);
+        let entity_3 = Entity::from_raw(2);
+        let group_1 = ReplicationGroupId(0);
+        let group_2 = ReplicationGroupId(1);
+        let group_3 = ReplicationGroupId(2);
+        manager.update_base_priority(group_1, 1.0);
+        manager.update_base_priority(group_2, 2.5);
+
+        let prepare = |manager: &mut ReplicationSender<MyProtocol>| {
+            manager.prepare_entity_update(
+                entity_1,
+                group_1,
+                MyComponentsProtocol::Component1(Component1(1.0)),
+            );
+            manager.prepare_entity_update(
+                entity_2,
+                group_2,
+                MyComponentsProtocol::Component1(Component1(2.0)),
+            );
+            manager.prepare_entity_spawn(entity_3, group_3);
+        };
+        let groups = |messages: &Vec<(
+            ChannelKind,
+            ReplicationGroupId,
+            ReplicationMessageData<
+                MyComponentsProtocol,
+                MyComponentsProtocolKind,
+                MyComponentsProtocolDelta,
+            >,
+        )>| messages.iter().map(|(_, g, _)| *g).collect::<Vec<_>>();
+
+        // actions are sent first, then updates by order of priority
+        prepare(&mut manager);
+        let messages = manager.finalize(Tick(1));
+        let messages = manager.sort_by_priority(messages);
+        assert_eq!(groups(&messages), vec![group_3, group_2, group_1]);
+
+        // group_2 was sent, but group_1 was deferred and accumulated priority
+        manager.track_update_message(MessageId(0), group_2, BevyTick::new(0));
+        manager.reset_update_priority(MessageId(0));
+        manager.defer_update(group_1);
+        prepare(&mut manager);
+        let messages = manager.finalize(Tick(2));
+        let messages = manager.sort_by_priority(messages);
+        assert_eq!(groups(&messages), vec![group_3, group_2, group_1]);
+        manager.track_update_message(MessageId(1), group_2, BevyTick::new(0));
+        manager.reset_update_priority(MessageId(1));
+        manager.defer_update(group_1);
+
+        // group_1 has now accumulated more priority than group_2
+        prepare(&mut manager);
+        let messages = manager.finalize(Tick(3));
+        let messages = manager.sort_by_priority(messages);
+        assert_eq!(groups(&messages), vec![group_3, group_1, group_2]);
+    }
+
+    #[test]
+    fn test_delta_fallback_to_full_update() {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let mut manager = ReplicationSender::<MyProtocol>::new(receiver);
+        let entity = Entity::from_raw(0);
+        let group = ReplicationGroupId(0);
+        let send_update = |manager: &mut ReplicationSender<MyProtocol>, i: u16| {
+            manager.prepare_entity_update(
+                entity,
+                group,
+                MyComponentsProtocol::Component5(Component5 {
+                    x: i as f32,
+                    y: 0.0,
+                }),
+            );
+            let mut messages = manager.finalize(Tick(i));
+            manager.track_update_message(MessageId(i), group, BevyTick::new(0));
+            let Some((_, _, ReplicationMessageData::Updates(message))) = messages.pop() else {
+                panic!("expected an update message");
+            };
+            message
+        };
+
+        // there is no acked base yet: the full value is sent
+        let message = send_update(&mut manager, 0);
+        assert_eq!(message.updates.len(), 1);
+        sender.send(MessageId(0)).unwrap();
+        manager.recv_update_acks();
+
+        // the next updates are sent as deltas against the acked value
+        for i in 1..MAX_DELTA_HISTORY_LEN as u16 {
+            let message = send_update(&mut manager, i);
+            assert!(message.updates.is_empty());
+            assert_eq!(message.deltas[0].1[0].base_tick, Tick(0));
+        }
+        // the remote might have dropped the base value from its history: the full value is sent
+        let message = send_update(&mut manager, MAX_DELTA_HISTORY_LEN as u16);
+        assert!(message.deltas.is_empty());
+        assert_eq!(message.updates.len(), 1);
+    }
 }
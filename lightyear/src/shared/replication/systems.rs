@@ -1,16 +1,21 @@
 //! Bevy [`bevy::prelude::System`]s used for replication
 use std::ops::Deref;
 
+use bevy::ecs::component::Tick as BevyTick;
 use bevy::ecs::entity::Entities;
+use bevy::ecs::event::ManualEventReader;
+use bevy::ecs::removal_detection::RemovedComponentEntity;
 use bevy::ecs::system::SystemChangeTick;
 use bevy::prelude::{
-    Added, App, Commands, Component, DetectChanges, Entity, IntoSystemConfigs, PostUpdate,
-    PreUpdate, Query, Ref, RemovedComponents, ResMut, Without,
+    Added, App, Commands, Component, DetectChanges, Entity, EntityRef, IntoSystemConfigs, Local,
+    Mut, PostUpdate, PreUpdate, Query, QueryState, Ref, RemovedComponents, ResMut, Without, World,
 };
+use bevy::utils::HashMap;
 use tracing::{debug, error, trace, warn};
 
 use crate::_reexport::{FromType, ShouldBeInterpolated};
 use crate::prelude::{MainSet, NetworkTarget, ShouldBePredicted};
+use crate::protocol::registry::{registered_kind, DynamicComponent, NetId, ProtocolRegistry};
 use crate::protocol::Protocol;
 use crate::server::room::ClientVisibility;
 use crate::shared::replication::components::{DespawnTracker, Replicate, ReplicationMode};
@@ -63,7 +68,10 @@ fn send_entity_despawn<P: Protocol, R: ReplicationSend<P>>(
 ) {
     // Despawn entities for clients that lost visibility
     query.iter().for_each(|(entity, replicate)| {
-        if matches!(replicate.replication_mode, ReplicationMode::Room) {
+        if matches!(
+            replicate.replication_mode,
+            ReplicationMode::Room | ReplicationMode::Visibility
+        ) {
             replicate
                 .replication_clients_cache
                 .iter()
@@ -124,7 +132,7 @@ fn send_entity_spawn<P: Protocol, R: ReplicationSend<P>>(
         match replicate.replication_mode {
             // for room mode, no need to handle newly-connected clients specially; they just need
             // to be added to the correct room
-            ReplicationMode::Room => {
+            ReplicationMode::Room | ReplicationMode::Visibility => {
                 replicate
                     .replication_clients_cache
                     .iter()
@@ -235,139 +243,175 @@ fn send_component_update<C: Component + Clone, P: Protocol, R: ReplicationSend<P
 {
     let kind = <P::ComponentKinds as FromType<C>>::from_type();
     query.iter().for_each(|(entity, component, replicate)| {
-        // do not replicate components that are disabled
-        if replicate.is_disabled::<C>() {
-            return;
-        }
-        match replicate.replication_mode {
-            ReplicationMode::Room => {
-                replicate
-                    .replication_clients_cache
-                    .iter()
-                    .for_each(|(client_id, visibility)| {
-                        if replicate.replication_target.should_send_to(client_id) {
-                            match visibility {
-                                ClientVisibility::Gained => {
-                                    let target = replicate.target::<C>(NetworkTarget::Only(vec![*client_id]));
+        replicate_component_update(
+            sender.as_mut(),
+            entity,
+            kind,
+            || component.clone().into(),
+            replicate,
+            UpdateTicks {
+                is_added: component.is_added(),
+                last_changed: component.last_changed(),
+                this_run: system_bevy_ticks.this_run(),
+            },
+        );
+    });
+}
+
+/// Change ticks of the component that is being replicated
+struct UpdateTicks {
+    /// The component was added since the last run of the system
+    is_added: bool,
+    last_changed: BevyTick,
+    this_run: BevyTick,
+}
+
+/// Send the insert or update of the component of kind `kind` of an entity, to every client that should receive it
+fn replicate_component_update<P: Protocol, R: ReplicationSend<P>>(
+    sender: &mut R,
+    entity: Entity,
+    kind: P::ComponentKinds,
+    component: impl Fn() -> P::Components,
+    replicate: &Replicate<P>,
+    ticks: UpdateTicks,
+) {
+    let UpdateTicks {
+        is_added,
+        last_changed,
+        this_run,
+    } = ticks;
+    // do not replicate components that are disabled
+    if replicate.is_kind_disabled(kind) {
+        return;
+    }
+    match replicate.replication_mode {
+        ReplicationMode::Room | ReplicationMode::Visibility => {
+            replicate
+                .replication_clients_cache
+                .iter()
+                .for_each(|(client_id, visibility)| {
+                    if replicate.replication_target.should_send_to(client_id) {
+                        match visibility {
+                            ClientVisibility::Gained => {
+                                let target = replicate.kind_target(kind, NetworkTarget::Only(vec![*client_id]));
+                                let _ = sender
+                                    .prepare_component_insert(
+                                        entity,
+                                        component(),
+                                        replicate,
+                                        target,
+                                        this_run,
+                                    )
+                                    .map_err(|e| {
+                                        error!("error sending component insert: {:?}", e);
+                                    });
+                            }
+                            ClientVisibility::Lost => {}
+                            ClientVisibility::Maintained => {
+                                // send an component_insert for components that were newly added
+                                if is_added {
+                                    let target = replicate.kind_target(kind, NetworkTarget::Only(vec![*client_id]));
                                     let _ = sender
                                         .prepare_component_insert(
                                             entity,
-                                            component.clone().into(),
+                                            component(),
                                             replicate,
                                             target,
-                                            system_bevy_ticks.this_run(),
+                                            this_run,
                                         )
                                         .map_err(|e| {
                                             error!("error sending component insert: {:?}", e);
                                         });
-                                }
-                                ClientVisibility::Lost => {}
-                                ClientVisibility::Maintained => {
-                                    // send an component_insert for components that were newly added
-                                    if component.is_added() {
-                                        let target = replicate.target::<C>(NetworkTarget::Only(vec![*client_id]));
-                                        let _ = sender
-                                            .prepare_component_insert(
-                                                entity,
-                                                component.clone().into(),
-                                                replicate,
-                                                target,
-                                                system_bevy_ticks.this_run(),
-                                            )
-                                            .map_err(|e| {
-                                                error!("error sending component insert: {:?}", e);
-                                            });
-                                        // only update components that were not newly added
-                                    } else {
-                                        // do not send updates for these components, only inserts/removes
-                                        if replicate.is_replicate_once::<C>() {
-                                            return;
-                                        }
-                                        let target = replicate.target::<C>(NetworkTarget::Only(vec![*client_id]));
-                                        let _ = sender
-                                            .prepare_entity_update(
-                                                entity,
-                                                component.clone().into(),
-                                                replicate,
-                                                target,
-                                                component.last_changed(),
-                                                system_bevy_ticks.this_run(),
-                                            )
-                                            .map_err(|e| {
-                                                error!("error sending component update: {:?}", e);
-                                            });
+                                    // only update components that were not newly added
+                                } else {
+                                    // do not send updates for these components, only inserts/removes
+                                    if replicate.is_kind_replicate_once(kind) {
+                                        return;
                                     }
+                                    let target = replicate.kind_target(kind, NetworkTarget::Only(vec![*client_id]));
+                                    let _ = sender
+                                        .prepare_entity_update(
+                                            entity,
+                                            component(),
+                                            replicate,
+                                            target,
+                                            last_changed,
+                                            this_run,
+                                        )
+                                        .map_err(|e| {
+                                            error!("error sending component update: {:?}", e);
+                                        });
                                 }
                             }
                         }
-                    })
-            }
-            ReplicationMode::NetworkTarget => {
-                let mut target = replicate.replication_target.clone();
+                    }
+                })
+        }
+        ReplicationMode::NetworkTarget => {
+            let mut target = replicate.replication_target.clone();
 
-                let new_connected_clients = sender.new_connected_clients().clone();
-                // replicate all components to newly connected clients
-                if !new_connected_clients.is_empty() {
-                    // replicate to the newly connected clients that match our target
-                    let mut new_connected_target = target.clone();
-                    new_connected_target
-                        .intersection(NetworkTarget::Only(new_connected_clients.clone()));
-                    let _ = sender
-                        .prepare_component_insert(
-                            entity,
-                            component.clone().into(),
-                            replicate,
-                            replicate.target::<C>(new_connected_target),
-                            system_bevy_ticks.this_run(),
-                        )
-                        .map_err(|e| {
-                            error!("error sending component insert: {:?}", e);
-                        });
-                    // don't re-send to newly connection client
-                    target.exclude(new_connected_clients.clone());
-                }
+            let new_connected_clients = sender.new_connected_clients().clone();
+            // replicate all components to newly connected clients
+            if !new_connected_clients.is_empty() {
+                // replicate to the newly connected clients that match our target
+                let mut new_connected_target = target.clone();
+                new_connected_target
+                    .intersection(NetworkTarget::Only(new_connected_clients.clone()));
+                let _ = sender
+                    .prepare_component_insert(
+                        entity,
+                        component(),
+                        replicate,
+                        replicate.kind_target(kind, new_connected_target),
+                        this_run,
+                    )
+                    .map_err(|e| {
+                        error!("error sending component insert: {:?}", e);
+                    });
+                // don't re-send to newly connection client
+                target.exclude(new_connected_clients.clone());
+            }
 
-                // send an component_insert for components that were newly added
-                if component.is_added() {
-                    trace!("component is added");
-                    let _ = sender
-                        .prepare_component_insert(
-                            entity,
-                            component.clone().into(),
-                            replicate,
-                            replicate.target::<C>(target),
-                            system_bevy_ticks.this_run(),
-                        )
-                        .map_err(|e| {
-                            error!("error sending component insert: {:?}", e);
-                        });
-                } else {
-                    // do not send updates for these components, only inserts/removes
-                    if replicate.is_replicate_once::<C>() {
-                        trace!(?entity,
-                            "not replicating updates for {:?} because it is marked as replicate_once",
-                            kind
-                        );
-                        return;
-                    }
-                    // otherwise send an update for all components that changed since the
-                    // last update we have ack-ed
-                    let _ = sender
-                        .prepare_entity_update(
-                            entity,
-                            component.clone().into(),
-                            replicate,
-                            replicate.target::<C>(target),
-                            component.last_changed(),
-                            system_bevy_ticks.this_run(),
-                        )
-                        .map_err(|e| {
-                            error!("error sending component update: {:?}", e);
-                        });
+            // send an component_insert for components that were newly added
+            if is_added {
+                trace!("component is added");
+                let _ = sender
+                    .prepare_component_insert(
+                        entity,
+                        component(),
+                        replicate,
+                        replicate.kind_target(kind, target),
+                        this_run,
+                    )
+                    .map_err(|e| {
+                        error!("error sending component insert: {:?}", e);
+                    });
+            } else {
+                // do not send updates for these components, only inserts/removes
+                if replicate.is_kind_replicate_once(kind) {
+                    trace!(?entity,
+                        "not replicating updates for {:?} because it is marked as replicate_once",
+                        kind
+                    );
+                    return;
                 }
+                // otherwise send an update for all components that changed since the
+                // last update we have ack-ed
+                let _ = sender
+                    .prepare_entity_update(
+                        entity,
+                        component(),
+                        replicate,
+                        replicate.kind_target(kind, target),
+                        last_changed,
+                        this_run,
+                    )
+                    .map_err(|e| {
+                        error!("error sending component update: {:?}", e);
+                    });
             }
         }
-    });
+    }
 }
 
 /// This system sends updates for all components that were removed
@@ -383,51 +427,133 @@ fn send_component_removed<C: Component + Clone, P: Protocol, R: ReplicationSend<
     let kind = <P::ComponentKinds as FromType<C>>::from_type();
     removed.read().for_each(|entity| {
         if let Ok(replicate) = query.get(entity) {
-            // do not replicate components that are disabled
-            if replicate.is_disabled::<C>() {
-                return;
+            replicate_component_removed(
+                sender.as_mut(),
+                entity,
+                kind,
+                replicate,
+                system_bevy_ticks.this_run(),
+            );
+        }
+    })
+}
+
+/// Send the removal of the component of kind `kind` of an entity, to every client that should receive it
+fn replicate_component_removed<P: Protocol, R: ReplicationSend<P>>(
+    sender: &mut R,
+    entity: Entity,
+    kind: P::ComponentKinds,
+    replicate: &Replicate<P>,
+    this_run: BevyTick,
+) {
+    // do not replicate components that are disabled
+    if replicate.is_kind_disabled(kind) {
+        return;
+    }
+    match replicate.replication_mode {
+        ReplicationMode::Room | ReplicationMode::Visibility => {
+            replicate.replication_clients_cache.iter().for_each(
+                |(client_id, visibility)| {
+                    if replicate.replication_target.should_send_to(client_id) {
+                        // TODO: maybe send no matter the vis?
+                        if matches!(visibility, ClientVisibility::Maintained) {
+                            let _ = sender
+                                .prepare_component_remove(
+                                    entity,
+                                    kind,
+                                    replicate,
+                                    replicate
+                                        .kind_target(kind, NetworkTarget::Only(vec![*client_id])),
+                                    this_run,
+                                )
+                                .map_err(|e| {
+                                    error!("error sending component remove: {:?}", e);
+                                });
+                        }
+                    }
+                },
+            )
+        }
+        ReplicationMode::NetworkTarget => {
+            trace!("sending component remove!");
+            let _ = sender
+                .prepare_component_remove(
+                    entity,
+                    kind,
+                    replicate,
+                    replicate.kind_target(kind, replicate.replication_target.clone()),
+                    this_run,
+                )
+                .map_err(|e| {
+                    error!("error sending component remove: {:?}", e);
+                });
+        }
+    }
+}
+
+/// This system sends the inserts/updates of the components registered with
+/// [`AppRegistrationExt::register_component`](crate::protocol::registry::AppRegistrationExt::register_component)
+fn send_registered_component_updates<P: Protocol, R: ReplicationSend<P>>(
+    world: &mut World,
+    query: &mut QueryState<(EntityRef, &Replicate<P>)>,
+) {
+    let last_run = world.last_change_tick();
+    let this_run = world.change_tick();
+    world.resource_scope(|world: &mut World, mut sender: Mut<R>| {
+        let Some(registry) = world.get_resource::<ProtocolRegistry>() else {
+            return;
+        };
+        for (net_id, registration) in registry.component_registrations() {
+            let kind = registered_kind::<P>(net_id);
+            for (entity, replicate) in query.iter(world) {
+                let Some(ticks) = (registration.change_ticks)(&entity) else {
+                    continue;
+                };
+                // the component is only cloned if it needs to be sent, and serialized with the rest of the message
+                let component = || {
+                    let value =
+                        (registration.clone)(&entity).expect("the entity has the component");
+                    P::Components::from(DynamicComponent { net_id, value })
+                };
+                replicate_component_update(
+                    sender.as_mut(),
+                    entity.id(),
+                    kind,
+                    component,
+                    replicate,
+                    UpdateTicks {
+                        is_added: ticks.is_added(last_run, this_run),
+                        last_changed: ticks.last_changed_tick(),
+                        this_run,
+                    },
+                );
             }
-            match replicate.replication_mode {
-                ReplicationMode::Room => {
-                    replicate.replication_clients_cache.iter().for_each(
-                        |(client_id, visibility)| {
-                            if replicate.replication_target.should_send_to(client_id) {
-                                // TODO: maybe send no matter the vis?
-                                if matches!(visibility, ClientVisibility::Maintained) {
-                                    let _ = sender
-                                        .prepare_component_remove(
-                                            entity,
-                                            kind,
-                                            replicate,
-                                            replicate
-                                                .target::<C>(NetworkTarget::Only(vec![*client_id])),
-                                            system_bevy_ticks.this_run(),
-                                        )
-                                        .map_err(|e| {
-                                            error!("error sending component remove: {:?}", e);
-                                        });
-                                }
-                            }
-                        },
-                    )
-                }
-                ReplicationMode::NetworkTarget => {
-                    trace!("sending component remove!");
-                    let _ = sender
-                        .prepare_component_remove(
-                            entity,
-                            kind,
-                            replicate,
-                            replicate.target::<C>(replicate.replication_target.clone()),
-                            system_bevy_ticks.this_run(),
-                        )
-                        .map_err(|e| {
-                            error!("error sending component remove: {:?}", e);
-                        });
+        }
+    });
+}
+
+/// This system sends the removals of the components registered with
+/// [`AppRegistrationExt::register_component`](crate::protocol::registry::AppRegistrationExt::register_component)
+fn send_registered_component_removed<P: Protocol, R: ReplicationSend<P>>(
+    world: &mut World,
+    query: &mut QueryState<&Replicate<P>>,
+    mut readers: Local<HashMap<NetId, ManualEventReader<RemovedComponentEntity>>>,
+) {
+    let this_run = world.change_tick();
+    world.resource_scope(|world: &mut World, mut sender: Mut<R>| {
+        let Some(registry) = world.get_resource::<ProtocolRegistry>() else {
+            return;
+        };
+        for (net_id, registration) in registry.component_registrations() {
+            let kind = registered_kind::<P>(net_id);
+            let reader = readers.entry(net_id).or_default();
+            for entity in (registration.removed)(world, reader) {
+                if let Ok(replicate) = query.get(world, entity) {
+                    replicate_component_removed(sender.as_mut(), entity, kind, replicate, this_run);
                 }
             }
         }
-    })
+    });
 }
 
 // add replication systems that are shared between client and server
@@ -454,6 +580,10 @@ pub fn add_replication_send_systems<P: Protocol, R: ReplicationSend<P>>(app: &mu
             )
                 .chain()
                 .in_set(ReplicationSet::SendDespawnsAndRemovals),
+            // systems for the components registered at runtime, which mirror the per-component systems
+            send_registered_component_removed::<P, R>
+                .in_set(ReplicationSet::SendDespawnsAndRemovals),
+            send_registered_component_updates::<P, R>.in_set(ReplicationSet::SendComponentUpdates),
         ),
     );
 }
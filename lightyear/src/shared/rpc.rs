@@ -0,0 +1,268 @@
+//! Request/response messages (RPC) built on top of the regular messages.
+//!
+//! A [`Request`] is a message that expects a [`Request::Response`] from the remote peer. Requests are sent on
+//! a reliable channel with a [`RequestId`], and the response is sent back on the same channel with the same id,
+//! so that users don't need to add their own correlation ids to the messages.
+//! If no response is received within [`Request::TIMEOUT`] (or if the response has the wrong type), a `RequestTimeoutEvent` is emitted.
+//!
+//! At most [`MAX_UNREAD_PER_KIND`] requests (and responses) of each kind are kept until they are read:
+//! the ones received after that are dropped, so a peer cannot fill the memory with requests that are never read.
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use bevy::utils::HashMap;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error, trace, warn};
+
+use crate::packet::message::Message;
+use crate::packet::message_manager::MessageManager;
+use crate::protocol::channel::ChannelKind;
+use crate::protocol::message::{MessageKind, MessageProtocol};
+
+/// Maximum number of unread requests (and responses) of each kind that are kept
+pub const MAX_UNREAD_PER_KIND: usize = 1024;
+
+/// A message that expects a response from the remote peer.
+///
+/// Both the request and the response must be part of the protocol's message enum.
+pub trait Request: Message {
+    /// The message that is sent back in response to this request
+    type Response: Message;
+
+    /// How long to wait for the response before emitting a `RequestTimeoutEvent`
+    const TIMEOUT: Duration = Duration::from_secs(10);
+}
+
+/// Identifies a request sent on a connection; the response to the request carries the same id
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RequestId(pub u32);
+
+/// A request received from the remote peer, that can be answered with a response
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReceivedRequest<Ctx = ()> {
+    pub(crate) request_id: RequestId,
+    /// The response is sent on the same channel as the request
+    pub(crate) channel: ChannelKind,
+    context: Ctx,
+}
+
+impl<Ctx> ReceivedRequest<Ctx> {
+    pub(crate) fn new(request_id: RequestId, channel: ChannelKind, context: Ctx) -> Self {
+        Self {
+            request_id,
+            channel,
+            context,
+        }
+    }
+
+    pub fn request_id(&self) -> RequestId {
+        self.request_id
+    }
+
+    pub fn context(&self) -> &Ctx {
+        &self.context
+    }
+}
+
+/// Requests can only be sent on reliable channels, so that a missing response means that the remote peer
+/// did not answer (rather than that the request or the response was lost)
+pub(crate) fn check_request_channel(
+    message_manager: &MessageManager,
+    channel: ChannelKind,
+) -> Result<()> {
+    let container = message_manager
+        .channels
+        .get(&channel)
+        .context("channel not found")?;
+    anyhow::ensure!(
+        container.setting.mode.is_reliable(),
+        "requests must be sent on a reliable channel"
+    );
+    Ok(())
+}
+
+#[derive(Debug)]
+struct PendingRequest {
+    request_kind: MessageKind,
+    response_kind: MessageKind,
+    /// Time left before the request times out
+    remaining: Duration,
+}
+
+/// Keeps track of the requests and responses of a connection
+#[derive(Debug)]
+pub(crate) struct RpcManager<M: MessageProtocol> {
+    next_request_id: RequestId,
+    /// Requests that we sent and that are still waiting for a response
+    pending_requests: HashMap<RequestId, PendingRequest>,
+    /// Requests received from the remote peer that haven't been read yet
+    received_requests: HashMap<MessageKind, Vec<(RequestId, ChannelKind, M)>>,
+    /// Responses received from the remote peer that haven't been read yet, by kind of the request
+    received_responses: HashMap<MessageKind, Vec<(RequestId, M)>>,
+    /// Requests that received a response of the wrong type; they are reported with the requests that timed out
+    failed_requests: Vec<RequestId>,
+}
+
+impl<M: MessageProtocol> Default for RpcManager<M> {
+    fn default() -> Self {
+        Self {
+            next_request_id: RequestId(0),
+            pending_requests: HashMap::default(),
+            received_requests: HashMap::default(),
+            received_responses: HashMap::default(),
+            failed_requests: vec![],
+        }
+    }
+}
+
+impl<M: MessageProtocol> RpcManager<M> {
+    /// Allocate the id of a new request and start waiting for its response
+    pub(crate) fn new_request<R: Request>(&mut self) -> RequestId {
+        let request_id = self.next_request_id;
+        self.next_request_id = RequestId(request_id.0.wrapping_add(1));
+        self.pending_requests.insert(
+            request_id,
+            PendingRequest {
+                request_kind: MessageKind::of::<R>(),
+                response_kind: MessageKind::of::<R::Response>(),
+                remaining: R::TIMEOUT,
+            },
+        );
+        request_id
+    }
+
+    /// Advance the timers of the pending requests, and return the requests that timed out
+    /// (or that received a response of the wrong type)
+    pub(crate) fn update(&mut self, delta: Duration) -> Vec<RequestId> {
+        let mut timed_out = std::mem::take(&mut self.failed_requests);
+        self.pending_requests.retain(|request_id, pending| {
+            match pending.remaining.checked_sub(delta) {
+                Some(remaining) if !remaining.is_zero() => {
+                    pending.remaining = remaining;
+                    true
+                }
+                _ => {
+                    debug!(?request_id, "Request timed out");
+                    timed_out.push(*request_id);
+                    false
+                }
+            }
+        });
+        timed_out
+    }
+
+    pub(crate) fn recv_request(&mut self, request_id: RequestId, channel: ChannelKind, request: M) {
+        trace!(?request_id, request = ?request.name(), "Received request");
+        let requests = self.received_requests.entry(request.kind()).or_default();
+        if requests.len() >= MAX_UNREAD_PER_KIND {
+            warn!(?request_id, request = ?request.name(), "Too many unread requests, dropping the request");
+            return;
+        }
+        requests.push((request_id, channel, request));
+    }
+
+    pub(crate) fn recv_response(&mut self, request_id: RequestId, response: M) {
+        let Some(pending) = self.pending_requests.remove(&request_id) else {
+            // the request might have timed out already
+            debug!(?request_id, "Received a response for an unknown request");
+            return;
+        };
+        if pending.response_kind != response.kind() {
+            error!(?request_id, response = ?response.name(), "Received a response of the wrong type");
+            self.failed_requests.push(request_id);
+            return;
+        }
+        trace!(?request_id, response = ?response.name(), "Received response");
+        let responses = self
+            .received_responses
+            .entry(pending.request_kind)
+            .or_default();
+        if responses.len() >= MAX_UNREAD_PER_KIND {
+            warn!(?request_id, response = ?response.name(), "Too many unread responses, dropping the response");
+            return;
+        }
+        responses.push((request_id, response));
+    }
+
+    /// Take the requests of type `R` that we received
+    pub(crate) fn drain_requests<R: Request>(&mut self) -> Vec<(RequestId, ChannelKind, R)>
+    where
+        M: TryInto<R, Error = ()>,
+    {
+        self.received_requests
+            .remove(&MessageKind::of::<R>())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(request_id, channel, request)| {
+                (request_id, channel, request.try_into().unwrap())
+            })
+            .collect()
+    }
+
+    /// Take the responses that we received to our requests of type `R`
+    pub(crate) fn drain_responses<R: Request>(&mut self) -> Vec<(RequestId, R::Response)>
+    where
+        M: TryInto<R::Response, Error = ()>,
+    {
+        self.received_responses
+            .remove(&MessageKind::of::<R>())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(request_id, response)| (request_id, response.try_into().unwrap()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::protocol::{Channel3, Message1, Message2, MyMessageProtocol};
+
+    use super::*;
+
+    #[test]
+    fn test_rpc_manager() {
+        let mut manager = RpcManager::<MyMessageProtocol>::default();
+        let first = manager.new_request::<Message1>();
+        let second = manager.new_request::<Message1>();
+        assert_ne!(first, second);
+
+        // responses of the wrong type or to unknown requests are dropped
+        manager.recv_response(first, Message1("wrong".to_string()).into());
+        manager.recv_response(RequestId(100), Message2(0).into());
+        assert!(manager.drain_responses::<Message1>().is_empty());
+
+        manager.recv_response(second, Message2(2).into());
+        assert_eq!(
+            manager.drain_responses::<Message1>(),
+            vec![(second, Message2(2))]
+        );
+        // the request that received a response of the wrong type is reported as failed right away
+        assert_eq!(manager.update(Duration::ZERO), vec![first]);
+        assert!(manager.update(Message1::TIMEOUT).is_empty());
+
+        let third = manager.new_request::<Message1>();
+        assert!(manager.update(Message1::TIMEOUT / 2).is_empty());
+        assert_eq!(manager.update(Message1::TIMEOUT / 2), vec![third]);
+        // a late response is dropped
+        manager.recv_response(third, Message2(3).into());
+        assert!(manager.drain_responses::<Message1>().is_empty());
+    }
+
+    #[test]
+    fn test_unread_requests_limit() {
+        let mut manager = RpcManager::<MyMessageProtocol>::default();
+        for i in 0..MAX_UNREAD_PER_KIND + 10 {
+            manager.recv_request(
+                RequestId(i as u32),
+                ChannelKind::of::<Channel3>(),
+                Message1(i.to_string()).into(),
+            );
+        }
+        let requests = manager.drain_requests::<Message1>();
+        assert_eq!(requests.len(), MAX_UNREAD_PER_KIND);
+        assert_eq!(
+            requests.last().unwrap().0,
+            RequestId(MAX_UNREAD_PER_KIND as u32 - 1)
+        );
+    }
+}
@@ -0,0 +1,681 @@
+//! Streaming of large payloads (level downloads, replays, user-generated content...).
+//!
+//! Fragmented messages are limited in size and are sent all at once. Instead, a stream splits a byte payload
+//! into chunks that are sent as reliable messages, keeping at most [`MAX_CHUNKS_IN_FLIGHT`] chunks of a transfer
+//! waiting for an ack at any time. When the stream is sent on a [`ChannelMode::Streaming`](crate::prelude::ChannelMode::Streaming)
+//! channel, the chunks are also paced to respect the bandwidth cap of the channel.
+//!
+//! Lost chunks are re-sent by the reliable channel, and both sides emit [`TransferProgress`] updates.
+//! Either side can cancel the transfer.
+//!
+//! The receiver stores the chunks as they arrive, and rejects transfers bigger than the
+//! [`max_transfer_size`](StreamSettings::max_transfer_size) of the channel, or that would exceed its
+//! [`max_incoming_transfers`](StreamSettings::max_incoming_transfers). Streams sent on other reliable channels
+//! use the limits of the default [`StreamSettings`].
+use anyhow::{Context, Result};
+use bevy::utils::{HashMap, HashSet};
+use bytes::Bytes;
+use crossbeam_channel::Receiver;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use tracing::{debug, error, trace};
+
+use crate::channel::builder::{ChannelMode, StreamSettings};
+use crate::channel::senders::ChannelSend;
+use crate::packet::message::MessageId;
+use crate::packet::message_manager::MessageManager;
+use crate::packet::packet::FRAGMENT_SIZE;
+use crate::protocol::channel::ChannelKind;
+
+/// Number of bytes of payload in each chunk, so that a chunk fits in a single packet
+pub(crate) const CHUNK_SIZE: usize = FRAGMENT_SIZE - 64;
+/// Maximum number of chunks of a transfer that have been sent but not acked yet
+pub(crate) const MAX_CHUNKS_IN_FLIGHT: usize = 64;
+/// Number of cancelled incoming transfers that are remembered to ignore their remaining chunks
+const MAX_CANCELLED_INCOMING: usize = 1024;
+
+/// Identifies a transfer in one direction of a connection
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TransferId(pub u32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TransferDirection {
+    /// We are sending the payload
+    Send,
+    /// We are receiving the payload
+    Receive,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferStatus {
+    InProgress,
+    /// All the bytes were received by the remote (for [`TransferDirection::Send`]), or the payload
+    /// was fully received (for [`TransferDirection::Receive`])
+    Completed,
+    /// The transfer was cancelled by one of the sides
+    Cancelled,
+}
+
+/// Progress of a transfer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransferProgress {
+    pub transfer_id: TransferId,
+    pub direction: TransferDirection,
+    pub status: TransferStatus,
+    /// Number of bytes acked by the remote (when sending) or received (when receiving)
+    pub bytes_transferred: usize,
+    pub total_bytes: usize,
+}
+
+impl TransferProgress {
+    /// Fraction of the payload that has been transferred, between 0.0 and 1.0
+    pub fn fraction(&self) -> f32 {
+        self.bytes_transferred as f32 / self.total_bytes as f32
+    }
+}
+
+/// Messages exchanged by the [`StreamManager`]s of the two sides of a connection
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum StreamMessage {
+    Chunk {
+        transfer_id: TransferId,
+        total_bytes: u32,
+        offset: u32,
+        bytes: Vec<u8>,
+    },
+    /// The sender cancelled the transfer
+    Cancel(TransferId),
+    /// The receiver cancelled the transfer
+    Reject(TransferId),
+}
+
+#[derive(Debug)]
+struct OutgoingTransfer {
+    channel: ChannelKind,
+    bytes: Bytes,
+    /// Offset of the next chunk to send
+    next_offset: usize,
+    acked_bytes: usize,
+    chunks_in_flight: usize,
+}
+
+#[derive(Debug)]
+struct IncomingTransfer {
+    channel: ChannelKind,
+    total_bytes: usize,
+    /// Chunks received so far, by index. The payload is only assembled once every chunk is received,
+    /// so that the memory used grows with the bytes actually received
+    chunks: BTreeMap<usize, Vec<u8>>,
+    received_bytes: usize,
+}
+
+/// Sends and receives the streams of a connection
+#[derive(Debug, Default)]
+pub(crate) struct StreamManager {
+    next_transfer_id: u32,
+    outgoing: HashMap<TransferId, OutgoingTransfer>,
+    incoming: HashMap<TransferId, IncomingTransfer>,
+    /// Incoming transfers that were cancelled; the chunks that are still in flight are ignored.
+    /// Only the last [`MAX_CANCELLED_INCOMING`] transfers are kept, in the order they were cancelled
+    cancelled_incoming: HashSet<TransferId>,
+    cancelled_incoming_order: VecDeque<TransferId>,
+    /// Acks of the channels used to send the streams
+    ack_receivers: HashMap<ChannelKind, Receiver<MessageId>>,
+    /// Chunks that were sent and are waiting for an ack, with their transfer and size
+    chunks_in_flight: HashMap<(ChannelKind, MessageId), (TransferId, usize)>,
+    /// Cancel/Reject messages that need to be sent
+    control_messages: Vec<(ChannelKind, StreamMessage)>,
+    /// Transfers that progressed since the last call to `drain_progress`
+    updated: HashSet<(TransferId, TransferDirection)>,
+    /// Transfers that completed or were cancelled since the last call to `drain_progress`
+    finished: Vec<TransferProgress>,
+    /// Payloads that were fully received
+    received: Vec<(TransferId, Bytes)>,
+}
+
+impl StreamManager {
+    /// Start sending `bytes` on the reliable channel `channel`
+    pub(crate) fn send(
+        &mut self,
+        message_manager: &mut MessageManager,
+        channel: ChannelKind,
+        bytes: Bytes,
+    ) -> Result<TransferId> {
+        anyhow::ensure!(!bytes.is_empty(), "cannot stream an empty payload");
+        // the remote rejects streams bigger than the limit of the channel
+        anyhow::ensure!(
+            bytes.len() <= Self::settings(message_manager, channel).max_transfer_size,
+            "the payload is bigger than the maximum transfer size of the channel"
+        );
+        let container = message_manager
+            .channels
+            .get_mut(&channel)
+            .context("channel not found")?;
+        anyhow::ensure!(
+            container.setting.mode.is_reliable(),
+            "streams must be sent on a reliable channel"
+        );
+        self.ack_receivers
+            .entry(channel)
+            .or_insert_with(|| container.sender.subscribe_acks());
+
+        let transfer_id = TransferId(self.next_transfer_id);
+        self.next_transfer_id = self.next_transfer_id.wrapping_add(1);
+        debug!(?transfer_id, len = bytes.len(), "Start sending stream");
+        self.outgoing.insert(
+            transfer_id,
+            OutgoingTransfer {
+                channel,
+                bytes,
+                next_offset: 0,
+                acked_bytes: 0,
+                chunks_in_flight: 0,
+            },
+        );
+        Ok(transfer_id)
+    }
+
+    /// Cancel a transfer. The remote is notified of the cancellation
+    pub(crate) fn cancel(
+        &mut self,
+        transfer_id: TransferId,
+        direction: TransferDirection,
+    ) -> Result<()> {
+        let progress = match direction {
+            TransferDirection::Send => {
+                let transfer = self
+                    .outgoing
+                    .remove(&transfer_id)
+                    .context("transfer not found")?;
+                self.control_messages
+                    .push((transfer.channel, StreamMessage::Cancel(transfer_id)));
+                TransferProgress {
+                    transfer_id,
+                    direction,
+                    status: TransferStatus::Cancelled,
+                    bytes_transferred: transfer.acked_bytes,
+                    total_bytes: transfer.bytes.len(),
+                }
+            }
+            TransferDirection::Receive => {
+                let transfer = self
+                    .incoming
+                    .remove(&transfer_id)
+                    .context("transfer not found")?;
+                self.reject(transfer.channel, transfer_id);
+                TransferProgress {
+                    transfer_id,
+                    direction,
+                    status: TransferStatus::Cancelled,
+                    bytes_transferred: transfer.received_bytes,
+                    total_bytes: transfer.total_bytes,
+                }
+            }
+        };
+        self.finish(progress);
+        Ok(())
+    }
+
+    /// Buffer the messages to send: the control messages, and the next chunks of each outgoing transfer
+    ///
+    /// `buffer` buffers a message on a channel and returns its [`MessageId`]
+    pub(crate) fn send_chunks(
+        &mut self,
+        mut buffer: impl FnMut(ChannelKind, StreamMessage) -> Result<Option<MessageId>>,
+    ) -> Result<()> {
+        for (channel, message) in self.control_messages.drain(..) {
+            buffer(channel, message)?;
+        }
+        for (transfer_id, transfer) in self.outgoing.iter_mut() {
+            while transfer.chunks_in_flight < MAX_CHUNKS_IN_FLIGHT
+                && transfer.next_offset < transfer.bytes.len()
+            {
+                let offset = transfer.next_offset;
+                let end = (offset + CHUNK_SIZE).min(transfer.bytes.len());
+                let message = StreamMessage::Chunk {
+                    transfer_id: *transfer_id,
+                    total_bytes: transfer.bytes.len() as u32,
+                    offset: offset as u32,
+                    bytes: transfer.bytes[offset..end].to_vec(),
+                };
+                let message_id = buffer(transfer.channel, message)?
+                    .context("streams must be sent on a reliable channel")?;
+                self.chunks_in_flight
+                    .insert((transfer.channel, message_id), (*transfer_id, end - offset));
+                transfer.next_offset = end;
+                transfer.chunks_in_flight += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Handle the acks of the chunks that we sent
+    pub(crate) fn process_acks(&mut self) {
+        for (channel, receiver) in self.ack_receivers.iter() {
+            while let Ok(message_id) = receiver.try_recv() {
+                // the channel can also be used for other messages
+                let Some((transfer_id, len)) =
+                    self.chunks_in_flight.remove(&(*channel, message_id))
+                else {
+                    continue;
+                };
+                // the transfer might have been cancelled
+                let Some(transfer) = self.outgoing.get_mut(&transfer_id) else {
+                    continue;
+                };
+                transfer.acked_bytes += len;
+                transfer.chunks_in_flight -= 1;
+                self.updated.insert((transfer_id, TransferDirection::Send));
+            }
+        }
+        let completed: Vec<TransferId> = self
+            .outgoing
+            .iter()
+            .filter(|(_, transfer)| transfer.acked_bytes == transfer.bytes.len())
+            .map(|(transfer_id, _)| *transfer_id)
+            .collect();
+        for transfer_id in completed {
+            let transfer = self.outgoing.remove(&transfer_id).unwrap();
+            debug!(?transfer_id, "Stream sent");
+            self.finish(TransferProgress {
+                transfer_id,
+                direction: TransferDirection::Send,
+                status: TransferStatus::Completed,
+                bytes_transferred: transfer.acked_bytes,
+                total_bytes: transfer.bytes.len(),
+            });
+        }
+    }
+
+    /// Limits of the streams of `channel`: the [`StreamSettings`] of a [`ChannelMode::Streaming`] channel,
+    /// or the default ones for the other channels
+    pub(crate) fn settings(
+        message_manager: &MessageManager,
+        channel: ChannelKind,
+    ) -> StreamSettings {
+        match message_manager
+            .channels
+            .get(&channel)
+            .map(|container| &container.setting.mode)
+        {
+            Some(ChannelMode::Streaming(settings)) => settings.clone(),
+            _ => StreamSettings::default(),
+        }
+    }
+
+    /// Handle a stream message received from the remote on `channel`, whose limits are `settings`
+    pub(crate) fn recv_message(
+        &mut self,
+        settings: &StreamSettings,
+        channel: ChannelKind,
+        message: StreamMessage,
+    ) {
+        match message {
+            StreamMessage::Chunk {
+                transfer_id,
+                total_bytes,
+                offset,
+                bytes,
+            } => self.recv_chunk(
+                settings,
+                channel,
+                transfer_id,
+                total_bytes as usize,
+                offset as usize,
+                bytes,
+            ),
+            StreamMessage::Cancel(transfer_id) => {
+                debug!(?transfer_id, "The sender cancelled the stream");
+                self.mark_cancelled(transfer_id);
+                if let Some(transfer) = self.incoming.remove(&transfer_id) {
+                    self.finish(TransferProgress {
+                        transfer_id,
+                        direction: TransferDirection::Receive,
+                        status: TransferStatus::Cancelled,
+                        bytes_transferred: transfer.received_bytes,
+                        total_bytes: transfer.total_bytes,
+                    });
+                }
+            }
+            StreamMessage::Reject(transfer_id) => {
+                debug!(?transfer_id, "The receiver cancelled the stream");
+                if let Some(transfer) = self.outgoing.remove(&transfer_id) {
+                    self.finish(TransferProgress {
+                        transfer_id,
+                        direction: TransferDirection::Send,
+                        status: TransferStatus::Cancelled,
+                        bytes_transferred: transfer.acked_bytes,
+                        total_bytes: transfer.bytes.len(),
+                    });
+                }
+            }
+        }
+    }
+
+    fn recv_chunk(
+        &mut self,
+        settings: &StreamSettings,
+        channel: ChannelKind,
+        transfer_id: TransferId,
+        total_bytes: usize,
+        offset: usize,
+        bytes: Vec<u8>,
+    ) {
+        if self.cancelled_incoming.contains(&transfer_id) {
+            trace!(?transfer_id, "Ignoring chunk of a cancelled stream");
+            return;
+        }
+        // check the size declared by the sender before buffering anything
+        if total_bytes == 0 || total_bytes > settings.max_transfer_size {
+            error!(
+                ?transfer_id,
+                total_bytes, "Rejecting stream with an invalid size"
+            );
+            self.reject(channel, transfer_id);
+            return;
+        }
+        let expected_len = CHUNK_SIZE.min(total_bytes.saturating_sub(offset));
+        if offset % CHUNK_SIZE != 0 || offset >= total_bytes || bytes.len() != expected_len {
+            error!(?transfer_id, offset, "Received an invalid stream chunk");
+            return;
+        }
+        if !self.incoming.contains_key(&transfer_id) {
+            let in_progress = self
+                .incoming
+                .values()
+                .filter(|transfer| transfer.channel == channel)
+                .count();
+            if in_progress >= settings.max_incoming_transfers {
+                error!(
+                    ?transfer_id,
+                    "Rejecting stream: too many incoming streams in progress"
+                );
+                self.reject(channel, transfer_id);
+                return;
+            }
+            self.incoming.insert(
+                transfer_id,
+                IncomingTransfer {
+                    channel,
+                    total_bytes,
+                    chunks: BTreeMap::new(),
+                    received_bytes: 0,
+                },
+            );
+        }
+        let transfer = self.incoming.get_mut(&transfer_id).unwrap();
+        if total_bytes != transfer.total_bytes {
+            error!(?transfer_id, offset, "Received an invalid stream chunk");
+            return;
+        }
+        let index = offset / CHUNK_SIZE;
+        if transfer.chunks.contains_key(&index) {
+            return;
+        }
+        transfer.received_bytes += bytes.len();
+        transfer.chunks.insert(index, bytes);
+        self.updated
+            .insert((transfer_id, TransferDirection::Receive));
+
+        if transfer.received_bytes == total_bytes {
+            let transfer = self.incoming.remove(&transfer_id).unwrap();
+            debug!(?transfer_id, "Stream received");
+            let mut payload = Vec::with_capacity(total_bytes);
+            for chunk in transfer.chunks.into_values() {
+                payload.extend_from_slice(&chunk);
+            }
+            self.received.push((transfer_id, Bytes::from(payload)));
+            self.finish(TransferProgress {
+                transfer_id,
+                direction: TransferDirection::Receive,
+                status: TransferStatus::Completed,
+                bytes_transferred: total_bytes,
+                total_bytes,
+            });
+        }
+    }
+
+    /// Reject an incoming transfer: the sender is notified, and the remaining chunks are ignored
+    fn reject(&mut self, channel: ChannelKind, transfer_id: TransferId) {
+        self.mark_cancelled(transfer_id);
+        self.control_messages
+            .push((channel, StreamMessage::Reject(transfer_id)));
+    }
+
+    fn mark_cancelled(&mut self, transfer_id: TransferId) {
+        if !self.cancelled_incoming.insert(transfer_id) {
+            return;
+        }
+        self.cancelled_incoming_order.push_back(transfer_id);
+        if self.cancelled_incoming_order.len() > MAX_CANCELLED_INCOMING {
+            let oldest = self.cancelled_incoming_order.pop_front().unwrap();
+            self.cancelled_incoming.remove(&oldest);
+        }
+    }
+
+    fn finish(&mut self, progress: TransferProgress) {
+        self.updated
+            .remove(&(progress.transfer_id, progress.direction));
+        self.finished.push(progress);
+    }
+
+    /// Progress of the transfers that were updated since the last call
+    pub(crate) fn drain_progress(&mut self) -> Vec<TransferProgress> {
+        let mut progress: Vec<TransferProgress> = self
+            .updated
+            .drain()
+            .filter_map(|(transfer_id, direction)| match direction {
+                TransferDirection::Send => {
+                    self.outgoing
+                        .get(&transfer_id)
+                        .map(|transfer| TransferProgress {
+                            transfer_id,
+                            direction,
+                            status: TransferStatus::InProgress,
+                            bytes_transferred: transfer.acked_bytes,
+                            total_bytes: transfer.bytes.len(),
+                        })
+                }
+                TransferDirection::Receive => {
+                    self.incoming
+                        .get(&transfer_id)
+                        .map(|transfer| TransferProgress {
+                            transfer_id,
+                            direction,
+                            status: TransferStatus::InProgress,
+                            bytes_transferred: transfer.received_bytes,
+                            total_bytes: transfer.total_bytes,
+                        })
+                }
+            })
+            .collect();
+        progress.append(&mut self.finished);
+        progress
+    }
+
+    /// Payloads that were fully received since the last call
+    pub(crate) fn drain_received(&mut self) -> Vec<(TransferId, Bytes)> {
+        std::mem::take(&mut self.received)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Send all the messages buffered by `sender` to `receiver`, and return the ids of the chunks
+    fn transmit(
+        sender: &mut StreamManager,
+        receiver: &mut StreamManager,
+        next_message_id: &mut u16,
+    ) -> Vec<MessageId> {
+        let channel = ChannelKind::from(std::any::TypeId::of::<()>());
+        let mut messages = vec![];
+        sender
+            .send_chunks(|_, message| {
+                messages.push(message);
+                let message_id = MessageId(*next_message_id);
+                *next_message_id += 1;
+                Ok(Some(message_id))
+            })
+            .unwrap();
+        let mut chunk_ids = vec![];
+        for (i, message) in messages.into_iter().enumerate() {
+            if matches!(message, StreamMessage::Chunk { .. }) {
+                chunk_ids.push(MessageId(*next_message_id - 1 - i as u16));
+            }
+            receiver.recv_message(&StreamSettings::default(), channel, message);
+        }
+        chunk_ids
+    }
+
+    #[test]
+    fn test_stream_receive() {
+        let channel = ChannelKind::from(std::any::TypeId::of::<()>());
+        let payload: Vec<u8> = (0..CHUNK_SIZE * 2 + 10).map(|i| i as u8).collect();
+        let mut sender = StreamManager::default();
+        sender.outgoing.insert(
+            TransferId(0),
+            OutgoingTransfer {
+                channel,
+                bytes: Bytes::from(payload.clone()),
+                next_offset: 0,
+                acked_bytes: 0,
+                chunks_in_flight: 0,
+            },
+        );
+        let mut receiver = StreamManager::default();
+        let mut next_message_id = 0;
+        transmit(&mut sender, &mut receiver, &mut next_message_id);
+        assert_eq!(sender.chunks_in_flight.len(), 3);
+
+        assert_eq!(
+            receiver.drain_received(),
+            vec![(TransferId(0), Bytes::from(payload.clone()))]
+        );
+        assert_eq!(
+            receiver.drain_progress(),
+            vec![TransferProgress {
+                transfer_id: TransferId(0),
+                direction: TransferDirection::Receive,
+                status: TransferStatus::Completed,
+                bytes_transferred: payload.len(),
+                total_bytes: payload.len(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_stream_cancel() {
+        let channel = ChannelKind::from(std::any::TypeId::of::<()>());
+        let mut receiver = StreamManager::default();
+        receiver.recv_message(
+            &StreamSettings::default(),
+            channel,
+            StreamMessage::Chunk {
+                transfer_id: TransferId(0),
+                total_bytes: (CHUNK_SIZE * 2) as u32,
+                offset: 0,
+                bytes: vec![1; CHUNK_SIZE],
+            },
+        );
+        assert_eq!(
+            receiver.drain_progress(),
+            vec![TransferProgress {
+                transfer_id: TransferId(0),
+                direction: TransferDirection::Receive,
+                status: TransferStatus::InProgress,
+                bytes_transferred: CHUNK_SIZE,
+                total_bytes: CHUNK_SIZE * 2,
+            }]
+        );
+
+        // the receiver cancels the transfer: the sender is notified, and the remaining chunks are ignored
+        receiver
+            .cancel(TransferId(0), TransferDirection::Receive)
+            .unwrap();
+        assert_eq!(
+            receiver.control_messages,
+            vec![(channel, StreamMessage::Reject(TransferId(0)))]
+        );
+        receiver.recv_message(
+            &StreamSettings::default(),
+            channel,
+            StreamMessage::Chunk {
+                transfer_id: TransferId(0),
+                total_bytes: (CHUNK_SIZE * 2) as u32,
+                offset: CHUNK_SIZE as u32,
+                bytes: vec![1; CHUNK_SIZE],
+            },
+        );
+        assert!(receiver.drain_received().is_empty());
+        let progress = receiver.drain_progress();
+        assert_eq!(progress.len(), 1);
+        assert_eq!(progress[0].status, TransferStatus::Cancelled);
+    }
+
+    #[test]
+    fn test_stream_reject_invalid() {
+        let channel = ChannelKind::from(std::any::TypeId::of::<()>());
+        let settings = StreamSettings {
+            max_transfer_size: CHUNK_SIZE * 4,
+            max_incoming_transfers: 3,
+            ..StreamSettings::default()
+        };
+        let mut receiver = StreamManager::default();
+        let chunk = |transfer_id: u32, total_bytes: usize| StreamMessage::Chunk {
+            transfer_id: TransferId(transfer_id),
+            total_bytes: total_bytes as u32,
+            offset: 0,
+            bytes: vec![1; CHUNK_SIZE.min(total_bytes)],
+        };
+
+        // empty transfers are rejected without being tracked
+        receiver.recv_message(&settings, channel, chunk(0, 0));
+        assert!(receiver.incoming.is_empty());
+        assert_eq!(
+            receiver.control_messages,
+            vec![(channel, StreamMessage::Reject(TransferId(0)))]
+        );
+        receiver.control_messages.clear();
+
+        // transfers that declare a size bigger than the limit are rejected before buffering any chunk
+        receiver.recv_message(&settings, channel, chunk(1, CHUNK_SIZE * 4 + 1));
+        assert!(receiver.incoming.is_empty());
+        assert_eq!(
+            receiver.control_messages,
+            vec![(channel, StreamMessage::Reject(TransferId(1)))]
+        );
+        receiver.control_messages.clear();
+
+        // only a limited number of transfers can be in progress at the same time
+        for i in 2..5 {
+            receiver.recv_message(&settings, channel, chunk(i, CHUNK_SIZE * 2));
+        }
+        assert_eq!(receiver.incoming.len(), 3);
+        assert!(receiver.control_messages.is_empty());
+        receiver.recv_message(&settings, channel, chunk(5, CHUNK_SIZE * 2));
+        assert_eq!(receiver.incoming.len(), 3);
+        assert_eq!(
+            receiver.control_messages,
+            vec![(channel, StreamMessage::Reject(TransferId(5)))]
+        );
+    }
+
+    #[test]
+    fn test_cancelled_incoming_pruned() {
+        let mut receiver = StreamManager::default();
+        for i in 0..(MAX_CANCELLED_INCOMING + 10) as u32 {
+            receiver.mark_cancelled(TransferId(i));
+        }
+        assert_eq!(receiver.cancelled_incoming.len(), MAX_CANCELLED_INCOMING);
+        assert_eq!(
+            receiver.cancelled_incoming_order.len(),
+            MAX_CANCELLED_INCOMING
+        );
+        assert!(!receiver.cancelled_incoming.contains(&TransferId(0)));
+        assert!(receiver
+            .cancelled_incoming
+            .contains(&TransferId((MAX_CANCELLED_INCOMING + 9) as u32)));
+    }
+}
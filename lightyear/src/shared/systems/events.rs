@@ -1,10 +1,14 @@
 use bevy::prelude::{Component, Events, World};
+use tracing::error;
 
 use crate::_reexport::FromType;
 use crate::connection::events::{
     IterComponentInsertEvent, IterComponentRemoveEvent, IterComponentUpdateEvent, IterMessageEvent,
 };
 use crate::packet::message::Message;
+use crate::protocol::registry::{
+    registered_kind, DynamicMessage, ProtocolRegistry, RegistryContext,
+};
 use crate::protocol::{EventContext, Protocol};
 use crate::shared::events::{
     ComponentInsertEvent, ComponentRemoveEvent, ComponentUpdateEvent, MessageEvent,
@@ -102,3 +106,80 @@ pub fn push_component_update_events<
         }
     }
 }
+
+/// Write the [`MessageEvent`]s of the messages registered with
+/// [`AppRegistrationExt::register_message`](crate::protocol::registry::AppRegistrationExt::register_message)
+pub(crate) fn push_registered_message_events<
+    P: Protocol,
+    E: IterMessageEvent<P, Ctx>,
+    Ctx: RegistryContext,
+>(
+    world: &mut World,
+    events: &mut E,
+) {
+    if !events.has_messages::<DynamicMessage>() {
+        return;
+    }
+    let registry = world.get_resource::<ProtocolRegistry>();
+    let messages = events
+        .into_iter_messages::<DynamicMessage>()
+        .map(|(message, ctx)| {
+            let push_event = registry
+                .and_then(|registry| registry.message_registration(message.net_id))
+                .map(Ctx::message_event);
+            (message, ctx, push_event)
+        })
+        .collect::<Vec<_>>();
+    for (message, ctx, push_event) in messages {
+        let Some(push_event) = push_event else {
+            error!(net_id = ?message.net_id, "received a message that is not registered");
+            continue;
+        };
+        push_event(world, message.value, ctx).unwrap_or_else(|e| {
+            error!("could not read registered message: {:?}", e);
+        });
+    }
+}
+
+/// Write the component events of the components registered with
+/// [`AppRegistrationExt::register_component`](crate::protocol::registry::AppRegistrationExt::register_component)
+pub(crate) fn push_registered_component_events<
+    P: Protocol,
+    E: IterComponentInsertEvent<P, Ctx>
+        + IterComponentRemoveEvent<P, Ctx>
+        + IterComponentUpdateEvent<P, Ctx>,
+    Ctx: RegistryContext,
+>(
+    world: &mut World,
+    events: &mut E,
+) {
+    let Some(registry) = world.get_resource::<ProtocolRegistry>() else {
+        return;
+    };
+    let registrations = registry
+        .component_registrations()
+        .map(|(net_id, registration)| {
+            (
+                registered_kind::<P>(net_id),
+                Ctx::component_events(registration),
+            )
+        })
+        .collect::<Vec<_>>();
+    for (kind, push_events) in registrations {
+        if events.has_component_insert_kind(kind) {
+            for (entity, ctx) in events.iter_component_insert_kind(kind) {
+                (push_events.insert)(world, entity, ctx);
+            }
+        }
+        if events.has_component_remove_kind(kind) {
+            for (entity, ctx) in events.iter_component_remove_kind(kind) {
+                (push_events.remove)(world, entity, ctx);
+            }
+        }
+        if events.has_component_update_kind(kind) {
+            for (entity, ctx) in events.iter_component_update_kind(kind) {
+                (push_events.update)(world, entity, ctx);
+            }
+        }
+    }
+}
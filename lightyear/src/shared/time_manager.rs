@@ -160,7 +160,7 @@ impl TimeManager {
 
 mod wrapped_time {
     use super::*;
-    use crate::_reexport::{ReadBuffer, WriteBuffer};
+    use crate::_reexport::{ReadBuffer, ReadWordBuffer, WriteBuffer, WriteWordBuffer};
     use crate::protocol::BitSerializable;
     use anyhow::Context;
     use bitcode::encoding::{Encoding, Fixed};
@@ -177,13 +177,13 @@ mod wrapped_time {
     // TODO: this lacks too much precision, use Duration but serialize only the millis.
 
     impl BitSerializable for WrappedTime {
-        fn encode(&self, writer: &mut impl WriteBuffer) -> anyhow::Result<()> {
+        fn encode(&self, writer: &mut WriteWordBuffer) -> anyhow::Result<()> {
             writer
                 .encode(self, Fixed)
                 .context("error encoding WrappedTime")
         }
 
-        fn decode(reader: &mut impl ReadBuffer) -> anyhow::Result<Self>
+        fn decode(reader: &mut ReadWordBuffer) -> anyhow::Result<Self>
         where
             Self: Sized,
         {
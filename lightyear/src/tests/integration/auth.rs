@@ -0,0 +1,121 @@
+use crate::netcode::{TokenServer, USER_DATA_BYTES};
+use crate::prelude::client::{
+    Authentication, InterpolationConfig, NetClient, PredictionConfig, SyncConfig,
+};
+use crate::prelude::server::NetServer;
+use crate::prelude::*;
+use crate::tests::protocol::{protocol, ClientConnectionManager, ClientMut};
+use crate::tests::stepper::{BevyStepper, Step};
+use bevy::ecs::system::SystemState;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+/// Check that a client can connect with a token fetched from a [`TokenServer`]
+#[test]
+fn test_remote_authentication() {
+    let frame_duration = Duration::from_millis(10);
+    let tick_duration = Duration::from_millis(10);
+    let shared_config = SharedConfig {
+        enable_replication: false,
+        tick: TickConfig::new(tick_duration),
+        ..Default::default()
+    };
+    let mut stepper = BevyStepper::new(
+        shared_config,
+        SyncConfig::default(),
+        PredictionConfig::default(),
+        InterpolationConfig::default(),
+        LinkConditionerConfig::default(),
+        frame_duration,
+    );
+    let token_server = TokenServer::new(
+        SocketAddr::from(([127, 0, 0, 1], 0)),
+        0,
+        stepper.private_key,
+        |_, credentials| (credentials == b"secret").then_some((42, [0; USER_DATA_BYTES])),
+    )
+    .start("127.0.0.1:0")
+    .unwrap();
+    stepper.set_client_auth(Authentication::Remote {
+        auth_addr: token_server.local_addr(),
+        credentials: b"secret".to_vec(),
+    });
+    stepper.init();
+    // the token is fetched in a background thread: the client connects once it is received
+    for _ in 0..500 {
+        if stepper
+            .client_app
+            .world
+            .resource::<ClientConnectionManager>()
+            .is_synced()
+        {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(1));
+        stepper.frame_step();
+    }
+    let mut client = SystemState::<ClientMut>::new(&mut stepper.client_app.world);
+    assert!(!client
+        .get_mut(&mut stepper.client_app.world)
+        .is_fetching_token());
+
+    assert!(stepper
+        .client_app
+        .world
+        .resource::<NetClient>()
+        .is_connected());
+    // the client got the id assigned by the token server
+    assert_eq!(
+        stepper
+            .server_app
+            .world
+            .resource::<NetServer>()
+            .connected_client_ids(),
+        vec![42]
+    );
+}
+
+/// Check that the server denies a client built with a different protocol
+#[test]
+fn test_protocol_mismatch() {
+    let frame_duration = Duration::from_millis(10);
+    let tick_duration = Duration::from_millis(10);
+    let shared_config = SharedConfig {
+        enable_replication: false,
+        tick: TickConfig::new(tick_duration),
+        ..Default::default()
+    };
+    let mut stepper = BevyStepper::new(
+        shared_config,
+        SyncConfig::default(),
+        PredictionConfig::default(),
+        InterpolationConfig::default(),
+        LinkConditionerConfig::default(),
+        frame_duration,
+    );
+    // replace the client's netcode with one that uses a different protocol fingerprint
+    let token = Authentication::Manual {
+        server_addr: SocketAddr::from(([127, 0, 0, 1], 0)),
+        protocol_id: 0,
+        private_key: stepper.private_key,
+        client_id: 111,
+    }
+    .get_token(10)
+    .unwrap();
+    let netcode = NetClient::with_config(
+        &token.try_into_bytes().unwrap(),
+        client::NetcodeConfig::default()
+            .build()
+            .protocol_fingerprint(protocol().fingerprint() + 1),
+    )
+    .unwrap();
+    stepper.client_app.insert_resource(netcode);
+    stepper.init();
+
+    let netclient = stepper.client_app.world.resource::<NetClient>();
+    assert!(!netclient.is_connected());
+    assert_eq!(netclient.deny_reason(), Some(DenyReason::ProtocolMismatch));
+    let netserver = stepper.server_app.world.resource::<NetServer>();
+    assert!(netserver.connected_client_ids().is_empty());
+    assert!(netserver.rejection_stats().protocol_mismatch > 0);
+}
@@ -0,0 +1,219 @@
+use crate::channel::receivers::ChannelReceive;
+use crate::packet::message::{FragmentData, MessageContainer, MessageId};
+use crate::prelude::client::{InterpolationConfig, NetClient, PredictionConfig, SyncConfig};
+use crate::prelude::server::NetServer;
+use crate::prelude::*;
+use crate::tests::protocol::*;
+use crate::tests::stepper::{BevyStepper, Step};
+use bevy::ecs::system::SystemState;
+use bevy::prelude::*;
+use std::time::Duration;
+
+/// Check that the reason of a disconnection sent by the server shows up on the
+/// `DisconnectEvent` of both the client and the server
+#[test]
+fn test_disconnect_reason() {
+    let frame_duration = Duration::from_millis(10);
+    let tick_duration = Duration::from_millis(10);
+    let shared_config = SharedConfig {
+        enable_replication: false,
+        tick: TickConfig::new(tick_duration),
+        ..Default::default()
+    };
+    let mut stepper = BevyStepper::new(
+        shared_config,
+        SyncConfig::default(),
+        PredictionConfig::default(),
+        InterpolationConfig::default(),
+        LinkConditionerConfig::default(),
+        frame_duration,
+    );
+    stepper.init();
+    assert!(stepper
+        .client_app
+        .world
+        .resource::<NetClient>()
+        .is_connected());
+
+    stepper
+        .server_app
+        .world
+        .resource_scope(|world, mut netserver: Mut<NetServer>| {
+            netserver
+                .disconnect(
+                    111,
+                    DisconnectReason::Kicked,
+                    b"afk",
+                    world.resource_mut::<Io>().as_mut(),
+                )
+                .unwrap();
+        });
+
+    let mut client_events = vec![];
+    let mut server_events = vec![];
+    for _ in 0..5 {
+        stepper.frame_step();
+        client_events.extend(
+            stepper
+                .client_app
+                .world
+                .resource_mut::<Events<client::DisconnectEvent>>()
+                .drain()
+                .map(|event| (event.reason(), event.payload().to_vec())),
+        );
+        server_events.extend(
+            stepper
+                .server_app
+                .world
+                .resource_mut::<Events<server::DisconnectEvent>>()
+                .drain()
+                .map(|event| (*event.context(), event.reason(), event.payload().to_vec())),
+        );
+    }
+    assert_eq!(
+        client_events,
+        vec![(DisconnectReason::Kicked, b"afk".to_vec())]
+    );
+    assert_eq!(
+        server_events,
+        vec![(111, DisconnectReason::Kicked, b"afk".to_vec())]
+    );
+    assert_eq!(
+        stepper
+            .client_app
+            .world
+            .resource::<NetClient>()
+            .disconnect_reason(),
+        Some(DisconnectReason::Kicked)
+    );
+}
+
+/// Check that a disconnection initiated by the client emits a `DisconnectEvent` on both sides
+#[test]
+fn test_client_disconnect() {
+    let frame_duration = Duration::from_millis(10);
+    let tick_duration = Duration::from_millis(10);
+    let shared_config = SharedConfig {
+        enable_replication: false,
+        tick: TickConfig::new(tick_duration),
+        ..Default::default()
+    };
+    let mut stepper = BevyStepper::new(
+        shared_config,
+        SyncConfig::default(),
+        PredictionConfig::default(),
+        InterpolationConfig::default(),
+        LinkConditionerConfig::default(),
+        frame_duration,
+    );
+    stepper.init();
+
+    let mut client = SystemState::<ClientMut>::new(&mut stepper.client_app.world);
+    client
+        .get_mut(&mut stepper.client_app.world)
+        .disconnect()
+        .unwrap();
+
+    let mut client_events = vec![];
+    let mut server_events = vec![];
+    for _ in 0..5 {
+        client_events.extend(
+            stepper
+                .client_app
+                .world
+                .resource_mut::<Events<client::DisconnectEvent>>()
+                .drain()
+                .map(|event| event.reason()),
+        );
+        stepper.frame_step();
+        server_events.extend(
+            stepper
+                .server_app
+                .world
+                .resource_mut::<Events<server::DisconnectEvent>>()
+                .drain()
+                .map(|event| (*event.context(), event.reason())),
+        );
+    }
+    assert_eq!(client_events, vec![DisconnectReason::ClientDisconnected]);
+    assert_eq!(
+        server_events,
+        vec![(111, DisconnectReason::ClientDisconnected)]
+    );
+}
+
+/// Check that a client that keeps sending fragments violating the limits of a channel gets disconnected
+#[test]
+fn test_fragment_limits_disconnect() {
+    let frame_duration = Duration::from_millis(10);
+    let tick_duration = Duration::from_millis(10);
+    let shared_config = SharedConfig {
+        enable_replication: false,
+        tick: TickConfig::new(tick_duration),
+        ..Default::default()
+    };
+    let mut stepper = BevyStepper::new(
+        shared_config,
+        SyncConfig::default(),
+        PredictionConfig::default(),
+        InterpolationConfig::default(),
+        LinkConditionerConfig::default(),
+        frame_duration,
+    );
+    stepper.init();
+
+    // simulate a client that sends the first fragment of many huge messages
+    {
+        let mut connection_manager = stepper
+            .server_app
+            .world
+            .resource_mut::<ServerConnectionManager>();
+        let channel = connection_manager
+            .connection_mut(111)
+            .unwrap()
+            .message_manager
+            .channels
+            .get_mut(&ChannelKind::of::<Channel1>())
+            .unwrap();
+        let max_violations = channel.setting.fragment.max_violations;
+        for i in 0..=max_violations {
+            channel
+                .receiver
+                .buffer_recv(MessageContainer::Fragment(FragmentData {
+                    message_id: MessageId(i as u16),
+                    tick: None,
+                    fragment_id: 0,
+                    num_fragments: u8::MAX,
+                    bytes: vec![0; 10].into(),
+                }))
+                .unwrap();
+        }
+    }
+
+    let mut client_events = vec![];
+    let mut server_events = vec![];
+    for _ in 0..5 {
+        stepper.frame_step();
+        client_events.extend(
+            stepper
+                .client_app
+                .world
+                .resource_mut::<Events<client::DisconnectEvent>>()
+                .drain()
+                .map(|event| event.reason()),
+        );
+        server_events.extend(
+            stepper
+                .server_app
+                .world
+                .resource_mut::<Events<server::DisconnectEvent>>()
+                .drain()
+                .map(|event| (*event.context(), event.reason())),
+        );
+    }
+    assert_eq!(client_events, vec![DisconnectReason::ProtocolViolation]);
+    assert_eq!(
+        server_events,
+        vec![(111, DisconnectReason::ProtocolViolation)]
+    );
+}
@@ -0,0 +1,82 @@
+use crate::prelude::client::{InterpolationConfig, PredictionConfig, SyncConfig};
+use crate::prelude::*;
+use crate::tests::protocol::*;
+use crate::tests::stepper::{BevyStepper, Step};
+use bevy::prelude::*;
+use std::time::Duration;
+
+/// Check that the handle returned when sending a message on a channel with acks
+/// shows up on the `MessageAckEvent` once the remote peer has received it
+#[test]
+fn test_message_ack_event() {
+    let frame_duration = Duration::from_millis(10);
+    let tick_duration = Duration::from_millis(10);
+    let shared_config = SharedConfig {
+        enable_replication: false,
+        tick: TickConfig::new(tick_duration),
+        ..Default::default()
+    };
+    let mut stepper = BevyStepper::new(
+        shared_config,
+        SyncConfig::default(),
+        PredictionConfig::default(),
+        InterpolationConfig::default(),
+        LinkConditionerConfig::default(),
+        frame_duration,
+    );
+    stepper.init();
+
+    // channels without acks don't return a handle
+    assert!(stepper
+        .client_app
+        .world
+        .resource_mut::<ClientConnectionManager>()
+        .send_message::<Channel1, _>(Message1("a".to_string()))
+        .unwrap()
+        .is_none());
+
+    let client_handle = stepper
+        .client_app
+        .world
+        .resource_mut::<ClientConnectionManager>()
+        .send_message::<Channel2, _>(Message1("b".to_string()))
+        .unwrap()
+        .unwrap();
+    let server_handle = stepper
+        .server_app
+        .world
+        .resource_mut::<ServerConnectionManager>()
+        .send_message::<Channel2, _>(111, Message2(1))
+        .unwrap()
+        .unwrap();
+    assert_eq!(client_handle.channel, ChannelKind::of::<Channel2>());
+
+    let mut client_acks = vec![];
+    let mut server_acks = vec![];
+    for _ in 0..10 {
+        stepper.frame_step();
+        client_acks.extend(
+            stepper
+                .client_app
+                .world
+                .resource_mut::<Events<client::MessageAckEvent>>()
+                .drain()
+                .map(|event| event.handle()),
+        );
+        server_acks.extend(
+            stepper
+                .server_app
+                .world
+                .resource_mut::<Events<server::MessageAckEvent>>()
+                .drain()
+                .map(|event| (event.handle(), *event.context())),
+        );
+    }
+    assert_eq!(client_acks, vec![client_handle]);
+    assert_eq!(server_acks, vec![(server_handle, 111)]);
+    assert!(stepper
+        .client_app
+        .world
+        .resource::<Events<client::MessageLostEvent>>()
+        .is_empty());
+}
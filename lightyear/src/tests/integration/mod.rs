@@ -1 +1,8 @@
+mod auth;
+mod disconnect;
+mod message_delivery;
+mod registry;
+mod resumption;
+mod rpc;
+mod stream;
 mod tick_wrapping;
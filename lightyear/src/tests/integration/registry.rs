@@ -0,0 +1,241 @@
+use crate::_reexport::*;
+use crate::prelude::client::{
+    ComponentSyncMode, InterpolationConfig, NetClient, PredictionConfig, SyncConfig,
+};
+use crate::prelude::*;
+use crate::tests::protocol::*;
+use crate::tests::stepper::{BevyStepper, Step};
+use bevy::ecs::event::ManualEventReader;
+use bevy::ecs::system::SystemState;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+// Types that are not part of `MyProtocol`, and are only known through the `ProtocolRegistry`
+#[derive(MessageInternal, Serialize, Deserialize, Debug, PartialEq, Clone)]
+struct Chat(String);
+
+#[derive(Component, MessageInternal, Serialize, Deserialize, Debug, PartialEq, Clone)]
+struct Health(u32);
+
+#[derive(Component, MessageInternal, Serialize, Deserialize, Debug, PartialEq, Clone)]
+struct Score(u32);
+
+fn stepper() -> BevyStepper {
+    let frame_duration = Duration::from_millis(10);
+    let tick_duration = Duration::from_millis(10);
+    let shared_config = SharedConfig {
+        enable_replication: true,
+        tick: TickConfig::new(tick_duration),
+        ..Default::default()
+    };
+    BevyStepper::new(
+        shared_config,
+        SyncConfig::default(),
+        PredictionConfig::default(),
+        InterpolationConfig::default(),
+        LinkConditionerConfig::default(),
+        frame_duration,
+    )
+}
+
+fn register(app: &mut App) {
+    app.register_message::<Chat>()
+        .unwrap()
+        .register_component::<Health>(ComponentSyncMode::Simple)
+        .unwrap();
+}
+
+/// Check that a registered message sent by the client is received by the server
+#[test]
+fn test_registered_message() {
+    let mut stepper = stepper();
+    register(&mut stepper.client_app);
+    register(&mut stepper.server_app);
+    stepper.init();
+
+    let mut client = SystemState::<ClientMut>::new(&mut stepper.client_app.world);
+    client
+        .get_mut(&mut stepper.client_app.world)
+        .send_registered_message::<Channel1, _>(Chat("hello".to_string()))
+        .unwrap();
+    // events are only kept for two frames, so read them after every frame
+    let mut reader = ManualEventReader::<server::MessageEvent<Chat>>::default();
+    let mut received = vec![];
+    for _ in 0..5 {
+        stepper.frame_step();
+        let events = stepper
+            .server_app
+            .world
+            .resource::<Events<server::MessageEvent<Chat>>>();
+        received.extend(
+            reader
+                .read(events)
+                .map(|event| (event.message().clone(), *event.context())),
+        );
+    }
+    assert_eq!(received, vec![(Chat("hello".to_string()), 111)]);
+}
+
+/// Check that the inserts, updates and removals of a registered component are replicated
+#[test]
+fn test_registered_component_replication() {
+    let mut stepper = stepper();
+    register(&mut stepper.client_app);
+    register(&mut stepper.server_app);
+    stepper.init();
+
+    let server_entity = stepper
+        .server_app
+        .world
+        .spawn((Health(10), Replicate::default()))
+        .id();
+    stepper.frame_step();
+    stepper.frame_step();
+
+    let client_entity = *stepper
+        .client_app
+        .world
+        .resource::<ClientConnectionManager>()
+        .replication_receiver
+        .remote_entity_map
+        .get_local(server_entity)
+        .unwrap();
+    assert_eq!(
+        stepper.client_app.world.get::<Health>(client_entity),
+        Some(&Health(10))
+    );
+    let events = stepper
+        .client_app
+        .world
+        .resource::<Events<client::ComponentInsertEvent<Health>>>();
+    assert_eq!(
+        events
+            .get_reader()
+            .read(events)
+            .map(|event| event.entity())
+            .collect::<Vec<_>>(),
+        vec![client_entity]
+    );
+
+    // update the component
+    stepper
+        .server_app
+        .world
+        .get_mut::<Health>(server_entity)
+        .unwrap()
+        .0 = 5;
+    stepper.frame_step();
+    stepper.frame_step();
+    assert_eq!(
+        stepper.client_app.world.get::<Health>(client_entity),
+        Some(&Health(5))
+    );
+
+    // remove the component
+    stepper
+        .server_app
+        .world
+        .entity_mut(server_entity)
+        .remove::<Health>();
+    stepper.frame_step();
+    stepper.frame_step();
+    assert!(stepper
+        .client_app
+        .world
+        .get::<Health>(client_entity)
+        .is_none());
+}
+
+/// Check that a registered component with `ComponentSyncMode::Full` gets a prediction history and an
+/// interpolation history, and is synced to the predicted and interpolated entities
+#[test]
+fn test_registered_component_full_sync() {
+    let mut stepper = stepper();
+    for app in [&mut stepper.client_app, &mut stepper.server_app] {
+        app.register_component::<Score>(ComponentSyncMode::Full)
+            .unwrap();
+    }
+    stepper.init();
+
+    let server_entity = stepper
+        .server_app
+        .world
+        .spawn((
+            Score(1),
+            Replicate {
+                prediction_target: NetworkTarget::All,
+                interpolation_target: NetworkTarget::All,
+                ..default()
+            },
+        ))
+        .id();
+    stepper.frame_step();
+    stepper.frame_step();
+
+    let confirmed_entity = *stepper
+        .client_app
+        .world
+        .resource::<ClientConnectionManager>()
+        .replication_receiver
+        .remote_entity_map
+        .get_local(server_entity)
+        .unwrap();
+    let confirmed = stepper
+        .client_app
+        .world
+        .get::<client::Confirmed>(confirmed_entity)
+        .unwrap();
+    let predicted_entity = confirmed.predicted.unwrap();
+    let interpolated_entity = confirmed.interpolated.unwrap();
+    assert_eq!(
+        stepper.client_app.world.get::<Score>(predicted_entity),
+        Some(&Score(1))
+    );
+    assert!(stepper
+        .client_app
+        .world
+        .get::<client::PredictionHistory<Score>>(predicted_entity)
+        .is_some());
+    assert!(stepper
+        .client_app
+        .world
+        .get::<client::ConfirmedHistory<Score>>(interpolated_entity)
+        .is_some());
+
+    // the predicted entity is rolled back to the new confirmed value
+    stepper
+        .server_app
+        .world
+        .get_mut::<Score>(server_entity)
+        .unwrap()
+        .0 = 2;
+    for _ in 0..5 {
+        stepper.frame_step();
+    }
+    assert_eq!(
+        stepper.client_app.world.get::<Score>(predicted_entity),
+        Some(&Score(2))
+    );
+
+    // the interpolated entity eventually reaches the confirmed value
+    for _ in 0..50 {
+        stepper.frame_step();
+    }
+    assert_eq!(
+        stepper.client_app.world.get::<Score>(interpolated_entity),
+        Some(&Score(2))
+    );
+}
+
+/// Check that a client and a server that registered different types cannot connect
+#[test]
+fn test_registry_mismatch() {
+    let mut stepper = stepper();
+    register(&mut stepper.server_app);
+    stepper.init();
+
+    let netcode = stepper.client_app.world.resource::<NetClient>();
+    assert!(!netcode.is_connected());
+    assert_eq!(netcode.deny_reason(), Some(DenyReason::ProtocolMismatch));
+}
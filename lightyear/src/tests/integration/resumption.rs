@@ -0,0 +1,256 @@
+use crate::prelude::client::{InterpolationConfig, NetClient, PredictionConfig, SyncConfig};
+use crate::prelude::server::NetcodeConfig;
+use crate::prelude::*;
+use crate::tests::protocol::*;
+use crate::tests::stepper::{BevyStepper, Step};
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+use std::time::Duration;
+
+/// Check that a client that times out and reconnects within the grace period resumes its session:
+/// no new connection on the server, the entities it already received are kept, and it receives the
+/// changes that happened while it was disconnected
+#[test]
+fn test_session_resumption() {
+    let frame_duration = Duration::from_millis(10);
+    let tick_duration = Duration::from_millis(10);
+    let shared_config = SharedConfig {
+        enable_replication: false,
+        tick: TickConfig::new(tick_duration),
+        ..Default::default()
+    };
+    let mut stepper = BevyStepper::new(
+        shared_config,
+        SyncConfig::default(),
+        PredictionConfig::default(),
+        InterpolationConfig::default(),
+        LinkConditionerConfig::default(),
+        frame_duration,
+    );
+    stepper.set_server_netcode_config(
+        NetcodeConfig::default().with_resumption_grace_period(Duration::from_secs(30)),
+    );
+    stepper.init();
+
+    let server_entity = stepper
+        .server_app
+        .world
+        .spawn((
+            Component1(0.0),
+            Replicate {
+                replication_target: NetworkTarget::All,
+                ..default()
+            },
+        ))
+        .id();
+    for _ in 0..10 {
+        stepper.frame_step();
+    }
+    let client_entity = *stepper
+        .client_app
+        .world
+        .resource::<ClientConnectionManager>()
+        .replication_receiver
+        .remote_entity_map
+        .get_local(server_entity)
+        .unwrap();
+
+    // the client stops responding: only the server keeps running, until it times the client out
+    for _ in 0..1100 {
+        stepper.current_time += frame_duration;
+        stepper
+            .server_app
+            .insert_resource(TimeUpdateStrategy::ManualInstant(stepper.current_time));
+        stepper.server_app.update();
+    }
+    assert!(stepper
+        .server_app
+        .world
+        .resource::<ServerConnectionManager>()
+        .is_suspended(111));
+
+    // the world changes while the client is away
+    stepper
+        .server_app
+        .world
+        .get_mut::<Component1>(server_entity)
+        .unwrap()
+        .0 = 1.0;
+    let new_server_entity = stepper
+        .server_app
+        .world
+        .spawn((
+            Component1(2.0),
+            Replicate {
+                replication_target: NetworkTarget::All,
+                ..default()
+            },
+        ))
+        .id();
+    stepper.server_app.update();
+
+    // the client comes back and reconnects
+    stepper
+        .client_app
+        .insert_resource(TimeUpdateStrategy::ManualInstant(stepper.current_time));
+    stepper.client_app.update();
+    stepper
+        .client_app
+        .world
+        .resource_mut::<NetClient>()
+        .connect();
+    stepper
+        .server_app
+        .world
+        .resource_mut::<Events<server::ConnectEvent>>()
+        .clear();
+    let mut server_events = vec![];
+    for _ in 0..50 {
+        stepper.frame_step();
+        server_events.extend(
+            stepper
+                .server_app
+                .world
+                .resource_mut::<Events<server::ConnectEvent>>()
+                .drain()
+                .map(|event| format!("connect {}", event.context())),
+        );
+        server_events.extend(
+            stepper
+                .server_app
+                .world
+                .resource_mut::<Events<server::DisconnectEvent>>()
+                .drain()
+                .map(|event| format!("disconnect {}", event.context())),
+        );
+    }
+    // the session was resumed: the server did not see a new connection
+    assert_eq!(server_events, Vec::<String>::new());
+    assert!(stepper
+        .client_app
+        .world
+        .resource::<NetClient>()
+        .is_connected());
+    assert!(!stepper
+        .server_app
+        .world
+        .resource::<ServerConnectionManager>()
+        .is_suspended(111));
+
+    // the client kept its entity and received the changes it missed
+    let receiver = &stepper
+        .client_app
+        .world
+        .resource::<ClientConnectionManager>()
+        .replication_receiver;
+    assert_eq!(
+        receiver.remote_entity_map.get_local(server_entity),
+        Some(&client_entity)
+    );
+    let new_client_entity = *receiver
+        .remote_entity_map
+        .get_local(new_server_entity)
+        .unwrap();
+    assert_eq!(
+        stepper.client_app.world.get::<Component1>(client_entity),
+        Some(&Component1(1.0))
+    );
+    assert_eq!(
+        stepper
+            .client_app
+            .world
+            .get::<Component1>(new_client_entity),
+        Some(&Component1(2.0))
+    );
+    assert_eq!(
+        stepper
+            .client_app
+            .world
+            .query::<&Component1>()
+            .iter(&stepper.client_app.world)
+            .count(),
+        2
+    );
+}
+
+/// Check that the session of a suspended client is ended if too many reliable messages are buffered for it
+#[test]
+fn test_suspended_session_unacked_messages_limit() {
+    let frame_duration = Duration::from_millis(10);
+    let tick_duration = Duration::from_millis(10);
+    let shared_config = SharedConfig {
+        enable_replication: false,
+        tick: TickConfig::new(tick_duration),
+        ..Default::default()
+    };
+    let mut stepper = BevyStepper::new(
+        shared_config,
+        SyncConfig::default(),
+        PredictionConfig::default(),
+        InterpolationConfig::default(),
+        LinkConditionerConfig::default(),
+        frame_duration,
+    );
+    stepper.set_server_netcode_config(
+        NetcodeConfig::default()
+            .with_resumption_grace_period(Duration::from_secs(30))
+            .with_resumption_max_unacked_messages(10),
+    );
+    stepper.init();
+
+    // the client stops responding: only the server keeps running, until it times the client out
+    for _ in 0..1100 {
+        stepper.current_time += frame_duration;
+        stepper
+            .server_app
+            .insert_resource(TimeUpdateStrategy::ManualInstant(stepper.current_time));
+        stepper.server_app.update();
+    }
+    assert!(stepper
+        .server_app
+        .world
+        .resource::<ServerConnectionManager>()
+        .is_suspended(111));
+    stepper
+        .server_app
+        .world
+        .resource_mut::<Events<server::DisconnectEvent>>()
+        .clear();
+
+    let mut server_events = vec![];
+    for i in 0..20 {
+        if !stepper
+            .server_app
+            .world
+            .resource::<ServerConnectionManager>()
+            .is_suspended(111)
+        {
+            break;
+        }
+        stepper
+            .server_app
+            .world
+            .resource_mut::<ServerConnectionManager>()
+            .send_message::<Channel3, Message1>(111, Message1(i.to_string()))
+            .unwrap();
+        stepper.current_time += frame_duration;
+        stepper
+            .server_app
+            .insert_resource(TimeUpdateStrategy::ManualInstant(stepper.current_time));
+        stepper.server_app.update();
+        server_events.extend(
+            stepper
+                .server_app
+                .world
+                .resource_mut::<Events<server::DisconnectEvent>>()
+                .drain()
+                .map(|event| (*event.context(), event.reason())),
+        );
+    }
+    assert_eq!(server_events, vec![(111, DisconnectReason::TimedOut)]);
+    assert!(!stepper
+        .server_app
+        .world
+        .resource::<ServerConnectionManager>()
+        .is_suspended(111));
+}
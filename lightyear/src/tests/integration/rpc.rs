@@ -0,0 +1,112 @@
+use crate::prelude::client::{InterpolationConfig, PredictionConfig, SyncConfig};
+use crate::prelude::*;
+use crate::tests::protocol::*;
+use crate::tests::stepper::{BevyStepper, Step};
+use bevy::ecs::system::SystemState;
+use bevy::prelude::*;
+use std::time::Duration;
+
+fn setup() -> BevyStepper {
+    let frame_duration = Duration::from_millis(10);
+    let tick_duration = Duration::from_millis(10);
+    let shared_config = SharedConfig {
+        enable_replication: false,
+        tick: TickConfig::new(tick_duration),
+        ..Default::default()
+    };
+    let mut stepper = BevyStepper::new(
+        shared_config,
+        SyncConfig::default(),
+        PredictionConfig::default(),
+        InterpolationConfig::default(),
+        LinkConditionerConfig::default(),
+        frame_duration,
+    );
+    stepper.init();
+    stepper
+}
+
+/// Check that a request sent by the client can be answered by the server,
+/// and that the response is matched with the request
+#[test]
+fn test_client_request() {
+    let mut stepper = setup();
+
+    // requests can only be sent on reliable channels
+    assert!(stepper
+        .client_app
+        .world
+        .resource_mut::<ClientConnectionManager>()
+        .send_request::<Channel1, _>(Message1("a".to_string()))
+        .is_err());
+
+    let mut client_rpc = SystemState::<ClientRpc<Message1>>::new(&mut stepper.client_app.world);
+    let request_id = client_rpc
+        .get_mut(&mut stepper.client_app.world)
+        .send_request::<Channel3>(Message1("a".to_string()))
+        .unwrap();
+    for _ in 0..5 {
+        stepper.frame_step();
+    }
+
+    let mut server_rpc = SystemState::<ServerRpc<Message1>>::new(&mut stepper.server_app.world);
+    let mut rpc = server_rpc.get_mut(&mut stepper.server_app.world);
+    let requests = rpc.drain_requests();
+    assert_eq!(requests.len(), 1);
+    let (request, message) = requests.into_iter().next().unwrap();
+    assert_eq!(message, Message1("a".to_string()));
+    assert_eq!(*request.context(), 111);
+    rpc.respond(request, Message2(1)).unwrap();
+    for _ in 0..5 {
+        stepper.frame_step();
+    }
+
+    assert_eq!(
+        client_rpc
+            .get_mut(&mut stepper.client_app.world)
+            .drain_responses(),
+        vec![(request_id, Message2(1))]
+    );
+    assert!(stepper
+        .client_app
+        .world
+        .resource::<Events<client::RequestTimeoutEvent>>()
+        .is_empty());
+}
+
+/// Check that a request sent by the server that is never answered emits a `RequestTimeoutEvent`
+#[test]
+fn test_server_request_timeout() {
+    let mut stepper = setup();
+
+    let request_id = stepper
+        .server_app
+        .world
+        .resource_mut::<ServerConnectionManager>()
+        .send_request::<Channel3, _>(111, Message2(1))
+        .unwrap();
+    for _ in 0..5 {
+        stepper.frame_step();
+    }
+    // the client received the request, but does not respond
+    let mut client_rpc = SystemState::<ClientRpc<Message2>>::new(&mut stepper.client_app.world);
+    let requests = client_rpc
+        .get_mut(&mut stepper.client_app.world)
+        .drain_requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].1, Message2(1));
+
+    let mut timeouts = vec![];
+    for _ in 0..10 {
+        stepper.frame_step();
+        timeouts.extend(
+            stepper
+                .server_app
+                .world
+                .resource_mut::<Events<server::RequestTimeoutEvent>>()
+                .drain()
+                .map(|event| (event.request_id(), *event.context())),
+        );
+    }
+    assert_eq!(timeouts, vec![(request_id, 111)]);
+}
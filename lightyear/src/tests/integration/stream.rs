@@ -0,0 +1,151 @@
+use crate::prelude::client::{InterpolationConfig, PredictionConfig, SyncConfig};
+use crate::prelude::*;
+use crate::tests::protocol::*;
+use crate::tests::stepper::{BevyStepper, Step};
+use bevy::prelude::*;
+use std::time::Duration;
+
+fn setup() -> BevyStepper {
+    let frame_duration = Duration::from_millis(10);
+    let tick_duration = Duration::from_millis(10);
+    let shared_config = SharedConfig {
+        enable_replication: false,
+        tick: TickConfig::new(tick_duration),
+        ..Default::default()
+    };
+    let mut stepper = BevyStepper::new(
+        shared_config,
+        SyncConfig::default(),
+        PredictionConfig::default(),
+        InterpolationConfig::default(),
+        LinkConditionerConfig::default(),
+        frame_duration,
+    );
+    stepper.init();
+    stepper
+}
+
+/// Check that a stream sent by the client is paced by the bandwidth cap of the channel,
+/// and that both sides get progress events until the payload is received
+#[test]
+fn test_client_stream() {
+    let mut stepper = setup();
+    let payload: Vec<u8> = (0..30_000).map(|i| (i % 251) as u8).collect();
+
+    // streams can only be sent on reliable channels
+    assert!(stepper
+        .client_app
+        .world
+        .resource_mut::<ClientConnectionManager>()
+        .send_stream::<Channel1>(payload.clone())
+        .is_err());
+    let transfer_id = stepper
+        .client_app
+        .world
+        .resource_mut::<ClientConnectionManager>()
+        .send_stream::<Channel4>(payload.clone())
+        .unwrap();
+
+    let mut client_progress = vec![];
+    let mut server_progress = vec![];
+    let mut received = vec![];
+    for frame in 0..200 {
+        stepper.frame_step();
+        client_progress.extend(
+            stepper
+                .client_app
+                .world
+                .resource_mut::<Events<client::TransferProgressEvent>>()
+                .drain()
+                .map(|event| *event.progress()),
+        );
+        server_progress.extend(
+            stepper
+                .server_app
+                .world
+                .resource_mut::<Events<server::TransferProgressEvent>>()
+                .drain()
+                .map(|event| *event.progress()),
+        );
+        received.extend(
+            stepper
+                .server_app
+                .world
+                .resource_mut::<Events<server::StreamReceivedEvent>>()
+                .drain()
+                .map(|event| (event.transfer_id(), *event.context(), event.bytes().clone())),
+        );
+        // the channel sends at most 100KB per second
+        if frame < 10 {
+            assert!(received.is_empty());
+        }
+    }
+
+    assert_eq!(received, vec![(transfer_id, 111, payload.into())]);
+    for progress in [&client_progress, &server_progress] {
+        assert!(progress.len() > 2);
+        assert!(progress
+            .windows(2)
+            .all(|w| w[0].bytes_transferred <= w[1].bytes_transferred));
+        let last = progress.last().unwrap();
+        assert_eq!(last.transfer_id, transfer_id);
+        assert_eq!(last.status, TransferStatus::Completed);
+        assert_eq!(last.fraction(), 1.0);
+    }
+    assert_eq!(client_progress[0].direction, TransferDirection::Send);
+    assert_eq!(server_progress[0].direction, TransferDirection::Receive);
+}
+
+/// Check that the receiver can cancel a stream, and that the sender is notified
+#[test]
+fn test_stream_cancel() {
+    let mut stepper = setup();
+    let transfer_id = stepper
+        .server_app
+        .world
+        .resource_mut::<ServerConnectionManager>()
+        .send_stream::<Channel4>(111, vec![1; 50_000])
+        .unwrap();
+
+    // wait until the client received some of the chunks
+    let mut client_progress = vec![];
+    while client_progress.is_empty() {
+        stepper.frame_step();
+        client_progress.extend(
+            stepper
+                .client_app
+                .world
+                .resource_mut::<Events<client::TransferProgressEvent>>()
+                .drain()
+                .map(|event| *event.progress()),
+        );
+    }
+    assert_eq!(client_progress[0].status, TransferStatus::InProgress);
+    stepper
+        .client_app
+        .world
+        .resource_mut::<ClientConnectionManager>()
+        .cancel_stream(transfer_id, TransferDirection::Receive)
+        .unwrap();
+
+    let mut server_progress = vec![];
+    for _ in 0..50 {
+        stepper.frame_step();
+        server_progress.extend(
+            stepper
+                .server_app
+                .world
+                .resource_mut::<Events<server::TransferProgressEvent>>()
+                .drain()
+                .map(|event| *event.progress()),
+        );
+    }
+    let last = server_progress.last().unwrap();
+    assert_eq!(last.status, TransferStatus::Cancelled);
+    assert!(last.bytes_transferred < last.total_bytes);
+    assert!(stepper
+        .client_app
+        .world
+        .resource::<Events<client::StreamReceivedEvent>>()
+        .is_empty());
+}
@@ -4,6 +4,7 @@ use cfg_if::cfg_if;
 use derive_more::{Add, Mul};
 
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
 use crate::_reexport::*;
 use crate::prelude::*;
@@ -15,6 +16,15 @@ pub struct Message1(pub String);
 #[derive(MessageInternal, Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct Message2(pub u32);
 
+impl Request for Message1 {
+    type Response = Message2;
+}
+
+impl Request for Message2 {
+    type Response = Message1;
+    const TIMEOUT: Duration = Duration::from_millis(100);
+}
+
 #[message_protocol_internal(protocol = "MyProtocol")]
 pub enum MyMessageProtocol {
     Message1(Message1),
@@ -45,6 +55,14 @@ impl<'a> MapEntities<'a> for Component4 {
     }
 }
 
+#[derive(
+    Component, MessageInternal, DiffableInternal, Serialize, Deserialize, Clone, Debug, PartialEq,
+)]
+pub struct Component5 {
+    pub x: f32,
+    pub y: f32,
+}
+
 #[component_protocol_internal(protocol = "MyProtocol")]
 pub enum MyComponentsProtocol {
     #[sync(full)]
@@ -55,6 +73,9 @@ pub enum MyComponentsProtocol {
     Component3(Component3),
     #[sync(simple)]
     Component4(Component4),
+    #[sync(simple)]
+    #[replication(delta)]
+    Component5(Component5),
 }
 
 // Inputs
@@ -107,15 +128,33 @@ pub struct Channel1;
 #[derive(ChannelInternal)]
 pub struct Channel2;
 
+#[derive(ChannelInternal)]
+pub struct Channel3;
+
+#[derive(ChannelInternal)]
+pub struct Channel4;
+
 pub fn protocol() -> MyProtocol {
     let mut p = MyProtocol::default();
     p.add_channel::<Channel1>(ChannelSettings {
         mode: ChannelMode::UnorderedUnreliable,
         direction: ChannelDirection::Bidirectional,
+        ..Default::default()
     });
     p.add_channel::<Channel2>(ChannelSettings {
         mode: ChannelMode::UnorderedUnreliableWithAcks,
         direction: ChannelDirection::Bidirectional,
+        ..Default::default()
+    });
+    p.add_channel::<Channel3>(ChannelSettings {
+        mode: ChannelMode::OrderedReliable(ReliableSettings::default()),
+        direction: ChannelDirection::Bidirectional,
+        ..Default::default()
+    });
+    p.add_channel::<Channel4>(ChannelSettings {
+        mode: ChannelMode::Streaming(StreamSettings::default()),
+        direction: ChannelDirection::Bidirectional,
+        ..Default::default()
     });
     p
 }
@@ -7,7 +7,7 @@ use bevy::prelude::{App, Mut, PluginGroup, Real, Time, World};
 use bevy::time::TimeUpdateStrategy;
 use bevy::{DefaultPlugins, MinimalPlugins};
 
-use crate::netcode::generate_key;
+use crate::netcode::{generate_key, Key};
 use crate::prelude::client::{
     Authentication, ClientConfig, InputConfig, InterpolationConfig, PredictionConfig, SyncConfig,
 };
@@ -32,6 +32,8 @@ pub struct BevyStepper {
     /// fixed timestep duration
     pub tick_duration: Duration,
     pub current_time: std::time::Instant,
+    /// private key shared by the client and the server
+    pub(crate) private_key: Key,
 }
 
 // Do not forget to use --features mock_time when using the LinkConditioner
@@ -82,6 +84,7 @@ impl BevyStepper {
             shared: shared_config.clone(),
             netcode: netcode_config,
             ping: PingConfig::default(),
+            priority: PriorityConfig::default(),
         };
         let plugin_config = server::PluginConfig::new(config, server_io, protocol());
         let plugin = server::ServerPlugin::new(plugin_config);
@@ -101,6 +104,7 @@ impl BevyStepper {
             input: InputConfig::default(),
             netcode: Default::default(),
             ping: PingConfig::default(),
+            priority: PriorityConfig::default(),
             sync: sync_config,
             prediction: prediction_config,
             interpolation: interpolation_config,
@@ -128,6 +132,7 @@ impl BevyStepper {
             frame_duration,
             tick_duration: shared_config.tick.tick_duration,
             current_time: now,
+            private_key,
         }
     }
 
@@ -147,11 +152,40 @@ impl BevyStepper {
     pub(crate) fn server_tick(&self) -> Tick {
         self.server_app.world.resource::<TickManager>().tick()
     }
+    /// Replace the server's netcode with one built from the given configuration.
+    /// The protocol id and private key shared with the client are kept.
+    ///
+    /// Must be called before [`BevyStepper::init`]
+    pub(crate) fn set_server_netcode_config(&mut self, config: NetcodeConfig) {
+        let config = config.with_protocol_id(0).with_key(self.private_key);
+        self.server_app.world.resource_mut::<ServerConfig>().netcode = config.clone();
+        self.server_app.insert_resource(crate::netcode::Server::new(
+            config,
+            protocol().fingerprint(),
+        ));
+    }
+
+    /// Replace the client's netcode with one that gets its connect token from `auth`.
+    ///
+    /// Must be called before [`BevyStepper::init`]
+    pub(crate) fn set_client_auth(&mut self, auth: Authentication) {
+        let mut client = SystemState::<ClientMut>::new(&mut self.client_app.world);
+        client
+            .get_mut(&mut self.client_app.world)
+            .reconnect(auth)
+            .expect("could not get token");
+    }
+
     pub(crate) fn init(&mut self) {
-        self.client_app
-            .world
-            .resource_mut::<crate::netcode::Client>()
-            .connect();
+        // `App::update` doesn't finish the plugins (only `App::run` does), but the plugins
+        // need it to take the types registered with `AppRegistrationExt` into account
+        self.client_app.finish();
+        self.client_app.cleanup();
+        self.server_app.finish();
+        self.server_app.cleanup();
+
+        let mut client = SystemState::<ClientMut>::new(&mut self.client_app.world);
+        client.get_mut(&mut self.client_app.world).connect();
 
         // Advance the world to let the connection process complete
         for _ in 0..100 {
@@ -0,0 +1,309 @@
+//! Compression of the payload packets sent and received
+//!
+//! The compression is enabled with [`IoConfig::with_compression`](crate::transport::io::IoConfig::with_compression),
+//! and applied by the netcode client and server on the payloads before they get encrypted (encrypted data
+//! doesn't compress). Both peers must use the same [`CompressionConfig`].
+//! Each compressed payload starts with a byte that tells if the payload is actually compressed: payloads
+//! that would get bigger once compressed are sent as is.
+use std::io::{Error, ErrorKind, Result};
+use std::ops::AddAssign;
+
+/// The payload is sent as is
+const RAW: u8 = 0;
+/// The payload is compressed
+const COMPRESSED: u8 = 1;
+
+#[derive(Clone, Debug)]
+pub enum CompressionConfig {
+    /// Compress packets with zstd
+    Zstd {
+        /// Compression level (between 1 and 22)
+        level: i32,
+        /// Pre-trained dictionary (see [`CompressionConfig::train_dictionary`])
+        dictionary: Option<Vec<u8>>,
+    },
+    /// Compress packets with lz4. Faster than zstd, but with a lower compression ratio
+    Lz4 {
+        /// Pre-trained dictionary (see [`CompressionConfig::train_dictionary`])
+        dictionary: Option<Vec<u8>>,
+    },
+}
+
+impl CompressionConfig {
+    pub fn zstd(level: i32) -> Self {
+        Self::Zstd {
+            level,
+            dictionary: None,
+        }
+    }
+
+    pub fn lz4() -> Self {
+        Self::Lz4 { dictionary: None }
+    }
+
+    /// Use a pre-trained dictionary. Small packets compress poorly without one.
+    pub fn with_dictionary(mut self, dictionary: Vec<u8>) -> Self {
+        match &mut self {
+            Self::Zstd { dictionary: d, .. } | Self::Lz4 { dictionary: d } => {
+                *d = Some(dictionary);
+            }
+        }
+        self
+    }
+
+    /// Check that the compression level is valid and that a compressor can be created with the dictionary
+    pub fn validate(&self) -> Result<()> {
+        if let Self::Zstd { level, .. } = self {
+            let range = zstd::compression_level_range();
+            if !range.contains(level) {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!(
+                        "invalid zstd compression level {}, expected a level between {} and {}",
+                        level,
+                        range.start(),
+                        range.end()
+                    ),
+                ));
+            }
+        }
+        Compressor::new(self.clone(), 0).map(|_| ())
+    }
+
+    /// Train a dictionary from samples of captured payloads. The dictionary will be at most `max_size` bytes.
+    pub fn train_dictionary<S: AsRef<[u8]>>(samples: &[S], max_size: usize) -> Result<Vec<u8>> {
+        zstd::dict::from_samples(samples, max_size)
+    }
+}
+
+/// Number of payload bytes before and after compression, to measure the gain of the compression.
+///
+/// They are reported in [`IoStats::compression`](crate::transport::io::IoStats::compression).
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct CompressionStats {
+    /// Number of payload bytes sent, before compression
+    pub uncompressed_bytes_sent: usize,
+    /// Number of payload bytes sent, after compression
+    pub compressed_bytes_sent: usize,
+    /// Number of payload bytes received, after decompression
+    pub uncompressed_bytes_received: usize,
+    /// Number of payload bytes received, before decompression
+    pub compressed_bytes_received: usize,
+}
+
+impl AddAssign for CompressionStats {
+    fn add_assign(&mut self, rhs: Self) {
+        self.uncompressed_bytes_sent += rhs.uncompressed_bytes_sent;
+        self.compressed_bytes_sent += rhs.compressed_bytes_sent;
+        self.uncompressed_bytes_received += rhs.uncompressed_bytes_received;
+        self.compressed_bytes_received += rhs.compressed_bytes_received;
+    }
+}
+
+enum CompressorKind {
+    Zstd {
+        compressor: zstd::bulk::Compressor<'static>,
+        decompressor: zstd::bulk::Decompressor<'static>,
+    },
+    Lz4 {
+        dictionary: Vec<u8>,
+    },
+}
+
+/// Compresses and decompresses payloads according to a [`CompressionConfig`]
+pub(crate) struct Compressor {
+    kind: CompressorKind,
+    /// Maximum size of a decompressed payload
+    max_size: usize,
+    /// Buffer that holds the last compressed or decompressed payload
+    buffer: Vec<u8>,
+    /// Stats since the last call to [`Compressor::take_stats`]
+    stats: CompressionStats,
+}
+
+impl Compressor {
+    /// Payloads that would be bigger than `max_size` once decompressed are rejected
+    pub(crate) fn new(config: CompressionConfig, max_size: usize) -> Result<Self> {
+        let kind = match config {
+            CompressionConfig::Zstd { level, dictionary } => {
+                let dictionary = dictionary.unwrap_or_default();
+                CompressorKind::Zstd {
+                    compressor: zstd::bulk::Compressor::with_dictionary(level, &dictionary)?,
+                    decompressor: zstd::bulk::Decompressor::with_dictionary(&dictionary)?,
+                }
+            }
+            CompressionConfig::Lz4 { dictionary } => CompressorKind::Lz4 {
+                dictionary: dictionary.unwrap_or_default(),
+            },
+        };
+        Ok(Self {
+            kind,
+            max_size,
+            buffer: Vec::with_capacity(max_size + 1),
+            stats: CompressionStats::default(),
+        })
+    }
+
+    /// Compress the payload. The payload is kept as is if compressing it doesn't make it smaller.
+    pub(crate) fn compress(&mut self, payload: &[u8]) -> Result<&[u8]> {
+        self.buffer.clear();
+        self.buffer.push(COMPRESSED);
+        match &mut self.kind {
+            CompressorKind::Zstd { compressor, .. } => {
+                // the compressed payload is written after the header
+                self.buffer
+                    .resize(1 + zstd::zstd_safe::compress_bound(payload.len()), 0);
+                let len = compressor.compress_to_buffer(payload, &mut self.buffer[1..])?;
+                self.buffer.truncate(1 + len);
+            }
+            CompressorKind::Lz4 { dictionary } => {
+                self.buffer
+                    .extend(lz4_flex::block::compress_prepend_size_with_dict(
+                        payload, dictionary,
+                    ));
+            }
+        }
+        if self.buffer.len() > payload.len() {
+            self.buffer.clear();
+            self.buffer.push(RAW);
+            self.buffer.extend_from_slice(payload);
+        }
+        self.stats.uncompressed_bytes_sent += payload.len();
+        self.stats.compressed_bytes_sent += self.buffer.len();
+        Ok(self.buffer.as_slice())
+    }
+
+    /// Decompress the payload.
+    ///
+    /// Payloads that would be bigger than the `max_size` of the compressor once decompressed are rejected.
+    pub(crate) fn decompress(&mut self, payload: &[u8]) -> Result<&[u8]> {
+        self.buffer.clear();
+        let Some((&header, data)) = payload.split_first() else {
+            return Err(Error::new(ErrorKind::Other, "empty payload"));
+        };
+        match (header, &mut self.kind) {
+            (RAW, _) => {
+                if data.len() > self.max_size {
+                    return Err(Error::new(
+                        ErrorKind::Other,
+                        format!("payload is too big: {} bytes", data.len()),
+                    ));
+                }
+                self.buffer.extend_from_slice(data);
+            }
+            (COMPRESSED, CompressorKind::Zstd { decompressor, .. }) => {
+                // the decompressor fails if the payload doesn't fit in the buffer
+                self.buffer.reserve(self.max_size);
+                decompressor.decompress_to_buffer(data, &mut self.buffer)?;
+                if self.buffer.len() > self.max_size {
+                    return Err(Error::new(
+                        ErrorKind::Other,
+                        format!(
+                            "decompressed payload is too big: {} bytes",
+                            self.buffer.len()
+                        ),
+                    ));
+                }
+            }
+            (COMPRESSED, CompressorKind::Lz4 { dictionary }) => {
+                let (size, data) = lz4_flex::block::uncompressed_size(data)
+                    .map_err(|e| Error::new(ErrorKind::Other, e))?;
+                if size > self.max_size {
+                    return Err(Error::new(
+                        ErrorKind::Other,
+                        format!("decompressed payload is too big: {} bytes", size),
+                    ));
+                }
+                self.buffer.resize(size, 0);
+                lz4_flex::block::decompress_into_with_dict(data, &mut self.buffer, dictionary)
+                    .map_err(|e| Error::new(ErrorKind::Other, e))?;
+            }
+            (header, _) => {
+                return Err(Error::new(
+                    ErrorKind::Other,
+                    format!("invalid compression header: {}", header),
+                ));
+            }
+        }
+        self.stats.compressed_bytes_received += payload.len();
+        self.stats.uncompressed_bytes_received += self.buffer.len();
+        Ok(self.buffer.as_slice())
+    }
+
+    /// Returns the stats accumulated since the last call, so that they can be added to the [`IoStats`](crate::transport::io::IoStats)
+    pub(crate) fn take_stats(&mut self) -> CompressionStats {
+        std::mem::take(&mut self.stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAX_SIZE: usize = 1200;
+
+    fn check_roundtrip(config: CompressionConfig) {
+        let mut compressor = Compressor::new(config.clone(), MAX_SIZE).unwrap();
+        let mut decompressor = Compressor::new(config, MAX_SIZE).unwrap();
+        let payload = [1, 2, 3, 4].repeat(100);
+        let compressed = compressor.compress(&payload).unwrap().to_vec();
+        assert!(compressed.len() < payload.len());
+        assert_eq!(
+            decompressor.decompress(&compressed).unwrap(),
+            payload.as_slice()
+        );
+        // invalid payloads are rejected
+        assert!(decompressor
+            .decompress(&[COMPRESSED, 255, 255, 255, 255])
+            .is_err());
+        assert!(decompressor.decompress(&[2, 0]).is_err());
+        assert!(decompressor.decompress(&[]).is_err());
+    }
+
+    #[test]
+    fn test_zstd() {
+        check_roundtrip(CompressionConfig::zstd(3));
+    }
+
+    #[test]
+    fn test_lz4() {
+        check_roundtrip(CompressionConfig::lz4());
+    }
+
+    #[test]
+    fn test_dictionary() {
+        let samples: Vec<Vec<u8>> = (0..1000u32)
+            .map(|i| format!("entity {} position x={} y={}", i % 10, i % 7, i % 3).into_bytes())
+            .collect();
+        let dictionary = CompressionConfig::train_dictionary(&samples, 1000).unwrap();
+        check_roundtrip(CompressionConfig::zstd(3).with_dictionary(dictionary.clone()));
+        check_roundtrip(CompressionConfig::lz4().with_dictionary(dictionary));
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(CompressionConfig::zstd(3).validate().is_ok());
+        assert!(CompressionConfig::lz4().validate().is_ok());
+        assert!(CompressionConfig::zstd(100).validate().is_err());
+    }
+
+    #[test]
+    fn test_incompressible_payload() {
+        let mut compressor = Compressor::new(CompressionConfig::zstd(3), MAX_SIZE).unwrap();
+        let payload = [7];
+        // the payload is sent as is, since compressing it would make it bigger
+        let compressed = compressor.compress(&payload).unwrap().to_vec();
+        assert_eq!(compressed, vec![RAW, 7]);
+        assert_eq!(compressor.decompress(&compressed).unwrap(), &payload);
+    }
+
+    #[test]
+    fn test_decompressed_size_limit() {
+        for config in [CompressionConfig::zstd(3), CompressionConfig::lz4()] {
+            let mut compressor = Compressor::new(config, MAX_SIZE).unwrap();
+            let payload = vec![0; MAX_SIZE + 1];
+            let compressed = compressor.compress(&payload).unwrap().to_vec();
+            assert!(compressor.decompress(&compressed).is_err());
+        }
+    }
+}
@@ -3,13 +3,16 @@ Contains the [`LinkConditionerConfig`] struct which can be used to simulate netw
 */
 use std::io::Result;
 use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
 
 use cfg_if::cfg_if;
 use rand;
-use rand::{thread_rng, Rng};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 
-use crate::transport::PacketReceiver;
+use crate::transport::{PacketReceiver, PacketSender};
 use crate::utils::ready_buffer::ReadyBuffer;
 
 cfg_if! {
@@ -21,7 +24,13 @@ cfg_if! {
 }
 
 /// Contains configuration required to initialize a LinkConditioner
-#[derive(Clone)]
+///
+/// A config conditions a single direction of the link: the received packets when it is used by a
+/// [`ConditionedPacketReceiver`] (`IoConfig::with_conditioner`), or the sent packets when it is used by a
+/// [`ConditionedPacketSender`] (`IoConfig::with_outgoing_conditioner`). All the fields apply to the packets of that
+/// direction only, so each direction of the link can have its own network conditions.
+/// (The `incoming_*` fields are named after the most common use; they apply to the sent packets in a [`ConditionedPacketSender`])
+#[derive(Clone, Debug, Default)]
 pub struct LinkConditionerConfig {
     /// Delay to receive incoming messages in milliseconds (half the RTT)
     pub incoming_latency: Duration,
@@ -32,62 +41,274 @@ pub struct LinkConditionerConfig {
     /// The % chance that an incoming packet will be dropped.
     /// Represented as a value between 0 and 1
     pub incoming_loss: f32,
+    /// The % chance that a packet will be duplicated. The duplicate gets its own latency and jitter.
+    /// Represented as a value between 0 and 1
+    pub duplication: f32,
+    /// The % chance that a packet is held back by an additional `reorder_delay`, so that it arrives
+    /// after packets that were sent later. Represented as a value between 0 and 1
+    pub reorder_probability: f32,
+    /// Additional delay for the packets that get reordered
+    pub reorder_delay: Duration,
+    /// Bursty packet loss, on top of `incoming_loss`
+    pub burst_loss: Option<BurstLossConfig>,
+    /// Maximum bandwidth of the link
+    pub bandwidth: Option<BandwidthConfig>,
+    /// Scheduled periods of degraded network conditions
+    pub spikes: Vec<NetworkSpike>,
+    /// Seed of the random number generator, to get reproducible network conditions
+    pub seed: Option<u64>,
 }
 
-// Conditions a packet-receiver T that sends packets P
-pub struct ConditionedPacketReceiver<T: PacketReceiver, P: Eq> {
-    packet_receiver: T,
+/// Gilbert-Elliott model of bursty packet loss.
+///
+/// The link is either in a 'good' state (where packets are lost with probability `incoming_loss`) or in a 'bad'
+/// state (where packets are lost with probability `bad_loss`). The state can change every time a packet is sent.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BurstLossConfig {
+    /// Probability to go from the good state to the bad state
+    pub good_to_bad: f32,
+    /// Probability to go from the bad state to the good state
+    pub bad_to_good: f32,
+    /// Probability that a packet is lost while in the bad state
+    pub bad_loss: f32,
+}
+
+/// Limits the bandwidth of the link. Packets are queued until the link can send them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BandwidthConfig {
+    /// Number of bytes that the link can send per second
+    pub bytes_per_second: usize,
+    /// Packets that would wait longer than this in the queue are dropped
+    pub max_queue_delay: Duration,
+}
+
+/// Period of time during which the network conditions are degraded
+#[derive(Clone, Debug, PartialEq)]
+pub struct NetworkSpike {
+    /// Start of the spike, measured from the creation of the conditioner
+    pub start: Duration,
+    pub duration: Duration,
+    /// Latency added to the packets sent during the spike
+    pub extra_latency: Duration,
+    /// Probability that a packet sent during the spike is lost
+    pub loss: f32,
+}
+
+impl NetworkSpike {
+    fn is_active(&self, now: Duration) -> bool {
+        now >= self.start && now < self.start + self.duration
+    }
+}
+
+/// Time source of the link conditioners.
+///
+/// The [`Io`](crate::transport::io::Io) uses a manual clock that it advances with the bevy virtual time, so that the
+/// conditioning is deterministic when the virtual time is.
+#[derive(Clone, Debug)]
+pub struct ConditionerClock(ClockKind);
+
+#[derive(Clone, Debug)]
+enum ClockKind {
+    /// Measures the real time elapsed since the creation of the clock
+    Real(Instant),
+    /// Only moves forward when it is advanced
+    Manual(Arc<AtomicU64>),
+}
+
+impl ConditionerClock {
+    /// Clock that follows the real time ([`Instant::now`])
+    pub fn real() -> Self {
+        Self(ClockKind::Real(Instant::now()))
+    }
+
+    /// Clock that only moves forward when it is advanced with [`ConditionerClock::advance`]
+    pub fn manual() -> Self {
+        Self(ClockKind::Manual(Arc::default()))
+    }
+
+    /// Time elapsed since the creation of the clock
+    pub fn now(&self) -> Duration {
+        match &self.0 {
+            ClockKind::Real(start) => Instant::now().duration_since(*start),
+            ClockKind::Manual(nanos) => Duration::from_nanos(nanos.load(Ordering::Relaxed)),
+        }
+    }
+
+    /// Advance a manual clock by `delta`. Does nothing if the clock follows the real time.
+    pub fn advance(&self, delta: Duration) {
+        if let ClockKind::Manual(nanos) = &self.0 {
+            nanos.fetch_add(delta.as_nanos() as u64, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Applies the network conditions to the packets `P`
+pub(crate) struct LinkConditioner<P: Eq> {
     config: LinkConditionerConfig,
-    pub time_queue: ReadyBuffer<Instant, P>,
-    last_packet: Option<P>,
+    rng: StdRng,
+    /// True if the burst loss model is in the 'bad' state
+    bad_state: bool,
+    /// Time at which the link will be done sending the queued packets
+    link_free_at: Duration,
+    /// Packets ordered by the time at which they are ready (and then by insertion order)
+    pub time_queue: ReadyBuffer<(Duration, u64), P>,
+    num_packets: u64,
 }
 
-impl<T: PacketReceiver, P: Eq> ConditionedPacketReceiver<T, P> {
-    pub fn new(packet_receiver: T, link_conditioner_config: LinkConditionerConfig) -> Self {
-        ConditionedPacketReceiver {
-            packet_receiver,
-            config: link_conditioner_config,
+impl<P: Eq + Clone> LinkConditioner<P> {
+    pub(crate) fn new(config: LinkConditionerConfig) -> Self {
+        let rng = match config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        Self {
+            config,
+            rng,
+            bad_state: false,
+            link_free_at: Duration::default(),
             time_queue: ReadyBuffer::new(),
-            last_packet: None,
+            num_packets: 0,
+        }
+    }
+
+    /// Condition a packet by potentially adding latency/jitter/loss/duplication to it
+    pub(crate) fn condition_packet(&mut self, now: Duration, packet: P, size: usize) {
+        let (spike_latency, spike_loss) = self
+            .config
+            .spikes
+            .iter()
+            .find(|spike| spike.is_active(now))
+            .map_or((Duration::default(), 0.0), |spike| {
+                (spike.extra_latency, spike.loss)
+            });
+
+        let base_latency = self.config.incoming_latency;
+        let jitter = self.config.incoming_jitter;
+        let mut loss = self.config.incoming_loss;
+
+        // loss
+        if let Some(burst_loss) = &self.config.burst_loss {
+            let transition = if self.bad_state {
+                burst_loss.bad_to_good
+            } else {
+                burst_loss.good_to_bad
+            };
+            if self.rng.gen_range(0.0..1.0) < transition {
+                self.bad_state = !self.bad_state;
+            }
+            if self.bad_state {
+                loss = burst_loss.bad_loss;
+            }
         }
+        if self.rng.gen_range(0.0..1.0) < loss.max(spike_loss) {
+            return;
+        }
+
+        // bandwidth: the packet has to wait until the link is done sending the previous packets
+        let mut sent_at = now;
+        if let Some(bandwidth) = &self.config.bandwidth {
+            let start = self.link_free_at.max(now);
+            if start - now > bandwidth.max_queue_delay {
+                return;
+            }
+            self.link_free_at =
+                start + Duration::from_secs_f64(size as f64 / bandwidth.bytes_per_second as f64);
+            sent_at = self.link_free_at;
+        }
+
+        let copies = if self.rng.gen_range(0.0..1.0) < self.config.duplication {
+            2
+        } else {
+            1
+        };
+        for _ in 0..copies {
+            let mut latency = base_latency.as_millis() as i64;
+            if jitter > Duration::default() {
+                let jitter = jitter.as_millis() as i64;
+                latency += self.rng.gen_range(-jitter..jitter);
+            }
+            let mut ready_at =
+                sent_at + Duration::from_millis(latency.max(0) as u64) + spike_latency;
+            if self.rng.gen_range(0.0..1.0) < self.config.reorder_probability {
+                ready_at += self.config.reorder_delay;
+            }
+            self.time_queue
+                .add_item((ready_at, self.num_packets), packet.clone());
+            self.num_packets += 1;
+        }
+    }
+
+    /// Pop a packet if it is ready
+    pub(crate) fn pop_packet(&mut self, now: Duration) -> Option<P> {
+        self.time_queue
+            .pop_item(&(now, u64::MAX))
+            .map(|(_, packet)| packet)
     }
 }
 
-// Condition a packet by potentially adding latency/jitter/loss to it
-fn condition_packet<P: Eq>(
-    config: &LinkConditionerConfig,
-    time_queue: &mut ReadyBuffer<Instant, P>,
-    packet: P,
-) {
-    let mut rng = thread_rng();
-    if rng.gen_range(0.0..1.0) <= config.incoming_loss {
-        return;
+/// Conditions the packets received by a packet-receiver T
+pub struct ConditionedPacketReceiver<T: PacketReceiver> {
+    packet_receiver: T,
+    conditioner: LinkConditioner<(SocketAddr, Box<[u8]>)>,
+    clock: ConditionerClock,
+    last_packet: Option<(SocketAddr, Box<[u8]>)>,
+}
+
+impl<T: PacketReceiver> ConditionedPacketReceiver<T> {
+    /// Creates a new conditioned receiver that uses the real time
+    pub fn new(packet_receiver: T, link_conditioner_config: LinkConditionerConfig) -> Self {
+        Self::with_clock(
+            packet_receiver,
+            link_conditioner_config,
+            ConditionerClock::real(),
+        )
     }
-    let mut latency: i32 = config.incoming_latency.as_millis() as i32;
-    // TODO: how can i use the virtual time here?
-    let mut packet_timestamp = Instant::now();
-    if config.incoming_jitter > Duration::default() {
-        let jitter: i32 = config.incoming_jitter.as_millis() as i32;
-        latency += rng.gen_range(-jitter..jitter);
+
+    /// Creates a new conditioned receiver with its own manual clock,
+    /// that must be advanced with [`ConditionedPacketReceiver::clock`].
+    pub fn with_manual_clock(
+        packet_receiver: T,
+        link_conditioner_config: LinkConditionerConfig,
+    ) -> Self {
+        Self::with_clock(
+            packet_receiver,
+            link_conditioner_config,
+            ConditionerClock::manual(),
+        )
     }
-    if latency > 0 {
-        packet_timestamp += Duration::from_millis(latency as u64);
+
+    /// Creates a new conditioned receiver that uses the given clock
+    pub fn with_clock(
+        packet_receiver: T,
+        link_conditioner_config: LinkConditionerConfig,
+        clock: ConditionerClock,
+    ) -> Self {
+        ConditionedPacketReceiver {
+            packet_receiver,
+            conditioner: LinkConditioner::new(link_conditioner_config),
+            clock,
+            last_packet: None,
+        }
+    }
+
+    pub fn clock(&self) -> &ConditionerClock {
+        &self.clock
     }
-    time_queue.add_item(packet_timestamp, packet);
 }
 
-impl<T: PacketReceiver> PacketReceiver for ConditionedPacketReceiver<T, (SocketAddr, Box<[u8]>)> {
+impl<T: PacketReceiver> PacketReceiver for ConditionedPacketReceiver<T> {
     fn recv(&mut self) -> Result<Option<(&mut [u8], SocketAddr)>> {
+        let now = self.clock.now();
         loop {
             // keep trying to receive packets from the inner packet receiver
             match self.packet_receiver.recv() {
                 Ok(option) => match option {
                     None => break,
                     // add conditioning (put the packets in the time queue)
-                    Some((data, addr)) => condition_packet(
-                        &self.config,
-                        &mut self.time_queue,
+                    Some((data, addr)) => self.conditioner.condition_packet(
+                        now,
                         (addr, data.to_vec().into_boxed_slice()),
+                        data.len(),
                     ),
                 },
                 Err(err) => {
@@ -96,8 +317,8 @@ impl<T: PacketReceiver> PacketReceiver for ConditionedPacketReceiver<T, (SocketA
             }
         }
         // only return a packet if it is ready to be returned
-        match self.time_queue.pop_item(&Instant::now()) {
-            Some((_, (addr, data))) => {
+        match self.conditioner.pop_packet(now) {
+            Some((addr, data)) => {
                 // we use `last_packet` to get ownership of the data
                 self.last_packet = Some((addr, data));
                 Ok(Some((self.last_packet.as_mut().unwrap().1.as_mut(), addr)))
@@ -107,6 +328,76 @@ impl<T: PacketReceiver> PacketReceiver for ConditionedPacketReceiver<T, (SocketA
     }
 }
 
+/// Conditions the packets sent by a packet-sender T.
+///
+/// Packets that are ready right away are sent immediately; the other ones are sent when
+/// [`PacketSender::flush`] is called after they become ready.
+pub struct ConditionedPacketSender<T: PacketSender> {
+    packet_sender: T,
+    conditioner: LinkConditioner<(SocketAddr, Box<[u8]>)>,
+    clock: ConditionerClock,
+}
+
+impl<T: PacketSender> ConditionedPacketSender<T> {
+    /// Creates a new conditioned sender that uses the real time
+    pub fn new(packet_sender: T, link_conditioner_config: LinkConditionerConfig) -> Self {
+        Self::with_clock(
+            packet_sender,
+            link_conditioner_config,
+            ConditionerClock::real(),
+        )
+    }
+
+    /// Creates a new conditioned sender with its own manual clock,
+    /// that must be advanced with [`ConditionedPacketSender::clock`].
+    pub fn with_manual_clock(
+        packet_sender: T,
+        link_conditioner_config: LinkConditionerConfig,
+    ) -> Self {
+        Self::with_clock(
+            packet_sender,
+            link_conditioner_config,
+            ConditionerClock::manual(),
+        )
+    }
+
+    /// Creates a new conditioned sender that uses the given clock
+    pub fn with_clock(
+        packet_sender: T,
+        link_conditioner_config: LinkConditionerConfig,
+        clock: ConditionerClock,
+    ) -> Self {
+        Self {
+            packet_sender,
+            conditioner: LinkConditioner::new(link_conditioner_config),
+            clock,
+        }
+    }
+
+    pub fn clock(&self) -> &ConditionerClock {
+        &self.clock
+    }
+}
+
+impl<T: PacketSender> PacketSender for ConditionedPacketSender<T> {
+    fn send(&mut self, payload: &[u8], address: &SocketAddr) -> Result<()> {
+        self.conditioner.condition_packet(
+            self.clock.now(),
+            (*address, payload.to_vec().into_boxed_slice()),
+            payload.len(),
+        );
+        self.flush()
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        let now = self.clock.now();
+        while let Some((address, data)) = self.conditioner.pop_packet(now) {
+            self.packet_sender.send(&data, &address)?;
+        }
+        self.packet_sender.flush()
+    }
+}
+
 impl LinkConditionerConfig {
     /// Creates a new LinkConditionerConfig
     pub fn new(incoming_latency: Duration, incoming_jitter: Duration, incoming_loss: f32) -> Self {
@@ -114,6 +405,7 @@ impl LinkConditionerConfig {
             incoming_latency,
             incoming_jitter,
             incoming_loss,
+            ..Default::default()
         }
     }
 
@@ -124,6 +416,7 @@ impl LinkConditionerConfig {
             incoming_latency: Duration::from_millis(40),
             incoming_jitter: Duration::from_millis(6),
             incoming_loss: 0.002,
+            ..Default::default()
         }
     }
 
@@ -134,6 +427,7 @@ impl LinkConditionerConfig {
             incoming_latency: Duration::from_millis(170),
             incoming_jitter: Duration::from_millis(45),
             incoming_loss: 0.02,
+            ..Default::default()
         }
     }
 
@@ -144,6 +438,223 @@ impl LinkConditionerConfig {
             incoming_latency: Duration::from_millis(300),
             incoming_jitter: Duration::from_millis(84),
             incoming_loss: 0.04,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_duplication(mut self, duplication: f32) -> Self {
+        self.duplication = duplication;
+        self
+    }
+
+    pub fn with_reordering(mut self, probability: f32, delay: Duration) -> Self {
+        self.reorder_probability = probability;
+        self.reorder_delay = delay;
+        self
+    }
+
+    pub fn with_burst_loss(mut self, burst_loss: BurstLossConfig) -> Self {
+        self.burst_loss = Some(burst_loss);
+        self
+    }
+
+    pub fn with_bandwidth(mut self, bandwidth: BandwidthConfig) -> Self {
+        self.bandwidth = Some(bandwidth);
+        self
+    }
+
+    pub fn with_spike(mut self, spike: NetworkSpike) -> Self {
+        self.spikes.push(spike);
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::transport::Transport;
+
+    use super::*;
+
+    fn incoming_conditioner(config: LinkConditionerConfig) -> LinkConditioner<u64> {
+        LinkConditioner::new(config)
+    }
+
+    fn send_packets(
+        conditioner: &mut LinkConditioner<u64>,
+        now: Duration,
+        packets: std::ops::Range<u64>,
+    ) {
+        for packet in packets {
+            conditioner.condition_packet(now, packet, 100);
+        }
+    }
+
+    fn pop_packets(conditioner: &mut LinkConditioner<u64>, now: Duration) -> Vec<u64> {
+        std::iter::from_fn(|| conditioner.pop_packet(now)).collect()
+    }
+
+    #[test]
+    fn test_latency_keeps_order() {
+        let mut conditioner = incoming_conditioner(LinkConditionerConfig::new(
+            Duration::from_millis(100),
+            Duration::default(),
+            0.0,
+        ));
+        send_packets(&mut conditioner, Duration::default(), 0..10);
+        assert!(pop_packets(&mut conditioner, Duration::from_millis(99)).is_empty());
+        assert_eq!(
+            pop_packets(&mut conditioner, Duration::from_millis(100)),
+            (0..10).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_duplication_and_reordering() {
+        let mut conditioner = incoming_conditioner(
+            LinkConditionerConfig::default()
+                .with_duplication(1.0)
+                .with_reordering(1.0, Duration::from_millis(50))
+                .with_seed(0),
+        );
+        send_packets(&mut conditioner, Duration::default(), 0..2);
+        assert!(pop_packets(&mut conditioner, Duration::from_millis(49)).is_empty());
+        assert_eq!(
+            pop_packets(&mut conditioner, Duration::from_millis(50)),
+            vec![0, 0, 1, 1]
+        );
+    }
+
+    #[test]
+    fn test_seed_is_deterministic() {
+        let config =
+            LinkConditionerConfig::new(Duration::from_millis(100), Duration::from_millis(50), 0.2)
+                .with_burst_loss(BurstLossConfig {
+                    good_to_bad: 0.1,
+                    bad_to_good: 0.3,
+                    bad_loss: 0.8,
+                })
+                .with_seed(42);
+        let received = || {
+            let mut conditioner = incoming_conditioner(config.clone());
+            send_packets(&mut conditioner, Duration::default(), 0..100);
+            pop_packets(&mut conditioner, Duration::from_secs(1))
+        };
+        let first = received();
+        assert!(first.len() < 100);
+        assert_eq!(first, received());
+    }
+
+    #[test]
+    fn test_bandwidth() {
+        // 1000 bytes per second: each packet of 100 bytes takes 100ms to be sent
+        let mut conditioner = incoming_conditioner(
+            LinkConditionerConfig::default().with_bandwidth(BandwidthConfig {
+                bytes_per_second: 1000,
+                max_queue_delay: Duration::from_millis(250),
+            }),
+        );
+        send_packets(&mut conditioner, Duration::default(), 0..5);
+        assert_eq!(
+            pop_packets(&mut conditioner, Duration::from_millis(100)),
+            vec![0]
+        );
+        assert_eq!(
+            pop_packets(&mut conditioner, Duration::from_millis(200)),
+            vec![1]
+        );
+        // packets that would have waited for more than 250ms in the queue were dropped
+        assert_eq!(
+            pop_packets(&mut conditioner, Duration::from_secs(1)),
+            vec![2]
+        );
+    }
+
+    #[test]
+    fn test_spike() {
+        let mut conditioner =
+            incoming_conditioner(LinkConditionerConfig::default().with_spike(NetworkSpike {
+                start: Duration::from_secs(1),
+                duration: Duration::from_secs(1),
+                extra_latency: Duration::from_millis(500),
+                loss: 0.0,
+            }));
+        send_packets(&mut conditioner, Duration::default(), 0..1);
+        assert_eq!(pop_packets(&mut conditioner, Duration::default()), vec![0]);
+        send_packets(&mut conditioner, Duration::from_millis(1500), 1..2);
+        assert!(pop_packets(&mut conditioner, Duration::from_millis(1999)).is_empty());
+        assert_eq!(
+            pop_packets(&mut conditioner, Duration::from_millis(2000)),
+            vec![1]
+        );
+    }
+
+    #[test]
+    fn test_real_clock() {
+        use mock_instant::MockClock;
+
+        let clock = ConditionerClock::real();
+        // advancing a real clock does nothing
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(clock.now(), Duration::default());
+        MockClock::advance(Duration::from_millis(10));
+        assert_eq!(clock.now(), Duration::from_millis(10));
+    }
+
+    #[test]
+    fn test_conditioned_sender() {
+        let (send, recv) = crossbeam_channel::unbounded();
+        let (local_sender, _) =
+            crate::transport::local::LocalChannel::new(crossbeam_channel::never(), send).listen();
+        let mut sender = ConditionedPacketSender::with_manual_clock(
+            local_sender,
+            LinkConditionerConfig::new(Duration::from_millis(100), Duration::default(), 0.0),
+        );
+        sender
+            .send(b"hello", &crate::transport::LOCAL_SOCKET)
+            .unwrap();
+        assert!(recv.try_recv().is_err());
+        sender.clock().advance(Duration::from_millis(100));
+        sender.flush().unwrap();
+        assert_eq!(recv.try_recv().unwrap(), b"hello".to_vec());
+    }
+
+    #[test]
+    fn test_conditioned_sender_sends_ready_packets() {
+        let (send, recv) = crossbeam_channel::unbounded();
+        let (local_sender, _) =
+            crate::transport::local::LocalChannel::new(crossbeam_channel::never(), send).listen();
+        let mut sender =
+            ConditionedPacketSender::with_manual_clock(local_sender, LinkConditionerConfig::default());
+        // packets without delay are sent without waiting for the next flush
+        sender
+            .send(b"hello", &crate::transport::LOCAL_SOCKET)
+            .unwrap();
+        assert_eq!(recv.try_recv().unwrap(), b"hello".to_vec());
+    }
+
+    #[test]
+    fn test_preset_conditions_sent_packets() {
+        let (send, recv) = crossbeam_channel::unbounded();
+        let (local_sender, _) =
+            crate::transport::local::LocalChannel::new(crossbeam_channel::never(), send).listen();
+        let mut sender = ConditionedPacketSender::with_manual_clock(
+            local_sender,
+            LinkConditionerConfig::average_condition().with_seed(1),
+        );
+        for _ in 0..10 {
+            sender
+                .send(b"hello", &crate::transport::LOCAL_SOCKET)
+                .unwrap();
         }
+        // the latency of the preset applies to the sent packets
+        assert!(recv.try_recv().is_err());
+        sender.clock().advance(Duration::from_secs(1));
+        sender.flush().unwrap();
+        assert!(recv.try_recv().is_ok());
     }
 }
@@ -1,5 +1,9 @@
 //! Wrapper around a transport, that can perform additional transformations such as
-//! bandwidth monitoring or compression
+//! link conditioning or bandwidth monitoring
+//!
+//! [`IoConfig::with_compression`] enables the compression of the payloads. They are compressed by the netcode layer,
+//! before they get encrypted (encrypted data doesn't compress), and the bytes before and after compression are
+//! reported in [`IoStats::compression`].
 use bevy::app::{App, Plugin};
 use bevy::diagnostic::{Diagnostic, DiagnosticId, Diagnostics, RegisterDiagnostic};
 use bevy::prelude::{Real, Res, Resource, Time};
@@ -7,17 +11,28 @@ use crossbeam_channel::{Receiver, Sender};
 use std::fmt::{Debug, Formatter};
 use std::io::Result;
 use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
 
 #[cfg(feature = "metrics")]
 use metrics;
+
+#[cfg(feature = "compression")]
+use crate::transport::compression::{CompressionConfig, CompressionStats};
 use tracing::info;
 #[cfg(feature = "webtransport")]
 use wtransport::tls::Certificate;
 
 use crate::transport::channels::Channels;
-use crate::transport::conditioner::{ConditionedPacketReceiver, LinkConditionerConfig};
+use crate::transport::conditioner::{
+    ConditionedPacketReceiver, ConditionedPacketSender, ConditionerClock, LinkConditionerConfig,
+};
 use crate::transport::local::LocalChannel;
+use crate::transport::multi::{MultiTransport, SharedMultiState};
 use crate::transport::udp::UdpSocket;
+#[cfg(feature = "websocket")]
+use crate::transport::websocket::client::WebSocketClientSocket;
+#[cfg(feature = "websocket")]
+use crate::transport::websocket::server::WebSocketServerSocket;
 #[cfg(feature = "webtransport")]
 use crate::transport::webtransport::client::WebTransportClientSocket;
 #[cfg(feature = "webtransport")]
@@ -37,6 +52,15 @@ pub enum TransportConfig {
         server_addr: SocketAddr,
         certificate: Certificate,
     },
+    #[cfg(feature = "websocket")]
+    WebSocketClient {
+        client_addr: SocketAddr,
+        server_addr: SocketAddr,
+    },
+    #[cfg(feature = "websocket")]
+    WebSocketServer {
+        server_addr: SocketAddr,
+    },
     Channels {
         channels: Vec<(SocketAddr, Receiver<Vec<u8>>, Sender<Vec<u8>>)>,
     },
@@ -44,6 +68,12 @@ pub enum TransportConfig {
         recv: Receiver<Vec<u8>>,
         send: Sender<Vec<u8>>,
     },
+    /// Use several transports at the same time (for example to accept both UDP and WebTransport clients on a server).
+    ///
+    /// Packets are sent back through the transport that the remote address was last seen on,
+    /// so it is not possible to send packets to an address before receiving packets from it.
+    /// The stats of each transport are available with [`Io::transport_stats`].
+    Multi(Vec<TransportConfig>),
 }
 
 impl TransportConfig {
@@ -77,6 +107,23 @@ impl TransportConfig {
                 let (sender, receiver) = transport.listen();
                 Io::new(addr, sender, receiver)
             }
+            #[cfg(feature = "websocket")]
+            TransportConfig::WebSocketClient {
+                client_addr,
+                server_addr,
+            } => {
+                let transport = WebSocketClientSocket::new(client_addr, server_addr).unwrap();
+                let addr = transport.local_addr();
+                let (sender, receiver) = transport.listen();
+                Io::new(addr, sender, receiver)
+            }
+            #[cfg(feature = "websocket")]
+            TransportConfig::WebSocketServer { server_addr } => {
+                let transport = WebSocketServerSocket::new(server_addr).unwrap();
+                let addr = transport.local_addr();
+                let (sender, receiver) = transport.listen();
+                Io::new(addr, sender, receiver)
+            }
             TransportConfig::Channels { channels } => {
                 let mut transport = Channels::new();
                 for (addr, remote_recv, remote_send) in channels.into_iter() {
@@ -92,6 +139,26 @@ impl TransportConfig {
                 let (sender, receiver) = transport.listen();
                 Io::new(addr, sender, receiver)
             }
+            TransportConfig::Multi(configs) => {
+                let transports = configs
+                    .into_iter()
+                    .map(|config| {
+                        let io = config.get_io();
+                        let addr = io.local_addr();
+                        let (receiver, sender) = io.to_parts();
+                        (addr, sender, receiver)
+                    })
+                    .collect();
+                let transport = MultiTransport::new(transports);
+                let addr = transport.local_addr();
+                let local_addrs = transport.local_addrs().to_vec();
+                let state = transport.state();
+                let (sender, receiver) = transport.listen();
+                let mut io = Io::new(addr, sender, receiver);
+                io.local_addrs = local_addrs;
+                io.multi_state = Some(state);
+                io
+            }
         }
     }
 }
@@ -99,7 +166,13 @@ impl TransportConfig {
 #[derive(Clone)]
 pub struct IoConfig {
     pub transport: TransportConfig,
+    /// Conditioner applied to the received packets
     pub conditioner: Option<LinkConditionerConfig>,
+    /// Conditioner applied to the sent packets
+    pub outgoing_conditioner: Option<LinkConditionerConfig>,
+    /// Compression applied to the payloads sent and received, before they get encrypted
+    #[cfg(feature = "compression")]
+    pub compression: Option<CompressionConfig>,
 }
 
 impl Default for IoConfig {
@@ -107,6 +180,9 @@ impl Default for IoConfig {
         Self {
             transport: TransportConfig::UdpSocket(SocketAddr::new(IpAddr::from([127, 0, 0, 1]), 0)),
             conditioner: None,
+            outgoing_conditioner: None,
+            #[cfg(feature = "compression")]
+            compression: None,
         }
     }
 }
@@ -116,6 +192,9 @@ impl IoConfig {
         Self {
             transport,
             conditioner: None,
+            outgoing_conditioner: None,
+            #[cfg(feature = "compression")]
+            compression: None,
         }
     }
     pub fn with_conditioner(mut self, conditioner_config: LinkConditionerConfig) -> Self {
@@ -123,14 +202,42 @@ impl IoConfig {
         self
     }
 
+    /// Condition the packets that are sent, independently of the received packets (see [`IoConfig::with_conditioner`])
+    pub fn with_outgoing_conditioner(mut self, conditioner_config: LinkConditionerConfig) -> Self {
+        self.outgoing_conditioner = Some(conditioner_config);
+        self
+    }
+
+    /// Compress the payloads sent and decompress the payloads received.
+    /// The remote peer must use the same compression config.
+    ///
+    /// Returns an error if the compression level or the dictionary are invalid.
+    #[cfg(feature = "compression")]
+    pub fn with_compression(mut self, compression_config: CompressionConfig) -> Result<Self> {
+        compression_config.validate()?;
+        self.compression = Some(compression_config);
+        Ok(self)
+    }
+
     pub fn get_io(self) -> Io {
         let mut io = self.transport.get_io();
+        #[cfg(feature = "compression")]
+        {
+            io.compression = self.compression;
+        }
         if let Some(conditioner) = self.conditioner {
-            io = Io::new(
-                io.local_addr,
+            io.receiver = Box::new(ConditionedPacketReceiver::with_clock(
+                io.receiver,
+                conditioner,
+                io.clock.clone(),
+            ));
+        }
+        if let Some(conditioner) = self.outgoing_conditioner {
+            io.sender = Box::new(ConditionedPacketSender::with_clock(
                 io.sender,
-                Box::new(ConditionedPacketReceiver::new(io.receiver, conditioner)),
-            );
+                conditioner,
+                io.clock.clone(),
+            ));
         }
         io
     }
@@ -139,17 +246,31 @@ impl IoConfig {
 #[derive(Resource)]
 pub struct Io {
     local_addr: SocketAddr,
+    /// Addresses of all the transports (several for [`TransportConfig::Multi`])
+    local_addrs: Vec<SocketAddr>,
     sender: Box<dyn PacketSender>,
     receiver: Box<dyn PacketReceiver>,
+    /// Time used by the link conditioners
+    clock: ConditionerClock,
+    /// Compression applied by the netcode layer to the payloads, if [`IoConfig::with_compression`] is set
+    #[cfg(feature = "compression")]
+    compression: Option<CompressionConfig>,
     pub(crate) stats: IoStats,
+    /// State of the multiplexed transports, if the transport is [`TransportConfig::Multi`]
+    multi_state: Option<SharedMultiState>,
 }
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone)]
 pub struct IoStats {
+    /// Number of bytes sent on the network
     pub bytes_sent: usize,
+    /// Number of bytes received from the network
     pub bytes_received: usize,
     pub packets_sent: usize,
     pub packets_received: usize,
+    /// Number of payload bytes before and after compression, if [`IoConfig::with_compression`] is set
+    #[cfg(feature = "compression")]
+    pub compression: CompressionStats,
 }
 
 impl Io {
@@ -164,15 +285,26 @@ impl Io {
     ) -> Self {
         Self {
             local_addr,
+            local_addrs: vec![local_addr],
             sender,
             receiver,
+            clock: ConditionerClock::manual(),
+            #[cfg(feature = "compression")]
+            compression: None,
             stats: IoStats::default(),
+            multi_state: None,
         }
     }
+    /// The local address of the transport (of the first transport for [`TransportConfig::Multi`])
     pub fn local_addr(&self) -> SocketAddr {
         self.local_addr
     }
 
+    /// The local addresses of all the transports
+    pub fn local_addrs(&self) -> &[SocketAddr] {
+        &self.local_addrs
+    }
+
     pub fn to_parts(self) -> (Box<dyn PacketReceiver>, Box<dyn PacketSender>) {
         (self.receiver, self.sender)
     }
@@ -184,6 +316,29 @@ impl Io {
     pub fn stats(&self) -> &IoStats {
         &self.stats
     }
+
+    /// The compression config of the payloads, if [`IoConfig::with_compression`] is set
+    #[cfg(feature = "compression")]
+    pub(crate) fn compression(&self) -> Option<&CompressionConfig> {
+        self.compression.as_ref()
+    }
+
+    /// Stats of each transport of a [`TransportConfig::Multi`], in the order in which they were provided
+    /// (empty for other transports).
+    ///
+    /// Unlike [`Io::stats`], they are never reset.
+    pub fn transport_stats(&self) -> Vec<IoStats> {
+        self.multi_state
+            .as_ref()
+            .map_or_else(Vec::new, |state| state.lock().unwrap().stats.clone())
+    }
+
+    /// Advance the time of the link conditioners by the bevy virtual time delta,
+    /// and send the outgoing packets that are ready
+    pub(crate) fn update(&mut self, delta: Duration) -> Result<()> {
+        self.clock.advance(delta);
+        self.sender.flush()
+    }
 }
 
 impl Debug for Io {
@@ -192,20 +347,33 @@ impl Debug for Io {
     }
 }
 
+impl Io {
+    fn record_packet_received(stats: &mut IoStats, bytes: usize) {
+        #[cfg(feature = "metrics")]
+        {
+            metrics::increment_counter!("transport.packets_received");
+            metrics::increment_gauge!("transport.bytes_received", bytes as f64);
+        }
+        stats.bytes_received += bytes;
+        stats.packets_received += 1;
+    }
+
+    fn record_packet_sent(stats: &mut IoStats, bytes: usize) {
+        #[cfg(feature = "metrics")]
+        {
+            metrics::increment_counter!("transport.packets_sent");
+            metrics::increment_gauge!("transport.bytes_sent", bytes as f64);
+        }
+        stats.bytes_sent += bytes;
+        stats.packets_sent += 1;
+    }
+}
+
 impl PacketReceiver for Io {
     fn recv(&mut self) -> Result<Option<(&mut [u8], SocketAddr)>> {
-        // todo: compression + bandwidth monitoring
-        // TODO: INSPECT IS UNSTABLE
-
         self.receiver.recv().map(|x| {
             if let Some((ref buffer, _)) = x {
-                #[cfg(feature = "metrics")]
-                {
-                    metrics::increment_counter!("transport.packets_received");
-                    metrics::increment_gauge!("transport.bytes_received", buffer.len() as f64);
-                }
-                self.stats.bytes_received += buffer.len();
-                self.stats.packets_received += 1;
+                Self::record_packet_received(&mut self.stats, buffer.len());
             }
             x
         })
@@ -214,14 +382,7 @@ impl PacketReceiver for Io {
 
 impl PacketSender for Io {
     fn send(&mut self, payload: &[u8], address: &SocketAddr) -> Result<()> {
-        // todo: compression + bandwidth monitoring
-        #[cfg(feature = "metrics")]
-        {
-            metrics::increment_counter!("transport.packets_sent");
-            metrics::increment_gauge!("transport.bytes_sent", payload.len() as f64);
-        }
-        self.stats.bytes_sent += payload.len();
-        self.stats.packets_sent += 1;
+        Self::record_packet_sent(&mut self.stats, payload.len());
         self.sender.send(payload, address)
     }
 }
@@ -243,6 +404,15 @@ impl IoDiagnosticsPlugin {
     pub const PACKETS_OUT: DiagnosticId =
         DiagnosticId::from_u128(314668465487051049643062180884137694217);
 
+    /// How many payload bytes do we receive per second, after decompression
+    #[cfg(feature = "compression")]
+    pub const UNCOMPRESSED_BYTES_IN: DiagnosticId =
+        DiagnosticId::from_u128(95018338510932795620498127734521938640);
+    /// How many payload bytes do we send per second, before compression
+    #[cfg(feature = "compression")]
+    pub const UNCOMPRESSED_BYTES_OUT: DiagnosticId =
+        DiagnosticId::from_u128(227445087152349614939813047268350571395);
+
     /// Max diagnostic history length.
     pub const DIAGNOSTIC_HISTORY_LEN: usize = 60;
 
@@ -265,6 +435,15 @@ impl IoDiagnosticsPlugin {
         diagnostics.add_measurement(Self::PACKETS_OUT, || {
             stats.packets_sent as f64 / delta_seconds
         });
+        #[cfg(feature = "compression")]
+        {
+            diagnostics.add_measurement(Self::UNCOMPRESSED_BYTES_IN, || {
+                stats.compression.uncompressed_bytes_received as f64 / delta_seconds
+            });
+            diagnostics.add_measurement(Self::UNCOMPRESSED_BYTES_OUT, || {
+                stats.compression.uncompressed_bytes_sent as f64 / delta_seconds
+            });
+        }
         *stats = IoStats::default()
     }
 }
@@ -291,6 +470,19 @@ impl Plugin for IoDiagnosticsPlugin {
             "packets sent per second",
             IoDiagnosticsPlugin::DIAGNOSTIC_HISTORY_LEN,
         ));
+        #[cfg(feature = "compression")]
+        {
+            app.register_diagnostic(Diagnostic::new(
+                IoDiagnosticsPlugin::UNCOMPRESSED_BYTES_IN,
+                "bytes received per second, after decompression",
+                IoDiagnosticsPlugin::DIAGNOSTIC_HISTORY_LEN,
+            ));
+            app.register_diagnostic(Diagnostic::new(
+                IoDiagnosticsPlugin::UNCOMPRESSED_BYTES_OUT,
+                "bytes sent per second, before compression",
+                IoDiagnosticsPlugin::DIAGNOSTIC_HISTORY_LEN,
+            ));
+        }
     }
 }
 
@@ -298,6 +490,10 @@ impl PacketSender for Box<dyn PacketSender + Send + Sync> {
     fn send(&mut self, payload: &[u8], address: &SocketAddr) -> Result<()> {
         (**self).send(payload, address)
     }
+
+    fn flush(&mut self) -> Result<()> {
+        (**self).flush()
+    }
 }
 
 impl PacketReceiver for Box<dyn PacketReceiver + Send + Sync> {
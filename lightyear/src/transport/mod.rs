@@ -3,6 +3,10 @@
 /// A conditioner is used to simulate network conditions such as latency, jitter and packet loss.
 pub(crate) mod conditioner;
 
+/// Compression of the packets with zstd or lz4
+#[cfg(feature = "compression")]
+pub mod compression;
+
 /// io is a wrapper around the underlying transport layer
 pub mod io;
 
@@ -15,13 +19,24 @@ pub(crate) mod udp;
 /// The transport is a map of channels (used for server, during testing)
 pub(crate) mod channels;
 
+/// The transport multiplexes several transports
+pub(crate) mod multi;
+
 /// The transport is using WebTransport
 #[cfg(feature = "webtransport")]
 pub mod webtransport;
 
+/// The transport is using WebSockets
+#[cfg(feature = "websocket")]
+pub mod websocket;
+
 use std::io::Result;
 use std::net::SocketAddr;
 
+// Maximum transmission units; maximum size in bytes of a UDP packet
+// See: https://gafferongames.com/post/packet_fragmentation_and_reassembly/
+pub(crate) const MTU: usize = 1472;
+
 pub const LOCAL_SOCKET: SocketAddr = SocketAddr::new(
     std::net::IpAddr::V4(std::net::Ipv4Addr::new(127, 0, 0, 1)),
     0,
@@ -40,12 +55,21 @@ pub trait Transport {
 pub trait PacketSender: Send + Sync {
     /// Send data on the socket to the remote address
     fn send(&mut self, payload: &[u8], address: &SocketAddr) -> Result<()>;
+
+    /// Send any packets that were held back (for example by a link conditioner)
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
 }
 
 impl PacketSender for Box<dyn PacketSender> {
     fn send(&mut self, payload: &[u8], address: &SocketAddr) -> Result<()> {
         (**self).send(payload, address)
     }
+
+    fn flush(&mut self) -> Result<()> {
+        (**self).flush()
+    }
 }
 
 /// Receive data from a remote address
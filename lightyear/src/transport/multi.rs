@@ -0,0 +1,364 @@
+//! Multiplexes several transports behind a single `Io`, so that a server can accept clients
+//! that use different transports (for example UDP and WebTransport) at the same time.
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use bevy::utils::HashMap;
+use cfg_if::cfg_if;
+use tracing::{debug, error};
+
+use crate::transport::io::IoStats;
+use crate::transport::{PacketReceiver, PacketSender, Transport, LOCAL_SOCKET};
+
+cfg_if! {
+    if #[cfg(any(test))] {
+        use mock_instant::Instant;
+    } else {
+        use std::time::Instant;
+    }
+}
+
+/// Routes of the remote addresses that did not send any packet for this duration are forgotten
+const ROUTE_TIMEOUT: Duration = Duration::from_secs(30);
+/// How often the expired routes are removed
+const ROUTE_CLEANUP_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy)]
+struct Route {
+    /// Index of the transport that the remote address was last seen on
+    transport: usize,
+    last_received: Instant,
+}
+
+/// State shared between the sender and the receiver of a [`MultiTransport`]
+#[derive(Debug)]
+pub(crate) struct MultiState {
+    routes: HashMap<SocketAddr, Route>,
+    last_cleanup: Instant,
+    /// Stats of each transport
+    pub(crate) stats: Vec<IoStats>,
+}
+
+impl MultiState {
+    fn new(num_transports: usize) -> Self {
+        Self {
+            routes: HashMap::default(),
+            last_cleanup: Instant::now(),
+            stats: vec![IoStats::default(); num_transports],
+        }
+    }
+
+    /// Remember that `addr` can be reached through the transport `index`.
+    ///
+    /// Packets are received before netcode authenticates them, so anyone can send packets from any address:
+    /// the route of an address is only learned from the first transport it is seen on, and doesn't change
+    /// until it expires.
+    fn learn_route(&mut self, addr: SocketAddr, index: usize, now: Instant) {
+        let route = self.routes.entry(addr).or_insert(Route {
+            transport: index,
+            last_received: now,
+        });
+        if route.transport == index {
+            route.last_received = now;
+        }
+    }
+
+    /// Forget the remote addresses that did not send any packet for [`ROUTE_TIMEOUT`]
+    /// (for example because the client disconnected)
+    fn remove_expired_routes(&mut self, now: Instant) {
+        if now.duration_since(self.last_cleanup) < ROUTE_CLEANUP_INTERVAL {
+            return;
+        }
+        self.last_cleanup = now;
+        self.routes
+            .retain(|_, route| now.duration_since(route.last_received) < ROUTE_TIMEOUT);
+    }
+}
+
+/// Errors after which the transport can still receive packets (for example, a UDP socket receives
+/// a `ConnectionReset` error when a packet that it sent could not be delivered)
+fn is_transient(error: &std::io::Error) -> bool {
+    matches!(
+        error.kind(),
+        std::io::ErrorKind::ConnectionReset
+            | std::io::ErrorKind::ConnectionRefused
+            | std::io::ErrorKind::Interrupted
+            | std::io::ErrorKind::TimedOut
+    )
+}
+
+pub(crate) type SharedMultiState = Arc<Mutex<MultiState>>;
+
+pub(crate) struct MultiTransport {
+    local_addrs: Vec<SocketAddr>,
+    senders: Vec<Box<dyn PacketSender>>,
+    receivers: Vec<Box<dyn PacketReceiver>>,
+    state: SharedMultiState,
+}
+
+impl MultiTransport {
+    /// Create a transport from the (local address, sender, receiver) of each transport.
+    ///
+    /// The local address of the first transport is used as the local address of the `MultiTransport`.
+    pub(crate) fn new(
+        transports: Vec<(SocketAddr, Box<dyn PacketSender>, Box<dyn PacketReceiver>)>,
+    ) -> Self {
+        let local_addrs = transports.iter().map(|(addr, _, _)| *addr).collect();
+        let state = MultiState::new(transports.len());
+        let (senders, receivers) = transports
+            .into_iter()
+            .map(|(_, sender, receiver)| (sender, receiver))
+            .unzip();
+        Self {
+            local_addrs,
+            senders,
+            receivers,
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    /// Handle to the state of the transport, used to read the per-transport stats
+    pub(crate) fn state(&self) -> SharedMultiState {
+        self.state.clone()
+    }
+
+    /// The local addresses of all the transports
+    pub(crate) fn local_addrs(&self) -> &[SocketAddr] {
+        &self.local_addrs
+    }
+}
+
+impl Transport for MultiTransport {
+    fn local_addr(&self) -> SocketAddr {
+        self.local_addrs.first().copied().unwrap_or(LOCAL_SOCKET)
+    }
+
+    fn listen(self) -> (Box<dyn PacketSender>, Box<dyn PacketReceiver>) {
+        let sender = MultiSender {
+            senders: self.senders,
+            state: self.state.clone(),
+        };
+        let receiver = MultiReceiver {
+            disabled: vec![false; self.receivers.len()],
+            receivers: self.receivers,
+            next: 0,
+            state: self.state,
+        };
+        (Box::new(sender), Box::new(receiver))
+    }
+}
+
+struct MultiSender {
+    senders: Vec<Box<dyn PacketSender>>,
+    state: SharedMultiState,
+}
+
+impl PacketSender for MultiSender {
+    /// Send the packet through the transport that we last received a packet from `address` on
+    fn send(&mut self, payload: &[u8], address: &SocketAddr) -> std::io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let index = state
+            .routes
+            .get(address)
+            .ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    format!("no transport has received packets from {}", address),
+                )
+            })?
+            .transport;
+        let stats = &mut state.stats[index];
+        stats.bytes_sent += payload.len();
+        stats.packets_sent += 1;
+        drop(state);
+        self.senders[index].send(payload, address)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        for sender in self.senders.iter_mut() {
+            sender.flush()?;
+        }
+        Ok(())
+    }
+}
+
+struct MultiReceiver {
+    receivers: Vec<Box<dyn PacketReceiver>>,
+    /// Receivers that failed with a non-transient error, and are not polled anymore
+    disabled: Vec<bool>,
+    /// Index of the transport to poll first, so that a busy transport cannot starve the others
+    next: usize,
+    state: SharedMultiState,
+}
+
+impl PacketReceiver for MultiReceiver {
+    fn recv(&mut self) -> std::io::Result<Option<(&mut [u8], SocketAddr)>> {
+        let now = Instant::now();
+        self.state.lock().unwrap().remove_expired_routes(now);
+        let num_transports = self.receivers.len();
+        let (head, tail) = self.receivers.split_at_mut(self.next);
+        let start = head.len();
+        let ordered = tail
+            .iter_mut()
+            .enumerate()
+            .map(|(i, receiver)| (start + i, receiver))
+            .chain(head.iter_mut().enumerate());
+        for (index, receiver) in ordered {
+            if self.disabled[index] {
+                continue;
+            }
+            match receiver.recv() {
+                Ok(Some((buffer, addr))) => {
+                    let mut state = self.state.lock().unwrap();
+                    state.learn_route(addr, index, now);
+                    let stats = &mut state.stats[index];
+                    stats.bytes_received += buffer.len();
+                    stats.packets_received += 1;
+                    self.next = (index + 1) % num_transports;
+                    return Ok(Some((buffer, addr)));
+                }
+                Ok(None) => {}
+                Err(e) if is_transient(&e) => {
+                    debug!(transport = index, "Error receiving packet: {}", e)
+                }
+                // a failing transport should not prevent receiving packets from the other ones
+                Err(e) => {
+                    error!(
+                        transport = index,
+                        "Error receiving packet, the transport is disabled: {}", e
+                    );
+                    self.disabled[index] = true;
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossbeam_channel::unbounded;
+
+    use crate::transport::io::{IoConfig, TransportConfig};
+
+    use super::*;
+
+    #[test]
+    fn test_multi_transport_routing() {
+        let addr_a = SocketAddr::from(([127, 0, 0, 1], 1000));
+        let addr_b = SocketAddr::from(([127, 0, 0, 1], 2000));
+        let (to_server_a, from_a) = unbounded();
+        let (to_a, from_server_a) = unbounded();
+        let (to_server_b, from_b) = unbounded();
+        let (to_b, from_server_b) = unbounded();
+        let mut io = IoConfig::from_transport(TransportConfig::Multi(vec![
+            TransportConfig::Channels {
+                channels: vec![(addr_a, from_a, to_a)],
+            },
+            TransportConfig::Channels {
+                channels: vec![(addr_b, from_b, to_b)],
+            },
+        ]))
+        .get_io();
+
+        // packets from both transports are received
+        to_server_a.send(vec![1, 2]).unwrap();
+        to_server_b.send(vec![3]).unwrap();
+        let mut received = vec![];
+        while let Some((buffer, addr)) = io.recv().unwrap() {
+            received.push((buffer.to_vec(), addr));
+        }
+        received.sort();
+        assert_eq!(received, vec![(vec![1, 2], addr_a), (vec![3], addr_b)]);
+
+        // packets are sent through the transport the remote address was seen on
+        io.send(&[4], &addr_b).unwrap();
+        assert_eq!(from_server_b.try_recv().unwrap(), vec![4]);
+        assert!(from_server_a.try_recv().is_err());
+
+        let stats = io.transport_stats();
+        assert_eq!(stats[0].packets_received, 1);
+        assert_eq!(stats[0].bytes_received, 2);
+        assert_eq!(stats[0].packets_sent, 0);
+        assert_eq!(stats[1].packets_received, 1);
+        assert_eq!(stats[1].packets_sent, 1);
+        assert_eq!(io.stats().packets_received, 2);
+
+        // we cannot send to an address that we never received from
+        assert!(io
+            .send(&[5], &SocketAddr::from(([127, 0, 0, 1], 3000)))
+            .is_err());
+
+        assert_eq!(io.local_addrs().len(), 2);
+    }
+
+    #[test]
+    fn test_route_is_not_overwritten() {
+        let addr = SocketAddr::from(([127, 0, 0, 1], 1000));
+        let start = Instant::now();
+        let mut state = MultiState::new(2);
+        state.learn_route(addr, 0, start);
+        // a packet claiming to come from the same address on another transport doesn't change the route
+        state.learn_route(addr, 1, start + Duration::from_secs(1));
+        assert_eq!(state.routes[&addr].transport, 0);
+        assert_eq!(state.routes[&addr].last_received, start);
+
+        // once the route expired, the address can be learned on another transport
+        state.remove_expired_routes(start + ROUTE_TIMEOUT);
+        state.learn_route(addr, 1, start + ROUTE_TIMEOUT);
+        assert_eq!(state.routes[&addr].transport, 1);
+    }
+
+    #[test]
+    fn test_failed_receiver_is_disabled() {
+        struct FailingReceiver {
+            calls: usize,
+        }
+        impl PacketReceiver for FailingReceiver {
+            fn recv(&mut self) -> std::io::Result<Option<(&mut [u8], SocketAddr)>> {
+                self.calls += 1;
+                Err(std::io::Error::other("closed"))
+            }
+        }
+        let mut receiver = MultiReceiver {
+            receivers: vec![Box::new(FailingReceiver { calls: 0 })],
+            disabled: vec![false],
+            next: 0,
+            state: Arc::new(Mutex::new(MultiState::new(1))),
+        };
+        assert!(receiver.recv().unwrap().is_none());
+        assert!(receiver.recv().unwrap().is_none());
+        assert_eq!(receiver.disabled, vec![true]);
+    }
+
+    #[test]
+    fn test_route_timeout() {
+        let addr_a = SocketAddr::from(([127, 0, 0, 1], 1000));
+        let addr_b = SocketAddr::from(([127, 0, 0, 1], 2000));
+        let start = Instant::now();
+        let mut state = MultiState::new(1);
+        state.routes.insert(
+            addr_a,
+            Route {
+                transport: 0,
+                last_received: start,
+            },
+        );
+        state.routes.insert(
+            addr_b,
+            Route {
+                transport: 0,
+                last_received: start + ROUTE_TIMEOUT - Duration::from_secs(5),
+            },
+        );
+
+        state.remove_expired_routes(start + ROUTE_TIMEOUT - Duration::from_secs(1));
+        assert_eq!(state.routes.len(), 2);
+
+        // the route of the address that stayed silent is removed
+        state.remove_expired_routes(start + ROUTE_TIMEOUT);
+        assert!(!state.routes.contains_key(&addr_a));
+        assert!(state.routes.contains_key(&addr_b));
+    }
+}
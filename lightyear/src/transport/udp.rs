@@ -3,15 +3,11 @@ use std::net::SocketAddr;
 use std::sync::{Arc, Mutex};
 use tracing::info;
 
-use crate::transport::{PacketReceiver, PacketSender, Transport};
+use crate::transport::{PacketReceiver, PacketSender, Transport, MTU};
 
 // use anyhow::Result;
 // use anyhow::{anyhow, Context};
 
-// Maximum transmission units; maximum size in bytes of a UDP packet
-// See: https://gafferongames.com/post/packet_fragmentation_and_reassembly/
-const MTU: usize = 1472;
-
 /// UDP Socket
 #[derive(Clone)]
 pub struct UdpSocket {
@@ -136,6 +132,7 @@ mod tests {
                 incoming_latency: Duration::from_millis(100),
                 incoming_jitter: Duration::from_millis(0),
                 incoming_loss: 0.0,
+                ..Default::default()
             },
         );
 
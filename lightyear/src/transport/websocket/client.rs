@@ -0,0 +1,203 @@
+//! WebSocket client implementation.
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use tokio::net::{TcpSocket, TcpStream};
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::{TryRecvError, TrySendError};
+use tokio_tungstenite::tungstenite::Message;
+use tracing::{debug, error, info, trace, warn};
+
+use crate::transport::websocket::{websocket_config, CHANNEL_CAPACITY};
+use crate::transport::{PacketReceiver, PacketSender, Transport};
+
+/// Number of attempts to connect to the server before giving up
+const CONNECT_ATTEMPTS: usize = 10;
+/// Delay between two attempts to connect to the server
+const CONNECT_RETRY_DELAY: Duration = Duration::from_millis(200);
+
+/// WebSocket client socket
+pub struct WebSocketClientSocket {
+    client_addr: SocketAddr,
+    server_addr: SocketAddr,
+}
+
+impl WebSocketClientSocket {
+    /// Bind the local tcp socket (so that the actual address is known when binding to port 0)
+    pub fn new(client_addr: SocketAddr, server_addr: SocketAddr) -> std::io::Result<Self> {
+        let client_addr = Self::bind(client_addr)?.local_addr()?;
+        Ok(Self {
+            client_addr,
+            server_addr,
+        })
+    }
+
+    fn bind(client_addr: SocketAddr) -> std::io::Result<TcpSocket> {
+        let socket = if client_addr.is_ipv4() {
+            TcpSocket::new_v4()
+        } else {
+            TcpSocket::new_v6()
+        }?;
+        // the address is bound again for each connection attempt
+        socket.set_reuseaddr(true)?;
+        socket.bind(client_addr)?;
+        Ok(socket)
+    }
+
+    /// Open the tcp connection to the server, retrying if the server is not reachable yet
+    async fn connect(
+        client_addr: SocketAddr,
+        server_addr: SocketAddr,
+    ) -> std::io::Result<TcpStream> {
+        let mut attempt = 1;
+        loop {
+            // bind the tcp socket ourselves so that the server sees the correct client address
+            let result = match Self::bind(client_addr) {
+                Ok(socket) => socket.connect(server_addr).await,
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(stream) => return Ok(stream),
+                Err(e) if attempt < CONNECT_ATTEMPTS => {
+                    warn!(
+                        "failed to connect to server (attempt {}/{}): {:?}",
+                        attempt, CONNECT_ATTEMPTS, e
+                    );
+                    attempt += 1;
+                    tokio::time::sleep(CONNECT_RETRY_DELAY).await;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+impl Transport for WebSocketClientSocket {
+    fn local_addr(&self) -> SocketAddr {
+        self.client_addr
+    }
+
+    fn listen(self) -> (Box<dyn PacketSender>, Box<dyn PacketReceiver>) {
+        let client_addr = self.client_addr;
+        let server_addr = self.server_addr;
+        let (to_server_sender, mut to_server_receiver) = mpsc::channel::<Vec<u8>>(CHANNEL_CAPACITY);
+        let (from_server_sender, from_server_receiver) = mpsc::channel(CHANNEL_CAPACITY);
+
+        tokio::spawn(async move {
+            let server_url = format!("ws://{}", server_addr);
+            debug!(
+                "Starting client websocket task with server url: {}",
+                &server_url
+            );
+            let stream = match Self::connect(client_addr, server_addr).await {
+                Ok(stream) => stream,
+                Err(e) => {
+                    error!("failed to connect to server: {:?}", e);
+                    return;
+                }
+            };
+            let Ok((ws_stream, _)) = tokio_tungstenite::client_async_with_config(
+                server_url,
+                stream,
+                Some(websocket_config()),
+            )
+            .await
+            else {
+                error!("websocket handshake with server failed");
+                return;
+            };
+            let (mut write, mut read) = ws_stream.split();
+            loop {
+                tokio::select! {
+                    // receive messages from server
+                    x = read.next() => {
+                        match x {
+                            Some(Ok(Message::Binary(data))) => {
+                                match from_server_sender.try_send(data.to_vec()) {
+                                    Ok(()) => {}
+                                    Err(TrySendError::Full(_)) => {
+                                        trace!("receive buffer is full, dropping packet from server");
+                                    }
+                                    // the client socket was dropped
+                                    Err(TrySendError::Closed(_)) => return,
+                                }
+                            }
+                            Some(Ok(Message::Close(_))) | None => {
+                                info!("Connection closed");
+                                return;
+                            }
+                            Some(Ok(_)) => {}
+                            Some(Err(e)) => {
+                                error!("receive websocket message error: {:?}", e);
+                                return;
+                            }
+                        }
+                    }
+
+                    // send messages to server
+                    Some(msg) = to_server_receiver.recv() => {
+                        if let Err(e) = write.send(Message::Binary(msg.into())).await {
+                            error!("send websocket message error: {:?}", e);
+                            return;
+                        }
+                    }
+                    // the client socket was dropped
+                    _ = from_server_sender.closed() => return,
+                }
+            }
+        });
+        let packet_sender = WebSocketClientPacketSender { to_server_sender };
+        let packet_receiver = WebSocketClientPacketReceiver {
+            server_addr,
+            from_server_receiver,
+            buffer: vec![],
+        };
+        (Box::new(packet_sender), Box::new(packet_receiver))
+    }
+}
+
+struct WebSocketClientPacketSender {
+    to_server_sender: mpsc::Sender<Vec<u8>>,
+}
+
+impl PacketSender for WebSocketClientPacketSender {
+    fn send(&mut self, payload: &[u8], address: &SocketAddr) -> std::io::Result<()> {
+        match self.to_server_sender.try_send(payload.to_vec()) {
+            Err(TrySendError::Full(_)) => {
+                trace!("send buffer is full, dropping packet to server");
+                Ok(())
+            }
+            result => result.map_err(|e| {
+                std::io::Error::other(format!("send websocket message error: {:?}", e))
+            }),
+        }
+    }
+}
+
+struct WebSocketClientPacketReceiver {
+    server_addr: SocketAddr,
+    from_server_receiver: mpsc::Receiver<Vec<u8>>,
+    buffer: Vec<u8>,
+}
+
+impl PacketReceiver for WebSocketClientPacketReceiver {
+    fn recv(&mut self) -> std::io::Result<Option<(&mut [u8], SocketAddr)>> {
+        match self.from_server_receiver.try_recv() {
+            Ok(data) => {
+                self.buffer = data;
+                Ok(Some((self.buffer.as_mut_slice(), self.server_addr)))
+            }
+            Err(e) => {
+                if e == TryRecvError::Empty {
+                    Ok(None)
+                } else {
+                    Err(std::io::Error::other(format!(
+                        "receive websocket message error: {:?}",
+                        e
+                    )))
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,108 @@
+//! Transport using the WebSocket protocol (based on TCP)
+//!
+//! Each packet is sent as a single binary frame.
+use tokio_tungstenite::tungstenite::protocol::WebSocketConfig;
+
+use crate::transport::MTU;
+
+pub(crate) mod client;
+pub(crate) mod server;
+
+/// Number of packets that can be buffered in each direction between the socket and the websocket task.
+/// Packets are dropped when the buffer is full, as they would be by a UDP socket.
+const CHANNEL_CAPACITY: usize = 256;
+
+/// Each packet is sent in its own frame, so frames and messages are never bigger than a packet
+fn websocket_config() -> WebSocketConfig {
+    WebSocketConfig::default()
+        .max_message_size(Some(MTU))
+        .max_frame_size(Some(MTU))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::client::*;
+    use super::server::*;
+    use crate::transport::{PacketReceiver, PacketSender, Transport};
+    use std::net::SocketAddr;
+    use std::time::Duration;
+
+    /// Wait until a packet is received (the websocket handshake can take a few round-trips)
+    async fn wait_for_packet(receiver: &mut Box<dyn PacketReceiver>) -> (Vec<u8>, SocketAddr) {
+        for _ in 0..100 {
+            if let Some((data, address)) = receiver.recv().unwrap() {
+                return (data.to_vec(), address);
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("expected to receive a packet");
+    }
+
+    /// The client keeps trying to connect if the server is not listening yet
+    #[tokio::test]
+    async fn test_websocket_client_retries_connection() -> anyhow::Result<()> {
+        // reserve a free port for the server
+        let server_addr = std::net::TcpListener::bind("127.0.0.1:0")?.local_addr()?;
+        let client_socket =
+            WebSocketClientSocket::new("127.0.0.1:0".parse().unwrap(), server_addr)?;
+        let client_addr = client_socket.local_addr();
+        let (mut client_send, _) = client_socket.listen();
+        client_send.send(b"hello", &server_addr)?;
+
+        // the server starts listening after the first connection attempt failed
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let (_, mut server_recv) = WebSocketServerSocket::new(server_addr)?.listen();
+        let (recv_msg, address) = wait_for_packet(&mut server_recv).await;
+        assert_eq!(address, client_addr);
+        assert_eq!(recv_msg, b"hello");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_websocket_socket() -> anyhow::Result<()> {
+        // the server is listening as soon as the socket is created
+        let server_socket = WebSocketServerSocket::new("127.0.0.1:0".parse().unwrap())?;
+        let server_addr = server_socket.local_addr();
+        let client_socket =
+            WebSocketClientSocket::new("127.0.0.1:0".parse().unwrap(), server_addr)?;
+        let client_addr = client_socket.local_addr();
+        assert_ne!(server_addr.port(), 0);
+        assert_ne!(client_addr.port(), 0);
+
+        let (mut server_send, mut server_recv) = server_socket.listen();
+        let (mut client_send, mut client_recv) = client_socket.listen();
+
+        let msg = b"hello world";
+
+        // client to server
+        client_send.send(msg, &server_addr)?;
+        let (recv_msg, address) = wait_for_packet(&mut server_recv).await;
+        assert_eq!(address, client_addr);
+        assert_eq!(recv_msg, msg);
+
+        // server to client
+        server_send.send(msg, &client_addr)?;
+        let (recv_msg, address) = wait_for_packet(&mut client_recv).await;
+        assert_eq!(address, server_addr);
+        assert_eq!(recv_msg, msg);
+        Ok(())
+    }
+
+    /// The server stops accepting connections once the server socket is dropped
+    #[tokio::test]
+    async fn test_websocket_server_stops_when_dropped() -> anyhow::Result<()> {
+        let server_socket = WebSocketServerSocket::new("127.0.0.1:0".parse().unwrap())?;
+        let server_addr = server_socket.local_addr();
+        let (server_send, server_recv) = server_socket.listen();
+        drop((server_send, server_recv));
+
+        // the listener is closed when the accept loop exits
+        for _ in 0..100 {
+            if tokio::net::TcpStream::connect(server_addr).await.is_err() {
+                return Ok(());
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("the server is still accepting connections");
+    }
+}
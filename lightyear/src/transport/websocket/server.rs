@@ -0,0 +1,219 @@
+//! WebSocket server implementation.
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use futures_util::{SinkExt, StreamExt};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::{TryRecvError, TrySendError};
+use tokio::sync::mpsc::{Receiver, Sender};
+use tokio_tungstenite::tungstenite::Message;
+use tracing::{debug, error, info, trace};
+
+use crate::transport::websocket::{websocket_config, CHANNEL_CAPACITY};
+use crate::transport::{PacketReceiver, PacketSender, Transport};
+
+type ClientChannels = Arc<Mutex<HashMap<SocketAddr, Sender<Vec<u8>>>>>;
+
+/// WebSocket server socket
+///
+/// Each websocket connection is identified by the `SocketAddr` of the remote client.
+pub struct WebSocketServerSocket {
+    /// The listener is bound when the socket is created, so that clients can connect
+    /// as soon as the socket is returned
+    listener: std::net::TcpListener,
+}
+
+impl WebSocketServerSocket {
+    pub(crate) fn new(server_addr: SocketAddr) -> std::io::Result<Self> {
+        let listener = std::net::TcpListener::bind(server_addr)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self { listener })
+    }
+
+    pub async fn handle_client(
+        stream: TcpStream,
+        client_addr: SocketAddr,
+        from_client_sender: Sender<(Vec<u8>, SocketAddr)>,
+        to_client_channels: ClientChannels,
+    ) {
+        let ws_stream =
+            match tokio_tungstenite::accept_async_with_config(stream, Some(websocket_config()))
+                .await
+            {
+                Ok(ws_stream) => ws_stream,
+                Err(e) => {
+                    error!(
+                        "websocket handshake with client {} failed: {:?}",
+                        client_addr, e
+                    );
+                    return;
+                }
+            };
+        debug!(
+            "Spawning new task to create connection with client: {}",
+            client_addr
+        );
+
+        // add a new channel for this client
+        let (to_client_sender, mut to_client_receiver) = mpsc::channel::<Vec<u8>>(CHANNEL_CAPACITY);
+        to_client_channels
+            .lock()
+            .unwrap()
+            .insert(client_addr, to_client_sender);
+
+        // connection established, waiting for data from client
+        let (mut write, mut read) = ws_stream.split();
+        loop {
+            tokio::select! {
+                // receive messages from client
+                x = read.next() => {
+                    match x {
+                        Some(Ok(Message::Binary(data))) => {
+                            match from_client_sender.try_send((data.to_vec(), client_addr)) {
+                                Ok(()) => {}
+                                Err(TrySendError::Full(_)) => {
+                                    trace!("receive buffer is full, dropping packet from client {}", client_addr);
+                                }
+                                // the server socket was dropped
+                                Err(TrySendError::Closed(_)) => break,
+                            }
+                        }
+                        // client disconnected
+                        Some(Ok(Message::Close(_))) | None => {
+                            info!("Connection closed");
+                            break;
+                        }
+                        Some(Ok(_)) => {}
+                        Some(Err(e)) => {
+                            error!("receive websocket message error: {:?}", e);
+                            break;
+                        }
+                    }
+                }
+                // send messages to client
+                Some(msg) = to_client_receiver.recv() => {
+                    if let Err(e) = write.send(Message::Binary(msg.into())).await {
+                        error!("send websocket message error: {:?}", e);
+                        break;
+                    }
+                }
+                // the server socket was dropped
+                _ = from_client_sender.closed() => break,
+            }
+        }
+        to_client_channels.lock().unwrap().remove(&client_addr);
+    }
+}
+
+impl Transport for WebSocketServerSocket {
+    fn local_addr(&self) -> SocketAddr {
+        self.listener
+            .local_addr()
+            .expect("error getting local addr")
+    }
+
+    fn listen(self) -> (Box<dyn PacketSender>, Box<dyn PacketReceiver>) {
+        let listener = self.listener;
+        let (from_client_sender, from_client_receiver) = mpsc::channel(CHANNEL_CAPACITY);
+        let to_client_senders: ClientChannels = Arc::new(Mutex::new(HashMap::new()));
+
+        let packet_sender = WebSocketServerSocketSender {
+            to_client_senders: to_client_senders.clone(),
+        };
+        let packet_receiver = WebSocketServerSocketReceiver {
+            buffer: vec![],
+            from_client_receiver,
+        };
+
+        tokio::spawn(async move {
+            debug!("Starting server websocket task");
+            let listener = match tokio::net::TcpListener::from_std(listener) {
+                Ok(listener) => listener,
+                Err(e) => {
+                    error!("failed to register the tcp listener: {:?}", e);
+                    return;
+                }
+            };
+
+            loop {
+                // new client connecting
+                let accepted = tokio::select! {
+                    accepted = listener.accept() => accepted,
+                    // the server socket was dropped, stop listening
+                    _ = from_client_sender.closed() => {
+                        debug!("Stopping server websocket task");
+                        return;
+                    }
+                };
+                let (stream, client_addr) = match accepted {
+                    Ok(x) => x,
+                    Err(e) => {
+                        error!("failed to accept tcp connection: {:?}", e);
+                        continue;
+                    }
+                };
+
+                tokio::spawn(Self::handle_client(
+                    stream,
+                    client_addr,
+                    from_client_sender.clone(),
+                    to_client_senders.clone(),
+                ));
+            }
+        });
+        (Box::new(packet_sender), Box::new(packet_receiver))
+    }
+}
+
+struct WebSocketServerSocketSender {
+    to_client_senders: ClientChannels,
+}
+
+impl PacketSender for WebSocketServerSocketSender {
+    fn send(&mut self, payload: &[u8], address: &SocketAddr) -> std::io::Result<()> {
+        if let Some(to_client_sender) = self.to_client_senders.lock().unwrap().get(address) {
+            match to_client_sender.try_send(payload.to_vec()) {
+                Err(TrySendError::Full(_)) => {
+                    trace!("send buffer is full, dropping packet to client {}", address);
+                    Ok(())
+                }
+                result => result.map_err(|e| {
+                    std::io::Error::other(format!("unable to send message to client: {}", e))
+                }),
+            }
+        } else {
+            Err(std::io::Error::other(format!(
+                "unable to find channel for client: {}",
+                address
+            )))
+        }
+    }
+}
+
+struct WebSocketServerSocketReceiver {
+    buffer: Vec<u8>,
+    from_client_receiver: Receiver<(Vec<u8>, SocketAddr)>,
+}
+
+impl PacketReceiver for WebSocketServerSocketReceiver {
+    fn recv(&mut self) -> std::io::Result<Option<(&mut [u8], SocketAddr)>> {
+        match self.from_client_receiver.try_recv() {
+            Ok((data, addr)) => {
+                self.buffer = data;
+                Ok(Some((self.buffer.as_mut_slice(), addr)))
+            }
+            Err(e) => {
+                if e == TryRecvError::Empty {
+                    Ok(None)
+                } else {
+                    Err(std::io::Error::other(format!(
+                        "unable to receive message from client: {}",
+                        e
+                    )))
+                }
+            }
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use crate::shared::bit_serializable_impl;
 use darling::ast::NestedMeta;
 use darling::util::PathList;
 use darling::{Error, FromField, FromMeta};
@@ -18,7 +19,7 @@ struct MacroAttrs {
     derive: PathList,
 }
 
-const ATTRIBUTES: &[&str] = &["sync"];
+const ATTRIBUTES: &[&str] = &["sync", "replication"];
 
 #[derive(Debug, FromField)]
 #[darling(attributes(sync))]
@@ -44,6 +45,20 @@ struct SyncField {
     corrector: Option<Ident>,
 }
 
+#[derive(Debug, FromField)]
+#[darling(attributes(replication))]
+struct ReplicationField {
+    // name of the enum field
+    ident: Option<Ident>,
+
+    // type of the field
+    ty: Type,
+
+    /// If true, component updates are sent as a delta compared to the last acked value
+    #[darling(default)]
+    delta: bool,
+}
+
 impl SyncField {
     fn get_mode_tokens(&self) -> TokenStream {
         let mut tokens = quote! {};
@@ -149,7 +164,14 @@ pub fn component_protocol_impl(
 
     // Helper Properties
     let fields = get_fields(&input);
-    let input_without_attributes = strip_attributes(&input);
+
+    // components registered at runtime with `AppRegistrationExt::register_component` are sent in a hidden variant,
+    // which is handled separately because it carries any registered component type
+    let mut full_input = input.clone();
+    full_input.variants.push(parse_quote! {
+        DynamicComponent(DynamicComponent)
+    });
+    let input_without_attributes = strip_attributes(&full_input);
 
     // Use darling to parse the attributes for each field
     let sync_fields: Vec<SyncField> = fields
@@ -160,10 +182,15 @@ pub fn component_protocol_impl(
     for field in &sync_fields {
         field.check_is_valid();
     }
+    let replication_fields: Vec<ReplicationField> = fields
+        .iter()
+        .map(|field| FromField::from_field(field).unwrap())
+        .collect();
 
     // Names
     let enum_name = &input.ident;
     let enum_kind_name = format_ident!("{}Kind", enum_name);
+    let enum_delta_name = format_ident!("{}Delta", enum_name);
     let lowercase_struct_name = Ident::new(
         enum_name.to_string().to_lowercase().as_str(),
         Span::call_site(),
@@ -174,21 +201,31 @@ pub fn component_protocol_impl(
     let sync_component_impl = sync_metadata_impl(&sync_fields, enum_name);
 
     // Methods
+    let type_names_method = type_names_method(&fields);
     let add_systems_method = add_per_component_replication_send_systems_method(&fields, protocol);
     let add_events_method = add_events_method(&fields);
     let push_component_events_method = push_component_events_method(&fields, protocol);
     let add_sync_systems_method = add_sync_systems_method(&sync_fields, protocol);
     // let mode_method = mode_method(&input, &fields);
-    let encode_method = encode_method();
-    let decode_method = decode_method();
-    let delegate_method = delegate_method(&input, &enum_kind_name);
+    let bit_serializable_impl =
+        bit_serializable_impl(enum_name, &get_fields(&full_input), &shared_crate_name);
+    let delegate_method = delegate_method(&full_input, &enum_kind_name);
     let insert_method = insert_method(&input, &fields);
     let update_method = update_method(&input, &fields);
+    let delta_methods = delta_methods(&replication_fields, &enum_delta_name, &shared_crate_name);
 
     // EnumKind methods
     let enum_kind = get_enum_kind(&input, &enum_kind_name);
     let from_method = from_method(&input, &enum_kind_name, &fields);
     let remove_method = remove_method(&input, &fields, &enum_kind_name);
+    let is_delta_compressed_method =
+        is_delta_compressed_method(&replication_fields, &enum_kind_name);
+    let enum_delta = get_enum_delta(
+        &replication_fields,
+        &enum_delta_name,
+        &enum_kind_name,
+        &shared_crate_name,
+    );
 
     let gen = quote! {
         #[doc(hidden)]
@@ -204,7 +241,7 @@ pub fn component_protocol_impl(
             #[cfg(feature = "leafwing")]
             use leafwing_input_manager::prelude::*;
 
-            #[derive(Serialize, Deserialize, Clone, PartialEq)]
+            #[derive(Clone, PartialEq)]
             #extra_derives
             #[enum_delegate::implement(ComponentBehaviour)]
             #input_without_attributes
@@ -212,12 +249,14 @@ pub fn component_protocol_impl(
             impl ComponentProtocol for #enum_name {
                 type Protocol = #protocol;
 
+                #type_names_method
                 #insert_method
                 #update_method
                 #add_systems_method
                 #add_events_method
                 #push_component_events_method
                 #add_sync_systems_method
+                #delta_methods
                 // #mode_method
             }
 
@@ -252,9 +291,11 @@ pub fn component_protocol_impl(
             }
 
             #from_method
+            #enum_delta
 
             impl ComponentKindBehaviour for #enum_kind_name {
                 #remove_method
+                #is_delta_compressed_method
             }
 
             impl std::fmt::Display for #enum_kind_name {
@@ -262,14 +303,11 @@ pub fn component_protocol_impl(
                     std::fmt::Debug::fmt(self, f)
                 }
             }
-            // TODO: we don't need to implement for now because we get it for free from Serialize + Deserialize + Clone
-            // impl BitSerializable for #enum_name {
-            //     #encode_method
-            //     #decode_method
-            // }
+            #bit_serializable_impl
         }
         pub use #module_name::#enum_name as #enum_name;
         pub use #module_name::#enum_kind_name as #enum_kind_name;
+        pub use #module_name::#enum_delta_name as #enum_delta_name;
     };
 
     proc_macro::TokenStream::from(gen)
@@ -470,29 +508,14 @@ fn add_sync_systems_method(fields: &Vec<SyncField>, protocol_name: &Ident) -> To
     }
 }
 
-fn encode_method() -> TokenStream {
-    quote! {
-        fn encode(&self, writer: &mut impl WriteBuffer) -> anyhow::Result<()> {
-            writer.serialize(&self)
-        }
-    }
-}
-
-fn decode_method() -> TokenStream {
-    quote! {
-        fn decode(reader: &mut impl ReadBuffer) -> anyhow::Result<Self>
-            where Self: Sized{
-            reader.deserialize::<Self>()
-        }
-    }
-}
-
 fn get_enum_kind(input: &ItemEnum, enum_kind_name: &Ident) -> TokenStream {
     // we use the original enum's names for the kind enum
     let variants = input.variants.iter().map(|v| v.ident.clone());
     quote! {
         pub enum #enum_kind_name {
-            #(#variants),*
+            #(#variants,)*
+            /// A component registered with `AppRegistrationExt::register_component`, identified by its net id
+            DynamicComponent(u16),
         }
     }
 }
@@ -524,6 +547,7 @@ fn from_method(input: &ItemEnum, enum_kind_name: &Ident, fields: &Vec<Field>) ->
             fn from(value: &'a #enum_name) -> Self {
                 match value {
                     #body
+                    &#enum_name::DynamicComponent(ref c) => #enum_kind_name::DynamicComponent(c.net_id()),
                 }
             }
         }
@@ -543,18 +567,108 @@ fn remove_method(input: &ItemEnum, fields: &[Field], enum_kind_name: &Ident) ->
     for (component_type, component_kind_name) in component_types.zip(component_kind_names) {
         field_body = quote! {
             #field_body
-            #enum_kind_name::#component_kind_name => entity.remove::<#component_type>(),
+            #enum_kind_name::#component_kind_name => {
+                entity.remove::<#component_type>();
+            }
         };
     }
     quote! {
         fn remove(self, entity: &mut EntityWorldMut) {
             match self {
                 #field_body
+                #enum_kind_name::DynamicComponent(net_id) => DynamicComponent::remove(net_id, entity),
             };
         }
     }
 }
 
+fn is_delta_compressed_method(fields: &[ReplicationField], enum_kind_name: &Ident) -> TokenStream {
+    let mut body = quote! {};
+    for field in fields {
+        let ident = &field.ident;
+        let delta = field.delta;
+        body = quote! {
+            #body
+            #enum_kind_name::#ident => #delta,
+        };
+    }
+    quote! {
+        fn is_delta_compressed(&self) -> bool {
+            match self {
+                #body
+                #enum_kind_name::DynamicComponent(_) => false,
+            }
+        }
+    }
+}
+
+fn delta_methods(
+    fields: &[ReplicationField],
+    enum_delta_name: &Ident,
+    shared_crate_name: &TokenStream,
+) -> TokenStream {
+    let mut diff_body = quote! {};
+    let mut apply_diff_body = quote! {};
+    for field in fields.iter().filter(|field| field.delta) {
+        let ident = &field.ident;
+        diff_body = quote! {
+            #diff_body
+            (Self::#ident(base), Self::#ident(new)) => Some(#enum_delta_name::#ident(#shared_crate_name::_reexport::Diffable::diff(base, new))),
+        };
+        apply_diff_body = quote! {
+            #apply_diff_body
+            (Self::#ident(base), #enum_delta_name::#ident(delta)) => {
+                let mut value = base.clone();
+                #shared_crate_name::_reexport::Diffable::apply_diff(&mut value, delta);
+                Ok(Self::#ident(value))
+            }
+        };
+    }
+    quote! {
+        type Delta = #enum_delta_name;
+
+        fn diff(&self, new: &Self) -> Option<Self::Delta> {
+            match (self, new) {
+                #diff_body
+                _ => None,
+            }
+        }
+        fn apply_diff(&self, delta: &Self::Delta) -> anyhow::Result<Self> {
+            match (self, delta) {
+                #apply_diff_body
+                _ => Err(anyhow::anyhow!("cannot apply the delta {:?} to component {:?}", delta, self)),
+            }
+        }
+    }
+}
+
+/// Enum containing the delta of each delta-compressed component, so that deltas can be serialized
+/// directly inside the replication messages
+fn get_enum_delta(
+    fields: &[ReplicationField],
+    enum_delta_name: &Ident,
+    enum_kind_name: &Ident,
+    shared_crate_name: &TokenStream,
+) -> TokenStream {
+    let fields: Vec<_> = fields.iter().filter(|field| field.delta).collect();
+    let idents: Vec<_> = fields.iter().map(|field| &field.ident).collect();
+    let tys: Vec<_> = fields.iter().map(|field| &field.ty).collect();
+    quote! {
+        #[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+        pub enum #enum_delta_name {
+            #(#idents(<#tys as #shared_crate_name::_reexport::Diffable>::Delta),)*
+        }
+
+        impl<'a> From<&'a #enum_delta_name> for #enum_kind_name {
+            fn from(value: &'a #enum_delta_name) -> Self {
+                match *value {
+                    #(#enum_delta_name::#idents(_) => #enum_kind_name::#idents,)*
+                }
+            }
+        }
+    }
+}
+
 // fn mode_method(input: &ItemEnum, fields: &Vec<Field>) -> TokenStream {
 //     let mut body = quote! {};
 //     for field in fields {
@@ -616,6 +730,15 @@ fn delegate_method(input: &ItemEnum, enum_kind_name: &Ident) -> TokenStream {
     }
 }
 
+fn type_names_method(fields: &[Field]) -> TokenStream {
+    let types = fields.iter().map(|field| &field.ty);
+    quote! {
+        fn type_names() -> Vec<&'static str> {
+            vec![#(<#types as Named>::NAME),*]
+        }
+    }
+}
+
 fn insert_method(input: &ItemEnum, fields: &Vec<Field>) -> TokenStream {
     let mut body = quote! {};
     for field in fields {
@@ -654,6 +777,7 @@ fn insert_method(input: &ItemEnum, fields: &Vec<Field>) -> TokenStream {
         fn insert(self, entity: &mut EntityWorldMut) {
             match self {
                 #body
+                Self::DynamicComponent(x) => x.insert(entity),
             }
         }
     }
@@ -709,6 +833,7 @@ fn update_method(input: &ItemEnum, fields: &Vec<Field>) -> TokenStream {
         fn update(self, entity: &mut EntityWorldMut) {
             match self {
                 #body
+                Self::DynamicComponent(x) => x.update(entity),
             }
         }
     }
@@ -0,0 +1,123 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, parse_quote, Data, DeriveInput, Fields, GenericParam, Index, Type};
+
+/// Derives the Diffable trait for a struct.
+///
+/// A `{StructName}Delta` struct is generated, where each field is an `Option` that is only
+/// set if the field changed. Every field type must implement `Clone`, `PartialEq`, `Debug`,
+/// `Serialize` and `DeserializeOwned` (and be `Send + Sync + 'static`), because the delta is
+/// serialized and sent over the network.
+pub fn diffable_impl(
+    input: proc_macro::TokenStream,
+    shared_crate_name: TokenStream,
+) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let Data::Struct(data) = &input.data else {
+        panic!("Can only derive Diffable on a struct");
+    };
+
+    // Names
+    let struct_name = &input.ident;
+    let delta_name = format_ident!("{}Delta", struct_name);
+    let vis = &input.vis;
+    let (impl_generics, type_generics, _) = input.generics.split_for_impl();
+
+    // The delta struct is declared with the type generics only: the bounds of the generic parameters
+    // are moved to the where clause, together with the bounds required on every field by the delta.
+    let field_tys: Vec<&Type> = data.fields.iter().map(|f| &f.ty).collect();
+    let mut generics = input.generics.clone();
+    let param_bounds: Vec<syn::WherePredicate> = generics
+        .params
+        .iter()
+        .filter_map(|param| match param {
+            GenericParam::Type(param) if !param.bounds.is_empty() => {
+                let (ident, bounds) = (&param.ident, &param.bounds);
+                Some(parse_quote!(#ident: #bounds))
+            }
+            GenericParam::Lifetime(param) if !param.bounds.is_empty() => {
+                let (lifetime, bounds) = (&param.lifetime, &param.bounds);
+                Some(parse_quote!(#lifetime: #bounds))
+            }
+            _ => None,
+        })
+        .collect();
+    let where_clause = generics.make_where_clause();
+    where_clause.predicates.extend(param_bounds);
+    for ty in &field_tys {
+        where_clause.predicates.push(parse_quote!(
+            #ty: Clone + PartialEq + std::fmt::Debug + serde::Serialize + serde::de::DeserializeOwned + Send + Sync + 'static
+        ));
+    }
+    let where_clause = &generics.where_clause;
+
+    let (delta_struct, diff_body, apply_diff_body) = match &data.fields {
+        Fields::Named(fields) => {
+            let idents: Vec<_> = fields.named.iter().map(|f| &f.ident).collect();
+            let tys = &field_tys;
+            (
+                quote! {
+                    #vis struct #delta_name #type_generics #where_clause {
+                        #(pub #idents: Option<#tys>,)*
+                    }
+                },
+                quote! {
+                    #delta_name {
+                        #(#idents: (self.#idents != new.#idents).then(|| new.#idents.clone()),)*
+                    }
+                },
+                quote! {
+                    #(
+                        if let Some(value) = &delta.#idents {
+                            self.#idents = value.clone();
+                        }
+                    )*
+                },
+            )
+        }
+        Fields::Unnamed(fields) => {
+            let indexes: Vec<_> = (0..fields.unnamed.len()).map(Index::from).collect();
+            let tys = &field_tys;
+            (
+                quote! {
+                    #vis struct #delta_name #type_generics (#(pub Option<#tys>,)*) #where_clause;
+                },
+                quote! {
+                    #delta_name (
+                        #((self.#indexes != new.#indexes).then(|| new.#indexes.clone()),)*
+                    )
+                },
+                quote! {
+                    #(
+                        if let Some(value) = &delta.#indexes {
+                            self.#indexes = value.clone();
+                        }
+                    )*
+                },
+            )
+        }
+        Fields::Unit => {
+            panic!("Cannot derive Diffable on a Unit struct");
+        }
+    };
+
+    let gen = quote! {
+        #[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+        #[serde(bound = "")]
+        #delta_struct
+
+        impl #impl_generics #shared_crate_name::_reexport::Diffable for #struct_name #type_generics #where_clause {
+            type Delta = #delta_name #type_generics;
+
+            fn diff(&self, new: &Self) -> Self::Delta {
+                #diff_body
+            }
+
+            fn apply_diff(&mut self, delta: &Self::Delta) {
+                #apply_diff_body
+            }
+        }
+    };
+
+    proc_macro::TokenStream::from(gen)
+}
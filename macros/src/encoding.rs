@@ -0,0 +1,402 @@
+use proc_macro2::{Literal, TokenStream};
+use quote::{format_ident, quote};
+use syn::spanned::Spanned;
+use syn::{
+    parenthesized, parse_quote, Data, DeriveInput, Expr, ExprRange, Field, Fields, GenericParam,
+    Index, Lit, LitInt, RangeLimits, Type, UnOp,
+};
+
+/// Wire encoding of a field, specified with a `#[lightyear(...)]` attribute
+enum FieldEncoding {
+    /// `#[lightyear(quantize(min = -1000.0, max = 1000.0, precision = 0.01))]`
+    Quantize { min: f64, max: f64, precision: f64 },
+    /// `#[lightyear(range(0..=100))]`
+    Range { min: i128, max: i128 },
+    /// `#[lightyear(smallest_three)]` or `#[lightyear(smallest_three(bits = 15))]`
+    SmallestThree { bits: u32 },
+}
+
+impl FieldEncoding {
+    fn from_field(field: &Field) -> syn::Result<Option<Self>> {
+        let mut encoding = None;
+        for attr in field
+            .attrs
+            .iter()
+            .filter(|a| a.path().is_ident("lightyear"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if encoding.is_some() {
+                    return Err(meta.error("a field can only have one lightyear encoding"));
+                }
+                if meta.path.is_ident("quantize") {
+                    let (mut min, mut max, mut precision) = (None, None, None);
+                    meta.parse_nested_meta(|meta| {
+                        let value = parse_float(&meta.value()?.parse()?)?;
+                        if meta.path.is_ident("min") {
+                            min = Some(value);
+                        } else if meta.path.is_ident("max") {
+                            max = Some(value);
+                        } else if meta.path.is_ident("precision") {
+                            precision = Some(value);
+                        } else {
+                            return Err(meta.error("expected `min`, `max` or `precision`"));
+                        }
+                        Ok(())
+                    })?;
+                    let (Some(min), Some(max), Some(precision)) = (min, max, precision) else {
+                        return Err(meta.error("quantize requires `min`, `max` and `precision`"));
+                    };
+                    if min >= max || precision <= 0.0 || (max - min) / precision >= u64::MAX as f64
+                    {
+                        return Err(
+                            meta.error("quantize requires `min < max` and a positive `precision`")
+                        );
+                    }
+                    encoding = Some(FieldEncoding::Quantize {
+                        min,
+                        max,
+                        precision,
+                    });
+                } else if meta.path.is_ident("range") {
+                    let content;
+                    parenthesized!(content in meta.input);
+                    let range: ExprRange = content.parse()?;
+                    let (Some(start), Some(end)) = (&range.start, &range.end) else {
+                        return Err(syn::Error::new(
+                            range.span(),
+                            "range requires a start and an end",
+                        ));
+                    };
+                    let min = parse_int(start)?;
+                    let mut max = parse_int(end)?;
+                    if let RangeLimits::HalfOpen(_) = range.limits {
+                        max -= 1;
+                    }
+                    if min > max || max - min > u64::MAX as i128 {
+                        return Err(syn::Error::new(range.span(), "invalid range"));
+                    }
+                    encoding = Some(FieldEncoding::Range { min, max });
+                } else if meta.path.is_ident("smallest_three") {
+                    let mut bits = 10;
+                    if meta.input.peek(syn::token::Paren) {
+                        meta.parse_nested_meta(|meta| {
+                            if !meta.path.is_ident("bits") {
+                                return Err(meta.error("expected `bits`"));
+                            }
+                            bits = meta.value()?.parse::<LitInt>()?.base10_parse()?;
+                            Ok(())
+                        })?;
+                    }
+                    if !(1..=20).contains(&bits) {
+                        return Err(meta.error("smallest_three requires between 1 and 20 bits"));
+                    }
+                    encoding = Some(FieldEncoding::SmallestThree { bits });
+                } else {
+                    return Err(meta.error("expected `quantize`, `range` or `smallest_three`"));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(encoding)
+    }
+
+    /// Number of bits needed to hold the encoded value
+    fn bits(&self) -> u32 {
+        match self {
+            FieldEncoding::Quantize {
+                min,
+                max,
+                precision,
+            } => {
+                // must match `quantize::num_steps`
+                let num_steps = ((max - min) / precision).ceil() as u64;
+                64 - num_steps.leading_zeros()
+            }
+            FieldEncoding::Range { min, max } => 64 - ((max - min) as u64).leading_zeros(),
+            FieldEncoding::SmallestThree { bits } => 2 + 3 * bits,
+        }
+    }
+
+    /// Integer type that is written with the number of bits needed to hold the encoded value
+    fn wire_int(&self, shared_crate_name: &TokenStream) -> TokenStream {
+        let bits = self.bits() as usize;
+        quote! { #shared_crate_name::_reexport::quantize::Bits<#bits> }
+    }
+
+    /// Type that is serialized instead of the field
+    fn wire_type(&self, ty: &Type, shared_crate_name: &TokenStream) -> TokenStream {
+        let int = self.wire_int(shared_crate_name);
+        match self {
+            FieldEncoding::Quantize { .. } => quote! {
+                <#ty as #shared_crate_name::_reexport::quantize::Quantize>::Wire<#int>
+            },
+            _ => int,
+        }
+    }
+
+    fn encode(
+        &self,
+        value: TokenStream,
+        ty: &Type,
+        shared_crate_name: &TokenStream,
+    ) -> TokenStream {
+        let quantize = quote! { #shared_crate_name::_reexport::quantize };
+        let bits = self.bits() as usize;
+        let int = quote! { #quantize::Bits::<#bits> };
+        match self {
+            FieldEncoding::Quantize {
+                min,
+                max,
+                precision,
+            } => {
+                let (min, max, precision) = (float(*min), float(*max), float(*precision));
+                quote! {
+                    <#ty as #quantize::Quantize>::quantize::<#bits>(#value, #min, #max, #precision)
+                }
+            }
+            FieldEncoding::Range { min, max } => {
+                let (min, max) = (int_literal(*min), int_literal(*max));
+                quote! { #int(#quantize::encode_range(#value as i128, #min, #max)) }
+            }
+            FieldEncoding::SmallestThree { bits } => {
+                quote! { #int(#quantize::encode_quat(#value, #bits)) }
+            }
+        }
+    }
+
+    fn decode(&self, wire: TokenStream, ty: &Type, shared_crate_name: &TokenStream) -> TokenStream {
+        let quantize = quote! { #shared_crate_name::_reexport::quantize };
+        match self {
+            FieldEncoding::Quantize {
+                min,
+                max,
+                precision,
+            } => {
+                let bits = self.bits() as usize;
+                let (min, max, precision) = (float(*min), float(*max), float(*precision));
+                quote! {
+                    <#ty as #quantize::Quantize>::dequantize::<#bits>(#wire, #min, #max, #precision)
+                }
+            }
+            FieldEncoding::Range { min, max } => {
+                let (min, max) = (int_literal(*min), int_literal(*max));
+                quote! { #quantize::decode_range(#wire.0, #min, #max) as #ty }
+            }
+            FieldEncoding::SmallestThree { bits } => {
+                quote! { #quantize::decode_quat(#wire.0, #bits) }
+            }
+        }
+    }
+}
+
+fn parse_float(expr: &Expr) -> syn::Result<f64> {
+    match expr {
+        Expr::Lit(lit) => match &lit.lit {
+            Lit::Float(f) => f.base10_parse(),
+            Lit::Int(i) => i.base10_parse(),
+            _ => Err(syn::Error::new(expr.span(), "expected a number")),
+        },
+        Expr::Unary(unary) if matches!(unary.op, UnOp::Neg(_)) => Ok(-parse_float(&unary.expr)?),
+        _ => Err(syn::Error::new(expr.span(), "expected a number")),
+    }
+}
+
+fn parse_int(expr: &Expr) -> syn::Result<i128> {
+    match expr {
+        Expr::Lit(lit) => match &lit.lit {
+            Lit::Int(i) => i.base10_parse(),
+            _ => Err(syn::Error::new(expr.span(), "expected an integer")),
+        },
+        Expr::Unary(unary) if matches!(unary.op, UnOp::Neg(_)) => Ok(-parse_int(&unary.expr)?),
+        _ => Err(syn::Error::new(expr.span(), "expected an integer")),
+    }
+}
+
+// negative literals cannot be created directly, so we emit the sign separately
+fn float(value: f64) -> TokenStream {
+    let literal = Literal::f64_unsuffixed(value.abs());
+    if value < 0.0 {
+        quote! { -#literal }
+    } else {
+        quote! { #literal }
+    }
+}
+
+fn int_literal(value: i128) -> TokenStream {
+    let literal = Literal::u128_unsuffixed(value.unsigned_abs());
+    if value < 0 {
+        quote! { -#literal }
+    } else {
+        quote! { #literal }
+    }
+}
+
+/// If some fields of the struct have a `#[lightyear(...)]` encoding attribute, implement `Serialize`
+/// and `Deserialize` for the struct so that these fields are sent with their encoding.
+///
+/// The struct is deserialized through a `{StructName}Wire` struct where the encoded fields are replaced
+/// by their wire type. It is emitted with the impls inside an anonymous `const _: () = { ... };` block so
+/// that it doesn't leak into the user's module.
+pub(crate) fn encoded_serde_impl(
+    input: &DeriveInput,
+    shared_crate_name: &TokenStream,
+) -> syn::Result<TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        Data::Enum(data) => {
+            for field in data.variants.iter().flat_map(|v| v.fields.iter()) {
+                if FieldEncoding::from_field(field)?.is_some() {
+                    return Err(syn::Error::new(
+                        field.span(),
+                        "lightyear encodings are only supported on the fields of structs",
+                    ));
+                }
+            }
+            return Ok(quote! {});
+        }
+        Data::Union(_) => return Ok(quote! {}),
+    };
+    let encodings = fields
+        .iter()
+        .map(FieldEncoding::from_field)
+        .collect::<syn::Result<Vec<_>>>()?;
+    if encodings.iter().all(Option::is_none) {
+        return Ok(quote! {});
+    }
+
+    let struct_name = &input.ident;
+    let struct_name_str = struct_name.to_string();
+    let wire_name = format_ident!("{}Wire", struct_name);
+    let generics = &input.generics;
+    let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
+
+    // the non-encoded fields are serialized as-is
+    let mut ser_generics = generics.clone();
+    let ser_where = ser_generics.make_where_clause();
+    for (field, encoding) in fields.iter().zip(&encodings) {
+        if encoding.is_none() {
+            let ty = &field.ty;
+            ser_where
+                .predicates
+                .push(parse_quote! { #ty: serde::Serialize });
+        }
+    }
+    let ser_where = &ser_generics.where_clause;
+
+    let mut de_generics = generics.clone();
+    de_generics
+        .params
+        .push(GenericParam::Lifetime(parse_quote! { 'de }));
+    de_generics
+        .make_where_clause()
+        .predicates
+        .push(parse_quote! { #wire_name #type_generics: serde::Deserialize<'de> });
+    let (de_impl_generics, _, de_where) = de_generics.split_for_impl();
+
+    let members: Vec<_> = fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| match &field.ident {
+            Some(ident) => quote! { #ident },
+            None => {
+                let index = Index::from(i);
+                quote! { #index }
+            }
+        })
+        .collect();
+    let wire_types: Vec<_> = fields
+        .iter()
+        .zip(&encodings)
+        .map(|(field, encoding)| match encoding {
+            Some(encoding) => encoding.wire_type(&field.ty, shared_crate_name),
+            None => {
+                let ty = &field.ty;
+                quote! { #ty }
+            }
+        })
+        .collect();
+    let values: Vec<_> = fields
+        .iter()
+        .zip(&encodings)
+        .zip(&members)
+        .map(|((field, encoding), member)| match encoding {
+            Some(encoding) => {
+                let encoded =
+                    encoding.encode(quote! { self.#member }, &field.ty, shared_crate_name);
+                quote! { &(#encoded) }
+            }
+            None => quote! { &self.#member },
+        })
+        .collect();
+    let decoded: Vec<_> = fields
+        .iter()
+        .zip(&encodings)
+        .zip(&members)
+        .map(|((field, encoding), member)| match encoding {
+            Some(encoding) => {
+                encoding.decode(quote! { wire.#member }, &field.ty, shared_crate_name)
+            }
+            None => quote! { wire.#member },
+        })
+        .collect();
+
+    let len = fields.len();
+    let (wire_struct, serialize_body, construct) = match fields {
+        Fields::Named(_) => {
+            let names: Vec<_> = members.iter().map(|m| m.to_string()).collect();
+            (
+                quote! {
+                    pub struct #wire_name #generics #where_clause {
+                        #(#members: #wire_types,)*
+                    }
+                },
+                quote! {
+                    use serde::ser::SerializeStruct;
+                    let mut state = serializer.serialize_struct(#struct_name_str, #len)?;
+                    #(state.serialize_field(#names, #values)?;)*
+                    state.end()
+                },
+                quote! { #struct_name { #(#members: #decoded,)* } },
+            )
+        }
+        Fields::Unnamed(_) => (
+            quote! {
+                pub struct #wire_name #generics (#(#wire_types,)*) #where_clause;
+            },
+            // serde serializes structs with a single unnamed field as newtype structs
+            if len == 1 {
+                quote! { serializer.serialize_newtype_struct(#struct_name_str, #(#values)*) }
+            } else {
+                quote! {
+                    use serde::ser::SerializeTupleStruct;
+                    let mut state = serializer.serialize_tuple_struct(#struct_name_str, #len)?;
+                    #(state.serialize_field(#values)?;)*
+                    state.end()
+                }
+            },
+            quote! { #struct_name ( #(#decoded,)* ) },
+        ),
+        Fields::Unit => unreachable!("unit structs have no fields"),
+    };
+
+    Ok(quote! {
+        const _: () = {
+            #[derive(serde::Deserialize)]
+            #[serde(rename = #struct_name_str)]
+            #wire_struct
+
+            impl #impl_generics serde::Serialize for #struct_name #type_generics #ser_where {
+                fn serialize<__S: serde::Serializer>(&self, serializer: __S) -> Result<__S::Ok, __S::Error> {
+                    #serialize_body
+                }
+            }
+
+            impl #de_impl_generics serde::Deserialize<'de> for #struct_name #type_generics #de_where {
+                fn deserialize<__D: serde::Deserializer<'de>>(deserializer: __D) -> Result<Self, __D::Error> {
+                    let wire = <#wire_name #type_generics as serde::Deserialize>::deserialize(deserializer)?;
+                    Ok(#construct)
+                }
+            }
+        };
+    })
+}
@@ -10,10 +10,13 @@ use syn::{parse_macro_input, ItemEnum};
 
 use channel::channel_impl;
 use component::component_protocol_impl;
+use diffable::diffable_impl;
 use message::{message_impl, message_protocol_impl};
 
 mod channel;
 mod component;
+mod diffable;
+mod encoding;
 mod message;
 mod shared;
 
@@ -36,14 +39,31 @@ pub fn channel_derive_internal(input: proc_macro::TokenStream) -> proc_macro::To
 // Message
 
 /// Derives the Message trait for a given struct
-#[proc_macro_derive(Message, attributes(message))]
+///
+/// The wire encoding of the fields of a struct can be changed with `#[lightyear(...)]` attributes:
+/// - `#[lightyear(quantize(min = -1000.0, max = 1000.0, precision = 0.01))]` on fields that implement `quantize::Quantize` (`f32`, `f64`, `Vec2`, `Vec3`, `Vec3A` or `Vec4`)
+/// - `#[lightyear(range(0..=100))]` on integer fields
+/// - `#[lightyear(smallest_three)]` (or `#[lightyear(smallest_three(bits = 15))]`) on `Quat` fields
+///
+/// In that case the derive also implements `Serialize` and `Deserialize` for the struct, so they must not be derived.
+///
+/// The whole type can also be serialized without serde:
+/// - `#[message(bitcode)]` uses its bitcode `Encode`/`Decode` implementation
+/// - `#[message(serialize_with = "encode_fn", deserialize_with = "decode_fn")]` uses custom functions
+///   `fn(&T, &mut WriteWordBuffer) -> anyhow::Result<()>` and `fn(&mut ReadWordBuffer) -> anyhow::Result<T>`
+///
+/// In that case the derive implements `BitSerializable` instead, so the type doesn't need to implement `Serialize` or `Deserialize`.
+///
+/// The name of the message (`Named::NAME`) is the name of the type, unless it is set with `#[message(name = "...")]`.
+/// It identifies the types registered with `AppRegistrationExt`, so two registered types cannot have the same name.
+#[proc_macro_derive(Message, attributes(message, lightyear))]
 pub fn message_derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let shared_crate_name = quote! { lightyear };
     message_impl(input, shared_crate_name)
 }
 
 #[doc(hidden)]
-#[proc_macro_derive(MessageInternal, attributes(message))]
+#[proc_macro_derive(MessageInternal, attributes(message, lightyear))]
 pub fn message_derive_internal(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let shared_crate_name = quote! { crate };
     message_impl(input, shared_crate_name)
@@ -90,3 +110,21 @@ pub fn component_protocol(
     let shared_crate_name = quote! { lightyear };
     component_protocol_impl(args, input, shared_crate_name)
 }
+
+// Delta-compression
+
+/// Derives the Diffable trait for a given struct, so that it can be replicated with delta-compression
+///
+/// Every field type must implement `Clone`, `PartialEq`, `Debug`, `Serialize` and `DeserializeOwned`.
+#[proc_macro_derive(Diffable)]
+pub fn diffable_derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let shared_crate_name = quote! { lightyear };
+    diffable_impl(input, shared_crate_name)
+}
+
+#[doc(hidden)]
+#[proc_macro_derive(DiffableInternal)]
+pub fn diffable_derive_internal(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let shared_crate_name = quote! { crate };
+    diffable_impl(input, shared_crate_name)
+}
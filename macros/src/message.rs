@@ -1,4 +1,5 @@
-use crate::shared::generate_unique_ident;
+use crate::encoding::encoded_serde_impl;
+use crate::shared::{bit_serializable_impl, generate_unique_ident};
 use darling::ast::NestedMeta;
 use darling::util::PathList;
 use darling::{Error, FromDeriveInput, FromMeta};
@@ -15,6 +16,18 @@ use syn::{
 struct MessageAttrs {
     #[darling(default)]
     custom_map: bool,
+    /// Serialize the message with its bitcode `Encode`/`Decode` implementation instead of serde
+    #[darling(default)]
+    bitcode: bool,
+    /// Custom function used to serialize the message
+    #[darling(default)]
+    serialize_with: Option<syn::Path>,
+    /// Custom function used to deserialize the message
+    #[darling(default)]
+    deserialize_with: Option<syn::Path>,
+    /// Name of the message, instead of the name of the type
+    #[darling(default)]
+    name: Option<String>,
 }
 pub fn message_impl(
     input: proc_macro::TokenStream,
@@ -30,11 +43,30 @@ pub fn message_impl(
     // Names
     let struct_name = &input.ident;
     // let struct_name_str = LitStr::new(&struct_name.to_string(), struct_name.span());
-    let struct_name_str = &struct_name.to_string();
+    let struct_name_str = &attrs
+        .name
+        .clone()
+        .unwrap_or_else(|| struct_name.to_string());
     let lowercase_struct_name =
         format_ident!("{}", struct_name.to_string().to_lowercase().as_str());
     let module_name = generate_unique_ident(&format!("mod_{}", lowercase_struct_name));
     let map_entities_trait = map_entities_trait(&input, map_entities);
+    let encoded_serde_impl = match encoded_serde_impl(&input, &shared_crate_name) {
+        Ok(tokens) => tokens,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    let serialize_mode_impl = match serialize_mode_impl(&input, &attrs, &shared_crate_name) {
+        Ok(tokens) => tokens,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    if !encoded_serde_impl.is_empty() && !serialize_mode_impl.is_empty() {
+        return syn::Error::new_spanned(
+            &input.ident,
+            "lightyear field encodings cannot be combined with a custom serialization mode",
+        )
+        .to_compile_error()
+        .into();
+    }
 
     // Methods
     let gen = quote! {
@@ -50,12 +82,65 @@ pub fn message_impl(
             impl #impl_generics Named for #struct_name #type_generics #where_clause {
                 const NAME: &'static str = #struct_name_str;
             }
+
+            #encoded_serde_impl
+
+            #serialize_mode_impl
         }
     };
 
     proc_macro::TokenStream::from(gen)
 }
 
+// Implement BitSerializable for the message if it uses the bitcode or the custom serialization mode,
+// so that it is written directly into the buffer instead of going through serde
+fn serialize_mode_impl(
+    input: &DeriveInput,
+    attrs: &MessageAttrs,
+    shared_crate_name: &TokenStream,
+) -> syn::Result<TokenStream> {
+    let (encode, decode) = match (
+        attrs.bitcode,
+        &attrs.serialize_with,
+        &attrs.deserialize_with,
+    ) {
+        (false, None, None) => return Ok(quote! {}),
+        (true, None, None) => (
+            quote! { #shared_crate_name::_reexport::serialize_mode::bitcode_encode },
+            quote! { #shared_crate_name::_reexport::serialize_mode::bitcode_decode },
+        ),
+        (false, Some(serialize_with), Some(deserialize_with)) => {
+            (quote! { #serialize_with }, quote! { #deserialize_with })
+        }
+        (true, _, _) => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "`bitcode` cannot be combined with `serialize_with`/`deserialize_with`",
+            ))
+        }
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "`serialize_with` and `deserialize_with` must be specified together",
+            ))
+        }
+    };
+
+    let struct_name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #shared_crate_name::_reexport::BitSerializable for #struct_name #type_generics #where_clause {
+            fn encode(&self, writer: &mut #shared_crate_name::_reexport::WriteWordBuffer) -> anyhow::Result<()> {
+                #encode(self, writer)
+            }
+
+            fn decode(reader: &mut #shared_crate_name::_reexport::ReadWordBuffer) -> anyhow::Result<Self> {
+                #decode(reader)
+            }
+        }
+    })
+}
+
 // Add the MapEntities trait for the message.
 // Need to combine the generics from the message with the generics from the trait
 fn map_entities_trait(input: &DeriveInput, ident_map: bool) -> TokenStream {
@@ -135,9 +220,24 @@ pub fn message_protocol_impl(
             #variant(#shared_crate_name::inputs::leafwing::InputMessage<<#protocol as Protocol>::#ty>)
         });
     }
+    // messages registered at runtime with `AppRegistrationExt::register_message`
+    input.variants.push(parse_quote! {
+        DynamicMessage(DynamicMessage)
+    });
 
     // Helper Properties
     let fields = get_fields(&input);
+    // the registered messages have their own events, which are written by the `ProtocolRegistry`
+    let event_fields: Vec<Field> = fields
+        .iter()
+        .filter(|field| {
+            field
+                .ident
+                .as_ref()
+                .map_or(false, |ident| ident != "DynamicMessage")
+        })
+        .cloned()
+        .collect();
 
     // Names
     let enum_name = &input.ident;
@@ -151,18 +251,17 @@ pub fn message_protocol_impl(
     let from_into_impl = from_into_impl(&input, &fields);
     let message_kind_method = message_kind_method(&input, &fields);
     let input_message_kind_method = input_message_kind_method(&input);
-    let add_events_method = add_events_method(&fields);
-    let push_message_events_method = push_message_events_method(&fields, protocol);
+    let add_events_method = add_events_method(&event_fields);
+    let push_message_events_method = push_message_events_method(&event_fields, protocol);
     let name_method = name_method(&input, &fields);
+    let type_names_method = type_names_method(&event_fields);
     let map_entities_impl = map_entities_impl(&input);
-    let encode_method = encode_method();
-    let decode_method = decode_method();
+    let bit_serializable_impl = bit_serializable_impl(enum_name, &fields, &shared_crate_name);
 
     let output = quote! {
         #[doc(hidden)]
         mod #module_name {
             use super::*;
-            use serde::{Serialize, Deserialize};
             use bevy::prelude::{App, Entity, World};
             use bevy::utils::{EntityHashMap, EntityHashSet};
             use #shared_crate_name::_reexport::*;
@@ -172,7 +271,7 @@ pub fn message_protocol_impl(
             use #shared_crate_name::shared::events::MessageEvent;
 
 
-            #[derive(Serialize, Deserialize, Clone, PartialEq)]
+            #[derive(Clone, PartialEq)]
             #extra_derives
             #input
 
@@ -180,6 +279,7 @@ pub fn message_protocol_impl(
                 type Protocol = #protocol;
 
                 #name_method
+                #type_names_method
                 #message_kind_method
                 #input_message_kind_method
                 #add_events_method
@@ -196,10 +296,7 @@ pub fn message_protocol_impl(
 
             // #from_into_methods
             #map_entities_impl
-            // impl BitSerializable for #enum_name {
-            //     #encode_method
-            //     #decode_method
-            // }
+            #bit_serializable_impl
         }
         pub use #module_name::#enum_name as #enum_name;
 
@@ -208,7 +305,7 @@ pub fn message_protocol_impl(
     proc_macro::TokenStream::from(output)
 }
 
-fn push_message_events_method(fields: &Vec<Field>, protocol_name: &Ident) -> TokenStream {
+fn push_message_events_method(fields: &[Field], protocol_name: &Ident) -> TokenStream {
     let mut body = quote! {};
     for field in fields {
         let message_type = &field.ty;
@@ -228,7 +325,7 @@ fn push_message_events_method(fields: &Vec<Field>, protocol_name: &Ident) -> Tok
     }
 }
 
-fn from_into_impl(input: &ItemEnum, fields: &Vec<Field>) -> TokenStream {
+fn from_into_impl(input: &ItemEnum, fields: &[Field]) -> TokenStream {
     let enum_name = &input.ident;
     let mut body = quote! {};
     for field in fields.iter() {
@@ -274,7 +371,7 @@ fn from_into_impl(input: &ItemEnum, fields: &Vec<Field>) -> TokenStream {
     body
 }
 
-fn message_kind_method(input: &ItemEnum, fields: &Vec<Field>) -> TokenStream {
+fn message_kind_method(input: &ItemEnum, fields: &[Field]) -> TokenStream {
     let enum_name = &input.ident;
     let mut body = quote! {};
     for field in fields.iter() {
@@ -329,7 +426,7 @@ fn input_message_kind_method(input: &ItemEnum) -> TokenStream {
     }
 }
 
-fn add_events_method(fields: &Vec<Field>) -> TokenStream {
+fn add_events_method(fields: &[Field]) -> TokenStream {
     let mut body = quote! {};
     for field in fields {
         let component_type = &field.ty;
@@ -346,7 +443,7 @@ fn add_events_method(fields: &Vec<Field>) -> TokenStream {
     }
 }
 
-fn name_method(input: &ItemEnum, fields: &Vec<Field>) -> TokenStream {
+fn name_method(input: &ItemEnum, fields: &[Field]) -> TokenStream {
     let enum_name = &input.ident;
     let mut body = quote! {};
     for field in fields.iter() {
@@ -366,6 +463,15 @@ fn name_method(input: &ItemEnum, fields: &Vec<Field>) -> TokenStream {
     }
 }
 
+fn type_names_method(fields: &[Field]) -> TokenStream {
+    let types = fields.iter().map(|field| &field.ty);
+    quote! {
+        fn type_names() -> Vec<&'static str> {
+            vec![#(<#types as Named>::NAME),*]
+        }
+    }
+}
+
 fn map_entities_impl(input: &ItemEnum) -> TokenStream {
     let enum_name = &input.ident;
     let variants = input.variants.iter().map(|v| v.ident.clone());
@@ -399,23 +505,6 @@ fn map_entities_impl(input: &ItemEnum) -> TokenStream {
     }
 }
 
-fn encode_method() -> TokenStream {
-    quote! {
-        fn encode(&self, writer: &mut impl WriteBuffer) -> anyhow::Result<()> {
-            writer.serialize(&self)
-        }
-    }
-}
-
-fn decode_method() -> TokenStream {
-    quote! {
-        fn decode(reader: &mut impl ReadBuffer) -> anyhow::Result<Self>
-            where Self: Sized{
-            reader.deserialize::<Self>()
-        }
-    }
-}
-
 fn get_fields(input: &ItemEnum) -> Vec<Field> {
     let mut fields = Vec::new();
     for field in &input.variants {
@@ -1,5 +1,6 @@
-use proc_macro2::{Ident, Span};
-use syn::{Data, DeriveInput, Fields};
+use proc_macro2::{Ident, Literal, Span, TokenStream};
+use quote::quote;
+use syn::{Data, DeriveInput, Field, Fields};
 
 pub enum StructType {
     Struct,
@@ -25,3 +26,67 @@ pub(crate) fn generate_unique_ident(prefix: &str) -> Ident {
 
     Ident::new(&ident, Span::call_site())
 }
+
+/// Implement `BitSerializable` for a protocol enum, whose variants each contain a single value.
+///
+/// The index of the variant is written (the same way serde enums are serialized), followed by the value with
+/// its own `BitSerializable` implementation, so that the types can choose their serialization mode.
+/// The `fields` are the values of the variants, with the ident of the variant.
+pub(crate) fn bit_serializable_impl(
+    enum_name: &Ident,
+    fields: &[Field],
+    shared_crate_name: &TokenStream,
+) -> TokenStream {
+    let enum_name_str = enum_name.to_string();
+    let idents: Vec<_> = fields.iter().map(|field| &field.ident).collect();
+    let names = idents
+        .iter()
+        .map(|ident| ident.as_ref().unwrap().to_string());
+    let tys: Vec<_> = fields.iter().map(|field| &field.ty).collect();
+    let indices: Vec<_> = (0..fields.len() as u32)
+        .map(Literal::u32_suffixed)
+        .collect();
+    quote! {
+        impl #shared_crate_name::_reexport::BitSerializable for #enum_name {
+            fn encode(&self, writer: &mut #shared_crate_name::_reexport::WriteWordBuffer) -> anyhow::Result<()> {
+                match self {
+                    #(#enum_name::#idents(inner) => {
+                        #shared_crate_name::_reexport::serialize_mode::encode_variant_index(#indices, writer)?;
+                        #shared_crate_name::_reexport::BitSerializable::encode(inner, writer)
+                    })*
+                }
+            }
+
+            fn decode(reader: &mut #shared_crate_name::_reexport::ReadWordBuffer) -> anyhow::Result<Self> {
+                Self::decode_with(reader, &#shared_crate_name::_reexport::ProtocolRegistry::default())
+            }
+
+            fn decode_with(
+                reader: &mut #shared_crate_name::_reexport::ReadWordBuffer,
+                registry: &#shared_crate_name::_reexport::ProtocolRegistry,
+            ) -> anyhow::Result<Self> {
+                match #shared_crate_name::_reexport::serialize_mode::decode_variant_index(reader)? {
+                    #(#indices => Ok(#enum_name::#idents(
+                        <#tys as #shared_crate_name::_reexport::BitSerializable>::decode_with(reader, registry)?
+                    )),)*
+                    index => Err(anyhow::anyhow!("invalid variant {} for {}", index, #enum_name_str)),
+                }
+            }
+
+            fn format(
+                tracer: &mut #shared_crate_name::protocol::schema::tracer::Tracer,
+            ) -> #shared_crate_name::protocol::schema::format::Format {
+                use #shared_crate_name::protocol::schema::format::{ContainerFormat, Named, VariantFormat};
+                let variants = std::collections::BTreeMap::from([
+                    #((#indices, Named {
+                        name: #names.to_string(),
+                        value: VariantFormat::NewType(Box::new(
+                            <#tys as #shared_crate_name::_reexport::BitSerializable>::format(tracer)
+                        )),
+                    }),)*
+                ]);
+                tracer.container(#enum_name_str, ContainerFormat::Enum(variants))
+            }
+        }
+    }
+}
@@ -8,7 +8,7 @@ pub mod some_channel {
 #[cfg(test)]
 mod tests {
     use lightyear::prelude::{
-        Channel, ChannelContainer, ChannelDirection, ChannelMode, ChannelSettings,
+        Channel, ChannelContainer, ChannelDirection, ChannelMode, ChannelSettings, FragmentSettings,
     };
 
     use super::some_channel::*;
@@ -18,6 +18,8 @@ mod tests {
         let settings = ChannelSettings {
             mode: ChannelMode::UnorderedUnreliable,
             direction: ChannelDirection::Bidirectional,
+            fragment: FragmentSettings::default(),
+            ..Default::default()
         };
         let builder = SomeChannel::get_builder(settings);
         let channel_container: ChannelContainer = builder.build();
@@ -55,3 +55,44 @@ mod tests {
         Ok(())
     }
 }
+
+pub mod encoded_message {
+    use bevy::prelude::{Quat, Vec2};
+
+    use lightyear::prelude::*;
+
+    #[derive(Message, Debug, PartialEq, Clone)]
+    pub struct PlayerState {
+        #[lightyear(quantize(min = -1000.0, max = 1000.0, precision = 0.01))]
+        pub position: Vec2,
+        #[lightyear(quantize(min = 0.0, max = 10.0, precision = 0.1))]
+        pub speed: f32,
+        #[lightyear(range(-10..=100))]
+        pub health: i32,
+        #[lightyear(smallest_three)]
+        pub rotation: Quat,
+        pub name: String,
+    }
+
+    #[derive(Message, Debug, PartialEq, Clone)]
+    pub struct Level(#[lightyear(range(0..16))] pub u64);
+}
+
+pub mod serialize_mode_message {
+    use lightyear::_reexport::{ReadWordBuffer, WriteWordBuffer};
+    use lightyear::prelude::*;
+
+    #[derive(Message, Debug, PartialEq, Clone)]
+    #[message(serialize_with = "write_health", deserialize_with = "read_health")]
+    pub struct Health(pub u8);
+
+    fn write_health(health: &Health, writer: &mut WriteWordBuffer) -> anyhow::Result<()> {
+        use lightyear::_reexport::WriteBuffer;
+        writer.serialize(&health.0)
+    }
+
+    fn read_health(reader: &mut ReadWordBuffer) -> anyhow::Result<Health> {
+        use lightyear::_reexport::ReadBuffer;
+        Ok(Health(reader.deserialize()?))
+    }
+}